@@ -27,6 +27,8 @@ pub enum Error {
     Radix(radix::RadixErr),
     InvalidPassword,
     InvalidAccountToken,
+    /// The sign-in attempt that locked the account after too many failures.
+    AccountLocked,
     PasswordHasher(djangohashers::HasherError),
     Reqwest(reqwest::Error),
     ApiBadRequest(&'static str, Box<dyn std::error::Error>),
@@ -57,6 +59,7 @@ impl error::Error for Error {
             Error::Generic(_)
             | Error::InvalidPassword
             | Error::InvalidAccountToken
+            | Error::AccountLocked
             | Error::PasswordHasher(_)
             | Error::ApiServerError(_)
             | Error::ApiNotFound(_, _)
@@ -154,9 +157,10 @@ impl ResponseError for Error {
             Error::Anyhow(_) | Error::Generic(_) | Error::Database(DBError::NotFound) => {
                 ("404.html", HttpResponse::NotFound())
             }
-            Error::Json(_) | Error::InvalidPassword | Error::InvalidAccountToken => {
-                ("400.html", HttpResponse::BadRequest())
-            }
+            Error::Json(_)
+            | Error::InvalidPassword
+            | Error::InvalidAccountToken
+            | Error::AccountLocked => ("400.html", HttpResponse::BadRequest()),
             _ => ("500.html", HttpResponse::InternalServerError()),
         };
         // Returning an Internal Server Error will trigger actix
@@ -3,11 +3,10 @@ use std::task::{Context, Poll};
 use actix_service::{Service, Transform};
 use actix_web::dev::{ServiceRequest, ServiceResponse};
 use actix_web::http::header::LOCATION;
-use actix_web::{Error, HttpResponse};
+use actix_web::{Error, HttpRequest, HttpResponse};
 use futures::future::{ok, Either, Ready};
 
 use crate::error::render;
-use crate::request::Authentication;
 
 /// A guard that enables route and scope authentication gating.
 #[derive(Debug)]
@@ -15,6 +14,10 @@ pub struct Auth {
     /// Where to redirect the user to if they fail an
     /// authentication check.
     pub redirect_to: &'static str,
+
+    /// Checks whether the request comes from a signed-in user, e.g.
+    /// `Authentication::is_authenticated` or a check against the app's session records.
+    pub is_authenticated: fn(&HttpRequest) -> Result<bool, crate::error::Error>,
 }
 
 impl<S, B> Transform<S> for Auth
@@ -33,6 +36,7 @@ where
         ok(AuthMiddleware {
             service,
             redirect_to: self.redirect_to,
+            is_authenticated: self.is_authenticated,
         })
     }
 }
@@ -44,6 +48,9 @@ pub struct AuthMiddleware<S> {
     /// Where to redirect to.
     redirect_to: &'static str,
 
+    /// The authentication check.
+    is_authenticated: fn(&HttpRequest) -> Result<bool, crate::error::Error>,
+
     /// The service provided.
     service: S,
 }
@@ -65,7 +72,7 @@ where
     fn call(&mut self, req: ServiceRequest) -> Self::Future {
         let (request, payload) = req.into_parts();
 
-        let status = request.is_authenticated();
+        let status = (self.is_authenticated)(&request);
 
         match status {
             Ok(v) if v == true => {
@@ -5,6 +5,11 @@ use actix_web::guard::{Guard, Header};
 pub mod auth;
 pub use auth::{Auth, AuthMiddleware};
 
+pub mod rate_limit;
+pub use rate_limit::{
+    RateLimit, RateLimitKey, RateLimitMiddleware, RateLimitStore, TokenValidator,
+};
+
 pub fn accepts_json() -> impl Guard {
     Header("content-type", "application/json")
 }
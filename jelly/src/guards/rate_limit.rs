@@ -0,0 +1,296 @@
+//! A guard that limits how many requests a client can make to a route or scope
+//! within a window of time.
+//!
+//! Requests are counted in fixed windows: the first request of a client starts
+//! a window, and once `requests` have been made, the following ones get a `429`
+//! with a `Retry-After` header until the window ends.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+use actix_service::{Service, Transform};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::RETRY_AFTER;
+use actix_web::{Error, HttpRequest, HttpResponse};
+use chrono::{DateTime, Duration, Utc};
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Integer, Timestamptz, Varchar};
+use futures::future::{ok, Either, Ready};
+use lazy_static::lazy_static;
+use rand::{thread_rng, Rng};
+use serde_json::json;
+
+use crate::request::client::{bearer_token, client_ip};
+use crate::request::{Authentication, DatabasePool};
+use crate::utils::error_constants::MSG_TOO_MANY_REQUESTS;
+use crate::{DieselPgConnection, DieselPgPool};
+
+table! {
+    rate_limits (key) {
+        key -> Varchar,
+        count -> Int4,
+        reset_at -> Timestamptz,
+    }
+}
+
+// Counts a request in the window of the key, starting a new window if the
+// last one has ended.
+const HIT_QUERY: &str = "INSERT INTO rate_limits (key, count, reset_at)
+VALUES ($1, 1, $2 + $3 * INTERVAL '1 millisecond')
+ON CONFLICT (key) DO UPDATE SET
+    count = CASE WHEN rate_limits.reset_at <= $2 THEN 1 ELSE rate_limits.count + 1 END,
+    reset_at = CASE WHEN rate_limits.reset_at <= $2
+        THEN EXCLUDED.reset_at ELSE rate_limits.reset_at END
+RETURNING count, reset_at";
+
+// Finished windows are dropped now and then, so the stores don't keep every
+// client ever seen.
+const MEMORY_PRUNE_SIZE: usize = 10_000;
+const POSTGRES_PRUNE_ONE_IN: u32 = 1_000;
+
+lazy_static! {
+    static ref SHARED_MEMORY_STORE: Arc<MemoryStore> = Arc::new(MemoryStore::default());
+}
+
+/// Returns the id of the API token of a request, or `None` when it doesn't authenticate.
+pub type TokenValidator = fn(&HttpRequest, &str) -> Option<i32>;
+
+/// What requests are counted by.
+#[derive(Clone, Copy, Debug)]
+pub enum RateLimitKey {
+    /// The IP address of the client.
+    Ip,
+    /// The signed-in user, or the IP address for anonymous requests.
+    User,
+    /// The `Authorization: Bearer` token, as identified by the validator, or the IP
+    /// address for requests without a valid one.
+    ApiToken(TokenValidator),
+}
+
+/// The requests counted in the current window of a client.
+#[derive(Clone, Copy, Debug, QueryableByName)]
+pub struct Hit {
+    #[sql_type = "Integer"]
+    pub count: i32,
+    #[sql_type = "Timestamptz"]
+    pub reset_at: DateTime<Utc>,
+}
+
+/// Keeps the counters in the memory of the process.
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    windows: Mutex<HashMap<String, Hit>>,
+}
+
+impl MemoryStore {
+    pub fn hit(&self, key: &str, window: Duration, now: DateTime<Utc>) -> Hit {
+        let mut windows = self.windows.lock().unwrap_or_else(|e| e.into_inner());
+        if windows.len() >= MEMORY_PRUNE_SIZE {
+            windows.retain(|_, hit| hit.reset_at > now);
+        }
+
+        let hit = windows.entry(key.to_string()).or_insert(Hit {
+            count: 0,
+            reset_at: now + window,
+        });
+        if hit.reset_at <= now {
+            hit.count = 0;
+            hit.reset_at = now + window;
+        }
+        hit.count += 1;
+        *hit
+    }
+}
+
+/// Where the counters are kept.
+#[derive(Clone, Debug)]
+pub enum RateLimitStore {
+    /// In memory. Counters are per process and are lost on restarts.
+    Memory(Arc<MemoryStore>),
+    /// In the `rate_limits` table, through the database pool of the app, so that
+    /// every process shares them.
+    Postgres,
+}
+
+impl RateLimitStore {
+    /// The memory store shared by every worker of the process.
+    pub fn memory() -> Self {
+        RateLimitStore::Memory(SHARED_MEMORY_STORE.clone())
+    }
+
+    fn hit<C>(&self, connection: C, key: &str, window: Duration) -> Result<Hit, crate::error::Error>
+    where
+        C: FnOnce() -> Result<DieselPgConnection, crate::error::Error>,
+    {
+        let now = Utc::now();
+        match self {
+            RateLimitStore::Memory(store) => Ok(store.hit(key, window, now)),
+            RateLimitStore::Postgres => {
+                let conn = connection()?;
+                if thread_rng().gen_range(0..POSTGRES_PRUNE_ONE_IN) == 0 {
+                    diesel::delete(rate_limits::table.filter(rate_limits::reset_at.le(now)))
+                        .execute(&conn)?;
+                }
+                Ok(diesel::sql_query(HIT_QUERY)
+                    .bind::<Varchar, _>(key)
+                    .bind::<Timestamptz, _>(now)
+                    .bind::<BigInt, _>(window.num_milliseconds())
+                    .get_result::<Hit>(&conn)?)
+            }
+        }
+    }
+}
+
+/// A guard that limits the requests of each client to a route or scope. Guards
+/// with different names count separately, even with the same store.
+#[derive(Clone, Debug)]
+pub struct RateLimit {
+    name: &'static str,
+    requests: u32,
+    window: Duration,
+    key: RateLimitKey,
+    store: RateLimitStore,
+}
+
+impl RateLimit {
+    /// Allows `requests` per `window` from each IP address, counted in memory.
+    pub fn new(name: &'static str, requests: u32, window: Duration) -> Self {
+        RateLimit {
+            name,
+            requests,
+            window,
+            key: RateLimitKey::Ip,
+            store: RateLimitStore::memory(),
+        }
+    }
+
+    /// Counts the requests by user or API token instead of IP address.
+    pub fn keyed_by(mut self, key: RateLimitKey) -> Self {
+        self.key = key;
+        self
+    }
+
+    pub fn with_store(mut self, store: RateLimitStore) -> Self {
+        self.store = store;
+        self
+    }
+
+    /// Counts a request of `client` outside of the middleware, for routes that only do the
+    /// limited work for some requests. Returns the seconds to wait when over the limit.
+    pub fn check(
+        &self,
+        client: &str,
+        pool: &DieselPgPool,
+    ) -> Result<Option<i64>, crate::error::Error> {
+        let key = format!("{}:{}", self.name, client);
+        let hit = self.store.hit(|| Ok(pool.get()?), &key, self.window)?;
+        Ok(self.retry_after(&hit))
+    }
+
+    fn retry_after(&self, hit: &Hit) -> Option<i64> {
+        if hit.count as u32 <= self.requests {
+            return None;
+        }
+        // Rounded up, so that the client doesn't come back before the window ends
+        let milliseconds = (hit.reset_at - Utc::now()).num_milliseconds();
+        Some(((milliseconds + 999) / 1000).max(1))
+    }
+
+    fn key_for(&self, request: &HttpRequest) -> String {
+        let client = match self.key {
+            RateLimitKey::Ip => None,
+            RateLimitKey::User => match request.user() {
+                Ok(user) if !user.is_anonymous => Some(format!("user:{}", user.id)),
+                _ => None,
+            },
+            // Made-up tokens are counted with the IP address they come from
+            RateLimitKey::ApiToken(validate) => bearer_token(request.headers())
+                .and_then(|token| validate(request, token))
+                .map(|id| format!("token:{}", id)),
+        };
+        let client = client.unwrap_or_else(|| {
+            format!(
+                "ip:{}",
+                client_ip(request).unwrap_or_else(|| "unknown".to_string())
+            )
+        });
+        format!("{}:{}", self.name, client)
+    }
+}
+
+impl<S, B> Transform<S> for RateLimit
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = RateLimitMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(RateLimitMiddleware {
+            service,
+            limit: self.clone(),
+        })
+    }
+}
+
+/// Middleware for counting requests and turning away the ones over the limit.
+/// You generally don't need this type, but it needs to be exported for
+/// compiler reasons.
+pub struct RateLimitMiddleware<S> {
+    /// The limit to apply.
+    limit: RateLimit,
+
+    /// The service provided.
+    service: S,
+}
+
+impl<S, B> Service for RateLimitMiddleware<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = Either<S::Future, Ready<Result<Self::Response, Self::Error>>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: ServiceRequest) -> Self::Future {
+        let (request, payload) = req.into_parts();
+        let key = self.limit.key_for(&request);
+
+        let hit = self
+            .limit
+            .store
+            .hit(|| request.db_connection(), &key, self.limit.window);
+        match hit.map(|hit| self.limit.retry_after(&hit)) {
+            Ok(Some(retry_after)) => {
+                return Either::Right(ok(ServiceResponse::new(
+                    request,
+                    HttpResponse::TooManyRequests()
+                        .header(RETRY_AFTER, retry_after.to_string())
+                        .json(json!({
+                            "ok": false,
+                            "msg": MSG_TOO_MANY_REQUESTS,
+                        }))
+                        .into_body(),
+                )));
+            }
+            Ok(None) => {}
+            // A failing store shouldn't take the routes it guards down with it
+            Err(e) => error!("Unable to count request for rate limit {}: {:?}", key, e),
+        }
+
+        let req = ServiceRequest::from_parts(request, payload).ok().unwrap();
+        Either::Left(self.service.call(req))
+    }
+}
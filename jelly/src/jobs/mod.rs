@@ -4,7 +4,11 @@ use std::sync::{Arc, RwLock};
 use tera::Tera;
 use crate::DieselPgPool;
 
-pub use background_jobs::{Job, WorkerConfig};
+pub use background_jobs::{Job, QueueHandle, WorkerConfig};
+pub use background_jobs::dev::{new_job, JobInfo, ReturnJobInfo, Storage};
+
+mod storage;
+pub use storage::{PostgresStorage, StorageError, STATUS_DEAD, STATUS_PENDING, STATUS_RUNNING};
 
 pub const DEFAULT_QUEUE: &'static str = "default";
 
@@ -26,4 +30,13 @@ impl JobState {
             templates,
         }
     }
+
+    /// Adds a job to the queue from inside another job. The job is saved to the job
+    /// storage, where the job server picks it up.
+    pub async fn queue<J: Job>(&self, job: J) -> Result<(), anyhow::Error> {
+        PostgresStorage::new(self.pool.clone())
+            .new_job(new_job(job)?)
+            .await?;
+        Ok(())
+    }
 }
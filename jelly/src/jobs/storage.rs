@@ -0,0 +1,386 @@
+//! A Postgres-backed `Storage` for the jobs queue, so that queued jobs
+//! survive restarts and deploys.
+//!
+//! Jobs stay in the `jobs` table until they complete, which gives
+//! at-least-once delivery: a job whose worker dies is picked up again once
+//! its timeout has passed. Failed jobs are retried with the backoff declared
+//! by the `Job`, and end up in the `dead` state once they run out of retries.
+
+use std::{error, fmt};
+
+use background_jobs::dev::{JobInfo, ReturnJobInfo, Storage};
+use background_jobs::{Backoff, MaxRetries, Stats};
+use chrono::{DateTime, Duration, Utc};
+use diesel::pg::upsert::excluded;
+use diesel::prelude::*;
+use diesel::r2d2::PoolError;
+use diesel::result::Error as DBError;
+use serde::Deserialize;
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::DieselPgPool;
+
+table! {
+    jobs (id) {
+        id -> Uuid,
+        queue -> Varchar,
+        status -> Varchar,
+        job -> Jsonb,
+        attempts -> Int4,
+        run_at -> Nullable<Timestamptz>,
+        timeout -> Int8,
+        runner_id -> Nullable<Uuid>,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+pub const STATUS_PENDING: &str = "pending";
+pub const STATUS_RUNNING: &str = "running";
+pub const STATUS_DEAD: &str = "dead";
+
+// Claims the next job of a queue that is either ready to run or was left
+// running for longer than its timeout.
+const CLAIM_JOB_QUERY: &str = "UPDATE jobs SET status = 'running', updated_at = $2
+WHERE id = (
+    SELECT id FROM jobs
+    WHERE queue = $1 AND (
+        (status = 'pending' AND (run_at IS NULL OR run_at < $2))
+        OR (status = 'running' AND updated_at + timeout * INTERVAL '1 millisecond' < $2)
+    )
+    ORDER BY run_at ASC NULLS FIRST, created_at ASC
+    LIMIT 1
+    FOR UPDATE SKIP LOCKED
+)
+RETURNING job";
+
+#[derive(Debug)]
+pub enum StorageError {
+    Pool(PoolError),
+    Database(DBError),
+    Json(serde_json::error::Error),
+}
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StorageError::Pool(e) => write!(f, "Job storage pool error: {}", e),
+            StorageError::Database(e) => write!(f, "Job storage database error: {}", e),
+            StorageError::Json(e) => write!(f, "Job storage serialization error: {}", e),
+        }
+    }
+}
+
+impl error::Error for StorageError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            StorageError::Pool(e) => Some(e),
+            StorageError::Database(e) => Some(e),
+            StorageError::Json(e) => Some(e),
+        }
+    }
+}
+
+impl From<PoolError> for StorageError {
+    fn from(e: PoolError) -> Self {
+        StorageError::Pool(e)
+    }
+}
+
+impl From<DBError> for StorageError {
+    fn from(e: DBError) -> Self {
+        StorageError::Database(e)
+    }
+}
+
+impl From<serde_json::error::Error> for StorageError {
+    fn from(e: serde_json::error::Error) -> Self {
+        StorageError::Json(e)
+    }
+}
+
+#[derive(QueryableByName)]
+#[table_name = "jobs"]
+struct ClaimedJob {
+    job: Value,
+}
+
+// `ReturnJobInfo` keeps its fields to the jobs crate, so we read them back
+// through its serialized form.
+#[derive(Deserialize)]
+struct ReturnedJob {
+    id: Uuid,
+    result: String,
+}
+
+// The retry settings a `JobInfo` was created with.
+#[derive(Deserialize)]
+struct RetryPolicy {
+    retry_count: u32,
+    max_retries: MaxRetries,
+    backoff_strategy: Backoff,
+}
+
+impl RetryPolicy {
+    fn should_retry(&self, attempts: u32) -> bool {
+        match self.max_retries {
+            MaxRetries::Infinite => true,
+            MaxRetries::Count(count) => attempts as usize <= count,
+        }
+    }
+
+    fn next_run(&self, attempts: u32, now: DateTime<Utc>) -> DateTime<Utc> {
+        let seconds = match self.backoff_strategy {
+            Backoff::Linear(seconds) => seconds,
+            Backoff::Exponential(base) => base.saturating_pow(attempts),
+        };
+        now + Duration::seconds(seconds as i64)
+    }
+}
+
+/// Stores jobs in the `jobs` table.
+#[derive(Clone)]
+pub struct PostgresStorage {
+    pool: DieselPgPool,
+}
+
+impl PostgresStorage {
+    /// Creates a new `PostgresStorage` on top of the given pool.
+    pub fn new(pool: DieselPgPool) -> Self {
+        PostgresStorage { pool }
+    }
+
+    /// Returns the jobs that failed permanently, oldest first.
+    pub fn dead_jobs(&self) -> Result<Vec<JobInfo>, StorageError> {
+        let conn = self.pool.get()?;
+        let values: Vec<Value> = jobs::table
+            .filter(jobs::status.eq(STATUS_DEAD))
+            .order_by(jobs::updated_at.asc())
+            .select(jobs::job)
+            .load(&conn)?;
+
+        Ok(values
+            .into_iter()
+            .map(serde_json::from_value)
+            .collect::<Result<_, _>>()?)
+    }
+
+    fn count_by_status(&self, job_status: &str) -> Result<usize, StorageError> {
+        let conn = self.pool.get()?;
+        let count: i64 = jobs::table
+            .filter(jobs::status.eq(job_status))
+            .count()
+            .get_result(&conn)?;
+
+        Ok(count as usize)
+    }
+
+    // Counts a failed attempt, then either schedules the next one or moves the
+    // job to the dead letter state.
+    fn fail_job(&self, id: Uuid) -> Result<(), StorageError> {
+        let conn = self.pool.get()?;
+        let mut value: Value = match jobs::table
+            .find(id)
+            .select(jobs::job)
+            .first(&conn)
+            .optional()?
+        {
+            Some(value) => value,
+            None => return Ok(()),
+        };
+
+        let policy: RetryPolicy = serde_json::from_value(value.clone())?;
+        let attempts = policy.retry_count + 1;
+        let now = Utc::now();
+        value["retry_count"] = attempts.into();
+        value["updated_at"] = serde_json::to_value(now)?;
+
+        if policy.should_retry(attempts) {
+            let next_run = policy.next_run(attempts, now);
+            value["status"] = "Pending".into();
+            value["next_queue"] = serde_json::to_value(next_run)?;
+            diesel::update(jobs::table.find(id))
+                .set((
+                    jobs::status.eq(STATUS_PENDING),
+                    jobs::job.eq(&value),
+                    jobs::attempts.eq(attempts as i32),
+                    jobs::run_at.eq(next_run),
+                    jobs::runner_id.eq(None::<Uuid>),
+                    jobs::updated_at.eq(now),
+                ))
+                .execute(&conn)?;
+        } else {
+            info!("Job {} failed permanently", id);
+            diesel::update(jobs::table.find(id))
+                .set((
+                    jobs::status.eq(STATUS_DEAD),
+                    jobs::job.eq(&value),
+                    jobs::attempts.eq(attempts as i32),
+                    jobs::runner_id.eq(None::<Uuid>),
+                    jobs::updated_at.eq(now),
+                ))
+                .execute(&conn)?;
+        }
+
+        Ok(())
+    }
+
+    // Puts a job that never ran back in its queue without counting an attempt.
+    fn requeue_job(&self, id: Uuid) -> Result<(), StorageError> {
+        let conn = self.pool.get()?;
+        let mut value: Value = match jobs::table
+            .find(id)
+            .select(jobs::job)
+            .first(&conn)
+            .optional()?
+        {
+            Some(value) => value,
+            None => return Ok(()),
+        };
+
+        let now = Utc::now();
+        value["status"] = "Pending".into();
+        value["updated_at"] = serde_json::to_value(now)?;
+        diesel::update(jobs::table.find(id))
+            .set((
+                jobs::status.eq(STATUS_PENDING),
+                jobs::job.eq(&value),
+                jobs::runner_id.eq(None::<Uuid>),
+                jobs::updated_at.eq(now),
+            ))
+            .execute(&conn)?;
+
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl Storage for PostgresStorage {
+    type Error = StorageError;
+
+    async fn generate_id(&self) -> Result<Uuid, Self::Error> {
+        Ok(Uuid::new_v4())
+    }
+
+    async fn save_job(&self, job: JobInfo) -> Result<(), Self::Error> {
+        let conn = self.pool.get()?;
+        let value = serde_json::to_value(&job)?;
+        let job_status = if job.status().is_running() {
+            STATUS_RUNNING
+        } else {
+            STATUS_PENDING
+        };
+        let attempts = value["retry_count"].as_i64().unwrap_or_default() as i32;
+
+        diesel::insert_into(jobs::table)
+            .values((
+                jobs::id.eq(job.id()),
+                jobs::queue.eq(job.queue()),
+                jobs::status.eq(job_status),
+                jobs::job.eq(&value),
+                jobs::attempts.eq(attempts),
+                jobs::run_at.eq(job.next_queue()),
+                jobs::timeout.eq(job.timeout()),
+                jobs::updated_at.eq(job.updated_at()),
+            ))
+            .on_conflict(jobs::id)
+            .do_update()
+            .set((
+                jobs::queue.eq(excluded(jobs::queue)),
+                jobs::status.eq(excluded(jobs::status)),
+                jobs::job.eq(excluded(jobs::job)),
+                jobs::attempts.eq(excluded(jobs::attempts)),
+                jobs::run_at.eq(excluded(jobs::run_at)),
+                jobs::timeout.eq(excluded(jobs::timeout)),
+                jobs::updated_at.eq(excluded(jobs::updated_at)),
+            ))
+            .execute(&conn)?;
+
+        Ok(())
+    }
+
+    async fn fetch_job(&self, id: Uuid) -> Result<Option<JobInfo>, Self::Error> {
+        let conn = self.pool.get()?;
+        let value: Option<Value> = jobs::table
+            .find(id)
+            .select(jobs::job)
+            .first(&conn)
+            .optional()?;
+
+        Ok(value.map(serde_json::from_value).transpose()?)
+    }
+
+    async fn fetch_job_from_queue(&self, queue: &str) -> Result<Option<JobInfo>, Self::Error> {
+        let conn = self.pool.get()?;
+        let claimed: Option<ClaimedJob> = diesel::sql_query(CLAIM_JOB_QUERY)
+            .bind::<diesel::sql_types::Varchar, _>(queue)
+            .bind::<diesel::sql_types::Timestamptz, _>(Utc::now())
+            .get_result(&conn)
+            .optional()?;
+
+        Ok(claimed
+            .map(|claimed| serde_json::from_value(claimed.job))
+            .transpose()?)
+    }
+
+    async fn queue_job(&self, queue: &str, id: Uuid) -> Result<(), Self::Error> {
+        let conn = self.pool.get()?;
+        diesel::update(jobs::table.find(id).filter(jobs::status.ne(STATUS_DEAD)))
+            .set((
+                jobs::queue.eq(queue),
+                jobs::status.eq(STATUS_PENDING),
+                jobs::runner_id.eq(None::<Uuid>),
+            ))
+            .execute(&conn)?;
+
+        Ok(())
+    }
+
+    async fn run_job(&self, id: Uuid, runner_id: Uuid) -> Result<(), Self::Error> {
+        let conn = self.pool.get()?;
+        diesel::update(jobs::table.find(id))
+            .set((
+                jobs::status.eq(STATUS_RUNNING),
+                jobs::runner_id.eq(runner_id),
+            ))
+            .execute(&conn)?;
+
+        Ok(())
+    }
+
+    async fn delete_job(&self, id: Uuid) -> Result<(), Self::Error> {
+        let conn = self.pool.get()?;
+        diesel::delete(jobs::table.find(id)).execute(&conn)?;
+
+        Ok(())
+    }
+
+    async fn get_stats(&self) -> Result<Stats, Self::Error> {
+        Ok(Stats {
+            pending: self.count_by_status(STATUS_PENDING)?,
+            running: self.count_by_status(STATUS_RUNNING)?,
+            ..Stats::new()
+        })
+    }
+
+    // Statistics are derived from the `jobs` table in `get_stats` instead.
+    async fn update_stats<F>(&self, _f: F) -> Result<(), Self::Error>
+    where
+        F: Fn(Stats) -> Stats + Send + 'static,
+    {
+        Ok(())
+    }
+
+    // Unlike the default implementation, jobs that run out of retries are kept
+    // in the dead letter state rather than deleted.
+    async fn return_job(&self, info: ReturnJobInfo) -> Result<(), Self::Error> {
+        let returned: ReturnedJob = serde_json::from_value(serde_json::to_value(&info)?)?;
+
+        match returned.result.as_str() {
+            "Success" => self.delete_job(returned.id).await,
+            "Failure" => self.fail_job(returned.id),
+            _ => self.requeue_job(returned.id),
+        }
+    }
+}
@@ -18,7 +18,9 @@ pub use futures;
 pub use serde;
 pub use serde_json;
 pub use tera;
-pub use diesel;
+
+#[macro_use]
+pub extern crate diesel;
 
 #[macro_use]
 pub extern crate log;
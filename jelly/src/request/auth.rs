@@ -1,8 +1,5 @@
 use actix_session::UserSession;
-use actix_web::{
-    cookie::{CookieJar, Key, Cookie},
-    HttpRequest, HttpMessage,
-};
+use actix_web::HttpRequest;
 
 use crate::accounts::User;
 use crate::error::Error;
@@ -14,7 +11,8 @@ use crate::error::Error;
 /// has issues, or... pick your poison I guess.
 ///
 pub trait Authentication {
-    /// Returns whether a user session exists and is valid.
+    /// Returns whether a user session exists. Apps that keep track of sessions should also
+    /// check them against their records.
     fn is_authenticated(&self) -> Result<bool, Error>;
 
     /// Sets a serializable user instance.
@@ -27,26 +25,10 @@ pub trait Authentication {
 impl Authentication for HttpRequest {
     #[inline(always)]
     fn is_authenticated(&self) -> Result<bool, Error> {
-        if self
+        Ok(self
             .get_session()
             .get::<serde_json::Value>("sku")?
-            .is_some() {
-            Ok(true)
-        }
-        else {
-            let remember_me_token = match self.cookie("remember_me_token") {
-                Some(cookie) => cookie.value().to_owned(),
-                None => return Ok(false)
-            };
-            const CRYPTOGRAPHIC_KEY_LEN: usize = 43;
-            let user_id = &remember_me_token[CRYPTOGRAPHIC_KEY_LEN + 1..];
-
-            let key = std::env::var("SECRET_KEY").expect("SECRET_KEY not set!");
-            let mut jar = CookieJar::new();
-            jar.signed(&Key::derive_from(key.as_bytes()))
-                .add(Cookie::new("re_signed_token", user_id.to_owned()));
-            Ok(remember_me_token == jar.get("re_signed_token").unwrap().value())
-        }
+            .is_some())
     }
 
     fn set_user(&self, account: User) -> Result<(), Error> {
@@ -0,0 +1,115 @@
+//! Helpers for telling who a request comes from.
+//!
+//! The `Forwarded` and `X-Forwarded-For` headers can be set by anyone, so they
+//! are only believed when the connection comes from one of the proxies listed
+//! in `TRUSTED_PROXIES`, a comma separated list of IP addresses and networks.
+
+use std::env;
+use std::net::{IpAddr, SocketAddr};
+
+use actix_web::http::header::{HeaderMap, AUTHORIZATION, FORWARDED};
+use actix_web::HttpRequest;
+use ipnet::IpNet;
+use lazy_static::lazy_static;
+
+lazy_static! {
+    static ref TRUSTED_PROXIES: Vec<IpNet> =
+        parse_trusted_proxies(&env::var("TRUSTED_PROXIES").unwrap_or_default());
+}
+
+/// Returns the IP address of the client: the peer address of the connection, or the
+/// address forwarded by the trusted proxies in front of the app.
+pub fn client_ip(request: &HttpRequest) -> Option<String> {
+    let peer = request.peer_addr()?.ip();
+    Some(resolve_client_ip(peer, &forwarded_for(request.headers()), &TRUSTED_PROXIES).to_string())
+}
+
+/// Walks the forwarded addresses back from the peer, as long as each hop was added by
+/// a trusted proxy, and returns the first address that wasn't.
+pub fn resolve_client_ip(peer: IpAddr, forwarded_for: &[String], trusted: &[IpNet]) -> IpAddr {
+    let mut client = peer;
+    for address in forwarded_for.iter().rev() {
+        if !trusted.iter().any(|network| network.contains(&client)) {
+            break;
+        }
+        match parse_address(address) {
+            Some(address) => client = address,
+            // Nothing past a hop we can't read can be trusted either
+            None => break,
+        }
+    }
+    client
+}
+
+/// Parses a comma separated list of IP addresses and networks, skipping invalid entries.
+pub fn parse_trusted_proxies(value: &str) -> Vec<IpNet> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .filter_map(|entry| {
+            let network = entry
+                .parse::<IpNet>()
+                .ok()
+                .or_else(|| entry.parse::<IpAddr>().ok().map(IpNet::from));
+            if network.is_none() {
+                warn!("Ignoring invalid TRUSTED_PROXIES entry: {}", entry);
+            }
+            network
+        })
+        .collect()
+}
+
+/// Returns the token of an `Authorization: Bearer <token>` header.
+pub fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    let authorization = headers.get(AUTHORIZATION)?.to_str().ok()?;
+    let (scheme, token) = authorization.trim().split_once(' ')?;
+    if !scheme.eq_ignore_ascii_case("bearer") || token.trim().is_empty() {
+        return None;
+    }
+    Some(token.trim())
+}
+
+// The hops of the `Forwarded` header, or of `X-Forwarded-For` without one, from the
+// client to the last proxy.
+fn forwarded_for(headers: &HeaderMap) -> Vec<String> {
+    let forwarded = headers
+        .get_all(FORWARDED)
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|hop| {
+            hop.split(';').find_map(|pair| {
+                let (name, value) = pair.trim().split_once('=')?;
+                if name.trim().eq_ignore_ascii_case("for") {
+                    Some(value.trim().trim_matches('"').to_string())
+                } else {
+                    None
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+    if !forwarded.is_empty() {
+        return forwarded;
+    }
+
+    headers
+        .get_all("x-forwarded-for")
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|hop| hop.trim().to_string())
+        .collect()
+}
+
+// Reads an address with or without a port, like `10.0.0.1:1234` or `[::1]:1234`.
+fn parse_address(address: &str) -> Option<IpAddr> {
+    address
+        .parse::<IpAddr>()
+        .or_else(|_| address.parse::<SocketAddr>().map(|address| address.ip()))
+        .or_else(|_| {
+            address
+                .trim_start_matches('[')
+                .trim_end_matches(']')
+                .parse()
+        })
+        .ok()
+}
@@ -0,0 +1,39 @@
+use actix_web::cookie::Cookie;
+use actix_web::dev::ServiceResponse;
+use actix_web::HttpRequest;
+
+/// Cookies waiting to be added to the response of a request.
+#[derive(Default)]
+struct PendingCookies(Vec<Cookie<'static>>);
+
+/// `ResponseCookies` lets code that only sees the request, like authentication helpers
+/// and guards, set cookies. The server adds them to whatever response the request gets.
+pub trait ResponseCookies {
+    /// Queues a cookie to be set on the response.
+    fn add_response_cookie(&self, cookie: Cookie<'static>);
+}
+
+impl ResponseCookies for HttpRequest {
+    fn add_response_cookie(&self, cookie: Cookie<'static>) {
+        let mut extensions = self.extensions_mut();
+        match extensions.get_mut::<PendingCookies>() {
+            Some(pending) => pending.0.push(cookie),
+            None => extensions.insert(PendingCookies(vec![cookie])),
+        }
+    }
+}
+
+/// Internally used; moves the queued cookies of the request onto its response.
+pub(crate) fn set_pending_cookies<B>(response: &mut ServiceResponse<B>) {
+    let pending = response
+        .request()
+        .extensions_mut()
+        .remove::<PendingCookies>();
+    if let Some(PendingCookies(cookies)) = pending {
+        for cookie in cookies {
+            if let Err(e) = response.response_mut().add_cookie(&cookie) {
+                error!("Error setting cookie {}: {:?}", cookie.name(), e);
+            }
+        }
+    }
+}
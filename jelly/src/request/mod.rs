@@ -6,6 +6,11 @@
 pub mod auth;
 pub use auth::Authentication;
 
+pub mod client;
+
+pub mod cookies;
+pub use cookies::ResponseCookies;
+
 pub mod database;
 pub use database::DatabasePool;
 
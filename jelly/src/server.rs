@@ -1,16 +1,17 @@
 use std::env;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 
 use crate::email::{Configurable, Email};
-use crate::jobs::{JobState, DEFAULT_QUEUE};
-use crate::request::Render;
+use crate::jobs::{JobState, PostgresStorage, DEFAULT_QUEUE};
+use crate::request::{cookies, Render};
 use crate::{database, DieselPgPool};
+use actix_service::Service;
 use actix_session::CookieSession;
 use actix_web::web::ServiceConfig;
 use actix_web::{dev, middleware, web, App, HttpResponse, HttpServer};
 use actix_web_middleware_redirect_scheme::RedirectSchemeBuilder;
-use background_jobs::memory_storage::Storage;
-use background_jobs::{create_server, WorkerConfig};
+use background_jobs::{create_server, QueueHandle, WorkerConfig};
 use middleware::normalize::TrailingSlash;
 
 /// This struct provides a slightly simpler way to write `main.rs` in
@@ -19,6 +20,7 @@ pub struct Server {
     apps: Vec<Box<dyn Fn(&mut ServiceConfig) + Send + Sync + 'static>>,
     jobs:
         Vec<Box<dyn Fn(WorkerConfig<JobState>) -> WorkerConfig<JobState> + Send + Sync + 'static>>,
+    schedules: Vec<Box<dyn Fn(&QueueHandle) + Send + Sync + 'static>>,
 }
 
 impl Server {
@@ -27,6 +29,7 @@ impl Server {
         Self {
             apps: vec![],
             jobs: vec![],
+            schedules: vec![],
         }
     }
 
@@ -48,6 +51,16 @@ impl Server {
         self
     }
 
+    /// Registers recurring jobs. The handler is called once with the queue,
+    /// not once per worker.
+    pub fn register_schedule<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(&QueueHandle) + Send + Sync + 'static,
+    {
+        self.schedules.push(Box::new(handler));
+        self
+    }
+
     /// Consumes and then runs the server, with default settings that we
     /// generally want.
     pub async fn run(self) -> std::io::Result<(dev::Server, DieselPgPool)> {
@@ -75,6 +88,8 @@ impl Server {
         let pool_return = pool.clone();
         let apps = Arc::new(self.apps);
         let jobs = Arc::new(self.jobs);
+        let schedules = Arc::new(self.schedules);
+        let is_scheduled = Arc::new(AtomicBool::new(false));
 
         let server = HttpServer::new(move || {
             // !production needs no domain set, because browsers.
@@ -115,6 +130,14 @@ impl Server {
                         .enable(should_redirect_https)
                         .build(),
                 )
+                .wrap_fn(|req, srv| {
+                    let response = srv.call(req);
+                    async {
+                        let mut response = response.await?;
+                        cookies::set_pending_cookies(&mut response);
+                        Ok(response)
+                    }
+                })
                 .wrap(session_storage)
                 .wrap(middleware::NormalizePath::new(TrailingSlash::Trim))
                 // Depending on your CORS needs, you may opt to change this
@@ -134,7 +157,7 @@ impl Server {
                 app = app.configure(|c| handler(c));
             }
 
-            let storage = Storage::new();
+            let storage = PostgresStorage::new(pool.clone());
             let queue = create_server(storage);
             let state = JobState::new("JobState", pool.clone(), templates.clone());
             let mut worker_config = WorkerConfig::new(move || state.clone());
@@ -148,6 +171,12 @@ impl Server {
                 .set_worker_count(DEFAULT_QUEUE, 16)
                 .start(queue.clone());
 
+            if !is_scheduled.swap(true, Ordering::SeqCst) {
+                for handler in schedules.iter() {
+                    handler(&queue);
+                }
+            }
+
             app.app_data(web::Data::new(queue.clone()))
         })
         .backlog(8192)
@@ -1,19 +1,78 @@
 pub const MSG_UNEXPECTED_ERROR: &str = "Encountered unexpected error.";
+pub const MSG_TOO_MANY_REQUESTS: &str = "Too many requests. Try again later.";
 pub const MSG_BAD_REQUEST: &str = "Bad Request.";
+pub const MSG_UNAUTHORIZED: &str = "Unauthorized.";
 pub const MSG_PACKAGE_NOT_FOUND: &str = "Package not found.";
 pub const MSG_ACCOUNT_NOT_FOUND: &str = "Account not found.";
 pub const MSG_INVITATION_NOT_FOUND: &str = "Invitation not found.";
 pub const MSG_COLLABORATOR_NOT_FOUND: &str = "Collaborator or invitation not found.";
+pub const MSG_PACKAGE_VERSION_NOT_FOUND: &str = "Package version not found.";
+pub const MSG_INVALID_API_TOKEN: &str = "Invalid API token.";
+pub const MSG_API_TOKEN_EXPIRED: &str =
+    "This API token has expired. Please generate a new token in your account settings.";
+pub const MSG_API_TOKEN_SCOPE_NOT_ALLOWED: &str =
+    "This API token doesn't have the scope required for this action.";
+pub const MSG_API_TOKEN_PACKAGE_NOT_ALLOWED: &str =
+    "This API token is not allowed to be used for this package.";
+pub const MSG_ADMIN_REQUIRED: &str = "Sorry, only administrators can do this.";
+pub const MSG_INVALID_REPOSITORY_URL: &str = "Please provide the url of a git repository.";
 pub const MSG_ACCOUNT_NOT_FOUND_DONT_INVITE: &str =
     "This account is not a Movey user. Inform them by entering their email address.";
 pub const MSG_ACCOUNT_NOT_FOUND_INVITING: &str = "This account is not a Movey user. We are trying to invite this person to join you as a collaborator.";
 
 pub const MSG_UNAUTHORIZED_TO_ADD_COLLABORATOR: &str =
-    "Sorry, only the owner or collaborator can add collaborators to this package.";
+    "Sorry, only the owner or collaborators allowed to invite can add collaborators to this package.";
 pub const MSG_UNAUTHORIZED_TO_REMOVE_COLLABORATOR: &str =
-    "Sorry, only the owner or collaborator can remove collaborators from this package.";
+    "Sorry, only the owner or collaborators allowed to manage collaborators can remove collaborators from this package.";
+pub const MSG_UNAUTHORIZED_TO_CHANGE_PERMISSIONS: &str =
+    "Sorry, only the owner or collaborators allowed to manage collaborators can change the permissions of other collaborators.";
 pub const MSG_UNAUTHORIZED_TO_TRANSFER_OWNERSHIP: &str =
     "Sorry, only the owner can transfer ownership to another collaborator.";
+pub const MSG_UNAUTHORIZED_TO_MANAGE_OWNERS: &str =
+    "Sorry, only the owners can add or remove other owners of this package.";
+pub const MSG_TWO_FACTOR_REQUIRED: &str =
+    "This package requires two-factor authentication. Please enable it in your profile settings first.";
+pub const MSG_ENABLE_TWO_FACTOR_FIRST: &str =
+    "Please enable two-factor authentication for your own account before requiring it for this package.";
+pub const MSG_OWNER_NOT_FOUND: &str = "This user is not an owner of the package.";
+pub const MSG_CANNOT_REMOVE_LAST_OWNER: &str =
+    "A package must keep at least one owner. Please add another owner first.";
+pub const MSG_UNAUTHORIZED_TO_YANK_VERSION: &str =
+    "Sorry, only the owner can yank or unyank versions of this package.";
+pub const MSG_UNAUTHORIZED_TO_MANAGE_WEBHOOK: &str =
+    "Sorry, only the owner can manage the webhook of this package.";
+pub const MSG_WEBHOOK_NOT_FOUND: &str = "This package has no webhook.";
+pub const MSG_INVALID_WEBHOOK_SIGNATURE: &str = "The webhook signature is invalid.";
+pub const MSG_INVALID_WEBHOOK_PAYLOAD: &str = "The webhook payload is invalid.";
+pub const MSG_WEBHOOK_REPOSITORY_MISMATCH: &str =
+    "The webhook was sent from another repository than the package's.";
+pub const MSG_WEBHOOK_EVENT_IGNORED: &str =
+    "Only tag pushes and published releases are published.";
+pub const MSG_PACKAGE_ALREADY_CLAIMED: &str = "This package already has an owner.";
+pub const MSG_GITHUB_ACCOUNT_NOT_LINKED: &str =
+    "Please connect your Github account to claim packages through Github.";
+pub const MSG_CLAIM_WITH_GITHUB_UNSUPPORTED: &str =
+    "Only packages hosted on Github can be claimed through Github. Please verify with a token instead.";
+pub const MSG_NO_REPOSITORY_PUSH_ACCESS: &str =
+    "Your Github account doesn't own the repository of this package. Please verify with a token instead.";
+pub const MSG_CLAIM_TOKEN_NOT_ISSUED: &str = "Please get a verification token first.";
+pub const MSG_CLAIM_TOKEN_NOT_FOUND: &str =
+    "The verification token was not found on the default branch of the repository.";
+pub const MSG_INVALID_NAME: &str = "Please provide a name with at least one letter or digit.";
+pub const MSG_ORGANIZATION_NOT_FOUND: &str = "Organization not found.";
+pub const MSG_ORGANIZATION_ALREADY_EXISTED: &str = "An organization with this name already exists.";
+pub const MSG_UNAUTHORIZED_TO_MANAGE_ORGANIZATION: &str =
+    "Sorry, only the admins can manage this organization.";
+pub const MSG_UNAUTHORIZED_TO_TRANSFER_PACKAGE_TO_ORGANIZATION: &str =
+    "Sorry, only the owner of the package can transfer it to an organization they administer.";
+pub const MSG_ORGANIZATION_MEMBER_NOT_FOUND: &str = "This user is not a member of the organization.";
+pub const MSG_ORGANIZATION_MEMBER_ALREADY_EXISTED: &str =
+    "This user is already a member of the organization.";
+pub const MSG_CANNOT_REMOVE_LAST_ORGANIZATION_ADMIN: &str =
+    "An organization needs at least one admin.";
+pub const MSG_TEAM_NOT_FOUND: &str = "Team not found.";
+pub const MSG_TEAM_ALREADY_EXISTED: &str = "A team with this name already exists.";
+pub const MSG_TEAM_MEMBER_ALREADY_EXISTED: &str = "This user is already a member of the team.";
 pub const MSG_FAILURE_INVITING_COLLABORATOR: &str = "Error creating collaborator invite.";
 pub const MSG_SUCCESSFULLY_INVITED_COLLABORATOR: &str =
     "Collaborator invitation is created successfully.";
@@ -21,6 +80,29 @@ pub const MSG_SUCCESSFULLY_TRANSFER_OWNERSHIP: &str =
     "An ownership transfer invitation has been sent to collaborator";
 pub const MSG_SUCCESSFULLY_ADDED_COLLABORATOR: &str = "Collaborator is added successfully.";
 pub const MSG_SUCCESSFULLY_REMOVED_COLLABORATOR: &str = "Collaborator is removed successfully.";
+pub const MSG_SUCCESSFULLY_ADDED_OWNER: &str = "Owner is added successfully.";
+pub const MSG_SUCCESSFULLY_REMOVED_OWNER: &str = "Owner is removed successfully.";
+pub const MSG_SUCCESSFULLY_UPDATED_PERMISSIONS: &str = "Permissions are updated successfully.";
+pub const MSG_SUCCESSFULLY_UPDATED_TWO_FACTOR_REQUIREMENT: &str =
+    "Two-factor authentication requirement is updated successfully.";
+pub const MSG_SUCCESSFULLY_YANKED_VERSION: &str = "Version is yanked successfully.";
+pub const MSG_SUCCESSFULLY_UNYANKED_VERSION: &str = "Version is unyanked successfully.";
+pub const MSG_SUCCESSFULLY_QUEUED_CRAWL: &str = "The repository will be crawled shortly.";
+pub const MSG_SUCCESSFULLY_CLAIMED_PACKAGE: &str = "You are now the owner of this package.";
+pub const MSG_SUCCESSFULLY_QUEUED_PUBLISH: &str = "The tag will be published shortly.";
+pub const MSG_SUCCESSFULLY_DELETED_WEBHOOK: &str = "Webhook is deleted successfully.";
+pub const MSG_SUCCESSFULLY_INVITED_ORGANIZATION_MEMBER: &str =
+    "Organization invitation is created successfully.";
+pub const MSG_SUCCESSFULLY_REMOVED_ORGANIZATION_MEMBER: &str =
+    "Member is removed from the organization successfully.";
+pub const MSG_SUCCESSFULLY_ADDED_TEAM_MEMBER: &str = "Member is added to the team successfully.";
+pub const MSG_SUCCESSFULLY_REMOVED_TEAM_MEMBER: &str =
+    "Member is removed from the team successfully.";
+pub const MSG_SUCCESSFULLY_TRANSFERRED_PACKAGE_TO_ORGANIZATION: &str =
+    "Package is now owned by the organization.";
+pub const MSG_SUCCESSFULLY_REMOVED_PACKAGE_FROM_ORGANIZATION: &str =
+    "Package is no longer owned by the organization.";
+pub const MSG_SUCCESSFULLY_UNLOCKED_ACCOUNT: &str = "Account is unlocked successfully.";
 pub const MSG_INVITATION_EXPIRED: &str = "Invitation expired.";
 pub const MSG_INVITATION_ALREADY_EXISTED: &str = "Invitation already sent.";
 pub const MSG_COLLABORATOR_ALREADY_EXISTED: &str = "Collaborator already existed.";
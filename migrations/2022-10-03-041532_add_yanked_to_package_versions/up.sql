@@ -0,0 +1 @@
+ALTER TABLE package_versions ADD COLUMN IF NOT EXISTS yanked BOOLEAN NOT NULL DEFAULT false;
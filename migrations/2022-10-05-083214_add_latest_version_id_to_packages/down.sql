@@ -0,0 +1 @@
+ALTER TABLE packages DROP COLUMN latest_version_id;
@@ -0,0 +1,27 @@
+ALTER TABLE packages ADD COLUMN IF NOT EXISTS latest_version_id INTEGER REFERENCES package_versions(id) ON DELETE SET NULL;
+
+-- Packages whose versions are all plain MAJOR.MINOR.PATCH are ranked here the same way as
+-- PackageVersion::latest_version_key: versions that are not yanked first, then by version
+-- number, then the newest row. Packages with pre-releases, build metadata or versions that
+-- are not semver are left for the BackfillLatestVersions job, which runs when the app starts
+-- and ranks them with the semver crate like the rest of the application.
+UPDATE packages
+SET latest_version_id = ranked.id
+FROM (
+    SELECT DISTINCT ON (package_id) package_id, id
+    FROM package_versions
+    WHERE package_id NOT IN (
+        SELECT package_id
+        FROM package_versions
+        WHERE version !~ '^(0|[1-9][0-9]*)\.(0|[1-9][0-9]*)\.(0|[1-9][0-9]*)$'
+    )
+    ORDER BY
+        package_id,
+        yanked ASC,
+        split_part(version, '.', 1)::NUMERIC DESC,
+        split_part(version, '.', 2)::NUMERIC DESC,
+        split_part(version, '.', 3)::NUMERIC DESC,
+        id DESC
+) AS ranked
+WHERE packages.id = ranked.package_id
+    AND packages.latest_version_id IS NULL;
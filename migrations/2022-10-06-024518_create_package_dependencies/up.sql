@@ -0,0 +1,16 @@
+CREATE TABLE IF NOT EXISTS package_dependencies (
+     id SERIAL PRIMARY KEY,
+     package_version_id INTEGER NOT NULL REFERENCES package_versions (id) ON DELETE CASCADE,
+     name TEXT NOT NULL,
+     kind INTEGER NOT NULL,
+     local TEXT,
+     git TEXT,
+     rev TEXT,
+     subdir TEXT,
+     movey TEXT,
+     dependency_package_id INTEGER REFERENCES packages (id) ON DELETE SET NULL,
+     created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS package_dependencies_package_version_id_idx ON package_dependencies (package_version_id);
+CREATE INDEX IF NOT EXISTS package_dependencies_dependency_package_id_idx ON package_dependencies (dependency_package_id);
@@ -0,0 +1,11 @@
+CREATE TABLE IF NOT EXISTS package_version_addresses (
+     id SERIAL PRIMARY KEY,
+     package_version_id INTEGER NOT NULL REFERENCES package_versions (id) ON DELETE CASCADE,
+     name TEXT NOT NULL,
+     address TEXT,
+     is_dev BOOLEAN NOT NULL DEFAULT false,
+     created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS package_version_addresses_package_version_id_idx ON package_version_addresses (package_version_id);
+CREATE INDEX IF NOT EXISTS package_version_addresses_address_idx ON package_version_addresses (address);
@@ -0,0 +1,4 @@
+ALTER TABLE api_tokens
+    DROP COLUMN endpoint_scopes,
+    DROP COLUMN package_scopes,
+    DROP COLUMN expired_at;
@@ -0,0 +1,5 @@
+-- NULL scopes mean the token is not restricted, which keeps existing tokens working
+ALTER TABLE api_tokens
+    ADD COLUMN endpoint_scopes TEXT[],
+    ADD COLUMN package_scopes TEXT[],
+    ADD COLUMN expired_at TIMESTAMPTZ;
@@ -0,0 +1,15 @@
+CREATE TABLE jobs (
+    id UUID PRIMARY KEY,
+    queue VARCHAR NOT NULL,
+    status VARCHAR NOT NULL DEFAULT 'pending',
+    job JSONB NOT NULL,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    run_at TIMESTAMPTZ,
+    timeout BIGINT NOT NULL,
+    runner_id UUID,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CONSTRAINT jobs_status_check CHECK (status IN ('pending', 'running', 'dead'))
+);
+
+CREATE INDEX jobs_queue_status_run_at_idx ON jobs (queue, status, run_at);
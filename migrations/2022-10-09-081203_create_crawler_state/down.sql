@@ -0,0 +1,2 @@
+DROP TABLE crawled_repos;
+DROP TABLE crawl_cursors;
@@ -0,0 +1,20 @@
+-- Where the GitHub search crawl is up to, so it can resume after a restart
+CREATE TABLE crawl_cursors (
+    name VARCHAR PRIMARY KEY,
+    page INTEGER NOT NULL DEFAULT 0,
+    next_run_at TIMESTAMPTZ,
+    locked_until TIMESTAMPTZ,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Every Move.toml found by the crawler, so packages can be re-crawled once they get stale
+CREATE TABLE crawled_repos (
+    id SERIAL PRIMARY KEY,
+    repository_url TEXT NOT NULL,
+    path TEXT NOT NULL,
+    last_crawled_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (repository_url, path)
+);
+
+CREATE INDEX crawled_repos_last_crawled_at_idx ON crawled_repos (last_crawled_at);
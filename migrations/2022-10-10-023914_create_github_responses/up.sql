@@ -0,0 +1,10 @@
+-- Responses from GitHub, so repeated fetches can be made as conditional requests
+CREATE TABLE github_responses (
+    url TEXT PRIMARY KEY,
+    etag TEXT,
+    body TEXT NOT NULL,
+    -- Content at a fixed commit never changes, so it is served without asking GitHub
+    is_immutable BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,10 @@
+-- GitHub webhooks that publish new versions of a package when a tag is pushed
+CREATE TABLE package_webhooks (
+    id SERIAL PRIMARY KEY,
+    package_id INTEGER NOT NULL UNIQUE REFERENCES packages (id) ON DELETE CASCADE,
+    -- Shared with GitHub to sign deliveries
+    secret VARCHAR NOT NULL,
+    created_by INTEGER NOT NULL REFERENCES accounts (id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
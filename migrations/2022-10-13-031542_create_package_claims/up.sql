@@ -0,0 +1,10 @@
+-- Verification tokens of users claiming the ownership of crawled packages
+CREATE TABLE package_claims (
+    id SERIAL PRIMARY KEY,
+    package_id INTEGER NOT NULL REFERENCES packages (id) ON DELETE CASCADE,
+    account_id INTEGER NOT NULL REFERENCES accounts (id) ON DELETE CASCADE,
+    -- Committed to the repository to prove control over it
+    token VARCHAR NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (package_id, account_id)
+);
@@ -0,0 +1,7 @@
+DROP INDEX IF EXISTS packages_organization_id_idx;
+ALTER TABLE packages DROP COLUMN IF EXISTS organization_id;
+DROP TABLE IF EXISTS organization_invitations;
+DROP TABLE IF EXISTS team_members;
+DROP TABLE IF EXISTS teams;
+DROP TABLE IF EXISTS organization_members;
+DROP TABLE IF EXISTS organizations;
@@ -0,0 +1,54 @@
+-- Organizations own packages on behalf of their members
+CREATE TABLE organizations (
+    id SERIAL PRIMARY KEY,
+    name VARCHAR NOT NULL CHECK (name <> ''),
+    slug VARCHAR NOT NULL UNIQUE,
+    description TEXT NOT NULL DEFAULT '',
+    created_by INTEGER NOT NULL REFERENCES accounts (id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE organization_members (
+    organization_id INTEGER NOT NULL REFERENCES organizations (id) ON DELETE CASCADE,
+    account_id INTEGER NOT NULL REFERENCES accounts (id) ON DELETE CASCADE,
+    -- 0 for admins, 1 for members
+    role INTEGER NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    PRIMARY KEY (organization_id, account_id)
+);
+
+CREATE TABLE teams (
+    id SERIAL PRIMARY KEY,
+    organization_id INTEGER NOT NULL REFERENCES organizations (id) ON DELETE CASCADE,
+    name VARCHAR NOT NULL CHECK (name <> ''),
+    slug VARCHAR NOT NULL,
+    -- Members of the team are collaborators on every package of the organization
+    is_collaborator BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    UNIQUE (organization_id, slug)
+);
+
+CREATE TABLE team_members (
+    team_id INTEGER NOT NULL REFERENCES teams (id) ON DELETE CASCADE,
+    account_id INTEGER NOT NULL REFERENCES accounts (id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    PRIMARY KEY (team_id, account_id)
+);
+
+CREATE TABLE organization_invitations (
+    invited_user_id INTEGER NOT NULL REFERENCES accounts (id) ON DELETE CASCADE,
+    invited_by_user_id INTEGER NOT NULL REFERENCES accounts (id) ON DELETE CASCADE,
+    organization_id INTEGER NOT NULL REFERENCES organizations (id) ON DELETE CASCADE,
+    token TEXT NOT NULL,
+    role INTEGER NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+
+    PRIMARY KEY (invited_user_id, organization_id)
+);
+
+ALTER TABLE packages ADD COLUMN organization_id INTEGER REFERENCES organizations (id) ON DELETE SET NULL;
+CREATE INDEX packages_organization_id_idx ON packages (organization_id);
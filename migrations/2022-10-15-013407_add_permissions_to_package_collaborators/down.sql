@@ -0,0 +1,4 @@
+ALTER TABLE package_collaborators
+    DROP COLUMN IF EXISTS can_publish,
+    DROP COLUMN IF EXISTS can_manage_collaborators,
+    DROP COLUMN IF EXISTS can_invite;
@@ -0,0 +1,6 @@
+-- Owners may do everything and are the only ones who change permissions. Collaborators
+-- need to be allowed to publish, invite and remove collaborators.
+ALTER TABLE package_collaborators
+    ADD COLUMN can_publish BOOLEAN NOT NULL DEFAULT FALSE,
+    ADD COLUMN can_manage_collaborators BOOLEAN NOT NULL DEFAULT FALSE,
+    ADD COLUMN can_invite BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS package_events;
+DROP FUNCTION IF EXISTS reject_package_events_change();
@@ -0,0 +1,26 @@
+-- Append-only log of the actions taken on packages. Accounts and tokens are kept as plain
+-- ids so that entries outlive them.
+CREATE TABLE package_events (
+    id SERIAL PRIMARY KEY,
+    package_id INTEGER NOT NULL REFERENCES packages (id),
+    kind INTEGER NOT NULL,
+    actor_id INTEGER,
+    api_token_id INTEGER,
+    -- The account the action was taken on, e.g. the invited collaborator
+    target_account_id INTEGER,
+    -- e.g. the published version or the email of an external invitation
+    details TEXT NOT NULL DEFAULT '',
+    ip_address VARCHAR,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX package_events_package_id_created_at_idx ON package_events (package_id, created_at);
+
+CREATE FUNCTION reject_package_events_change() RETURNS TRIGGER AS $$
+BEGIN
+    RAISE EXCEPTION 'package_events is append-only';
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER package_events_append_only BEFORE UPDATE OR DELETE ON package_events
+    FOR EACH ROW EXECUTE PROCEDURE reject_package_events_change();
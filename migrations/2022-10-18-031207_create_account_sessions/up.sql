@@ -0,0 +1,18 @@
+-- Signed-in browsers of an account. The cookie session and the remember-me cookie both point
+-- to a row, so deleting it signs that browser out.
+CREATE TABLE account_sessions (
+    id SERIAL PRIMARY KEY,
+    account_id INTEGER NOT NULL REFERENCES accounts (id) ON DELETE CASCADE,
+    -- SHA256 of the remember-me token, rotated each time it is used
+    remember_me_token VARCHAR UNIQUE,
+    -- SHA256 of the password hash of the account at sign in, changing the password
+    -- invalidates the session
+    password_fingerprint VARCHAR NOT NULL,
+    user_agent VARCHAR NOT NULL DEFAULT '',
+    ip_address VARCHAR,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    last_seen_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX account_sessions_account_id_idx ON account_sessions (account_id);
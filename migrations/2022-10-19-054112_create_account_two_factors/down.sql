@@ -0,0 +1,2 @@
+ALTER TABLE packages DROP COLUMN IF EXISTS require_two_factor;
+DROP TABLE IF EXISTS account_two_factors;
@@ -0,0 +1,14 @@
+-- An account has at most one TOTP secret. It only protects sign-ins once the first
+-- code has been confirmed and `enabled_at` is set.
+CREATE TABLE account_two_factors (
+    account_id INTEGER PRIMARY KEY REFERENCES accounts (id) ON DELETE CASCADE,
+    secret VARCHAR NOT NULL,
+    -- sha256 hashes of the unused recovery codes
+    recovery_codes TEXT[] NOT NULL DEFAULT '{}',
+    -- time step of the last accepted code, so that a code can't be used twice
+    last_used_step BIGINT,
+    enabled_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+ALTER TABLE packages ADD COLUMN require_two_factor BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,15 @@
+-- Passkeys and security keys registered with WebAuthn
+CREATE TABLE webauthn_credentials (
+    id SERIAL PRIMARY KEY,
+    account_id INTEGER NOT NULL REFERENCES accounts (id) ON DELETE CASCADE,
+    -- base64url encoded id chosen by the authenticator
+    credential_id VARCHAR NOT NULL UNIQUE,
+    -- COSE encoded public key
+    public_key BYTEA NOT NULL,
+    sign_count BIGINT NOT NULL DEFAULT 0,
+    name VARCHAR NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    last_used_at TIMESTAMP WITH TIME ZONE
+);
+
+CREATE INDEX webauthn_credentials_account_id_idx ON webauthn_credentials (account_id);
@@ -0,0 +1,3 @@
+ALTER TABLE accounts DROP COLUMN locked_until;
+
+DROP TABLE auth_attempts;
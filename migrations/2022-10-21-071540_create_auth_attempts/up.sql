@@ -0,0 +1,15 @@
+-- Failed sign-ins, password reset and registration requests, kept for a day to throttle them
+CREATE TABLE auth_attempts (
+    id SERIAL PRIMARY KEY,
+    kind INTEGER NOT NULL,
+    -- lowercased, not necessarily the email of an account
+    email VARCHAR,
+    ip_address VARCHAR,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX auth_attempts_kind_email_idx ON auth_attempts (kind, email, created_at);
+CREATE INDEX auth_attempts_kind_ip_address_idx ON auth_attempts (kind, ip_address, created_at);
+CREATE INDEX auth_attempts_created_at_idx ON auth_attempts (created_at);
+
+ALTER TABLE accounts ADD COLUMN locked_until TIMESTAMP WITH TIME ZONE;
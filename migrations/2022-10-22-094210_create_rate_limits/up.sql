@@ -0,0 +1,8 @@
+-- Request counters of jelly::guards::RateLimit with the Postgres store
+CREATE TABLE rate_limits (
+    key VARCHAR PRIMARY KEY,
+    count INTEGER NOT NULL,
+    reset_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX rate_limits_reset_at_idx ON rate_limits (reset_at);
@@ -0,0 +1,14 @@
+-- Downloads of package versions, one per client and version within the deduplication window.
+-- They are added to the download counters in batches, then kept until the window ends.
+CREATE TABLE package_downloads (
+    id SERIAL PRIMARY KEY,
+    package_version_id INTEGER NOT NULL REFERENCES package_versions (id) ON DELETE CASCADE,
+    -- hash of the IP address of the client
+    fingerprint VARCHAR NOT NULL,
+    counted BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX package_downloads_version_fingerprint_idx ON package_downloads (package_version_id, fingerprint, created_at);
+CREATE INDEX package_downloads_not_counted_idx ON package_downloads (package_version_id) WHERE NOT counted;
+CREATE INDEX package_downloads_created_at_idx ON package_downloads (created_at);
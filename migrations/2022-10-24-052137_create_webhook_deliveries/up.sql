@@ -0,0 +1,8 @@
+-- Ids of the webhook deliveries already received, kept for a week so that redeliveries
+-- don't publish a tag twice
+CREATE TABLE webhook_deliveries (
+    delivery_id VARCHAR PRIMARY KEY,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX webhook_deliveries_created_at_idx ON webhook_deliveries (created_at);
@@ -0,0 +1,9 @@
+-- Shadow packages queued for the CreateShadowPackage job, so that downloads of the same
+-- unknown version queue a single job. The job removes its request when it's done.
+CREATE TABLE shadow_package_requests (
+    url VARCHAR NOT NULL,
+    rev VARCHAR NOT NULL,
+    subdir VARCHAR NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (url, rev, subdir)
+);
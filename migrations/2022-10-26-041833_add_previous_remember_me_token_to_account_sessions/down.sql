@@ -0,0 +1,2 @@
+ALTER TABLE account_sessions DROP COLUMN rotated_at;
+ALTER TABLE account_sessions DROP COLUMN previous_remember_me_token;
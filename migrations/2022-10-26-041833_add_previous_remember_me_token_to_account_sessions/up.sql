@@ -0,0 +1,7 @@
+-- The remember-me token a session had before its last rotation. Concurrent requests of the
+-- same browser may still send it for a moment, and must not sign the browser out.
+ALTER TABLE account_sessions ADD COLUMN previous_remember_me_token VARCHAR;
+ALTER TABLE account_sessions ADD COLUMN rotated_at TIMESTAMPTZ;
+
+CREATE INDEX account_sessions_previous_remember_me_token_idx
+    ON account_sessions (previous_remember_me_token);
@@ -0,0 +1,13 @@
+-- Security events of an account, shown in its security log: sign-ins, wrong second factors,
+-- revoked sessions and password changes. Unlike auth attempts, they aren't cleared once the
+-- account signs in.
+CREATE TABLE account_events (
+    id SERIAL PRIMARY KEY,
+    account_id INTEGER NOT NULL REFERENCES accounts (id) ON DELETE CASCADE,
+    kind INTEGER NOT NULL,
+    user_agent VARCHAR NOT NULL DEFAULT '',
+    ip_address VARCHAR,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX account_events_account_id_created_at_idx ON account_events (account_id, created_at);
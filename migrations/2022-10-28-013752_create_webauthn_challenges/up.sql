@@ -0,0 +1,9 @@
+-- Challenges of WebAuthn ceremonies waiting for the browser to sign them. The cookie session
+-- only names the challenge, and answering deletes it, so that replaying an old cookie can't
+-- answer it again.
+CREATE TABLE webauthn_challenges (
+    challenge VARCHAR PRIMARY KEY,
+    -- the ceremony it was created for, e.g. webauthn_sign_in
+    ceremony VARCHAR NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL
+);
@@ -0,0 +1 @@
+ALTER TABLE package_events DROP COLUMN system_actor;
@@ -0,0 +1,3 @@
+-- Set instead of actor_id when Movey acted on its own, e.g. when it published a tag pushed
+-- to the repository of the package
+ALTER TABLE package_events ADD COLUMN system_actor VARCHAR;
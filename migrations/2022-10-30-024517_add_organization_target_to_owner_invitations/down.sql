@@ -0,0 +1,23 @@
+CREATE TABLE organization_invitations (
+    invited_user_id INTEGER NOT NULL REFERENCES accounts (id) ON DELETE CASCADE,
+    invited_by_user_id INTEGER NOT NULL REFERENCES accounts (id) ON DELETE CASCADE,
+    organization_id INTEGER NOT NULL REFERENCES organizations (id) ON DELETE CASCADE,
+    token TEXT NOT NULL,
+    role INTEGER NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+
+    PRIMARY KEY (invited_user_id, organization_id)
+);
+INSERT INTO organization_invitations (invited_user_id, invited_by_user_id, organization_id, token, role, created_at)
+SELECT invited_user_id, invited_by_user_id, organization_id, token, organization_role, created_at
+FROM owner_invitations WHERE organization_id IS NOT NULL;
+DELETE FROM owner_invitations WHERE organization_id IS NOT NULL;
+
+DROP INDEX owner_invitations_organization_idx;
+DROP INDEX owner_invitations_package_idx;
+ALTER TABLE owner_invitations DROP CONSTRAINT owner_invitations_target_check;
+ALTER TABLE owner_invitations DROP COLUMN organization_role;
+ALTER TABLE owner_invitations DROP COLUMN organization_id;
+ALTER TABLE owner_invitations ALTER COLUMN package_id SET NOT NULL;
+ALTER TABLE owner_invitations DROP COLUMN id;
+ALTER TABLE owner_invitations ADD PRIMARY KEY (invited_user_id, package_id);
@@ -0,0 +1,18 @@
+-- Invitations to join an organization are owner invitations whose target is the organization
+-- instead of a package, with the role the invited user gets once they accept
+ALTER TABLE owner_invitations DROP CONSTRAINT owner_invitations_pkey;
+ALTER TABLE owner_invitations ADD COLUMN id SERIAL PRIMARY KEY;
+ALTER TABLE owner_invitations ALTER COLUMN package_id DROP NOT NULL;
+ALTER TABLE owner_invitations ADD COLUMN organization_id INTEGER REFERENCES organizations (id) ON DELETE CASCADE;
+-- 0 for admins, 1 for members
+ALTER TABLE owner_invitations ADD COLUMN organization_role INTEGER;
+ALTER TABLE owner_invitations ADD CONSTRAINT owner_invitations_target_check CHECK (
+    (package_id IS NULL) <> (organization_id IS NULL)
+    AND (organization_id IS NULL) = (organization_role IS NULL)
+);
+CREATE UNIQUE INDEX owner_invitations_package_idx ON owner_invitations (invited_user_id, package_id);
+CREATE UNIQUE INDEX owner_invitations_organization_idx ON owner_invitations (invited_user_id, organization_id);
+
+INSERT INTO owner_invitations (invited_user_id, invited_by_user_id, organization_id, organization_role, token, created_at)
+SELECT invited_user_id, invited_by_user_id, organization_id, role, token, created_at FROM organization_invitations;
+DROP TABLE organization_invitations;
@@ -26,6 +26,18 @@ impl Validation for LoginForm {
     }
 }
 
+/// The second step of signing in, with a code from an authenticator app or a recovery code.
+#[derive(Default, Debug, Deserialize, Serialize)]
+pub struct TwoFactorForm {
+    pub code: String,
+}
+
+impl Validation for TwoFactorForm {
+    fn is_valid(&mut self) -> bool {
+        !self.code.trim().is_empty()
+    }
+}
+
 #[derive(Debug, Default, Deserialize, Serialize)]
 pub struct NewAccountForm {
     pub email: EmailField,
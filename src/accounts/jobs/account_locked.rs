@@ -0,0 +1,59 @@
+use std::env::var;
+use std::future::Future;
+use std::pin::Pin;
+
+use jelly::anyhow::{anyhow, Error};
+use jelly::email::Email;
+use jelly::jobs::{Job, JobState};
+use jelly::serde::{Deserialize, Serialize};
+use jelly::tera::Context;
+
+use crate::accounts::models::auth_attempt::LOCKOUT_MINUTES;
+use crate::accounts::Account;
+
+/// An email that gets sent when too many failed sign-ins lock an account,
+/// in case someone else is guessing its password.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SendAccountLockedEmail {
+    pub to: String,
+}
+
+pub fn build_context(name: &str) -> Context {
+    let mut context = Context::new();
+    context.insert("name", name);
+    context.insert("lockout_minutes", &LOCKOUT_MINUTES);
+    context.insert(
+        "action_url",
+        &format!(
+            "{}/accounts/reset/",
+            var("JELLY_DOMAIN").expect("JELLY_DOMAIN not set?")
+        ),
+    );
+    context
+}
+
+impl Job for SendAccountLockedEmail {
+    type State = JobState;
+    type Future = Pin<Box<dyn Future<Output = Result<(), Error>> + Send>>;
+
+    const NAME: &'static str = "SendAccountLockedEmailJob";
+
+    fn run(self, state: JobState) -> Self::Future {
+        Box::pin(async move {
+            let name = Account::fetch_name_from_email(&self.to, &state.pool)
+                .map_err(|e| anyhow!("Error fetching user name for account lockout: {:?}", e))?;
+
+            let email = Email::new(
+                "email/account-locked",
+                &[self.to],
+                "Your Movey account has been locked",
+                build_context(&name),
+                state.templates,
+            );
+
+            email?.send()?;
+
+            Ok(())
+        })
+    }
+}
@@ -12,9 +12,14 @@ mod odd_registration_attempt;
 pub use odd_registration_attempt::build_context as build_odd_registration_attempt_context;
 pub use odd_registration_attempt::SendAccountOddRegisterAttemptEmail;
 
+mod account_locked;
+pub use account_locked::build_context as build_account_locked_context;
+pub use account_locked::SendAccountLockedEmail;
+
 pub fn configure(config: WorkerConfig<JobState>) -> WorkerConfig<JobState> {
     let mut config = config.register::<SendResetPasswordEmail>();
     config = config.register::<SendPasswordWasResetEmail>();
     config = config.register::<SendAccountOddRegisterAttemptEmail>();
+    config = config.register::<SendAccountLockedEmail>();
     config.register::<SendVerifyAccountEmail>()
 }
@@ -11,6 +11,7 @@ pub mod models;
 #[cfg(test)]
 pub mod tests;
 pub mod views;
+pub mod webauthn;
 
 pub use models::Account;
 
@@ -67,6 +68,25 @@ pub fn configure(config: &mut ServiceConfig) {
                     .route(get().to(views::login::form))
                     .route(post().to(views::login::authenticate)),
             )
+            .service(
+                resource("/login/two-factor")
+                    .route(get().to(views::two_factor::form))
+                    .route(post().to(views::two_factor::verify)),
+            )
+            .service(
+                resource("/login/two-factor/passkey/options")
+                    .route(post().to(views::passkey::two_factor_options)),
+            )
+            .service(
+                resource("/login/two-factor/passkey")
+                    .route(post().to(views::passkey::two_factor_authenticate)),
+            )
+            .service(
+                resource("/login/passkey/options").route(post().to(views::passkey::options)),
+            )
+            .service(
+                resource("/login/passkey").route(post().to(views::passkey::authenticate)),
+            )
             .service(
                 resource("/verify/{uidb64}-{ts}-{token}")
                     .route(get().to(views::verify::with_token)),
@@ -0,0 +1,146 @@
+use std::cmp::Reverse;
+
+use diesel::prelude::*;
+use diesel::{Identifiable, Queryable};
+use jelly::chrono::{DateTime, Duration, Utc};
+use jelly::serde::Serialize;
+use jelly::DieselPgConnection;
+use jelly::Result;
+
+use crate::accounts::models::account_session::SessionDevice;
+use crate::accounts::models::auth_attempt::{AuthAttempt, AuthAttemptKind};
+use crate::accounts::Account;
+use crate::schema::account_events;
+
+#[cfg(test)]
+mod tests;
+
+/// Events older than this are pruned.
+pub const KEEP_DAYS: i64 = 90;
+/// Entries shown in the security log of an account.
+pub const SECURITY_LOG_SIZE: usize = 50;
+
+/// What happened to the security of an account. Kinds are stored as integers, so new ones go
+/// at the end.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccountEventKind {
+    SignedIn = 0,
+    FailedSecondFactor = 1,
+    SessionRevoked = 2,
+    AllSessionsRevoked = 3,
+    PasswordChanged = 4,
+    PasswordReset = 5,
+}
+
+impl AccountEventKind {
+    const ALL: [AccountEventKind; 6] = [
+        AccountEventKind::SignedIn,
+        AccountEventKind::FailedSecondFactor,
+        AccountEventKind::SessionRevoked,
+        AccountEventKind::AllSessionsRevoked,
+        AccountEventKind::PasswordChanged,
+        AccountEventKind::PasswordReset,
+    ];
+
+    pub fn from_i32(kind: i32) -> Option<Self> {
+        Self::ALL.iter().copied().find(|k| *k as i32 == kind)
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            AccountEventKind::SignedIn => "Signed in",
+            AccountEventKind::FailedSecondFactor => "Wrong two-factor code or passkey",
+            AccountEventKind::SessionRevoked => "Revoked a session",
+            AccountEventKind::AllSessionsRevoked => "Signed out everywhere",
+            AccountEventKind::PasswordChanged => "Changed the password",
+            AccountEventKind::PasswordReset => "Reset the password",
+        }
+    }
+}
+
+/// An event of the security log of an account.
+#[derive(Debug, Serialize, Queryable, Identifiable)]
+#[table_name = "account_events"]
+pub struct AccountEvent {
+    pub id: i32,
+    pub account_id: i32,
+    pub kind: i32,
+    pub user_agent: String,
+    pub ip_address: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// An entry of the security log on the profile page, from an `AccountEvent` or from a
+/// recent `AuthAttempt` for the email of the account.
+#[derive(Debug, Serialize)]
+pub struct SecurityLogEntry {
+    pub description: &'static str,
+    pub user_agent: String,
+    pub ip_address: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl AccountEvent {
+    pub fn record(
+        account_id_: i32,
+        kind_: AccountEventKind,
+        device: &SessionDevice,
+        conn: &DieselPgConnection,
+    ) -> Result<()> {
+        diesel::delete(
+            account_events::table
+                .filter(account_events::created_at.lt(Utc::now() - Duration::days(KEEP_DAYS))),
+        )
+        .execute(conn)?;
+        diesel::insert_into(account_events::table)
+            .values((
+                account_events::account_id.eq(account_id_),
+                account_events::kind.eq(kind_ as i32),
+                account_events::user_agent.eq(&device.user_agent),
+                account_events::ip_address.eq(&device.ip_address),
+            ))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    /// The latest events of the account, together with the failed sign-ins and password
+    /// reset requests for its email that are still on record, the most recent first.
+    pub fn security_log(
+        account: &Account,
+        conn: &DieselPgConnection,
+    ) -> Result<Vec<SecurityLogEntry>> {
+        let events = account_events::table
+            .filter(account_events::account_id.eq(account.id))
+            .order((account_events::created_at.desc(), account_events::id.desc()))
+            .limit(SECURITY_LOG_SIZE as i64)
+            .load::<Self>(conn)?;
+        let mut entries = events
+            .into_iter()
+            .filter_map(|event| {
+                Some(SecurityLogEntry {
+                    description: AccountEventKind::from_i32(event.kind)?.description(),
+                    user_agent: event.user_agent,
+                    ip_address: event.ip_address,
+                    created_at: event.created_at,
+                })
+            })
+            .collect::<Vec<_>>();
+        // Wrong second factors are events too, as succeeding clears their attempts
+        for (kind, description) in [
+            (AuthAttemptKind::FailedLogin, "Wrong password"),
+            (AuthAttemptKind::PasswordReset, "Requested a password reset"),
+        ] {
+            for (ip_address, created_at) in AuthAttempt::get_by_email(kind, &account.email, conn)? {
+                entries.push(SecurityLogEntry {
+                    description,
+                    user_agent: String::new(),
+                    ip_address,
+                    created_at,
+                });
+            }
+        }
+        entries.sort_by_key(|entry| Reverse(entry.created_at));
+        entries.truncate(SECURITY_LOG_SIZE);
+        Ok(entries)
+    }
+}
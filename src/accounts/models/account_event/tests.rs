@@ -0,0 +1,64 @@
+use crate::accounts::models::account_event::*;
+use crate::accounts::models::auth_attempt::{AuthAttempt, AuthAttemptKind};
+use crate::schema::account_events;
+use crate::test::util::setup_user;
+use crate::test::{DatabaseTestContext, DB_POOL};
+use jelly::chrono::{Duration, Utc};
+
+fn device() -> SessionDevice {
+    SessionDevice {
+        user_agent: "Mozilla/5.0".to_string(),
+        ip_address: Some("127.0.0.1".to_string()),
+    }
+}
+
+#[actix_rt::test]
+async fn security_log_shows_events_and_failed_sign_ins_most_recent_first() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let uid = setup_user(None, None);
+    let other_uid = setup_user(Some("other@host.com".to_string()), None);
+    let account = Account::get(uid, &DB_POOL).unwrap();
+    let conn = DB_POOL.get().unwrap();
+
+    AccountEvent::record(uid, AccountEventKind::SignedIn, &device(), &conn).unwrap();
+    AccountEvent::record(other_uid, AccountEventKind::SignedIn, &device(), &conn).unwrap();
+    AuthAttempt::record(
+        AuthAttemptKind::FailedLogin,
+        &account.email.to_uppercase(),
+        Some("10.0.0.1"),
+        &conn,
+    )
+    .unwrap();
+    AccountEvent::record(uid, AccountEventKind::PasswordChanged, &device(), &conn).unwrap();
+
+    let log = AccountEvent::security_log(&account, &conn).unwrap();
+    let descriptions = log.iter().map(|e| e.description).collect::<Vec<_>>();
+    assert_eq!(
+        descriptions,
+        vec!["Changed the password", "Wrong password", "Signed in"]
+    );
+    assert_eq!(log[0].user_agent, "Mozilla/5.0");
+    assert_eq!(log[1].ip_address, Some("10.0.0.1".to_string()));
+}
+
+#[actix_rt::test]
+async fn record_prunes_old_events() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let uid = setup_user(None, None);
+    let account = Account::get(uid, &DB_POOL).unwrap();
+    let conn = DB_POOL.get().unwrap();
+    AccountEvent::record(uid, AccountEventKind::SignedIn, &device(), &conn).unwrap();
+    diesel::update(account_events::table)
+        .set(account_events::created_at.eq(Utc::now() - Duration::days(KEEP_DAYS + 1)))
+        .execute(&conn)
+        .unwrap();
+
+    AccountEvent::record(uid, AccountEventKind::SessionRevoked, &device(), &conn).unwrap();
+    let log = AccountEvent::security_log(&account, &conn).unwrap();
+    assert_eq!(log.len(), 1);
+    assert_eq!(log[0].description, "Revoked a session");
+}
@@ -0,0 +1,251 @@
+use diesel::prelude::*;
+use diesel::{Associations, Identifiable, Queryable};
+use jelly::chrono::{DateTime, Duration, Utc};
+use jelly::serde::Serialize;
+use jelly::DieselPgConnection;
+use jelly::Result;
+
+use crate::accounts::Account;
+use crate::schema::account_sessions;
+use crate::schema::accounts;
+use crate::utils::token::SecureToken;
+
+#[cfg(test)]
+mod tests;
+
+/// Where a session was started from.
+#[derive(Clone, Debug, Default)]
+pub struct SessionDevice {
+    pub user_agent: String,
+    pub ip_address: Option<String>,
+}
+
+/// A browser signed in to an account. Deleting the row signs that browser out.
+#[derive(Debug, Serialize, Queryable, Identifiable, Associations)]
+#[belongs_to(Account)]
+pub struct AccountSession {
+    pub id: i32,
+    pub account_id: i32,
+    #[serde(skip)]
+    pub remember_me_token: Option<String>,
+    #[serde(skip)]
+    pub password_fingerprint: String,
+    pub user_agent: String,
+    pub ip_address: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_seen_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    #[serde(skip)]
+    pub previous_remember_me_token: Option<String>,
+    #[serde(skip)]
+    pub rotated_at: Option<DateTime<Utc>>,
+}
+
+pub struct CreatedAccountSession {
+    pub model: AccountSession,
+    /// Plaintext remember-me token for the cookie, only set when the browser asked to be
+    /// remembered. Only its hash is stored.
+    pub remember_me_token: Option<String>,
+}
+
+/// The result of `AccountSession::renew`.
+// Returned once per renewal and matched right away, so boxing would only add an allocation
+#[allow(clippy::large_enum_variant)]
+pub enum Renewal {
+    /// Signed in again, with the new remember-me token.
+    Renewed(Account, CreatedAccountSession),
+    /// The token was exchanged moments ago by a concurrent request of the same browser,
+    /// whose response carries the new one.
+    Rotated,
+    /// The token doesn't belong to a live session.
+    Rejected,
+}
+
+/// How long a remember-me token is recognized after it was exchanged for a new one.
+pub const ROTATION_GRACE_SECONDS: i64 = 60;
+
+/// How long sessions and remember-me tokens last.
+pub fn max_remember_me_days() -> i64 {
+    std::env::var("MAX_REMEMBER_ME_DAYS")
+        .expect("MAX_REMEMBER_ME_DAYS not set!")
+        .parse::<i64>()
+        .expect("MAX_REMEMBER_ME_DAYS must be an integer")
+}
+
+// Sessions store this instead of the password hash itself, so that they stop working
+// as soon as the password changes.
+fn password_fingerprint(password_hash: &str) -> String {
+    SecureToken::hash(password_hash)
+}
+
+impl AccountSession {
+    pub fn create(
+        account: &Account,
+        device: &SessionDevice,
+        remember_me: bool,
+        conn: &DieselPgConnection,
+    ) -> Result<CreatedAccountSession> {
+        let token = if remember_me {
+            Some(SecureToken::generate())
+        } else {
+            None
+        };
+        let model = diesel::insert_into(account_sessions::table)
+            .values((
+                account_sessions::account_id.eq(account.id),
+                account_sessions::remember_me_token
+                    .eq(token.as_ref().map(|token| &token.inner.sha256)),
+                account_sessions::password_fingerprint.eq(password_fingerprint(&account.password)),
+                account_sessions::user_agent.eq(&device.user_agent),
+                account_sessions::ip_address.eq(&device.ip_address),
+                account_sessions::expires_at
+                    .eq(Utc::now() + Duration::days(max_remember_me_days())),
+            ))
+            .get_result::<Self>(conn)?;
+
+        Ok(CreatedAccountSession {
+            model,
+            remember_me_token: token.map(|token| token.plaintext),
+        })
+    }
+
+    /// Returns the session if it belongs to the account and hasn't expired or been
+    /// invalidated by a password change.
+    pub fn get_live(
+        session_id: i32,
+        account_id_: i32,
+        conn: &DieselPgConnection,
+    ) -> Result<Option<Self>> {
+        let session = account_sessions::table
+            .inner_join(accounts::table)
+            .filter(account_sessions::id.eq(session_id))
+            .filter(account_sessions::account_id.eq(account_id_))
+            .filter(account_sessions::expires_at.gt(Utc::now()))
+            .select((account_sessions::all_columns, accounts::password))
+            .first::<(Self, String)>(conn)
+            .optional()?;
+
+        Ok(session
+            .filter(|(session, password)| {
+                session.password_fingerprint == password_fingerprint(password)
+            })
+            .map(|(session, _)| session))
+    }
+
+    /// Checks that the session is live and records when it was last seen.
+    pub fn touch(session_id: i32, account_id_: i32, conn: &DieselPgConnection) -> Result<bool> {
+        if Self::get_live(session_id, account_id_, conn)?.is_none() {
+            return Ok(false);
+        }
+        // Once a minute is precise enough for the sessions page
+        diesel::update(
+            account_sessions::table
+                .find(session_id)
+                .filter(account_sessions::last_seen_at.lt(Utc::now() - Duration::minutes(1))),
+        )
+        .set(account_sessions::last_seen_at.eq(Utc::now()))
+        .execute(conn)?;
+
+        Ok(true)
+    }
+
+    /// Exchanges a remember-me token for a new one of the same session, so that each token
+    /// can only be used once.
+    pub fn renew(plaintext_token: &str, conn: &DieselPgConnection) -> Result<Renewal> {
+        let hashed_token = SecureToken::hash(plaintext_token);
+        let (session, account) = match account_sessions::table
+            .inner_join(accounts::table)
+            .filter(account_sessions::remember_me_token.eq(&hashed_token))
+            .filter(account_sessions::expires_at.gt(Utc::now()))
+            .first::<(Self, Account)>(conn)
+            .optional()?
+        {
+            Some((session, account))
+                if session.password_fingerprint == password_fingerprint(&account.password) =>
+            {
+                (session, account)
+            }
+            Some(_) => return Ok(Renewal::Rejected),
+            None => return Self::check_rotated(&hashed_token, conn),
+        };
+
+        let token = SecureToken::generate();
+        // Filtering on the old token again makes concurrent renewals fail instead of both
+        // getting a new token.
+        let model = diesel::update(
+            account_sessions::table
+                .find(session.id)
+                .filter(account_sessions::remember_me_token.eq(&hashed_token)),
+        )
+        .set((
+            account_sessions::remember_me_token.eq(&token.inner.sha256),
+            account_sessions::previous_remember_me_token.eq(&hashed_token),
+            account_sessions::rotated_at.eq(Utc::now()),
+            account_sessions::last_seen_at.eq(Utc::now()),
+            account_sessions::expires_at.eq(Utc::now() + Duration::days(max_remember_me_days())),
+        ))
+        .get_result::<Self>(conn)
+        .optional()?;
+
+        match model {
+            Some(model) => Ok(Renewal::Renewed(
+                account,
+                CreatedAccountSession {
+                    model,
+                    remember_me_token: Some(token.plaintext),
+                },
+            )),
+            None => Self::check_rotated(&hashed_token, conn),
+        }
+    }
+
+    // Whether the token is the previous one of a live session, exchanged within the grace
+    // period
+    fn check_rotated(hashed_token: &str, conn: &DieselPgConnection) -> Result<Renewal> {
+        let rotated = account_sessions::table
+            .filter(account_sessions::previous_remember_me_token.eq(hashed_token))
+            .filter(
+                account_sessions::rotated_at
+                    .gt(Utc::now() - Duration::seconds(ROTATION_GRACE_SECONDS)),
+            )
+            .filter(account_sessions::expires_at.gt(Utc::now()))
+            .count()
+            .get_result::<i64>(conn)?;
+        Ok(if rotated > 0 {
+            Renewal::Rotated
+        } else {
+            Renewal::Rejected
+        })
+    }
+
+    /// Live sessions of the account, the most recently seen first.
+    pub fn get_by_account(account: &Account, conn: &DieselPgConnection) -> Result<Vec<Self>> {
+        Ok(account_sessions::table
+            .filter(account_sessions::account_id.eq(account.id))
+            .filter(account_sessions::expires_at.gt(Utc::now()))
+            .filter(
+                account_sessions::password_fingerprint.eq(password_fingerprint(&account.password)),
+            )
+            .order_by((
+                account_sessions::last_seen_at.desc(),
+                account_sessions::id.desc(),
+            ))
+            .load::<Self>(conn)?)
+    }
+
+    pub fn revoke(session_id: i32, account_id_: i32, conn: &DieselPgConnection) -> Result<usize> {
+        Ok(diesel::delete(
+            account_sessions::table
+                .filter(account_sessions::id.eq(session_id))
+                .filter(account_sessions::account_id.eq(account_id_)),
+        )
+        .execute(conn)?)
+    }
+
+    pub fn revoke_all(account_id_: i32, conn: &DieselPgConnection) -> Result<usize> {
+        Ok(diesel::delete(
+            account_sessions::table.filter(account_sessions::account_id.eq(account_id_)),
+        )
+        .execute(conn)?)
+    }
+}
@@ -0,0 +1,255 @@
+use crate::accounts::models::account_session::{
+    AccountSession, Renewal, SessionDevice, ROTATION_GRACE_SECONDS,
+};
+use crate::accounts::Account;
+use crate::schema::account_sessions;
+use crate::test::util::setup_user;
+use crate::test::{DatabaseTestContext, DB_POOL};
+use crate::utils::token::SecureToken;
+use diesel::prelude::*;
+use jelly::chrono::{Duration, Utc};
+
+fn device() -> SessionDevice {
+    SessionDevice {
+        user_agent: "Mozilla/5.0".to_string(),
+        ip_address: Some("127.0.0.1".to_string()),
+    }
+}
+
+#[actix_rt::test]
+async fn create_stores_only_the_hash_of_the_remember_me_token() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let uid = setup_user(None, None);
+    let account = Account::get(uid, &DB_POOL).unwrap();
+    let conn = DB_POOL.get().unwrap();
+
+    let created = AccountSession::create(&account, &device(), true, &conn).unwrap();
+    let token = created.remember_me_token.unwrap();
+    assert_eq!(created.model.account_id, uid);
+    assert_eq!(created.model.user_agent, "Mozilla/5.0");
+    assert_eq!(created.model.ip_address, Some("127.0.0.1".to_string()));
+    assert_eq!(
+        created.model.remember_me_token,
+        Some(SecureToken::hash(&token))
+    );
+
+    let created = AccountSession::create(&account, &device(), false, &conn).unwrap();
+    assert!(created.remember_me_token.is_none());
+    assert!(created.model.remember_me_token.is_none());
+}
+
+#[actix_rt::test]
+async fn renew_rotates_the_remember_me_token() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let uid = setup_user(None, None);
+    let account = Account::get(uid, &DB_POOL).unwrap();
+    let conn = DB_POOL.get().unwrap();
+    let created = AccountSession::create(&account, &device(), true, &conn).unwrap();
+    let token = created.remember_me_token.unwrap();
+
+    let (renewed_account, renewed) = match AccountSession::renew(&token, &conn).unwrap() {
+        Renewal::Renewed(account, renewed) => (account, renewed),
+        _ => panic!("the token wasn't renewed"),
+    };
+    assert_eq!(renewed_account.id, uid);
+    assert_eq!(renewed.model.id, created.model.id);
+    let new_token = renewed.remember_me_token.unwrap();
+    assert_ne!(new_token, token);
+
+    // the old token can't be used again
+    assert!(matches!(
+        AccountSession::renew(&token, &conn).unwrap(),
+        Renewal::Rotated
+    ));
+    assert!(matches!(
+        AccountSession::renew(&new_token, &conn).unwrap(),
+        Renewal::Renewed(..)
+    ));
+}
+
+#[actix_rt::test]
+async fn renew_recognizes_the_previous_token_only_for_a_moment() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let uid = setup_user(None, None);
+    let account = Account::get(uid, &DB_POOL).unwrap();
+    let conn = DB_POOL.get().unwrap();
+    let created = AccountSession::create(&account, &device(), true, &conn).unwrap();
+    let token = created.remember_me_token.unwrap();
+    assert!(matches!(
+        AccountSession::renew(&token, &conn).unwrap(),
+        Renewal::Renewed(..)
+    ));
+    assert!(matches!(
+        AccountSession::renew(&token, &conn).unwrap(),
+        Renewal::Rotated
+    ));
+
+    diesel::update(account_sessions::table.find(created.model.id))
+        .set(
+            account_sessions::rotated_at
+                .eq(Utc::now() - Duration::seconds(ROTATION_GRACE_SECONDS + 1)),
+        )
+        .execute(&conn)
+        .unwrap();
+    assert!(matches!(
+        AccountSession::renew(&token, &conn).unwrap(),
+        Renewal::Rejected
+    ));
+}
+
+#[actix_rt::test]
+async fn renew_rejects_forged_tokens() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let uid = setup_user(None, None);
+    let account = Account::get(uid, &DB_POOL).unwrap();
+    let conn = DB_POOL.get().unwrap();
+    let created = AccountSession::create(&account, &device(), true, &conn).unwrap();
+
+    assert!(matches!(
+        AccountSession::renew("forged-token", &conn).unwrap(),
+        Renewal::Rejected
+    ));
+    // the old cookie format, a signature followed by the user id
+    let old_format = format!("{}={}", "a".repeat(43), uid);
+    assert!(matches!(
+        AccountSession::renew(&old_format, &conn).unwrap(),
+        Renewal::Rejected
+    ));
+    // the stored hash isn't a token either
+    let hashed_token = created.model.remember_me_token.unwrap();
+    assert!(matches!(
+        AccountSession::renew(&hashed_token, &conn).unwrap(),
+        Renewal::Rejected
+    ));
+}
+
+#[actix_rt::test]
+async fn changing_the_password_invalidates_sessions() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let uid = setup_user(None, None);
+    let account = Account::get(uid, &DB_POOL).unwrap();
+    let conn = DB_POOL.get().unwrap();
+    let created = AccountSession::create(&account, &device(), true, &conn).unwrap();
+    assert!(AccountSession::touch(created.model.id, uid, &conn).unwrap());
+
+    Account::change_password(
+        uid,
+        "So$trongpas0word!".to_string(),
+        "An0ther$trongpassword!".to_string(),
+        &DB_POOL,
+    )
+    .unwrap();
+    let account = Account::get(uid, &DB_POOL).unwrap();
+
+    assert!(!AccountSession::touch(created.model.id, uid, &conn).unwrap());
+    let token = created.remember_me_token.unwrap();
+    assert!(matches!(
+        AccountSession::renew(&token, &conn).unwrap(),
+        Renewal::Rejected
+    ));
+    assert!(AccountSession::get_by_account(&account, &conn)
+        .unwrap()
+        .is_empty());
+}
+
+#[actix_rt::test]
+async fn expired_sessions_are_not_live() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let uid = setup_user(None, None);
+    let account = Account::get(uid, &DB_POOL).unwrap();
+    let conn = DB_POOL.get().unwrap();
+    let created = AccountSession::create(&account, &device(), true, &conn).unwrap();
+    diesel::update(account_sessions::table.find(created.model.id))
+        .set(account_sessions::expires_at.eq(Utc::now() - Duration::minutes(1)))
+        .execute(&conn)
+        .unwrap();
+
+    assert!(!AccountSession::touch(created.model.id, uid, &conn).unwrap());
+    let token = created.remember_me_token.unwrap();
+    assert!(matches!(
+        AccountSession::renew(&token, &conn).unwrap(),
+        Renewal::Rejected
+    ));
+    assert!(AccountSession::get_by_account(&account, &conn)
+        .unwrap()
+        .is_empty());
+}
+
+#[actix_rt::test]
+async fn touch_records_last_seen_at() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let uid = setup_user(None, None);
+    let other_uid = setup_user(Some("other@host.com".to_string()), None);
+    let account = Account::get(uid, &DB_POOL).unwrap();
+    let conn = DB_POOL.get().unwrap();
+    let created = AccountSession::create(&account, &device(), false, &conn).unwrap();
+    let an_hour_ago = Utc::now() - Duration::hours(1);
+    diesel::update(account_sessions::table.find(created.model.id))
+        .set(account_sessions::last_seen_at.eq(an_hour_ago))
+        .execute(&conn)
+        .unwrap();
+
+    assert!(!AccountSession::touch(created.model.id, other_uid, &conn).unwrap());
+    assert!(AccountSession::touch(created.model.id, uid, &conn).unwrap());
+    let session = AccountSession::get_live(created.model.id, uid, &conn)
+        .unwrap()
+        .unwrap();
+    assert!(session.last_seen_at > an_hour_ago);
+}
+
+#[actix_rt::test]
+async fn revoke_works() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let uid = setup_user(None, None);
+    let other_uid = setup_user(Some("other@host.com".to_string()), None);
+    let account = Account::get(uid, &DB_POOL).unwrap();
+    let conn = DB_POOL.get().unwrap();
+    let session1 = AccountSession::create(&account, &device(), true, &conn).unwrap();
+    let session2 = AccountSession::create(&account, &device(), false, &conn).unwrap();
+    assert_eq!(
+        AccountSession::get_by_account(&account, &conn)
+            .unwrap()
+            .len(),
+        2
+    );
+
+    // sessions of other accounts can't be revoked
+    assert_eq!(
+        AccountSession::revoke(session1.model.id, other_uid, &conn).unwrap(),
+        0
+    );
+    assert_eq!(
+        AccountSession::revoke(session1.model.id, uid, &conn).unwrap(),
+        1
+    );
+    let token = session1.remember_me_token.unwrap();
+    assert!(matches!(
+        AccountSession::renew(&token, &conn).unwrap(),
+        Renewal::Rejected
+    ));
+    let sessions = AccountSession::get_by_account(&account, &conn).unwrap();
+    assert_eq!(sessions.len(), 1);
+    assert_eq!(sessions[0].id, session2.model.id);
+
+    AccountSession::create(&account, &device(), false, &conn).unwrap();
+    assert_eq!(AccountSession::revoke_all(uid, &conn).unwrap(), 2);
+    assert!(AccountSession::get_by_account(&account, &conn)
+        .unwrap()
+        .is_empty());
+}
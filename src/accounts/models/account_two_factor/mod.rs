@@ -0,0 +1,204 @@
+use diesel::dsl::{exists, select};
+use diesel::prelude::*;
+use diesel::{Associations, Identifiable, Queryable};
+use jelly::chrono::{DateTime, Utc};
+use jelly::error::Error;
+use jelly::DieselPgConnection;
+use jelly::Result;
+use qrcode::render::svg;
+use qrcode::QrCode;
+
+use crate::accounts::Account;
+use crate::schema::account_two_factors;
+use crate::sql::array_remove;
+use crate::utils::token::{generate_secure_alphanumeric_string, SecureToken};
+use crate::utils::totp;
+
+#[cfg(test)]
+mod tests;
+
+pub const RECOVERY_CODES_COUNT: usize = 10;
+const TOTP_ISSUER: &str = "Movey";
+
+/// The TOTP secret of an account. Sign-ins need a code from it once it's enabled.
+#[derive(Debug, Queryable, Identifiable, Associations)]
+#[primary_key(account_id)]
+#[belongs_to(Account)]
+pub struct AccountTwoFactor {
+    pub account_id: i32,
+    pub secret: String,
+    pub recovery_codes: Vec<String>,
+    pub last_used_step: Option<i64>,
+    pub enabled_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+// Recovery codes look like `abcde-12345`, and are accepted with any case and separators.
+fn normalize_recovery_code(code: &str) -> String {
+    code.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .collect::<String>()
+        .to_lowercase()
+}
+
+fn generate_recovery_codes() -> Vec<String> {
+    (0..RECOVERY_CODES_COUNT)
+        .map(|_| {
+            let code = generate_secure_alphanumeric_string(10).to_lowercase();
+            format!("{}-{}", &code[..5], &code[5..])
+        })
+        .collect()
+}
+
+fn hash_recovery_codes(codes: &[String]) -> Vec<String> {
+    codes
+        .iter()
+        .map(|code| SecureToken::hash(&normalize_recovery_code(code)))
+        .collect()
+}
+
+fn is_totp_code(code: &str) -> bool {
+    let digits: Vec<char> = code.chars().filter(|c| !c.is_whitespace()).collect();
+    digits.len() == totp::DIGITS as usize && digits.iter().all(char::is_ascii_digit)
+}
+
+impl AccountTwoFactor {
+    pub fn get(account_id_: i32, conn: &DieselPgConnection) -> Result<Option<Self>> {
+        Ok(account_two_factors::table
+            .find(account_id_)
+            .first::<Self>(conn)
+            .optional()?)
+    }
+
+    pub fn is_enabled(account_id_: i32, conn: &DieselPgConnection) -> Result<bool> {
+        Ok(select(exists(
+            account_two_factors::table
+                .find(account_id_)
+                .filter(account_two_factors::enabled_at.is_not_null()),
+        ))
+        .get_result(conn)?)
+    }
+
+    /// Creates a new secret for the account, replacing any that was never confirmed.
+    pub fn start_enrollment(account_id_: i32, conn: &DieselPgConnection) -> Result<Self> {
+        conn.transaction(|| {
+            if Self::is_enabled(account_id_, conn)? {
+                return Err(Error::Generic(String::from(
+                    "Two-factor authentication is already enabled.",
+                )));
+            }
+            diesel::delete(account_two_factors::table.find(account_id_)).execute(conn)?;
+            Ok(diesel::insert_into(account_two_factors::table)
+                .values((
+                    account_two_factors::account_id.eq(account_id_),
+                    account_two_factors::secret.eq(totp::generate_secret()),
+                ))
+                .get_result::<Self>(conn)?)
+        })
+    }
+
+    /// Enables two-factor authentication once the authenticator app shows the right code.
+    /// Returns the plaintext recovery codes, or `None` if the code is wrong. Only their
+    /// hashes are stored.
+    pub fn confirm_enrollment(
+        account_id_: i32,
+        code: &str,
+        conn: &DieselPgConnection,
+    ) -> Result<Option<Vec<String>>> {
+        let two_factor = match Self::get(account_id_, conn)? {
+            Some(two_factor) if two_factor.enabled_at.is_none() => two_factor,
+            _ => return Ok(None),
+        };
+        let step = match totp::verify(&two_factor.secret, code, Utc::now().timestamp() as u64) {
+            Some(step) => step,
+            None => return Ok(None),
+        };
+
+        let recovery_codes = generate_recovery_codes();
+        diesel::update(account_two_factors::table.find(account_id_))
+            .set((
+                account_two_factors::recovery_codes.eq(hash_recovery_codes(&recovery_codes)),
+                account_two_factors::last_used_step.eq(step as i64),
+                account_two_factors::enabled_at.eq(Utc::now()),
+            ))
+            .execute(conn)?;
+
+        Ok(Some(recovery_codes))
+    }
+
+    /// Checks a code from the authenticator app or one of the recovery codes. Accepted
+    /// codes can't be used again.
+    pub fn verify(account_id_: i32, code: &str, conn: &DieselPgConnection) -> Result<bool> {
+        let two_factor = match Self::get(account_id_, conn)? {
+            Some(two_factor) if two_factor.enabled_at.is_some() => two_factor,
+            _ => return Ok(false),
+        };
+
+        if is_totp_code(code) {
+            let step = match totp::verify(&two_factor.secret, code, Utc::now().timestamp() as u64) {
+                Some(step) => step as i64,
+                None => return Ok(false),
+            };
+            // Filtering on the last step makes a code seen twice, even concurrently, fail
+            // the second time.
+            let updated = diesel::update(
+                account_two_factors::table.find(account_id_).filter(
+                    account_two_factors::last_used_step
+                        .is_null()
+                        .or(account_two_factors::last_used_step.lt(step)),
+                ),
+            )
+            .set(account_two_factors::last_used_step.eq(step))
+            .execute(conn)?;
+            return Ok(updated == 1);
+        }
+
+        let hashed_code = SecureToken::hash(&normalize_recovery_code(code));
+        let updated = diesel::update(
+            account_two_factors::table
+                .find(account_id_)
+                .filter(account_two_factors::recovery_codes.contains(vec![&hashed_code])),
+        )
+        .set(account_two_factors::recovery_codes.eq(array_remove(
+            account_two_factors::recovery_codes,
+            &hashed_code,
+        )))
+        .execute(conn)?;
+        Ok(updated == 1)
+    }
+
+    /// Replaces the recovery codes of an enabled account, returning the new ones.
+    pub fn regenerate_recovery_codes(
+        account_id_: i32,
+        conn: &DieselPgConnection,
+    ) -> Result<Vec<String>> {
+        let recovery_codes = generate_recovery_codes();
+        let updated = diesel::update(
+            account_two_factors::table
+                .find(account_id_)
+                .filter(account_two_factors::enabled_at.is_not_null()),
+        )
+        .set(account_two_factors::recovery_codes.eq(hash_recovery_codes(&recovery_codes)))
+        .execute(conn)?;
+        if updated == 0 {
+            return Err(diesel::NotFound.into());
+        }
+
+        Ok(recovery_codes)
+    }
+
+    pub fn disable(account_id_: i32, conn: &DieselPgConnection) -> Result<usize> {
+        Ok(diesel::delete(account_two_factors::table.find(account_id_)).execute(conn)?)
+    }
+
+    /// The URI authenticator apps read from the QR code.
+    pub fn otpauth_uri(&self, account_email: &str) -> String {
+        totp::otpauth_uri(TOTP_ISSUER, account_email, &self.secret)
+    }
+
+    pub fn qr_code_svg(&self, account_email: &str) -> Result<String> {
+        let code = QrCode::new(self.otpauth_uri(account_email).as_bytes())
+            .map_err(|e| Error::Generic(format!("Could not create the QR code: {}", e)))?;
+        Ok(code.render::<svg::Color>().min_dimensions(200, 200).build())
+    }
+}
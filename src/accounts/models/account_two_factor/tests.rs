@@ -0,0 +1,162 @@
+use crate::accounts::models::account_two_factor::{AccountTwoFactor, RECOVERY_CODES_COUNT};
+use crate::schema::account_two_factors;
+use crate::test::util::setup_user;
+use crate::test::{DatabaseTestContext, DB_POOL};
+use crate::utils::totp;
+use diesel::prelude::*;
+use jelly::chrono::Utc;
+
+fn current_code(secret: &str) -> String {
+    totp::code_at(secret, totp::step_at(Utc::now().timestamp() as u64)).unwrap()
+}
+
+fn wrong_code(secret: &str) -> String {
+    let code = current_code(secret);
+    let step = totp::step_at(Utc::now().timestamp() as u64);
+    // any code that doesn't belong to one of the accepted steps
+    (0..1000000)
+        .map(|n| format!("{:06}", n))
+        .find(|candidate| {
+            *candidate != code
+                && (step - 1..=step + 1)
+                    .all(|step| totp::code_at(secret, step).unwrap() != *candidate)
+        })
+        .unwrap()
+}
+
+#[actix_rt::test]
+async fn enrollment_needs_a_confirmed_code() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let uid = setup_user(None, None);
+    let conn = DB_POOL.get().unwrap();
+
+    let two_factor = AccountTwoFactor::start_enrollment(uid, &conn).unwrap();
+    assert!(!AccountTwoFactor::is_enabled(uid, &conn).unwrap());
+    assert!(two_factor
+        .otpauth_uri("email@host.com")
+        .starts_with("otpauth://totp/Movey:email@host.com?secret="));
+    assert!(two_factor
+        .qr_code_svg("email@host.com")
+        .unwrap()
+        .contains("<svg"));
+    // codes don't sign in before the enrollment is confirmed
+    assert!(!AccountTwoFactor::verify(uid, &current_code(&two_factor.secret), &conn).unwrap());
+
+    let wrong_code = wrong_code(&two_factor.secret);
+    assert!(
+        AccountTwoFactor::confirm_enrollment(uid, &wrong_code, &conn)
+            .unwrap()
+            .is_none()
+    );
+    assert!(!AccountTwoFactor::is_enabled(uid, &conn).unwrap());
+
+    let recovery_codes =
+        AccountTwoFactor::confirm_enrollment(uid, &current_code(&two_factor.secret), &conn)
+            .unwrap()
+            .unwrap();
+    assert_eq!(recovery_codes.len(), RECOVERY_CODES_COUNT);
+    assert!(AccountTwoFactor::is_enabled(uid, &conn).unwrap());
+    let stored = AccountTwoFactor::get(uid, &conn).unwrap().unwrap();
+    assert!(!stored.recovery_codes.contains(&recovery_codes[0]));
+
+    // an enabled secret can't be replaced without disabling it first
+    assert!(AccountTwoFactor::start_enrollment(uid, &conn).is_err());
+}
+
+#[actix_rt::test]
+async fn start_enrollment_replaces_unconfirmed_secrets() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let uid = setup_user(None, None);
+    let conn = DB_POOL.get().unwrap();
+
+    let first = AccountTwoFactor::start_enrollment(uid, &conn).unwrap();
+    let second = AccountTwoFactor::start_enrollment(uid, &conn).unwrap();
+    assert_ne!(first.secret, second.secret);
+    assert_eq!(
+        AccountTwoFactor::get(uid, &conn).unwrap().unwrap().secret,
+        second.secret
+    );
+}
+
+#[actix_rt::test]
+async fn verify_does_not_accept_a_code_twice() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let uid = setup_user(None, None);
+    let other_uid = setup_user(Some("other@host.com".to_string()), None);
+    let conn = DB_POOL.get().unwrap();
+    let two_factor = AccountTwoFactor::start_enrollment(uid, &conn).unwrap();
+    let code = current_code(&two_factor.secret);
+    AccountTwoFactor::confirm_enrollment(uid, &code, &conn)
+        .unwrap()
+        .unwrap();
+
+    // the code used to confirm the enrollment is spent
+    assert!(!AccountTwoFactor::verify(uid, &code, &conn).unwrap());
+    assert!(!AccountTwoFactor::verify(other_uid, &code, &conn).unwrap());
+
+    let step = totp::step_at(Utc::now().timestamp() as u64) as i64;
+    diesel::update(account_two_factors::table.find(uid))
+        .set(account_two_factors::last_used_step.eq(step - 2))
+        .execute(&conn)
+        .unwrap();
+    assert!(AccountTwoFactor::verify(uid, &code, &conn).unwrap());
+    assert!(!AccountTwoFactor::verify(uid, &code, &conn).unwrap());
+}
+
+#[actix_rt::test]
+async fn recovery_codes_can_be_used_once() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let uid = setup_user(None, None);
+    let conn = DB_POOL.get().unwrap();
+    let two_factor = AccountTwoFactor::start_enrollment(uid, &conn).unwrap();
+    let recovery_codes =
+        AccountTwoFactor::confirm_enrollment(uid, &current_code(&two_factor.secret), &conn)
+            .unwrap()
+            .unwrap();
+
+    assert!(!AccountTwoFactor::verify(uid, "aaaaa-aaaaa", &conn).unwrap());
+    assert!(AccountTwoFactor::verify(uid, &recovery_codes[0], &conn).unwrap());
+    assert!(!AccountTwoFactor::verify(uid, &recovery_codes[0], &conn).unwrap());
+    // separators and case don't matter
+    let typed = recovery_codes[1].replace('-', " ").to_uppercase();
+    assert!(AccountTwoFactor::verify(uid, &typed, &conn).unwrap());
+    assert_eq!(
+        AccountTwoFactor::get(uid, &conn)
+            .unwrap()
+            .unwrap()
+            .recovery_codes
+            .len(),
+        RECOVERY_CODES_COUNT - 2
+    );
+
+    let new_codes = AccountTwoFactor::regenerate_recovery_codes(uid, &conn).unwrap();
+    assert!(!AccountTwoFactor::verify(uid, &recovery_codes[2], &conn).unwrap());
+    assert!(AccountTwoFactor::verify(uid, &new_codes[0], &conn).unwrap());
+}
+
+#[actix_rt::test]
+async fn disable_works() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let uid = setup_user(None, None);
+    let conn = DB_POOL.get().unwrap();
+    let two_factor = AccountTwoFactor::start_enrollment(uid, &conn).unwrap();
+    let recovery_codes =
+        AccountTwoFactor::confirm_enrollment(uid, &current_code(&two_factor.secret), &conn)
+            .unwrap()
+            .unwrap();
+
+    assert_eq!(AccountTwoFactor::disable(uid, &conn).unwrap(), 1);
+    assert!(!AccountTwoFactor::is_enabled(uid, &conn).unwrap());
+    assert!(!AccountTwoFactor::verify(uid, &recovery_codes[0], &conn).unwrap());
+    assert!(AccountTwoFactor::regenerate_recovery_codes(uid, &conn).is_err());
+}
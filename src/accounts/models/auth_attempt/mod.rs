@@ -0,0 +1,204 @@
+use diesel::pg::Pg;
+use diesel::prelude::*;
+use jelly::chrono::{DateTime, Duration, Utc};
+use jelly::DieselPgConnection;
+use jelly::Result;
+
+use crate::schema::auth_attempts;
+
+#[cfg(test)]
+mod tests;
+
+/// Failed sign-ins within this window slow down the next ones.
+pub const LOGIN_WINDOW_MINUTES: i64 = 15;
+/// Failed sign-ins for an email, and from an IP address, allowed before each attempt is delayed.
+pub const FREE_FAILURES_PER_EMAIL: i64 = 3;
+pub const FREE_FAILURES_PER_IP: i64 = 10;
+pub const MAX_DELAY_SECONDS: i64 = 60;
+/// Failed sign-ins for an email within the window that lock the account.
+pub const LOCKOUT_FAILURES: i64 = 10;
+pub const LOCKOUT_MINUTES: i64 = 30;
+/// Wrong second factors for an account within `KEEP_HOURS` that lock it.
+pub const LOCKOUT_SECOND_FACTOR_FAILURES: i64 = 5;
+
+/// Password reset and registration requests allowed per hour.
+pub const REQUESTS_PER_EMAIL: i64 = 3;
+pub const REQUESTS_PER_IP: i64 = 10;
+
+// Attempts older than the longest window are pruned.
+pub const KEEP_HOURS: i64 = 24;
+
+/// What was attempted. Kinds are stored as integers, so new ones go at the end.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthAttemptKind {
+    FailedLogin = 0,
+    PasswordReset = 1,
+    Registration = 2,
+    /// A wrong two-factor code, recovery code or passkey after the password was right.
+    FailedSecondFactor = 3,
+}
+
+/// Whether a password reset or registration request may send an email.
+#[derive(Debug, PartialEq, Eq)]
+pub enum RequestLimit {
+    Allowed,
+    /// Too many requests for the email. The request should look successful, so it doesn't
+    /// tell whether the email has an account.
+    EmailLimited,
+    IpLimited,
+}
+
+type AttemptsQuery<'a> = auth_attempts::BoxedQuery<'a, Pg>;
+
+/// A failed sign-in, or a request that sends an email, kept to throttle the next ones.
+/// IP addresses must come from `ClientIp` or `SessionDevice`, which only believe the
+/// forwarded headers of trusted proxies, so clients can't pick their own.
+pub struct AuthAttempt;
+
+impl AuthAttempt {
+    pub fn record(
+        kind: AuthAttemptKind,
+        email: &str,
+        ip_address: Option<&str>,
+        conn: &DieselPgConnection,
+    ) -> Result<()> {
+        diesel::delete(
+            auth_attempts::table
+                .filter(auth_attempts::created_at.lt(Utc::now() - Duration::hours(KEEP_HOURS))),
+        )
+        .execute(conn)?;
+        diesel::insert_into(auth_attempts::table)
+            .values((
+                auth_attempts::kind.eq(kind as i32),
+                auth_attempts::email.eq(normalize_email(email)),
+                auth_attempts::ip_address.eq(ip_address),
+            ))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    pub fn clear(kind: AuthAttemptKind, email: &str, conn: &DieselPgConnection) -> Result<()> {
+        diesel::delete(
+            auth_attempts::table
+                .filter(auth_attempts::kind.eq(kind as i32))
+                .filter(auth_attempts::email.eq(normalize_email(email))),
+        )
+        .execute(conn)?;
+        Ok(())
+    }
+
+    /// Number of attempts for the email since the given time.
+    pub fn count_for_email(
+        kind: AuthAttemptKind,
+        email: &str,
+        since: DateTime<Utc>,
+        conn: &DieselPgConnection,
+    ) -> Result<i64> {
+        let query =
+            Self::since(kind, since).filter(auth_attempts::email.eq(normalize_email(email)));
+        Ok(Self::count_and_last(query, conn)?.0)
+    }
+
+    /// IP addresses and times of the attempts for the email still on record, the most
+    /// recent first.
+    pub fn get_by_email(
+        kind: AuthAttemptKind,
+        email: &str,
+        conn: &DieselPgConnection,
+    ) -> Result<Vec<(Option<String>, DateTime<Utc>)>> {
+        Ok(auth_attempts::table
+            .filter(auth_attempts::kind.eq(kind as i32))
+            .filter(auth_attempts::email.eq(normalize_email(email)))
+            .select((auth_attempts::ip_address, auth_attempts::created_at))
+            .order(auth_attempts::created_at.desc())
+            .load::<(Option<String>, DateTime<Utc>)>(conn)?)
+    }
+
+    /// Seconds to wait before the next sign-in for the email from the IP address. Each failure
+    /// past the free ones doubles the delay, up to `MAX_DELAY_SECONDS`.
+    pub fn login_delay(
+        email: &str,
+        ip_address: Option<&str>,
+        conn: &DieselPgConnection,
+    ) -> Result<Option<i64>> {
+        let since = Utc::now() - Duration::minutes(LOGIN_WINDOW_MINUTES);
+        let by_email = Self::since(AuthAttemptKind::FailedLogin, since)
+            .filter(auth_attempts::email.eq(normalize_email(email)));
+        let mut delay = remaining_delay(
+            Self::count_and_last(by_email, conn)?,
+            FREE_FAILURES_PER_EMAIL,
+        );
+        if let Some(ip_address) = ip_address {
+            let by_ip = Self::since(AuthAttemptKind::FailedLogin, since)
+                .filter(auth_attempts::ip_address.eq(ip_address));
+            delay = delay.max(remaining_delay(
+                Self::count_and_last(by_ip, conn)?,
+                FREE_FAILURES_PER_IP,
+            ));
+        }
+        Ok(delay)
+    }
+
+    /// Checks the hourly limits of a request that sends an email, and records it if allowed.
+    pub fn limit_request(
+        kind: AuthAttemptKind,
+        email: &str,
+        ip_address: Option<&str>,
+        conn: &DieselPgConnection,
+    ) -> Result<RequestLimit> {
+        let since = Utc::now() - Duration::hours(1);
+        if let Some(ip_address) = ip_address {
+            let by_ip = Self::since(kind, since).filter(auth_attempts::ip_address.eq(ip_address));
+            if Self::count_and_last(by_ip, conn)?.0 >= REQUESTS_PER_IP {
+                return Ok(RequestLimit::IpLimited);
+            }
+        }
+        if Self::count_for_email(kind, email, since, conn)? >= REQUESTS_PER_EMAIL {
+            return Ok(RequestLimit::EmailLimited);
+        }
+
+        Self::record(kind, email, ip_address, conn)?;
+        Ok(RequestLimit::Allowed)
+    }
+
+    fn since<'a>(kind: AuthAttemptKind, since: DateTime<Utc>) -> AttemptsQuery<'a> {
+        auth_attempts::table
+            .filter(auth_attempts::kind.eq(kind as i32))
+            .filter(auth_attempts::created_at.gt(since))
+            .into_boxed()
+    }
+
+    // The throttling keeps the attempts within a window few, so they are simply loaded
+    fn count_and_last(
+        query: AttemptsQuery,
+        conn: &DieselPgConnection,
+    ) -> Result<(i64, Option<DateTime<Utc>>)> {
+        let times = query
+            .select(auth_attempts::created_at)
+            .order(auth_attempts::created_at.desc())
+            .load::<DateTime<Utc>>(conn)?;
+        Ok((times.len() as i64, times.first().copied()))
+    }
+}
+
+/// Emails are compared case insensitively, whether they belong to an account or not.
+pub fn normalize_email(email: &str) -> String {
+    email.trim().to_lowercase()
+}
+
+fn remaining_delay((failures, last): (i64, Option<DateTime<Utc>>), free: i64) -> Option<i64> {
+    let last = last?;
+    if failures < free {
+        return None;
+    }
+    let delay = 2i64
+        .pow((failures - free).min(6) as u32)
+        .min(MAX_DELAY_SECONDS);
+    let remaining = (last + Duration::seconds(delay) - Utc::now()).num_milliseconds();
+    // Rounded up, so that waiting the given seconds is always enough
+    if remaining > 0 {
+        Some((remaining + 999) / 1000)
+    } else {
+        None
+    }
+}
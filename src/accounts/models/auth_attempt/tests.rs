@@ -0,0 +1,152 @@
+use crate::accounts::models::auth_attempt::*;
+use crate::schema::auth_attempts;
+use crate::test::{DatabaseTestContext, DB_POOL};
+use jelly::chrono::{Duration, Utc};
+
+fn record_failures(count: usize, email: &str, ip: &str) {
+    let conn = DB_POOL.get().unwrap();
+    for _ in 0..count {
+        AuthAttempt::record(AuthAttemptKind::FailedLogin, email, Some(ip), &conn).unwrap();
+    }
+}
+
+#[actix_rt::test]
+async fn login_delay_starts_after_the_free_failures_of_an_email() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+    let conn = DB_POOL.get().unwrap();
+
+    record_failures(FREE_FAILURES_PER_EMAIL as usize - 1, "a@b.com", "10.0.0.1");
+    assert_eq!(
+        AuthAttempt::login_delay("a@b.com", Some("10.0.0.1"), &conn).unwrap(),
+        None
+    );
+
+    record_failures(1, "A@b.com ", "10.0.0.2");
+    assert_eq!(
+        AuthAttempt::login_delay("a@b.com", None, &conn).unwrap(),
+        Some(1)
+    );
+    assert_eq!(
+        AuthAttempt::login_delay("other@b.com", Some("10.0.0.1"), &conn).unwrap(),
+        None
+    );
+
+    record_failures(10, "a@b.com", "10.0.0.3");
+    let delay = AuthAttempt::login_delay("a@b.com", None, &conn)
+        .unwrap()
+        .unwrap();
+    assert!(delay > 1 && delay <= MAX_DELAY_SECONDS);
+}
+
+#[actix_rt::test]
+async fn login_delay_counts_the_failures_of_an_ip_address_for_every_email() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+    let conn = DB_POOL.get().unwrap();
+
+    for i in 0..FREE_FAILURES_PER_IP {
+        record_failures(1, &format!("user{}@b.com", i), "10.0.0.1");
+    }
+    assert_eq!(
+        AuthAttempt::login_delay("new@b.com", Some("10.0.0.1"), &conn).unwrap(),
+        Some(1)
+    );
+    assert_eq!(
+        AuthAttempt::login_delay("new@b.com", Some("10.0.0.2"), &conn).unwrap(),
+        None
+    );
+}
+
+#[actix_rt::test]
+async fn login_delay_ignores_failures_outside_the_window() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+    let conn = DB_POOL.get().unwrap();
+
+    record_failures(5, "a@b.com", "10.0.0.1");
+    diesel::update(auth_attempts::table)
+        .set(auth_attempts::created_at.eq(Utc::now() - Duration::minutes(LOGIN_WINDOW_MINUTES + 1)))
+        .execute(&conn)
+        .unwrap();
+    assert_eq!(
+        AuthAttempt::login_delay("a@b.com", Some("10.0.0.1"), &conn).unwrap(),
+        None
+    );
+
+    AuthAttempt::clear(AuthAttemptKind::FailedLogin, "a@b.com", &conn).unwrap();
+    assert_eq!(
+        auth_attempts::table
+            .count()
+            .get_result::<i64>(&conn)
+            .unwrap(),
+        0
+    );
+}
+
+#[actix_rt::test]
+async fn record_prunes_old_attempts() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+    let conn = DB_POOL.get().unwrap();
+
+    record_failures(2, "a@b.com", "10.0.0.1");
+    diesel::update(auth_attempts::table)
+        .set(auth_attempts::created_at.eq(Utc::now() - Duration::days(2)))
+        .execute(&conn)
+        .unwrap();
+    record_failures(1, "a@b.com", "10.0.0.1");
+    assert_eq!(
+        auth_attempts::table
+            .count()
+            .get_result::<i64>(&conn)
+            .unwrap(),
+        1
+    );
+}
+
+#[actix_rt::test]
+async fn limit_request_limits_emails_and_ip_addresses() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+    let conn = DB_POOL.get().unwrap();
+    let kind = AuthAttemptKind::PasswordReset;
+
+    for _ in 0..REQUESTS_PER_EMAIL {
+        assert_eq!(
+            AuthAttempt::limit_request(kind, "a@b.com", Some("10.0.0.1"), &conn).unwrap(),
+            RequestLimit::Allowed
+        );
+    }
+    assert_eq!(
+        AuthAttempt::limit_request(kind, "a@b.com", Some("10.0.0.2"), &conn).unwrap(),
+        RequestLimit::EmailLimited
+    );
+    // Registrations have their own limits
+    assert_eq!(
+        AuthAttempt::limit_request(
+            AuthAttemptKind::Registration,
+            "a@b.com",
+            Some("10.0.0.1"),
+            &conn
+        )
+        .unwrap(),
+        RequestLimit::Allowed
+    );
+
+    for i in REQUESTS_PER_EMAIL..REQUESTS_PER_IP {
+        let email = format!("user{}@b.com", i);
+        assert_eq!(
+            AuthAttempt::limit_request(kind, &email, Some("10.0.0.1"), &conn).unwrap(),
+            RequestLimit::Allowed
+        );
+    }
+    assert_eq!(
+        AuthAttempt::limit_request(kind, "new@b.com", Some("10.0.0.1"), &conn).unwrap(),
+        RequestLimit::IpLimited
+    );
+    assert_eq!(
+        AuthAttempt::limit_request(kind, "new@b.com", Some("10.0.0.2"), &conn).unwrap(),
+        RequestLimit::Allowed
+    );
+}
@@ -9,13 +9,20 @@ use diesel::result::Error as DBError;
 use diesel::{AsChangeset, Identifiable, Insertable, Queryable};
 
 use jelly::accounts::{OneTimeUseTokenGenerator, User};
-use jelly::chrono::{offset, DateTime, Utc};
+use jelly::chrono::{offset, DateTime, Duration, Utc};
 use jelly::djangohashers::{check_password, make_password};
 use jelly::error::Error;
 use jelly::error::Error::Generic;
 use jelly::serde::{Deserialize, Serialize};
 use jelly::{DieselPgConnection, DieselPgPool};
 
+use self::account_event::{AccountEvent, AccountEventKind};
+use self::account_session::SessionDevice;
+use self::auth_attempt::AuthAttemptKind::{FailedLogin, FailedSecondFactor};
+use self::auth_attempt::{
+    AuthAttempt, KEEP_HOURS, LOCKOUT_FAILURES, LOCKOUT_MINUTES, LOCKOUT_SECOND_FACTOR_FAILURES,
+    LOGIN_WINDOW_MINUTES,
+};
 use super::forms::{LoginForm, NewAccountForm};
 use super::views::avatar::Gravatar;
 use super::views::verify::GithubOauthUser;
@@ -27,6 +34,13 @@ use crate::schema::api_tokens::dsl::{
 use crate::schema::package_collaborators;
 use crate::utils::token::generate_secure_alphanumeric_string;
 
+pub mod account_event;
+pub mod account_session;
+pub mod account_two_factor;
+pub mod auth_attempt;
+pub mod webauthn_challenge;
+pub mod webauthn_credential;
+
 #[cfg(test)]
 mod tests;
 
@@ -47,8 +61,12 @@ pub struct Account {
     pub github_id: Option<i64>,
     pub avatar: Option<String>,
     pub slug: Option<String>,
+    /// Set after too many failed sign-ins. Passwords aren't checked until then.
+    pub locked_until: Option<DateTime<Utc>>,
 }
 
+pub const MSG_ACCOUNT_LOCKED: &str = "Your account is temporarily locked after too many failed sign-in attempts. Try again later or reset your password.";
+
 impl Account {
     pub fn get(uid: i32, pool: &DieselPgPool) -> Result<Self, Error> {
         let connection = pool.get()?;
@@ -80,19 +98,55 @@ impl Account {
         Ok(result)
     }
 
-    pub fn authenticate(form: &LoginForm, pool: &DieselPgPool) -> Result<User, Error> {
+    /// Checks the email and password of a sign-in. Failures are recorded to delay the next
+    /// attempts for the email and the IP address, and lock the account after too many of them.
+    /// The attempt that locks it returns `Error::AccountLocked`.
+    pub fn authenticate(
+        form: &LoginForm,
+        ip_address: Option<&str>,
+        pool: &DieselPgPool,
+    ) -> Result<User, Error> {
         let connection = pool.get()?;
-        let user = accounts
+        if let Some(seconds) = AuthAttempt::login_delay(&form.email.value, ip_address, &connection)?
+        {
+            return Err(Generic(format!(
+                "Too many failed attempts. Try again in {} seconds.",
+                seconds
+            )));
+        }
+        let user = match accounts
             .filter(email.eq(&form.email.value))
-            .first::<Account>(&connection)?;
+            .first::<Account>(&connection)
+        {
+            Err(DBError::NotFound) => {
+                AuthAttempt::record(FailedLogin, &form.email.value, ip_address, &connection)?;
+                return Err(Error::Database(DBError::NotFound));
+            }
+            result => result?,
+        };
         if !user.has_verified_email {
             return Err(Generic(String::from(
                 "Your account has not been activated.",
             )));
         }
         if !check_password(&form.password, &user.password)? {
+            AuthAttempt::record(FailedLogin, &form.email.value, ip_address, &connection)?;
+            let since = offset::Utc::now() - Duration::minutes(LOGIN_WINDOW_MINUTES);
+            let failures =
+                AuthAttempt::count_for_email(FailedLogin, &form.email.value, since, &connection)?;
+            if failures >= LOCKOUT_FAILURES && Self::lock(user.id, &connection)? {
+                // The lockout takes over from the delays, and the count starts over after it
+                AuthAttempt::clear(FailedLogin, &form.email.value, &connection)?;
+                return Err(Error::AccountLocked);
+            }
             return Err(Error::InvalidPassword);
         }
+        // Only told once the password matched, so that the lockout doesn't confirm the email
+        // has an account
+        if user.is_locked() {
+            return Err(Generic(String::from(MSG_ACCOUNT_LOCKED)));
+        }
+        AuthAttempt::clear(FailedLogin, &form.email.value, &connection)?;
 
         Ok(User {
             id: user.id,
@@ -102,6 +156,85 @@ impl Account {
         })
     }
 
+    /// Records a wrong second factor of a sign-in, and locks the account after too many of
+    /// them. Unlike wrong passwords, they aren't forgotten when the account gets locked, so
+    /// each one after the lockout locks it again. Returns `Error::AccountLocked` if it was
+    /// locked now.
+    pub fn fail_second_factor(
+        uid: i32,
+        device: &SessionDevice,
+        connection: &DieselPgConnection,
+    ) -> Result<(), Error> {
+        let account_email = accounts
+            .find(uid)
+            .select(email)
+            .first::<String>(connection)?;
+        AuthAttempt::record(
+            FailedSecondFactor,
+            &account_email,
+            device.ip_address.as_deref(),
+            connection,
+        )?;
+        AccountEvent::record(
+            uid,
+            AccountEventKind::FailedSecondFactor,
+            device,
+            connection,
+        )?;
+        let since = offset::Utc::now() - Duration::hours(KEEP_HOURS);
+        let failures =
+            AuthAttempt::count_for_email(FailedSecondFactor, &account_email, since, connection)?;
+        if failures >= LOCKOUT_SECOND_FACTOR_FAILURES && Self::lock(uid, connection)? {
+            return Err(Error::AccountLocked);
+        }
+        Ok(())
+    }
+
+    /// Forgets the wrong second factors of the account, once it signed in.
+    pub fn clear_second_factor_failures(
+        uid: i32,
+        connection: &DieselPgConnection,
+    ) -> Result<(), Error> {
+        let account_email = accounts
+            .find(uid)
+            .select(email)
+            .first::<String>(connection)?;
+        AuthAttempt::clear(FailedSecondFactor, &account_email, connection)?;
+        Ok(())
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.locked_until
+            .is_some_and(|until| until > offset::Utc::now())
+    }
+
+    /// Locks the account for `LOCKOUT_MINUTES`, unless it is already locked. Returns whether
+    /// it was locked now.
+    fn lock(uid: i32, connection: &DieselPgConnection) -> Result<bool, Error> {
+        let now = offset::Utc::now();
+        let locked = diesel::update(
+            accounts
+                .filter(id.eq(uid))
+                .filter(locked_until.is_null().or(locked_until.le(now))),
+        )
+        .set(locked_until.eq(now + Duration::minutes(LOCKOUT_MINUTES)))
+        .execute(connection)?;
+        Ok(locked == 1)
+    }
+
+    /// Unlocks the account and forgets its failed sign-ins.
+    pub fn unlock(uid: i32, pool: &DieselPgPool) -> Result<(), Error> {
+        let connection = pool.get()?;
+        let account_email = diesel::update(accounts.filter(id.eq(uid)))
+            .set(locked_until.eq(None::<DateTime<Utc>>))
+            .returning(email)
+            .get_result::<String>(&connection)?;
+        AuthAttempt::clear(FailedLogin, &account_email, &connection)?;
+        AuthAttempt::clear(FailedSecondFactor, &account_email, &connection)?;
+
+        Ok(())
+    }
+
     pub fn fetch_email(uid: i32, pool: &DieselPgPool) -> Result<(String, String), Error> {
         let connection = pool.get()?;
         let result = accounts
@@ -88,7 +88,7 @@ async fn authenticate_works() {
     let uid = setup_user(None, None);
     Account::mark_verified(uid, &DB_POOL).unwrap();
 
-    let user = Account::authenticate(&login_form(), &DB_POOL).unwrap();
+    let user = Account::authenticate(&login_form(), None, &DB_POOL).unwrap();
     assert_eq!(user.id, uid);
 }
 
@@ -112,7 +112,7 @@ async fn authenticate_with_wrong_email_return_err() {
         remember_me: "off".to_string(),
         redirect: "".to_string(),
     };
-    match Account::authenticate(&invalid_login_form, &DB_POOL) {
+    match Account::authenticate(&invalid_login_form, None, &DB_POOL) {
         Err(Error::Database(DBError::NotFound)) => (),
         _ => panic!(),
     }
@@ -138,7 +138,7 @@ async fn authenticate_with_wrong_password_return_err() {
         remember_me: "off".to_string(),
         redirect: "".to_string(),
     };
-    match Account::authenticate(&invalid_login_form, &DB_POOL) {
+    match Account::authenticate(&invalid_login_form, None, &DB_POOL) {
         Err(Error::InvalidPassword) => (),
         _ => panic!(),
     }
@@ -161,7 +161,7 @@ async fn authenticate_with_unverified_account_return_err() {
     };
     Account::register(&form, &DB_POOL).unwrap();
 
-    match Account::authenticate(&login_form(), &DB_POOL) {
+    match Account::authenticate(&login_form(), None, &DB_POOL) {
         Err(Generic(e)) => {
             assert_eq!(e, String::from("Your account has not been activated."))
         }
@@ -169,6 +169,95 @@ async fn authenticate_with_unverified_account_return_err() {
     }
 }
 
+fn wrong_password_form() -> LoginForm {
+    let mut form = login_form();
+    form.password.value = "wrongpassword".to_string();
+    form
+}
+
+// Failures from a few minutes ago count toward the lockout without delaying the next attempt
+fn record_earlier_failures(count: usize, ip: &str) {
+    use crate::schema::auth_attempts;
+
+    let created_at = offset::Utc::now() - Duration::minutes(5);
+    for _ in 0..count {
+        diesel::insert_into(auth_attempts::table)
+            .values((
+                auth_attempts::kind.eq(FailedLogin as i32),
+                auth_attempts::email.eq("email@host.com"),
+                auth_attempts::ip_address.eq(ip),
+                auth_attempts::created_at.eq(created_at),
+            ))
+            .execute(&DB_POOL.get().unwrap())
+            .unwrap();
+    }
+}
+
+#[actix_rt::test]
+async fn authenticate_delays_attempts_after_failures() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+    let uid = setup_user(None, None);
+    Account::mark_verified(uid, &DB_POOL).unwrap();
+
+    for _ in 0..3 {
+        match Account::authenticate(&wrong_password_form(), Some("10.0.0.1"), &DB_POOL) {
+            Err(Error::InvalidPassword) => (),
+            _ => panic!(),
+        }
+    }
+    // The password isn't checked while delayed
+    match Account::authenticate(&login_form(), Some("10.0.0.1"), &DB_POOL) {
+        Err(Generic(e)) => assert!(e.starts_with("Too many failed attempts.")),
+        _ => panic!(),
+    }
+}
+
+#[actix_rt::test]
+async fn authenticate_locks_account_after_too_many_failures() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+    let uid = setup_user(None, None);
+    Account::mark_verified(uid, &DB_POOL).unwrap();
+    record_earlier_failures(LOCKOUT_FAILURES as usize - 1, "10.0.0.1");
+
+    match Account::authenticate(&wrong_password_form(), Some("10.0.0.2"), &DB_POOL) {
+        Err(Error::AccountLocked) => (),
+        _ => panic!(),
+    }
+    assert!(Account::get(uid, &DB_POOL).unwrap().is_locked());
+    // Wrong passwords don't tell that the account is locked
+    match Account::authenticate(&wrong_password_form(), Some("10.0.0.3"), &DB_POOL) {
+        Err(Error::InvalidPassword) => (),
+        _ => panic!(),
+    }
+    match Account::authenticate(&login_form(), Some("10.0.0.3"), &DB_POOL) {
+        Err(Generic(e)) => assert_eq!(e, MSG_ACCOUNT_LOCKED),
+        _ => panic!(),
+    }
+
+    Account::unlock(uid, &DB_POOL).unwrap();
+    assert!(!Account::get(uid, &DB_POOL).unwrap().is_locked());
+    let user = Account::authenticate(&login_form(), Some("10.0.0.3"), &DB_POOL).unwrap();
+    assert_eq!(user.id, uid);
+}
+
+#[actix_rt::test]
+async fn authenticate_clears_failures_after_signing_in() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+    let uid = setup_user(None, None);
+    Account::mark_verified(uid, &DB_POOL).unwrap();
+    record_earlier_failures(LOCKOUT_FAILURES as usize - 1, "10.0.0.1");
+
+    Account::authenticate(&login_form(), None, &DB_POOL).unwrap();
+    match Account::authenticate(&wrong_password_form(), None, &DB_POOL) {
+        Err(Error::InvalidPassword) => (),
+        _ => panic!(),
+    }
+    assert!(!Account::get(uid, &DB_POOL).unwrap().is_locked());
+}
+
 #[actix_rt::test]
 async fn register_works() {
     crate::test::init();
@@ -261,7 +350,7 @@ async fn change_password_works() {
     .unwrap();
     let mut login_form = login_form();
     login_form.password.value = new_password.clone();
-    match Account::authenticate(&login_form, &DB_POOL) {
+    match Account::authenticate(&login_form, None, &DB_POOL) {
         Ok(user) => assert_eq!(user.id, uid),
         _ => panic!(),
     }
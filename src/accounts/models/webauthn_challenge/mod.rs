@@ -0,0 +1,46 @@
+use diesel::prelude::*;
+use jelly::chrono::{DateTime, Utc};
+use jelly::DieselPgConnection;
+use jelly::Result;
+
+use crate::schema::webauthn_challenges;
+
+/// A challenge of a WebAuthn ceremony waiting for the browser to sign it. The cookie session
+/// only names it, so deleting the row is what makes it single use.
+pub struct WebauthnChallenge;
+
+impl WebauthnChallenge {
+    pub fn create(
+        challenge: &str,
+        ceremony: &str,
+        expires_at: DateTime<Utc>,
+        conn: &DieselPgConnection,
+    ) -> Result<()> {
+        diesel::delete(
+            webauthn_challenges::table.filter(webauthn_challenges::expires_at.le(Utc::now())),
+        )
+        .execute(conn)?;
+        diesel::insert_into(webauthn_challenges::table)
+            .values((
+                webauthn_challenges::challenge.eq(challenge),
+                webauthn_challenges::ceremony.eq(ceremony),
+                webauthn_challenges::expires_at.eq(expires_at),
+            ))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    /// Deletes the challenge of the ceremony. Returns whether it was still waiting, which
+    /// only one request can see.
+    pub fn take(challenge: &str, ceremony: &str, conn: &DieselPgConnection) -> Result<bool> {
+        let deleted = diesel::delete(
+            webauthn_challenges::table
+                .filter(webauthn_challenges::challenge.eq(challenge))
+                .filter(webauthn_challenges::ceremony.eq(ceremony)),
+        )
+        .returning(webauthn_challenges::expires_at)
+        .get_result::<DateTime<Utc>>(conn)
+        .optional()?;
+        Ok(deleted.is_some_and(|expires_at| expires_at > Utc::now()))
+    }
+}
@@ -0,0 +1,142 @@
+use diesel::dsl::{exists, select};
+use diesel::prelude::*;
+use diesel::{Associations, Identifiable, Queryable};
+use jelly::chrono::{DateTime, Utc};
+use jelly::DieselPgConnection;
+use jelly::Result;
+use serde::Serialize;
+
+use crate::accounts::webauthn::{
+    verify_assertion, AssertionCredential, CredentialDescriptor, RelyingParty, VerifiedCredential,
+};
+use crate::accounts::Account;
+use crate::schema::webauthn_credentials;
+
+#[cfg(test)]
+mod tests;
+
+pub const MAX_NAME_LENGTH: usize = 64;
+
+/// A passkey or security key of an account, registered with WebAuthn.
+#[derive(Debug, Serialize, Queryable, Identifiable, Associations)]
+#[belongs_to(Account)]
+pub struct WebauthnCredential {
+    pub id: i32,
+    pub account_id: i32,
+    pub credential_id: String,
+    #[serde(skip)]
+    pub public_key: Vec<u8>,
+    #[serde(skip)]
+    pub sign_count: i64,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+impl WebauthnCredential {
+    pub fn create(
+        account_id_: i32,
+        name_: &str,
+        credential: &VerifiedCredential,
+        conn: &DieselPgConnection,
+    ) -> Result<Self> {
+        Ok(diesel::insert_into(webauthn_credentials::table)
+            .values((
+                webauthn_credentials::account_id.eq(account_id_),
+                webauthn_credentials::credential_id.eq(&credential.credential_id),
+                webauthn_credentials::public_key.eq(&credential.public_key),
+                webauthn_credentials::sign_count.eq(i64::from(credential.sign_count)),
+                webauthn_credentials::name.eq(name_),
+            ))
+            .get_result::<Self>(conn)?)
+    }
+
+    pub fn get_by_account(account_id_: i32, conn: &DieselPgConnection) -> Result<Vec<Self>> {
+        Ok(webauthn_credentials::table
+            .filter(webauthn_credentials::account_id.eq(account_id_))
+            .order_by(webauthn_credentials::created_at.asc())
+            .load::<Self>(conn)?)
+    }
+
+    pub fn exists_for_account(account_id_: i32, conn: &DieselPgConnection) -> Result<bool> {
+        Ok(select(exists(
+            webauthn_credentials::table.filter(webauthn_credentials::account_id.eq(account_id_)),
+        ))
+        .get_result(conn)?)
+    }
+
+    /// The credentials of the account, for browsers to pick from or to exclude.
+    pub fn descriptors(
+        account_id_: i32,
+        conn: &DieselPgConnection,
+    ) -> Result<Vec<CredentialDescriptor>> {
+        Ok(webauthn_credentials::table
+            .filter(webauthn_credentials::account_id.eq(account_id_))
+            .select(webauthn_credentials::credential_id)
+            .load::<String>(conn)?
+            .iter()
+            .map(|credential_id| CredentialDescriptor::new(credential_id))
+            .collect())
+    }
+
+    pub fn delete(id_: i32, account_id_: i32, conn: &DieselPgConnection) -> Result<usize> {
+        Ok(diesel::delete(
+            webauthn_credentials::table
+                .find(id_)
+                .filter(webauthn_credentials::account_id.eq(account_id_)),
+        )
+        .execute(conn)?)
+    }
+
+    /// Checks an assertion against the stored credential, and returns the account it belongs
+    /// to. `expected_account` restricts it to one account, when signing in with a second
+    /// factor. `None` for unknown credentials and failed checks.
+    pub fn authenticate(
+        rp: &RelyingParty,
+        challenge: &str,
+        assertion: &AssertionCredential,
+        expected_account: Option<i32>,
+        require_user_verification: bool,
+        conn: &DieselPgConnection,
+    ) -> Result<Option<i32>> {
+        let credential = match webauthn_credentials::table
+            .filter(webauthn_credentials::credential_id.eq(&assertion.id))
+            .first::<Self>(conn)
+            .optional()?
+        {
+            Some(credential) => credential,
+            None => return Ok(None),
+        };
+        if expected_account.is_some_and(|account_id_| account_id_ != credential.account_id) {
+            return Ok(None);
+        }
+
+        let sign_count = match verify_assertion(
+            rp,
+            challenge,
+            assertion,
+            &credential.public_key,
+            credential.sign_count,
+            require_user_verification,
+        ) {
+            Ok(sign_count) => i64::from(sign_count),
+            Err(error) => {
+                warn!("Rejected a WebAuthn assertion: {}", error);
+                return Ok(None);
+            }
+        };
+
+        // Filtering on the stored counter makes the second of two concurrent uses fail
+        let updated = diesel::update(
+            webauthn_credentials::table
+                .find(credential.id)
+                .filter(webauthn_credentials::sign_count.eq(credential.sign_count)),
+        )
+        .set((
+            webauthn_credentials::sign_count.eq(sign_count),
+            webauthn_credentials::last_used_at.eq(Utc::now()),
+        ))
+        .execute(conn)?;
+        Ok(Some(credential.account_id).filter(|_| updated == 1))
+    }
+}
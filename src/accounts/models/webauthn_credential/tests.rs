@@ -0,0 +1,110 @@
+use crate::accounts::models::webauthn_credential::WebauthnCredential;
+use crate::accounts::webauthn::software_authenticator::SoftwareAuthenticator;
+use crate::accounts::webauthn::{verify_registration, RelyingParty};
+use crate::test::util::setup_user;
+use crate::test::{DatabaseTestContext, DB_POOL};
+use jelly::DieselPgConnection;
+
+fn rp() -> RelyingParty {
+    RelyingParty::new("http://127.0.0.1:17001").unwrap()
+}
+
+fn challenge() -> String {
+    base64_url::encode(&[7u8; 32])
+}
+
+fn register(
+    account_id: i32,
+    authenticator: &SoftwareAuthenticator,
+    conn: &DieselPgConnection,
+) -> WebauthnCredential {
+    let registration = authenticator.register(&rp(), &challenge());
+    let verified = verify_registration(&rp(), &challenge(), &registration).unwrap();
+    WebauthnCredential::create(account_id, "Security key", &verified, conn).unwrap()
+}
+
+#[actix_rt::test]
+async fn create_and_delete_work() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let uid = setup_user(None, None);
+    let other_uid = setup_user(Some("other@host.com".to_string()), None);
+    let conn = DB_POOL.get().unwrap();
+    assert!(!WebauthnCredential::exists_for_account(uid, &conn).unwrap());
+
+    let authenticator = SoftwareAuthenticator::es256();
+    let credential = register(uid, &authenticator, &conn);
+    assert!(WebauthnCredential::exists_for_account(uid, &conn).unwrap());
+    assert_eq!(credential.credential_id, authenticator.credential_id());
+    let descriptors = WebauthnCredential::descriptors(uid, &conn).unwrap();
+    assert_eq!(descriptors.len(), 1);
+    assert_eq!(descriptors[0].id, authenticator.credential_id());
+
+    // the same authenticator can't be registered twice
+    let registration = authenticator.register(&rp(), &challenge());
+    let verified = verify_registration(&rp(), &challenge(), &registration).unwrap();
+    assert!(WebauthnCredential::create(other_uid, "Security key", &verified, &conn).is_err());
+
+    assert_eq!(
+        WebauthnCredential::delete(credential.id, other_uid, &conn).unwrap(),
+        0
+    );
+    assert_eq!(
+        WebauthnCredential::delete(credential.id, uid, &conn).unwrap(),
+        1
+    );
+    assert!(WebauthnCredential::get_by_account(uid, &conn)
+        .unwrap()
+        .is_empty());
+}
+
+#[actix_rt::test]
+async fn authenticate_works() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let uid = setup_user(None, None);
+    let other_uid = setup_user(Some("other@host.com".to_string()), None);
+    let conn = DB_POOL.get().unwrap();
+    let mut authenticator = SoftwareAuthenticator::ed25519();
+    register(uid, &authenticator, &conn);
+
+    let assertion = authenticator.assert(&rp(), &challenge());
+    assert_eq!(
+        WebauthnCredential::authenticate(
+            &rp(),
+            &challenge(),
+            &assertion,
+            Some(other_uid),
+            true,
+            &conn
+        )
+        .unwrap(),
+        None
+    );
+    assert_eq!(
+        WebauthnCredential::authenticate(&rp(), &challenge(), &assertion, None, true, &conn)
+            .unwrap(),
+        Some(uid)
+    );
+    let credential = &WebauthnCredential::get_by_account(uid, &conn).unwrap()[0];
+    assert_eq!(credential.sign_count, 1);
+    assert!(credential.last_used_at.is_some());
+
+    // the same assertion can't be used again
+    assert_eq!(
+        WebauthnCredential::authenticate(&rp(), &challenge(), &assertion, Some(uid), false, &conn)
+            .unwrap(),
+        None
+    );
+
+    // unknown credentials
+    let mut unknown = SoftwareAuthenticator::es256();
+    let assertion = unknown.assert(&rp(), &challenge());
+    assert_eq!(
+        WebauthnCredential::authenticate(&rp(), &challenge(), &assertion, None, false, &conn)
+            .unwrap(),
+        None
+    );
+}
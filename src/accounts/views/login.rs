@@ -1,21 +1,21 @@
 use jelly::actix_session::UserSession;
 use jelly::actix_web::{web, web::Form, web::Query, HttpMessage, HttpRequest};
 use jelly::prelude::*;
-use jelly::request::{Authentication, DatabasePool};
+use jelly::request::DatabasePool;
 use jelly::Result;
 use oauth2::{basic::BasicClient, CsrfToken, Scope};
 use serde::Deserialize;
 
-use crate::utils::request_utils;
-use jelly::actix_web::{
-    cookie::{Cookie, CookieJar, Key},
-    http::header,
-};
-use time::Duration;
+use crate::utils::request_utils::{self, SignIn};
+use jelly::actix_web::http::header;
 
 use crate::accounts::forms::LoginForm;
+use crate::accounts::jobs::SendAccountLockedEmail;
+use crate::accounts::models::account_session::SessionDevice;
 use crate::accounts::Account;
 
+const MSG_INVALID_CREDENTIALS: &str = "Invalid email or password! Try again.";
+
 /// The login form.
 pub async fn form(request: HttpRequest) -> Result<HttpResponse> {
     if request_utils::is_authenticated(&request)? {
@@ -33,7 +33,11 @@ pub async fn form(request: HttpRequest) -> Result<HttpResponse> {
 }
 
 /// POST-handler for logging in.
-pub async fn authenticate(request: HttpRequest, form: Form<LoginForm>) -> Result<HttpResponse> {
+pub async fn authenticate(
+    request: HttpRequest,
+    form: Form<LoginForm>,
+    device: SessionDevice,
+) -> Result<HttpResponse> {
     if request_utils::is_authenticated(&request)? {
         return request.redirect("/settings/profile");
     }
@@ -42,52 +46,39 @@ pub async fn authenticate(request: HttpRequest, form: Form<LoginForm>) -> Result
     if !form.is_valid() {
         return request.render(400, "accounts/login.html", {
             let mut context = Context::new();
-            context.insert("error", "Invalid email or password! Try again.");
+            context.insert("error", MSG_INVALID_CREDENTIALS);
             context.insert("form", &form);
             context
         });
     }
 
     let db = request.db_pool()?;
-    let error_message = match Account::authenticate(&form, db) {
+    let error_message = match Account::authenticate(&form, device.ip_address.as_deref(), db) {
         Ok(user) => {
-            let user_id = user.id;
-            Account::update_last_login(user_id, db)?;
-            request.set_user(user)?;
+            let account = Account::get(user.id, db)?;
+            let sign_in = request_utils::start_sign_in(
+                &request.get_session(),
+                &account,
+                &device,
+                form.remember_me != "off",
+                &db.get()?,
+            )?;
+            // Accounts with two-factor authentication are signed in after the second step
+            if matches!(sign_in, SignIn::Complete(_)) {
+                Account::update_last_login(user.id, db)?;
+            }
 
-            return if form.remember_me == "off" {
-                request.redirect("/settings/profile")
-            } else {
-                let key = std::env::var("SECRET_KEY").expect("SECRET_KEY not set!");
-                let value = user_id;
-                let max_age_days = std::env::var("MAX_REMEMBER_ME_DAYS")
-                    .expect("MAX_REMEMBER_ME_DAYS not set!")
-                    .parse::<i64>()
-                    .expect("MAX_REMEMBER_ME_DAYS must be an integer");
-
-                let mut jar = CookieJar::new();
-                jar.signed(&Key::derive_from(key.as_bytes())).add(
-                    Cookie::build("remember_me_token", value.to_string())
-                        .path("/")
-                        .max_age(Duration::days(max_age_days))
-                        .http_only(true)
-                        .finish(),
-                );
-
-                Ok(HttpResponse::Found()
-                    .header(
-                        header::SET_COOKIE,
-                        jar.get("remember_me_token")
-                            .expect("Getting key from cookie jar should not fail.")
-                            .encoded()
-                            .to_string(),
-                    )
-                    .header(header::LOCATION, "/settings/profile")
-                    .finish())
-            };
+            return Ok(sign_in.redirect("/settings/profile"));
+        }
+        // The password was wrong, so the lockout is only told by email
+        Err(Error::AccountLocked) => {
+            request.queue(SendAccountLockedEmail {
+                to: form.email.value.clone(),
+            })?;
+            String::from(MSG_INVALID_CREDENTIALS)
         }
         Err(Error::Generic(e)) => e,
-        Err(_) => String::from("Invalid email or password! Try again."),
+        Err(_) => String::from(MSG_INVALID_CREDENTIALS),
     };
 
     request.render(400, "accounts/login.html", {
@@ -1,20 +1,22 @@
 //!  Views for user auth.
 
 use crate::constants;
-use jelly::actix_session::UserSession;
+use crate::utils::request_utils;
 use jelly::actix_web::http::header;
 use jelly::prelude::*;
 use jelly::Result;
 
 pub mod avatar;
 pub mod login;
+pub mod passkey;
 pub mod register;
 pub mod reset_password;
+pub mod two_factor;
 pub mod utils;
 pub mod verify;
 
 pub async fn logout(request: HttpRequest) -> Result<HttpResponse> {
-    request.get_session().clear();
+    request_utils::sign_out(&request)?;
     Ok(HttpResponse::Found()
         .header(header::SET_COOKIE, constants::REMEMBER_ME_TOKEN_INVALIDATE)
         .header(header::LOCATION, "/accounts/login/")
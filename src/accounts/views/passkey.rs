@@ -0,0 +1,146 @@
+use jelly::actix_session::UserSession;
+use jelly::actix_web::{web::Json, HttpRequest};
+use jelly::prelude::*;
+use jelly::request::DatabasePool;
+use jelly::Result;
+use serde::Deserialize;
+
+use crate::accounts::jobs::SendAccountLockedEmail;
+use crate::accounts::models::account_session::SessionDevice;
+use crate::accounts::models::webauthn_credential::WebauthnCredential;
+use crate::accounts::models::MSG_ACCOUNT_LOCKED;
+use crate::accounts::webauthn::{self, AssertionCredential, RelyingParty, RequestOptions};
+use crate::accounts::Account;
+use crate::utils::request_utils::{self, SignIn};
+
+const MSG_EXPIRED: &str = "The request expired. Try again.";
+const MSG_INVALID_PASSKEY: &str = "Invalid passkey! Try again.";
+
+#[derive(Deserialize)]
+pub struct PasskeySignInRequest {
+    pub credential: AssertionCredential,
+    #[serde(default)]
+    pub remember_me: bool,
+}
+
+/// The options for `navigator.credentials.get()` to sign in without a password. The browser
+/// offers the passkeys it has for the site.
+pub async fn options(request: HttpRequest) -> Result<HttpResponse> {
+    let challenge = webauthn::start_ceremony(
+        &request.get_session(),
+        webauthn::SIGN_IN_CHALLENGE_KEY,
+        &request.db_pool()?.get()?,
+    )?;
+    let options = RequestOptions::new(&RelyingParty::from_env()?, challenge, vec![], true);
+    Ok(HttpResponse::Ok().json(&options))
+}
+
+/// Signs in with a passkey. The user verification it requires, with a PIN or biometrics,
+/// makes it a second factor of its own, so two-factor authentication is not asked for.
+pub async fn authenticate(
+    request: HttpRequest,
+    req: Json<PasskeySignInRequest>,
+    device: SessionDevice,
+) -> Result<HttpResponse> {
+    if request_utils::is_authenticated(&request)? {
+        return Ok(SignIn::Complete(None).json_redirect("/settings/profile"));
+    }
+    let session = request.get_session();
+    let db = request.db_pool()?;
+    let conn = db.get()?;
+    let challenge =
+        match webauthn::take_challenge(&session, webauthn::SIGN_IN_CHALLENGE_KEY, &conn)? {
+            Some(challenge) => challenge,
+            None => return Ok(HttpResponse::BadRequest().body(MSG_EXPIRED)),
+        };
+
+    let account_id = match WebauthnCredential::authenticate(
+        &RelyingParty::from_env()?,
+        &challenge,
+        &req.credential,
+        None,
+        true,
+        &conn,
+    )? {
+        Some(account_id) => account_id,
+        None => return Ok(HttpResponse::BadRequest().body(MSG_INVALID_PASSKEY)),
+    };
+    let account = Account::get(account_id, db)?;
+    if !account.has_verified_email {
+        return Ok(HttpResponse::BadRequest().body("Your account has not been activated."));
+    }
+
+    Account::update_last_login(account.id, db)?;
+    let cookie = request_utils::sign_in(&session, &account, &device, req.remember_me, &conn)?;
+    Ok(SignIn::Complete(cookie).json_redirect("/settings/profile"))
+}
+
+/// The options for `navigator.credentials.get()` on the second step of signing in, limited
+/// to the passkeys of the pending account.
+pub async fn two_factor_options(request: HttpRequest) -> Result<HttpResponse> {
+    let session = request.get_session();
+    let pending = match request_utils::pending_two_factor(&session)? {
+        Some(pending) => pending,
+        None => return Ok(HttpResponse::BadRequest().body(MSG_EXPIRED)),
+    };
+    let conn = request.db_pool()?.get()?;
+    let credentials = WebauthnCredential::descriptors(pending.account_id, &conn)?;
+    if credentials.is_empty() {
+        return Ok(HttpResponse::BadRequest().body("You have no passkeys."));
+    }
+
+    let challenge = webauthn::start_ceremony(&session, webauthn::TWO_FACTOR_CHALLENGE_KEY, &conn)?;
+    let options = RequestOptions::new(&RelyingParty::from_env()?, challenge, credentials, false);
+    Ok(HttpResponse::Ok().json(&options))
+}
+
+/// Signs in the pending account with one of its passkeys instead of a code.
+pub async fn two_factor_authenticate(
+    request: HttpRequest,
+    credential: Json<AssertionCredential>,
+    device: SessionDevice,
+) -> Result<HttpResponse> {
+    if request_utils::is_authenticated(&request)? {
+        return Ok(SignIn::Complete(None).json_redirect("/settings/profile"));
+    }
+    let session = request.get_session();
+    let db = request.db_pool()?;
+    let challenge =
+        match webauthn::take_challenge(&session, webauthn::TWO_FACTOR_CHALLENGE_KEY, &db.get()?)? {
+            Some(challenge) => challenge,
+            None => return Ok(HttpResponse::BadRequest().body(MSG_EXPIRED)),
+        };
+
+    let rp = RelyingParty::from_env()?;
+    let pending = request_utils::pending_two_factor(&session)?;
+    let sign_in =
+        request_utils::complete_two_factor_sign_in(&session, &device, db, |account_id, conn| {
+            Ok(WebauthnCredential::authenticate(
+                &rp,
+                &challenge,
+                &credential,
+                Some(account_id),
+                false,
+                conn,
+            )?
+            .is_some())
+        });
+    match sign_in {
+        Ok(Some(sign_in)) => Ok(sign_in.json_redirect("/settings/profile")),
+        Ok(None) if pending.is_none() => Ok(HttpResponse::BadRequest().body(MSG_EXPIRED)),
+        // The account got locked from somewhere else
+        Ok(None) if request_utils::pending_two_factor(&session)?.is_none() => {
+            Ok(HttpResponse::BadRequest().body(MSG_ACCOUNT_LOCKED))
+        }
+        Ok(None) => Ok(HttpResponse::BadRequest().body(MSG_INVALID_PASSKEY)),
+        Err(Error::AccountLocked) => {
+            if let Some(pending) = pending {
+                request.queue(SendAccountLockedEmail {
+                    to: Account::get(pending.account_id, db)?.email,
+                })?;
+            }
+            Ok(HttpResponse::BadRequest().body(MSG_ACCOUNT_LOCKED))
+        }
+        Err(e) => Err(e),
+    }
+}
@@ -5,8 +5,10 @@ use jelly::Result;
 
 use crate::accounts::forms::NewAccountForm;
 use crate::accounts::jobs::{SendAccountOddRegisterAttemptEmail, SendVerifyAccountEmail};
+use crate::accounts::models::auth_attempt::{AuthAttempt, AuthAttemptKind, RequestLimit};
+use crate::accounts::views::reset_password::MSG_TOO_MANY_REQUESTS;
 use crate::accounts::Account;
-use crate::utils::request_utils;
+use crate::utils::request_utils::{self, ClientIp};
 
 pub async fn form(request: HttpRequest) -> Result<HttpResponse> {
     if request_utils::is_authenticated(&request)? {
@@ -23,6 +25,7 @@ pub async fn form(request: HttpRequest) -> Result<HttpResponse> {
 pub async fn create_account(
     request: HttpRequest,
     form: Form<NewAccountForm>,
+    client_ip: ClientIp,
 ) -> Result<HttpResponse> {
     if request_utils::is_authenticated(&request)? {
         return request.redirect("/settings/profile");
@@ -43,6 +46,25 @@ pub async fn create_account(
     //  - pass requesting user through normal "fake" flow to avoid leaking if
     //      an account exists?
     let db = request.db_pool()?;
+    // Each registration sends an email, so they are limited like password reset requests
+    match AuthAttempt::limit_request(
+        AuthAttemptKind::Registration,
+        &form.email.value,
+        client_ip.0.as_deref(),
+        &db.get()?,
+    )? {
+        RequestLimit::Allowed => {}
+        RequestLimit::EmailLimited => return request.redirect("/accounts/verify/"),
+        RequestLimit::IpLimited => {
+            return request.render(429, "accounts/register.html", {
+                let mut ctx = Context::new();
+                ctx.insert("error", MSG_TOO_MANY_REQUESTS);
+                ctx.insert("form", &form);
+                ctx
+            });
+        }
+    }
+
     match Account::register(&form, db) {
         Ok(uid) => {
             request.queue(SendVerifyAccountEmail { to: uid })?;
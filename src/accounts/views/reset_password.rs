@@ -1,18 +1,25 @@
-use jelly::accounts::User;
+#[allow(unused_imports)]
+use jelly::actix_session::UserSession;
 use jelly::actix_web::web::{Form, Path};
 use jelly::prelude::*;
 use jelly::Result;
 
 use crate::accounts::forms::{ChangePasswordViaEmailForm, EmailForm};
 use crate::accounts::jobs::{SendPasswordWasResetEmail, SendResetPasswordEmail};
+use crate::accounts::models::account_event::{AccountEvent, AccountEventKind};
+use crate::accounts::models::account_session::SessionDevice;
+use crate::accounts::models::auth_attempt::{AuthAttempt, AuthAttemptKind, RequestLimit};
 use crate::accounts::views::utils::validate_token;
 use crate::accounts::Account;
 #[cfg(test)]
 use crate::test::mock::MockHttpRequest as HttpRequest;
-use crate::utils::presenter;
+use crate::utils::request_utils::ClientIp;
+use crate::utils::{presenter, request_utils};
 #[cfg(not(test))]
 use jelly::actix_web::HttpRequest;
 
+pub const MSG_TOO_MANY_REQUESTS: &str = "Too many requests. Try again in an hour.";
+
 /// Just renders a standard "Enter Your Email" password reset page.
 pub async fn form(request: HttpRequest) -> Result<HttpResponse> {
     request.render(200, "accounts/reset_password/index.html", {
@@ -26,7 +33,14 @@ pub async fn form(request: HttpRequest) -> Result<HttpResponse> {
 /// Processes the reset password request, which ultimately just passes
 /// it to a background worker to execute - we do this to avoid any timing
 /// attacks re: leaking user existence.
-pub async fn request_reset(request: HttpRequest, form: Form<EmailForm>) -> Result<HttpResponse> {
+///
+/// Requests past the hourly limit of the email look sent but don't send anything,
+/// so the form can't be used to flood someone's inbox.
+pub async fn request_reset(
+    request: HttpRequest,
+    form: Form<EmailForm>,
+    client_ip: ClientIp,
+) -> Result<HttpResponse> {
     let mut form = form.into_inner();
     if !form.is_valid() {
         return request.render(400, "accounts/reset_password/index.html", {
@@ -37,9 +51,29 @@ pub async fn request_reset(request: HttpRequest, form: Form<EmailForm>) -> Resul
         });
     }
 
-    request.queue(SendResetPasswordEmail {
-        to: form.email.value.clone(),
-    })?;
+    let conn = request.db_pool()?.get()?;
+    match AuthAttempt::limit_request(
+        AuthAttemptKind::PasswordReset,
+        &form.email.value,
+        client_ip.0.as_deref(),
+        &conn,
+    )? {
+        RequestLimit::Allowed => {
+            request.queue(SendResetPasswordEmail {
+                to: form.email.value.clone(),
+            })?;
+        }
+        RequestLimit::EmailLimited => {}
+        RequestLimit::IpLimited => {
+            form.email.errors.push(MSG_TOO_MANY_REQUESTS.to_string());
+            return request.render(429, "accounts/reset_password/index.html", {
+                let mut context = Context::new();
+                context.insert("form", &form);
+                context.insert("sent", &false);
+                context
+            });
+        }
+    }
 
     request.render(200, "accounts/reset_password/requested.html", {
         let mut context = Context::new();
@@ -82,6 +116,7 @@ pub async fn reset(
     request: HttpRequest,
     Path((uidb64, ts, token)): Path<(String, String, String)>,
     form: Form<ChangePasswordViaEmailForm>,
+    device: SessionDevice,
 ) -> Result<HttpResponse> {
     let mut form = form.into_inner();
 
@@ -105,19 +140,31 @@ pub async fn reset(
 
         let pool = request.db_pool()?;
         Account::update_password_and_last_login(account.id, &form.password, pool)?;
+        AccountEvent::record(
+            account.id,
+            AccountEventKind::PasswordReset,
+            &device,
+            &pool.get()?,
+        )?;
         // If they has come this far, assume they have verified their email (or else they won't be able to get to this page at all)
         Account::mark_verified(account.id, pool)?;
+        // A new password also lifts a lockout from failed sign-ins
+        Account::unlock(account.id, pool)?;
 
         request.queue(SendPasswordWasResetEmail {
             to: account.email.clone(),
         })?;
 
-        request.set_user(User {
-            id: account.id,
-            name: account.name,
-            is_admin: account.is_admin,
-            is_anonymous: false,
-        })?;
+        // Sessions started with the old password don't work anymore. Accounts with two-factor
+        // authentication still need a code to sign in.
+        let account = Account::get(account.id, pool)?;
+        request_utils::start_sign_in(
+            &request.get_session(),
+            &account,
+            &device,
+            false,
+            &pool.get()?,
+        )?;
 
         return request.render(200, "accounts/reset_password/success.html", Context::new());
     }
@@ -128,10 +175,14 @@ pub async fn reset(
 #[cfg(test)]
 mod tests {
     use crate::accounts::forms::EmailForm;
+    use crate::accounts::jobs::SendResetPasswordEmail;
+    use crate::accounts::models::auth_attempt::{REQUESTS_PER_EMAIL, REQUESTS_PER_IP};
     use crate::accounts::views::reset_password::request_reset;
-    use crate::test::{mock, DB_POOL};
+    use crate::test::{mock, DatabaseTestContext, DB_POOL};
+    use crate::utils::request_utils::ClientIp;
+    use jelly::actix_web::test::TestRequest;
     use jelly::actix_web::web::Form;
-    use jelly::actix_web::HttpResponse;
+    use jelly::actix_web::{FromRequest, HttpResponse};
     use jelly::email::Context;
     use jelly::forms::EmailField;
 
@@ -153,6 +204,88 @@ mod tests {
                 errors: vec![],
             },
         });
-        request_reset(mock_http_request, form).await.unwrap();
+        request_reset(mock_http_request, form, ClientIp(None))
+            .await
+            .unwrap();
+    }
+
+    fn email_form(email: &str) -> Form<EmailForm> {
+        Form(EmailForm {
+            email: EmailField {
+                value: email.to_string(),
+                errors: vec![],
+            },
+        })
+    }
+
+    fn mock_request(sends: usize, code: usize) -> mock::MockHttpRequest {
+        let mut mock_http_request = mock::MockHttpRequest::new();
+        mock_http_request
+            .expect_db_pool()
+            .returning(|| Ok(&DB_POOL));
+        mock_http_request
+            .expect_queue::<SendResetPasswordEmail>()
+            .times(sends)
+            .returning(|_| Ok(()));
+        mock_http_request
+            .expect_render()
+            .withf(move |c: &usize, _template: &str, _context: &Context| c == &code)
+            .returning(|_, _, _| Ok(HttpResponse::Ok().finish()));
+        mock_http_request
+    }
+
+    #[actix_rt::test]
+    async fn request_reset_stops_sending_emails_after_the_limit() {
+        crate::test::init();
+        let _ctx = DatabaseTestContext::new();
+        let ip = || ClientIp(Some("10.0.0.1".to_string()));
+
+        for _ in 0..REQUESTS_PER_EMAIL {
+            request_reset(mock_request(1, 200), email_form("a@b.com"), ip())
+                .await
+                .unwrap();
+        }
+        // Looks sent, but isn't
+        request_reset(mock_request(0, 200), email_form("a@b.com"), ip())
+            .await
+            .unwrap();
+
+        for i in REQUESTS_PER_EMAIL..REQUESTS_PER_IP {
+            let email = format!("user{}@b.com", i);
+            request_reset(mock_request(1, 200), email_form(&email), ip())
+                .await
+                .unwrap();
+        }
+        request_reset(mock_request(0, 429), email_form("new@b.com"), ip())
+            .await
+            .unwrap();
+    }
+
+    #[actix_rt::test]
+    async fn request_reset_limits_the_peer_address_whatever_it_forwards() {
+        crate::test::init();
+        let _ctx = DatabaseTestContext::new();
+        // Without TRUSTED_PROXIES, a client can't pass as many by making up forwarded addresses
+        let ip = |i: i64| {
+            let request = TestRequest::default()
+                .peer_addr("10.0.0.1:4000".parse().unwrap())
+                .header("X-Forwarded-For", format!("10.1.0.{}", i))
+                .to_http_request();
+            ClientIp::extract(&request).into_inner().unwrap()
+        };
+
+        for i in 0..REQUESTS_PER_IP {
+            let email = format!("user{}@b.com", i);
+            request_reset(mock_request(1, 200), email_form(&email), ip(i))
+                .await
+                .unwrap();
+        }
+        request_reset(
+            mock_request(0, 429),
+            email_form("new@b.com"),
+            ip(REQUESTS_PER_IP),
+        )
+        .await
+        .unwrap();
     }
 }
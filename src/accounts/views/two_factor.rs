@@ -0,0 +1,84 @@
+use jelly::actix_session::UserSession;
+use jelly::actix_web::{web::Form, HttpRequest};
+use jelly::prelude::*;
+use jelly::request::DatabasePool;
+use jelly::Result;
+
+use crate::accounts::forms::{LoginForm, TwoFactorForm};
+use crate::accounts::jobs::SendAccountLockedEmail;
+use crate::accounts::models::account_session::SessionDevice;
+use crate::accounts::models::webauthn_credential::WebauthnCredential;
+use crate::accounts::models::MSG_ACCOUNT_LOCKED;
+use crate::accounts::Account;
+use crate::utils::request_utils;
+
+/// The second step of signing in to accounts with two-factor authentication.
+pub async fn form(request: HttpRequest) -> Result<HttpResponse> {
+    if request_utils::is_authenticated(&request)? {
+        return request.redirect("/settings/profile");
+    }
+    let pending = match request_utils::pending_two_factor(&request.get_session())? {
+        Some(pending) => pending,
+        None => return request.redirect("/accounts/login/"),
+    };
+    let conn = request.db_pool()?.get()?;
+    let has_passkeys = WebauthnCredential::exists_for_account(pending.account_id, &conn)?;
+
+    request.render(200, "accounts/two_factor.html", {
+        let mut ctx = Context::new();
+        ctx.insert("form", &TwoFactorForm::default());
+        ctx.insert("has_passkeys", &has_passkeys);
+        ctx
+    })
+}
+
+/// POST-handler for the code of the second step.
+pub async fn verify(
+    request: HttpRequest,
+    form: Form<TwoFactorForm>,
+    device: SessionDevice,
+) -> Result<HttpResponse> {
+    if request_utils::is_authenticated(&request)? {
+        return request.redirect("/settings/profile");
+    }
+    let session = request.get_session();
+    let pending = match request_utils::pending_two_factor(&session)? {
+        Some(pending) => pending,
+        None => return request.redirect("/accounts/login/"),
+    };
+
+    let db = request.db_pool()?;
+    let mut form = form.into_inner();
+    if form.is_valid() {
+        match request_utils::finish_two_factor_sign_in(&session, &form.code, &device, db) {
+            Ok(Some(sign_in)) => return Ok(sign_in.redirect("/settings/profile")),
+            Ok(None) => {}
+            // Too many wrong codes lock the account
+            Err(Error::AccountLocked) => {
+                request.queue(SendAccountLockedEmail {
+                    to: Account::get(pending.account_id, db)?.email,
+                })?;
+                return request.render(400, "accounts/login.html", {
+                    let mut context = Context::new();
+                    context.insert("error", MSG_ACCOUNT_LOCKED);
+                    context.insert("form", &LoginForm::default());
+                    context
+                });
+            }
+            Err(e) => return Err(e),
+        }
+        // The account got locked from somewhere else
+        if request_utils::pending_two_factor(&session)?.is_none() {
+            return request.redirect("/accounts/login/");
+        }
+    }
+
+    let has_passkeys = WebauthnCredential::exists_for_account(pending.account_id, &db.get()?)?;
+    request.render(400, "accounts/two_factor.html", {
+        let mut context = Context::new();
+        context.insert("error", "Invalid authentication code! Try again.");
+        context.insert("form", &TwoFactorForm::default());
+        context.insert("has_passkeys", &has_passkeys);
+        context
+    })
+}
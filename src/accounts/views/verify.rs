@@ -1,9 +1,11 @@
+use crate::accounts::models::account_session::SessionDevice;
 use crate::accounts::views::utils::validate_token;
 use crate::accounts::Account;
 use crate::package_collaborators::models::external_invitation::ExternalInvitation;
 use crate::package_collaborators::models::owner_invitation::OwnerInvitation;
 #[cfg(test)]
 use crate::test::mock::MockHttpRequest as HttpRequest;
+use crate::utils::request_utils;
 use diesel::result::Error as DBError;
 use jelly::accounts::User;
 #[allow(unused_imports)]
@@ -38,23 +40,19 @@ pub async fn verify(request: HttpRequest) -> Result<HttpResponse> {
 pub async fn with_token(
     request: HttpRequest,
     Path((uidb64, ts, token)): Path<(String, String, String)>,
+    device: SessionDevice,
 ) -> Result<HttpResponse> {
     if let Ok(account) = validate_token(&request, &uidb64, &ts, &token) {
         let db = request.db_pool()?;
         Account::mark_verified(account.id, db)?;
 
-        request.set_user(User {
-            id: account.id,
-            name: account.name,
-            is_admin: account.is_admin,
-            is_anonymous: false,
-        })?;
-
         // Shift all pending invitations to this verified account
         let conn = db.get()?;
+        let sign_in =
+            request_utils::start_sign_in(&request.get_session(), &account, &device, false, &conn)?;
         let pending_invitations = ExternalInvitation::find_by_email(&account.email, &conn)?;
         if pending_invitations.is_empty() {
-            return request.redirect("/settings/profile");
+            return Ok(sign_in.redirect("/settings/profile"));
         } else {
             for invitation in pending_invitations {
                 OwnerInvitation::create(
@@ -67,7 +65,7 @@ pub async fn with_token(
                 )?;
                 invitation.delete(&conn)?;
             }
-            return request.redirect("/settings/invitations");
+            return Ok(sign_in.redirect("/settings/invitations"));
         }
     }
 
@@ -92,6 +90,7 @@ pub async fn callback_github(
     request: HttpRequest,
     params: Query<AuthRequest>,
     client: web::Data<BasicClient>,
+    device: SessionDevice,
 ) -> Result<HttpResponse> {
     return match request.get_session().get::<String>("oauth_state") {
         Ok(Some(state)) if state.eq(&params.state) => {
@@ -122,8 +121,15 @@ pub async fn callback_github(
                         }
                     };
 
-                    request.set_user(user)?;
-                    request.redirect("/settings/profile")
+                    let account = Account::get(user.id, db)?;
+                    let sign_in = request_utils::start_sign_in(
+                        &request.get_session(),
+                        &account,
+                        &device,
+                        false,
+                        &db.get()?,
+                    )?;
+                    Ok(sign_in.redirect("/settings/profile"))
                 }
                 Err(e) => {
                     error!("Error getting information from Github: {:?}", e);
@@ -0,0 +1,519 @@
+//! Server side of WebAuthn (https://www.w3.org/TR/webauthn-2/) for passkeys and security
+//! keys. Only `none` attestation is accepted: credentials are trusted for being registered by
+//! a signed-in user, not for their make.
+use jelly::actix_session::Session;
+use jelly::chrono::{Duration, Utc};
+use jelly::error::Error;
+use jelly::{DieselPgConnection, Result};
+use rand::{rngs::OsRng, RngCore};
+use reqwest::Url;
+use ring::signature::{
+    RsaPublicKeyComponents, UnparsedPublicKey, ECDSA_P256_SHA256_ASN1, ED25519,
+    RSA_PKCS1_2048_8192_SHA256,
+};
+use serde::{Deserialize, Serialize};
+use serde_cbor::Value;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+
+use crate::accounts::models::webauthn_challenge::WebauthnChallenge;
+
+#[cfg(test)]
+pub mod software_authenticator;
+#[cfg(test)]
+mod tests;
+
+// Keys of the pending challenges in the cookie session, and their ceremonies in the database
+pub const REGISTRATION_CHALLENGE_KEY: &str = "webauthn_registration";
+pub const SIGN_IN_CHALLENGE_KEY: &str = "webauthn_sign_in";
+pub const TWO_FACTOR_CHALLENGE_KEY: &str = "webauthn_two_factor";
+const CHALLENGE_LENGTH: usize = 32;
+const CHALLENGE_SECONDS: i64 = 5 * 60;
+const RP_NAME: &str = "Movey";
+
+// COSE algorithm identifiers, https://www.iana.org/assignments/cose/cose.xhtml
+const ES256: i64 = -7;
+const EDDSA: i64 = -8;
+const RS256: i64 = -257;
+
+// Authenticator data flags
+const USER_PRESENT: u8 = 0x01;
+const USER_VERIFIED: u8 = 0x04;
+const ATTESTED_CREDENTIAL_DATA: u8 = 0x40;
+
+fn invalid(message: &str) -> Error {
+    Error::Generic(format!("Invalid WebAuthn credential: {}", message))
+}
+
+/// The site credentials are scoped to: the host of `JELLY_DOMAIN`, and the origin browsers
+/// report in the client data.
+#[derive(Debug, Clone)]
+pub struct RelyingParty {
+    pub id: String,
+    pub origin: String,
+}
+
+impl RelyingParty {
+    pub fn new(domain: &str) -> Result<Self> {
+        let url = Url::parse(domain)
+            .map_err(|e| Error::Generic(format!("Invalid domain {}: {}", domain, e)))?;
+        let id = url
+            .host_str()
+            .ok_or_else(|| Error::Generic(format!("Invalid domain {}: no host", domain)))?
+            .to_string();
+        Ok(RelyingParty {
+            id,
+            origin: url.origin().ascii_serialization(),
+        })
+    }
+
+    pub fn from_env() -> Result<Self> {
+        let domain = std::env::var("JELLY_DOMAIN").expect("JELLY_DOMAIN is not set");
+        Self::new(&domain)
+    }
+}
+
+/// Creates a challenge for a ceremony. It is stored in the database, and the cookie session
+/// keeps it under `key` to tell which one the browser answers.
+pub fn start_ceremony(session: &Session, key: &str, conn: &DieselPgConnection) -> Result<String> {
+    let mut bytes = [0u8; CHALLENGE_LENGTH];
+    OsRng.fill_bytes(&mut bytes);
+    let challenge = base64_url::encode(&bytes);
+    WebauthnChallenge::create(
+        &challenge,
+        key,
+        Utc::now() + Duration::seconds(CHALLENGE_SECONDS),
+        conn,
+    )?;
+    session.set(key, &challenge)?;
+    Ok(challenge)
+}
+
+/// Deletes the challenge of the ceremony, so that it is only answered once, even by a replayed
+/// cookie. `None` if there is none or it expired.
+pub fn take_challenge(
+    session: &Session,
+    key: &str,
+    conn: &DieselPgConnection,
+) -> Result<Option<String>> {
+    let challenge = session.get::<String>(key)?;
+    session.remove(key);
+    match challenge {
+        Some(challenge) if WebauthnChallenge::take(&challenge, key, conn)? => Ok(Some(challenge)),
+        _ => Ok(None),
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct RpEntity {
+    pub id: String,
+    pub name: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserEntity {
+    pub id: String,
+    pub name: String,
+    pub display_name: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PubKeyCredParam {
+    #[serde(rename = "type")]
+    pub type_: &'static str,
+    pub alg: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CredentialDescriptor {
+    #[serde(rename = "type")]
+    pub type_: &'static str,
+    pub id: String,
+}
+
+impl CredentialDescriptor {
+    pub fn new(credential_id: &str) -> Self {
+        CredentialDescriptor {
+            type_: "public-key",
+            id: credential_id.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthenticatorSelection {
+    pub resident_key: &'static str,
+    pub user_verification: &'static str,
+}
+
+/// `PublicKeyCredentialCreationOptions` for `navigator.credentials.create()`, with binary
+/// values base64url encoded.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreationOptions {
+    pub challenge: String,
+    pub rp: RpEntity,
+    pub user: UserEntity,
+    pub pub_key_cred_params: Vec<PubKeyCredParam>,
+    pub timeout: i64,
+    pub attestation: &'static str,
+    pub exclude_credentials: Vec<CredentialDescriptor>,
+    pub authenticator_selection: AuthenticatorSelection,
+}
+
+impl CreationOptions {
+    /// `exclude_credentials` are the ids the account already registered, so that an
+    /// authenticator isn't registered twice.
+    pub fn new(
+        rp: &RelyingParty,
+        challenge: String,
+        account_id: i32,
+        account_email: &str,
+        account_name: &str,
+        exclude_credentials: Vec<CredentialDescriptor>,
+    ) -> Self {
+        CreationOptions {
+            challenge,
+            rp: RpEntity {
+                id: rp.id.clone(),
+                name: RP_NAME.to_string(),
+            },
+            user: UserEntity {
+                id: base64_url::encode(&account_id.to_be_bytes()),
+                name: account_email.to_string(),
+                display_name: if account_name.is_empty() {
+                    account_email.to_string()
+                } else {
+                    account_name.to_string()
+                },
+            },
+            pub_key_cred_params: [ES256, EDDSA, RS256]
+                .iter()
+                .map(|&alg| PubKeyCredParam {
+                    type_: "public-key",
+                    alg,
+                })
+                .collect(),
+            timeout: CHALLENGE_SECONDS * 1000,
+            attestation: "none",
+            exclude_credentials,
+            authenticator_selection: AuthenticatorSelection {
+                resident_key: "preferred",
+                user_verification: "preferred",
+            },
+        }
+    }
+}
+
+/// `PublicKeyCredentialRequestOptions` for `navigator.credentials.get()`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RequestOptions {
+    pub challenge: String,
+    pub rp_id: String,
+    pub timeout: i64,
+    pub allow_credentials: Vec<CredentialDescriptor>,
+    pub user_verification: &'static str,
+}
+
+impl RequestOptions {
+    /// Passkeys are found by the browser when `allow_credentials` is empty. Signing in without
+    /// a password needs user verification, a second factor doesn't.
+    pub fn new(
+        rp: &RelyingParty,
+        challenge: String,
+        allow_credentials: Vec<CredentialDescriptor>,
+        require_user_verification: bool,
+    ) -> Self {
+        RequestOptions {
+            challenge,
+            rp_id: rp.id.clone(),
+            timeout: CHALLENGE_SECONDS * 1000,
+            allow_credentials,
+            user_verification: if require_user_verification {
+                "required"
+            } else {
+                "discouraged"
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AttestationResponse {
+    #[serde(rename = "clientDataJSON")]
+    pub client_data_json: String,
+    pub attestation_object: String,
+}
+
+/// The credential `navigator.credentials.create()` resolves to, base64url encoded.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegistrationCredential {
+    pub id: String,
+    pub response: AttestationResponse,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AssertionResponse {
+    #[serde(rename = "clientDataJSON")]
+    pub client_data_json: String,
+    pub authenticator_data: String,
+    pub signature: String,
+    #[serde(default)]
+    pub user_handle: Option<String>,
+}
+
+/// The credential `navigator.credentials.get()` resolves to, base64url encoded.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AssertionCredential {
+    pub id: String,
+    pub response: AssertionResponse,
+}
+
+/// A new credential that passed `verify_registration`.
+#[derive(Debug)]
+pub struct VerifiedCredential {
+    /// base64url encoded
+    pub credential_id: String,
+    /// COSE encoded
+    pub public_key: Vec<u8>,
+    pub sign_count: u32,
+}
+
+fn decode(value: &str, name: &str) -> Result<Vec<u8>> {
+    base64_url::decode(value).map_err(|_| invalid(&format!("{} is not base64url", name)))
+}
+
+#[derive(Deserialize)]
+struct ClientData {
+    #[serde(rename = "type")]
+    type_: String,
+    challenge: String,
+    origin: String,
+}
+
+/// Checks the client data against the ceremony, and returns its hash, which is part of what
+/// the authenticator signs.
+fn verify_client_data(
+    rp: &RelyingParty,
+    challenge: &str,
+    client_data_json: &str,
+    expected_type: &str,
+) -> Result<Vec<u8>> {
+    let client_data_json = decode(client_data_json, "clientDataJSON")?;
+    let client_data: ClientData = serde_json::from_slice(&client_data_json)
+        .map_err(|_| invalid("clientDataJSON is not valid"))?;
+    if client_data.type_ != expected_type {
+        return Err(invalid("wrong ceremony type"));
+    }
+    if decode(&client_data.challenge, "challenge")? != decode(challenge, "challenge")? {
+        return Err(invalid("wrong challenge"));
+    }
+    if client_data.origin != rp.origin {
+        return Err(invalid("wrong origin"));
+    }
+    Ok(Sha256::digest(&client_data_json).to_vec())
+}
+
+struct AuthenticatorData<'a> {
+    flags: u8,
+    sign_count: u32,
+    // What follows the fixed length part: the attested credential data and extensions
+    rest: &'a [u8],
+}
+
+fn parse_authenticator_data<'a>(
+    rp: &RelyingParty,
+    data: &'a [u8],
+    require_user_verification: bool,
+) -> Result<AuthenticatorData<'a>> {
+    if data.len() < 37 {
+        return Err(invalid("authenticator data is too short"));
+    }
+    if data[..32] != Sha256::digest(rp.id.as_bytes())[..] {
+        return Err(invalid("wrong relying party"));
+    }
+    let flags = data[32];
+    if flags & USER_PRESENT == 0 {
+        return Err(invalid("the user was not present"));
+    }
+    if require_user_verification && flags & USER_VERIFIED == 0 {
+        return Err(invalid("the user was not verified"));
+    }
+    Ok(AuthenticatorData {
+        flags,
+        sign_count: u32::from_be_bytes([data[33], data[34], data[35], data[36]]),
+        rest: &data[37..],
+    })
+}
+
+/// The public key of a credential, from its COSE encoding (RFC 8152).
+#[derive(Debug)]
+pub enum CosePublicKey {
+    /// The uncompressed P-256 point
+    Es256(Vec<u8>),
+    EdDsa(Vec<u8>),
+    Rs256 {
+        n: Vec<u8>,
+        e: Vec<u8>,
+    },
+}
+
+fn cose_bytes(key: &BTreeMap<Value, Value>, label: i128) -> Result<Vec<u8>> {
+    match key.get(&Value::Integer(label)) {
+        Some(Value::Bytes(bytes)) => Ok(bytes.clone()),
+        _ => Err(invalid("incomplete public key")),
+    }
+}
+
+fn cose_integer(key: &BTreeMap<Value, Value>, label: i128) -> Option<i128> {
+    match key.get(&Value::Integer(label)) {
+        Some(Value::Integer(value)) => Some(*value),
+        _ => None,
+    }
+}
+
+impl CosePublicKey {
+    pub fn from_cose(bytes: &[u8]) -> Result<Self> {
+        let key = match serde_cbor::from_slice(bytes) {
+            Ok(Value::Map(key)) => key,
+            _ => return Err(invalid("public key is not a COSE key")),
+        };
+        // kty is label 1, alg 3, and the key parameters negative labels
+        match (cose_integer(&key, 1), cose_integer(&key, 3)) {
+            (Some(2), Some(alg)) if alg == ES256 as i128 => {
+                if cose_integer(&key, -1) != Some(1) {
+                    return Err(invalid("unsupported curve"));
+                }
+                let (x, y) = (cose_bytes(&key, -2)?, cose_bytes(&key, -3)?);
+                if x.len() != 32 || y.len() != 32 {
+                    return Err(invalid("incomplete public key"));
+                }
+                let mut point = vec![0x04];
+                point.extend(x);
+                point.extend(y);
+                Ok(CosePublicKey::Es256(point))
+            }
+            (Some(1), Some(alg)) if alg == EDDSA as i128 => {
+                if cose_integer(&key, -1) != Some(6) {
+                    return Err(invalid("unsupported curve"));
+                }
+                Ok(CosePublicKey::EdDsa(cose_bytes(&key, -2)?))
+            }
+            (Some(3), Some(alg)) if alg == RS256 as i128 => Ok(CosePublicKey::Rs256 {
+                n: cose_bytes(&key, -1)?,
+                e: cose_bytes(&key, -2)?,
+            }),
+            _ => Err(invalid("unsupported public key algorithm")),
+        }
+    }
+
+    pub fn verify(&self, message: &[u8], signature: &[u8]) -> bool {
+        match self {
+            CosePublicKey::Es256(point) => UnparsedPublicKey::new(&ECDSA_P256_SHA256_ASN1, point)
+                .verify(message, signature)
+                .is_ok(),
+            CosePublicKey::EdDsa(key) => UnparsedPublicKey::new(&ED25519, key)
+                .verify(message, signature)
+                .is_ok(),
+            CosePublicKey::Rs256 { n, e } => RsaPublicKeyComponents { n, e }
+                .verify(&RSA_PKCS1_2048_8192_SHA256, message, signature)
+                .is_ok(),
+        }
+    }
+}
+
+/// Checks the response of the authenticator to `CreationOptions`, and returns the credential
+/// to store.
+pub fn verify_registration(
+    rp: &RelyingParty,
+    challenge: &str,
+    credential: &RegistrationCredential,
+) -> Result<VerifiedCredential> {
+    verify_client_data(
+        rp,
+        challenge,
+        &credential.response.client_data_json,
+        "webauthn.create",
+    )?;
+
+    let attestation_object = decode(&credential.response.attestation_object, "attestationObject")?;
+    let attestation = match serde_cbor::from_slice(&attestation_object) {
+        Ok(Value::Map(attestation)) => attestation,
+        _ => return Err(invalid("attestationObject is not valid")),
+    };
+    match attestation.get(&Value::Text("fmt".to_string())) {
+        Some(Value::Text(fmt)) if fmt == "none" => {}
+        _ => return Err(invalid("unsupported attestation format")),
+    }
+    let auth_data = match attestation.get(&Value::Text("authData".to_string())) {
+        Some(Value::Bytes(auth_data)) => auth_data,
+        _ => return Err(invalid("attestationObject has no authenticator data")),
+    };
+
+    let auth_data = parse_authenticator_data(rp, auth_data, false)?;
+    if auth_data.flags & ATTESTED_CREDENTIAL_DATA == 0 || auth_data.rest.len() < 18 {
+        return Err(invalid("no attested credential"));
+    }
+    // The AAGUID of the authenticator model comes first, then the length of the id
+    let id_length = u16::from_be_bytes([auth_data.rest[16], auth_data.rest[17]]) as usize;
+    let credential_data = &auth_data.rest[18..];
+    if credential_data.len() < id_length {
+        return Err(invalid("incomplete credential id"));
+    }
+    let (credential_id, public_key) = credential_data.split_at(id_length);
+    if credential_id != &decode(&credential.id, "id")?[..] {
+        return Err(invalid("the credential id doesn't match"));
+    }
+
+    // The COSE key may be followed by extensions, its length is only known once parsed
+    let mut deserializer = serde_cbor::Deserializer::from_slice(public_key);
+    Value::deserialize(&mut deserializer).map_err(|_| invalid("public key is not valid"))?;
+    let public_key = &public_key[..deserializer.byte_offset()];
+    CosePublicKey::from_cose(public_key)?;
+
+    Ok(VerifiedCredential {
+        credential_id: base64_url::encode(credential_id),
+        public_key: public_key.to_vec(),
+        sign_count: auth_data.sign_count,
+    })
+}
+
+/// Checks the response of the authenticator to `RequestOptions` with the stored public key
+/// and signature counter, and returns the new counter.
+pub fn verify_assertion(
+    rp: &RelyingParty,
+    challenge: &str,
+    credential: &AssertionCredential,
+    public_key: &[u8],
+    stored_sign_count: i64,
+    require_user_verification: bool,
+) -> Result<u32> {
+    let client_data_hash = verify_client_data(
+        rp,
+        challenge,
+        &credential.response.client_data_json,
+        "webauthn.get",
+    )?;
+    let authenticator_data = decode(&credential.response.authenticator_data, "authenticatorData")?;
+    let auth_data = parse_authenticator_data(rp, &authenticator_data, require_user_verification)?;
+
+    let mut message = authenticator_data.clone();
+    message.extend(client_data_hash);
+    let signature = decode(&credential.response.signature, "signature")?;
+    if !CosePublicKey::from_cose(public_key)?.verify(&message, &signature) {
+        return Err(invalid("wrong signature"));
+    }
+
+    // Authenticators that count signatures never go back, unless they were cloned.
+    // Passkeys synced between devices always report 0.
+    let sign_count = auth_data.sign_count;
+    if (sign_count != 0 || stored_sign_count != 0) && i64::from(sign_count) <= stored_sign_count {
+        return Err(invalid("the signature counter went back"));
+    }
+    Ok(sign_count)
+}
@@ -0,0 +1,176 @@
+//! An authenticator in software, to answer WebAuthn ceremonies in tests like a browser with a
+//! security key would.
+use ring::rand::SystemRandom;
+use ring::signature::{EcdsaKeyPair, Ed25519KeyPair, KeyPair, ECDSA_P256_SHA256_ASN1_SIGNING};
+use serde_cbor::Value;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+
+use crate::accounts::webauthn::{
+    AssertionCredential, AssertionResponse, AttestationResponse, RegistrationCredential,
+    RelyingParty,
+};
+
+enum Key {
+    Es256(EcdsaKeyPair),
+    EdDsa(Ed25519KeyPair),
+}
+
+pub struct SoftwareAuthenticator {
+    key: Key,
+    pub credential_id: Vec<u8>,
+    pub sign_count: u32,
+    /// Passkeys synced between devices don't count signatures
+    pub counts_signatures: bool,
+    /// Whether the user is verified, with a PIN or biometrics, when signing
+    pub user_verified: bool,
+}
+
+impl SoftwareAuthenticator {
+    fn new(key: Key) -> Self {
+        let credential_id: [u8; 16] = rand::random();
+        SoftwareAuthenticator {
+            key,
+            credential_id: credential_id.to_vec(),
+            sign_count: 0,
+            counts_signatures: true,
+            user_verified: true,
+        }
+    }
+
+    pub fn es256() -> Self {
+        let rng = SystemRandom::new();
+        let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, &rng).unwrap();
+        let key =
+            EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, pkcs8.as_ref()).unwrap();
+        Self::new(Key::Es256(key))
+    }
+
+    pub fn ed25519() -> Self {
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+        Self::new(Key::EdDsa(
+            Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap(),
+        ))
+    }
+
+    pub fn credential_id(&self) -> String {
+        base64_url::encode(&self.credential_id)
+    }
+
+    fn cose_public_key(&self) -> Vec<u8> {
+        let mut key = BTreeMap::new();
+        match &self.key {
+            Key::Es256(pair) => {
+                let point = pair.public_key().as_ref();
+                key.insert(Value::Integer(1), Value::Integer(2));
+                key.insert(Value::Integer(3), Value::Integer(-7));
+                key.insert(Value::Integer(-1), Value::Integer(1));
+                key.insert(Value::Integer(-2), Value::Bytes(point[1..33].to_vec()));
+                key.insert(Value::Integer(-3), Value::Bytes(point[33..].to_vec()));
+            }
+            Key::EdDsa(pair) => {
+                key.insert(Value::Integer(1), Value::Integer(1));
+                key.insert(Value::Integer(3), Value::Integer(-8));
+                key.insert(Value::Integer(-1), Value::Integer(6));
+                key.insert(
+                    Value::Integer(-2),
+                    Value::Bytes(pair.public_key().as_ref().to_vec()),
+                );
+            }
+        }
+        serde_cbor::to_vec(&Value::Map(key)).unwrap()
+    }
+
+    fn authenticator_data(&self, rp: &RelyingParty, attested: bool) -> Vec<u8> {
+        let mut flags = 0x01;
+        if self.user_verified {
+            flags |= 0x04;
+        }
+        if attested {
+            flags |= 0x40;
+        }
+        let mut data = Sha256::digest(rp.id.as_bytes()).to_vec();
+        data.push(flags);
+        data.extend(&self.sign_count.to_be_bytes());
+        if attested {
+            data.extend(&[0u8; 16]);
+            data.extend(&(self.credential_id.len() as u16).to_be_bytes());
+            data.extend(&self.credential_id);
+            data.extend(self.cose_public_key());
+        }
+        data
+    }
+
+    fn client_data_json(rp: &RelyingParty, challenge: &str, type_: &str) -> Vec<u8> {
+        serde_json::to_vec(&serde_json::json!({
+            "type": type_,
+            "challenge": challenge,
+            "origin": rp.origin,
+            "crossOrigin": false,
+        }))
+        .unwrap()
+    }
+
+    fn sign(&self, message: &[u8]) -> Vec<u8> {
+        match &self.key {
+            Key::Es256(pair) => pair
+                .sign(&SystemRandom::new(), message)
+                .unwrap()
+                .as_ref()
+                .to_vec(),
+            Key::EdDsa(pair) => pair.sign(message).as_ref().to_vec(),
+        }
+    }
+
+    /// Answers `navigator.credentials.create()` with `none` attestation.
+    pub fn register(&self, rp: &RelyingParty, challenge: &str) -> RegistrationCredential {
+        let mut attestation = BTreeMap::new();
+        attestation.insert(
+            Value::Text("fmt".to_string()),
+            Value::Text("none".to_string()),
+        );
+        attestation.insert(
+            Value::Text("attStmt".to_string()),
+            Value::Map(BTreeMap::new()),
+        );
+        attestation.insert(
+            Value::Text("authData".to_string()),
+            Value::Bytes(self.authenticator_data(rp, true)),
+        );
+
+        RegistrationCredential {
+            id: self.credential_id(),
+            response: AttestationResponse {
+                client_data_json: base64_url::encode(&Self::client_data_json(
+                    rp,
+                    challenge,
+                    "webauthn.create",
+                )),
+                attestation_object: base64_url::encode(
+                    &serde_cbor::to_vec(&Value::Map(attestation)).unwrap(),
+                ),
+            },
+        }
+    }
+
+    /// Answers `navigator.credentials.get()`, counting the signature.
+    pub fn assert(&mut self, rp: &RelyingParty, challenge: &str) -> AssertionCredential {
+        if self.counts_signatures {
+            self.sign_count += 1;
+        }
+        let authenticator_data = self.authenticator_data(rp, false);
+        let client_data_json = Self::client_data_json(rp, challenge, "webauthn.get");
+        let mut message = authenticator_data.clone();
+        message.extend(Sha256::digest(&client_data_json));
+
+        AssertionCredential {
+            id: self.credential_id(),
+            response: AssertionResponse {
+                client_data_json: base64_url::encode(&client_data_json),
+                authenticator_data: base64_url::encode(&authenticator_data),
+                signature: base64_url::encode(&self.sign(&message)),
+                user_handle: None,
+            },
+        }
+    }
+}
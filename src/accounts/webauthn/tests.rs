@@ -0,0 +1,201 @@
+use crate::accounts::webauthn::software_authenticator::SoftwareAuthenticator;
+use crate::accounts::webauthn::*;
+use crate::schema::webauthn_challenges;
+use crate::test::{DatabaseTestContext, DB_POOL};
+use diesel::prelude::*;
+use jelly::actix_session::UserSession;
+use jelly::actix_web::test::TestRequest;
+use jelly::chrono::{Duration, Utc};
+
+fn rp() -> RelyingParty {
+    RelyingParty::new("http://127.0.0.1:17001").unwrap()
+}
+
+fn challenge() -> String {
+    base64_url::encode(&[7u8; 32])
+}
+
+#[actix_rt::test]
+async fn relying_party_comes_from_the_domain() {
+    let movey = RelyingParty::new("https://www.movey.net/").unwrap();
+    assert_eq!(movey.id, "www.movey.net");
+    assert_eq!(movey.origin, "https://www.movey.net");
+    assert_eq!(rp().origin, "http://127.0.0.1:17001");
+    assert!(RelyingParty::new("not a url").is_err());
+}
+
+#[actix_rt::test]
+async fn registration_and_assertion_work() {
+    for mut authenticator in [
+        SoftwareAuthenticator::es256(),
+        SoftwareAuthenticator::ed25519(),
+    ] {
+        let registration = authenticator.register(&rp(), &challenge());
+        let verified = verify_registration(&rp(), &challenge(), &registration).unwrap();
+        assert_eq!(verified.credential_id, authenticator.credential_id());
+        assert_eq!(verified.sign_count, 0);
+
+        let assertion = authenticator.assert(&rp(), &challenge());
+        let sign_count = verify_assertion(
+            &rp(),
+            &challenge(),
+            &assertion,
+            &verified.public_key,
+            0,
+            true,
+        )
+        .unwrap();
+        assert_eq!(sign_count, 1);
+    }
+}
+
+#[actix_rt::test]
+async fn registration_is_bound_to_the_ceremony() {
+    let authenticator = SoftwareAuthenticator::es256();
+    let registration = authenticator.register(&rp(), &challenge());
+
+    let other_challenge = base64_url::encode(&[8u8; 32]);
+    assert!(verify_registration(&rp(), &other_challenge, &registration).is_err());
+
+    // same site id, another origin
+    let other_origin = RelyingParty {
+        origin: "http://127.0.0.1:17002".to_string(),
+        ..rp()
+    };
+    assert!(verify_registration(&other_origin, &challenge(), &registration).is_err());
+
+    let other_site = RelyingParty::new("https://evil.example").unwrap();
+    let registration = authenticator.register(&other_site, &challenge());
+    assert!(verify_registration(&rp(), &challenge(), &registration).is_err());
+
+    // an assertion isn't a registration
+    let mut authenticator = SoftwareAuthenticator::es256();
+    let assertion = authenticator.assert(&rp(), &challenge());
+    let registration = RegistrationCredential {
+        id: assertion.id,
+        response: AttestationResponse {
+            client_data_json: assertion.response.client_data_json,
+            attestation_object: assertion.response.authenticator_data,
+        },
+    };
+    assert!(verify_registration(&rp(), &challenge(), &registration).is_err());
+}
+
+#[actix_rt::test]
+async fn assertion_rejects_wrong_signatures() {
+    let mut authenticator = SoftwareAuthenticator::es256();
+    let public_key = verify_registration(
+        &rp(),
+        &challenge(),
+        &authenticator.register(&rp(), &challenge()),
+    )
+    .unwrap()
+    .public_key;
+    let other_public_key = {
+        let other = SoftwareAuthenticator::es256();
+        verify_registration(&rp(), &challenge(), &other.register(&rp(), &challenge()))
+            .unwrap()
+            .public_key
+    };
+
+    let assertion = authenticator.assert(&rp(), &challenge());
+    assert!(verify_assertion(&rp(), &challenge(), &assertion, &other_public_key, 0, true).is_err());
+
+    let mut tampered = assertion.clone();
+    tampered.response.signature = base64_url::encode(&[0u8; 70]);
+    assert!(verify_assertion(&rp(), &challenge(), &tampered, &public_key, 0, true).is_err());
+
+    let other_challenge = base64_url::encode(&[8u8; 32]);
+    assert!(verify_assertion(&rp(), &other_challenge, &assertion, &public_key, 0, true).is_err());
+
+    assert!(verify_assertion(&rp(), &challenge(), &assertion, &public_key, 0, true).is_ok());
+}
+
+#[actix_rt::test]
+async fn assertion_checks_user_verification_and_counter() {
+    let mut authenticator = SoftwareAuthenticator::ed25519();
+    let public_key = verify_registration(
+        &rp(),
+        &challenge(),
+        &authenticator.register(&rp(), &challenge()),
+    )
+    .unwrap()
+    .public_key;
+
+    authenticator.user_verified = false;
+    let assertion = authenticator.assert(&rp(), &challenge());
+    assert!(verify_assertion(&rp(), &challenge(), &assertion, &public_key, 0, true).is_err());
+    // enough for a second factor
+    assert!(verify_assertion(&rp(), &challenge(), &assertion, &public_key, 0, false).is_ok());
+
+    // a counter that doesn't increase means a cloned authenticator
+    assert!(verify_assertion(&rp(), &challenge(), &assertion, &public_key, 1, false).is_err());
+
+    // authenticators without a counter always report 0
+    let mut authenticator = SoftwareAuthenticator::ed25519();
+    authenticator.counts_signatures = false;
+    let public_key = verify_registration(
+        &rp(),
+        &challenge(),
+        &authenticator.register(&rp(), &challenge()),
+    )
+    .unwrap()
+    .public_key;
+    let assertion = authenticator.assert(&rp(), &challenge());
+    assert!(verify_assertion(&rp(), &challenge(), &assertion, &public_key, 0, false).is_ok());
+    assert!(verify_assertion(&rp(), &challenge(), &assertion, &public_key, 0, false).is_ok());
+}
+
+#[actix_rt::test]
+async fn challenges_can_only_be_taken_once() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+    let conn = DB_POOL.get().unwrap();
+    let request = TestRequest::default()
+        .app_data(DB_POOL.clone())
+        .to_http_request();
+    let session = request.get_session();
+
+    assert!(take_challenge(&session, SIGN_IN_CHALLENGE_KEY, &conn)
+        .unwrap()
+        .is_none());
+    let challenge = start_ceremony(&session, SIGN_IN_CHALLENGE_KEY, &conn).unwrap();
+    assert_eq!(decode(&challenge, "challenge").unwrap().len(), 32);
+    // each ceremony has its own challenge
+    assert!(take_challenge(&session, REGISTRATION_CHALLENGE_KEY, &conn)
+        .unwrap()
+        .is_none());
+    assert_eq!(
+        take_challenge(&session, SIGN_IN_CHALLENGE_KEY, &conn).unwrap(),
+        Some(challenge.clone())
+    );
+    assert!(take_challenge(&session, SIGN_IN_CHALLENGE_KEY, &conn)
+        .unwrap()
+        .is_none());
+
+    // a replayed cookie still names the challenge, but it is gone
+    session.set(SIGN_IN_CHALLENGE_KEY, &challenge).unwrap();
+    assert!(take_challenge(&session, SIGN_IN_CHALLENGE_KEY, &conn)
+        .unwrap()
+        .is_none());
+}
+
+#[actix_rt::test]
+async fn expired_challenges_cannot_be_taken() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+    let conn = DB_POOL.get().unwrap();
+    let request = TestRequest::default()
+        .app_data(DB_POOL.clone())
+        .to_http_request();
+    let session = request.get_session();
+
+    start_ceremony(&session, SIGN_IN_CHALLENGE_KEY, &conn).unwrap();
+    diesel::update(webauthn_challenges::table)
+        .set(webauthn_challenges::expires_at.eq(Utc::now() - Duration::seconds(1)))
+        .execute(&conn)
+        .unwrap();
+    assert!(take_challenge(&session, SIGN_IN_CHALLENGE_KEY, &conn)
+        .unwrap()
+        .is_none());
+}
@@ -0,0 +1,192 @@
+//! Extractors that authenticate API requests with an `Authorization: Bearer <token>`
+//! header, so that API endpoints can be used from scripts and CI pipelines.
+
+use futures::future::{ready, Ready};
+use jelly::actix_web::dev::Payload;
+use jelly::actix_web::error::InternalError;
+use jelly::actix_web::http::header;
+use jelly::actix_web::{Error as ActixError, FromRequest, HttpRequest};
+use jelly::prelude::Error::*;
+use jelly::prelude::*;
+use jelly::request::client::bearer_token;
+use jelly::utils::error_constants::*;
+use jelly::{DieselPgPool, Result};
+
+use crate::accounts::Account;
+use crate::packages::models::package_event::EventActor;
+use crate::settings::models::token::{ApiToken, EndpointScope};
+use crate::utils::request_utils;
+use crate::utils::request_utils::ClientIp;
+
+#[cfg(test)]
+mod tests;
+
+/// The account of an API request, authenticated by a bearer token or, when the request
+/// has no `Authorization` header, by the session cookie.
+pub struct ApiAccount {
+    pub account: Account,
+    /// `None` when authenticated by the session, which isn't restricted by token scopes.
+    pub token: Option<ApiToken>,
+}
+
+impl ApiAccount {
+    /// Checks that the token allows `scope` on the package with the given name and slug.
+    pub fn authorize(
+        &self,
+        scope: EndpointScope,
+        package_name: &str,
+        package_slug: &str,
+    ) -> Result<()> {
+        self.authorize_scope(scope)?;
+        let token = match &self.token {
+            Some(token) => token,
+            None => return Ok(()),
+        };
+        if !token.allows_package(package_name, package_slug) {
+            return Err(ApiForbidden(
+                MSG_API_TOKEN_PACKAGE_NOT_ALLOWED,
+                Box::new(Error::Generic(format!(
+                    "Token isn't allowed for package {}. token id: {}",
+                    package_slug, token.id
+                ))),
+            ));
+        }
+        Ok(())
+    }
+
+    /// The actor recorded in the audit log of the packages this request acts on.
+    pub fn event_actor(&self, client_ip: ClientIp) -> EventActor {
+        EventActor {
+            account_id: Some(self.account.id),
+            api_token_id: self.token.as_ref().map(|token| token.id),
+            ip_address: client_ip.0,
+            system_actor: None,
+        }
+    }
+
+    /// Checks that the token allows `scope`, for endpoints that don't act on a package.
+    pub fn authorize_scope(&self, scope: EndpointScope) -> Result<()> {
+        let token = match &self.token {
+            Some(token) => token,
+            None => return Ok(()),
+        };
+        if !token.has_endpoint_scope(scope) {
+            return Err(ApiForbidden(
+                MSG_API_TOKEN_SCOPE_NOT_ALLOWED,
+                Box::new(Error::Generic(format!(
+                    "Token doesn't have the {} scope. token id: {}",
+                    scope.as_str(),
+                    token.id
+                ))),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// The account of a bearer token, if the request has one. Used by endpoints that still
+/// accept the API token in the request body.
+pub struct BearerAccount(pub Option<ApiAccount>);
+
+impl BearerAccount {
+    /// Falls back to the API token sent in the request body.
+    pub fn or_token(self, token: &str, request: &impl DatabasePool) -> Result<ApiAccount> {
+        match self.0 {
+            Some(api_account) => Ok(api_account),
+            None => {
+                let db = request.db_pool().map_err(|e| ApiServerError(Box::new(e)))?;
+                authenticate_token(token, db)
+            }
+        }
+    }
+}
+
+/// Returns the account of a plaintext API token, rejecting expired tokens.
+pub fn authenticate_token(plaintext_token: &str, db: &DieselPgPool) -> Result<ApiAccount> {
+    let token = ApiToken::get_by_plaintext(plaintext_token, db)
+        .map_err(|e| ApiUnauthorized(MSG_INVALID_API_TOKEN, Box::new(e)))?;
+    if token.is_expired() {
+        return Err(ApiUnauthorized(
+            MSG_API_TOKEN_EXPIRED,
+            Box::new(Error::Generic(format!(
+                "API token has expired. token id: {}",
+                token.id
+            ))),
+        ));
+    }
+    let account = ApiToken::associated_account(plaintext_token, db)
+        .map_err(|e| ApiUnauthorized(MSG_INVALID_API_TOKEN, Box::new(e)))?;
+    Ok(ApiAccount {
+        account,
+        token: Some(token),
+    })
+}
+
+/// Returns the id of a bearer token that authenticates, so that `RateLimitKey::ApiToken`
+/// counts the requests of each token and not each string sent as one.
+pub fn api_token_id(request: &HttpRequest, plaintext_token: &str) -> Option<i32> {
+    let db = request.db_pool().ok()?;
+    let token = ApiToken::get_by_plaintext(plaintext_token, db).ok()?;
+    (!token.is_expired()).then_some(token.id)
+}
+
+fn authenticate_bearer(request: &HttpRequest) -> Result<Option<ApiAccount>> {
+    let token = match bearer_token(request.headers()) {
+        Some(token) => token,
+        None => {
+            if request.headers().contains_key(header::AUTHORIZATION) {
+                return Err(ApiUnauthorized(
+                    MSG_INVALID_API_TOKEN,
+                    Box::new(Error::Generic(String::from(
+                        "Authorization header is not a bearer token.",
+                    ))),
+                ));
+            }
+            return Ok(None);
+        }
+    };
+    let db = request.db_pool().map_err(|e| ApiServerError(Box::new(e)))?;
+    Ok(Some(authenticate_token(token, db)?))
+}
+
+impl FromRequest for BearerAccount {
+    type Error = jelly::error::Error;
+    type Future = Ready<Result<Self>>;
+    type Config = ();
+
+    fn from_request(request: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(authenticate_bearer(request).map(BearerAccount))
+    }
+}
+
+impl FromRequest for ApiAccount {
+    type Error = ActixError;
+    type Future = Ready<std::result::Result<Self, ActixError>>;
+    type Config = ();
+
+    fn from_request(request: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(match authenticate_bearer(request) {
+            Ok(Some(api_account)) => Ok(api_account),
+            Ok(None) => authenticate_session(request),
+            Err(e) => Err(e.into()),
+        })
+    }
+}
+
+fn authenticate_session(request: &HttpRequest) -> std::result::Result<ApiAccount, ActixError> {
+    if !request_utils::is_authenticated(request)? {
+        return Err(InternalError::from_response(
+            MSG_UNAUTHORIZED,
+            request_utils::clear_cookie(request),
+        )
+        .into());
+    }
+    let user = request.user().map_err(|e| ApiServerError(Box::new(e)))?;
+    let db = request.db_pool().map_err(|e| ApiServerError(Box::new(e)))?;
+    let account =
+        Account::get(user.id, db).map_err(|e| ApiUnauthorized(MSG_UNAUTHORIZED, Box::new(e)))?;
+    Ok(ApiAccount {
+        account,
+        token: None,
+    })
+}
@@ -0,0 +1,232 @@
+use super::*;
+use crate::settings::models::token::ApiTokenScopes;
+use crate::test::util::setup_user;
+use crate::test::{DatabaseTestContext, DB_POOL};
+
+use jelly::actix_web::http::{HeaderMap, HeaderValue, StatusCode};
+use jelly::actix_web::test::TestRequest;
+use jelly::chrono::{Duration, Utc};
+
+fn create_token(uid: i32) -> String {
+    create_scoped_token(uid, ApiTokenScopes::default())
+}
+
+fn create_scoped_token(uid: i32, scopes: ApiTokenScopes) -> String {
+    let account = Account::get(uid, &DB_POOL).unwrap();
+    ApiToken::insert_with_scopes(&account, "test_key", &scopes, &DB_POOL)
+        .unwrap()
+        .plaintext
+}
+
+fn request_with_authorization(authorization: &str) -> HttpRequest {
+    TestRequest::default()
+        .app_data(DB_POOL.clone())
+        .header(header::AUTHORIZATION, authorization)
+        .to_http_request()
+}
+
+#[test]
+fn bearer_token_works() {
+    let mut headers = HeaderMap::new();
+    assert_eq!(bearer_token(&headers), None);
+
+    headers.insert(
+        header::AUTHORIZATION,
+        HeaderValue::from_static("Bearer some-token"),
+    );
+    assert_eq!(bearer_token(&headers), Some("some-token"));
+    headers.insert(
+        header::AUTHORIZATION,
+        HeaderValue::from_static("bearer  some-token "),
+    );
+    assert_eq!(bearer_token(&headers), Some("some-token"));
+    headers.insert(
+        header::AUTHORIZATION,
+        HeaderValue::from_static("Basic dXNlcjpwYXNz"),
+    );
+    assert_eq!(bearer_token(&headers), None);
+    headers.insert(header::AUTHORIZATION, HeaderValue::from_static("Bearer "));
+    assert_eq!(bearer_token(&headers), None);
+}
+
+#[actix_rt::test]
+async fn bearer_account_works() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let uid = setup_user(None, None);
+    let token = create_token(uid);
+
+    let request = request_with_authorization(&format!("Bearer {}", token));
+    let bearer = BearerAccount::extract(&request).await.unwrap();
+    assert_eq!(bearer.0.unwrap().account.id, uid);
+    let api_token = ApiToken::get_by_account(uid, &DB_POOL).unwrap();
+    assert!(api_token[0].last_used_at.is_some());
+
+    let request = TestRequest::default()
+        .app_data(DB_POOL.clone())
+        .to_http_request();
+    let bearer = BearerAccount::extract(&request).await.unwrap();
+    assert!(bearer.0.is_none());
+}
+
+#[actix_rt::test]
+async fn bearer_account_returns_error_with_invalid_token() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let request = request_with_authorization("Bearer invalid-token");
+    let result = BearerAccount::extract(&request).await;
+    assert!(matches!(result, Err(Error::ApiUnauthorized(..))));
+
+    let request = request_with_authorization("Basic dXNlcjpwYXNz");
+    let result = BearerAccount::extract(&request).await;
+    assert!(matches!(result, Err(Error::ApiUnauthorized(..))));
+}
+
+#[actix_rt::test]
+async fn bearer_account_falls_back_to_token_in_body() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let uid = setup_user(None, None);
+    let token = create_token(uid);
+    let request = TestRequest::default()
+        .app_data(DB_POOL.clone())
+        .to_http_request();
+
+    let api_account = BearerAccount(None).or_token(&token, &request).unwrap();
+    assert_eq!(api_account.account.id, uid);
+    let result = BearerAccount(None).or_token("invalid-token", &request);
+    assert!(matches!(result, Err(Error::ApiUnauthorized(..))));
+}
+
+#[actix_rt::test]
+async fn api_account_works_with_bearer_token() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let uid = setup_user(None, None);
+    let token = create_token(uid);
+
+    let request = request_with_authorization(&format!("Bearer {}", token));
+    let api_account = ApiAccount::extract(&request).await.unwrap();
+    assert_eq!(api_account.account.id, uid);
+    assert!(api_account.token.is_some());
+
+    let request = request_with_authorization("Bearer invalid-token");
+    let response = ApiAccount::extract(&request)
+        .await
+        .err()
+        .unwrap()
+        .as_response_error()
+        .error_response();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[actix_rt::test]
+async fn api_account_returns_unauthorized_without_session() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let request = TestRequest::default()
+        .app_data(DB_POOL.clone())
+        .to_http_request();
+    let response = ApiAccount::extract(&request)
+        .await
+        .err()
+        .unwrap()
+        .as_response_error()
+        .error_response();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[actix_rt::test]
+async fn bearer_account_returns_error_with_expired_token() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let uid = setup_user(None, None);
+    let token = create_scoped_token(
+        uid,
+        ApiTokenScopes {
+            expired_at: Some(Utc::now().naive_utc() - Duration::days(1)),
+            ..Default::default()
+        },
+    );
+
+    let request = request_with_authorization(&format!("Bearer {}", token));
+    let result = BearerAccount::extract(&request).await;
+    assert!(matches!(
+        result,
+        Err(Error::ApiUnauthorized(MSG_API_TOKEN_EXPIRED, _))
+    ));
+    let api_token = ApiToken::get_by_account(uid, &DB_POOL).unwrap();
+    assert!(api_token[0].last_used_at.is_none());
+}
+
+#[actix_rt::test]
+async fn api_token_id_only_identifies_valid_tokens() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let uid = setup_user(None, None);
+    let token = create_token(uid);
+    let expired_token = ApiToken::insert_with_scopes(
+        &Account::get(uid, &DB_POOL).unwrap(),
+        "expired_key",
+        &ApiTokenScopes {
+            expired_at: Some(Utc::now().naive_utc() - Duration::days(1)),
+            ..Default::default()
+        },
+        &DB_POOL,
+    )
+    .unwrap()
+    .plaintext;
+    let request = TestRequest::default()
+        .app_data(DB_POOL.clone())
+        .to_http_request();
+
+    let api_token = ApiToken::get_by_plaintext(&token, &DB_POOL).unwrap();
+    assert_eq!(api_token_id(&request, &token), Some(api_token.id));
+    assert_eq!(api_token_id(&request, &expired_token), None);
+    assert_eq!(api_token_id(&request, "invalid-token"), None);
+}
+
+#[actix_rt::test]
+async fn authorize_checks_token_scopes() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let uid = setup_user(None, None);
+    let token = create_scoped_token(
+        uid,
+        ApiTokenScopes {
+            endpoint_scopes: Some(vec![EndpointScope::Yank]),
+            package_scopes: Some(vec!["move-*".to_string()]),
+            expired_at: Some(Utc::now().naive_utc() + Duration::days(1)),
+        },
+    );
+    let api_account = authenticate_token(&token, &DB_POOL).unwrap();
+
+    assert!(api_account
+        .authorize(EndpointScope::Yank, "MoveStdlib", "move-stdlib")
+        .is_ok());
+    assert!(matches!(
+        api_account.authorize(EndpointScope::PublishUpdate, "MoveStdlib", "move-stdlib"),
+        Err(Error::ApiForbidden(MSG_API_TOKEN_SCOPE_NOT_ALLOWED, _))
+    ));
+    assert!(matches!(
+        api_account.authorize(EndpointScope::Yank, "Other", "other"),
+        Err(Error::ApiForbidden(MSG_API_TOKEN_PACKAGE_NOT_ALLOWED, _))
+    ));
+
+    // Sessions are not restricted by scopes
+    let api_account = ApiAccount {
+        account: Account::get(uid, &DB_POOL).unwrap(),
+        token: None,
+    };
+    assert!(api_account
+        .authorize(EndpointScope::PublishUpdate, "Other", "other")
+        .is_ok());
+}
@@ -1,12 +1,22 @@
+#[cfg(test)]
+use crate::test::mock::MockHttpRequest as HttpRequest;
+#[cfg(not(test))]
+use jelly::actix_web::HttpRequest;
 use crate::package_collaborators::jobs::invite_collaborator::{SendRegisterToCollabEmail, SendCollaboratorInvitationEmail};
 use crate::package_collaborators::jobs::transfer_ownership::SendOwnershipTransferEmail;
 use crate::accounts::Account;
-use crate::api::collaborators::views::{CollaboratorJson, InvitationResponse};
+use crate::api::auth::ApiAccount;
+use crate::accounts::models::account_two_factor::AccountTwoFactor;
+use crate::api::collaborators::views::{
+    CollaboratorJson, InvitationResponse, PermissionsJson, TwoFactorRequirementJson,
+};
 use crate::package_collaborators::models::external_invitation::ExternalInvitation;
 use crate::package_collaborators::models::owner_invitation::OwnerInvitation;
-use crate::package_collaborators::package_collaborator::{PackageCollaborator, Role};
+use crate::package_collaborators::package_collaborator::{PackageCollaborator, Permission, Role};
+use crate::packages::models::package_event::{EventActor, PackageEvent, PackageEventKind};
 use crate::packages::Package;
-use crate::utils::request_utils;
+use crate::settings::models::token::EndpointScope;
+use crate::utils::request_utils::ClientIp;
 use diesel::result::Error as DBError;
 use diesel::Connection;
 use jelly::actix_web::web;
@@ -14,26 +24,32 @@ use jelly::actix_web::web::Path;
 use jelly::prelude::Error::*;
 use jelly::prelude::*;
 use jelly::utils::error_constants::*;
-use jelly::Result;
+use jelly::{DieselPgConnection, Result};
 use serde_json::json;
 
 pub async fn add_collaborators(
     request: HttpRequest,
+    api_account: ApiAccount,
+    client_ip: ClientIp,
     Path(package_slug): Path<String>,
     json: web::Json<CollaboratorJson>,
 ) -> Result<HttpResponse> {
-    if !request_utils::is_authenticated(&request)? {
-        return Ok(request_utils::clear_cookie(&request));
-    }
     let db = request.db_pool().map_err(|e| ApiServerError(Box::new(e)))?;
     let conn = db.get().map_err(|e| ApiServerError(Box::new(e)))?;
 
     let package = Package::get_by_slug(&package_slug, &conn)
         .map_err(|e| ApiNotFound(MSG_PACKAGE_NOT_FOUND, Box::new(e)))?;
+    api_account.authorize(EndpointScope::ManageCollaborators, &package.name, &package.slug)?;
+    let user = &api_account.account;
+    let actor = api_account.event_actor(client_ip);
 
-    let user = request.user().map_err(|e| ApiServerError(Box::new(e)))?;
-    PackageCollaborator::get(package.id, user.id, &conn)
-        .map_err(|e| ApiForbidden(MSG_UNAUTHORIZED_TO_ADD_COLLABORATOR, Box::new(e)))?;
+    check_permission(
+        &package,
+        user.id,
+        Permission::Invite,
+        MSG_UNAUTHORIZED_TO_ADD_COLLABORATOR,
+        &conn,
+    )?;
 
     let invited_account = match Account::get_by_email_or_gh_login(&json.user, db) {
         Ok(account) => account,
@@ -41,6 +57,14 @@ pub async fn add_collaborators(
             if matches!(e, Error::Database(DBError::NotFound)) && json.user.contains('@') {
                 ExternalInvitation::create(&json.user, user.id, package.id, &conn)
                     .map_err(|e| ApiBadRequest(MSG_INVITATION_ALREADY_EXISTED, Box::new(e)))?;
+                record_event(
+                    &package,
+                    PackageEventKind::CollaboratorInvited,
+                    &actor,
+                    None,
+                    &json.user,
+                    &conn,
+                )?;
                 // TODO: Handle error for this line
                 let _ = request.queue(SendRegisterToCollabEmail {
                     to: json.user.clone(),
@@ -70,6 +94,14 @@ pub async fn add_collaborators(
     let invitation =
         OwnerInvitation::create(invited_account.id, user.id, package.id, None, None, &conn)
             .map_err(|e| ApiBadRequest(MSG_INVITATION_ALREADY_EXISTED, Box::new(e)))?;
+    record_event(
+        &package,
+        PackageEventKind::CollaboratorInvited,
+        &actor,
+        Some(invited_account.id),
+        "",
+        &conn,
+    )?;
     if !invited_account.is_generated_email() {
         request.queue(SendCollaboratorInvitationEmail {
             to: invited_account.email,
@@ -86,22 +118,23 @@ pub async fn add_collaborators(
 
 pub async fn transfer_ownership(
     request: HttpRequest,
+    api_account: ApiAccount,
+    client_ip: ClientIp,
     Path(package_slug): Path<String>,
     json: web::Json<CollaboratorJson>,
 ) -> Result<HttpResponse> {
-    if !request_utils::is_authenticated(&request)? {
-        return Ok(request_utils::clear_cookie(&request));
-    }
     let db = request.db_pool().map_err(|e| ApiServerError(Box::new(e)))?;
     let conn = db.get().map_err(|e| ApiServerError(Box::new(e)))?;
 
     let package = Package::get_by_slug(&package_slug, &conn)
         .map_err(|e| ApiNotFound(MSG_PACKAGE_NOT_FOUND, Box::new(e)))?;
+    api_account.authorize(EndpointScope::ManageCollaborators, &package.name, &package.slug)?;
+    let user = &api_account.account;
+    check_two_factor(&package, user.id, &conn)?;
 
     let invited_account = Account::get_by_email_or_gh_login(&json.user, db)
         .map_err(|e| ApiNotFound(MSG_ACCOUNT_NOT_FOUND, Box::new(e)))?;
 
-    let user = request.user().map_err(|e| ApiServerError(Box::new(e)))?;
     let ids = vec![user.id, invited_account.id];
     let collaborators = PackageCollaborator::get_in_bulk_order_by_role(package.id, ids, &conn)
         .map_err(|e| ApiForbidden(MSG_UNAUTHORIZED_TO_ADD_COLLABORATOR, Box::new(e)))?;
@@ -132,6 +165,14 @@ pub async fn transfer_ownership(
         &conn,
     )
     .map_err(|e| ApiBadRequest(MSG_INVITATION_ALREADY_EXISTED, Box::new(e)))?;
+    record_event(
+        &package,
+        PackageEventKind::OwnershipTransferRequested,
+        &api_account.event_actor(client_ip),
+        Some(invited_account.id),
+        "",
+        &conn,
+    )?;
 
     if !invited_account.is_generated_email() {
         request.queue(SendOwnershipTransferEmail {
@@ -149,16 +190,17 @@ pub async fn transfer_ownership(
 
 pub async fn handle_invite(
     request: HttpRequest,
+    api_account: ApiAccount,
+    client_ip: ClientIp,
     json: web::Json<InvitationResponse>,
 ) -> Result<HttpResponse> {
-    if !request_utils::is_authenticated(&request)? {
-        return Ok(request_utils::clear_cookie(&request));
-    }
-
     let db = request.db_pool().map_err(|e| ApiServerError(Box::new(e)))?;
     let conn = db.get().map_err(|e| ApiServerError(Box::new(e)))?;
 
-    let user = request.user().map_err(|e| ApiServerError(Box::new(e)))?;
+    let package = Package::get(json.package_id, db)
+        .map_err(|e| ApiNotFound(MSG_PACKAGE_NOT_FOUND, Box::new(e)))?;
+    api_account.authorize(EndpointScope::ManageCollaborators, &package.name, &package.slug)?;
+    let user = &api_account.account;
     let invitation = OwnerInvitation::find_by_id(user.id, json.package_id, &conn)
         .map_err(|e| ApiNotFound(MSG_INVITATION_NOT_FOUND, Box::new(e)))?;
     if invitation.is_expired() {
@@ -166,24 +208,29 @@ pub async fn handle_invite(
             MSG_INVITATION_EXPIRED,
             Box::new(Error::Generic(format!(
                 "Invitation is expired. invited id: {}, package id: {}",
-                invitation.invited_user_id, invitation.package_id
+                invitation.invited_user_id, json.package_id
             ))),
         ));
     }
+    let actor = api_account.event_actor(client_ip);
     if json.accepted {
+        invitation
+            .accept(&actor, &conn)
+            .map_err(|e| ApiUnauthorized(MSG_UNEXPECTED_ERROR, Box::new(e)))?;
+    } else {
         conn.transaction(|| -> Result<()> {
             invitation
-                .accept(&conn)
+                .delete(&conn)
                 .map_err(|e| ApiUnauthorized(MSG_UNEXPECTED_ERROR, Box::new(e)))?;
-            if invitation.is_transferring {
-                Package::change_owner(invitation.package_id, invitation.invited_user_id, &conn)?;
-            }
-            Ok(())
+            record_event(
+                &package,
+                PackageEventKind::InvitationDeclined,
+                &actor,
+                Some(user.id),
+                "",
+                &conn,
+            )
         })?
-    } else {
-        invitation
-            .delete(&conn)
-            .map_err(|e| ApiUnauthorized(MSG_UNEXPECTED_ERROR, Box::new(e)))?;
     }
     Ok(HttpResponse::Ok().json(json!({
         "ok": true,
@@ -193,21 +240,27 @@ pub async fn handle_invite(
 
 pub async fn remove_collaborator(
     request: HttpRequest,
+    api_account: ApiAccount,
+    client_ip: ClientIp,
     Path(package_slug): Path<String>,
     json: web::Json<CollaboratorJson>,
 ) -> Result<HttpResponse> {
-    if !request_utils::is_authenticated(&request)? {
-        return Ok(request_utils::clear_cookie(&request));
-    }
     let db = request.db_pool().map_err(|e| ApiServerError(Box::new(e)))?;
     let conn = db.get().map_err(|e| ApiServerError(Box::new(e)))?;
 
     let package = Package::get_by_slug(&package_slug, &conn)
         .map_err(|e| ApiNotFound(MSG_PACKAGE_NOT_FOUND, Box::new(e)))?;
+    api_account.authorize(EndpointScope::ManageCollaborators, &package.name, &package.slug)?;
+    let user = &api_account.account;
+    let actor = api_account.event_actor(client_ip);
 
-    let user = request.user().map_err(|e| ApiServerError(Box::new(e)))?;
-    PackageCollaborator::get(package.id, user.id, &conn)
-        .map_err(|e| ApiForbidden(MSG_UNAUTHORIZED_TO_ADD_COLLABORATOR, Box::new(e)))?;
+    check_permission(
+        &package,
+        user.id,
+        Permission::ManageCollaborators,
+        MSG_UNAUTHORIZED_TO_REMOVE_COLLABORATOR,
+        &conn,
+    )?;
 
     let target_account = Account::get_by_email_or_gh_login(&json.user, db);
     match target_account {
@@ -229,6 +282,23 @@ pub async fn remove_collaborator(
                         ))),
                     ));
                 }
+                record_event(
+                    &package,
+                    PackageEventKind::CollaboratorRemoved,
+                    &actor,
+                    Some(account.id),
+                    "",
+                    &conn,
+                )?;
+            } else {
+                record_event(
+                    &package,
+                    PackageEventKind::InvitationCancelled,
+                    &actor,
+                    Some(account.id),
+                    "",
+                    &conn,
+                )?;
             }
         }
         Err(e) => {
@@ -238,6 +308,14 @@ pub async fn remove_collaborator(
             }
             ExternalInvitation::delete_by_id(&json.user, package.id, &conn)
                 .map_err(|e| ApiNotFound(MSG_ACCOUNT_NOT_FOUND, Box::new(e)))?;
+            record_event(
+                &package,
+                PackageEventKind::InvitationCancelled,
+                &actor,
+                None,
+                &json.user,
+                &conn,
+            )?;
         }
     }
 
@@ -246,3 +324,311 @@ pub async fn remove_collaborator(
         "msg": MSG_SUCCESSFULLY_REMOVED_COLLABORATOR,
     })))
 }
+
+/// Changes what a collaborator may do. Only owners change permissions, so that
+/// collaborators can't grant themselves or others more than they were given.
+pub async fn update_permissions(
+    request: HttpRequest,
+    api_account: ApiAccount,
+    client_ip: ClientIp,
+    Path(package_slug): Path<String>,
+    json: web::Json<PermissionsJson>,
+) -> Result<HttpResponse> {
+    let db = request.db_pool().map_err(|e| ApiServerError(Box::new(e)))?;
+    let conn = db.get().map_err(|e| ApiServerError(Box::new(e)))?;
+
+    let package = Package::get_by_slug(&package_slug, &conn)
+        .map_err(|e| ApiNotFound(MSG_PACKAGE_NOT_FOUND, Box::new(e)))?;
+    api_account.authorize(EndpointScope::ManageCollaborators, &package.name, &package.slug)?;
+    let user = &api_account.account;
+
+    check_owner(
+        &package,
+        user.id,
+        MSG_UNAUTHORIZED_TO_CHANGE_PERMISSIONS,
+        &conn,
+    )?;
+
+    let target_account = Account::get_by_email_or_gh_login(&json.user, db)
+        .map_err(|e| ApiNotFound(MSG_ACCOUNT_NOT_FOUND, Box::new(e)))?;
+    if target_account.id == user.id {
+        return Err(ApiForbidden(
+            MSG_UNAUTHORIZED_TO_CHANGE_PERMISSIONS,
+            Box::new(Error::Generic(format!(
+                "Collaborators can't change their own permissions. uid: {}, package id: {}",
+                user.id, package.id
+            ))),
+        ));
+    }
+    let num_updated_collaborators = PackageCollaborator::update_permissions(
+        package.id,
+        target_account.id,
+        &json.permissions,
+        &conn,
+    )
+    .map_err(|e| ApiServerError(Box::new(e)))?;
+    if num_updated_collaborators == 0 {
+        return Err(ApiNotFound(
+            MSG_COLLABORATOR_NOT_FOUND,
+            Box::new(Error::Generic(format!(
+                "Failure trying to update permissions of collaborator. requester id: {}, target id: {}, package id: {}",
+                user.id, target_account.id, package.id
+            ))),
+        ));
+    }
+    record_event(
+        &package,
+        PackageEventKind::PermissionsChanged,
+        &api_account.event_actor(client_ip),
+        Some(target_account.id),
+        &json.permissions.to_string(),
+        &conn,
+    )?;
+
+    Ok(HttpResponse::Ok().json(json!({
+        "ok": true,
+        "msg": MSG_SUCCESSFULLY_UPDATED_PERMISSIONS,
+    })))
+}
+
+/// Makes an accepted collaborator one more owner of the package.
+pub async fn add_owner(
+    request: HttpRequest,
+    api_account: ApiAccount,
+    client_ip: ClientIp,
+    Path(package_slug): Path<String>,
+    json: web::Json<CollaboratorJson>,
+) -> Result<HttpResponse> {
+    let db = request.db_pool().map_err(|e| ApiServerError(Box::new(e)))?;
+    let conn = db.get().map_err(|e| ApiServerError(Box::new(e)))?;
+
+    let package = Package::get_by_slug(&package_slug, &conn)
+        .map_err(|e| ApiNotFound(MSG_PACKAGE_NOT_FOUND, Box::new(e)))?;
+    api_account.authorize(EndpointScope::ManageCollaborators, &package.name, &package.slug)?;
+    let user = &api_account.account;
+    check_owner(
+        &package,
+        user.id,
+        MSG_UNAUTHORIZED_TO_MANAGE_OWNERS,
+        &conn,
+    )?;
+
+    let target_account = Account::get_by_email_or_gh_login(&json.user, db)
+        .map_err(|e| ApiNotFound(MSG_ACCOUNT_NOT_FOUND, Box::new(e)))?;
+    let num_promoted_collaborators =
+        PackageCollaborator::promote_to_owner(package.id, target_account.id, &conn)
+            .map_err(|e| ApiServerError(Box::new(e)))?;
+    if num_promoted_collaborators == 0 {
+        return Err(ApiNotFound(
+            MSG_COLLABORATOR_NOT_FOUND,
+            Box::new(Error::Generic(format!(
+                "Failure trying to add owner to package. requester id: {}, target id: {}, package id: {}",
+                user.id, target_account.id, package.id
+            ))),
+        ));
+    }
+    record_event(
+        &package,
+        PackageEventKind::OwnerAdded,
+        &api_account.event_actor(client_ip),
+        Some(target_account.id),
+        "",
+        &conn,
+    )?;
+
+    Ok(HttpResponse::Ok().json(json!({
+        "ok": true,
+        "msg": MSG_SUCCESSFULLY_ADDED_OWNER,
+    })))
+}
+
+/// Removes an owner from the package, possibly the requester. The last owner can't be removed.
+pub async fn remove_owner(
+    request: HttpRequest,
+    api_account: ApiAccount,
+    client_ip: ClientIp,
+    Path(package_slug): Path<String>,
+    json: web::Json<CollaboratorJson>,
+) -> Result<HttpResponse> {
+    let db = request.db_pool().map_err(|e| ApiServerError(Box::new(e)))?;
+    let conn = db.get().map_err(|e| ApiServerError(Box::new(e)))?;
+
+    let package = Package::get_by_slug(&package_slug, &conn)
+        .map_err(|e| ApiNotFound(MSG_PACKAGE_NOT_FOUND, Box::new(e)))?;
+    api_account.authorize(EndpointScope::ManageCollaborators, &package.name, &package.slug)?;
+    let user = &api_account.account;
+    check_owner(
+        &package,
+        user.id,
+        MSG_UNAUTHORIZED_TO_MANAGE_OWNERS,
+        &conn,
+    )?;
+
+    let target_account = Account::get_by_email_or_gh_login(&json.user, db)
+        .map_err(|e| ApiNotFound(MSG_ACCOUNT_NOT_FOUND, Box::new(e)))?;
+    let owner_ids = PackageCollaborator::get_owner_ids(package.id, &conn)
+        .map_err(|e| ApiServerError(Box::new(e)))?;
+    if !owner_ids.contains(&target_account.id) {
+        return Err(ApiNotFound(
+            MSG_OWNER_NOT_FOUND,
+            Box::new(Error::Generic(format!(
+                "Account is not an owner of the package. target id: {}, package id: {}",
+                target_account.id, package.id
+            ))),
+        ));
+    }
+    let num_deleted_owners =
+        PackageCollaborator::delete_owner_by_id(target_account.id, package.id, &conn)
+            .map_err(|e| ApiServerError(Box::new(e)))?;
+    if num_deleted_owners == 0 {
+        return Err(ApiBadRequest(
+            MSG_CANNOT_REMOVE_LAST_OWNER,
+            Box::new(Error::Generic(format!(
+                "Failure trying to remove the last owner of package. requester id: {}, package id: {}",
+                user.id, package.id
+            ))),
+        ));
+    }
+    record_event(
+        &package,
+        PackageEventKind::OwnerRemoved,
+        &api_account.event_actor(client_ip),
+        Some(target_account.id),
+        "",
+        &conn,
+    )?;
+
+    Ok(HttpResponse::Ok().json(json!({
+        "ok": true,
+        "msg": MSG_SUCCESSFULLY_REMOVED_OWNER,
+    })))
+}
+
+/// Lets owners require two-factor authentication from everyone who publishes or manages the
+/// collaborators of the package. Owners need it themselves to turn the requirement on.
+pub async fn update_two_factor_requirement(
+    request: HttpRequest,
+    api_account: ApiAccount,
+    client_ip: ClientIp,
+    Path(package_slug): Path<String>,
+    json: web::Json<TwoFactorRequirementJson>,
+) -> Result<HttpResponse> {
+    let db = request.db_pool().map_err(|e| ApiServerError(Box::new(e)))?;
+    let conn = db.get().map_err(|e| ApiServerError(Box::new(e)))?;
+
+    let package = Package::get_by_slug(&package_slug, &conn)
+        .map_err(|e| ApiNotFound(MSG_PACKAGE_NOT_FOUND, Box::new(e)))?;
+    api_account.authorize(EndpointScope::ManageCollaborators, &package.name, &package.slug)?;
+    let user = &api_account.account;
+    check_owner(
+        &package,
+        user.id,
+        MSG_UNAUTHORIZED_TO_MANAGE_OWNERS,
+        &conn,
+    )?;
+
+    let has_two_factor =
+        AccountTwoFactor::is_enabled(user.id, &conn).map_err(|e| ApiServerError(Box::new(e)))?;
+    if json.require_two_factor && !has_two_factor {
+        return Err(ApiForbidden(
+            MSG_ENABLE_TWO_FACTOR_FIRST,
+            Box::new(Error::Generic(format!(
+                "Owner doesn't have two-factor authentication. uid: {}, package id: {}",
+                user.id, package.id
+            ))),
+        ));
+    }
+    Package::set_require_two_factor(package.id, json.require_two_factor, &conn)
+        .map_err(|e| ApiServerError(Box::new(e)))?;
+    if json.require_two_factor != package.require_two_factor {
+        record_event(
+            &package,
+            PackageEventKind::TwoFactorRequirementChanged,
+            &api_account.event_actor(client_ip),
+            None,
+            if json.require_two_factor {
+                "required"
+            } else {
+                "not required"
+            },
+            &conn,
+        )?;
+    }
+
+    Ok(HttpResponse::Ok().json(json!({
+        "ok": true,
+        "msg": MSG_SUCCESSFULLY_UPDATED_TWO_FACTOR_REQUIREMENT,
+    })))
+}
+
+fn record_event(
+    package: &Package,
+    kind: PackageEventKind,
+    actor: &EventActor,
+    target_account_id: Option<i32>,
+    details: &str,
+    conn: &DieselPgConnection,
+) -> Result<()> {
+    PackageEvent::create(package.id, kind, actor, target_account_id, details, conn)
+        .map_err(|e| ApiServerError(Box::new(e)))?;
+    Ok(())
+}
+
+fn check_two_factor(package: &Package, account_id: i32, conn: &DieselPgConnection) -> Result<()> {
+    let meets_requirement = package
+        .meets_two_factor_requirement(account_id, conn)
+        .map_err(|e| ApiServerError(Box::new(e)))?;
+    if !meets_requirement {
+        return Err(ApiForbidden(
+            MSG_TWO_FACTOR_REQUIRED,
+            Box::new(Error::Generic(format!(
+                "Account doesn't have two-factor authentication. uid: {}, package id: {}",
+                account_id, package.id
+            ))),
+        ));
+    }
+    Ok(())
+}
+
+fn check_owner(
+    package: &Package,
+    account_id: i32,
+    error_message: &'static str,
+    conn: &DieselPgConnection,
+) -> Result<()> {
+    check_two_factor(package, account_id, conn)?;
+    let role = PackageCollaborator::get_role(package, account_id, conn)
+        .map_err(|e| ApiForbidden(error_message, Box::new(e)))?;
+    if role != Role::Owner {
+        return Err(ApiForbidden(
+            error_message,
+            Box::new(Error::Generic(format!(
+                "Account is not an owner of the package. uid: {}, package id: {}",
+                account_id, package.id
+            ))),
+        ));
+    }
+    Ok(())
+}
+
+fn check_permission(
+    package: &Package,
+    account_id: i32,
+    permission: Permission,
+    error_message: &'static str,
+    conn: &DieselPgConnection,
+) -> Result<()> {
+    check_two_factor(package, account_id, conn)?;
+    let has_permission = PackageCollaborator::has_permission(package, account_id, permission, conn)
+        .map_err(|e| ApiServerError(Box::new(e)))?;
+    if !has_permission {
+        return Err(ApiForbidden(
+            error_message,
+            Box::new(Error::Generic(format!(
+                "Account doesn't have the {:?} permission. uid: {}, package id: {}",
+                permission, account_id, package.id
+            ))),
+        ));
+    }
+    Ok(())
+}
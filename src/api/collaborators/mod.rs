@@ -3,6 +3,9 @@ use jelly::actix_web::web::{delete, post, resource, scope, ServiceConfig};
 pub mod controllers;
 pub mod views;
 
+#[cfg(test)]
+mod tests;
+
 pub fn configure(config: &mut ServiceConfig) {
     config.service(
         scope("/api/v1/collaborators")
@@ -18,7 +21,18 @@ pub fn configure(config: &mut ServiceConfig) {
                     ))
                     .service(resource("/transfer").route(
                         post().to(controllers::transfer_ownership),
-                    )),
+                    ))
+                    .service(resource("/permissions").route(
+                        post().to(controllers::update_permissions),
+                    ))
+                    .service(resource("/two-factor").route(
+                        post().to(controllers::update_two_factor_requirement),
+                    ))
+                    .service(
+                        resource("/owners")
+                            .route(post().to(controllers::add_owner))
+                            .route(delete().to(controllers::remove_owner)),
+                    ),
             )
             .service(resource("/handle").route(
                 post().to(controllers::handle_invite),
@@ -0,0 +1,131 @@
+use crate::accounts::Account;
+use crate::api::auth::ApiAccount;
+use crate::api::collaborators::controllers::update_permissions;
+use crate::api::collaborators::views::PermissionsJson;
+use crate::package_collaborators::package_collaborator::{
+    CollaboratorPermissions, PackageCollaborator,
+};
+use crate::packages::Package;
+use crate::test::util::{setup_package, setup_user};
+use crate::test::{mock, DatabaseTestContext, DB_POOL};
+use crate::utils::request_utils::ClientIp;
+
+use jelly::actix_web::http::StatusCode;
+use jelly::actix_web::web::{Json, Path};
+use jelly::error::Error;
+use jelly::utils::error_constants::MSG_UNAUTHORIZED_TO_CHANGE_PERMISSIONS;
+
+fn api_account(uid: i32) -> ApiAccount {
+    ApiAccount {
+        account: Account::get(uid, &DB_POOL).unwrap(),
+        token: None,
+    }
+}
+
+fn mock_request() -> mock::MockHttpRequest {
+    let mut mock_http_request = mock::MockHttpRequest::new();
+    mock_http_request
+        .expect_db_pool()
+        .returning(|| Ok(&DB_POOL));
+    mock_http_request
+}
+
+fn add_collaborator(
+    package: &Package,
+    owner_id: i32,
+    email: &str,
+    permissions: CollaboratorPermissions,
+) -> i32 {
+    let conn = DB_POOL.get().unwrap();
+    let uid = setup_user(Some(email.to_string()), None);
+    PackageCollaborator::new_collaborator(package.id, uid, owner_id, &conn).unwrap();
+    PackageCollaborator::update_permissions(package.id, uid, &permissions, &conn).unwrap();
+    uid
+}
+
+const NO_PERMISSIONS: CollaboratorPermissions = CollaboratorPermissions {
+    can_publish: false,
+    can_manage_collaborators: false,
+    can_invite: false,
+};
+
+fn grant_publish(email: &str) -> Json<PermissionsJson> {
+    Json(PermissionsJson {
+        user: email.to_string(),
+        permissions: CollaboratorPermissions {
+            can_publish: true,
+            can_manage_collaborators: false,
+            can_invite: false,
+        },
+    })
+}
+
+fn collaborator_permissions(package_id: i32, uid: i32) -> CollaboratorPermissions {
+    PackageCollaborator::get(package_id, uid, &DB_POOL.get().unwrap())
+        .unwrap()
+        .permissions()
+}
+
+#[actix_rt::test]
+async fn update_permissions_works_for_owner() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let owner_id = setup_user(None, None);
+    let pid = setup_package("package1", "", owner_id);
+    let package = Package::get(pid, &DB_POOL).unwrap();
+    let collaborator_id =
+        add_collaborator(&package, owner_id, "collaborator@host.com", NO_PERMISSIONS);
+
+    let response = update_permissions(
+        mock_request(),
+        api_account(owner_id),
+        ClientIp(None),
+        Path(package.slug.clone()),
+        grant_publish("collaborator@host.com"),
+    )
+    .await
+    .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(collaborator_permissions(pid, collaborator_id).can_publish);
+}
+
+#[actix_rt::test]
+async fn update_permissions_returns_error_if_not_owner() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let owner_id = setup_user(None, None);
+    let pid = setup_package("package1", "", owner_id);
+    let package = Package::get(pid, &DB_POOL).unwrap();
+    let manager_id = add_collaborator(
+        &package,
+        owner_id,
+        "manager@host.com",
+        CollaboratorPermissions {
+            can_publish: false,
+            can_manage_collaborators: true,
+            can_invite: true,
+        },
+    );
+    let collaborator_id =
+        add_collaborator(&package, owner_id, "collaborator@host.com", NO_PERMISSIONS);
+
+    let result = update_permissions(
+        mock_request(),
+        api_account(manager_id),
+        ClientIp(None),
+        Path(package.slug.clone()),
+        grant_publish("collaborator@host.com"),
+    )
+    .await;
+    assert!(matches!(
+        result,
+        Err(Error::ApiForbidden(
+            MSG_UNAUTHORIZED_TO_CHANGE_PERMISSIONS,
+            _
+        ))
+    ));
+    assert!(!collaborator_permissions(pid, collaborator_id).can_publish);
+    assert!(!collaborator_permissions(pid, manager_id).can_publish);
+}
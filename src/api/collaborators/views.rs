@@ -1,10 +1,24 @@
 use serde::{Deserialize, Serialize};
 
+use crate::package_collaborators::package_collaborator::CollaboratorPermissions;
+
 #[derive(Deserialize)]
 pub struct CollaboratorJson {
     pub user: String,
 }
 
+#[derive(Deserialize)]
+pub struct PermissionsJson {
+    pub user: String,
+    #[serde(flatten)]
+    pub permissions: CollaboratorPermissions,
+}
+
+#[derive(Deserialize)]
+pub struct TwoFactorRequirementJson {
+    pub require_two_factor: bool,
+}
+
 #[derive(Deserialize, Serialize, Debug, Copy, Clone)]
 pub struct InvitationResponse {
     pub package_id: i32,
@@ -0,0 +1,117 @@
+#[cfg(test)]
+use crate::test::mock::MockHttpRequest as HttpRequest;
+#[cfg(not(test))]
+use jelly::actix_web::HttpRequest;
+
+use jelly::actix_web::http::header::{self, HttpDate};
+use jelly::actix_web::web::Path;
+use jelly::prelude::*;
+use jelly::Result;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::api::index::view::{IndexConfig, IndexEntry};
+use crate::packages::models::package_dependency::PackageDependency;
+use crate::packages::{Package, PackageVersion, PackageVersionSort};
+use crate::utils::presenter;
+
+/// Path of a package's file in the index, e.g. `1/a`, `3/a/abc`, `mo/ve/move-stdlib`.
+pub fn index_path(package_slug: &str) -> String {
+    let chars: Vec<char> = package_slug.chars().collect();
+    match chars.len() {
+        1 => format!("1/{}", package_slug),
+        2 => format!("2/{}", package_slug),
+        3 => format!("3/{}/{}", chars[0], package_slug),
+        _ => format!(
+            "{}/{}/{}",
+            chars[..2].iter().collect::<String>(),
+            chars[2..4].iter().collect::<String>(),
+            package_slug
+        ),
+    }
+}
+
+pub async fn index_config() -> Result<HttpResponse> {
+    let domain = std::env::var("JELLY_DOMAIN").expect("JELLY_DOMAIN is not set");
+    Ok(HttpResponse::Ok().json(IndexConfig {
+        api: format!("{}/api/v1", domain),
+    }))
+}
+
+pub async fn index_file(request: HttpRequest, Path(path): Path<String>) -> Result<HttpResponse> {
+    let package_slug = path.rsplit('/').next().unwrap_or_default();
+    if package_slug.is_empty() || index_path(package_slug) != path {
+        return Ok(HttpResponse::NotFound().finish());
+    }
+
+    let db = request.db_pool()?;
+    let conn = db.get()?;
+    let package = match Package::get_by_slug(package_slug, &conn) {
+        Ok(package) => package,
+        Err(_) => return Ok(HttpResponse::NotFound().finish()),
+    };
+    // Versions are listed in the order they were published
+    let versions = PackageVersion::from_package_id(package.id, &PackageVersionSort::Oldest, db)?;
+    let last_modified = match versions.iter().map(|version| version.updated_at).max() {
+        Some(last_modified) => last_modified,
+        None => return Ok(HttpResponse::NotFound().finish()),
+    };
+    let dependencies = PackageDependency::get_by_package_versions(
+        versions.iter().map(|version| version.id).collect(),
+        &conn,
+    )?;
+    let (repository, subdir) = presenter::make_package_install_instruction(&package.repository_url);
+
+    let mut body = String::new();
+    for version in versions.iter() {
+        let version_dependencies: Vec<&PackageDependency> = dependencies
+            .iter()
+            .filter(|dependency| dependency.package_version_id == version.id)
+            .collect();
+        let entry = IndexEntry::new(
+            &package,
+            version,
+            &repository,
+            &subdir,
+            &version_dependencies,
+        );
+        body.push_str(&serde_json::to_string(&entry)?);
+        body.push('\n');
+    }
+
+    let etag = format!("\"{:x}\"", md5::compute(body.as_bytes()));
+    let last_modified = HttpDate::from(SystemTime::from(last_modified));
+    // If-Modified-Since is ignored when If-None-Match is present
+    let is_not_modified = match request.headers().get(header::IF_NONE_MATCH) {
+        Some(if_none_match) => if_none_match.to_str().is_ok_and(|if_none_match| {
+            if_none_match
+                .split(',')
+                .any(|tag| tag.trim() == etag || tag.trim() == "*")
+        }),
+        None => request
+            .headers()
+            .get(header::IF_MODIFIED_SINCE)
+            .and_then(|if_modified_since| if_modified_since.to_str().ok())
+            .and_then(|if_modified_since| if_modified_since.parse::<HttpDate>().ok())
+            .is_some_and(|if_modified_since| {
+                seconds_since_epoch(last_modified) <= seconds_since_epoch(if_modified_since)
+            }),
+    };
+
+    if is_not_modified {
+        return Ok(HttpResponse::NotModified()
+            .header(header::ETAG, etag)
+            .header(header::LAST_MODIFIED, last_modified)
+            .finish());
+    }
+    Ok(HttpResponse::Ok()
+        .content_type("text/plain; charset=utf-8")
+        .header(header::ETAG, etag)
+        .header(header::LAST_MODIFIED, last_modified)
+        .body(body))
+}
+
+fn seconds_since_epoch(date: HttpDate) -> u64 {
+    SystemTime::from(date)
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+}
@@ -0,0 +1,17 @@
+//! Sparse registry index, laid out the same way as cargo's: one file per package
+//! containing one JSON line per version.
+
+use jelly::actix_web::web::{get, resource, scope, ServiceConfig};
+
+pub mod controller;
+#[cfg(test)]
+mod tests;
+pub mod view;
+
+pub fn configure(config: &mut ServiceConfig) {
+    config.service(
+        scope("/api/v1/index")
+            .service(resource("/config.json").route(get().to(controller::index_config)))
+            .service(resource("/{path:.*}").route(get().to(controller::index_file))),
+    );
+}
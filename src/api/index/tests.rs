@@ -0,0 +1,167 @@
+use crate::api::index::controller::{index_file, index_path};
+use crate::github_service::{GithubRepoData, MoveDependency};
+use crate::packages::Package;
+use crate::test::{mock, DatabaseTestContext, DB_POOL};
+
+use jelly::actix_web::body::{Body, ResponseBody};
+use jelly::actix_web::http::{header, HeaderMap, HeaderValue, StatusCode};
+use jelly::actix_web::web::Path;
+
+fn create_package() -> Package {
+    let github_data = GithubRepoData {
+        name: "MoveStdlib".to_string(),
+        version: "0.1.0".to_string(),
+        readme_content: "".to_string(),
+        license: "Apache-2.0".to_string(),
+        description: "".to_string(),
+        size: 0,
+        stars_count: 0,
+        forks_count: 0,
+        url: "".to_string(),
+        rev: "".to_string(),
+        dependencies: vec![MoveDependency {
+            name: "MoveNursery".to_string(),
+            is_dev: true,
+            local: Some("../move-nursery".to_string()),
+            ..Default::default()
+        }],
+        addresses: vec![],
+    };
+    let package = Package::create_from_crawled_data(
+        "https://github.com/move-language/move/tree/main/language/move-stdlib",
+        "",
+        "a8383d88fa48f4e1e0e91264cffbbd27136e4732",
+        0,
+        0,
+        None,
+        github_data,
+        &DB_POOL,
+    )
+    .unwrap();
+    Package::get(package.id, &DB_POOL).unwrap()
+}
+
+fn mock_request(headers: HeaderMap) -> mock::MockHttpRequest {
+    let mut mock_http_request = mock::MockHttpRequest::new();
+    mock_http_request
+        .expect_db_pool()
+        .returning(|| Ok(&DB_POOL));
+    mock_http_request.expect_headers().return_const(headers);
+    mock_http_request
+}
+
+fn body_text(body: &ResponseBody<Body>) -> String {
+    match body.as_ref().unwrap() {
+        Body::Bytes(bytes) => String::from_utf8(bytes.to_vec()).unwrap(),
+        _ => panic!("Unexpected response body"),
+    }
+}
+
+#[test]
+fn index_path_works() {
+    assert_eq!(index_path("a"), "1/a");
+    assert_eq!(index_path("ab"), "2/ab");
+    assert_eq!(index_path("abc"), "3/a/abc");
+    assert_eq!(index_path("move-stdlib"), "mo/ve/move-stdlib");
+}
+
+#[actix_rt::test]
+async fn index_file_returns_every_version_of_package() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+    let package = create_package();
+
+    let response = index_file(
+        mock_request(HeaderMap::new()),
+        Path(index_path(&package.slug)),
+    )
+    .await
+    .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.headers().contains_key(header::ETAG));
+    assert!(response.headers().contains_key(header::LAST_MODIFIED));
+
+    let body = body_text(response.body());
+    let lines: Vec<serde_json::Value> = body
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 1);
+    assert_eq!(lines[0]["name"], "MoveStdlib");
+    assert_eq!(lines[0]["vers"], "0.1.0");
+    assert_eq!(lines[0]["rev"], "a8383d88fa48f4e1e0e91264cffbbd27136e4732");
+    assert_eq!(
+        lines[0]["repository"],
+        "https://github.com/move-language/move.git"
+    );
+    assert_eq!(lines[0]["subdir"], "language/move-stdlib");
+    assert_eq!(lines[0]["license"], "Apache-2.0");
+    assert_eq!(lines[0]["yanked"], false);
+    assert_eq!(lines[0]["deps"][0]["name"], "MoveNursery");
+    assert_eq!(lines[0]["deps"][0]["kind"], "dev");
+    assert_eq!(lines[0]["deps"][0]["local"], "../move-nursery");
+}
+
+#[actix_rt::test]
+async fn index_file_returns_not_modified_with_matching_etag() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+    let package = create_package();
+
+    let response = index_file(
+        mock_request(HeaderMap::new()),
+        Path(index_path(&package.slug)),
+    )
+    .await
+    .unwrap();
+    let etag = response.headers().get(header::ETAG).unwrap().clone();
+    let last_modified = response
+        .headers()
+        .get(header::LAST_MODIFIED)
+        .unwrap()
+        .clone();
+
+    let mut headers = HeaderMap::new();
+    headers.insert(header::IF_NONE_MATCH, etag);
+    let response = index_file(mock_request(headers), Path(index_path(&package.slug)))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+
+    let mut headers = HeaderMap::new();
+    headers.insert(header::IF_MODIFIED_SINCE, last_modified);
+    let response = index_file(mock_request(headers), Path(index_path(&package.slug)))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+
+    let mut headers = HeaderMap::new();
+    headers.insert(header::IF_NONE_MATCH, HeaderValue::from_static("\"stale\""));
+    let response = index_file(mock_request(headers), Path(index_path(&package.slug)))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[actix_rt::test]
+async fn index_file_returns_not_found() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+    let package = create_package();
+
+    let response = index_file(
+        mock_request(HeaderMap::new()),
+        Path(format!("1/{}", package.slug)),
+    )
+    .await
+    .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = index_file(
+        mock_request(HeaderMap::new()),
+        Path(index_path("not-a-package")),
+    )
+    .await
+    .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
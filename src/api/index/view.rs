@@ -0,0 +1,78 @@
+use jelly::chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::packages::models::package_dependency::{DependencyKind, PackageDependency};
+use crate::packages::{Package, PackageVersion};
+
+#[derive(Serialize, Deserialize)]
+pub struct IndexConfig {
+    pub api: String,
+}
+
+// One line of a package's index file
+#[derive(Serialize, Deserialize)]
+pub struct IndexEntry {
+    pub name: String,
+    pub vers: String,
+    pub rev: String,
+    pub repository: String,
+    pub subdir: String,
+    pub license: String,
+    pub yanked: bool,
+    pub published_at: DateTime<Utc>,
+    pub deps: Vec<IndexDependency>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct IndexDependency {
+    pub name: String,
+    pub kind: String,
+    pub local: Option<String>,
+    pub git: Option<String>,
+    pub rev: Option<String>,
+    pub subdir: Option<String>,
+    pub movey: Option<String>,
+}
+
+impl IndexEntry {
+    pub fn new(
+        package: &Package,
+        package_version: &PackageVersion,
+        repository: &str,
+        subdir: &str,
+        dependencies: &[&PackageDependency],
+    ) -> Self {
+        IndexEntry {
+            name: package.name.clone(),
+            vers: package_version.version.clone(),
+            rev: package_version.rev.clone().unwrap_or_default(),
+            repository: repository.to_string(),
+            subdir: subdir.to_string(),
+            license: package_version.license.clone().unwrap_or_default(),
+            yanked: package_version.yanked,
+            published_at: package_version.created_at,
+            deps: dependencies
+                .iter()
+                .map(|dependency| IndexDependency::from(*dependency))
+                .collect(),
+        }
+    }
+}
+
+impl From<&PackageDependency> for IndexDependency {
+    fn from(dependency: &PackageDependency) -> Self {
+        IndexDependency {
+            name: dependency.name.clone(),
+            kind: if dependency.kind == DependencyKind::Dev as i32 {
+                "dev".to_string()
+            } else {
+                "normal".to_string()
+            },
+            local: dependency.local.clone(),
+            git: dependency.git.clone(),
+            rev: dependency.rev.clone(),
+            subdir: dependency.subdir.clone(),
+            movey: dependency.movey.clone(),
+        }
+    }
+}
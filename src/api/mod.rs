@@ -2,12 +2,21 @@
 
 use jelly::actix_web::web::ServiceConfig;
 
+pub mod auth;
 pub mod collaborators;
+pub mod index;
+pub mod organizations;
 pub mod package;
 pub mod setting;
+pub mod users;
+pub mod webhooks;
 
 pub fn configure(config: &mut ServiceConfig) {
     package::configure(config);
     setting::configure(config);
     collaborators::configure(config);
+    index::configure(config);
+    users::configure(config);
+    organizations::configure(config);
+    webhooks::configure(config);
 }
@@ -0,0 +1,374 @@
+#[cfg(test)]
+use crate::test::mock::MockHttpRequest as HttpRequest;
+#[cfg(not(test))]
+use jelly::actix_web::HttpRequest;
+
+use diesel::result::{DatabaseErrorKind, Error as DBError};
+use jelly::actix_web::web;
+use jelly::actix_web::web::Path;
+use jelly::prelude::Error::*;
+use jelly::prelude::*;
+use jelly::utils::error_constants::*;
+use jelly::{DieselPgConnection, Result};
+use serde_json::json;
+
+use crate::accounts::Account;
+use crate::api::auth::ApiAccount;
+use crate::api::collaborators::views::CollaboratorJson;
+use crate::api::organizations::views::{
+    OrganizationJson, OrganizationMemberJson, OrganizationResponse, TeamJson, TeamResponse,
+};
+use crate::organizations::jobs::invite_member::SendOrganizationInvitationEmail;
+use crate::organizations::models::organization::{Organization, OrganizationRole};
+use crate::organizations::models::team::Team;
+use crate::package_collaborators::models::owner_invitation::OwnerInvitation;
+use crate::package_collaborators::package_collaborator::{PackageCollaborator, Role};
+use crate::packages::Package;
+use crate::settings::models::token::EndpointScope;
+
+pub async fn create_organization(
+    request: HttpRequest,
+    api_account: ApiAccount,
+    json: web::Json<OrganizationJson>,
+) -> Result<HttpResponse> {
+    let db = request.db_pool().map_err(|e| ApiServerError(Box::new(e)))?;
+    let conn = db.get().map_err(|e| ApiServerError(Box::new(e)))?;
+    api_account.authorize_scope(EndpointScope::ManageCollaborators)?;
+    check_name(&json.name)?;
+
+    let organization = Organization::create(
+        json.name.trim(),
+        json.description.trim(),
+        api_account.account.id,
+        &conn,
+    )
+    .map_err(|e| match e {
+        Error::Database(DBError::DatabaseError(DatabaseErrorKind::UniqueViolation, _)) => {
+            ApiBadRequest(MSG_ORGANIZATION_ALREADY_EXISTED, Box::new(e))
+        }
+        _ => ApiServerError(Box::new(e)),
+    })?;
+
+    organization_response(organization, &conn)
+}
+
+pub async fn get_organization(
+    request: HttpRequest,
+    Path(organization_slug): Path<String>,
+) -> Result<HttpResponse> {
+    let db = request.db_pool().map_err(|e| ApiServerError(Box::new(e)))?;
+    let conn = db.get().map_err(|e| ApiServerError(Box::new(e)))?;
+    let organization = Organization::get_by_slug(&organization_slug, &conn)
+        .map_err(|e| ApiNotFound(MSG_ORGANIZATION_NOT_FOUND, Box::new(e)))?;
+
+    organization_response(organization, &conn)
+}
+
+pub async fn invite_member(
+    request: HttpRequest,
+    api_account: ApiAccount,
+    Path(organization_slug): Path<String>,
+    json: web::Json<OrganizationMemberJson>,
+) -> Result<HttpResponse> {
+    let db = request.db_pool().map_err(|e| ApiServerError(Box::new(e)))?;
+    let conn = db.get().map_err(|e| ApiServerError(Box::new(e)))?;
+    let organization = get_administered_organization(&organization_slug, &api_account, &conn)?;
+
+    let invited_account = Account::get_by_email_or_gh_login(&json.user, db)
+        .map_err(|e| ApiNotFound(MSG_ACCOUNT_NOT_FOUND, Box::new(e)))?;
+    if Organization::get_member(organization.id, invited_account.id, &conn).is_ok() {
+        return Err(ApiBadRequest(
+            MSG_ORGANIZATION_MEMBER_ALREADY_EXISTED,
+            Box::new(Error::Generic(format!(
+                "Member already existed. uid: {}, organization id: {}",
+                invited_account.id, organization.id,
+            ))),
+        ));
+    }
+
+    let invitation = OwnerInvitation::create_for_organization(
+        invited_account.id,
+        api_account.account.id,
+        organization.id,
+        json.role.unwrap_or(OrganizationRole::Member),
+        None,
+        &conn,
+    )
+    .map_err(|e| ApiBadRequest(MSG_INVITATION_ALREADY_EXISTED, Box::new(e)))?;
+    if !invited_account.is_generated_email() {
+        request.queue(SendOrganizationInvitationEmail {
+            to: invited_account.email,
+            organization_name: organization.name,
+            token: invitation.token,
+        })?;
+    }
+
+    Ok(HttpResponse::Ok().json(json!({
+        "ok": true,
+        "msg": MSG_SUCCESSFULLY_INVITED_ORGANIZATION_MEMBER,
+    })))
+}
+
+/// Removes a member, or a pending invitation. Members can also remove themselves.
+pub async fn remove_member(
+    request: HttpRequest,
+    api_account: ApiAccount,
+    Path(organization_slug): Path<String>,
+    json: web::Json<CollaboratorJson>,
+) -> Result<HttpResponse> {
+    let db = request.db_pool().map_err(|e| ApiServerError(Box::new(e)))?;
+    let conn = db.get().map_err(|e| ApiServerError(Box::new(e)))?;
+    api_account.authorize_scope(EndpointScope::ManageCollaborators)?;
+    let organization = Organization::get_by_slug(&organization_slug, &conn)
+        .map_err(|e| ApiNotFound(MSG_ORGANIZATION_NOT_FOUND, Box::new(e)))?;
+    let target_account = Account::get_by_email_or_gh_login(&json.user, db)
+        .map_err(|e| ApiNotFound(MSG_ACCOUNT_NOT_FOUND, Box::new(e)))?;
+    if target_account.id != api_account.account.id {
+        check_admin(&organization, &api_account, &conn)?;
+    }
+
+    let num_deleted_invitations =
+        OwnerInvitation::delete_by_organization_id(target_account.id, organization.id, &conn)
+            .map_err(|e| ApiServerError(Box::new(e)))?;
+    if num_deleted_invitations == 0 {
+        let member = Organization::get_member(organization.id, target_account.id, &conn)
+            .map_err(|e| ApiNotFound(MSG_ORGANIZATION_MEMBER_NOT_FOUND, Box::new(e)))?;
+        if member.role == OrganizationRole::Admin as i32 {
+            let admin_count = Organization::count_admins(organization.id, &conn)
+                .map_err(|e| ApiServerError(Box::new(e)))?;
+            if admin_count <= 1 {
+                return Err(ApiBadRequest(
+                    MSG_CANNOT_REMOVE_LAST_ORGANIZATION_ADMIN,
+                    Box::new(Error::Generic(format!(
+                        "Last admin can't be removed. uid: {}, organization id: {}",
+                        target_account.id, organization.id
+                    ))),
+                ));
+            }
+        }
+        Organization::remove_member(organization.id, target_account.id, &conn)
+            .map_err(|e| ApiServerError(Box::new(e)))?;
+    }
+
+    Ok(HttpResponse::Ok().json(json!({
+        "ok": true,
+        "msg": MSG_SUCCESSFULLY_REMOVED_ORGANIZATION_MEMBER,
+    })))
+}
+
+pub async fn create_team(
+    request: HttpRequest,
+    api_account: ApiAccount,
+    Path(organization_slug): Path<String>,
+    json: web::Json<TeamJson>,
+) -> Result<HttpResponse> {
+    let db = request.db_pool().map_err(|e| ApiServerError(Box::new(e)))?;
+    let conn = db.get().map_err(|e| ApiServerError(Box::new(e)))?;
+    let organization = get_administered_organization(&organization_slug, &api_account, &conn)?;
+    check_name(&json.name)?;
+
+    let team = Team::create(
+        organization.id,
+        json.name.trim(),
+        json.is_collaborator,
+        &conn,
+    )
+    .map_err(|e| match e {
+        Error::Database(DBError::DatabaseError(DatabaseErrorKind::UniqueViolation, _)) => {
+            ApiBadRequest(MSG_TEAM_ALREADY_EXISTED, Box::new(e))
+        }
+        _ => ApiServerError(Box::new(e)),
+    })?;
+
+    Ok(HttpResponse::Ok().json(TeamResponse::from(team)))
+}
+
+pub async fn add_team_member(
+    request: HttpRequest,
+    api_account: ApiAccount,
+    Path((organization_slug, team_slug)): Path<(String, String)>,
+    json: web::Json<CollaboratorJson>,
+) -> Result<HttpResponse> {
+    let db = request.db_pool().map_err(|e| ApiServerError(Box::new(e)))?;
+    let conn = db.get().map_err(|e| ApiServerError(Box::new(e)))?;
+    let organization = get_administered_organization(&organization_slug, &api_account, &conn)?;
+    let team = Team::get_by_slug(organization.id, &team_slug, &conn)
+        .map_err(|e| ApiNotFound(MSG_TEAM_NOT_FOUND, Box::new(e)))?;
+
+    // Only members of the organization can join its teams
+    let account = Account::get_by_email_or_gh_login(&json.user, db)
+        .map_err(|e| ApiNotFound(MSG_ACCOUNT_NOT_FOUND, Box::new(e)))?;
+    Organization::get_member(organization.id, account.id, &conn)
+        .map_err(|e| ApiBadRequest(MSG_ORGANIZATION_MEMBER_NOT_FOUND, Box::new(e)))?;
+    Team::add_member(team.id, account.id, &conn)
+        .map_err(|e| ApiBadRequest(MSG_TEAM_MEMBER_ALREADY_EXISTED, Box::new(e)))?;
+
+    Ok(HttpResponse::Ok().json(json!({
+        "ok": true,
+        "msg": MSG_SUCCESSFULLY_ADDED_TEAM_MEMBER,
+    })))
+}
+
+pub async fn remove_team_member(
+    request: HttpRequest,
+    api_account: ApiAccount,
+    Path((organization_slug, team_slug)): Path<(String, String)>,
+    json: web::Json<CollaboratorJson>,
+) -> Result<HttpResponse> {
+    let db = request.db_pool().map_err(|e| ApiServerError(Box::new(e)))?;
+    let conn = db.get().map_err(|e| ApiServerError(Box::new(e)))?;
+    let organization = get_administered_organization(&organization_slug, &api_account, &conn)?;
+    let team = Team::get_by_slug(organization.id, &team_slug, &conn)
+        .map_err(|e| ApiNotFound(MSG_TEAM_NOT_FOUND, Box::new(e)))?;
+
+    let account = Account::get_by_email_or_gh_login(&json.user, db)
+        .map_err(|e| ApiNotFound(MSG_ACCOUNT_NOT_FOUND, Box::new(e)))?;
+    let num_deleted_members =
+        Team::remove_member(team.id, account.id, &conn).map_err(|e| ApiServerError(Box::new(e)))?;
+    if num_deleted_members == 0 {
+        return Err(ApiNotFound(
+            MSG_ORGANIZATION_MEMBER_NOT_FOUND,
+            Box::new(Error::Generic(format!(
+                "Account is not a member of the team. uid: {}, team id: {}",
+                account.id, team.id
+            ))),
+        ));
+    }
+
+    Ok(HttpResponse::Ok().json(json!({
+        "ok": true,
+        "msg": MSG_SUCCESSFULLY_REMOVED_TEAM_MEMBER,
+    })))
+}
+
+/// Transfers a package to the organization. The requester must own the package and
+/// administer the organization.
+pub async fn add_package(
+    request: HttpRequest,
+    api_account: ApiAccount,
+    Path((organization_slug, package_slug)): Path<(String, String)>,
+) -> Result<HttpResponse> {
+    let db = request.db_pool().map_err(|e| ApiServerError(Box::new(e)))?;
+    let conn = db.get().map_err(|e| ApiServerError(Box::new(e)))?;
+    let organization = get_administered_organization(&organization_slug, &api_account, &conn)?;
+    let package = Package::get_by_slug(&package_slug, &conn)
+        .map_err(|e| ApiNotFound(MSG_PACKAGE_NOT_FOUND, Box::new(e)))?;
+    api_account.authorize(
+        EndpointScope::ManageCollaborators,
+        &package.name,
+        &package.slug,
+    )?;
+
+    let role =
+        PackageCollaborator::get_role(&package, api_account.account.id, &conn).map_err(|e| {
+            ApiForbidden(
+                MSG_UNAUTHORIZED_TO_TRANSFER_PACKAGE_TO_ORGANIZATION,
+                Box::new(e),
+            )
+        })?;
+    if role != Role::Owner {
+        return Err(ApiForbidden(
+            MSG_UNAUTHORIZED_TO_TRANSFER_PACKAGE_TO_ORGANIZATION,
+            Box::new(Error::Generic(format!(
+                "Only owners can transfer packages. uid: {}, package id: {}",
+                api_account.account.id, package.id
+            ))),
+        ));
+    }
+    Organization::set_package_organization(package.id, Some(organization.id), &conn)
+        .map_err(|e| ApiServerError(Box::new(e)))?;
+
+    Ok(HttpResponse::Ok().json(json!({
+        "ok": true,
+        "msg": MSG_SUCCESSFULLY_TRANSFERRED_PACKAGE_TO_ORGANIZATION,
+    })))
+}
+
+pub async fn remove_package(
+    request: HttpRequest,
+    api_account: ApiAccount,
+    Path((organization_slug, package_slug)): Path<(String, String)>,
+) -> Result<HttpResponse> {
+    let db = request.db_pool().map_err(|e| ApiServerError(Box::new(e)))?;
+    let conn = db.get().map_err(|e| ApiServerError(Box::new(e)))?;
+    let organization = get_administered_organization(&organization_slug, &api_account, &conn)?;
+    let package = Package::get_by_slug(&package_slug, &conn)
+        .map_err(|e| ApiNotFound(MSG_PACKAGE_NOT_FOUND, Box::new(e)))?;
+    api_account.authorize(
+        EndpointScope::ManageCollaborators,
+        &package.name,
+        &package.slug,
+    )?;
+    if package.organization_id != Some(organization.id) {
+        return Err(ApiNotFound(
+            MSG_PACKAGE_NOT_FOUND,
+            Box::new(Error::Generic(format!(
+                "Package isn't owned by the organization. package id: {}, organization id: {}",
+                package.id, organization.id
+            ))),
+        ));
+    }
+    Organization::set_package_organization(package.id, None, &conn)
+        .map_err(|e| ApiServerError(Box::new(e)))?;
+
+    Ok(HttpResponse::Ok().json(json!({
+        "ok": true,
+        "msg": MSG_SUCCESSFULLY_REMOVED_PACKAGE_FROM_ORGANIZATION,
+    })))
+}
+
+// Names must have a slug
+fn check_name(name: &str) -> Result<()> {
+    if slug::slugify(name).is_empty() {
+        return Err(ApiBadRequest(
+            MSG_INVALID_NAME,
+            Box::new(Error::Generic(format!("Invalid name: {}", name))),
+        ));
+    }
+    Ok(())
+}
+
+fn organization_response(
+    organization: Organization,
+    conn: &DieselPgConnection,
+) -> Result<HttpResponse> {
+    let members = Organization::get_members(organization.id, conn)
+        .map_err(|e| ApiServerError(Box::new(e)))?;
+    let teams = Team::get_by_organization_id(organization.id, conn)
+        .map_err(|e| ApiServerError(Box::new(e)))?;
+
+    Ok(HttpResponse::Ok().json(OrganizationResponse::new(organization, members, teams)))
+}
+
+fn get_administered_organization(
+    organization_slug: &str,
+    api_account: &ApiAccount,
+    conn: &DieselPgConnection,
+) -> Result<Organization> {
+    api_account.authorize_scope(EndpointScope::ManageCollaborators)?;
+    let organization = Organization::get_by_slug(organization_slug, conn)
+        .map_err(|e| ApiNotFound(MSG_ORGANIZATION_NOT_FOUND, Box::new(e)))?;
+    check_admin(&organization, api_account, conn)?;
+
+    Ok(organization)
+}
+
+fn check_admin(
+    organization: &Organization,
+    api_account: &ApiAccount,
+    conn: &DieselPgConnection,
+) -> Result<()> {
+    let member = Organization::get_member(organization.id, api_account.account.id, conn)
+        .map_err(|e| ApiForbidden(MSG_UNAUTHORIZED_TO_MANAGE_ORGANIZATION, Box::new(e)))?;
+    if member.role != OrganizationRole::Admin as i32 {
+        return Err(ApiForbidden(
+            MSG_UNAUTHORIZED_TO_MANAGE_ORGANIZATION,
+            Box::new(Error::Generic(format!(
+                "Only admins can manage the organization. uid: {}, organization id: {}",
+                api_account.account.id, organization.id
+            ))),
+        ));
+    }
+
+    Ok(())
+}
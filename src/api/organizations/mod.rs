@@ -0,0 +1,33 @@
+use jelly::actix_web::web::{delete, get, post, resource, scope, ServiceConfig};
+
+pub mod controllers;
+#[cfg(test)]
+mod tests;
+pub mod views;
+
+pub fn configure(config: &mut ServiceConfig) {
+    config.service(
+        scope("/api/v1/organizations")
+            .service(resource("").route(post().to(controllers::create_organization)))
+            .service(
+                scope("/{organization_slug}")
+                    .service(resource("").route(get().to(controllers::get_organization)))
+                    .service(
+                        resource("/members")
+                            .route(post().to(controllers::invite_member))
+                            .route(delete().to(controllers::remove_member)),
+                    )
+                    .service(resource("/teams").route(post().to(controllers::create_team)))
+                    .service(
+                        resource("/teams/{team_slug}/members")
+                            .route(post().to(controllers::add_team_member))
+                            .route(delete().to(controllers::remove_team_member)),
+                    )
+                    .service(
+                        resource("/packages/{package_slug}")
+                            .route(post().to(controllers::add_package))
+                            .route(delete().to(controllers::remove_package)),
+                    ),
+            ),
+    );
+}
@@ -0,0 +1,299 @@
+use crate::accounts::Account;
+use crate::api::auth::{ApiAccount, BearerAccount};
+use crate::api::collaborators::views::CollaboratorJson;
+use crate::api::organizations::controllers::{
+    add_package, add_team_member, create_organization, create_team, invite_member, remove_member,
+};
+use crate::api::organizations::views::{
+    OrganizationJson, OrganizationMemberJson, OrganizationResponse, TeamJson,
+};
+use crate::api::package::controller::yank_version;
+use crate::organizations::jobs::invite_member::SendOrganizationInvitationEmail;
+use crate::organizations::models::organization::{Organization, OrganizationRole};
+use crate::organizations::models::team::Team;
+use crate::package_collaborators::models::owner_invitation::OwnerInvitation;
+use crate::packages::Package;
+use crate::test::util::{setup_package, setup_user};
+use crate::test::{mock, DatabaseTestContext, DB_POOL};
+
+use jelly::actix_web::body::{Body, ResponseBody};
+use jelly::actix_web::http::StatusCode;
+use jelly::actix_web::web::{Json, Path};
+use jelly::error::Error;
+use jelly::utils::error_constants::{
+    MSG_CANNOT_REMOVE_LAST_ORGANIZATION_ADMIN, MSG_ORGANIZATION_ALREADY_EXISTED,
+    MSG_ORGANIZATION_MEMBER_NOT_FOUND, MSG_UNAUTHORIZED_TO_MANAGE_ORGANIZATION,
+    MSG_UNAUTHORIZED_TO_TRANSFER_PACKAGE_TO_ORGANIZATION,
+};
+
+fn api_account(uid: i32) -> ApiAccount {
+    ApiAccount {
+        account: Account::get(uid, &DB_POOL).unwrap(),
+        token: None,
+    }
+}
+
+fn mock_request() -> mock::MockHttpRequest {
+    let mut mock_http_request = mock::MockHttpRequest::new();
+    mock_http_request
+        .expect_db_pool()
+        .returning(|| Ok(&DB_POOL));
+    mock_http_request
+}
+
+fn body_text(body: &ResponseBody<Body>) -> String {
+    match body.as_ref().unwrap() {
+        Body::Bytes(bytes) => String::from_utf8(bytes.to_vec()).unwrap(),
+        _ => panic!("Unexpected response body"),
+    }
+}
+
+fn user_json(user: &str) -> Json<CollaboratorJson> {
+    Json(CollaboratorJson {
+        user: user.to_string(),
+    })
+}
+
+fn create_organization_with_member(admin_uid: i32, member_uid: i32) -> Organization {
+    let conn = DB_POOL.get().unwrap();
+    let organization = Organization::create("Move Labs", "", admin_uid, &conn).unwrap();
+    Organization::add_member(organization.id, member_uid, OrganizationRole::Member, &conn).unwrap();
+    organization
+}
+
+fn create_package(owner_id: i32) -> Package {
+    let pid = setup_package(
+        "MoveStdlib",
+        "https://github.com/move-language/move",
+        owner_id,
+    );
+    Package::get(pid, &DB_POOL).unwrap()
+}
+
+#[actix_rt::test]
+async fn create_organization_works() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let uid = setup_user(None, None);
+    let json = || {
+        Json(OrganizationJson {
+            name: "Move Labs".to_string(),
+            description: "Move tooling".to_string(),
+        })
+    };
+    let response = create_organization(mock_request(), api_account(uid), json())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let organization =
+        serde_json::from_str::<OrganizationResponse>(&body_text(response.body())).unwrap();
+    assert_eq!(organization.slug, "move-labs");
+    assert_eq!(organization.members.len(), 1);
+    assert_eq!(organization.members[0].role, OrganizationRole::Admin);
+
+    let response = create_organization(mock_request(), api_account(uid), json()).await;
+    assert!(
+        matches!(response, Err(Error::ApiBadRequest(msg, _)) if msg == MSG_ORGANIZATION_ALREADY_EXISTED)
+    );
+}
+
+#[actix_rt::test]
+async fn invite_member_works() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let admin_uid = setup_user(None, None);
+    let member_uid = setup_user(Some("member@host.com".to_string()), None);
+    let invited_uid = setup_user(Some("invited@host.com".to_string()), None);
+    let organization = create_organization_with_member(admin_uid, member_uid);
+
+    let json = || {
+        Json(OrganizationMemberJson {
+            user: "invited@host.com".to_string(),
+            role: None,
+        })
+    };
+    let response = invite_member(
+        mock_request(),
+        api_account(member_uid),
+        Path(organization.slug.clone()),
+        json(),
+    )
+    .await;
+    assert!(
+        matches!(response, Err(Error::ApiForbidden(msg, _)) if msg == MSG_UNAUTHORIZED_TO_MANAGE_ORGANIZATION)
+    );
+
+    let mut mock_http_request = mock_request();
+    mock_http_request
+        .expect_queue::<SendOrganizationInvitationEmail>()
+        .withf(|job| job.to == "invited@host.com" && job.organization_name == "Move Labs")
+        .times(1)
+        .returning(|_| Ok(()));
+    let response = invite_member(
+        mock_http_request,
+        api_account(admin_uid),
+        Path(organization.slug.clone()),
+        json(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let conn = DB_POOL.get().unwrap();
+    assert_eq!(
+        OwnerInvitation::delete_by_organization_id(invited_uid, organization.id, &conn).unwrap(),
+        1
+    );
+}
+
+#[actix_rt::test]
+async fn remove_member_keeps_an_admin() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let admin_uid = setup_user(None, None);
+    let member_uid = setup_user(Some("member@host.com".to_string()), None);
+    let organization = create_organization_with_member(admin_uid, member_uid);
+
+    let response = remove_member(
+        mock_request(),
+        api_account(admin_uid),
+        Path(organization.slug.clone()),
+        user_json("email@host.com"),
+    )
+    .await;
+    assert!(
+        matches!(response, Err(Error::ApiBadRequest(msg, _)) if msg == MSG_CANNOT_REMOVE_LAST_ORGANIZATION_ADMIN)
+    );
+
+    // Members can leave by themselves
+    let response = remove_member(
+        mock_request(),
+        api_account(member_uid),
+        Path(organization.slug.clone()),
+        user_json("member@host.com"),
+    )
+    .await
+    .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(
+        Organization::get_member(organization.id, member_uid, &DB_POOL.get().unwrap()).is_err()
+    );
+}
+
+#[actix_rt::test]
+async fn add_team_member_requires_organization_member() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let admin_uid = setup_user(None, None);
+    let member_uid = setup_user(Some("member@host.com".to_string()), None);
+    setup_user(Some("outsider@host.com".to_string()), None);
+    let organization = create_organization_with_member(admin_uid, member_uid);
+
+    let response = create_team(
+        mock_request(),
+        api_account(admin_uid),
+        Path(organization.slug.clone()),
+        Json(TeamJson {
+            name: "Core".to_string(),
+            is_collaborator: true,
+        }),
+    )
+    .await
+    .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = add_team_member(
+        mock_request(),
+        api_account(admin_uid),
+        Path((organization.slug.clone(), "core".to_string())),
+        user_json("outsider@host.com"),
+    )
+    .await;
+    assert!(
+        matches!(response, Err(Error::ApiBadRequest(msg, _)) if msg == MSG_ORGANIZATION_MEMBER_NOT_FOUND)
+    );
+
+    let response = add_team_member(
+        mock_request(),
+        api_account(admin_uid),
+        Path((organization.slug.clone(), "core".to_string())),
+        user_json("member@host.com"),
+    )
+    .await
+    .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let conn = DB_POOL.get().unwrap();
+    let team = Team::get_by_slug(organization.id, "core", &conn).unwrap();
+    assert_eq!(
+        Team::get_member_ids(team.id, &conn).unwrap(),
+        vec![member_uid]
+    );
+}
+
+#[actix_rt::test]
+async fn organization_admins_own_its_packages() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let owner_uid = setup_user(None, None);
+    let admin_uid = setup_user(Some("admin@host.com".to_string()), None);
+    let package = create_package(owner_uid);
+    let organization = create_organization_with_member(admin_uid, owner_uid);
+
+    // The owner of the package isn't an admin of the organization yet
+    let response = add_package(
+        mock_request(),
+        api_account(owner_uid),
+        Path((organization.slug.clone(), package.slug.clone())),
+    )
+    .await;
+    assert!(
+        matches!(response, Err(Error::ApiForbidden(msg, _)) if msg == MSG_UNAUTHORIZED_TO_MANAGE_ORGANIZATION)
+    );
+    // The admin doesn't own the package
+    let response = add_package(
+        mock_request(),
+        api_account(admin_uid),
+        Path((organization.slug.clone(), package.slug.clone())),
+    )
+    .await;
+    assert!(
+        matches!(response, Err(Error::ApiForbidden(msg, _)) if msg == MSG_UNAUTHORIZED_TO_TRANSFER_PACKAGE_TO_ORGANIZATION)
+    );
+
+    Organization::remove_member(organization.id, owner_uid, &DB_POOL.get().unwrap()).unwrap();
+    Organization::add_member(
+        organization.id,
+        owner_uid,
+        OrganizationRole::Admin,
+        &DB_POOL.get().unwrap(),
+    )
+    .unwrap();
+    let response = add_package(
+        mock_request(),
+        api_account(owner_uid),
+        Path((organization.slug.clone(), package.slug.clone())),
+    )
+    .await
+    .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        Package::get(package.id, &DB_POOL).unwrap().organization_id,
+        Some(organization.id)
+    );
+
+    // Other admins of the organization can now yank its versions
+    let response = yank_version(
+        mock_request(),
+        BearerAccount(Some(api_account(admin_uid))),
+        Path((package.slug.clone(), "1.0.0".to_string())),
+        None,
+    )
+    .await
+    .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
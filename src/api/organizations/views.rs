@@ -0,0 +1,86 @@
+use serde::{Deserialize, Serialize};
+
+use crate::organizations::models::organization::{
+    Organization, OrganizationMemberQuery, OrganizationRole,
+};
+use crate::organizations::models::team::Team;
+
+#[derive(Deserialize)]
+pub struct OrganizationJson {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+}
+
+#[derive(Deserialize)]
+pub struct OrganizationMemberJson {
+    pub user: String,
+    /// New members are invited as `member` unless told otherwise.
+    pub role: Option<OrganizationRole>,
+}
+
+#[derive(Deserialize)]
+pub struct TeamJson {
+    pub name: String,
+    #[serde(default)]
+    pub is_collaborator: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct OrganizationMemberResponse {
+    pub name: String,
+    pub slug: Option<String>,
+    pub role: OrganizationRole,
+}
+
+impl From<OrganizationMemberQuery> for OrganizationMemberResponse {
+    fn from(member: OrganizationMemberQuery) -> Self {
+        OrganizationMemberResponse {
+            name: member.name,
+            slug: member.slug,
+            role: OrganizationRole::from_i32(member.role),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct TeamResponse {
+    pub name: String,
+    pub slug: String,
+    pub is_collaborator: bool,
+}
+
+impl From<Team> for TeamResponse {
+    fn from(team: Team) -> Self {
+        TeamResponse {
+            name: team.name,
+            slug: team.slug,
+            is_collaborator: team.is_collaborator,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct OrganizationResponse {
+    pub name: String,
+    pub slug: String,
+    pub description: String,
+    pub members: Vec<OrganizationMemberResponse>,
+    pub teams: Vec<TeamResponse>,
+}
+
+impl OrganizationResponse {
+    pub fn new(
+        organization: Organization,
+        members: Vec<OrganizationMemberQuery>,
+        teams: Vec<Team>,
+    ) -> Self {
+        OrganizationResponse {
+            name: organization.name,
+            slug: organization.slug,
+            description: organization.description,
+            members: members.into_iter().map(From::from).collect(),
+            teams: teams.into_iter().map(From::from).collect(),
+        }
+    }
+}
@@ -5,26 +5,48 @@ use jelly::actix_web::HttpRequest;
 
 use diesel::result::DatabaseErrorKind;
 use diesel::result::Error as DBError;
+use jelly::actix_web::http::header::RETRY_AFTER;
 use jelly::actix_web::web;
+use jelly::actix_web::web::Path;
+use jelly::chrono::Duration;
+use jelly::guards::{RateLimit, RateLimitStore};
+use jelly::prelude::Error::*;
 use jelly::prelude::*;
-use jelly::Result;
+use jelly::utils::error_constants::*;
+use jelly::{DieselPgConnection, Result};
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 
 #[cfg(not(test))]
 use crate::github_service::GithubService;
 #[cfg(test)]
 use crate::test::mock::GithubService;
 
-use crate::api::package::view::PackageBadgeRespond;
-use crate::packages::Package;
-use crate::packages::models::{PackageSortField, PackageSortOrder};
-use crate::settings::models::token::ApiToken;
+use crate::accounts::Account;
+use crate::api::auth::{ApiAccount, BearerAccount};
+use crate::api::package::view::{
+    ClaimTokenResponse, OwnerResponse, PackageBadgeRespond, PackageListResponse, PackageResponse,
+    PackageSummaryResponse, PaginationMeta, VersionResponse,
+};
+use crate::jobs::{CrawlRepository, CreateShadowPackage};
+use crate::package_collaborators::models::package_claim::{PackageClaim, CLAIM_FILE_PATH};
+use crate::package_collaborators::package_collaborator::{PackageCollaborator, Role};
+use crate::packages::{Package, PackageVersion, PackageVersionSort};
+use crate::packages::models::package_download::{client_fingerprint, PackageDownload};
+use crate::packages::models::package_event::{PackageEvent, PackageEventKind};
+use crate::packages::models::{PackageSortField, PackageSortOrder, PACKAGES_PER_PAGE};
+use crate::packages::views::serializer::slugify_package_name;
+use crate::settings::models::token::EndpointScope;
+use crate::source_hosts;
 use crate::utils::presenter::validate_version;
+use crate::utils::request_utils::ClientIp;
 
 #[derive(Serialize, Deserialize)]
 pub struct PackageRequest {
     pub github_repo_url: String,
     pub total_files: i32,
+    // Not needed when the token is sent in the `Authorization` header
+    #[serde(default)]
     pub token: String,
     pub subdir: String,
 }
@@ -36,6 +58,8 @@ pub struct PackageSearch {
 
 pub async fn register_package(
     request: HttpRequest,
+    bearer: BearerAccount,
+    client_ip: ClientIp,
     mut req: web::Json<PackageRequest>,
 ) -> Result<HttpResponse> {
     let db = match request.db_pool() {
@@ -45,14 +69,12 @@ pub async fn register_package(
                 .body("Something went wrong, please try again later."))
         }
     };
-    if ApiToken::get(&req.token, db).is_err() {
-        return Ok(HttpResponse::BadRequest().body("Invalid API token."));
-    }
-
-    let token_account_id = match ApiToken::associated_account(&req.token, db) {
-        Ok(account) => account.id,
+    let api_account = match bearer.or_token(&req.token, &request) {
+        Ok(api_account) => api_account,
+        Err(ApiUnauthorized(msg, _)) => return Ok(HttpResponse::BadRequest().body(msg)),
         Err(_) => return Ok(HttpResponse::BadRequest().body("Invalid API token.")),
     };
+    let token_account_id = api_account.account.id;
     let service = GithubService::new();
     if req.subdir.ends_with('\n') {
         req.subdir.pop();
@@ -69,9 +91,10 @@ pub async fn register_package(
         Err(_) => return Ok(HttpResponse::NotFound().body("Cannot get package info from Github.")),
     };
     if !req.subdir.is_empty() {
-        req.github_repo_url = format!(
-            "{}/blob/{}/{}",
-            req.github_repo_url, github_data.rev, req.subdir
+        req.github_repo_url = source_hosts::for_url(&req.github_repo_url).tree_url(
+            &req.github_repo_url,
+            &github_data.rev,
+            &req.subdir,
         );
     }
     let hints = validate_version(&github_data.version);
@@ -81,6 +104,32 @@ pub async fn register_package(
             hints.join("; ")
         )));
     }
+    let conn = match db.get() {
+        Ok(conn) => conn,
+        Err(_) => {
+            return Ok(HttpResponse::InternalServerError()
+                .body("Something went wrong, please try again later."))
+        }
+    };
+    let existing_package =
+        Package::get_by_name_and_repo_url(&github_data.name, &req.github_repo_url, &conn);
+    let authorized = match existing_package {
+        Ok(package) => {
+            if !package.meets_two_factor_requirement(token_account_id, &conn)? {
+                return Ok(HttpResponse::Forbidden().body(MSG_TWO_FACTOR_REQUIRED));
+            }
+            api_account.authorize(EndpointScope::PublishUpdate, &package.name, &package.slug)
+        }
+        Err(_) => api_account.authorize(
+            EndpointScope::PublishNew,
+            &github_data.name,
+            &slugify_package_name(&github_data.name),
+        ),
+    };
+    if let Err(ApiForbidden(msg, _)) = authorized {
+        return Ok(HttpResponse::Forbidden().body(msg));
+    }
+    let version = github_data.version.clone();
     let result = Package::create_from_crawled_data(
         &req.github_repo_url,
         &github_data.description.clone(),
@@ -92,7 +141,21 @@ pub async fn register_package(
         db,
     );
     match result {
-        Ok(res) => Ok(HttpResponse::Ok().body(res.slug)),
+        Ok(res) => {
+            // The version is already published, a missing entry in the audit log shouldn't
+            // fail the upload
+            if let Err(e) = PackageEvent::create(
+                res.id,
+                PackageEventKind::VersionPublished,
+                &api_account.event_actor(client_ip),
+                None,
+                &version,
+                &conn,
+            ) {
+                error!("Error recording the upload of {}: {:?}", res.slug, e);
+            }
+            Ok(HttpResponse::Ok().body(res.slug))
+        }
         Err(Error::Database(DBError::DatabaseError(kind, slug))) => {
             let domain = std::env::var("JELLY_DOMAIN").expect("JELLY_DOMAIN is not set");
             let package_slug = slug.message();
@@ -104,7 +167,8 @@ pub async fn register_package(
                     Please commit your changes to Github and try again."
                     ),
                     DatabaseErrorKind::ForeignKeyViolation => format!(
-                        "Only owners can update new versions. Please check the package information at \
+                        "Only owners and collaborators allowed to publish can upload new versions. \
+                    Please check the package information at \
                     {domain}/packages/{package_slug}."
                     ),
                     _ => "Something went wrong, please try again later.".to_string(),
@@ -119,6 +183,10 @@ pub async fn register_package(
     }
 }
 
+// Shadow packages are created from the source host, whose API quota every server shares
+pub const SHADOW_PACKAGES_PER_CLIENT_PER_HOUR: u32 = 10;
+pub const SHADOW_PACKAGES_PER_HOUR: u32 = 500;
+
 #[derive(Clone, Deserialize)]
 pub struct DownloadInfo {
     pub url: String,
@@ -127,14 +195,17 @@ pub struct DownloadInfo {
 }
 
 impl Validation for DownloadInfo {
+    /// Only repositories of the supported source hosts are counted, as unknown ones are
+    /// fetched from their host.
     fn is_valid(&mut self) -> bool {
-        !self.url.is_empty() && !self.rev.is_empty()
+        source_hosts::is_repo_url(&self.url) && !self.rev.is_empty()
     }
 }
 
 pub async fn increase_download_count(
     request: HttpRequest,
     form: web::Form<DownloadInfo>,
+    client_ip: ClientIp,
 ) -> Result<HttpResponse> {
     let mut form = form.into_inner();
     if !form.is_valid() {
@@ -148,14 +219,56 @@ pub async fn increase_download_count(
                 .body("Something went wrong, please try again later."))
         }
     };
-    let service = GithubService::new();
-    if let Ok(res) =
-        Package::increase_download_count(&form.url, &form.rev, &form.subdir, &service, db)
-    {
-        Ok(HttpResponse::Ok().body(res.to_string()))
-    } else {
-        Ok(HttpResponse::NotFound().body("Cannot find url or rev."))
+    let fingerprint = client_fingerprint(client_ip.0.as_deref());
+    let conn = db.get()?;
+    if let Some(version_id) = Package::find_version_id(&form.url, &form.subdir, &form.rev, &conn)? {
+        let counted = PackageDownload::record(version_id, &fingerprint, &conn)?;
+        return Ok(HttpResponse::Ok().body(if counted { "1" } else { "0" }));
+    }
+
+    // Unknown versions are fetched from the source host in the background, within limits.
+    // Every client is charged for its requests, while the budget shared by all clients is
+    // only charged for the jobs actually queued.
+    let client = format!("ip:{}", client_ip.0.as_deref().unwrap_or("unknown"));
+    let client_limit = RateLimit::new(
+        "shadow-packages",
+        SHADOW_PACKAGES_PER_CLIENT_PER_HOUR,
+        Duration::hours(1),
+    )
+    .with_store(RateLimitStore::Postgres);
+    if let Some(retry_after) = client_limit.check(&client, db)? {
+        return Ok(too_many_shadow_packages(retry_after));
+    }
+    if !PackageDownload::request_shadow_package(&form.url, &form.rev, &form.subdir, &conn)? {
+        return Ok(HttpResponse::Accepted().finish());
+    }
+    let global_limit = RateLimit::new(
+        "shadow-packages-all",
+        SHADOW_PACKAGES_PER_HOUR,
+        Duration::hours(1),
+    )
+    .with_store(RateLimitStore::Postgres);
+    if let Some(retry_after) = global_limit.check("all", db)? {
+        PackageDownload::forget_shadow_package(&form.url, &form.rev, &form.subdir, &conn)?;
+        return Ok(too_many_shadow_packages(retry_after));
+    }
+    let job = CreateShadowPackage {
+        url: form.url.clone(),
+        rev: form.rev.clone(),
+        subdir: form.subdir.clone(),
+        fingerprint,
+    };
+    if let Err(e) = request.queue(job) {
+        PackageDownload::forget_shadow_package(&form.url, &form.rev, &form.subdir, &conn)?;
+        return Err(e);
     }
+    Ok(HttpResponse::Accepted().finish())
+}
+
+fn too_many_shadow_packages(retry_after: i64) -> HttpResponse {
+    HttpResponse::TooManyRequests()
+        .header(RETRY_AFTER, retry_after.to_string())
+        .body(MSG_TOO_MANY_REQUESTS)
 }
 
 pub async fn search_package(
@@ -192,3 +305,402 @@ pub async fn package_badge_info(
     }
     Ok(HttpResponse::NotFound().finish())
 }
+
+#[derive(Serialize, Deserialize)]
+pub struct YankRequest {
+    #[serde(default)]
+    pub token: String,
+}
+
+// The request body is optional when the token is sent in the `Authorization` header
+pub async fn yank_version(
+    request: HttpRequest,
+    bearer: BearerAccount,
+    Path((package_slug, version)): Path<(String, String)>,
+    req: Option<web::Json<YankRequest>>,
+) -> Result<HttpResponse> {
+    let token = req.map(|req| req.into_inner().token).unwrap_or_default();
+    let api_account = bearer.or_token(&token, &request)?;
+    update_yanked(&request, &package_slug, &version, &api_account, true)
+}
+
+pub async fn unyank_version(
+    request: HttpRequest,
+    bearer: BearerAccount,
+    Path((package_slug, version)): Path<(String, String)>,
+    req: Option<web::Json<YankRequest>>,
+) -> Result<HttpResponse> {
+    let token = req.map(|req| req.into_inner().token).unwrap_or_default();
+    let api_account = bearer.or_token(&token, &request)?;
+    update_yanked(&request, &package_slug, &version, &api_account, false)
+}
+
+fn update_yanked(
+    request: &HttpRequest,
+    package_slug: &str,
+    version: &str,
+    api_account: &ApiAccount,
+    is_yanked: bool,
+) -> Result<HttpResponse> {
+    let db = request.db_pool().map_err(|e| ApiServerError(Box::new(e)))?;
+    let conn = db.get().map_err(|e| ApiServerError(Box::new(e)))?;
+
+    let package = Package::get_by_slug(package_slug, &conn)
+        .map_err(|e| ApiNotFound(MSG_PACKAGE_NOT_FOUND, Box::new(e)))?;
+    api_account.authorize(EndpointScope::Yank, &package.name, &package.slug)?;
+    let account = &api_account.account;
+
+    let role = PackageCollaborator::get_role(&package, account.id, &conn)
+        .map_err(|e| ApiForbidden(MSG_UNAUTHORIZED_TO_YANK_VERSION, Box::new(e)))?;
+    if role != Role::Owner {
+        return Err(ApiForbidden(
+            MSG_UNAUTHORIZED_TO_YANK_VERSION,
+            Box::new(Error::Generic(format!(
+                "Only owners can yank versions. uid: {}, package id: {}",
+                account.id, package.id
+            ))),
+        ));
+    }
+
+    let package_version = package
+        .get_version(&version.to_string(), &conn)
+        .map_err(|e| ApiNotFound(MSG_PACKAGE_VERSION_NOT_FOUND, Box::new(e)))?;
+    package_version
+        .set_yanked(is_yanked, &conn)
+        .map_err(|e| ApiServerError(Box::new(e)))?;
+
+    Ok(HttpResponse::Ok().json(json!({
+        "ok": true,
+        "msg": if is_yanked {
+            MSG_SUCCESSFULLY_YANKED_VERSION
+        } else {
+            MSG_SUCCESSFULLY_UNYANKED_VERSION
+        },
+    })))
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct CrawlRequest {
+    pub github_repo_url: String,
+    // Same format as for uploads, e.g. `language/move-stdlib/`
+    #[serde(default)]
+    pub subdir: String,
+}
+
+pub async fn crawl_repository(
+    request: HttpRequest,
+    api_account: ApiAccount,
+    req: web::Json<CrawlRequest>,
+) -> Result<HttpResponse> {
+    if !api_account.account.is_admin {
+        return Err(ApiForbidden(
+            MSG_ADMIN_REQUIRED,
+            Box::new(Error::Generic(format!(
+                "Only admins can trigger a crawl. uid: {}",
+                api_account.account.id
+            ))),
+        ));
+    }
+    let repository_url = source_hosts::normalize_repo_url(&req.github_repo_url);
+    if !source_hosts::is_repo_url(&repository_url) {
+        return Err(ApiBadRequest(
+            MSG_INVALID_REPOSITORY_URL,
+            Box::new(Error::Generic(format!(
+                "Invalid repository url: {}",
+                req.github_repo_url
+            ))),
+        ));
+    }
+
+    request
+        .queue(CrawlRepository {
+            repository_url,
+            path: format!("{}Move.toml", req.subdir.trim()),
+        })
+        .map_err(|e| ApiServerError(Box::new(e)))?;
+
+    Ok(HttpResponse::Accepted().json(json!({
+        "ok": true,
+        "msg": MSG_SUCCESSFULLY_QUEUED_CRAWL,
+    })))
+}
+
+/// Issues the token that proves control over the repository of a crawled package
+/// once committed to it.
+pub async fn get_claim_token(
+    request: HttpRequest,
+    api_account: ApiAccount,
+    Path(package_slug): Path<String>,
+) -> Result<HttpResponse> {
+    let db = request.db_pool().map_err(|e| ApiServerError(Box::new(e)))?;
+    let conn = db.get().map_err(|e| ApiServerError(Box::new(e)))?;
+    let package = get_unclaimed_package(&package_slug, &api_account, &conn)?;
+
+    let claim = PackageClaim::get_or_create(package.id, api_account.account.id, &conn)
+        .map_err(|e| ApiServerError(Box::new(e)))?;
+
+    Ok(HttpResponse::Ok().json(ClaimTokenResponse {
+        token: claim.token,
+        path: CLAIM_FILE_PATH.to_string(),
+    }))
+}
+
+/// Makes the user the owner of a crawled package once their claim token is found
+/// on the default branch of its repository.
+pub async fn claim_package_with_token(
+    request: HttpRequest,
+    api_account: ApiAccount,
+    Path(package_slug): Path<String>,
+) -> Result<HttpResponse> {
+    let db = request.db_pool().map_err(|e| ApiServerError(Box::new(e)))?;
+    let conn = db.get().map_err(|e| ApiServerError(Box::new(e)))?;
+    let package = get_unclaimed_package(&package_slug, &api_account, &conn)?;
+    let account = &api_account.account;
+
+    let claim = PackageClaim::get(package.id, account.id, &conn)
+        .map_err(|e| ApiBadRequest(MSG_CLAIM_TOKEN_NOT_ISSUED, Box::new(e)))?;
+    let (repo_url, _) =
+        source_hosts::for_url(&package.repository_url).split_tree_url(&package.repository_url);
+    let claim_file = GithubService::new()
+        .fetch_default_branch_file(&repo_url, CLAIM_FILE_PATH)
+        .map_err(|e| ApiServerError(Box::new(e)))?;
+    if !claim_file.is_some_and(|content| claim.is_listed_in(&content)) {
+        return Err(ApiForbidden(
+            MSG_CLAIM_TOKEN_NOT_FOUND,
+            Box::new(Error::Generic(format!(
+                "Claim token not found in {}. uid: {}, package id: {}",
+                repo_url, account.id, package.id
+            ))),
+        ));
+    }
+
+    claim_package(&package, account.id, &conn)
+}
+
+/// Makes the user the owner of a crawled package hosted on Github when their linked
+/// Github account owns its repository, e.g. `octocat` for `https://github.com/octocat/move`.
+/// Movey can't see who else may push to repositories it doesn't belong to, so collaborators
+/// and repositories of organizations are claimed with a token instead.
+pub async fn claim_package_with_github(
+    request: HttpRequest,
+    api_account: ApiAccount,
+    Path(package_slug): Path<String>,
+) -> Result<HttpResponse> {
+    let db = request.db_pool().map_err(|e| ApiServerError(Box::new(e)))?;
+    let conn = db.get().map_err(|e| ApiServerError(Box::new(e)))?;
+    let package = get_unclaimed_package(&package_slug, &api_account, &conn)?;
+    let account = &api_account.account;
+
+    let github_login = account.github_login.as_ref().ok_or_else(|| {
+        ApiBadRequest(
+            MSG_GITHUB_ACCOUNT_NOT_LINKED,
+            Box::new(Error::Generic(format!(
+                "Account has no Github login. uid: {}",
+                account.id
+            ))),
+        )
+    })?;
+    let (repo_url, _) =
+        source_hosts::for_url(&package.repository_url).split_tree_url(&package.repository_url);
+    if !source_hosts::is_github_url(&repo_url) {
+        return Err(ApiBadRequest(
+            MSG_CLAIM_WITH_GITHUB_UNSUPPORTED,
+            Box::new(Error::Generic(format!(
+                "Package isn't hosted on Github. package id: {}",
+                package.id
+            ))),
+        ));
+    }
+    let owns_repository = source_hosts::owner(&repo_url)
+        .is_some_and(|(owner_login, _)| owner_login.eq_ignore_ascii_case(github_login));
+    if !owns_repository {
+        return Err(ApiForbidden(
+            MSG_NO_REPOSITORY_PUSH_ACCESS,
+            Box::new(Error::Generic(format!(
+                "{} doesn't own {}. uid: {}",
+                github_login, repo_url, account.id
+            ))),
+        ));
+    }
+
+    claim_package(&package, account.id, &conn)
+}
+
+// Crawled packages are the ones without collaborators
+fn get_unclaimed_package(
+    package_slug: &str,
+    api_account: &ApiAccount,
+    conn: &DieselPgConnection,
+) -> Result<Package> {
+    let package = Package::get_by_slug(package_slug, conn)
+        .map_err(|e| ApiNotFound(MSG_PACKAGE_NOT_FOUND, Box::new(e)))?;
+    api_account.authorize(EndpointScope::PublishNew, &package.name, &package.slug)?;
+
+    let collaborators = PackageCollaborator::get_by_package_id(package.id, conn)
+        .map_err(|e| ApiServerError(Box::new(e)))?;
+    if !collaborators.is_empty() {
+        return Err(ApiBadRequest(
+            MSG_PACKAGE_ALREADY_CLAIMED,
+            Box::new(Error::Generic(format!(
+                "Package already has collaborators. package id: {}",
+                package.id
+            ))),
+        ));
+    }
+
+    Ok(package)
+}
+
+fn claim_package(
+    package: &Package,
+    account_id: i32,
+    conn: &DieselPgConnection,
+) -> Result<HttpResponse> {
+    let is_claimed =
+        PackageClaim::claim(package.id, account_id, conn).map_err(|e| ApiServerError(Box::new(e)))?;
+    if !is_claimed {
+        return Err(ApiBadRequest(
+            MSG_PACKAGE_ALREADY_CLAIMED,
+            Box::new(Error::Generic(format!(
+                "Package was claimed by someone else. package id: {}",
+                package.id
+            ))),
+        ));
+    }
+
+    Ok(HttpResponse::Ok().json(json!({
+        "ok": true,
+        "msg": MSG_SUCCESSFULLY_CLAIMED_PACKAGE,
+    })))
+}
+
+#[derive(Deserialize)]
+pub struct PackageListParams {
+    pub field: Option<PackageSortField>,
+    pub order: Option<PackageSortOrder>,
+    pub page: Option<i64>,
+    pub per_page: Option<i64>,
+}
+
+pub const MAX_PACKAGES_PER_PAGE: i64 = 100;
+
+pub async fn get_packages(
+    request: HttpRequest,
+    params: web::Query<PackageListParams>,
+) -> Result<HttpResponse> {
+    let params = params.into_inner();
+    let db = request.db_pool().map_err(|e| ApiServerError(Box::new(e)))?;
+
+    let field = params.field.unwrap_or(PackageSortField::NewlyAdded);
+    let order = params.order.unwrap_or(match field {
+        PackageSortField::Name => PackageSortOrder::Asc,
+        _ => PackageSortOrder::Desc,
+    });
+    let page = params.page.unwrap_or(1);
+    let per_page = params.per_page.unwrap_or(PACKAGES_PER_PAGE);
+    if page < 1 || !(1..=MAX_PACKAGES_PER_PAGE).contains(&per_page) {
+        return Err(ApiBadRequest(
+            MSG_BAD_REQUEST,
+            Box::new(Error::Generic(format!(
+                "Invalid pagination. page: {}, per_page: {}",
+                page, per_page
+            ))),
+        ));
+    }
+
+    let (packages, total_count, total_pages) =
+        Package::all_packages(&field, &order, Some(page), Some(per_page), db)
+            .map_err(|e| ApiServerError(Box::new(e)))?;
+    Ok(HttpResponse::Ok().json(PackageListResponse {
+        packages: packages
+            .into_iter()
+            .map(PackageSummaryResponse::from)
+            .collect(),
+        meta: PaginationMeta {
+            page,
+            per_page,
+            total_count,
+            total_pages,
+        },
+    }))
+}
+
+pub async fn get_package(
+    request: HttpRequest,
+    Path(package_slug): Path<String>,
+) -> Result<HttpResponse> {
+    let db = request.db_pool().map_err(|e| ApiServerError(Box::new(e)))?;
+    let conn = db.get().map_err(|e| ApiServerError(Box::new(e)))?;
+    let package = Package::get_by_slug(&package_slug, &conn)
+        .map_err(|e| ApiNotFound(MSG_PACKAGE_NOT_FOUND, Box::new(e)))?;
+    let latest_version = package.get_latest_version(&conn).ok();
+
+    Ok(HttpResponse::Ok().json(PackageResponse::new(package, latest_version)))
+}
+
+pub async fn get_package_versions(
+    request: HttpRequest,
+    Path(package_slug): Path<String>,
+) -> Result<HttpResponse> {
+    let db = request.db_pool().map_err(|e| ApiServerError(Box::new(e)))?;
+    let conn = db.get().map_err(|e| ApiServerError(Box::new(e)))?;
+    let package = Package::get_by_slug(&package_slug, &conn)
+        .map_err(|e| ApiNotFound(MSG_PACKAGE_NOT_FOUND, Box::new(e)))?;
+    let versions = PackageVersion::from_package_id(package.id, &PackageVersionSort::Latest, db)
+        .map_err(|e| ApiServerError(Box::new(e)))?;
+
+    Ok(HttpResponse::Ok().json(
+        versions
+            .into_iter()
+            .map(|package_version| VersionResponse::new(package_version, false))
+            .collect::<Vec<VersionResponse>>(),
+    ))
+}
+
+pub async fn get_package_version(
+    request: HttpRequest,
+    Path((package_slug, version)): Path<(String, String)>,
+) -> Result<HttpResponse> {
+    let db = request.db_pool().map_err(|e| ApiServerError(Box::new(e)))?;
+    let conn = db.get().map_err(|e| ApiServerError(Box::new(e)))?;
+    let package = Package::get_by_slug(&package_slug, &conn)
+        .map_err(|e| ApiNotFound(MSG_PACKAGE_NOT_FOUND, Box::new(e)))?;
+    let package_version = package
+        .get_version(&version, &conn)
+        .map_err(|e| ApiNotFound(MSG_PACKAGE_VERSION_NOT_FOUND, Box::new(e)))?;
+
+    Ok(HttpResponse::Ok().json(VersionResponse::new(package_version, true)))
+}
+
+pub async fn get_package_owners(
+    request: HttpRequest,
+    Path(package_slug): Path<String>,
+) -> Result<HttpResponse> {
+    let db = request.db_pool().map_err(|e| ApiServerError(Box::new(e)))?;
+    let conn = db.get().map_err(|e| ApiServerError(Box::new(e)))?;
+    let package = Package::get_by_slug(&package_slug, &conn)
+        .map_err(|e| ApiNotFound(MSG_PACKAGE_NOT_FOUND, Box::new(e)))?;
+
+    let account_ids = PackageCollaborator::get_by_package_id(package.id, &conn)
+        .map_err(|e| ApiServerError(Box::new(e)))?;
+    let collaborators =
+        PackageCollaborator::get_in_bulk_order_by_role(package.id, account_ids.clone(), &conn)
+            .map_err(|e| ApiServerError(Box::new(e)))?;
+    let mut accounts = Account::get_accounts(&account_ids, &conn)
+        .map_err(|e| ApiServerError(Box::new(e)))?;
+
+    // Keep the owner first, as ordered by `get_in_bulk_order_by_role`
+    let owners: Vec<OwnerResponse> = collaborators
+        .iter()
+        .filter_map(|collaborator| {
+            let index = accounts
+                .iter()
+                .position(|account| account.id == collaborator.account_id)?;
+            Some(OwnerResponse::new(
+                accounts.swap_remove(index),
+                collaborator.role,
+            ))
+        })
+        .collect();
+    Ok(HttpResponse::Ok().json(owners))
+}
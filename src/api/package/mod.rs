@@ -1,28 +1,89 @@
-use jelly::actix_web::web::{get, post, resource, scope, ServiceConfig};
+use jelly::actix_web::web::{delete, get, post, put, resource, scope, ServiceConfig};
+use jelly::chrono::Duration;
+
+use crate::utils::new_rate_limit;
 
 pub mod controller;
 #[cfg(test)]
 mod tests;
 pub mod view;
 
+// Both endpoints are public. A build counts every dependency, hence the higher limit.
+const DOWNLOAD_COUNTS_PER_MINUTE: u32 = 120;
+const SEARCHES_PER_MINUTE: u32 = 60;
+
 pub fn configure(config: &mut ServiceConfig) {
     config.service(
         scope("/api/v1/packages")
+            .service(
+                resource("")
+                    .route(get().to(controller::get_packages)),
+            )
             .service(
                 resource("/upload")
                     .route(post().to(controller::register_package)),
             )
+            .service(
+                resource("/crawl")
+                    .route(post().to(controller::crawl_repository)),
+            )
             .service(
                 resource("/count")
+                    .wrap(new_rate_limit(
+                        "packages-count",
+                        DOWNLOAD_COUNTS_PER_MINUTE,
+                        Duration::minutes(1),
+                    ))
                     .route(post().to(controller::increase_download_count)),
             )
             .service(
                 resource("/search")
+                    .wrap(new_rate_limit(
+                        "packages-search",
+                        SEARCHES_PER_MINUTE,
+                        Duration::minutes(1),
+                    ))
                     .route(post().to(controller::search_package))
             )
             .service(
                 resource("{package_name}/badge")
                     .route(get().to(controller::package_badge_info)),
+            )
+            .service(
+                resource("/{package_slug}/claim/token")
+                    .route(post().to(controller::get_claim_token)),
+            )
+            .service(
+                resource("/{package_slug}/claim/verify")
+                    .route(post().to(controller::claim_package_with_token)),
+            )
+            .service(
+                resource("/{package_slug}/claim/github")
+                    .route(post().to(controller::claim_package_with_github)),
+            )
+            .service(
+                resource("/{package_slug}/versions/{version}/yank")
+                    .route(delete().to(controller::yank_version)),
+            )
+            .service(
+                resource("/{package_slug}/versions/{version}/unyank")
+                    .route(put().to(controller::unyank_version)),
+            )
+            .service(
+                resource("/{package_slug}")
+                    .route(get().to(controller::get_package)),
+            )
+            .service(
+                resource("/{package_slug}/versions")
+                    .route(get().to(controller::get_package_versions)),
+            )
+            .service(
+                resource("/{package_slug}/versions/{version}")
+                    .route(get().to(controller::get_package_version)),
+            )
+            .service(
+                resource("/{package_slug}/owners")
+                    .route(get().to(controller::get_package_owners)),
             ),
     );
 }
@@ -1,14 +1,40 @@
+use crate::accounts::Account;
+use crate::api::auth::{authenticate_token, ApiAccount, BearerAccount};
 use crate::api::package::controller::{
-    increase_download_count, register_package, DownloadInfo, PackageRequest,
+    claim_package_with_github, claim_package_with_token, crawl_repository, get_claim_token, get_package, get_package_owners, get_package_version,
+    get_package_versions, get_packages, increase_download_count, register_package,
+    unyank_version, yank_version, CrawlRequest, DownloadInfo, PackageListParams, PackageRequest,
+    YankRequest, SHADOW_PACKAGES_PER_CLIENT_PER_HOUR, SHADOW_PACKAGES_PER_HOUR,
 };
+use crate::github_service::GithubRepoData;
+use crate::jobs::{CrawlRepository, CreateShadowPackage};
+use crate::schema::{accounts, package_claims};
+use crate::package_collaborators::package_collaborator::{PackageCollaborator, Role};
+use crate::packages::models::package_download::{client_fingerprint, PackageDownload};
+use crate::packages::models::package_event::{PackageEvent, PackageEventKind};
+use crate::packages::models::PackageSortField;
 use crate::packages::{Package, PackageVersion};
-use crate::test::util::create_test_token;
+use crate::settings::models::token::{ApiToken, ApiTokenScopes, EndpointScope};
+use crate::test::util::{create_stub_packages, create_test_token, setup_two_factor, setup_user};
 use crate::test::{mock, DatabaseTestContext, DB_POOL};
+use crate::utils::request_utils::ClientIp;
 
 use jelly::actix_web::body::Body;
+use jelly::actix_web::http::header::RETRY_AFTER;
 use jelly::actix_web::http::StatusCode;
 use jelly::actix_web::web;
+use jelly::actix_web::HttpResponse;
+use jelly::chrono::Duration;
 use jelly::error::Error;
+use jelly::guards::{RateLimit, RateLimitStore};
+use jelly::utils::error_constants::{
+    MSG_ADMIN_REQUIRED, MSG_API_TOKEN_SCOPE_NOT_ALLOWED, MSG_CLAIM_TOKEN_NOT_FOUND,
+    MSG_CLAIM_TOKEN_NOT_ISSUED, MSG_GITHUB_ACCOUNT_NOT_LINKED, MSG_INVALID_REPOSITORY_URL,
+    MSG_NO_REPOSITORY_PUSH_ACCESS, MSG_PACKAGE_ALREADY_CLAIMED, MSG_TOO_MANY_REQUESTS,
+    MSG_TWO_FACTOR_REQUIRED,
+};
+
+use diesel::prelude::*;
 
 fn init_form() -> web::Form<DownloadInfo> {
     web::Form(DownloadInfo {
@@ -39,12 +65,27 @@ async fn register_package_create_new_packages() {
     let package_request = package_request();
     assert_eq!(Package::count(&DB_POOL).unwrap(), 0);
 
-    let response = register_package(mock_http_request, package_request).await;
+    let response = register_package(
+        mock_http_request,
+        BearerAccount(None),
+        ClientIp(None),
+        package_request,
+    )
+    .await;
     assert!(response.is_ok());
     let resp = response.unwrap();
     assert_eq!(resp.status(), StatusCode::OK);
     assert_eq!(resp.body().as_ref().unwrap(), &Body::from("name-1"));
     assert_eq!(Package::count(&DB_POOL).unwrap(), 1);
+
+    let conn = DB_POOL.get().unwrap();
+    let package = Package::get_by_slug("name-1", &conn).unwrap();
+    let (events, _, _) =
+        PackageEvent::get_by_package_id_paginated(package.id, None, &conn).unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].kind(), Some(PackageEventKind::VersionPublished));
+    assert_eq!(events[0].details, "0.1.0");
+    assert!(events[0].api_token_id.is_some());
 }
 
 #[actix_rt::test]
@@ -60,7 +101,13 @@ async fn register_package_returns_error_with_invalid_token() {
     package_request.token = "".to_string();
     assert_eq!(Package::count(&DB_POOL).unwrap(), 0);
 
-    let response = register_package(mock_http_request, package_request).await;
+    let response = register_package(
+        mock_http_request,
+        BearerAccount(None),
+        ClientIp(None),
+        package_request,
+    )
+    .await;
     assert!(response.is_ok());
     let resp = response.unwrap();
     assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
@@ -71,6 +118,38 @@ async fn register_package_returns_error_with_invalid_token() {
     assert_eq!(Package::count(&DB_POOL).unwrap(), 0);
 }
 
+fn client_ip(ip_address: &str) -> ClientIp {
+    ClientIp(Some(ip_address.to_string()))
+}
+
+fn expect_shadow_package_job(mock_http_request: &mut mock::MockHttpRequest, ip_address: &str) {
+    let fingerprint = client_fingerprint(Some(ip_address));
+    let form = init_form();
+    mock_http_request
+        .expect_queue::<CreateShadowPackage>()
+        .withf(move |job| {
+            job.url == form.url
+                && job.rev == form.rev
+                && job.subdir == form.subdir
+                && job.fingerprint == fingerprint
+        })
+        .times(1)
+        .returning(|_| Ok(()));
+}
+
+// What the queued `CreateShadowPackage` job does
+fn create_shadow_package(ip_address: &str) {
+    let form = init_form();
+    CreateShadowPackage {
+        url: form.url.clone(),
+        rev: form.rev.clone(),
+        subdir: form.subdir.clone(),
+        fingerprint: client_fingerprint(Some(ip_address)),
+    }
+    .create(&mock::GithubService::new(), &DB_POOL)
+    .unwrap();
+}
+
 #[actix_rt::test]
 async fn increase_download_count_creates_shadow_package_when_package_version_not_existed() {
     crate::test::init();
@@ -80,10 +159,15 @@ async fn increase_download_count_creates_shadow_package_when_package_version_not
     mock_http_request
         .expect_db_pool()
         .returning(|| Ok(&DB_POOL));
+    expect_shadow_package_job(&mut mock_http_request, "10.0.0.1");
     let form = init_form();
-    increase_download_count(mock_http_request, form)
+    let response = increase_download_count(mock_http_request, form, client_ip("10.0.0.1"))
         .await
         .unwrap();
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+    // The source host is only called by the job
+    assert_eq!(Package::count(&DB_POOL).unwrap(), 0);
+    create_shadow_package("10.0.0.1");
     let package = Package::get_by_name(&"name1".to_string(), &DB_POOL).unwrap();
     PackageVersion::delete_by_package_id(package.id, &DB_POOL).unwrap();
     assert_eq!(Package::count(&DB_POOL).unwrap(), 1);
@@ -93,10 +177,12 @@ async fn increase_download_count_creates_shadow_package_when_package_version_not
     mock_http_request
         .expect_db_pool()
         .returning(|| Ok(&DB_POOL));
+    expect_shadow_package_job(&mut mock_http_request, "10.0.0.1");
     let form = init_form();
-    increase_download_count(mock_http_request, form)
+    increase_download_count(mock_http_request, form, client_ip("10.0.0.1"))
         .await
         .unwrap();
+    create_shadow_package("10.0.0.1");
     assert_eq!(Package::count(&DB_POOL).unwrap(), 1);
     assert_eq!(PackageVersion::count(&DB_POOL).unwrap(), 1);
 }
@@ -105,22 +191,156 @@ async fn increase_download_count_creates_shadow_package_when_package_version_not
 async fn increase_download_count_works() {
     crate::test::init();
     let _ctx = DatabaseTestContext::new();
+    assert_eq!(Package::count(&DB_POOL).unwrap(), 0);
+    assert_eq!(PackageVersion::count(&DB_POOL).unwrap(), 0);
+    create_shadow_package("10.0.0.1");
 
     let mut mock_http_request = mock::MockHttpRequest::new();
     mock_http_request
         .expect_db_pool()
         .returning(|| Ok(&DB_POOL));
-    let form = init_form();
-    assert_eq!(Package::count(&DB_POOL).unwrap(), 0);
-    assert_eq!(PackageVersion::count(&DB_POOL).unwrap(), 0);
-
-    let response = increase_download_count(mock_http_request, form).await;
+    mock_http_request
+        .expect_queue::<CreateShadowPackage>()
+        .times(0);
+    let response =
+        increase_download_count(mock_http_request, init_form(), client_ip("10.0.0.2")).await;
     assert!(response.is_ok());
     let resp = response.unwrap();
     assert_eq!(resp.status(), StatusCode::OK);
-    assert_eq!(resp.body().as_ref().unwrap(), &Body::from("2"));
+    assert_eq!(resp.body().as_ref().unwrap(), &Body::from("1"));
     assert_eq!(Package::count(&DB_POOL).unwrap(), 1);
     assert_eq!(PackageVersion::count(&DB_POOL).unwrap(), 1);
+
+    // Downloading the version again within the window isn't counted
+    let mut mock_http_request = mock::MockHttpRequest::new();
+    mock_http_request
+        .expect_db_pool()
+        .returning(|| Ok(&DB_POOL));
+    let resp = increase_download_count(mock_http_request, init_form(), client_ip("10.0.0.1"))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(resp.body().as_ref().unwrap(), &Body::from("0"));
+
+    // Counters are updated in batches
+    let package = Package::get_by_name("name1", &DB_POOL).unwrap();
+    assert_eq!(package.total_downloads_count, 0);
+    PackageDownload::flush(&DB_POOL.get().unwrap()).unwrap();
+    let package = Package::get_by_name("name1", &DB_POOL).unwrap();
+    assert_eq!(package.total_downloads_count, 2);
+}
+
+#[actix_rt::test]
+async fn increase_download_count_queues_one_job_per_shadow_package() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let mut mock_http_request = mock::MockHttpRequest::new();
+    mock_http_request
+        .expect_db_pool()
+        .returning(|| Ok(&DB_POOL));
+    expect_shadow_package_job(&mut mock_http_request, "10.0.0.1");
+    let response = increase_download_count(mock_http_request, init_form(), client_ip("10.0.0.1"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+
+    // The same version is requested while its job is queued
+    for ip_address in ["10.0.0.1", "10.0.0.2"] {
+        let mut mock_http_request = mock::MockHttpRequest::new();
+        mock_http_request
+            .expect_db_pool()
+            .returning(|| Ok(&DB_POOL));
+        mock_http_request
+            .expect_queue::<CreateShadowPackage>()
+            .times(0);
+        let response =
+            increase_download_count(mock_http_request, init_form(), client_ip(ip_address))
+                .await
+                .unwrap();
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+    }
+    // Once the job is done, the version can be requested again
+    create_shadow_package("10.0.0.1");
+    let package = Package::get_by_name("name1", &DB_POOL).unwrap();
+    PackageVersion::delete_by_package_id(package.id, &DB_POOL).unwrap();
+    let mut mock_http_request = mock::MockHttpRequest::new();
+    mock_http_request
+        .expect_db_pool()
+        .returning(|| Ok(&DB_POOL));
+    expect_shadow_package_job(&mut mock_http_request, "10.0.0.2");
+    let response = increase_download_count(mock_http_request, init_form(), client_ip("10.0.0.2"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+
+    // Only the two queued jobs were charged to the budget shared by all clients
+    let limit = RateLimit::new(
+        "shadow-packages-all",
+        SHADOW_PACKAGES_PER_HOUR,
+        Duration::hours(1),
+    )
+    .with_store(RateLimitStore::Postgres);
+    for _ in 2..SHADOW_PACKAGES_PER_HOUR {
+        assert_eq!(limit.check("all", &DB_POOL).unwrap(), None);
+    }
+    assert!(limit.check("all", &DB_POOL).unwrap().is_some());
+}
+
+#[actix_rt::test]
+async fn increase_download_count_limits_shadow_packages_per_client() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    for _ in 0..SHADOW_PACKAGES_PER_CLIENT_PER_HOUR {
+        let mut mock_http_request = mock::MockHttpRequest::new();
+        mock_http_request
+            .expect_db_pool()
+            .returning(|| Ok(&DB_POOL));
+        expect_shadow_package_job(&mut mock_http_request, "10.0.0.1");
+        let response =
+            increase_download_count(mock_http_request, init_form(), client_ip("10.0.0.1"))
+                .await
+                .unwrap();
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+        // The job is done, so that the next request queues a new one
+        let form = init_form();
+        PackageDownload::forget_shadow_package(
+            &form.url,
+            &form.rev,
+            &form.subdir,
+            &DB_POOL.get().unwrap(),
+        )
+        .unwrap();
+    }
+
+    let mut mock_http_request = mock::MockHttpRequest::new();
+    mock_http_request
+        .expect_db_pool()
+        .returning(|| Ok(&DB_POOL));
+    mock_http_request
+        .expect_queue::<CreateShadowPackage>()
+        .times(0);
+    let response = increase_download_count(mock_http_request, init_form(), client_ip("10.0.0.1"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert!(response.headers().contains_key(RETRY_AFTER));
+    assert_eq!(
+        response.body().as_ref().unwrap(),
+        &Body::from(MSG_TOO_MANY_REQUESTS)
+    );
+
+    // Other clients have their own limit
+    let mut mock_http_request = mock::MockHttpRequest::new();
+    mock_http_request
+        .expect_db_pool()
+        .returning(|| Ok(&DB_POOL));
+    expect_shadow_package_job(&mut mock_http_request, "10.0.0.2");
+    let response = increase_download_count(mock_http_request, init_form(), client_ip("10.0.0.2"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
 }
 
 #[actix_rt::test]
@@ -135,7 +355,7 @@ async fn increase_download_count_returns_error_with_empty_rev() {
     let mut form = init_form();
     form.rev = "".to_string();
 
-    let response = increase_download_count(mock_http_request, form).await;
+    let response = increase_download_count(mock_http_request, form, client_ip("10.0.0.1")).await;
     assert!(response.is_ok());
     let resp = response.unwrap();
     assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
@@ -159,7 +379,7 @@ async fn increase_download_count_returns_error_with_empty_url() {
     let mut form = init_form();
     form.url = "".to_string();
 
-    let response = increase_download_count(mock_http_request, form).await;
+    let response = increase_download_count(mock_http_request, form, client_ip("10.0.0.1")).await;
     assert!(response.is_ok());
     let resp = response.unwrap();
     assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
@@ -171,6 +391,34 @@ async fn increase_download_count_returns_error_with_empty_url() {
     assert_eq!(PackageVersion::count(&DB_POOL).unwrap(), 0);
 }
 
+#[actix_rt::test]
+async fn increase_download_count_returns_error_with_unsupported_url() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let urls = [
+        "not a url",
+        "http://github.com/move-language/move",
+        "https://github.com/move-language",
+        "https://example.com/move-language/move",
+        "https://127.0.0.1/move-language/move",
+    ];
+    for url in urls {
+        let mut mock_http_request = mock::MockHttpRequest::new();
+        mock_http_request
+            .expect_db_pool()
+            .returning(|| Ok(&DB_POOL));
+        let mut form = init_form();
+        form.url = url.to_string();
+
+        let resp = increase_download_count(mock_http_request, form, client_ip("10.0.0.1"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST, "{}", url);
+    }
+    assert_eq!(Package::count(&DB_POOL).unwrap(), 0);
+}
+
 #[actix_rt::test]
 async fn register_package_returns_error_when_database_is_not_available() {
     crate::test::init();
@@ -182,7 +430,13 @@ async fn register_package_returns_error_when_database_is_not_available() {
         .returning(|| Err(Error::Generic("Cannot get db pool".to_string())));
     let package_request = package_request();
 
-    let response = register_package(mock_http_request, package_request).await;
+    let response = register_package(
+        mock_http_request,
+        BearerAccount(None),
+        ClientIp(None),
+        package_request,
+    )
+    .await;
     assert!(response.is_ok());
     let resp = response.unwrap();
     assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
@@ -203,7 +457,7 @@ async fn increase_download_count_returns_error_when_database_is_not_available()
         .returning(|| Err(Error::Generic("Cannot get db pool".to_string())));
     let form = init_form();
 
-    let response = increase_download_count(mock_http_request, form).await;
+    let response = increase_download_count(mock_http_request, form, client_ip("10.0.0.1")).await;
     assert!(response.is_ok());
     let resp = response.unwrap();
     assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
@@ -212,3 +466,734 @@ async fn increase_download_count_returns_error_when_database_is_not_available()
         &Body::from("Something went wrong, please try again later.")
     );
 }
+
+async fn register_test_package(token: &str) -> Package {
+    let mut mock_http_request = mock::MockHttpRequest::new();
+    mock_http_request
+        .expect_db_pool()
+        .returning(|| Ok(&DB_POOL));
+    let package_request = web::Json(PackageRequest {
+        github_repo_url: "".to_string(),
+        total_files: 0,
+        token: token.to_string(),
+        subdir: "".to_string(),
+    });
+    register_package(
+        mock_http_request,
+        BearerAccount(None),
+        ClientIp(None),
+        package_request,
+    )
+    .await
+    .unwrap();
+    Package::get_by_slug("name-1", &DB_POOL.get().unwrap()).unwrap()
+}
+
+fn yank_path() -> web::Path<(String, String)> {
+    web::Path(("name-1".to_string(), "0.1.0".to_string()))
+}
+
+#[actix_rt::test]
+async fn yank_version_works() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let token = create_test_token();
+    let package = register_test_package(&token).await;
+
+    let mut mock_http_request = mock::MockHttpRequest::new();
+    mock_http_request
+        .expect_db_pool()
+        .returning(|| Ok(&DB_POOL));
+    let response = yank_version(
+        mock_http_request,
+        BearerAccount(None),
+        yank_path(),
+        Some(web::Json(YankRequest {
+            token: token.clone(),
+        })),
+    )
+    .await
+    .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let conn = DB_POOL.get().unwrap();
+    assert!(package.get_version(&"0.1.0".to_string(), &conn).unwrap().yanked);
+
+    let mut mock_http_request = mock::MockHttpRequest::new();
+    mock_http_request
+        .expect_db_pool()
+        .returning(|| Ok(&DB_POOL));
+    let response = unyank_version(
+        mock_http_request,
+        BearerAccount(None),
+        yank_path(),
+        Some(web::Json(YankRequest { token })),
+    )
+    .await
+    .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(!package.get_version(&"0.1.0".to_string(), &conn).unwrap().yanked);
+}
+
+#[actix_rt::test]
+async fn yank_version_returns_error_with_invalid_token() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let package = register_test_package(&create_test_token()).await;
+
+    let mut mock_http_request = mock::MockHttpRequest::new();
+    mock_http_request
+        .expect_db_pool()
+        .returning(|| Ok(&DB_POOL));
+    let response = yank_version(
+        mock_http_request,
+        BearerAccount(None),
+        yank_path(),
+        Some(web::Json(YankRequest {
+            token: "invalid-token".to_string(),
+        })),
+    )
+    .await;
+    assert!(matches!(response, Err(Error::ApiUnauthorized(..))));
+    let conn = DB_POOL.get().unwrap();
+    assert!(!package.get_version(&"0.1.0".to_string(), &conn).unwrap().yanked);
+}
+
+#[actix_rt::test]
+async fn yank_version_returns_error_if_not_owner() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let package = register_test_package(&create_test_token()).await;
+    let uid = setup_user(Some("collaborator@host.com".to_string()), None);
+    let account = Account::get(uid, &DB_POOL).unwrap();
+    let token = ApiToken::insert(&account, "test_key", &DB_POOL)
+        .unwrap()
+        .plaintext;
+
+    let mut mock_http_request = mock::MockHttpRequest::new();
+    mock_http_request
+        .expect_db_pool()
+        .returning(|| Ok(&DB_POOL));
+    let response = yank_version(
+        mock_http_request,
+        BearerAccount(None),
+        yank_path(),
+        Some(web::Json(YankRequest { token })),
+    )
+    .await;
+    assert!(matches!(response, Err(Error::ApiForbidden(..))));
+    let conn = DB_POOL.get().unwrap();
+    assert!(!package.get_version(&"0.1.0".to_string(), &conn).unwrap().yanked);
+}
+
+#[actix_rt::test]
+async fn yank_version_returns_error_with_nonexistent_version() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let token = create_test_token();
+    register_test_package(&token).await;
+
+    let mut mock_http_request = mock::MockHttpRequest::new();
+    mock_http_request
+        .expect_db_pool()
+        .returning(|| Ok(&DB_POOL));
+    let response = yank_version(
+        mock_http_request,
+        BearerAccount(None),
+        web::Path(("name-1".to_string(), "9.9.9".to_string())),
+        Some(web::Json(YankRequest { token })),
+    )
+    .await;
+    assert!(matches!(response, Err(Error::ApiNotFound(..))));
+}
+
+fn response_json(response: &HttpResponse) -> serde_json::Value {
+    match response.body().as_ref().unwrap() {
+        Body::Bytes(bytes) => serde_json::from_slice(bytes).unwrap(),
+        _ => panic!("Unexpected response body"),
+    }
+}
+
+fn list_params(page: Option<i64>, per_page: Option<i64>) -> web::Query<PackageListParams> {
+    web::Query(PackageListParams {
+        field: Some(PackageSortField::Name),
+        order: None,
+        page,
+        per_page,
+    })
+}
+
+#[actix_rt::test]
+async fn get_packages_works() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let uid = setup_user(None, None);
+    create_stub_packages(uid, 3);
+
+    let mut mock_http_request = mock::MockHttpRequest::new();
+    mock_http_request
+        .expect_db_pool()
+        .returning(|| Ok(&DB_POOL));
+    let response = get_packages(mock_http_request, list_params(Some(2), Some(2)))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let json = response_json(&response);
+    assert_eq!(json["meta"]["page"], 2);
+    assert_eq!(json["meta"]["total_count"], 3);
+    assert_eq!(json["meta"]["total_pages"], 2);
+    assert_eq!(json["packages"].as_array().unwrap().len(), 1);
+    assert_eq!(
+        json["packages"][0]["name"],
+        format!("package_2_{}", uid)
+    );
+    assert_eq!(json["packages"][0]["latest_version"], "0.0.0");
+    // Times are in UTC, like in the other responses
+    for field in ["created_at", "updated_at"] {
+        assert!(json["packages"][0][field].as_str().unwrap().ends_with('Z'));
+    }
+}
+
+#[actix_rt::test]
+async fn get_packages_returns_error_with_invalid_pagination() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let mut mock_http_request = mock::MockHttpRequest::new();
+    mock_http_request
+        .expect_db_pool()
+        .returning(|| Ok(&DB_POOL));
+    let response = get_packages(mock_http_request, list_params(Some(0), None)).await;
+    assert!(matches!(response, Err(Error::ApiBadRequest(..))));
+
+    let mut mock_http_request = mock::MockHttpRequest::new();
+    mock_http_request
+        .expect_db_pool()
+        .returning(|| Ok(&DB_POOL));
+    let response = get_packages(mock_http_request, list_params(None, Some(1000))).await;
+    assert!(matches!(response, Err(Error::ApiBadRequest(..))));
+}
+
+#[actix_rt::test]
+async fn get_package_works() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let package = register_test_package(&create_test_token()).await;
+
+    let mut mock_http_request = mock::MockHttpRequest::new();
+    mock_http_request
+        .expect_db_pool()
+        .returning(|| Ok(&DB_POOL));
+    let response = get_package(mock_http_request, web::Path(package.slug.clone()))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let json = response_json(&response);
+    assert_eq!(json["name"], package.name);
+    assert_eq!(json["slug"], package.slug);
+    assert_eq!(json["latest_version"], "0.1.0");
+
+    let mut mock_http_request = mock::MockHttpRequest::new();
+    mock_http_request
+        .expect_db_pool()
+        .returning(|| Ok(&DB_POOL));
+    let response = get_package(mock_http_request, web::Path("not-a-package".to_string())).await;
+    assert!(matches!(response, Err(Error::ApiNotFound(..))));
+}
+
+#[actix_rt::test]
+async fn get_package_versions_works() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let package = register_test_package(&create_test_token()).await;
+    let conn = DB_POOL.get().unwrap();
+    PackageVersion::create(
+        package.id,
+        "0.2.0".to_string(),
+        "readme".to_string(),
+        "".to_string(),
+        "".to_string(),
+        0,
+        0,
+        None,
+        &conn,
+    )
+    .unwrap();
+
+    let mut mock_http_request = mock::MockHttpRequest::new();
+    mock_http_request
+        .expect_db_pool()
+        .returning(|| Ok(&DB_POOL));
+    let response = get_package_versions(mock_http_request, web::Path(package.slug.clone()))
+        .await
+        .unwrap();
+    let json = response_json(&response);
+    let versions = json.as_array().unwrap();
+    assert_eq!(versions.len(), 2);
+    assert_eq!(versions[0]["version"], "0.2.0");
+    assert_eq!(versions[1]["version"], "0.1.0");
+    assert!(versions[0].get("readme_content").is_none());
+
+    let mut mock_http_request = mock::MockHttpRequest::new();
+    mock_http_request
+        .expect_db_pool()
+        .returning(|| Ok(&DB_POOL));
+    let response = get_package_version(
+        mock_http_request,
+        web::Path((package.slug.clone(), "0.2.0".to_string())),
+    )
+    .await
+    .unwrap();
+    let json = response_json(&response);
+    assert_eq!(json["version"], "0.2.0");
+    assert_eq!(json["readme_content"], "readme");
+    assert_eq!(json["yanked"], false);
+
+    let mut mock_http_request = mock::MockHttpRequest::new();
+    mock_http_request
+        .expect_db_pool()
+        .returning(|| Ok(&DB_POOL));
+    let response = get_package_version(
+        mock_http_request,
+        web::Path((package.slug, "9.9.9".to_string())),
+    )
+    .await;
+    assert!(matches!(response, Err(Error::ApiNotFound(..))));
+}
+
+#[actix_rt::test]
+async fn get_package_owners_works() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let package = register_test_package(&create_test_token()).await;
+    let owner_id = PackageCollaborator::get_by_package_id(package.id, &DB_POOL.get().unwrap())
+        .unwrap()[0];
+    let uid = setup_user(Some("collaborator@host.com".to_string()), None);
+    PackageCollaborator::new_collaborator(package.id, uid, owner_id, &DB_POOL.get().unwrap())
+        .unwrap();
+
+    let mut mock_http_request = mock::MockHttpRequest::new();
+    mock_http_request
+        .expect_db_pool()
+        .returning(|| Ok(&DB_POOL));
+    let response = get_package_owners(mock_http_request, web::Path(package.slug))
+        .await
+        .unwrap();
+    let json = response_json(&response);
+    let owners = json.as_array().unwrap();
+    assert_eq!(owners.len(), 2);
+    assert_eq!(owners[0]["role"], "owner");
+    assert_eq!(owners[1]["role"], "collaborator");
+    assert_eq!(
+        owners[1]["slug"],
+        Account::get(uid, &DB_POOL).unwrap().slug.unwrap()
+    );
+    // Emails are never exposed
+    assert!(owners[0].get("email").is_none());
+}
+
+#[actix_rt::test]
+async fn yank_version_works_with_bearer_token() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let token = create_test_token();
+    let package = register_test_package(&token).await;
+    let api_account = authenticate_token(&token, &DB_POOL).unwrap();
+
+    let mut mock_http_request = mock::MockHttpRequest::new();
+    mock_http_request
+        .expect_db_pool()
+        .returning(|| Ok(&DB_POOL));
+    let response = yank_version(
+        mock_http_request,
+        BearerAccount(Some(api_account)),
+        yank_path(),
+        None,
+    )
+    .await
+    .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let conn = DB_POOL.get().unwrap();
+    assert!(package.get_version(&"0.1.0".to_string(), &conn).unwrap().yanked);
+}
+
+fn create_scoped_token(token: &str, endpoint_scopes: Vec<EndpointScope>) -> String {
+    let account = ApiToken::associated_account(token, &DB_POOL).unwrap();
+    let scopes = ApiTokenScopes {
+        endpoint_scopes: Some(endpoint_scopes),
+        ..Default::default()
+    };
+    ApiToken::insert_with_scopes(&account, "scoped_key", &scopes, &DB_POOL)
+        .unwrap()
+        .plaintext
+}
+
+#[actix_rt::test]
+async fn register_package_returns_forbidden_without_publish_scope() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let token = create_scoped_token(&create_test_token(), vec![EndpointScope::PublishUpdate]);
+    let mut mock_http_request = mock::MockHttpRequest::new();
+    mock_http_request
+        .expect_db_pool()
+        .returning(|| Ok(&DB_POOL));
+    let package_request = web::Json(PackageRequest {
+        github_repo_url: "".to_string(),
+        total_files: 0,
+        token,
+        subdir: "".to_string(),
+    });
+
+    let response = register_package(
+        mock_http_request,
+        BearerAccount(None),
+        ClientIp(None),
+        package_request,
+    )
+    .await
+    .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_eq!(Package::count(&DB_POOL).unwrap(), 0);
+}
+
+#[actix_rt::test]
+async fn register_package_returns_forbidden_without_required_two_factor() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let token = create_test_token();
+    let package = register_test_package(&token).await;
+    let conn = DB_POOL.get().unwrap();
+    Package::set_require_two_factor(package.id, true, &conn).unwrap();
+
+    let upload = || {
+        let mut mock_http_request = mock::MockHttpRequest::new();
+        mock_http_request
+            .expect_db_pool()
+            .returning(|| Ok(&DB_POOL));
+        let package_request = web::Json(PackageRequest {
+            github_repo_url: "".to_string(),
+            total_files: 0,
+            token: token.clone(),
+            subdir: "".to_string(),
+        });
+        register_package(
+            mock_http_request,
+            BearerAccount(None),
+            ClientIp(None),
+            package_request,
+        )
+    };
+    let response = upload().await.unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_eq!(
+        response.body().as_ref().unwrap(),
+        &Body::from(MSG_TWO_FACTOR_REQUIRED)
+    );
+
+    let owner_id = PackageCollaborator::get_owner_ids(package.id, &conn).unwrap()[0];
+    setup_two_factor(owner_id);
+    // passes the check, and fails because the version is already published
+    let response = upload().await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[actix_rt::test]
+async fn yank_version_returns_error_without_yank_scope() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let token = create_test_token();
+    let package = register_test_package(&token).await;
+    let token = create_scoped_token(&token, vec![EndpointScope::PublishUpdate]);
+
+    let mut mock_http_request = mock::MockHttpRequest::new();
+    mock_http_request
+        .expect_db_pool()
+        .returning(|| Ok(&DB_POOL));
+    let response = yank_version(
+        mock_http_request,
+        BearerAccount(None),
+        yank_path(),
+        Some(web::Json(YankRequest { token })),
+    )
+    .await;
+    assert!(matches!(
+        response,
+        Err(Error::ApiForbidden(MSG_API_TOKEN_SCOPE_NOT_ALLOWED, _))
+    ));
+    let conn = DB_POOL.get().unwrap();
+    assert!(!package.get_version(&"0.1.0".to_string(), &conn).unwrap().yanked);
+}
+
+fn api_account(is_admin: bool) -> ApiAccount {
+    let uid = setup_user(None, None);
+    diesel::update(accounts::table.find(uid))
+        .set(accounts::is_admin.eq(is_admin))
+        .execute(&DB_POOL.get().unwrap())
+        .unwrap();
+    ApiAccount {
+        account: Account::get(uid, &DB_POOL).unwrap(),
+        token: None,
+    }
+}
+
+fn crawl_request(github_repo_url: &str) -> web::Json<CrawlRequest> {
+    web::Json(CrawlRequest {
+        github_repo_url: github_repo_url.to_string(),
+        subdir: "language/move-stdlib/".to_string(),
+    })
+}
+
+#[actix_rt::test]
+async fn crawl_repository_queues_job() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let mut mock_http_request = mock::MockHttpRequest::new();
+    mock_http_request
+        .expect_queue::<CrawlRepository>()
+        .withf(|job| {
+            job.repository_url == "https://github.com/move-language/move"
+                && job.path == "language/move-stdlib/Move.toml"
+        })
+        .times(1)
+        .returning(|_| Ok(()));
+    let response = crawl_repository(
+        mock_http_request,
+        api_account(true),
+        crawl_request("https://github.com/move-language/move.git"),
+    )
+    .await
+    .unwrap();
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+}
+
+#[actix_rt::test]
+async fn crawl_repository_returns_error_if_not_admin() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let mut mock_http_request = mock::MockHttpRequest::new();
+    mock_http_request.expect_queue::<CrawlRepository>().times(0);
+    let response = crawl_repository(
+        mock_http_request,
+        api_account(false),
+        crawl_request("https://github.com/move-language/move"),
+    )
+    .await;
+    assert!(matches!(
+        response,
+        Err(Error::ApiForbidden(MSG_ADMIN_REQUIRED, _))
+    ));
+}
+
+#[actix_rt::test]
+async fn crawl_repository_returns_error_with_invalid_url() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let mut mock_http_request = mock::MockHttpRequest::new();
+    mock_http_request.expect_queue::<CrawlRepository>().times(0);
+    let response = crawl_repository(
+        mock_http_request,
+        api_account(true),
+        crawl_request("https://github.com/move-language"),
+    )
+    .await;
+    assert!(matches!(
+        response,
+        Err(Error::ApiBadRequest(MSG_INVALID_REPOSITORY_URL, _))
+    ));
+}
+
+fn create_crawled_package() -> Package {
+    let github_data = GithubRepoData {
+        name: "MoveStdlib".to_string(),
+        version: "0.1.0".to_string(),
+        readme_content: "".to_string(),
+        license: "".to_string(),
+        description: "".to_string(),
+        size: 0,
+        stars_count: 0,
+        forks_count: 0,
+        url: "".to_string(),
+        rev: "".to_string(),
+        dependencies: vec![],
+        addresses: vec![],
+    };
+    Package::create_from_crawled_data(
+        "https://github.com/move-language/move/tree/main/language/move-stdlib",
+        "",
+        "",
+        0,
+        0,
+        None,
+        github_data,
+        &DB_POOL,
+    )
+    .unwrap()
+}
+
+fn setup_claimer(github_login: Option<&str>) -> i32 {
+    let uid = setup_user(Some("claimer@host.com".to_string()), None);
+    diesel::update(accounts::table.find(uid))
+        .set(accounts::github_login.eq(github_login))
+        .execute(&DB_POOL.get().unwrap())
+        .unwrap();
+    uid
+}
+
+fn claimer(uid: i32) -> ApiAccount {
+    ApiAccount {
+        account: Account::get(uid, &DB_POOL).unwrap(),
+        token: None,
+    }
+}
+
+fn db_request() -> mock::MockHttpRequest {
+    let mut mock_http_request = mock::MockHttpRequest::new();
+    mock_http_request
+        .expect_db_pool()
+        .returning(|| Ok(&DB_POOL));
+    mock_http_request
+}
+
+fn is_owner(package: &Package, uid: i32) -> bool {
+    PackageCollaborator::get(package.id, uid, &DB_POOL.get().unwrap())
+        .is_ok_and(|collaborator| collaborator.role == Role::Owner as i32)
+}
+
+#[actix_rt::test]
+async fn claim_package_with_github_works() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let package = create_crawled_package();
+    // Logins are compared case insensitively, like on Github
+    let uid = setup_claimer(Some("Move-Language"));
+    let response = claim_package_with_github(
+        db_request(),
+        claimer(uid),
+        web::Path(package.slug.clone()),
+    )
+    .await
+    .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(is_owner(&package, uid));
+}
+
+#[actix_rt::test]
+async fn claim_package_with_github_returns_error_if_not_repository_owner() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    // Collaborators of the repository claim with a token
+    let package = create_crawled_package();
+    let uid = setup_claimer(Some("movey-collaborator"));
+    let response = claim_package_with_github(
+        db_request(),
+        claimer(uid),
+        web::Path(package.slug.clone()),
+    )
+    .await;
+    assert!(matches!(
+        response,
+        Err(Error::ApiForbidden(MSG_NO_REPOSITORY_PUSH_ACCESS, _))
+    ));
+    assert!(!is_owner(&package, uid));
+}
+
+#[actix_rt::test]
+async fn claim_package_with_github_returns_error_without_github_account() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let package = create_crawled_package();
+    let response =
+        claim_package_with_github(db_request(), claimer(setup_claimer(None)), web::Path(package.slug)).await;
+    assert!(matches!(
+        response,
+        Err(Error::ApiBadRequest(MSG_GITHUB_ACCOUNT_NOT_LINKED, _))
+    ));
+}
+
+#[actix_rt::test]
+async fn claim_package_with_token_works() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let package = create_crawled_package();
+    let uid = setup_claimer(None);
+    let response = claim_package_with_token(
+        db_request(),
+        claimer(uid),
+        web::Path(package.slug.clone()),
+    )
+    .await;
+    assert!(matches!(
+        response,
+        Err(Error::ApiBadRequest(MSG_CLAIM_TOKEN_NOT_ISSUED, _))
+    ));
+
+    let response = get_claim_token(
+        db_request(),
+        claimer(uid),
+        web::Path(package.slug.clone()),
+    )
+    .await
+    .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    // The token isn't committed yet
+    let response = claim_package_with_token(
+        db_request(),
+        claimer(uid),
+        web::Path(package.slug.clone()),
+    )
+    .await;
+    assert!(matches!(
+        response,
+        Err(Error::ApiForbidden(MSG_CLAIM_TOKEN_NOT_FOUND, _))
+    ));
+
+    diesel::update(package_claims::table)
+        .set(package_claims::token.eq("test-claim-token"))
+        .execute(&DB_POOL.get().unwrap())
+        .unwrap();
+    let response = claim_package_with_token(
+        db_request(),
+        claimer(uid),
+        web::Path(package.slug.clone()),
+    )
+    .await
+    .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(is_owner(&package, uid));
+}
+
+#[actix_rt::test]
+async fn claim_package_returns_error_if_package_has_owner() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let package = register_test_package(&create_test_token()).await;
+    let response = get_claim_token(
+        db_request(),
+        claimer(setup_claimer(Some("movey-collaborator"))),
+        web::Path(package.slug),
+    )
+    .await;
+    assert!(matches!(
+        response,
+        Err(Error::ApiBadRequest(MSG_PACKAGE_ALREADY_CLAIMED, _))
+    ));
+}
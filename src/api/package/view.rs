@@ -1,5 +1,11 @@
+use jelly::chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
+use crate::accounts::Account;
+use crate::package_collaborators::package_collaborator::Role;
+use crate::packages::models::PackageSearchResult;
+use crate::packages::{Package, PackageVersion};
+
 #[derive(Serialize, Deserialize)]
 pub struct PackageBadgeRespond {
     package_name: String,
@@ -14,9 +20,9 @@ pub struct PackageBadgeVersion {
     download_counts: i32,
 }
 
-impl From<Vec<(String, i32, String, i32)>> for PackageBadgeRespond {
-    fn from(tuples: Vec<(String, i32, String, i32)>) -> Self {
-        let mut max_version: String = tuples[0].2.to_string();
+impl From<Vec<(String, i32, String, i32, bool)>> for PackageBadgeRespond {
+    fn from(tuples: Vec<(String, i32, String, i32, bool)>) -> Self {
+        let mut latest_version: String = tuples[0].2.to_string();
         let mut shield_respond = PackageBadgeRespond {
             package_name: tuples[0].0.to_string(),
             total_download_counts: tuples[0].1,
@@ -28,12 +34,153 @@ impl From<Vec<(String, i32, String, i32)>> for PackageBadgeRespond {
                 version: record.2.to_string(),
                 download_counts: record.3,
             };
-            if record.2 > max_version {
-                max_version = record.2.to_string()
+            if record.4 {
+                latest_version = record.2.to_string()
             }
             shield_respond.versions.push(version)
         }
-        shield_respond.latest_version = max_version;
+        shield_respond.latest_version = latest_version;
         shield_respond
     }
 }
+
+#[derive(Serialize, Deserialize)]
+pub struct PaginationMeta {
+    pub page: i64,
+    pub per_page: i64,
+    pub total_count: i64,
+    pub total_pages: i64,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PackageListResponse {
+    pub packages: Vec<PackageSummaryResponse>,
+    pub meta: PaginationMeta,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PackageSummaryResponse {
+    pub name: String,
+    pub slug: String,
+    pub description: String,
+    pub latest_version: String,
+    pub total_downloads_count: i32,
+    pub stars_count: i32,
+    pub forks_count: i32,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<PackageSearchResult> for PackageSummaryResponse {
+    fn from(package: PackageSearchResult) -> Self {
+        PackageSummaryResponse {
+            name: package.name,
+            slug: package.slug,
+            description: package.description,
+            latest_version: package.version,
+            total_downloads_count: package.total_downloads_count,
+            stars_count: package.stars_count,
+            forks_count: package.forks_count,
+            created_at: package.created_at,
+            updated_at: package.updated_at,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PackageResponse {
+    pub name: String,
+    pub slug: String,
+    pub description: String,
+    pub repository_url: String,
+    pub latest_version: Option<String>,
+    pub total_downloads_count: i32,
+    pub stars_count: i32,
+    pub forks_count: i32,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl PackageResponse {
+    pub fn new(package: Package, latest_version: Option<PackageVersion>) -> Self {
+        PackageResponse {
+            name: package.name,
+            slug: package.slug,
+            description: package.description,
+            repository_url: package.repository_url,
+            latest_version: latest_version.map(|package_version| package_version.version),
+            total_downloads_count: package.total_downloads_count,
+            stars_count: package.stars_count,
+            forks_count: package.forks_count,
+            created_at: package.created_at,
+            updated_at: package.updated_at,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct VersionResponse {
+    pub version: String,
+    pub rev: Option<String>,
+    pub license: Option<String>,
+    pub yanked: bool,
+    pub downloads_count: i32,
+    pub total_files: Option<i32>,
+    pub total_size: Option<i32>,
+    pub created_at: DateTime<Utc>,
+    // Only included when a single version is requested
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub readme_content: Option<String>,
+}
+
+impl VersionResponse {
+    pub fn new(package_version: PackageVersion, include_readme: bool) -> Self {
+        VersionResponse {
+            version: package_version.version,
+            rev: package_version.rev,
+            license: package_version.license,
+            yanked: package_version.yanked,
+            downloads_count: package_version.downloads_count,
+            total_files: package_version.total_files,
+            total_size: package_version.total_size,
+            created_at: package_version.created_at,
+            readme_content: if include_readme {
+                Some(package_version.readme_content.unwrap_or_default())
+            } else {
+                None
+            },
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct OwnerResponse {
+    pub name: String,
+    pub slug: Option<String>,
+    pub github_login: Option<String>,
+    pub avatar: Option<String>,
+    pub role: String,
+}
+
+impl OwnerResponse {
+    pub fn new(account: Account, role: i32) -> Self {
+        OwnerResponse {
+            name: account.name,
+            slug: account.slug,
+            github_login: account.github_login,
+            avatar: account.avatar,
+            role: if role == Role::Owner as i32 {
+                "owner".to_string()
+            } else {
+                "collaborator".to_string()
+            },
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ClaimTokenResponse {
+    pub token: String,
+    /// Where the token has to be committed, relative to the repository root.
+    pub path: String,
+}
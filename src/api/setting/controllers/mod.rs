@@ -1,2 +1,5 @@
+pub mod passkey;
 pub mod profile;
+pub mod session;
 pub mod token;
+pub mod two_factor;
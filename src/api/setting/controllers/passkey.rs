@@ -0,0 +1,101 @@
+use crate::accounts::models::webauthn_credential::{WebauthnCredential, MAX_NAME_LENGTH};
+use crate::accounts::webauthn::{self, CreationOptions, RegistrationCredential, RelyingParty};
+use crate::accounts::Account;
+use crate::utils::request_utils;
+use diesel::result::DatabaseErrorKind;
+use diesel::result::Error::DatabaseError;
+use jelly::actix_session::UserSession;
+use jelly::actix_web::http::header::ContentType;
+use jelly::actix_web::{web, web::Path};
+use jelly::forms::TextField;
+use jelly::prelude::*;
+use jelly::Result;
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+pub struct PasskeyRequest {
+    pub name: TextField,
+    pub credential: RegistrationCredential,
+}
+
+/// The options for `navigator.credentials.create()`, with a challenge for the browser to sign.
+pub async fn registration_options(request: HttpRequest) -> Result<HttpResponse> {
+    if !request_utils::is_authenticated(&request)? {
+        return Ok(request_utils::clear_cookie(&request));
+    }
+    let user = request.user()?;
+    let db = request.db_pool()?;
+    let account = Account::get(user.id, db)?;
+    let conn = db.get()?;
+    let challenge = webauthn::start_ceremony(
+        &request.get_session(),
+        webauthn::REGISTRATION_CHALLENGE_KEY,
+        &conn,
+    )?;
+
+    let options = CreationOptions::new(
+        &RelyingParty::from_env()?,
+        challenge,
+        account.id,
+        &account.email,
+        &account.name,
+        WebauthnCredential::descriptors(account.id, &conn)?,
+    );
+    Ok(HttpResponse::Ok().set(ContentType::json()).json(&options))
+}
+
+/// Stores the credential created by the browser for the challenge of `registration_options`.
+pub async fn register(
+    request: HttpRequest,
+    mut req: web::Json<PasskeyRequest>,
+) -> Result<HttpResponse> {
+    req.name.value = req.name.value.trim().to_string();
+    if !req.name.is_valid() {
+        return Ok(HttpResponse::BadRequest().body(&req.name.errors[0]));
+    }
+    if req.name.value.chars().count() > MAX_NAME_LENGTH {
+        return Ok(HttpResponse::BadRequest().body("Name is too long."));
+    }
+    if !request_utils::is_authenticated(&request)? {
+        return Ok(request_utils::clear_cookie(&request));
+    }
+    let user = request.user()?;
+    let conn = request.db_pool()?.get()?;
+    let challenge = match webauthn::take_challenge(
+        &request.get_session(),
+        webauthn::REGISTRATION_CHALLENGE_KEY,
+        &conn,
+    )? {
+        Some(challenge) => challenge,
+        None => return Ok(HttpResponse::BadRequest().body("The request expired. Try again.")),
+    };
+    let verified = match webauthn::verify_registration(
+        &RelyingParty::from_env()?,
+        &challenge,
+        &req.credential,
+    ) {
+        Err(Error::Generic(error)) => return Ok(HttpResponse::BadRequest().body(error)),
+        result => result?,
+    };
+
+    match WebauthnCredential::create(user.id, &req.name.value, &verified, &conn) {
+        Err(Error::Database(DatabaseError(DatabaseErrorKind::UniqueViolation, _))) => {
+            Ok(HttpResponse::BadRequest().body("This passkey is already registered."))
+        }
+        result => Ok(HttpResponse::Ok().set(ContentType::json()).json(&result?)),
+    }
+}
+
+pub async fn delete(request: HttpRequest, Path(id): Path<i32>) -> Result<HttpResponse> {
+    if !request_utils::is_authenticated(&request)? {
+        return Ok(request_utils::clear_cookie(&request));
+    }
+    let user = request.user()?;
+    let conn = request.db_pool()?.get()?;
+
+    // only deletes passkeys of the account
+    if WebauthnCredential::delete(id, user.id, &conn)? == 0 {
+        return Ok(HttpResponse::NotFound().body("Passkey not found."));
+    }
+    Ok(HttpResponse::Ok().body(""))
+}
@@ -0,0 +1,60 @@
+use crate::accounts::models::account_event::{AccountEvent, AccountEventKind};
+use crate::accounts::models::account_session::{AccountSession, SessionDevice};
+use crate::constants;
+use crate::utils::request_utils;
+use jelly::actix_session::UserSession;
+use jelly::actix_web::http::header;
+use jelly::actix_web::web::Path;
+use jelly::prelude::*;
+use jelly::Result;
+
+pub async fn revoke_session(
+    request: HttpRequest,
+    Path(session_id): Path<i32>,
+    device: SessionDevice,
+) -> Result<HttpResponse> {
+    if !request_utils::is_authenticated(&request)? {
+        return Ok(request_utils::clear_cookie(&request));
+    }
+    let user = request.user()?;
+    let conn = request.db_pool()?.get()?;
+    let is_current_session =
+        request_utils::current_session_id(&request.get_session())? == Some(session_id);
+
+    // only deletes sessions of the account
+    if AccountSession::revoke(session_id, user.id, &conn)? > 0 {
+        AccountEvent::record(user.id, AccountEventKind::SessionRevoked, &device, &conn)?;
+    }
+
+    if is_current_session {
+        request.get_session().clear();
+        return Ok(HttpResponse::Ok()
+            .header(header::SET_COOKIE, constants::REMEMBER_ME_TOKEN_INVALIDATE)
+            .body(""));
+    }
+    Ok(HttpResponse::Ok().body(""))
+}
+
+/// Signs the account out of every browser, including this one.
+pub async fn revoke_all_sessions(
+    request: HttpRequest,
+    device: SessionDevice,
+) -> Result<HttpResponse> {
+    if !request_utils::is_authenticated(&request)? {
+        return Ok(request_utils::clear_cookie(&request));
+    }
+    let user = request.user()?;
+    let conn = request.db_pool()?.get()?;
+    AccountSession::revoke_all(user.id, &conn)?;
+    AccountEvent::record(
+        user.id,
+        AccountEventKind::AllSessionsRevoked,
+        &device,
+        &conn,
+    )?;
+
+    request.get_session().clear();
+    Ok(HttpResponse::Ok()
+        .header(header::SET_COOKIE, constants::REMEMBER_ME_TOKEN_INVALIDATE)
+        .body(""))
+}
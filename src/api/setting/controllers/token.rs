@@ -1,12 +1,13 @@
 use crate::accounts::Account;
 use crate::api::setting::views::EncodableApiTokenWithToken;
-use crate::settings::models::token::ApiToken;
+use crate::settings::models::token::{ApiToken, ApiTokenScopes, EndpointScope};
 use crate::utils::request_utils;
 use diesel::result::DatabaseErrorKind;
 use diesel::result::Error::DatabaseError;
 use jelly::actix_web::http::header::ContentType;
 use jelly::actix_web::{web, web::Path};
 use jelly::anyhow::anyhow;
+use jelly::chrono::{NaiveDateTime, Utc};
 use jelly::forms::TextField;
 use jelly::prelude::*;
 use jelly::Result;
@@ -15,6 +16,45 @@ use serde::Deserialize;
 #[derive(Deserialize)]
 pub struct TokenRequest {
     pub name: TextField,
+    // Leaving out any of these creates a token without that restriction
+    #[serde(default)]
+    pub endpoint_scopes: Option<Vec<EndpointScope>>,
+    #[serde(default)]
+    pub package_scopes: Option<Vec<String>>,
+    #[serde(default)]
+    pub expired_at: Option<NaiveDateTime>,
+}
+
+impl TokenRequest {
+    fn scopes(&self) -> std::result::Result<ApiTokenScopes, &'static str> {
+        if let Some(endpoint_scopes) = &self.endpoint_scopes {
+            if endpoint_scopes.is_empty() {
+                return Err("Please select at least one scope.");
+            }
+        }
+        if let Some(expired_at) = self.expired_at {
+            if expired_at <= Utc::now().naive_utc() {
+                return Err("Expiration date must be in the future.");
+            }
+        }
+        let package_scopes = self.package_scopes.as_ref().and_then(|package_scopes| {
+            let package_scopes: Vec<String> = package_scopes
+                .iter()
+                .map(|package_scope| package_scope.trim().to_string())
+                .filter(|package_scope| !package_scope.is_empty())
+                .collect();
+            if package_scopes.is_empty() {
+                None
+            } else {
+                Some(package_scopes)
+            }
+        });
+        Ok(ApiTokenScopes {
+            endpoint_scopes: self.endpoint_scopes.clone(),
+            package_scopes,
+            expired_at: self.expired_at,
+        })
+    }
 }
 
 pub async fn create_token(
@@ -29,8 +69,12 @@ pub async fn create_token(
     }
     let user = request.user()?;
     let db = request.db_pool()?;
+    let scopes = match req.scopes() {
+        Ok(scopes) => scopes,
+        Err(error) => return Ok(HttpResponse::BadRequest().body(error)),
+    };
     let account = Account::get(user.id, db)?;
-    let api_token = ApiToken::insert(&account, &req.name.value, db);
+    let api_token = ApiToken::insert_with_scopes(&account, &req.name.value, &scopes, db);
 
     let api_token = match api_token {
         Err(Error::Database(DatabaseError(DatabaseErrorKind::UniqueViolation, _))) => {
@@ -0,0 +1,123 @@
+use crate::accounts::jobs::SendAccountLockedEmail;
+use crate::accounts::models::account_session::SessionDevice;
+use crate::accounts::models::account_two_factor::AccountTwoFactor;
+use crate::accounts::models::MSG_ACCOUNT_LOCKED;
+use crate::accounts::Account;
+use crate::api::setting::views::{EncodableRecoveryCodes, EncodableTwoFactorEnrollment};
+use crate::utils::request_utils;
+use jelly::actix_web::http::header::ContentType;
+use jelly::actix_web::web;
+use jelly::prelude::*;
+use jelly::Result;
+use serde::Deserialize;
+
+const MSG_INVALID_CODE: &str = "Invalid authentication code.";
+
+#[derive(Deserialize)]
+pub struct TwoFactorCodeRequest {
+    pub code: String,
+}
+
+// Checks the current code asked for by changes to two-factor authentication. Returns the
+// response to send instead when it is wrong, or when too many wrong codes locked the account.
+fn check_code(
+    request: &HttpRequest,
+    code: &str,
+    device: &SessionDevice,
+) -> Result<Option<HttpResponse>> {
+    match request_utils::verify_two_factor_code(request, code, device) {
+        Ok(true) => Ok(None),
+        Ok(false) => Ok(Some(HttpResponse::BadRequest().body(MSG_INVALID_CODE))),
+        Err(Error::AccountLocked) => {
+            let user = request.user()?;
+            request.queue(SendAccountLockedEmail {
+                to: Account::get(user.id, request.db_pool()?)?.email,
+            })?;
+            Ok(Some(HttpResponse::Forbidden().body(MSG_ACCOUNT_LOCKED)))
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Creates the secret to scan into an authenticator app. Two-factor authentication is only
+/// enabled once a code from the app is confirmed.
+pub async fn start_enrollment(request: HttpRequest) -> Result<HttpResponse> {
+    if !request_utils::is_authenticated(&request)? {
+        return Ok(request_utils::clear_cookie(&request));
+    }
+    let user = request.user()?;
+    let db = request.db_pool()?;
+    let account = Account::get(user.id, db)?;
+    let two_factor = match AccountTwoFactor::start_enrollment(account.id, &db.get()?) {
+        Err(Error::Generic(error)) => return Ok(HttpResponse::BadRequest().body(error)),
+        result => result?,
+    };
+
+    let enrollment = EncodableTwoFactorEnrollment {
+        otpauth_uri: two_factor.otpauth_uri(&account.email),
+        qr_code_svg: two_factor.qr_code_svg(&account.email)?,
+        secret: two_factor.secret,
+    };
+    Ok(HttpResponse::Ok()
+        .set(ContentType::json())
+        .json(&enrollment))
+}
+
+/// Enables two-factor authentication with the first code from the app, and returns the
+/// recovery codes. They are only shown this once.
+pub async fn confirm_enrollment(
+    request: HttpRequest,
+    req: web::Json<TwoFactorCodeRequest>,
+) -> Result<HttpResponse> {
+    if !request_utils::is_authenticated(&request)? {
+        return Ok(request_utils::clear_cookie(&request));
+    }
+    let user = request.user()?;
+    let conn = request.db_pool()?.get()?;
+    match AccountTwoFactor::confirm_enrollment(user.id, &req.code, &conn)? {
+        Some(recovery_codes) => Ok(HttpResponse::Ok()
+            .set(ContentType::json())
+            .json(&EncodableRecoveryCodes { recovery_codes })),
+        None => Ok(HttpResponse::BadRequest().body(MSG_INVALID_CODE)),
+    }
+}
+
+/// Replaces the recovery codes. Needs a current code, like disabling.
+pub async fn regenerate_recovery_codes(
+    request: HttpRequest,
+    req: web::Json<TwoFactorCodeRequest>,
+    device: SessionDevice,
+) -> Result<HttpResponse> {
+    if !request_utils::is_authenticated(&request)? {
+        return Ok(request_utils::clear_cookie(&request));
+    }
+    if let Some(response) = check_code(&request, &req.code, &device)? {
+        return Ok(response);
+    }
+    let user = request.user()?;
+    let conn = request.db_pool()?.get()?;
+
+    let recovery_codes = AccountTwoFactor::regenerate_recovery_codes(user.id, &conn)?;
+    Ok(HttpResponse::Ok()
+        .set(ContentType::json())
+        .json(&EncodableRecoveryCodes { recovery_codes }))
+}
+
+/// Turns two-factor authentication off, with a current code.
+pub async fn disable(
+    request: HttpRequest,
+    req: web::Json<TwoFactorCodeRequest>,
+    device: SessionDevice,
+) -> Result<HttpResponse> {
+    if !request_utils::is_authenticated(&request)? {
+        return Ok(request_utils::clear_cookie(&request));
+    }
+    if let Some(response) = check_code(&request, &req.code, &device)? {
+        return Ok(response);
+    }
+    let user = request.user()?;
+    let conn = request.db_pool()?.get()?;
+
+    AccountTwoFactor::disable(user.id, &conn)?;
+    Ok(HttpResponse::Ok().body(""))
+}
@@ -1,4 +1,4 @@
-use jelly::actix_web::web::{delete, get, put, resource, scope, ServiceConfig};
+use jelly::actix_web::web::{delete, get, post, put, resource, scope, ServiceConfig};
 
 pub mod controllers;
 pub mod views;
@@ -14,6 +14,36 @@ pub fn configure(config: &mut ServiceConfig) {
                 resource("/tokens")
                     .route(put().to(controllers::token::create_token)),
             )
+            .service(
+                resource("/sessions/{session_id}")
+                    .route(delete().to(controllers::session::revoke_session)),
+            )
+            .service(
+                resource("/sessions")
+                    .route(delete().to(controllers::session::revoke_all_sessions)),
+            )
+            .service(
+                resource("/two-factor/recovery-codes")
+                    .route(post().to(controllers::two_factor::regenerate_recovery_codes)),
+            )
+            .service(
+                resource("/two-factor")
+                    .route(post().to(controllers::two_factor::start_enrollment))
+                    .route(put().to(controllers::two_factor::confirm_enrollment))
+                    .route(delete().to(controllers::two_factor::disable)),
+            )
+            .service(
+                resource("/passkeys/options")
+                    .route(post().to(controllers::passkey::registration_options)),
+            )
+            .service(
+                resource("/passkeys/{id}")
+                    .route(delete().to(controllers::passkey::delete)),
+            )
+            .service(
+                resource("/passkeys")
+                    .route(post().to(controllers::passkey::register)),
+            )
             .service(
                 resource("/me")
                     .route(get().to(controllers::profile::get_logged_in_user)),
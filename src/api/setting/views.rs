@@ -9,6 +9,21 @@ pub struct EncodableApiTokenWithToken {
     pub token: String,
     pub created_at: NaiveDateTime,
     pub last_used_at: Option<NaiveDateTime>,
+    pub endpoint_scopes: Option<Vec<String>>,
+    pub package_scopes: Option<Vec<String>>,
+    pub expired_at: Option<NaiveDateTime>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct EncodableTwoFactorEnrollment {
+    pub secret: String,
+    pub otpauth_uri: String,
+    pub qr_code_svg: String,
+}
+
+#[derive(Serialize, Debug)]
+pub struct EncodableRecoveryCodes {
+    pub recovery_codes: Vec<String>,
 }
 
 #[derive(Serialize, Debug)]
@@ -32,6 +47,9 @@ impl From<CreatedApiToken> for EncodableApiTokenWithToken {
             token: token.plaintext,
             created_at: token.model.created_at,
             last_used_at: token.model.last_used_at,
+            endpoint_scopes: token.model.endpoint_scopes,
+            package_scopes: token.model.package_scopes,
+            expired_at: token.model.expired_at,
         }
     }
 }
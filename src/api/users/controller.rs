@@ -0,0 +1,73 @@
+#[cfg(test)]
+use crate::test::mock::MockHttpRequest as HttpRequest;
+#[cfg(not(test))]
+use jelly::actix_web::HttpRequest;
+
+use jelly::actix_web::web::Path;
+use jelly::prelude::Error::*;
+use jelly::prelude::*;
+use jelly::utils::error_constants::*;
+use jelly::Result;
+use serde_json::json;
+
+use crate::accounts::Account;
+use crate::api::auth::ApiAccount;
+use crate::api::package::view::PackageSummaryResponse;
+use crate::api::users::view::UserResponse;
+use crate::packages::Package;
+
+pub async fn get_user(request: HttpRequest, Path(user_slug): Path<String>) -> Result<HttpResponse> {
+    let db = request.db_pool().map_err(|e| ApiServerError(Box::new(e)))?;
+    let account = Account::get_by_slug(&user_slug, db)
+        .map_err(|e| ApiNotFound(MSG_ACCOUNT_NOT_FOUND, Box::new(e)))?;
+    // Unverified accounts don't have a public profile either
+    if !account.has_verified_email {
+        return Err(ApiNotFound(
+            MSG_ACCOUNT_NOT_FOUND,
+            Box::new(Error::Generic(format!(
+                "Account has not been verified. uid: {}",
+                account.id
+            ))),
+        ));
+    }
+
+    let packages =
+        Package::get_by_account(account.id, db).map_err(|e| ApiServerError(Box::new(e)))?;
+    let total_downloads_count =
+        Package::get_downloads(account.id, db).map_err(|e| ApiServerError(Box::new(e)))?;
+
+    Ok(HttpResponse::Ok().json(UserResponse::new(
+        account,
+        total_downloads_count,
+        packages
+            .into_iter()
+            .map(PackageSummaryResponse::from)
+            .collect(),
+    )))
+}
+
+/// Lifts the lockout of an account after too many failed sign-ins, before it expires.
+pub async fn unlock_user(
+    request: HttpRequest,
+    api_account: ApiAccount,
+    Path(user_slug): Path<String>,
+) -> Result<HttpResponse> {
+    if !api_account.account.is_admin {
+        return Err(ApiForbidden(
+            MSG_ADMIN_REQUIRED,
+            Box::new(Error::Generic(format!(
+                "Only admins can unlock accounts. uid: {}",
+                api_account.account.id
+            ))),
+        ));
+    }
+    let db = request.db_pool().map_err(|e| ApiServerError(Box::new(e)))?;
+    let account = Account::get_by_slug(&user_slug, db)
+        .map_err(|e| ApiNotFound(MSG_ACCOUNT_NOT_FOUND, Box::new(e)))?;
+    Account::unlock(account.id, db).map_err(|e| ApiServerError(Box::new(e)))?;
+
+    Ok(HttpResponse::Ok().json(json!({
+        "ok": true,
+        "msg": MSG_SUCCESSFULLY_UNLOCKED_ACCOUNT,
+    })))
+}
@@ -0,0 +1,14 @@
+use jelly::actix_web::web::{get, post, resource, scope, ServiceConfig};
+
+pub mod controller;
+#[cfg(test)]
+mod tests;
+pub mod view;
+
+pub fn configure(config: &mut ServiceConfig) {
+    config.service(
+        scope("/api/v1/users")
+            .service(resource("/{user_slug}").route(get().to(controller::get_user)))
+            .service(resource("/{user_slug}/unlock").route(post().to(controller::unlock_user))),
+    );
+}
@@ -0,0 +1,117 @@
+use crate::accounts::Account;
+use crate::api::auth::ApiAccount;
+use crate::api::users::controller::{get_user, unlock_user};
+use crate::schema::accounts;
+use crate::test::util::{create_stub_packages, setup_user};
+use crate::test::{mock, DatabaseTestContext, DB_POOL};
+use diesel::prelude::*;
+use jelly::chrono::{Duration, Utc};
+
+use jelly::actix_web::body::Body;
+use jelly::actix_web::http::StatusCode;
+use jelly::actix_web::web;
+use jelly::error::Error;
+use jelly::utils::error_constants::MSG_ADMIN_REQUIRED;
+
+#[actix_rt::test]
+async fn get_user_works() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let uid = setup_user(None, None);
+    create_stub_packages(uid, 2);
+    let account = Account::get(uid, &DB_POOL).unwrap();
+
+    let mut mock_http_request = mock::MockHttpRequest::new();
+    mock_http_request
+        .expect_db_pool()
+        .returning(|| Ok(&DB_POOL));
+    let response = get_user(mock_http_request, web::Path(account.slug.clone().unwrap()))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let json: serde_json::Value = match response.body().as_ref().unwrap() {
+        Body::Bytes(bytes) => serde_json::from_slice(bytes).unwrap(),
+        _ => panic!("Unexpected response body"),
+    };
+    assert_eq!(json["slug"], account.slug.unwrap());
+    assert_eq!(json["total_downloads_count"], 0);
+    assert_eq!(json["packages"].as_array().unwrap().len(), 2);
+    assert!(json.get("email").is_none());
+}
+
+#[actix_rt::test]
+async fn get_user_returns_error_with_unknown_slug() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let mut mock_http_request = mock::MockHttpRequest::new();
+    mock_http_request
+        .expect_db_pool()
+        .returning(|| Ok(&DB_POOL));
+    let response = get_user(mock_http_request, web::Path("not-a-user".to_string())).await;
+    assert!(matches!(response, Err(Error::ApiNotFound(..))));
+}
+
+fn api_account(is_admin: bool) -> ApiAccount {
+    let uid = setup_user(Some("admin@host.com".to_string()), None);
+    diesel::update(accounts::table.find(uid))
+        .set(accounts::is_admin.eq(is_admin))
+        .execute(&DB_POOL.get().unwrap())
+        .unwrap();
+    ApiAccount {
+        account: Account::get(uid, &DB_POOL).unwrap(),
+        token: None,
+    }
+}
+
+fn locked_account() -> Account {
+    let uid = setup_user(None, None);
+    diesel::update(accounts::table.find(uid))
+        .set(accounts::locked_until.eq(Utc::now() + Duration::minutes(30)))
+        .execute(&DB_POOL.get().unwrap())
+        .unwrap();
+    Account::get(uid, &DB_POOL).unwrap()
+}
+
+#[actix_rt::test]
+async fn unlock_user_works() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let account = locked_account();
+    assert!(account.is_locked());
+    let mut mock_http_request = mock::MockHttpRequest::new();
+    mock_http_request
+        .expect_db_pool()
+        .returning(|| Ok(&DB_POOL));
+    let response = unlock_user(
+        mock_http_request,
+        api_account(true),
+        web::Path(account.slug.unwrap()),
+    )
+    .await
+    .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(!Account::get(account.id, &DB_POOL).unwrap().is_locked());
+}
+
+#[actix_rt::test]
+async fn unlock_user_returns_error_if_not_admin() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let account = locked_account();
+    let mock_http_request = mock::MockHttpRequest::new();
+    let response = unlock_user(
+        mock_http_request,
+        api_account(false),
+        web::Path(account.slug.unwrap()),
+    )
+    .await;
+    assert!(matches!(
+        response,
+        Err(Error::ApiForbidden(MSG_ADMIN_REQUIRED, _))
+    ));
+    assert!(Account::get(account.id, &DB_POOL).unwrap().is_locked());
+}
@@ -0,0 +1,34 @@
+use jelly::chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::accounts::Account;
+use crate::api::package::view::PackageSummaryResponse;
+
+#[derive(Serialize, Deserialize)]
+pub struct UserResponse {
+    pub name: String,
+    pub slug: Option<String>,
+    pub github_login: Option<String>,
+    pub avatar: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub total_downloads_count: i64,
+    pub packages: Vec<PackageSummaryResponse>,
+}
+
+impl UserResponse {
+    pub fn new(
+        account: Account,
+        total_downloads_count: i64,
+        packages: Vec<PackageSummaryResponse>,
+    ) -> Self {
+        UserResponse {
+            name: account.name,
+            slug: account.slug,
+            github_login: account.github_login,
+            avatar: account.avatar,
+            created_at: account.created,
+            total_downloads_count,
+            packages,
+        }
+    }
+}
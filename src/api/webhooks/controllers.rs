@@ -0,0 +1,200 @@
+#[cfg(test)]
+use crate::test::mock::MockHttpRequest as HttpRequest;
+#[cfg(not(test))]
+use jelly::actix_web::HttpRequest;
+
+use jelly::actix_web::web;
+use jelly::actix_web::web::Path;
+use jelly::prelude::Error::*;
+use jelly::prelude::*;
+use jelly::utils::error_constants::*;
+use jelly::{DieselPgConnection, Result};
+use serde_json::json;
+
+use crate::api::auth::ApiAccount;
+use crate::api::webhooks::views::{GithubWebhookPayload, WebhookResponse};
+use crate::jobs::PublishTag;
+use crate::package_collaborators::package_collaborator::{PackageCollaborator, Role};
+use crate::packages::models::package_webhook::PackageWebhook;
+use crate::packages::Package;
+use crate::settings::models::token::EndpointScope;
+use crate::source_hosts;
+
+pub const SIGNATURE_HEADER: &str = "X-Hub-Signature-256";
+pub const EVENT_HEADER: &str = "X-GitHub-Event";
+pub const DELIVERY_HEADER: &str = "X-GitHub-Delivery";
+/// GitHub caps webhook payloads at 25MB, push events with many commits easily exceed the
+/// default limit of actix-web.
+pub const MAX_PAYLOAD_SIZE: usize = 25 * 1024 * 1024;
+
+/// Creates the webhook of a package, or generates a new secret for it. The secret is
+/// only shown in this response.
+pub async fn create_webhook(
+    request: HttpRequest,
+    api_account: ApiAccount,
+    Path(package_slug): Path<String>,
+) -> Result<HttpResponse> {
+    let db = request.db_pool().map_err(|e| ApiServerError(Box::new(e)))?;
+    let conn = db.get().map_err(|e| ApiServerError(Box::new(e)))?;
+    let package = get_owned_package(&package_slug, &api_account, &conn)?;
+
+    let webhook = PackageWebhook::create_or_regenerate(package.id, api_account.account.id, &conn)
+        .map_err(|e| ApiServerError(Box::new(e)))?;
+    let domain = std::env::var("JELLY_DOMAIN").expect("JELLY_DOMAIN is not set");
+
+    Ok(HttpResponse::Ok().json(WebhookResponse {
+        url: format!("{}/api/v1/webhooks/github/{}", domain, package.slug),
+        secret: webhook.secret,
+    }))
+}
+
+pub async fn delete_webhook(
+    request: HttpRequest,
+    api_account: ApiAccount,
+    Path(package_slug): Path<String>,
+) -> Result<HttpResponse> {
+    let db = request.db_pool().map_err(|e| ApiServerError(Box::new(e)))?;
+    let conn = db.get().map_err(|e| ApiServerError(Box::new(e)))?;
+    let package = get_owned_package(&package_slug, &api_account, &conn)?;
+
+    let deleted_rows = PackageWebhook::delete_by_package_id(package.id, &conn)
+        .map_err(|e| ApiServerError(Box::new(e)))?;
+    if deleted_rows == 0 {
+        return Err(ApiNotFound(
+            MSG_WEBHOOK_NOT_FOUND,
+            Box::new(Error::Generic(format!(
+                "Package has no webhook. package id: {}",
+                package.id
+            ))),
+        ));
+    }
+
+    Ok(HttpResponse::Ok().json(json!({
+        "ok": true,
+        "msg": MSG_SUCCESSFULLY_DELETED_WEBHOOK,
+    })))
+}
+
+/// Receives the `push` and `release` events GitHub sends to the webhook of a package,
+/// and publishes the tagged version in the background.
+pub async fn receive_github_webhook(
+    request: HttpRequest,
+    Path(package_slug): Path<String>,
+    body: web::Bytes,
+) -> Result<HttpResponse> {
+    let db = request.db_pool().map_err(|e| ApiServerError(Box::new(e)))?;
+    let conn = db.get().map_err(|e| ApiServerError(Box::new(e)))?;
+
+    let package = Package::get_by_slug(&package_slug, &conn)
+        .map_err(|e| ApiNotFound(MSG_PACKAGE_NOT_FOUND, Box::new(e)))?;
+    let webhook = PackageWebhook::get_by_package_id(package.id, &conn)
+        .map_err(|e| ApiNotFound(MSG_WEBHOOK_NOT_FOUND, Box::new(e)))?;
+
+    let headers = request.headers();
+    let signature = headers
+        .get(SIGNATURE_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("");
+    if !webhook.verify_signature(&body, signature) {
+        return Err(ApiUnauthorized(
+            MSG_INVALID_WEBHOOK_SIGNATURE,
+            Box::new(Error::Generic(format!(
+                "Invalid webhook signature. package id: {}",
+                package.id
+            ))),
+        ));
+    }
+    let event = headers
+        .get(EVENT_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("")
+        .to_string();
+    // e.g. the `ping` event sent when the webhook is added
+    if event != "push" && event != "release" {
+        return Ok(ignored_event_response());
+    }
+
+    let payload = serde_json::from_slice::<GithubWebhookPayload>(&body)
+        .map_err(|e| ApiBadRequest(MSG_INVALID_WEBHOOK_PAYLOAD, Box::new(e)))?;
+    let (tag, rev) = match payload.tag_and_rev(&event) {
+        Some(tag_and_rev) => tag_and_rev,
+        None => return Ok(ignored_event_response()),
+    };
+    let (repository_url, _) =
+        source_hosts::for_url(&package.repository_url).split_tree_url(&package.repository_url);
+    if !source_hosts::normalize_repo_url(&payload.repository.html_url)
+        .eq_ignore_ascii_case(&source_hosts::normalize_repo_url(&repository_url))
+    {
+        return Err(ApiBadRequest(
+            MSG_WEBHOOK_REPOSITORY_MISMATCH,
+            Box::new(Error::Generic(format!(
+                "Webhook sent from {} to package {}",
+                payload.repository.html_url, package.id
+            ))),
+        ));
+    }
+
+    // GitHub redelivers webhooks with the same delivery id, which are only handled once
+    let delivery_id = request
+        .headers()
+        .get(DELIVERY_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|delivery_id| !delivery_id.is_empty())
+        .map(|delivery_id| delivery_id.to_string());
+    if let Some(delivery_id) = &delivery_id {
+        let is_new_delivery = PackageWebhook::record_delivery(delivery_id, &conn)
+            .map_err(|e| ApiServerError(Box::new(e)))?;
+        if !is_new_delivery {
+            return Ok(ignored_event_response());
+        }
+    }
+
+    if let Err(e) = request.queue(PublishTag {
+        package_id: package.id,
+        tag,
+        rev,
+    }) {
+        if let Some(delivery_id) = &delivery_id {
+            let _ = PackageWebhook::forget_delivery(delivery_id, &conn);
+        }
+        return Err(ApiServerError(Box::new(e)));
+    }
+
+    Ok(HttpResponse::Accepted().json(json!({
+        "ok": true,
+        "msg": MSG_SUCCESSFULLY_QUEUED_PUBLISH,
+    })))
+}
+
+// Other events are acknowledged so that GitHub doesn't report failed deliveries
+fn ignored_event_response() -> HttpResponse {
+    HttpResponse::Ok().json(json!({
+        "ok": true,
+        "msg": MSG_WEBHOOK_EVENT_IGNORED,
+    }))
+}
+
+fn get_owned_package(
+    package_slug: &str,
+    api_account: &ApiAccount,
+    conn: &DieselPgConnection,
+) -> Result<Package> {
+    let package = Package::get_by_slug(package_slug, conn)
+        .map_err(|e| ApiNotFound(MSG_PACKAGE_NOT_FOUND, Box::new(e)))?;
+    api_account.authorize(EndpointScope::PublishUpdate, &package.name, &package.slug)?;
+    let account = &api_account.account;
+
+    let role = PackageCollaborator::get_role(&package, account.id, conn)
+        .map_err(|e| ApiForbidden(MSG_UNAUTHORIZED_TO_MANAGE_WEBHOOK, Box::new(e)))?;
+    if role != Role::Owner {
+        return Err(ApiForbidden(
+            MSG_UNAUTHORIZED_TO_MANAGE_WEBHOOK,
+            Box::new(Error::Generic(format!(
+                "Only owners can manage webhooks. uid: {}, package id: {}",
+                account.id, package.id
+            ))),
+        ));
+    }
+
+    Ok(package)
+}
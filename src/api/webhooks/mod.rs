@@ -0,0 +1,22 @@
+use jelly::actix_web::web::{delete, post, resource, scope, PayloadConfig, ServiceConfig};
+
+pub mod controllers;
+#[cfg(test)]
+mod tests;
+pub mod views;
+
+pub fn configure(config: &mut ServiceConfig) {
+    config.service(
+        scope("/api/v1/webhooks")
+            .service(
+                resource("/packages/{package_slug}")
+                    .route(post().to(controllers::create_webhook))
+                    .route(delete().to(controllers::delete_webhook)),
+            )
+            .service(
+                resource("/github/{package_slug}")
+                    .app_data(PayloadConfig::new(controllers::MAX_PAYLOAD_SIZE))
+                    .route(post().to(controllers::receive_github_webhook)),
+            ),
+    );
+}
@@ -0,0 +1,308 @@
+use crate::accounts::Account;
+use crate::api::auth::ApiAccount;
+use crate::api::webhooks::controllers::{
+    create_webhook, delete_webhook, receive_github_webhook, DELIVERY_HEADER, EVENT_HEADER,
+    SIGNATURE_HEADER,
+};
+use crate::api::webhooks::views::{GithubWebhookPayload, WebhookResponse};
+use crate::jobs::PublishTag;
+use crate::package_collaborators::package_collaborator::PackageCollaborator;
+use crate::packages::models::package_webhook::PackageWebhook;
+use crate::packages::Package;
+use crate::test::util::{setup_package, setup_user};
+use crate::test::{mock, DatabaseTestContext, DB_POOL};
+
+use jelly::actix_web::body::{Body, ResponseBody};
+use jelly::actix_web::http::{HeaderMap, HeaderName, HeaderValue, StatusCode};
+use jelly::actix_web::web::{Bytes, Path};
+use jelly::error::Error;
+use jelly::utils::error_constants::{
+    MSG_INVALID_WEBHOOK_SIGNATURE, MSG_UNAUTHORIZED_TO_MANAGE_WEBHOOK,
+    MSG_WEBHOOK_REPOSITORY_MISMATCH,
+};
+use serde_json::json;
+
+fn create_package(owner_id: i32) -> Package {
+    let pid = setup_package(
+        "MoveStdlib",
+        "https://github.com/move-language/move/tree/main/language/move-stdlib",
+        owner_id,
+    );
+    Package::get(pid, &DB_POOL).unwrap()
+}
+
+fn api_account(uid: i32) -> ApiAccount {
+    ApiAccount {
+        account: Account::get(uid, &DB_POOL).unwrap(),
+        token: None,
+    }
+}
+
+fn mock_request() -> mock::MockHttpRequest {
+    let mut mock_http_request = mock::MockHttpRequest::new();
+    mock_http_request
+        .expect_db_pool()
+        .returning(|| Ok(&DB_POOL));
+    mock_http_request
+}
+
+fn mock_delivery(event: &str, signature: &str) -> mock::MockHttpRequest {
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        HeaderName::from_static("x-github-event"),
+        HeaderValue::from_str(event).unwrap(),
+    );
+    headers.insert(
+        HeaderName::from_static("x-hub-signature-256"),
+        HeaderValue::from_str(signature).unwrap(),
+    );
+    headers.insert(
+        HeaderName::from_static("x-github-delivery"),
+        HeaderValue::from_static("72d3162e-cc78-11e3-81ab-4c9367dc0958"),
+    );
+    assert!(
+        headers.contains_key(EVENT_HEADER)
+            && headers.contains_key(SIGNATURE_HEADER)
+            && headers.contains_key(DELIVERY_HEADER)
+    );
+    let mut mock_http_request = mock_request();
+    mock_http_request.expect_headers().return_const(headers);
+    mock_http_request
+}
+
+fn tag_push_payload(repository_url: &str) -> Bytes {
+    Bytes::from(
+        json!({
+            "ref": "refs/tags/v1.1.0",
+            "after": "30b0b8dd1b6b5b0a0a1ad4c4a1b0b2c8ee4ad421",
+            "deleted": false,
+            "repository": { "html_url": repository_url },
+        })
+        .to_string(),
+    )
+}
+
+fn body_text(body: &ResponseBody<Body>) -> String {
+    match body.as_ref().unwrap() {
+        Body::Bytes(bytes) => String::from_utf8(bytes.to_vec()).unwrap(),
+        _ => panic!("Unexpected response body"),
+    }
+}
+
+#[test]
+fn tag_and_rev_works() {
+    let payload = serde_json::from_value::<GithubWebhookPayload>(json!({
+        "ref": "refs/heads/main",
+        "after": "30b0b8dd1b6b5b0a0a1ad4c4a1b0b2c8ee4ad421",
+        "repository": { "html_url": "https://github.com/move-language/move" },
+    }))
+    .unwrap();
+    assert_eq!(payload.tag_and_rev("push"), None);
+
+    let payload = serde_json::from_value::<GithubWebhookPayload>(json!({
+        "ref": "refs/tags/v1.1.0",
+        "after": "0000000000000000000000000000000000000000",
+        "deleted": true,
+        "repository": { "html_url": "https://github.com/move-language/move" },
+    }))
+    .unwrap();
+    assert_eq!(payload.tag_and_rev("push"), None);
+
+    let payload = serde_json::from_value::<GithubWebhookPayload>(json!({
+        "action": "published",
+        "release": { "tag_name": "v1.1.0" },
+        "repository": { "html_url": "https://github.com/move-language/move" },
+    }))
+    .unwrap();
+    assert_eq!(
+        payload.tag_and_rev("release"),
+        Some(("v1.1.0".to_string(), "v1.1.0".to_string()))
+    );
+}
+
+#[actix_rt::test]
+async fn create_webhook_works() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let uid = setup_user(None, None);
+    let package = create_package(uid);
+    let response = create_webhook(mock_request(), api_account(uid), Path(package.slug.clone()))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let webhook_response =
+        serde_json::from_str::<WebhookResponse>(&body_text(response.body())).unwrap();
+    assert!(webhook_response
+        .url
+        .ends_with(&format!("/api/v1/webhooks/github/{}", package.slug)));
+    let webhook = PackageWebhook::get_by_package_id(package.id, &DB_POOL.get().unwrap()).unwrap();
+    assert_eq!(webhook_response.secret, webhook.secret);
+}
+
+#[actix_rt::test]
+async fn create_webhook_returns_error_if_not_owner() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let owner_id = setup_user(None, None);
+    let package = create_package(owner_id);
+    let collaborator_id = setup_user(Some("collaborator@host.com".to_string()), None);
+    PackageCollaborator::new_collaborator(
+        package.id,
+        collaborator_id,
+        owner_id,
+        &DB_POOL.get().unwrap(),
+    )
+    .unwrap();
+
+    let response = create_webhook(
+        mock_request(),
+        api_account(collaborator_id),
+        Path(package.slug.clone()),
+    )
+    .await;
+    assert!(
+        matches!(response, Err(Error::ApiForbidden(msg, _)) if msg == MSG_UNAUTHORIZED_TO_MANAGE_WEBHOOK)
+    );
+    assert!(PackageWebhook::get_by_package_id(package.id, &DB_POOL.get().unwrap()).is_err());
+}
+
+#[actix_rt::test]
+async fn delete_webhook_works() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let uid = setup_user(None, None);
+    let package = create_package(uid);
+    PackageWebhook::create_or_regenerate(package.id, uid, &DB_POOL.get().unwrap()).unwrap();
+
+    let response = delete_webhook(mock_request(), api_account(uid), Path(package.slug.clone()))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(PackageWebhook::get_by_package_id(package.id, &DB_POOL.get().unwrap()).is_err());
+
+    let response = delete_webhook(mock_request(), api_account(uid), Path(package.slug)).await;
+    assert!(matches!(response, Err(Error::ApiNotFound(..))));
+}
+
+#[actix_rt::test]
+async fn receive_github_webhook_queues_tag_push() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let uid = setup_user(None, None);
+    let package = create_package(uid);
+    let webhook =
+        PackageWebhook::create_or_regenerate(package.id, uid, &DB_POOL.get().unwrap()).unwrap();
+    let payload = tag_push_payload("https://github.com/move-language/move");
+
+    let mut mock_http_request = mock_delivery("push", &webhook.sign(&payload));
+    let package_id = package.id;
+    mock_http_request
+        .expect_queue::<PublishTag>()
+        .withf(move |job| {
+            job.package_id == package_id
+                && job.tag == "v1.1.0"
+                && job.rev == "30b0b8dd1b6b5b0a0a1ad4c4a1b0b2c8ee4ad421"
+        })
+        .times(1)
+        .returning(|_| Ok(()));
+    let response = receive_github_webhook(mock_http_request, Path(package.slug), payload)
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+}
+
+#[actix_rt::test]
+async fn receive_github_webhook_ignores_redeliveries() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let uid = setup_user(None, None);
+    let package = create_package(uid);
+    let webhook =
+        PackageWebhook::create_or_regenerate(package.id, uid, &DB_POOL.get().unwrap()).unwrap();
+    let payload = tag_push_payload("https://github.com/move-language/move");
+
+    let mut mock_http_request = mock_delivery("push", &webhook.sign(&payload));
+    mock_http_request
+        .expect_queue::<PublishTag>()
+        .times(1)
+        .returning(|_| Ok(()));
+    let response = receive_github_webhook(
+        mock_http_request,
+        Path(package.slug.clone()),
+        payload.clone(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+
+    let mut mock_http_request = mock_delivery("push", &webhook.sign(&payload));
+    mock_http_request.expect_queue::<PublishTag>().times(0);
+    let response = receive_github_webhook(mock_http_request, Path(package.slug), payload)
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[actix_rt::test]
+async fn receive_github_webhook_ignores_other_events() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let uid = setup_user(None, None);
+    let package = create_package(uid);
+    let webhook =
+        PackageWebhook::create_or_regenerate(package.id, uid, &DB_POOL.get().unwrap()).unwrap();
+    let payload = Bytes::from(json!({ "zen": "Keep it logically awesome." }).to_string());
+
+    let mut mock_http_request = mock_delivery("ping", &webhook.sign(&payload));
+    mock_http_request.expect_queue::<PublishTag>().times(0);
+    let response = receive_github_webhook(mock_http_request, Path(package.slug), payload)
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[actix_rt::test]
+async fn receive_github_webhook_returns_error_with_invalid_signature() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let uid = setup_user(None, None);
+    let package = create_package(uid);
+    let mut webhook =
+        PackageWebhook::create_or_regenerate(package.id, uid, &DB_POOL.get().unwrap()).unwrap();
+    webhook.secret = "another secret".to_string();
+    let payload = tag_push_payload("https://github.com/move-language/move");
+
+    let mut mock_http_request = mock_delivery("push", &webhook.sign(&payload));
+    mock_http_request.expect_queue::<PublishTag>().times(0);
+    let response = receive_github_webhook(mock_http_request, Path(package.slug), payload).await;
+    assert!(
+        matches!(response, Err(Error::ApiUnauthorized(msg, _)) if msg == MSG_INVALID_WEBHOOK_SIGNATURE)
+    );
+}
+
+#[actix_rt::test]
+async fn receive_github_webhook_returns_error_from_other_repository() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let uid = setup_user(None, None);
+    let package = create_package(uid);
+    let webhook =
+        PackageWebhook::create_or_regenerate(package.id, uid, &DB_POOL.get().unwrap()).unwrap();
+    let payload = tag_push_payload("https://github.com/move-language/move-fork");
+
+    let mut mock_http_request = mock_delivery("push", &webhook.sign(&payload));
+    mock_http_request.expect_queue::<PublishTag>().times(0);
+    let response = receive_github_webhook(mock_http_request, Path(package.slug), payload).await;
+    assert!(
+        matches!(response, Err(Error::ApiBadRequest(msg, _)) if msg == MSG_WEBHOOK_REPOSITORY_MISMATCH)
+    );
+}
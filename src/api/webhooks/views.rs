@@ -0,0 +1,49 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize)]
+pub struct WebhookResponse {
+    pub url: String,
+    pub secret: String,
+}
+
+#[derive(Deserialize)]
+pub struct GithubWebhookRepository {
+    pub html_url: String,
+}
+
+#[derive(Deserialize)]
+pub struct GithubRelease {
+    pub tag_name: String,
+}
+
+/// The fields of GitHub `push` and `release` events needed to publish a tag.
+#[derive(Deserialize)]
+pub struct GithubWebhookPayload {
+    #[serde(rename = "ref")]
+    pub git_ref: Option<String>,
+    pub after: Option<String>,
+    #[serde(default)]
+    pub deleted: bool,
+    pub action: Option<String>,
+    pub release: Option<GithubRelease>,
+    pub repository: GithubWebhookRepository,
+}
+
+impl GithubWebhookPayload {
+    /// The pushed tag and the commit it points to, or the tag of a published release.
+    /// `None` for events that don't publish anything, e.g. a push to a branch.
+    pub fn tag_and_rev(&self, event: &str) -> Option<(String, String)> {
+        match event {
+            "push" if !self.deleted => {
+                let tag = self.git_ref.as_deref()?.strip_prefix("refs/tags/")?;
+                Some((tag.to_string(), self.after.clone()?))
+            }
+            // Release events don't include the sha of the tagged commit
+            "release" if self.action.as_deref() == Some("published") => {
+                let tag = &self.release.as_ref()?.tag_name;
+                Some((tag.clone(), tag.clone()))
+            }
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,80 @@
+use diesel::prelude::*;
+use diesel::{Identifiable, Queryable};
+use jelly::chrono::{DateTime, Utc};
+use jelly::DieselPgPool;
+use jelly::Result;
+
+use crate::schema::github_responses;
+
+/// A response from Github, kept to make conditional requests for the same url.
+#[derive(Debug, Queryable, Identifiable)]
+#[table_name = "github_responses"]
+#[primary_key(url)]
+pub struct CachedResponse {
+    pub url: String,
+    pub etag: Option<String>,
+    pub body: String,
+    pub is_immutable: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl CachedResponse {
+    pub fn get(url_: &str, pool: &DieselPgPool) -> Result<Option<Self>> {
+        let conn = pool.get()?;
+        Ok(github_responses::table
+            .find(url_)
+            .first::<CachedResponse>(&conn)
+            .optional()?)
+    }
+
+    pub fn save(
+        url_: &str,
+        etag_: Option<&str>,
+        body_: &str,
+        is_immutable_: bool,
+        pool: &DieselPgPool,
+    ) -> Result<()> {
+        let conn = pool.get()?;
+        diesel::insert_into(github_responses::table)
+            .values((
+                github_responses::url.eq(url_),
+                github_responses::etag.eq(etag_),
+                github_responses::body.eq(body_),
+                github_responses::is_immutable.eq(is_immutable_),
+            ))
+            .on_conflict(github_responses::url)
+            .do_update()
+            .set((
+                github_responses::etag.eq(etag_),
+                github_responses::body.eq(body_),
+                github_responses::is_immutable.eq(is_immutable_),
+                github_responses::updated_at.eq(Utc::now()),
+            ))
+            .execute(&conn)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::{DatabaseTestContext, DB_POOL};
+
+    #[actix_rt::test]
+    async fn save_replaces_cached_response() {
+        crate::test::init();
+        let _ctx = DatabaseTestContext::new();
+
+        let url = "https://api.github.com/repos/move-language/move";
+        assert!(CachedResponse::get(url, &DB_POOL).unwrap().is_none());
+
+        CachedResponse::save(url, Some("\"v1\""), "first", false, &DB_POOL).unwrap();
+        CachedResponse::save(url, Some("\"v2\""), "second", false, &DB_POOL).unwrap();
+        let cached = CachedResponse::get(url, &DB_POOL).unwrap().unwrap();
+        assert_eq!(cached.etag, Some("\"v2\"".to_string()));
+        assert_eq!(cached.body, "second");
+        assert!(!cached.is_immutable);
+    }
+}
@@ -0,0 +1,654 @@
+use jelly::error::Error;
+use jelly::error::Error::Generic;
+use jelly::DieselPgPool;
+use lazy_static::lazy_static;
+use reqwest::blocking::{Client, Response};
+use reqwest::header::{self, HeaderMap};
+use reqwest::StatusCode;
+use serde::de::DeserializeOwned;
+use std::collections::HashMap;
+use std::env;
+use std::sync::{Condvar, Mutex, RwLock};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::github_service::APP_USER_AGENT;
+
+pub mod cache;
+use cache::CachedResponse;
+
+lazy_static! {
+    /// Client shared by everything that talks to Github, so that the rate limit
+    /// is tracked across the crawler's threads.
+    pub static ref GITHUB_CLIENT: GithubClient = GithubClient::new(Default::default());
+}
+
+pub struct GithubClientConfig {
+    /// Requests that can be in flight at the same time
+    pub max_concurrent_requests: usize,
+    /// Retries after a 429 response, or a 403 response for a rate limit
+    pub max_retries: u32,
+    /// Delay before the first retry, doubled on every following one
+    pub base_delay: Duration,
+    /// Requests fail instead of waiting longer than this for the rate limit to reset
+    pub max_wait: Duration,
+}
+
+impl Default for GithubClientConfig {
+    fn default() -> Self {
+        GithubClientConfig {
+            max_concurrent_requests: 4,
+            max_retries: 3,
+            base_delay: Duration::from_secs(1),
+            max_wait: Duration::from_secs(5 * 60),
+        }
+    }
+}
+
+// What Github told us about a rate limit. The bucket is refilled when the limit resets.
+#[derive(Clone, Copy, Debug)]
+struct RateLimitBucket {
+    remaining: u64,
+    reset_at: SystemTime,
+}
+
+pub struct GithubClient {
+    client: Client,
+    config: GithubClientConfig,
+    // Keyed by the rate limit resource, e.g. `core` or `search`
+    buckets: Mutex<HashMap<&'static str, RateLimitBucket>>,
+    in_flight: Mutex<usize>,
+    slot_freed: Condvar,
+    // Responses are only cached once a database is configured
+    cache: RwLock<Option<DieselPgPool>>,
+}
+
+/// The status and body of a Github response, which may come from the cache.
+pub struct GithubResponse {
+    status: StatusCode,
+    body: String,
+}
+
+impl GithubResponse {
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    pub fn text(self) -> String {
+        self.body
+    }
+
+    pub fn json<T: DeserializeOwned>(&self) -> serde_json::Result<T> {
+        serde_json::from_str(&self.body)
+    }
+}
+
+impl GithubClient {
+    pub fn new(config: GithubClientConfig) -> Self {
+        GithubClient {
+            client: Client::builder()
+                .user_agent(APP_USER_AGENT)
+                .build()
+                .expect("Unable to build Github client"),
+            config,
+            buckets: Mutex::new(HashMap::new()),
+            in_flight: Mutex::new(0),
+            slot_freed: Condvar::new(),
+            cache: RwLock::new(None),
+        }
+    }
+
+    /// Caches responses in the database from now on.
+    pub fn use_cache(&self, pool: DieselPgPool) {
+        *self.cache.write().unwrap() = Some(pool);
+    }
+
+    /// Sends an authenticated GET request, waiting for the rate limit when it is
+    /// used up and backing off when Github rejects the request.
+    pub fn get(&self, url: &str) -> Result<Response, Error> {
+        self.send(url, None)
+    }
+
+    /// Like `get`, but asks Github whether the cached response for `url` is still
+    /// current and serves it from the cache if it is. Immutable responses, e.g. files
+    /// at a fixed commit, are never requested again once cached.
+    pub fn get_cached(&self, url: &str, is_immutable: bool) -> Result<GithubResponse, Error> {
+        let pool = match self.cache.read().unwrap().clone() {
+            Some(pool) => pool,
+            None => {
+                let response = self.get(url)?;
+                return Ok(GithubResponse {
+                    status: response.status(),
+                    body: response.text()?,
+                });
+            }
+        };
+
+        let cached = CachedResponse::get(url, &pool).unwrap_or_else(|e| {
+            error!(
+                "Error reading cached response. url: {}, error: {:?}",
+                url, e
+            );
+            None
+        });
+        if let Some(cached) = &cached {
+            if cached.is_immutable {
+                return Ok(GithubResponse {
+                    status: StatusCode::OK,
+                    body: cached.body.clone(),
+                });
+            }
+        }
+
+        let response = self.send(url, cached.as_ref().and_then(|c| c.etag.as_deref()))?;
+        let status = response.status();
+        if status == StatusCode::NOT_MODIFIED {
+            if let Some(cached) = cached {
+                return Ok(GithubResponse {
+                    status: StatusCode::OK,
+                    body: cached.body,
+                });
+            }
+        }
+
+        let etag = header_value::<String>(response.headers(), "etag");
+        let body = response.text()?;
+        if status == StatusCode::OK && (etag.is_some() || is_immutable) {
+            if let Err(e) = CachedResponse::save(url, etag.as_deref(), &body, is_immutable, &pool) {
+                error!("Error caching response. url: {}, error: {:?}", url, e);
+            }
+        }
+        Ok(GithubResponse { status, body })
+    }
+
+    fn send(&self, url: &str, etag: Option<&str>) -> Result<Response, Error> {
+        let access_token =
+            env::var("GITHUB_ACCESS_TOKEN").expect("Unable to pull GITHUB_ACCESS_TOKEN");
+        let resource = rate_limit_resource(url);
+        let mut attempt = 0;
+        loop {
+            self.wait_for_rate_limit(resource)?;
+            let response = {
+                let _slot = self.acquire_slot();
+                let mut request = self
+                    .client
+                    .get(url)
+                    .header(header::AUTHORIZATION, format!("token {}", &access_token));
+                if let Some(etag) = etag {
+                    request = request.header(header::IF_NONE_MATCH, etag);
+                }
+                request.send()?
+            };
+            self.update_rate_limit(resource, response.headers());
+
+            let status = response.status();
+            let delay = match status {
+                StatusCode::TOO_MANY_REQUESTS => retry_after(response.headers())
+                    .unwrap_or_else(|| self.config.base_delay * 2u32.pow(attempt)),
+                // Other 403s, e.g. for missing permissions, won't pass by retrying
+                StatusCode::FORBIDDEN => match retry_after(response.headers()) {
+                    Some(delay) => delay,
+                    None => return Ok(response),
+                },
+                _ => return Ok(response),
+            };
+            if attempt >= self.config.max_retries {
+                warn!(
+                    "Github rejected request after {} retries. url: {}",
+                    attempt, url
+                );
+                return Ok(response);
+            }
+
+            if delay > self.config.max_wait {
+                return Err(Generic(format!(
+                    "Github asked to retry in {:?}. url: {}",
+                    delay, url
+                )));
+            }
+            warn!(
+                "Github responded with {}, retrying in {:?}. url: {}",
+                status, delay, url
+            );
+            thread::sleep(delay);
+            attempt += 1;
+        }
+    }
+
+    fn wait_for_rate_limit(&self, resource: &'static str) -> Result<(), Error> {
+        loop {
+            let mut buckets = self.buckets.lock().unwrap();
+            let bucket = match buckets.get_mut(resource) {
+                Some(bucket) => bucket,
+                None => return Ok(()),
+            };
+            if bucket.remaining > 0 {
+                bucket.remaining -= 1;
+                return Ok(());
+            }
+
+            let wait = match bucket.reset_at.duration_since(SystemTime::now()) {
+                Ok(wait) if !wait.is_zero() => wait,
+                // The next response tells us how much is left after the reset
+                _ => {
+                    buckets.remove(resource);
+                    return Ok(());
+                }
+            };
+            if wait > self.config.max_wait {
+                return Err(Generic(format!(
+                    "Github {} rate limit is used up for another {:?}",
+                    resource, wait
+                )));
+            }
+            // The bucket is kept while sleeping, so that other requests wait for the reset too
+            drop(buckets);
+            info!(
+                "Github {} rate limit is used up, waiting {:?}",
+                resource, wait
+            );
+            thread::sleep(wait);
+        }
+    }
+
+    fn update_rate_limit(&self, resource: &'static str, headers: &HeaderMap) {
+        let remaining = header_value::<u64>(headers, "x-ratelimit-remaining");
+        let reset = header_value::<u64>(headers, "x-ratelimit-reset");
+        if let (Some(remaining), Some(reset)) = (remaining, reset) {
+            self.buckets.lock().unwrap().insert(
+                resource,
+                RateLimitBucket {
+                    remaining,
+                    reset_at: UNIX_EPOCH + Duration::from_secs(reset),
+                },
+            );
+        }
+    }
+
+    fn acquire_slot(&self) -> RequestSlot<'_> {
+        let mut in_flight = self.in_flight.lock().unwrap();
+        while *in_flight >= self.config.max_concurrent_requests {
+            in_flight = self.slot_freed.wait(in_flight).unwrap();
+        }
+        *in_flight += 1;
+        RequestSlot { client: self }
+    }
+}
+
+// Frees its place among the concurrent requests when dropped
+struct RequestSlot<'a> {
+    client: &'a GithubClient,
+}
+
+impl Drop for RequestSlot<'_> {
+    fn drop(&mut self) {
+        *self.client.in_flight.lock().unwrap() -= 1;
+        self.client.slot_freed.notify_one();
+    }
+}
+
+// Github counts searches separately from the other API calls
+fn rate_limit_resource(url: &str) -> &'static str {
+    if url.contains("/search/") {
+        "search"
+    } else {
+        "core"
+    }
+}
+
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    if let Some(seconds) = header_value::<u64>(headers, "retry-after") {
+        return Some(Duration::from_secs(seconds));
+    }
+    // A 403 without Retry-After is only a rate limit if there's nothing left
+    if header_value::<u64>(headers, "x-ratelimit-remaining") == Some(0) {
+        let reset = header_value::<u64>(headers, "x-ratelimit-reset")?;
+        return Some(
+            (UNIX_EPOCH + Duration::from_secs(reset))
+                .duration_since(SystemTime::now())
+                .unwrap_or_default(),
+        );
+    }
+    None
+}
+
+fn header_value<T: std::str::FromStr>(headers: &HeaderMap, name: &str) -> Option<T> {
+    headers.get(name)?.to_str().ok()?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use httpmock::prelude::*;
+    use httpmock::MockServer;
+    use std::sync::Arc;
+    use std::time::Instant;
+
+    use super::*;
+    use crate::test::{DatabaseTestContext, DB_POOL};
+
+    fn test_client(max_concurrent_requests: usize) -> GithubClient {
+        GithubClient::new(GithubClientConfig {
+            max_concurrent_requests,
+            max_retries: 2,
+            base_delay: Duration::from_millis(10),
+            max_wait: Duration::from_secs(5),
+        })
+    }
+
+    fn seconds_from_now(seconds: u64) -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+            + seconds
+    }
+
+    #[test]
+    fn get_sends_authenticated_request() {
+        crate::test::init();
+
+        let access_token = env::var("GITHUB_ACCESS_TOKEN").unwrap();
+        let server = MockServer::start();
+        let server_mock = server.mock(|when, then| {
+            when.method(GET)
+                .path("/repos/move-language/move")
+                .header("User-Agent", APP_USER_AGENT)
+                .header("authorization", format!("token {}", &access_token));
+            then.status(200).body("ok");
+        });
+
+        let response = test_client(1)
+            .get(&format!("{}/repos/move-language/move", server.base_url()))
+            .unwrap();
+        server_mock.assert();
+        assert_eq!(response.text().unwrap(), "ok");
+    }
+
+    #[test]
+    fn get_retries_with_backoff_when_rate_limited() {
+        crate::test::init();
+
+        let server = MockServer::start();
+        let server_mock = server.mock(|when, then| {
+            when.method(GET).path("/repos/move-language/move");
+            then.status(429);
+        });
+
+        let started_at = Instant::now();
+        let response = test_client(1)
+            .get(&format!("{}/repos/move-language/move", server.base_url()))
+            .unwrap();
+        server_mock.assert_hits(3);
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        // 10ms, then 20ms
+        assert!(started_at.elapsed() >= Duration::from_millis(30));
+    }
+
+    #[test]
+    fn get_honors_retry_after() {
+        crate::test::init();
+
+        let server = MockServer::start();
+        let server_mock = server.mock(|when, then| {
+            when.method(GET).path("/repos/move-language/move");
+            then.status(403).header("Retry-After", "1");
+        });
+
+        let client = GithubClient::new(GithubClientConfig {
+            max_concurrent_requests: 1,
+            max_retries: 1,
+            base_delay: Duration::from_millis(10),
+            max_wait: Duration::from_secs(5),
+        });
+        let started_at = Instant::now();
+        client
+            .get(&format!("{}/repos/move-language/move", server.base_url()))
+            .unwrap();
+        server_mock.assert_hits(2);
+        assert!(started_at.elapsed() >= Duration::from_secs(1));
+    }
+
+    #[test]
+    fn get_does_not_retry_forbidden_requests_without_rate_limit() {
+        crate::test::init();
+
+        let server = MockServer::start();
+        let server_mock = server.mock(|when, then| {
+            when.method(GET).path("/repos/move-language/move");
+            then.status(403).header("X-RateLimit-Remaining", "4999");
+        });
+
+        let response = test_client(1)
+            .get(&format!("{}/repos/move-language/move", server.base_url()))
+            .unwrap();
+        server_mock.assert_hits(1);
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[test]
+    fn get_fails_when_retry_after_is_too_long() {
+        crate::test::init();
+
+        let server = MockServer::start();
+        let server_mock = server.mock(|when, then| {
+            when.method(GET).path("/repos/move-language/move");
+            then.status(429).header("Retry-After", "3600");
+        });
+
+        let result = test_client(1).get(&format!("{}/repos/move-language/move", server.base_url()));
+        server_mock.assert_hits(1);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn get_waits_for_rate_limit_reset() {
+        crate::test::init();
+
+        let server = MockServer::start();
+        let reset = seconds_from_now(2).to_string();
+        let server_mock = server.mock(|when, then| {
+            when.method(GET).path("/repos/move-language/move");
+            then.status(200)
+                .header("X-RateLimit-Remaining", "0")
+                .header("X-RateLimit-Reset", &reset);
+        });
+
+        let client = test_client(1);
+        let url = format!("{}/repos/move-language/move", server.base_url());
+        client.get(&url).unwrap();
+        let started_at = Instant::now();
+        client.get(&url).unwrap();
+        server_mock.assert_hits(2);
+        assert!(started_at.elapsed() >= Duration::from_secs(1));
+    }
+
+    #[test]
+    fn get_makes_every_request_wait_for_rate_limit_reset() {
+        crate::test::init();
+
+        let server = MockServer::start();
+        let reset = seconds_from_now(2).to_string();
+        let server_mock = server.mock(|when, then| {
+            when.method(GET).path("/repos/move-language/move");
+            then.status(200)
+                .header("X-RateLimit-Remaining", "0")
+                .header("X-RateLimit-Reset", &reset);
+        });
+
+        let client = Arc::new(test_client(2));
+        let url = format!("{}/repos/move-language/move", server.base_url());
+        client.get(&url).unwrap();
+        let started_at = Instant::now();
+        let handles = (0..2)
+            .map(|_| {
+                let client = client.clone();
+                let url = url.clone();
+                thread::spawn(move || {
+                    client.get(&url).unwrap();
+                    started_at.elapsed()
+                })
+            })
+            .collect::<Vec<_>>();
+        for handle in handles {
+            assert!(handle.join().unwrap() >= Duration::from_secs(1));
+        }
+        server_mock.assert_hits(3);
+    }
+
+    #[test]
+    fn get_fails_instead_of_waiting_too_long_for_rate_limit_reset() {
+        crate::test::init();
+
+        let server = MockServer::start();
+        let reset = seconds_from_now(3600).to_string();
+        let server_mock = server.mock(|when, then| {
+            when.method(GET).path("/repos/move-language/move");
+            then.status(200)
+                .header("X-RateLimit-Remaining", "0")
+                .header("X-RateLimit-Reset", &reset);
+        });
+
+        let client = test_client(1);
+        let url = format!("{}/repos/move-language/move", server.base_url());
+        client.get(&url).unwrap();
+        assert!(client.get(&url).is_err());
+        server_mock.assert_hits(1);
+
+        // Searches have their own rate limit
+        let search_mock = server.mock(|when, then| {
+            when.method(GET).path("/search/code");
+            then.status(200);
+        });
+        client
+            .get(&format!("{}/search/code", server.base_url()))
+            .unwrap();
+        search_mock.assert();
+    }
+
+    #[test]
+    fn get_limits_concurrent_requests() {
+        crate::test::init();
+
+        let server = MockServer::start();
+        let server_mock = server.mock(|when, then| {
+            when.method(GET).path("/repos/move-language/move");
+            then.status(200).delay(Duration::from_millis(200));
+        });
+
+        let client = Arc::new(test_client(2));
+        let url = format!("{}/repos/move-language/move", server.base_url());
+        let started_at = Instant::now();
+        let handles = (0..4)
+            .map(|_| {
+                let client = client.clone();
+                let url = url.clone();
+                thread::spawn(move || client.get(&url).unwrap())
+            })
+            .collect::<Vec<_>>();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        server_mock.assert_hits(4);
+        // Two batches of two requests
+        assert!(started_at.elapsed() >= Duration::from_millis(400));
+    }
+
+    #[actix_rt::test]
+    async fn get_cached_serves_cached_response_when_not_modified() {
+        crate::test::init();
+        let _ctx = DatabaseTestContext::new();
+
+        let server = MockServer::start();
+        let url = format!("{}/repos/move-language/move", server.base_url());
+        let mut first_mock = server.mock(|when, then| {
+            when.method(GET).path("/repos/move-language/move");
+            then.status(200).header("ETag", "\"v1\"").body("content");
+        });
+        let client = test_client(1);
+        client.use_cache(DB_POOL.clone());
+        assert_eq!(client.get_cached(&url, false).unwrap().text(), "content");
+        first_mock.assert();
+        first_mock.delete();
+
+        let not_modified_mock = server.mock(|when, then| {
+            when.method(GET)
+                .path("/repos/move-language/move")
+                .header("If-None-Match", "\"v1\"");
+            then.status(304);
+        });
+        let response = client.get_cached(&url, false).unwrap();
+        not_modified_mock.assert();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.text(), "content");
+    }
+
+    #[actix_rt::test]
+    async fn get_cached_replaces_modified_response() {
+        crate::test::init();
+        let _ctx = DatabaseTestContext::new();
+
+        let server = MockServer::start();
+        let url = format!("{}/repos/move-language/move", server.base_url());
+        CachedResponse::save(&url, Some("\"v1\""), "old content", false, &DB_POOL).unwrap();
+        let server_mock = server.mock(|when, then| {
+            when.method(GET)
+                .path("/repos/move-language/move")
+                .header("If-None-Match", "\"v1\"");
+            then.status(200)
+                .header("ETag", "\"v2\"")
+                .body("new content");
+        });
+
+        let client = test_client(1);
+        client.use_cache(DB_POOL.clone());
+        assert_eq!(
+            client.get_cached(&url, false).unwrap().text(),
+            "new content"
+        );
+        server_mock.assert();
+        let cached = CachedResponse::get(&url, &DB_POOL).unwrap().unwrap();
+        assert_eq!(cached.etag, Some("\"v2\"".to_string()));
+        assert_eq!(cached.body, "new content");
+    }
+
+    #[actix_rt::test]
+    async fn get_cached_never_requests_immutable_response_again() {
+        crate::test::init();
+        let _ctx = DatabaseTestContext::new();
+
+        let server = MockServer::start();
+        let url = format!("{}/move-language/move/4c0d1e8/Move.toml", server.base_url());
+        let server_mock = server.mock(|when, then| {
+            when.method(GET)
+                .path("/move-language/move/4c0d1e8/Move.toml");
+            then.status(200).body("[package]");
+        });
+
+        let client = test_client(1);
+        client.use_cache(DB_POOL.clone());
+        assert_eq!(client.get_cached(&url, true).unwrap().text(), "[package]");
+        assert_eq!(client.get_cached(&url, true).unwrap().text(), "[package]");
+        server_mock.assert_hits(1);
+    }
+
+    #[actix_rt::test]
+    async fn get_cached_does_not_cache_errors() {
+        crate::test::init();
+        let _ctx = DatabaseTestContext::new();
+
+        let server = MockServer::start();
+        let url = format!("{}/move-language/move/4c0d1e8/README.md", server.base_url());
+        let server_mock = server.mock(|when, then| {
+            when.method(GET)
+                .path("/move-language/move/4c0d1e8/README.md");
+            then.status(404).header("ETag", "\"v1\"");
+        });
+
+        let client = test_client(1);
+        client.use_cache(DB_POOL.clone());
+        let response = client.get_cached(&url, true).unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        server_mock.assert();
+        assert!(CachedResponse::get(&url, &DB_POOL).unwrap().is_none());
+    }
+}
@@ -1,14 +1,22 @@
 use jelly::error::Error;
 use jelly::error::Error::Generic;
-use reqwest::blocking::{multipart, Response};
-use reqwest::header;
+use reqwest::blocking::multipart;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::env;
 use std::hash::{Hash, Hasher};
 
 #[derive(Deserialize, Serialize)]
 struct MoveToml {
     package: PackageToml,
+    #[serde(default)]
+    dependencies: BTreeMap<String, DependencyToml>,
+    #[serde(default, rename = "dev-dependencies")]
+    dev_dependencies: BTreeMap<String, DependencyToml>,
+    #[serde(default)]
+    addresses: BTreeMap<String, String>,
+    #[serde(default, rename = "dev-addresses")]
+    dev_addresses: BTreeMap<String, String>,
 }
 
 #[derive(Deserialize, Serialize)]
@@ -17,6 +25,72 @@ struct PackageToml {
     version: String,
 }
 
+// A dependency is either a local path, a git repository or a package published on Movey, e.g.
+// MoveStdlib = { git = "https://github.com/move-language/move.git", subdir = "language/move-stdlib", rev = "main" }
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+struct DependencyToml {
+    local: Option<String>,
+    git: Option<String>,
+    rev: Option<String>,
+    subdir: Option<String>,
+    movey: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+pub struct MoveDependency {
+    pub name: String,
+    pub is_dev: bool,
+    pub local: Option<String>,
+    pub git: Option<String>,
+    pub rev: Option<String>,
+    pub subdir: Option<String>,
+    pub movey: Option<String>,
+}
+
+// Named address, e.g. `std = "0x1"`. Unassigned addresses (`std = "_"`) have no value.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+pub struct MoveAddress {
+    pub name: String,
+    pub address: Option<String>,
+    pub is_dev: bool,
+}
+
+impl MoveToml {
+    fn all_dependencies(&self) -> Vec<MoveDependency> {
+        let dependencies = self.dependencies.iter().map(|dep| (dep, false));
+        let dev_dependencies = self.dev_dependencies.iter().map(|dep| (dep, true));
+        dependencies
+            .chain(dev_dependencies)
+            .map(|((dep_name, dep), is_dev)| MoveDependency {
+                name: dep_name.clone(),
+                is_dev,
+                local: dep.local.clone(),
+                git: dep.git.clone(),
+                rev: dep.rev.clone(),
+                subdir: dep.subdir.clone(),
+                movey: dep.movey.clone(),
+            })
+            .collect()
+    }
+
+    fn all_addresses(&self) -> Vec<MoveAddress> {
+        let addresses = self.addresses.iter().map(|address| (address, false));
+        let dev_addresses = self.dev_addresses.iter().map(|address| (address, true));
+        addresses
+            .chain(dev_addresses)
+            .map(|((address_name, address), is_dev)| MoveAddress {
+                name: address_name.clone(),
+                address: if address == "_" {
+                    None
+                } else {
+                    Some(address.clone())
+                },
+                is_dev,
+            })
+            .collect()
+    }
+}
+
 #[derive(Clone, Debug, Eq, Deserialize)]
 pub struct GithubRepoData {
     pub name: String,
@@ -29,6 +103,10 @@ pub struct GithubRepoData {
     pub forks_count: i32,
     pub url: String,
     pub rev: String,
+    #[serde(default)]
+    pub dependencies: Vec<MoveDependency>,
+    #[serde(default)]
+    pub addresses: Vec<MoveAddress>,
 }
 
 impl PartialEq for GithubRepoData {
@@ -69,9 +147,10 @@ pub struct GithubRepoCommit {
 pub static APP_USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"),);
 
 use crate::constants::DEEP_AI_URL;
+use crate::github_client::{GithubResponse, GITHUB_CLIENT};
+use crate::source_hosts::{self, is_commit_sha, SourceHost};
 #[cfg(test)]
 use mockall::{automock, predicate::*};
-use oauth2::http::StatusCode;
 
 pub struct GithubService {}
 
@@ -89,109 +168,129 @@ impl Default for GithubService {
 
 #[cfg_attr(test, automock)]
 impl GithubService {
+    /// Fetches the package at `path` of a repository hosted on Github, Gitlab or Gitea.
     pub fn fetch_repo_data(
         &self,
         repo_url: &str,
         path: Option<String>,
-        mut rev: Option<String>,
+        rev: Option<String>,
     ) -> Result<GithubRepoData, Error> {
-        let mut github_info = get_repo_description_and_size(repo_url)?;
-        if github_info.default_branch.is_empty() {
-            github_info.default_branch = "master".to_string();
+        let host = source_hosts::for_url(repo_url);
+        fetch_repo_data_from(host.as_ref(), repo_url, path, rev)
+    }
+
+    /// Number of files in `subdir` of the repository at `rev`.
+    pub fn count_files(&self, repo_url: &str, rev: &str, subdir: &str) -> Result<i32, Error> {
+        source_hosts::for_url(repo_url).count_files(repo_url, rev, subdir)
+    }
+
+    /// Content of the file at `path` on the default branch, or `None` if there is no such file.
+    pub fn fetch_default_branch_file(
+        &self,
+        repo_url: &str,
+        path: &str,
+    ) -> Result<Option<String>, Error> {
+        let host = source_hosts::for_url(repo_url);
+        let mut default_branch = host.repo_info(repo_url)?.default_branch;
+        if default_branch.is_empty() {
+            default_branch = "master".to_string();
         }
+        host.fetch_file(&host.raw_file_url(repo_url, &default_branch, path), false)
+    }
+}
 
-        if rev.is_none() {
-            if let Ok(sha) = get_repo_latest_commit_sha(repo_url) {
-                rev = Some(sha)
-            } else {
-                rev = Some(github_info.default_branch.clone());
-            }
+fn fetch_repo_data_from(
+    host: &dyn SourceHost,
+    repo_url: &str,
+    path: Option<String>,
+    mut rev: Option<String>,
+) -> Result<GithubRepoData, Error> {
+    let mut github_info = host.repo_info(repo_url)?;
+    if github_info.default_branch.is_empty() {
+        github_info.default_branch = "master".to_string();
+    }
+
+    if rev.is_none() {
+        if let Ok(sha) = host.latest_commit_sha(repo_url) {
+            rev = Some(sha)
+        } else {
+            rev = Some(github_info.default_branch.clone());
         }
-        let rev = rev.unwrap();
-
-        // example readme url:
-        // https://raw.githubusercontent.com/move-language/move/main/README.md
-        let readme_url = format!(
-            "{}/{}/README.md",
-            repo_url.replace("https://github.com", "https://raw.githubusercontent.com"),
-            rev
-        );
-        let mut readme_content = "".to_string();
-        let response = call_github_api(&readme_url)?;
-        if response.status() != StatusCode::NOT_FOUND {
-            match response.text() {
-                Ok(content) => {
-                    // generate description from readme if not existed
-                    if github_info.description.is_none() {
-                        let mut description = call_deep_ai_api(content.clone(), None)?;
-                        if description.chars().count() > 100 {
-                            let deepai_summary = call_deep_ai_api(description.clone(), None)?;
-                            if !deepai_summary.is_empty() {
-                                description = deepai_summary;
-                            }
-                        }
-                        description = strip_markdown::strip_markdown(&description);
-
-                        if description.is_empty() {
-                            description = strip_markdown::strip_markdown(&content);
-                        }
-                        description = description.chars().take(100).collect();
-                        github_info.description = Some(description);
-                    }
-
-                    let formatted_readme_src_http =
-                        format!("src=\"{}", readme_url.replace("README.md", ""),);
-                    let formatted_readme_src_markdown =
-                        format!("]({}", readme_url.replace("README.md", ""),);
-
-                    // Add prefix url for linkable content in readme file such as: images, link...
-                    let formatted_readme_content = content
-                        .replace("src=\"", &formatted_readme_src_http)
-                        .replace(&format!("{}http", &formatted_readme_src_http), "src=\"http")
-                        .replace("](", &formatted_readme_src_markdown)
-                        .replace(&format!("{}http", &formatted_readme_src_markdown), "](http");
-
-                    readme_content = formatted_readme_content
-                }
-                _ => {
-                    warn!("Error getting README.md content. url: {}", readme_url);
+    }
+    let rev = rev.unwrap();
+
+    // Files at a commit never change, so they don't need to be fetched again
+    let is_immutable = is_commit_sha(&rev);
+    let readme_url = host.raw_file_url(repo_url, &rev, "README.md");
+    let mut readme_content = "".to_string();
+    if let Some(content) = host.fetch_file(&readme_url, is_immutable)? {
+        // generate description from readme if not existed
+        if github_info.description.is_none() {
+            let mut description = call_deep_ai_api(content.clone(), None)?;
+            if description.chars().count() > 100 {
+                let deepai_summary = call_deep_ai_api(description.clone(), None)?;
+                if !deepai_summary.is_empty() {
+                    description = deepai_summary;
                 }
             }
-        }
+            description = strip_markdown::strip_markdown(&description);
 
-        let move_url = match path {
-            // example Move.toml url with subdir:
-            // https://raw.githubusercontent.com/move-language/move/main/language/evm/hardhat-examples/contracts/ABIStruct/Move.toml
-            Some(path) => {
-                format!("{}/{}", readme_url.replace("/README.md", ""), path)
-            }
-            None => {
-                // Move.toml in top directory:
-                // https://raw.githubusercontent.com/taoheorg/taohe/master/Move.toml
-                readme_url.replace("README.md", "Move.toml")
+            if description.is_empty() {
+                description = strip_markdown::strip_markdown(&content);
             }
-        };
+            description = description.chars().take(100).collect();
+            github_info.description = Some(description);
+        }
 
-        let mut move_toml_content = "".to_string();
-        match call_github_api(&move_url)?.text() {
-            Ok(content) => move_toml_content = content,
-            Err(error) => {
-                error!(
-                    "Error getting Move.toml content. url: {:?}, error: {}",
-                    move_url, error
-                );
-            }
-        };
+        let formatted_readme_src_http =
+            format!("src=\"{}", readme_url.replace("README.md", ""),);
+        let formatted_readme_src_markdown =
+            format!("]({}", readme_url.replace("README.md", ""),);
 
-        let license = match github_info.license {
-            Some(license) => license.name,
-            None => "".to_string(),
-        };
+        // Add prefix url for linkable content in readme file such as: images, link...
+        let formatted_readme_content = content
+            .replace("src=\"", &formatted_readme_src_http)
+            .replace(&format!("{}http", &formatted_readme_src_http), "src=\"http")
+            .replace("](", &formatted_readme_src_markdown)
+            .replace(&format!("{}http", &formatted_readme_src_markdown), "](http");
 
-        match toml::from_str::<MoveToml>(&move_toml_content) {
-            Ok(move_toml) => Ok(GithubRepoData {
-                name: move_toml.package.name,
-                version: move_toml.package.version,
+        readme_content = formatted_readme_content
+    }
+
+    let move_url = host.raw_file_url(repo_url, &rev, path.as_deref().unwrap_or("Move.toml"));
+    let move_toml_content = host
+        .fetch_file(&move_url, is_immutable)?
+        .unwrap_or_default();
+
+    let license = match github_info.license {
+        Some(license) => license.name,
+        None => "".to_string(),
+    };
+
+    match toml::from_str::<MoveToml>(&move_toml_content) {
+        Ok(move_toml) => Ok(GithubRepoData {
+            dependencies: move_toml.all_dependencies(),
+            addresses: move_toml.all_addresses(),
+            name: move_toml.package.name,
+            version: move_toml.package.version,
+            readme_content,
+            license,
+            description: github_info.description.unwrap_or_else(|| "".to_string()),
+            size: github_info.size,
+            stars_count: github_info.stargazers_count,
+            forks_count: github_info.forks_count,
+            // this field is overwritten in the crawler, modified this to save default branch
+            url: github_info.default_branch,
+            rev,
+        }),
+        Err(error) => {
+            warn!(
+                "Invalid Move.toml url: {}, content: {}, error: {}",
+                &move_url, &move_toml_content, &error
+            );
+            Ok(GithubRepoData {
+                name: String::from(""),
+                version: String::from(""),
                 readme_content,
                 license,
                 description: github_info.description.unwrap_or_else(|| "".to_string()),
@@ -201,40 +300,15 @@ impl GithubService {
                 // this field is overwritten in the crawler, modified this to save default branch
                 url: github_info.default_branch,
                 rev,
-            }),
-            Err(error) => {
-                warn!(
-                    "Invalid Move.toml url: {}, content: {}, error: {}",
-                    &move_url, &move_toml_content, &error
-                );
-                Ok(GithubRepoData {
-                    name: String::from(""),
-                    version: String::from(""),
-                    readme_content,
-                    license,
-                    description: github_info.description.unwrap_or_else(|| "".to_string()),
-                    size: github_info.size,
-                    stars_count: github_info.stargazers_count,
-                    forks_count: github_info.forks_count,
-                    // this field is overwritten in the crawler, modified this to save default branch
-                    url: github_info.default_branch,
-                    rev,
-                })
-            }
+                dependencies: vec![],
+                addresses: vec![],
+            })
         }
     }
 }
 
-fn call_github_api(url: &str) -> Result<Response, Error> {
-    let access_token = env::var("GITHUB_ACCESS_TOKEN").expect("Unable to pull GITHUB_ACCESS_TOKEN");
-    let client = reqwest::blocking::Client::builder()
-        .user_agent(APP_USER_AGENT)
-        .build()?;
-    let res = client
-        .get(url)
-        .header(header::AUTHORIZATION, format!("token {}", &access_token))
-        .send()?;
-    Ok(res)
+pub(crate) fn call_github_api(url: &str, is_immutable: bool) -> Result<GithubResponse, Error> {
+    GITHUB_CLIENT.get_cached(url, is_immutable)
 }
 
 // url param is only used in testing
@@ -276,9 +350,9 @@ fn call_deep_ai_api(content: String, url: Option<&str>) -> Result<String, Error>
     }
 }
 
-fn get_repo_description_and_size(repo_url: &str) -> Result<GithubRepoInfo, Error> {
+pub(crate) fn get_repo_description_and_size(repo_url: &str) -> Result<GithubRepoInfo, Error> {
     let url = repo_url.replace("https://github.com/", "https://api.github.com/repos/");
-    let response = call_github_api(&url)?;
+    let response = call_github_api(&url, false)?;
     match response.json::<GithubRepoInfo>() {
         Ok(info) => Ok(info),
         Err(error) => {
@@ -291,10 +365,10 @@ fn get_repo_description_and_size(repo_url: &str) -> Result<GithubRepoInfo, Error
     }
 }
 
-fn get_repo_latest_commit_sha(repo_url: &str) -> Result<String, Error> {
+pub(crate) fn get_repo_latest_commit_sha(repo_url: &str) -> Result<String, Error> {
     let mut url = repo_url.replace("https://github.com/", "https://api.github.com/repos/");
     url.push_str("/commits");
-    let response = call_github_api(&url)?;
+    let response = call_github_api(&url, false)?;
     match response.json::<Vec<GithubRepoCommit>>() {
         Ok(info) if !info.is_empty() => Ok(info.get(0).unwrap().sha.clone()),
         Ok(_) => {
@@ -328,6 +402,7 @@ mod tests {
     use std::env;
 
     use super::*;
+    use crate::source_hosts::GithubHost;
 
     #[test]
     fn get_repo_description_and_size_works() {
@@ -523,12 +598,32 @@ mod tests {
             then.status(200).body("test readme content - <img src=\"one\" /> - <img src=\"http://two\" /> - [three](three) - [four](http://four)");
         });
 
-        let move_toml = MoveToml {
+        let mut move_toml = MoveToml {
             package: PackageToml {
                 name: "test package name".to_string(),
                 version: "0.0.0".to_string(),
             },
+            dependencies: BTreeMap::new(),
+            dev_dependencies: BTreeMap::new(),
+            addresses: BTreeMap::new(),
+            dev_addresses: BTreeMap::new(),
         };
+        move_toml.dependencies.insert(
+            "MoveStdlib".to_string(),
+            DependencyToml {
+                git: Some("https://github.com/move-language/move.git".to_string()),
+                rev: Some("main".to_string()),
+                subdir: Some("language/move-stdlib".to_string()),
+                ..Default::default()
+            },
+        );
+        move_toml.dev_dependencies.insert(
+            "TestHelpers".to_string(),
+            DependencyToml {
+                local: Some("../test-helpers".to_string()),
+                ..Default::default()
+            },
+        );
         let move_toml_mock = server.mock(|when, then| {
             when.method(GET)
                 .path("/EastAgile/ea-movey/rev/Move.toml")
@@ -537,11 +632,9 @@ mod tests {
             then.status(200).body(toml::to_string(&move_toml).unwrap());
         });
 
-        let gh_service = GithubService::new();
         let repo_url = format!("{}/EastAgile/ea-movey", server.base_url());
-        let gh_repo_data = gh_service
-            .fetch_repo_data(&repo_url, None, Some("rev".to_string()))
-            .unwrap();
+        let gh_repo_data =
+            fetch_repo_data_from(&GithubHost, &repo_url, None, Some("rev".to_string())).unwrap();
 
         let expected_readme_content = format!("test readme content - <img src=\"{}/rev/one\" /> - <img src=\"http://two\" /> - [three]({}/rev/three) - [four](http://four)", &repo_url, &repo_url);
 
@@ -557,5 +650,99 @@ mod tests {
         assert_eq!(gh_repo_data.forks_count, 30);
         assert_eq!(gh_repo_data.url, "test-default-branch");
         assert_eq!(gh_repo_data.rev, "rev");
+        assert_eq!(
+            gh_repo_data.dependencies,
+            vec![
+                MoveDependency {
+                    name: "MoveStdlib".to_string(),
+                    is_dev: false,
+                    git: Some("https://github.com/move-language/move.git".to_string()),
+                    rev: Some("main".to_string()),
+                    subdir: Some("language/move-stdlib".to_string()),
+                    ..Default::default()
+                },
+                MoveDependency {
+                    name: "TestHelpers".to_string(),
+                    is_dev: true,
+                    local: Some("../test-helpers".to_string()),
+                    ..Default::default()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn move_toml_parses_named_addresses() {
+        let move_toml = toml::from_str::<MoveToml>(
+            r#"
+            [package]
+            name = "test"
+            version = "0.1.0"
+
+            [addresses]
+            std = "0x1"
+            test = "_"
+
+            [dev-addresses]
+            test = "0x42"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            move_toml.all_addresses(),
+            vec![
+                MoveAddress {
+                    name: "std".to_string(),
+                    address: Some("0x1".to_string()),
+                    is_dev: false,
+                },
+                MoveAddress {
+                    name: "test".to_string(),
+                    address: None,
+                    is_dev: false,
+                },
+                MoveAddress {
+                    name: "test".to_string(),
+                    address: Some("0x42".to_string()),
+                    is_dev: true,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn move_toml_parses_registry_dependencies() {
+        let move_toml = toml::from_str::<MoveToml>(
+            r#"
+            [package]
+            name = "test"
+            version = "0.1.0"
+
+            [addresses]
+            std = "0x1"
+
+            [dependencies]
+            Registry = { movey = "registry-package" }
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            move_toml.all_addresses(),
+            vec![MoveAddress {
+                name: "std".to_string(),
+                address: Some("0x1".to_string()),
+                is_dev: false,
+            }]
+        );
+
+        assert_eq!(
+            move_toml.all_dependencies(),
+            vec![MoveDependency {
+                name: "Registry".to_string(),
+                movey: Some("registry-package".to_string()),
+                ..Default::default()
+            }]
+        );
     }
 }
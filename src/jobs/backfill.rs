@@ -0,0 +1,38 @@
+use std::future::Future;
+use std::pin::Pin;
+
+use jelly::actix_web::web;
+use jelly::anyhow::{anyhow, Error};
+use jelly::jobs::{Job, JobState};
+use jelly::serde::{Deserialize, Serialize};
+
+use crate::packages::Package;
+
+/// Sets the latest version of packages that don't have one yet. Queued when the app
+/// starts, so that packages created before `latest_version_id` existed are ranked
+/// with the semver crate like every other package.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BackfillLatestVersions;
+
+impl Job for BackfillLatestVersions {
+    type State = JobState;
+    type Future = Pin<Box<dyn Future<Output = Result<(), Error>> + Send>>;
+    const NAME: &'static str = "BackfillLatestVersionsJob";
+
+    fn run(self, state: JobState) -> Self::Future {
+        Box::pin(async move {
+            let pool = state.pool;
+            web::block(move || {
+                let conn = pool.get().map_err(|e| format!("{:?}", e))?;
+                Package::backfill_latest_versions(&conn).map_err(|e| format!("{:?}", e))
+            })
+            .await
+            .map(|count| {
+                if count > 0 {
+                    info!("Backfilled the latest version of {} packages", count);
+                }
+            })
+            .map_err(|e| anyhow!("Error backfilling latest versions: {:?}", e))
+        })
+    }
+}
@@ -0,0 +1,266 @@
+use std::future::Future;
+use std::pin::Pin;
+
+use jelly::actix_web::web;
+use jelly::anyhow::{anyhow, Error};
+use jelly::chrono::{DateTime, Duration, Utc};
+use jelly::jobs::{Job, JobState};
+use jelly::serde::{Deserialize, Serialize};
+use jelly::DieselPgPool;
+use mockall_double::double;
+
+#[double]
+use crate::github_service::GithubService;
+
+use super::models::{CrawlCursor, CrawledRepo, GITHUB_SEARCH_CURSOR};
+use super::{GithubCrawler, GithubSearchItem, SEARCH_PAGES};
+
+// How long a crawler may hold the cursor before another one takes over
+const CRAWL_LEASE_MINUTES: i64 = 10;
+// How long to wait between two passes over the search pages
+const SEARCH_INTERVAL_HOURS: i64 = 24;
+// Repos are crawled again once they haven't been crawled for this long
+const STALE_AFTER_HOURS: i64 = 24;
+const RECRAWL_BATCH_SIZE: i64 = 20;
+
+/// Runs one step of the Github crawl: the next search page while a search
+/// pass is due, otherwise a batch of stale repos.
+pub fn crawl_github(pool: &DieselPgPool, github_service: &GithubService) -> jelly::Result<()> {
+    let cursor = match CrawlCursor::claim(
+        GITHUB_SEARCH_CURSOR,
+        Duration::minutes(CRAWL_LEASE_MINUTES),
+        pool,
+    )? {
+        Some(cursor) => cursor,
+        None => {
+            info!("Crawler: another crawl is in progress, skipped");
+            return Ok(());
+        }
+    };
+
+    let mut crawler = GithubCrawler::new(pool.clone());
+    let now = Utc::now();
+    if cursor.is_due(now) {
+        info!("Crawler: crawling search page {}", cursor.page);
+        let result = crawler.crawl_search_page(cursor.page, github_service);
+        release_search_cursor(&cursor, result, now, pool)
+    } else {
+        let stale_repos = CrawledRepo::get_stale(
+            now - Duration::hours(STALE_AFTER_HOURS),
+            RECRAWL_BATCH_SIZE,
+            pool,
+        )?;
+        info!("Crawler: crawling {} stale repos", stale_repos.len());
+        let items = stale_repos
+            .iter()
+            .map(|repo| GithubSearchItem::from_repo(&repo.repository_url, &repo.path))
+            .collect();
+        crawler.crawl_repos(items, github_service);
+        cursor.release(cursor.page, cursor.next_run_at, pool)
+    }
+}
+
+// Moves the cursor past a search page that was crawled. A page that failed, e.g. because
+// the search was rate limited, is searched again on the next run.
+fn release_search_cursor(
+    cursor: &CrawlCursor,
+    result: jelly::Result<()>,
+    now: DateTime<Utc>,
+    pool: &DieselPgPool,
+) -> jelly::Result<()> {
+    if let Err(e) = result {
+        error!(
+            "Crawler: error crawling search page {}: {:?}",
+            cursor.page, e
+        );
+        return cursor.release(cursor.page, cursor.next_run_at, pool);
+    }
+    if cursor.page + 1 < SEARCH_PAGES {
+        cursor.release(cursor.page + 1, None, pool)
+    } else {
+        cursor.release(0, Some(now + Duration::hours(SEARCH_INTERVAL_HOURS)), pool)
+    }
+}
+
+// Crawling makes blocking calls to Github, so it runs on the thread pool
+async fn run_blocking<F>(f: F) -> Result<(), Error>
+where
+    F: FnOnce() -> jelly::Result<()> + Send + 'static,
+{
+    web::block(move || f().map_err(|e| format!("{:?}", e)))
+        .await
+        .map_err(|e| anyhow!("{:?}", e))
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CrawlGithub;
+
+impl Job for CrawlGithub {
+    type State = JobState;
+    type Future = Pin<Box<dyn Future<Output = Result<(), Error>> + Send>>;
+    const NAME: &'static str = "CrawlGithubJob";
+    const TIMEOUT: i64 = CRAWL_LEASE_MINUTES * 60 * 1000;
+
+    fn run(self, state: JobState) -> Self::Future {
+        Box::pin(async move {
+            let pool = state.pool;
+            run_blocking(move || crawl_github(&pool, &GithubService::new())).await
+        })
+    }
+}
+
+/// Crawls the Move.toml at `path` of a repository right away.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CrawlRepository {
+    pub repository_url: String,
+    pub path: String,
+}
+
+impl Job for CrawlRepository {
+    type State = JobState;
+    type Future = Pin<Box<dyn Future<Output = Result<(), Error>> + Send>>;
+    const NAME: &'static str = "CrawlRepositoryJob";
+    const TIMEOUT: i64 = CRAWL_LEASE_MINUTES * 60 * 1000;
+
+    fn run(self, state: JobState) -> Self::Future {
+        Box::pin(async move {
+            let pool = state.pool;
+            run_blocking(move || {
+                let mut crawler = GithubCrawler::new(pool);
+                crawler.crawl_repos(
+                    vec![GithubSearchItem::from_repo(
+                        &self.repository_url,
+                        &self.path,
+                    )],
+                    &GithubService::new(),
+                );
+                Ok(())
+            })
+            .await
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::github_service::GithubRepoData;
+    use crate::packages::Package;
+    use crate::schema::crawled_repos;
+    use crate::test::{DatabaseTestContext, DB_POOL};
+    use diesel::prelude::*;
+
+    fn stub_repo_data() -> GithubRepoData {
+        GithubRepoData {
+            name: "MoveStdlib".to_string(),
+            version: "1.5.0".to_string(),
+            readme_content: "readme".to_string(),
+            license: "".to_string(),
+            description: "".to_string(),
+            size: 0,
+            stars_count: 0,
+            forks_count: 0,
+            url: "main".to_string(),
+            rev: "30b0b8dd1b6b5b0a0a1ad4c4a1b0b2c8ee4ad421".to_string(),
+            dependencies: vec![],
+            addresses: vec![],
+        }
+    }
+
+    #[actix_rt::test]
+    async fn crawl_github_crawls_stale_repos_between_search_passes() {
+        crate::test::init();
+        let _ctx = DatabaseTestContext::new();
+
+        let cursor = CrawlCursor::claim(GITHUB_SEARCH_CURSOR, Duration::minutes(1), &DB_POOL)
+            .unwrap()
+            .unwrap();
+        let next_run_at = Utc::now() + Duration::hours(1);
+        cursor.release(0, Some(next_run_at), &DB_POOL).unwrap();
+
+        let url = "https://github.com/move-language/move";
+        let path = "language/move-stdlib/Move.toml";
+        CrawledRepo::touch(url, path, &DB_POOL).unwrap();
+        diesel::update(crawled_repos::table)
+            .set(crawled_repos::last_crawled_at.eq(Utc::now() - Duration::days(2)))
+            .execute(&DB_POOL.get().unwrap())
+            .unwrap();
+
+        let mut mock_github_service = GithubService::new();
+        mock_github_service
+            .expect_fetch_repo_data()
+            .withf(move |repo_url, repo_path, rev| {
+                repo_url == url && repo_path.as_deref() == Some(path) && rev.is_none()
+            })
+            .times(1)
+            .returning(|_, _, _| Ok(stub_repo_data()));
+        crawl_github(&DB_POOL, &mock_github_service).unwrap();
+
+        let package = Package::get_by_name("MoveStdlib", &DB_POOL).unwrap();
+        assert_eq!(
+            package.repository_url,
+            "https://github.com/move-language/move/tree/main/language/move-stdlib"
+        );
+        let conn = DB_POOL.get().unwrap();
+        assert_eq!(
+            package.get_latest_version(&conn).unwrap().rev,
+            Some(stub_repo_data().rev)
+        );
+        let repo = CrawledRepo::get(url, path, &DB_POOL).unwrap();
+        assert!(repo.last_crawled_at > Utc::now() - Duration::minutes(1));
+
+        // The cursor is released and keeps waiting for the next search pass
+        let cursor = CrawlCursor::claim(GITHUB_SEARCH_CURSOR, Duration::minutes(1), &DB_POOL)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            cursor.next_run_at.unwrap().timestamp(),
+            next_run_at.timestamp()
+        );
+    }
+
+    #[actix_rt::test]
+    async fn release_search_cursor_only_advances_after_crawled_pages() {
+        crate::test::init();
+        let _ctx = DatabaseTestContext::new();
+        let claim = || {
+            CrawlCursor::claim(GITHUB_SEARCH_CURSOR, Duration::minutes(1), &DB_POOL)
+                .unwrap()
+                .unwrap()
+        };
+
+        let cursor = claim();
+        let error = jelly::error::Error::Generic("Rate limited".to_string());
+        release_search_cursor(&cursor, Err(error), Utc::now(), &DB_POOL).unwrap();
+        let cursor = claim();
+        assert_eq!(cursor.page, 0);
+        assert!(cursor.is_due(Utc::now()));
+
+        release_search_cursor(&cursor, Ok(()), Utc::now(), &DB_POOL).unwrap();
+        let cursor = claim();
+        assert_eq!(cursor.page, 1);
+        cursor.release(SEARCH_PAGES - 1, None, &DB_POOL).unwrap();
+
+        // The last page waits for the next search pass
+        let cursor = claim();
+        release_search_cursor(&cursor, Ok(()), Utc::now(), &DB_POOL).unwrap();
+        let cursor = claim();
+        assert_eq!(cursor.page, 0);
+        assert!(!cursor.is_due(Utc::now()));
+    }
+
+    #[actix_rt::test]
+    async fn crawl_github_skips_while_another_crawl_is_in_progress() {
+        crate::test::init();
+        let _ctx = DatabaseTestContext::new();
+
+        CrawlCursor::claim(GITHUB_SEARCH_CURSOR, Duration::minutes(10), &DB_POOL)
+            .unwrap()
+            .unwrap();
+        let mut mock_github_service = GithubService::new();
+        mock_github_service.expect_fetch_repo_data().times(0);
+
+        crawl_github(&DB_POOL, &mock_github_service).unwrap();
+        assert_eq!(Package::count(&DB_POOL).unwrap(), 0);
+    }
+}
@@ -0,0 +1,85 @@
+use std::future::Future;
+use std::pin::Pin;
+
+use jelly::actix_web::web;
+use jelly::anyhow::{anyhow, Error};
+use jelly::jobs::{Job, JobState};
+use jelly::serde::{Deserialize, Serialize};
+use jelly::DieselPgPool;
+use mockall_double::double;
+
+#[double]
+use crate::github_service::GithubService;
+use crate::packages::models::package_download::{PackageDownload, SHADOW_PACKAGE_REQUEST_MINUTES};
+use crate::packages::Package;
+
+/// Counts a download of a version Movey doesn't know yet, creating the shadow package or
+/// version from the repository first. Done in the background so that download requests
+/// never wait for, or trigger unlimited calls to, the source host.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateShadowPackage {
+    pub url: String,
+    pub rev: String,
+    pub subdir: String,
+    pub fingerprint: String,
+}
+
+impl CreateShadowPackage {
+    /// Counts the download, creating the shadow package first if needed, then forgets the
+    /// request so that later downloads of a version that still doesn't exist queue a new job.
+    pub fn create(&self, service: &GithubService, pool: &DieselPgPool) -> jelly::Result<bool> {
+        let result = Package::increase_download_count(
+            &self.url,
+            &self.rev,
+            &self.subdir,
+            &self.fingerprint,
+            service,
+            pool,
+        );
+        PackageDownload::forget_shadow_package(&self.url, &self.rev, &self.subdir, &pool.get()?)?;
+        result
+    }
+}
+
+impl Job for CreateShadowPackage {
+    type State = JobState;
+    type Future = Pin<Box<dyn Future<Output = Result<(), Error>> + Send>>;
+    const NAME: &'static str = "CreateShadowPackageJob";
+    const TIMEOUT: i64 = SHADOW_PACKAGE_REQUEST_MINUTES * 60 * 1000;
+
+    fn run(self, state: JobState) -> Self::Future {
+        Box::pin(async move {
+            let pool = state.pool;
+            // Fetching the repository makes blocking calls to the source host
+            web::block(move || {
+                self.create(&GithubService::new(), &pool)
+                    .map_err(|e| format!("{:?}", e))
+            })
+            .await
+            .map(|_| ())
+            .map_err(|e| anyhow!("Error creating shadow package: {:?}", e))
+        })
+    }
+}
+
+/// Adds the downloads recorded since the last run to the download counters.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FlushDownloadCounts;
+
+impl Job for FlushDownloadCounts {
+    type State = JobState;
+    type Future = Pin<Box<dyn Future<Output = Result<(), Error>> + Send>>;
+    const NAME: &'static str = "FlushDownloadCountsJob";
+
+    fn run(self, state: JobState) -> Self::Future {
+        Box::pin(async move {
+            let conn = state
+                .pool
+                .get()
+                .map_err(|e| anyhow!("Error getting a connection: {:?}", e))?;
+            PackageDownload::flush(&conn)
+                .map(|_| ())
+                .map_err(|e| anyhow!("Error flushing download counts: {:?}", e))
+        })
+    }
+}
@@ -1,8 +1,8 @@
-use crate::github_service::{GithubRepoData, APP_USER_AGENT};
+use crate::github_client::GITHUB_CLIENT;
+use crate::github_service::GithubRepoData;
+use crate::source_hosts;
 use crate::utils::presenter::validate_version;
-use core::time::Duration;
-use jelly::actix_web::http::header;
-use jelly::actix_web::rt::time::delay_for;
+use jelly::jobs::{JobState, QueueHandle, WorkerConfig};
 use jelly::DieselPgPool;
 use mockall_double::double;
 use rayon::prelude::*;
@@ -11,11 +11,71 @@ use std::collections::HashSet;
 use std::env;
 use std::iter::FromIterator;
 use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
 
 #[double]
 use crate::github_service::GithubService;
 use crate::packages::Package;
 
+mod backfill;
+pub use backfill::BackfillLatestVersions;
+
+mod crawl;
+pub use crawl::{crawl_github, CrawlGithub, CrawlRepository};
+
+mod downloads;
+pub use downloads::{CreateShadowPackage, FlushDownloadCounts};
+
+mod publish;
+pub use publish::{publish_tag, tag_matches_version, PublishTag, SendTagPublishedEmail};
+
+pub mod models;
+use models::CrawledRepo;
+
+// Github API only allows us to query the first ten pages of a search, and its responses are
+// unstable, so each pass queries them several times in both orders to find all packages
+pub const SEARCH_PAGES: i32 = 120;
+const SCRAPE_TRIALS: u32 = 3;
+// Doubled after every failed trial
+const SCRAPE_RETRY_DELAY: Duration = Duration::from_millis(500);
+
+pub fn configure(config: WorkerConfig<JobState>) -> WorkerConfig<JobState> {
+    config
+        .register::<CrawlGithub>()
+        .register::<CrawlRepository>()
+        .register::<PublishTag>()
+        .register::<SendTagPublishedEmail>()
+        .register::<CreateShadowPackage>()
+        .register::<FlushDownloadCounts>()
+        .register::<BackfillLatestVersions>()
+}
+
+/// Backfills the latest versions of packages once on start, flushes download counts every
+/// minute, and crawls Github every minute when `CRAWLING` is set to `true`.
+pub fn schedule(queue: &QueueHandle) {
+    if let Err(e) = queue.queue(BackfillLatestVersions) {
+        error!("Error queueing latest version backfill: {:?}", e);
+    }
+    queue.every(Duration::from_secs(60), FlushDownloadCounts);
+
+    let is_crawling = env::var("CRAWLING").unwrap_or_else(|_| "".to_string());
+    if is_crawling.to_lowercase() == "true" {
+        queue.every(Duration::from_secs(60), CrawlGithub);
+    }
+}
+
+/// Github code search url of the given page of a search pass.
+pub fn search_url(page: i32) -> String {
+    let order = if page < SEARCH_PAGES / 2 { "desc" } else { "asc" };
+    format!(
+        "https://api.github.com/search/code?q=\
+        package%20in:file%20extension:toml%20filename:Move%20language:TOML&per_page=100&page={}&order={}&sort=indexed",
+        (page % 10) + 1,
+        order
+    )
+}
+
 #[derive(Deserialize)]
 pub struct GithubSearchRepository {
     pub html_url: String,
@@ -29,6 +89,21 @@ pub struct GithubSearchItem {
     pub html_url: String,
 }
 
+impl GithubSearchItem {
+    /// An item for the Move.toml at `path` on the default branch of the repository.
+    pub fn from_repo(repository_url: &str, path: &str) -> Self {
+        GithubSearchItem {
+            url: "".to_string(),
+            path: path.to_string(),
+            repository: GithubSearchRepository {
+                html_url: repository_url.to_string(),
+            },
+            // The branch is replaced with the default branch while scraping
+            html_url: format!("{}/blob/HEAD/{}", repository_url, path),
+        }
+    }
+}
+
 #[derive(Deserialize)]
 pub struct GithubSearchResult {
     pub items: Vec<GithubSearchItem>,
@@ -41,67 +116,65 @@ pub struct GithubCrawler {
 }
 
 impl GithubCrawler {
-    pub async fn run(mut self) {
-        let mut order = "desc";
-        // Github API response is unstable, need to query it multiple times to get all packages
-        for page in 1..120 {
-            if page == 61 {
-                order = "asc";
-            }
-            // Github API only allows us to query first ten pages
-            let url = format!(
-                "https://api.github.com/search/code?q=\
-                package%20in:file%20extension:toml%20filename:Move%20language:TOML&per_page=100&page={}&order={}&sort=indexed",
-                (page % 10) + 1,
-                order
-            );
-            self.find_new_repos(&url);
-            let gh_service = GithubService::new();
-            self.scrape(&gh_service);
-            self.save_to_db();
-            if page != 50 {
-                delay_for(Duration::from_secs(60)).await;
-            }
+    pub fn new(pool: DieselPgPool) -> Self {
+        GithubCrawler {
+            repo_urls: vec![],
+            repos_data: Mutex::new(vec![]),
+            pool,
         }
     }
 
-    fn find_new_repos(&mut self, url: &str) {
-        let access_token = env::var("GITHUB_ACCESS_TOKEN")
-            .expect("Unable to pull GITHUB_ACCESS_TOKEN for account token generation");
-        let client = match reqwest::blocking::Client::builder()
-            .user_agent(APP_USER_AGENT)
-            .build()
-        {
-            Ok(client) => client,
-            Err(e) => {
-                error!("Error building request: {:?}", e);
-                return;
-            }
-        };
-        let response = match client
-            .get(url)
-            .header(header::AUTHORIZATION, format!("Bearer {}", &access_token))
-            .send()
-        {
-            Ok(response) => response,
-            Err(e) => {
-                error!("Error sending request: {:?}", e);
-                return;
+    /// Looks for new packages on a page of the Github code search. Fails if the page
+    /// couldn't be searched, e.g. when the search is rate limited.
+    pub fn crawl_search_page(
+        &mut self,
+        page: i32,
+        github_service: &GithubService,
+    ) -> jelly::Result<()> {
+        self.find_new_repos(&search_url(page))?;
+        self.scrape(github_service);
+        self.save_to_db();
+        Ok(())
+    }
+
+    /// Crawls the given repos again to pick up new versions.
+    pub fn crawl_repos(&mut self, items: Vec<GithubSearchItem>, github_service: &GithubService) {
+        self.repo_urls = items;
+        self.scrape(github_service);
+        self.save_to_db();
+        // Repos that failed wait for the next round as well
+        for item in self.repo_urls.iter() {
+            if let Err(e) = CrawledRepo::touch(&item.repository.html_url, &item.path, &self.pool) {
+                error!(
+                    "Error saving crawl time. url: {}, error: {:?}",
+                    &item.repository.html_url, e
+                );
             }
-        };
-        let response_json: GithubSearchResult = match response.json() {
-            Ok(response_json) => response_json,
-            Err(_) => GithubSearchResult { items: vec![] },
-        };
+        }
+    }
+
+    fn find_new_repos(&mut self, url: &str) -> jelly::Result<()> {
+        self.repo_urls = vec![];
+        let response = GITHUB_CLIENT.get(url)?;
+        if !response.status().is_success() {
+            return Err(jelly::error::Error::Generic(format!(
+                "Error searching Github. url: {}, status: {}",
+                url,
+                response.status()
+            )));
+        }
+        let response_json = response.json::<GithubSearchResult>()?;
         self.repo_urls = response_json.items;
         info!("Found {} packages", self.repo_urls.len());
+        Ok(())
     }
 
     fn scrape(&mut self, github_service: &GithubService) {
         self.repos_data = Mutex::new(vec![]);
         (0..self.repo_urls.len()).into_par_iter().for_each(|index| {
             if let Some(item) = self.repo_urls.get(index) {
-                let mut no_of_trial = 3;
+                let mut no_of_trial = SCRAPE_TRIALS;
+                let mut retry_delay = SCRAPE_RETRY_DELAY;
                 loop {
                     match github_service.fetch_repo_data(
                         &item.repository.html_url,
@@ -109,6 +182,16 @@ impl GithubCrawler {
                         None,
                     ) {
                         Ok(mut repo_data) => {
+                            if let Err(e) = CrawledRepo::touch(
+                                &item.repository.html_url,
+                                &item.path,
+                                &self.pool,
+                            ) {
+                                error!(
+                                    "Error saving crawl time. url: {}, error: {:?}",
+                                    &item.repository.html_url, e
+                                );
+                            }
                             let mut guard = match self.repos_data.lock() {
                                 Ok(guard) => guard,
                                 Err(e) => {
@@ -121,29 +204,26 @@ impl GithubCrawler {
                             } else {
                                 repo_data.url
                             };
-                            repo_data.url = if item.path == "Move.toml" {
+                            // Move.toml in subdir, so get the url to subdir on the default branch, e.g:
+                            // "path": "aptos-move/framework/move-stdlib/Move.toml"
+                            // -> https://github.com/alinush/aptos-core/tree/main/aptos-move/framework/move-stdlib
+                            let subdir = item.path.trim_end_matches("Move.toml").trim_end_matches('/');
+                            repo_data.url = if subdir.is_empty() {
                                 // Move.toml is in top directory, get
                                 item.repository.html_url.clone()
                             } else {
-                                // Move.toml in subdir, so get the url to subdir, e.g:
-                                // "path": "aptos-move/framework/move-stdlib/Move.toml"
-                                item.html_url.replace("/Move.toml", "")
+                                source_hosts::for_url(&item.repository.html_url).tree_url(
+                                    &item.repository.html_url,
+                                    &default_branch,
+                                    subdir,
+                                )
                             };
-                            // Beautify the url
-                            // e.g: https://github.com/alinush/aptos-core/blob/d594ba96bc97438be82755c15845410fd5c2a5e0
-                            // 		/aptos-move/framework/move-stdlib/Move.toml
-                            if repo_data.url.contains("/blob/") {
-                                let mut repo_url_tokens =
-                                    repo_data.url.split('/').collect::<Vec<&str>>();
-                                if repo_url_tokens[5] == "blob" {
-                                    repo_url_tokens[5] = "tree";
-                                    repo_url_tokens[6] = &default_branch;
-                                    repo_data.url = repo_url_tokens.join("/");
-                                }
-                            }
                             // e.g: "url": "https://api.github.com/repositories/467805361/contents/aptos-move/framework/move-stdlib
                             // 				/Move.toml?ref=d594ba96bc97438be82755c15845410fd5c2a5e0"
-                            repo_data.rev = item.url.split("ref=").last().unwrap_or("").to_string();
+                            // Items that don't come from a search keep the latest commit
+                            if let Some((_, rev)) = item.url.split_once("ref=") {
+                                repo_data.rev = rev.to_string();
+                            }
                             guard.push(repo_data);
                             break;
                         }
@@ -151,10 +231,13 @@ impl GithubCrawler {
                             no_of_trial -= 1;
                             if no_of_trial == 0 {
                                 error!(
-                                    "Cannot get package info. url: {}, error: {}",
+                                    "Cannot get package info. url: {}, error: {:?}",
                                     &item.repository.html_url, e
                                 );
+                                break;
                             };
+                            thread::sleep(retry_delay);
+                            retry_delay *= 2;
                         }
                     }
                 }
@@ -195,9 +278,13 @@ impl GithubCrawler {
     }
 }
 
+#[cfg(test)]
+mod storage_tests;
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::github_service::APP_USER_AGENT;
     use crate::packages::Package;
     use crate::test::{DatabaseTestContext, DB_POOL};
     use httpmock::prelude::GET;
@@ -225,6 +312,8 @@ mod tests {
                     forks_count: 0,
                     url: "".to_string(),
                     rev: "".to_string(),
+                    dependencies: vec![],
+                    addresses: vec![],
                 },
                 GithubRepoData {
                     name: "name2".to_string(),
@@ -237,6 +326,8 @@ mod tests {
                     forks_count: 0,
                     url: "".to_string(),
                     rev: "".to_string(),
+                    dependencies: vec![],
+                    addresses: vec![],
                 },
             ]),
             pool: pool.clone(),
@@ -258,6 +349,8 @@ mod tests {
                     forks_count: 0,
                     url: "".to_string(),
                     rev: "".to_string(),
+                    dependencies: vec![],
+                    addresses: vec![],
                 },
                 GithubRepoData {
                     name: "name3".to_string(),
@@ -270,6 +363,8 @@ mod tests {
                     forks_count: 0,
                     url: "".to_string(),
                     rev: "".to_string(),
+                    dependencies: vec![],
+                    addresses: vec![],
                 },
             ]),
             pool: pool.clone(),
@@ -291,6 +386,8 @@ mod tests {
                     forks_count: 0,
                     url: "".to_string(),
                     rev: "".to_string(),
+                    dependencies: vec![],
+                    addresses: vec![],
                 },
             ]),
             pool,
@@ -331,6 +428,8 @@ mod tests {
                     forks_count: 0,
                     url: "".to_string(),
                     rev: "".to_string(),
+                    dependencies: vec![],
+                    addresses: vec![],
                 })
             });
         crawler.scrape(&mock_github_service);
@@ -348,6 +447,8 @@ mod tests {
             forks_count: 0,
             url: "".to_string(),
             rev: "".to_string(),
+            dependencies: vec![],
+            addresses: vec![],
         };
         assert_eq!(repo_data[0], stub1);
     }
@@ -384,6 +485,8 @@ mod tests {
                     rev: "".to_string(),
                     stars_count: 0,
                     forks_count: 0,
+                    dependencies: vec![],
+                    addresses: vec![],
                 })
             });
         crawler.scrape(&mock_github_service);
@@ -403,6 +506,8 @@ mod tests {
             "https://github.com/alinush/aptos-core/tree/master/aptos-move/framework/move-stdlib"
                 .to_string(),
             rev: "".to_string(),
+            dependencies: vec![],
+            addresses: vec![],
         };
         assert_eq!(repo_data[0], stub1);
         assert_eq!(repo_data[0].readme_content, stub1.readme_content);
@@ -412,6 +517,27 @@ mod tests {
         assert_eq!(repo_data[0].rev, stub1.rev);
     }
 
+    #[actix_rt::test]
+    async fn scrape_gives_up_after_failed_trials() {
+        crate::test::init();
+        let _ctx = DatabaseTestContext::new();
+        let mut crawler = GithubCrawler {
+            repo_urls: vec![GithubSearchItem::from_repo(
+                "https://github.com/move-language/move",
+                "Move.toml",
+            )],
+            repos_data: Mutex::new(vec![]),
+            pool: (*DB_POOL).clone(),
+        };
+        let mut mock_github_service = GithubService::new();
+        mock_github_service
+            .expect_fetch_repo_data()
+            .times(SCRAPE_TRIALS as usize)
+            .returning(|_, _, _| Err(jelly::error::Error::Generic("Not found".to_string())));
+        crawler.scrape(&mock_github_service);
+        assert!(crawler.repos_data.lock().unwrap().is_empty());
+    }
+
     #[actix_rt::test]
     async fn find_new_repos_works() {
         crate::test::init();
@@ -422,7 +548,7 @@ mod tests {
             when.method(GET)
                 .path_contains("/search/code")
                 .header("User-Agent", APP_USER_AGENT)
-                .header("authorization", format!("Bearer {}", &access_token));
+                .header("authorization", format!("token {}", &access_token));
             then.status(200).json_body(json!(
                 {
                     "items": [
@@ -444,7 +570,9 @@ mod tests {
             pool: (*DB_POOL).clone(),
         };
         assert_eq!(gh_crawler.repo_urls.len(), 0);
-        gh_crawler.find_new_repos(&format!("{}/search/code", server.base_url()));
+        gh_crawler
+            .find_new_repos(&format!("{}/search/code", server.base_url()))
+            .unwrap();
         server_mock.assert();
         assert_eq!(gh_crawler.repo_urls.len(), 1);
         let repo_url = gh_crawler.repo_urls.get(0).unwrap();
@@ -464,7 +592,7 @@ mod tests {
             when.method(GET)
                 .path_contains("/search/code")
                 .header("User-Agent", APP_USER_AGENT)
-                .header("authorization", format!("Bearer {}", &access_token));
+                .header("authorization", format!("token {}", &access_token));
             then.status(200).json_body(json!({}));
         });
         let mut gh_crawler = GithubCrawler {
@@ -472,7 +600,9 @@ mod tests {
             repos_data: Mutex::new(vec![]),
             pool: (*DB_POOL).clone(),
         };
-        gh_crawler.find_new_repos(&format!("{}/search/code", server.base_url()));
+        assert!(gh_crawler
+            .find_new_repos(&format!("{}/search/code", server.base_url()))
+            .is_err());
         server_mock.assert();
         assert_eq!(gh_crawler.repo_urls.len(), 0);
     }
@@ -0,0 +1,133 @@
+use diesel::prelude::*;
+use diesel::{Identifiable, Queryable};
+use jelly::chrono::{DateTime, Duration, Utc};
+use jelly::serde::{Deserialize, Serialize};
+use jelly::DieselPgPool;
+use jelly::Result;
+
+use crate::schema::{crawl_cursors, crawled_repos};
+
+#[cfg(test)]
+mod tests;
+
+pub const GITHUB_SEARCH_CURSOR: &str = "github_search";
+
+/// Where a crawl is up to. Only one crawler can hold a cursor at a time.
+#[derive(Debug, Serialize, Deserialize, Queryable, Identifiable)]
+#[table_name = "crawl_cursors"]
+#[primary_key(name)]
+pub struct CrawlCursor {
+    pub name: String,
+    pub page: i32,
+    pub next_run_at: Option<DateTime<Utc>>,
+    pub locked_until: Option<DateTime<Utc>>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl CrawlCursor {
+    /// Locks the cursor for `lease`, creating it on first use. Returns `None`
+    /// while another crawler holds it.
+    pub fn claim(cursor_name: &str, lease: Duration, pool: &DieselPgPool) -> Result<Option<Self>> {
+        let conn = pool.get()?;
+        diesel::insert_into(crawl_cursors::table)
+            .values(crawl_cursors::name.eq(cursor_name))
+            .on_conflict_do_nothing()
+            .execute(&conn)?;
+
+        let now = Utc::now();
+        Ok(diesel::update(
+            crawl_cursors::table.find(cursor_name).filter(
+                crawl_cursors::locked_until
+                    .is_null()
+                    .or(crawl_cursors::locked_until.lt(now)),
+            ),
+        )
+        .set((
+            crawl_cursors::locked_until.eq(now + lease),
+            crawl_cursors::updated_at.eq(now),
+        ))
+        .get_result::<CrawlCursor>(&conn)
+        .optional()?)
+    }
+
+    /// Whether the next page should be crawled now.
+    pub fn is_due(&self, now: DateTime<Utc>) -> bool {
+        match self.next_run_at {
+            Some(next_run_at) => next_run_at <= now,
+            None => true,
+        }
+    }
+
+    /// Saves the position of the crawl and unlocks the cursor.
+    pub fn release(
+        &self,
+        page: i32,
+        next_run_at: Option<DateTime<Utc>>,
+        pool: &DieselPgPool,
+    ) -> Result<()> {
+        let conn = pool.get()?;
+        diesel::update(crawl_cursors::table.find(&self.name))
+            .set((
+                crawl_cursors::page.eq(page),
+                crawl_cursors::next_run_at.eq(next_run_at),
+                crawl_cursors::locked_until.eq(None::<DateTime<Utc>>),
+                crawl_cursors::updated_at.eq(Utc::now()),
+            ))
+            .execute(&conn)?;
+
+        Ok(())
+    }
+}
+
+/// A Move.toml found by the crawler.
+#[derive(Debug, Serialize, Deserialize, Queryable, Identifiable)]
+#[table_name = "crawled_repos"]
+pub struct CrawledRepo {
+    pub id: i32,
+    pub repository_url: String,
+    pub path: String,
+    pub last_crawled_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl CrawledRepo {
+    /// Records that the Move.toml at `path` of the repository was just crawled.
+    pub fn touch(repository_url_: &str, path_: &str, pool: &DieselPgPool) -> Result<()> {
+        let conn = pool.get()?;
+        let now = Utc::now();
+        diesel::insert_into(crawled_repos::table)
+            .values((
+                crawled_repos::repository_url.eq(repository_url_),
+                crawled_repos::path.eq(path_),
+                crawled_repos::last_crawled_at.eq(now),
+            ))
+            .on_conflict((crawled_repos::repository_url, crawled_repos::path))
+            .do_update()
+            .set(crawled_repos::last_crawled_at.eq(now))
+            .execute(&conn)?;
+
+        Ok(())
+    }
+
+    /// Repos that weren't crawled since `crawled_before`, least recently crawled first.
+    pub fn get_stale(
+        crawled_before: DateTime<Utc>,
+        limit: i64,
+        pool: &DieselPgPool,
+    ) -> Result<Vec<Self>> {
+        let conn = pool.get()?;
+        Ok(crawled_repos::table
+            .filter(crawled_repos::last_crawled_at.lt(crawled_before))
+            .order_by(crawled_repos::last_crawled_at.asc())
+            .limit(limit)
+            .load::<CrawledRepo>(&conn)?)
+    }
+
+    pub fn get(repository_url_: &str, path_: &str, pool: &DieselPgPool) -> Result<Self> {
+        let conn = pool.get()?;
+        Ok(crawled_repos::table
+            .filter(crawled_repos::repository_url.eq(repository_url_))
+            .filter(crawled_repos::path.eq(path_))
+            .first::<CrawledRepo>(&conn)?)
+    }
+}
@@ -0,0 +1,93 @@
+use super::*;
+use crate::test::{DatabaseTestContext, DB_POOL};
+
+#[actix_rt::test]
+async fn claim_locks_cursor() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let cursor = CrawlCursor::claim(GITHUB_SEARCH_CURSOR, Duration::minutes(10), &DB_POOL)
+        .unwrap()
+        .unwrap();
+    assert_eq!(cursor.page, 0);
+    assert!(cursor.is_due(Utc::now()));
+    assert!(
+        CrawlCursor::claim(GITHUB_SEARCH_CURSOR, Duration::minutes(10), &DB_POOL)
+            .unwrap()
+            .is_none()
+    );
+
+    cursor.release(5, None, &DB_POOL).unwrap();
+    let cursor = CrawlCursor::claim(GITHUB_SEARCH_CURSOR, Duration::minutes(10), &DB_POOL)
+        .unwrap()
+        .unwrap();
+    assert_eq!(cursor.page, 5);
+}
+
+#[actix_rt::test]
+async fn claim_takes_over_expired_lease() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    CrawlCursor::claim(GITHUB_SEARCH_CURSOR, Duration::minutes(-1), &DB_POOL)
+        .unwrap()
+        .unwrap();
+    assert!(
+        CrawlCursor::claim(GITHUB_SEARCH_CURSOR, Duration::minutes(10), &DB_POOL)
+            .unwrap()
+            .is_some()
+    );
+}
+
+#[actix_rt::test]
+async fn release_schedules_next_run() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let cursor = CrawlCursor::claim(GITHUB_SEARCH_CURSOR, Duration::minutes(10), &DB_POOL)
+        .unwrap()
+        .unwrap();
+    let next_run_at = Utc::now() + Duration::hours(1);
+    cursor.release(0, Some(next_run_at), &DB_POOL).unwrap();
+
+    let cursor = CrawlCursor::claim(GITHUB_SEARCH_CURSOR, Duration::minutes(10), &DB_POOL)
+        .unwrap()
+        .unwrap();
+    assert!(!cursor.is_due(Utc::now()));
+    assert!(cursor.is_due(next_run_at + Duration::seconds(1)));
+}
+
+#[actix_rt::test]
+async fn touch_updates_last_crawled_at() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let url = "https://github.com/move-language/move";
+    CrawledRepo::touch(url, "Move.toml", &DB_POOL).unwrap();
+    let repo = CrawledRepo::get(url, "Move.toml", &DB_POOL).unwrap();
+
+    CrawledRepo::touch(url, "Move.toml", &DB_POOL).unwrap();
+    let touched_repo = CrawledRepo::get(url, "Move.toml", &DB_POOL).unwrap();
+    assert_eq!(touched_repo.id, repo.id);
+    assert!(touched_repo.last_crawled_at > repo.last_crawled_at);
+}
+
+#[actix_rt::test]
+async fn get_stale_returns_least_recently_crawled_repos() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let url = "https://github.com/move-language/move";
+    CrawledRepo::touch(url, "language/move-stdlib/Move.toml", &DB_POOL).unwrap();
+    CrawledRepo::touch(url, "language/move-nursery/Move.toml", &DB_POOL).unwrap();
+    let crawled_before = Utc::now();
+    CrawledRepo::touch(url, "Move.toml", &DB_POOL).unwrap();
+
+    let stale_repos = CrawledRepo::get_stale(crawled_before, 10, &DB_POOL).unwrap();
+    assert_eq!(stale_repos.len(), 2);
+    assert_eq!(stale_repos[0].path, "language/move-stdlib/Move.toml");
+    assert_eq!(stale_repos[1].path, "language/move-nursery/Move.toml");
+
+    let stale_repos = CrawledRepo::get_stale(crawled_before, 1, &DB_POOL).unwrap();
+    assert_eq!(stale_repos.len(), 1);
+}
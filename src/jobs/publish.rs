@@ -0,0 +1,398 @@
+use std::env;
+use std::future::Future;
+use std::pin::Pin;
+
+use diesel::result::{DatabaseErrorKind, Error as DBError};
+use jelly::actix_web::error::BlockingError;
+use jelly::actix_web::web;
+use jelly::anyhow::{anyhow, Error};
+use jelly::email::Email;
+use jelly::error::Error::Generic;
+use jelly::jobs::{Job, JobState};
+use jelly::serde::{Deserialize, Serialize};
+use jelly::tera::Context;
+use jelly::DieselPgPool;
+use mockall_double::double;
+
+use crate::accounts::Account;
+#[double]
+use crate::github_service::GithubService;
+use crate::package_collaborators::package_collaborator::PackageCollaborator;
+use crate::packages::models::package_event::{EventActor, PackageEvent, PackageEventKind};
+use crate::packages::Package;
+use crate::source_hosts;
+use crate::utils::presenter::validate_version;
+
+/// Publishing fetches the tree of the tag and counts its files, which can take a while
+/// for large repositories.
+const PUBLISH_TIMEOUT_MINUTES: i64 = 10;
+/// Who the audit log of the package says published the tag.
+const TAG_WEBHOOK_ACTOR: &str = "Tag webhook";
+
+/// Whether a git tag names a version, e.g. `v1.2.0` or `1.2.0` for `1.2.0`.
+pub fn tag_matches_version(tag: &str, version: &str) -> bool {
+    tag.strip_prefix('v').unwrap_or(tag) == version
+}
+
+/// Publishes the version of a package tagged in its repository. Returns the published
+/// version, `None` if the version was already published, e.g. when a tag is pushed
+/// again, or an error explaining to the owners why nothing was published.
+pub fn publish_tag(
+    package_id: i32,
+    tag: &str,
+    rev: &str,
+    pool: &DieselPgPool,
+    github_service: &GithubService,
+) -> jelly::Result<Option<String>> {
+    let conn = pool.get()?;
+    let package = Package::get(package_id, pool)?;
+    let tagged_version = tag.strip_prefix('v').unwrap_or(tag).to_string();
+    match package.get_version(&tagged_version, &conn) {
+        Ok(_) => {
+            info!(
+                "Skipping tag {} of package {}, its version is already published",
+                tag, package.id
+            );
+            return Ok(None);
+        }
+        Err(jelly::error::Error::Database(DBError::NotFound)) => {}
+        Err(e) => return Err(e),
+    }
+
+    let (repo_url, subdir) =
+        source_hosts::for_url(&package.repository_url).split_tree_url(&package.repository_url);
+    let path = if subdir.is_empty() {
+        None
+    } else {
+        Some(format!("{}/Move.toml", subdir.trim_end_matches('/')))
+    };
+
+    let github_data = github_service
+        .fetch_repo_data(&repo_url, path, Some(rev.to_string()))
+        .map_err(|e| {
+            error!("Error fetching tag {} of {}: {:?}", tag, repo_url, e);
+            Generic(format!("The Move.toml of tag {} could not be read.", tag))
+        })?;
+    if github_data.name != package.name {
+        return Err(Generic(format!(
+            "The Move.toml of tag {} is for package {}, not {}.",
+            tag, github_data.name, package.name
+        )));
+    }
+    if !tag_matches_version(tag, &github_data.version) {
+        return Err(Generic(format!(
+            "Tag {} doesn't match version {} in Move.toml.",
+            tag, github_data.version
+        )));
+    }
+    if !validate_version(&github_data.version).is_empty() {
+        return Err(Generic(format!(
+            "Version {} doesn't adhere to semantic versioning.",
+            github_data.version
+        )));
+    }
+    let total_files = github_service
+        .count_files(&repo_url, rev, &subdir)
+        .map_err(|e| {
+            error!(
+                "Error listing files of tag {} of {}: {:?}",
+                tag, repo_url, e
+            );
+            Generic(format!("The files of tag {} could not be listed.", tag))
+        })?;
+
+    let owner_id = PackageCollaborator::get_owner_ids(package.id, &conn)?
+        .first()
+        .copied();
+    let version = github_data.version.clone();
+    let description = github_data.description.clone();
+    match Package::create_from_crawled_data(
+        &package.repository_url,
+        &description,
+        rev,
+        total_files,
+        github_data.size,
+        owner_id,
+        github_data,
+        pool,
+    ) {
+        Ok(_) => {
+            // The version is already published, a missing entry in the audit log shouldn't
+            // fail the job
+            if let Err(e) = PackageEvent::create(
+                package.id,
+                PackageEventKind::VersionPublished,
+                &EventActor::system(TAG_WEBHOOK_ACTOR),
+                None,
+                &version,
+                &conn,
+            ) {
+                error!(
+                    "Error recording the tag {} of {}: {:?}",
+                    tag, package.slug, e
+                );
+            }
+            Ok(Some(version))
+        }
+        // Published by another delivery in the meantime
+        Err(jelly::error::Error::Database(DBError::DatabaseError(
+            DatabaseErrorKind::UniqueViolation,
+            _,
+        ))) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Publishes a tag pushed to the repository of a package, then queues an email to its
+/// owners. Tags of versions that are already published are skipped silently.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PublishTag {
+    pub package_id: i32,
+    pub tag: String,
+    pub rev: String,
+}
+
+impl Job for PublishTag {
+    type State = JobState;
+    type Future = Pin<Box<dyn Future<Output = Result<(), Error>> + Send>>;
+
+    const NAME: &'static str = "PublishTagJob";
+    const TIMEOUT: i64 = PUBLISH_TIMEOUT_MINUTES * 60 * 1000;
+
+    fn run(self, state: JobState) -> Self::Future {
+        Box::pin(async move {
+            let pool = state.pool.clone();
+            let (package_id, tag, rev) = (self.package_id, self.tag.clone(), self.rev.clone());
+            // Fetching the tag makes blocking calls to the source host
+            let result = web::block(move || {
+                publish_tag(package_id, &tag, &rev, &pool, &GithubService::new()).map_err(|e| {
+                    match e {
+                        Generic(reason) => reason,
+                        e => {
+                            error!("Error publishing tag: {:?}", e);
+                            "An unexpected error occurred.".to_string()
+                        }
+                    }
+                })
+            })
+            .await;
+            let outcome = match result {
+                Ok(Some(version)) => Ok(version),
+                Ok(None) => return Ok(()),
+                Err(BlockingError::Error(reason)) => Err(reason),
+                Err(BlockingError::Canceled) => Err("An unexpected error occurred.".to_string()),
+            };
+
+            // Sent separately, so that a failed email doesn't publish the tag again
+            state
+                .queue(SendTagPublishedEmail {
+                    package_id: self.package_id,
+                    tag: self.tag,
+                    outcome,
+                })
+                .await
+        })
+    }
+}
+
+/// An email that lets the owners of a package know whether a tag pushed to its
+/// repository was published.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SendTagPublishedEmail {
+    pub package_id: i32,
+    pub tag: String,
+    /// The published version, or why the tag wasn't published.
+    pub outcome: Result<String, String>,
+}
+
+impl Job for SendTagPublishedEmail {
+    type State = JobState;
+    type Future = Pin<Box<dyn Future<Output = Result<(), Error>> + Send>>;
+
+    const NAME: &'static str = "SendTagPublishedEmailJob";
+
+    fn run(self, state: JobState) -> Self::Future {
+        Box::pin(async move {
+            let package = Package::get(self.package_id, &state.pool)
+                .map_err(|e| anyhow!("Error fetching package of published tag: {:?}", e))?;
+            let domain = env::var("JELLY_DOMAIN").expect("No JELLY_DOMAIN value set!");
+            let mut context = Context::new();
+            context.insert("package_name", &package.name);
+            context.insert("tag", &self.tag);
+            context.insert(
+                "action_url",
+                &format!("{}/packages/{}", domain, package.slug),
+            );
+            let (template, subject) = match &self.outcome {
+                Ok(version) => {
+                    context.insert("version", version);
+                    (
+                        "email/tag-published",
+                        format!("Version {} of {} was published", version, package.name),
+                    )
+                }
+                Err(reason) => {
+                    context.insert("reason", reason);
+                    (
+                        "email/tag-publish-failed",
+                        format!(
+                            "Tag {} of {} could not be published",
+                            self.tag, package.name
+                        ),
+                    )
+                }
+            };
+
+            let conn = state.pool.get()?;
+            let owner_ids = PackageCollaborator::get_owner_ids(package.id, &conn)
+                .map_err(|e| anyhow!("Error fetching package owners: {:?}", e))?;
+            for owner_id in owner_ids {
+                let owner = Account::get(owner_id, &state.pool)
+                    .map_err(|e| anyhow!("Error fetching package owner: {:?}", e))?;
+                if owner.is_generated_email() {
+                    continue;
+                }
+                Email::new(
+                    template,
+                    &[owner.email],
+                    &subject,
+                    context.clone(),
+                    state.templates.clone(),
+                )?
+                .send()?;
+            }
+
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::github_service::GithubRepoData;
+    use crate::test::util::{setup_package, setup_user};
+    use crate::test::{DatabaseTestContext, DB_POOL};
+
+    fn repo_data(version: &str) -> GithubRepoData {
+        GithubRepoData {
+            name: "MoveStdlib".to_string(),
+            version: version.to_string(),
+            readme_content: "readme".to_string(),
+            license: "".to_string(),
+            description: "description".to_string(),
+            size: 1,
+            stars_count: 0,
+            forks_count: 0,
+            url: "".to_string(),
+            rev: "".to_string(),
+            dependencies: vec![],
+            addresses: vec![],
+        }
+    }
+
+    fn create_package(owner_id: i32) -> i32 {
+        setup_package(
+            "MoveStdlib",
+            "https://github.com/move-language/move/tree/main/language/move-stdlib",
+            owner_id,
+        )
+    }
+
+    fn github_service_returning(version: &'static str) -> GithubService {
+        let mut mock_github_service = GithubService::default();
+        mock_github_service
+            .expect_fetch_repo_data()
+            .withf(|repo_url, path, rev| {
+                repo_url == "https://github.com/move-language/move"
+                    && path.as_deref() == Some("language/move-stdlib/Move.toml")
+                    && rev.as_deref() == Some("test sha")
+            })
+            .returning(move |_, _, _| Ok(repo_data(version)));
+        mock_github_service
+            .expect_count_files()
+            .withf(|repo_url, rev, subdir| {
+                repo_url == "https://github.com/move-language/move"
+                    && rev == "test sha"
+                    && subdir == "language/move-stdlib"
+            })
+            .returning(|_, _, _| Ok(12));
+        mock_github_service
+    }
+
+    #[test]
+    fn tag_matches_version_works() {
+        assert!(tag_matches_version("v1.2.0", "1.2.0"));
+        assert!(tag_matches_version("1.2.0", "1.2.0"));
+        assert!(!tag_matches_version("v1.2", "1.2.0"));
+        assert!(!tag_matches_version("release-1.2.0", "1.2.0"));
+    }
+
+    #[actix_rt::test]
+    async fn publish_tag_creates_version() {
+        crate::test::init();
+        let _ctx = DatabaseTestContext::new();
+
+        let uid = setup_user(None, None);
+        let pid = create_package(uid);
+        let version = publish_tag(
+            pid,
+            "v1.1.0",
+            "test sha",
+            &DB_POOL,
+            &github_service_returning("1.1.0"),
+        )
+        .unwrap();
+        assert_eq!(version, Some("1.1.0".to_string()));
+
+        let conn = DB_POOL.get().unwrap();
+        let package = Package::get(pid, &DB_POOL).unwrap();
+        let package_version = package.get_version(&"1.1.0".to_string(), &conn).unwrap();
+        assert_eq!(package_version.rev, Some("test sha".to_string()));
+        assert_eq!(package_version.total_files, Some(12));
+
+        let (events, _, _) = PackageEvent::get_by_package_id_paginated(pid, None, &conn).unwrap();
+        assert_eq!(events[0].kind(), Some(PackageEventKind::VersionPublished));
+        assert_eq!(events[0].details, "1.1.0");
+        assert_eq!(events[0].actor_id, None);
+        assert_eq!(events[0].system_actor, Some(TAG_WEBHOOK_ACTOR.to_string()));
+    }
+
+    #[actix_rt::test]
+    async fn publish_tag_returns_error_if_tag_does_not_match_version() {
+        crate::test::init();
+        let _ctx = DatabaseTestContext::new();
+
+        let uid = setup_user(None, None);
+        let pid = create_package(uid);
+        let error = publish_tag(
+            pid,
+            "v1.2.0",
+            "test sha",
+            &DB_POOL,
+            &github_service_returning("1.1.0"),
+        )
+        .unwrap_err();
+        assert!(
+            matches!(error, Generic(reason) if reason == "Tag v1.2.0 doesn't match version 1.1.0 in Move.toml.")
+        );
+        let package = Package::get(pid, &DB_POOL).unwrap();
+        assert!(package
+            .get_version(&"1.2.0".to_string(), &DB_POOL.get().unwrap())
+            .is_err());
+    }
+
+    #[actix_rt::test]
+    async fn publish_tag_skips_published_versions() {
+        crate::test::init();
+        let _ctx = DatabaseTestContext::new();
+
+        let uid = setup_user(None, None);
+        let pid = create_package(uid);
+        let mut mock_github_service = GithubService::default();
+        mock_github_service.expect_fetch_repo_data().times(0);
+        mock_github_service.expect_count_files().times(0);
+        let version = publish_tag(pid, "v1.0.0", "test sha", &DB_POOL, &mock_github_service);
+        assert_eq!(version.unwrap(), None);
+    }
+}
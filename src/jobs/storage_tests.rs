@@ -0,0 +1,226 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, RwLock};
+
+use jelly::anyhow::Error;
+use jelly::chrono::{Duration, Utc};
+use jelly::jobs::{
+    new_job, Job, JobInfo, JobState, PostgresStorage, ReturnJobInfo, Storage, DEFAULT_QUEUE,
+};
+use jelly::serde::{Deserialize, Serialize};
+use jelly::tera::Tera;
+use serde_json::json;
+
+use crate::test::{DatabaseTestContext, DB_POOL};
+
+#[derive(Debug, Serialize, Deserialize)]
+struct TestJob {
+    message: String,
+}
+
+impl Job for TestJob {
+    type State = JobState;
+    type Future = Pin<Box<dyn Future<Output = Result<(), Error>> + Send>>;
+    const NAME: &'static str = "TestJob";
+
+    fn run(self, _state: JobState) -> Self::Future {
+        Box::pin(async move { Ok(()) })
+    }
+}
+
+fn returned(id: impl Serialize, result: &str) -> ReturnJobInfo {
+    serde_json::from_value(json!({ "id": id, "result": result })).unwrap()
+}
+
+async fn create_job(storage: &PostgresStorage) -> JobInfo {
+    let job = new_job(TestJob {
+        message: "hello".to_string(),
+    })
+    .unwrap();
+    let id = storage.new_job(job).await.unwrap();
+    storage.fetch_job(id).await.unwrap().unwrap()
+}
+
+#[actix_rt::test]
+async fn new_job_is_persisted_and_claimed_once() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let storage = PostgresStorage::new(DB_POOL.clone());
+    let job = create_job(&storage).await;
+    assert_eq!(storage.get_stats().await.unwrap().pending, 1);
+
+    // A storage created after a restart still sees the job
+    let storage = PostgresStorage::new(DB_POOL.clone());
+    let runner_id = storage.generate_id().await.unwrap();
+    let claimed = storage
+        .request_job(DEFAULT_QUEUE, runner_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(claimed.id(), job.id());
+    assert!(claimed.status().is_running());
+    assert_eq!(storage.get_stats().await.unwrap().running, 1);
+
+    assert!(storage
+        .request_job(DEFAULT_QUEUE, runner_id)
+        .await
+        .unwrap()
+        .is_none());
+    assert!(storage
+        .request_job("another-queue", runner_id)
+        .await
+        .unwrap()
+        .is_none());
+}
+
+#[actix_rt::test]
+async fn successful_job_is_deleted() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let storage = PostgresStorage::new(DB_POOL.clone());
+    let job = create_job(&storage).await;
+    let runner_id = storage.generate_id().await.unwrap();
+    storage.request_job(DEFAULT_QUEUE, runner_id).await.unwrap();
+
+    storage
+        .return_job(returned(job.id(), "Success"))
+        .await
+        .unwrap();
+    assert!(storage.fetch_job(job.id()).await.unwrap().is_none());
+    assert!(storage.dead_jobs().unwrap().is_empty());
+}
+
+#[actix_rt::test]
+async fn failed_job_is_retried_with_backoff() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let storage = PostgresStorage::new(DB_POOL.clone());
+    let job = create_job(&storage).await;
+    let runner_id = storage.generate_id().await.unwrap();
+    storage.request_job(DEFAULT_QUEUE, runner_id).await.unwrap();
+
+    storage
+        .return_job(returned(job.id(), "Failure"))
+        .await
+        .unwrap();
+    let retried = storage.fetch_job(job.id()).await.unwrap().unwrap();
+    assert!(retried.status().is_pending());
+    assert!(retried.next_queue().unwrap() > Utc::now());
+
+    // Not picked up again until the backoff has passed
+    assert!(storage
+        .request_job(DEFAULT_QUEUE, runner_id)
+        .await
+        .unwrap()
+        .is_none());
+    assert_eq!(storage.get_stats().await.unwrap().pending, 1);
+}
+
+#[actix_rt::test]
+async fn job_is_dead_after_running_out_of_retries() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let storage = PostgresStorage::new(DB_POOL.clone());
+    let job = create_job(&storage).await;
+
+    // TestJob is allowed 5 retries on top of its first attempt
+    for _ in 0..6 {
+        storage
+            .return_job(returned(job.id(), "Failure"))
+            .await
+            .unwrap();
+    }
+
+    let dead_jobs = storage.dead_jobs().unwrap();
+    assert_eq!(dead_jobs.len(), 1);
+    assert_eq!(dead_jobs[0].id(), job.id());
+    let stats = storage.get_stats().await.unwrap();
+    assert_eq!(stats.pending, 0);
+    assert_eq!(stats.running, 0);
+
+    // Dead jobs are never handed out again
+    storage.queue_job(DEFAULT_QUEUE, job.id()).await.unwrap();
+    let runner_id = storage.generate_id().await.unwrap();
+    assert!(storage
+        .request_job(DEFAULT_QUEUE, runner_id)
+        .await
+        .unwrap()
+        .is_none());
+}
+
+#[actix_rt::test]
+async fn running_job_is_claimed_again_after_timeout() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let storage = PostgresStorage::new(DB_POOL.clone());
+    let job = create_job(&storage).await;
+    let runner_id = storage.generate_id().await.unwrap();
+    storage.request_job(DEFAULT_QUEUE, runner_id).await.unwrap();
+
+    // Simulate a worker that died an hour ago while running the job
+    let running = storage.fetch_job(job.id()).await.unwrap().unwrap();
+    let mut value = serde_json::to_value(&running).unwrap();
+    value["updated_at"] = json!(Utc::now() - Duration::hours(1));
+    storage
+        .save_job(serde_json::from_value(value).unwrap())
+        .await
+        .unwrap();
+
+    let claimed = storage
+        .request_job(DEFAULT_QUEUE, runner_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(claimed.id(), job.id());
+}
+
+#[actix_rt::test]
+async fn unexecuted_job_is_requeued_without_counting_an_attempt() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let storage = PostgresStorage::new(DB_POOL.clone());
+    let job = create_job(&storage).await;
+    let runner_id = storage.generate_id().await.unwrap();
+    let claimed = storage
+        .request_job(DEFAULT_QUEUE, runner_id)
+        .await
+        .unwrap()
+        .unwrap();
+
+    storage.return_job(claimed.unexecuted()).await.unwrap();
+    let claimed = storage
+        .request_job(DEFAULT_QUEUE, runner_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(claimed.id(), job.id());
+    let value = serde_json::to_value(&claimed).unwrap();
+    assert_eq!(value["retry_count"], 0);
+}
+
+#[actix_rt::test]
+async fn job_state_queues_jobs_in_storage() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let state = JobState::new(
+        "test",
+        DB_POOL.clone(),
+        Arc::new(RwLock::new(Tera::default())),
+    );
+    state
+        .queue(TestJob {
+            message: "hello".to_string(),
+        })
+        .await
+        .unwrap();
+
+    let storage = PostgresStorage::new(DB_POOL.clone());
+    assert_eq!(storage.get_stats().await.unwrap().pending, 1);
+}
@@ -4,8 +4,6 @@ use jelly::actix_web::dev;
 #[cfg(not(feature = "test"))]
 use std::env;
 use std::io;
-#[cfg(not(feature = "test"))]
-use std::sync::Mutex;
 
 #[macro_use]
 extern crate diesel;
@@ -21,11 +19,14 @@ extern crate core;
 
 pub mod accounts;
 pub mod api;
+pub mod github_client;
 pub mod github_service;
+pub mod organizations;
 pub mod packages;
 pub mod pages;
 pub mod policy;
 pub mod settings;
+pub mod source_hosts;
 pub mod users;
 mod utils;
 
@@ -58,18 +59,7 @@ pub async fn main() -> io::Result<()> {
             ..Default::default()
         },
     ));
-    let (server, pool) = start_server().await?;
-    let is_crawling = env::var("CRAWLING").unwrap_or_else(|_| "".to_string());
-    if is_crawling.to_lowercase() == "true" {
-        actix_rt::spawn(async {
-            let gh_crawler = jobs::GithubCrawler {
-                repo_urls: vec![],
-                repos_data: Mutex::new(vec![]),
-                pool,
-            };
-            gh_crawler.run().await;
-        });
-    }
+    let (server, _) = start_server().await?;
     server.await
 }
 
@@ -80,7 +70,7 @@ pub async fn main() -> io::Result<()> {
 }
 
 async fn start_server() -> io::Result<(dev::Server, DieselPgPool)> {
-    Server::new()
+    let (server, pool) = Server::new()
         .register_service(package_collaborators::configure)
         .register_jobs(package_collaborators::jobs::configure)
         .register_service(pages::configure)
@@ -91,7 +81,13 @@ async fn start_server() -> io::Result<(dev::Server, DieselPgPool)> {
         .register_service(api::configure)
         .register_service(settings::configure)
         .register_service(users::configure)
+        .register_service(organizations::configure)
+        .register_jobs(organizations::jobs::configure)
         .register_service(policy::configure)
+        .register_jobs(jobs::configure)
+        .register_schedule(jobs::schedule)
         .run()
-        .await
+        .await?;
+    github_client::GITHUB_CLIENT.use_cache(pool.clone());
+    Ok((server, pool))
 }
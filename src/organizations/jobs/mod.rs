@@ -0,0 +1,9 @@
+use jelly::jobs::{JobState, WorkerConfig};
+
+use invite_member::SendOrganizationInvitationEmail;
+
+pub mod invite_member;
+
+pub fn configure(config: WorkerConfig<JobState>) -> WorkerConfig<JobState> {
+    config.register::<SendOrganizationInvitationEmail>()
+}
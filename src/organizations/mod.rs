@@ -0,0 +1,19 @@
+pub mod jobs;
+pub mod models;
+pub mod views;
+
+use jelly::actix_web::web::{get, resource, scope, ServiceConfig};
+
+pub fn configure(config: &mut ServiceConfig) {
+    config.service(
+        scope("/organizations")
+            .service(
+                resource("/accept/{token}")
+                    .route(get().to(views::invitation::accept_invite_with_token)),
+            )
+            .service(
+                resource("/{organization_slug}")
+                    .route(get().to(views::profile::get_public_profile)),
+            ),
+    );
+}
@@ -0,0 +1,2 @@
+pub mod organization;
+pub mod team;
@@ -0,0 +1,236 @@
+use diesel::dsl::count;
+use diesel::prelude::*;
+use diesel::{Identifiable, Insertable, Queryable};
+use jelly::chrono::{DateTime, Utc};
+use jelly::serde::{Deserialize, Serialize};
+use jelly::DieselPgConnection;
+use jelly::Result;
+
+use crate::package_collaborators::package_collaborator::Role;
+use crate::schema::{accounts, organization_members, organizations, packages, team_members, teams};
+
+#[cfg(test)]
+mod tests;
+
+#[derive(Debug, Serialize, Deserialize, Queryable, Identifiable)]
+#[table_name = "organizations"]
+pub struct Organization {
+    pub id: i32,
+    pub name: String,
+    pub slug: String,
+    pub description: String,
+    pub created_by: i32,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OrganizationRole {
+    Admin = 0,
+    Member = 1,
+}
+
+impl OrganizationRole {
+    pub fn from_i32(role: i32) -> Self {
+        if role == OrganizationRole::Admin as i32 {
+            OrganizationRole::Admin
+        } else {
+            OrganizationRole::Member
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Queryable, Identifiable)]
+#[table_name = "organization_members"]
+#[primary_key(organization_id, account_id)]
+pub struct OrganizationMember {
+    pub organization_id: i32,
+    pub account_id: i32,
+    pub role: i32,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Queryable)]
+pub struct OrganizationMemberQuery {
+    pub account_id: i32,
+    pub name: String,
+    pub slug: Option<String>,
+    pub role: i32,
+}
+
+#[derive(Insertable)]
+#[table_name = "organizations"]
+struct NewOrganization<'a> {
+    name: &'a str,
+    slug: String,
+    description: &'a str,
+    created_by: i32,
+}
+
+impl Organization {
+    /// Creates an organization with its creator as the first admin.
+    pub fn create(
+        name: &str,
+        description: &str,
+        created_by: i32,
+        conn: &DieselPgConnection,
+    ) -> Result<Self> {
+        conn.transaction(|| {
+            let organization = diesel::insert_into(organizations::table)
+                .values(NewOrganization {
+                    name,
+                    slug: slug::slugify(name),
+                    description,
+                    created_by,
+                })
+                .get_result::<Self>(conn)?;
+            Self::add_member(organization.id, created_by, OrganizationRole::Admin, conn)?;
+
+            Ok(organization)
+        })
+    }
+
+    pub fn get(organization_id: i32, conn: &DieselPgConnection) -> Result<Self> {
+        Ok(organizations::table
+            .find(organization_id)
+            .first::<Self>(conn)?)
+    }
+
+    pub fn get_by_slug(organization_slug: &str, conn: &DieselPgConnection) -> Result<Self> {
+        Ok(organizations::table
+            .filter(organizations::slug.eq(organization_slug))
+            .first::<Self>(conn)?)
+    }
+
+    pub fn add_member(
+        organization_id: i32,
+        account_id: i32,
+        role: OrganizationRole,
+        conn: &DieselPgConnection,
+    ) -> Result<()> {
+        diesel::insert_into(organization_members::table)
+            .values((
+                organization_members::organization_id.eq(organization_id),
+                organization_members::account_id.eq(account_id),
+                organization_members::role.eq(role as i32),
+            ))
+            .execute(conn)?;
+
+        Ok(())
+    }
+
+    pub fn get_member(
+        organization_id: i32,
+        account_id: i32,
+        conn: &DieselPgConnection,
+    ) -> Result<OrganizationMember> {
+        Ok(organization_members::table
+            .find((organization_id, account_id))
+            .first::<OrganizationMember>(conn)?)
+    }
+
+    pub fn get_members(
+        organization_id: i32,
+        conn: &DieselPgConnection,
+    ) -> Result<Vec<OrganizationMemberQuery>> {
+        Ok(organization_members::table
+            .inner_join(accounts::table)
+            .filter(organization_members::organization_id.eq(organization_id))
+            .select((
+                accounts::id,
+                accounts::name,
+                accounts::slug,
+                organization_members::role,
+            ))
+            // Admins first
+            .order((organization_members::role.asc(), accounts::id.asc()))
+            .load::<OrganizationMemberQuery>(conn)?)
+    }
+
+    pub fn count_admins(organization_id: i32, conn: &DieselPgConnection) -> Result<i64> {
+        Ok(organization_members::table
+            .filter(
+                organization_members::organization_id
+                    .eq(organization_id)
+                    .and(organization_members::role.eq(OrganizationRole::Admin as i32)),
+            )
+            .select(count(organization_members::account_id))
+            .first::<i64>(conn)?)
+    }
+
+    /// Removes the account from the organization and from all of its teams.
+    pub fn remove_member(
+        organization_id: i32,
+        account_id: i32,
+        conn: &DieselPgConnection,
+    ) -> Result<usize> {
+        conn.transaction(|| {
+            let team_ids = teams::table
+                .filter(teams::organization_id.eq(organization_id))
+                .select(teams::id);
+            diesel::delete(
+                team_members::table.filter(
+                    team_members::team_id
+                        .eq_any(team_ids)
+                        .and(team_members::account_id.eq(account_id)),
+                ),
+            )
+            .execute(conn)?;
+
+            Ok(
+                diesel::delete(organization_members::table.find((organization_id, account_id)))
+                    .execute(conn)?,
+            )
+        })
+    }
+
+    /// Makes the organization the owner of the package, or releases it when `None`.
+    pub fn set_package_organization(
+        package_id: i32,
+        organization_id: Option<i32>,
+        conn: &DieselPgConnection,
+    ) -> Result<()> {
+        diesel::update(packages::table.find(package_id))
+            .set(packages::organization_id.eq(organization_id))
+            .execute(conn)?;
+
+        Ok(())
+    }
+
+    /// The role that membership of the organization grants on its packages: admins are
+    /// owners and members of collaborator teams are collaborators.
+    pub fn get_package_role(
+        organization_id: i32,
+        account_id: i32,
+        conn: &DieselPgConnection,
+    ) -> Result<Option<Role>> {
+        let member = organization_members::table
+            .find((organization_id, account_id))
+            .first::<OrganizationMember>(conn)
+            .optional()?;
+        let member = match member {
+            Some(member) => member,
+            None => return Ok(None),
+        };
+        if member.role == OrganizationRole::Admin as i32 {
+            return Ok(Some(Role::Owner));
+        }
+
+        let collaborator_teams = team_members::table
+            .inner_join(teams::table)
+            .filter(
+                teams::organization_id
+                    .eq(organization_id)
+                    .and(teams::is_collaborator.eq(true))
+                    .and(team_members::account_id.eq(account_id)),
+            )
+            .select(count(teams::id))
+            .first::<i64>(conn)?;
+        Ok(if collaborator_teams > 0 {
+            Some(Role::Collaborator)
+        } else {
+            None
+        })
+    }
+}
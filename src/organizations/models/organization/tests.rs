@@ -0,0 +1,103 @@
+use super::*;
+use crate::organizations::models::team::Team;
+use crate::package_collaborators::package_collaborator::PackageCollaborator;
+use crate::packages::Package;
+use crate::test::util::{setup_package, setup_user};
+use crate::test::{DatabaseTestContext, DB_POOL};
+
+#[actix_rt::test]
+async fn create_makes_the_creator_admin() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let uid = setup_user(None, None);
+    let conn = DB_POOL.get().unwrap();
+    let organization = Organization::create("Move Labs", "", uid, &conn).unwrap();
+    assert_eq!(organization.slug, "move-labs");
+    assert_eq!(
+        Organization::get_by_slug("move-labs", &conn).unwrap().id,
+        organization.id
+    );
+
+    let member = Organization::get_member(organization.id, uid, &conn).unwrap();
+    assert_eq!(member.role, OrganizationRole::Admin as i32);
+    assert_eq!(
+        Organization::count_admins(organization.id, &conn).unwrap(),
+        1
+    );
+
+    // Slugs are unique
+    assert!(Organization::create("move labs", "", uid, &conn).is_err());
+}
+
+#[actix_rt::test]
+async fn remove_member_leaves_teams() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let uid = setup_user(None, None);
+    let member_uid = setup_user(Some("member@host.com".to_string()), None);
+    let conn = DB_POOL.get().unwrap();
+    let organization = Organization::create("Move Labs", "", uid, &conn).unwrap();
+    Organization::add_member(organization.id, member_uid, OrganizationRole::Member, &conn).unwrap();
+    let team = Team::create(organization.id, "Core", true, &conn).unwrap();
+    Team::add_member(team.id, member_uid, &conn).unwrap();
+
+    let members = Organization::get_members(organization.id, &conn).unwrap();
+    assert_eq!(
+        members.iter().map(|m| m.account_id).collect::<Vec<i32>>(),
+        vec![uid, member_uid]
+    );
+
+    assert_eq!(
+        Organization::remove_member(organization.id, member_uid, &conn).unwrap(),
+        1
+    );
+    assert!(Organization::get_member(organization.id, member_uid, &conn).is_err());
+    assert!(Team::get_member_ids(team.id, &conn).unwrap().is_empty());
+}
+
+#[actix_rt::test]
+async fn get_package_role_works() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let admin_uid = setup_user(None, None);
+    let collaborator_uid = setup_user(Some("collaborator@host.com".to_string()), None);
+    let member_uid = setup_user(Some("member@host.com".to_string()), None);
+    let outsider_uid = setup_user(Some("outsider@host.com".to_string()), None);
+    let conn = DB_POOL.get().unwrap();
+
+    let organization = Organization::create("Move Labs", "", admin_uid, &conn).unwrap();
+    for uid in [collaborator_uid, member_uid] {
+        Organization::add_member(organization.id, uid, OrganizationRole::Member, &conn).unwrap();
+    }
+    let collaborators = Team::create(organization.id, "Core", true, &conn).unwrap();
+    Team::add_member(collaborators.id, collaborator_uid, &conn).unwrap();
+    let readers = Team::create(organization.id, "Docs", false, &conn).unwrap();
+    Team::add_member(readers.id, member_uid, &conn).unwrap();
+
+    let role = |uid| Organization::get_package_role(organization.id, uid, &conn).unwrap();
+    assert_eq!(role(admin_uid), Some(Role::Owner));
+    assert_eq!(role(collaborator_uid), Some(Role::Collaborator));
+    assert_eq!(role(member_uid), None);
+    assert_eq!(role(outsider_uid), None);
+
+    let pid = setup_package("package1", "", outsider_uid);
+    Organization::set_package_organization(pid, Some(organization.id), &conn).unwrap();
+    let package = Package::get(pid, &DB_POOL).unwrap();
+    assert_eq!(package.organization_id, Some(organization.id));
+    assert_eq!(
+        PackageCollaborator::get_role(&package, admin_uid, &conn).unwrap(),
+        Role::Owner
+    );
+    assert_eq!(
+        PackageCollaborator::get_role(&package, collaborator_uid, &conn).unwrap(),
+        Role::Collaborator
+    );
+    assert_eq!(
+        PackageCollaborator::get_role(&package, outsider_uid, &conn).unwrap(),
+        Role::Owner
+    );
+    assert!(PackageCollaborator::get_role(&package, member_uid, &conn).is_err());
+}
@@ -0,0 +1,93 @@
+use diesel::prelude::*;
+use diesel::{Identifiable, Queryable};
+use jelly::chrono::{DateTime, Utc};
+use jelly::serde::{Deserialize, Serialize};
+use jelly::DieselPgConnection;
+use jelly::Result;
+
+use crate::schema::{team_members, teams};
+
+#[cfg(test)]
+mod tests;
+
+/// A group of members of an organization. Members of a collaborator team are
+/// collaborators on every package of the organization.
+#[derive(Debug, Serialize, Deserialize, Queryable, Identifiable)]
+#[table_name = "teams"]
+pub struct Team {
+    pub id: i32,
+    pub organization_id: i32,
+    pub name: String,
+    pub slug: String,
+    pub is_collaborator: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Team {
+    pub fn create(
+        organization_id: i32,
+        name: &str,
+        is_collaborator: bool,
+        conn: &DieselPgConnection,
+    ) -> Result<Self> {
+        Ok(diesel::insert_into(teams::table)
+            .values((
+                teams::organization_id.eq(organization_id),
+                teams::name.eq(name),
+                teams::slug.eq(slug::slugify(name)),
+                teams::is_collaborator.eq(is_collaborator),
+            ))
+            .get_result::<Self>(conn)?)
+    }
+
+    pub fn get_by_slug(
+        organization_id: i32,
+        team_slug: &str,
+        conn: &DieselPgConnection,
+    ) -> Result<Self> {
+        Ok(teams::table
+            .filter(
+                teams::organization_id
+                    .eq(organization_id)
+                    .and(teams::slug.eq(team_slug)),
+            )
+            .first::<Self>(conn)?)
+    }
+
+    pub fn get_by_organization_id(
+        organization_id: i32,
+        conn: &DieselPgConnection,
+    ) -> Result<Vec<Self>> {
+        Ok(teams::table
+            .filter(teams::organization_id.eq(organization_id))
+            .order(teams::name.asc())
+            .load::<Self>(conn)?)
+    }
+
+    pub fn add_member(team_id: i32, account_id: i32, conn: &DieselPgConnection) -> Result<()> {
+        diesel::insert_into(team_members::table)
+            .values((
+                team_members::team_id.eq(team_id),
+                team_members::account_id.eq(account_id),
+            ))
+            .execute(conn)?;
+
+        Ok(())
+    }
+
+    pub fn remove_member(
+        team_id: i32,
+        account_id: i32,
+        conn: &DieselPgConnection,
+    ) -> Result<usize> {
+        Ok(diesel::delete(team_members::table.find((team_id, account_id))).execute(conn)?)
+    }
+
+    pub fn get_member_ids(team_id: i32, conn: &DieselPgConnection) -> Result<Vec<i32>> {
+        Ok(team_members::table
+            .filter(team_members::team_id.eq(team_id))
+            .select(team_members::account_id)
+            .order(team_members::created_at.asc())
+            .load::<i32>(conn)?)
+    }
+}
@@ -0,0 +1,37 @@
+use super::*;
+use crate::organizations::models::organization::Organization;
+use crate::test::util::setup_user;
+use crate::test::{DatabaseTestContext, DB_POOL};
+
+#[actix_rt::test]
+async fn team_members_work() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let uid = setup_user(None, None);
+    let conn = DB_POOL.get().unwrap();
+    let organization = Organization::create("Move Labs", "", uid, &conn).unwrap();
+
+    let team = Team::create(organization.id, "Core Team", true, &conn).unwrap();
+    assert_eq!(team.slug, "core-team");
+    assert!(team.is_collaborator);
+    assert!(Team::create(organization.id, "core team", false, &conn).is_err());
+    assert_eq!(
+        Team::get_by_slug(organization.id, "core-team", &conn)
+            .unwrap()
+            .id,
+        team.id
+    );
+    assert_eq!(
+        Team::get_by_organization_id(organization.id, &conn)
+            .unwrap()
+            .len(),
+        1
+    );
+
+    Team::add_member(team.id, uid, &conn).unwrap();
+    assert!(Team::add_member(team.id, uid, &conn).is_err());
+    assert_eq!(Team::get_member_ids(team.id, &conn).unwrap(), vec![uid]);
+    assert_eq!(Team::remove_member(team.id, uid, &conn).unwrap(), 1);
+    assert_eq!(Team::remove_member(team.id, uid, &conn).unwrap(), 0);
+}
@@ -0,0 +1,38 @@
+use crate::organizations::models::organization::Organization;
+use crate::package_collaborators::models::owner_invitation::OwnerInvitation;
+use crate::packages::models::package_event::EventActor;
+use crate::utils::request_utils::ClientIp;
+use jelly::actix_web::web::Path;
+use jelly::prelude::*;
+use jelly::Result;
+
+pub async fn accept_invite_with_token(
+    request: HttpRequest,
+    client_ip: ClientIp,
+    Path(token): Path<String>,
+) -> Result<HttpResponse> {
+    let conn = request.db_pool()?.get()?;
+    let invitation = OwnerInvitation::find_by_token(&token, &conn)?;
+    let organization_id = invitation.organization_id.ok_or_else(|| {
+        Error::Generic(format!(
+            "Not an organization invitation. id: {}",
+            invitation.id
+        ))
+    })?;
+    if invitation.is_expired() {
+        return request.render(410, "accounts/invalid_token.html", Context::new());
+    }
+    // The token of the invitation stands for the invited user
+    let actor = EventActor {
+        account_id: Some(invitation.invited_user_id),
+        api_token_id: None,
+        ip_address: client_ip.0,
+        system_actor: None,
+    };
+    if let Err(e) = invitation.accept(&actor, &conn) {
+        warn!("accept organization invite failed, error: {:?}", e);
+        return request.render(503, "503.html", Context::new());
+    }
+    let organization = Organization::get(organization_id, &conn)?;
+    request.redirect(&format!("/organizations/{}", organization.slug))
+}
@@ -1,3 +1,4 @@
 pub mod external_invitation;
 pub mod owner_invitation;
+pub mod package_claim;
 pub mod package_collaborator;
@@ -1,26 +1,33 @@
 #[cfg(test)]
 mod tests;
 
-use crate::package_collaborators::package_collaborator::{PackageCollaborator, Role};
+use crate::organizations::models::organization::{Organization, OrganizationRole};
+use crate::package_collaborators::package_collaborator::PackageCollaborator;
+use crate::packages::models::package_event::{EventActor, PackageEvent, PackageEventKind};
 use crate::schema;
-use crate::schema::{accounts, owner_invitations, package_collaborators, packages};
+use crate::schema::{accounts, owner_invitations, packages};
 use crate::utils::token::SecureToken;
 use diesel::dsl::now;
 use diesel::pg::expression::extensions::IntervalDsl;
 use diesel::prelude::*;
 use diesel::{Identifiable, Insertable, Queryable};
 use jelly::chrono::{NaiveDateTime, Utc};
+use jelly::error::Error;
 use jelly::Result;
 use jelly::{chrono, DieselPgConnection};
 use serde::Serialize;
 use std::env;
 
+/// An invitation to become an owner of a package, or to join an organization with
+/// `organization_role`. Exactly one of `package_id` and `organization_id` is set.
 #[derive(Clone, Debug, Eq, Identifiable, Queryable)]
-#[primary_key(invited_user_id, package_id)]
 pub struct OwnerInvitation {
+    pub id: i32,
     pub invited_user_id: i32,
     pub invited_by_user_id: i32,
-    pub package_id: i32,
+    pub package_id: Option<i32>,
+    pub organization_id: Option<i32>,
+    pub organization_role: Option<i32>,
     pub token: String,
     pub is_transferring: bool,
     pub created_at: NaiveDateTime,
@@ -41,6 +48,8 @@ impl PartialEq for OwnerInvitation {
         self.invited_user_id == other.invited_user_id
             && self.invited_by_user_id == other.invited_by_user_id
             && self.package_id == other.package_id
+            && self.organization_id == other.organization_id
+            && self.organization_role == other.organization_role
             && SecureToken::hash(&self.token) == other.token
             && self.created_at == other.created_at
     }
@@ -60,7 +69,9 @@ impl PartialEq for OwnerInvitationQuery {
 struct NewRecord {
     invited_user_id: i32,
     invited_by_user_id: i32,
-    package_id: i32,
+    package_id: Option<i32>,
+    organization_id: Option<i32>,
+    organization_role: Option<i32>,
     token: String,
     is_transferring: bool,
     created_at: Option<NaiveDateTime>,
@@ -75,6 +86,45 @@ impl OwnerInvitation {
         created_at: Option<NaiveDateTime>,
         conn: &DieselPgConnection,
     ) -> Result<Self> {
+        Self::insert(
+            NewRecord {
+                invited_user_id,
+                invited_by_user_id,
+                package_id: Some(package_id),
+                organization_id: None,
+                organization_role: None,
+                token: String::new(),
+                is_transferring: is_transferring.unwrap_or(false),
+                created_at,
+            },
+            conn,
+        )
+    }
+
+    pub fn create_for_organization(
+        invited_user_id: i32,
+        invited_by_user_id: i32,
+        organization_id: i32,
+        role: OrganizationRole,
+        created_at: Option<NaiveDateTime>,
+        conn: &DieselPgConnection,
+    ) -> Result<Self> {
+        Self::insert(
+            NewRecord {
+                invited_user_id,
+                invited_by_user_id,
+                package_id: None,
+                organization_id: Some(organization_id),
+                organization_role: Some(role as i32),
+                token: String::new(),
+                is_transferring: false,
+                created_at,
+            },
+            conn,
+        )
+    }
+
+    fn insert(mut record: NewRecord, conn: &DieselPgConnection) -> Result<Self> {
         // Before actually creating the invite, check if an expired invitation already exists
         // and delete it from the database. This allows obtaining a new invite if the old one
         // expired, instead of returning "already exists".
@@ -82,7 +132,11 @@ impl OwnerInvitation {
             // This does a SELECT FOR UPDATE + DELETE instead of a DELETE with a WHERE clause to
             // use the model's `is_expired` method, centralizing our expiration checking logic.
             let existing: Option<OwnerInvitation> = owner_invitations::table
-                .find((invited_user_id, package_id))
+                .filter(owner_invitations::invited_user_id.eq(record.invited_user_id))
+                .filter(owner_invitations::package_id.is_not_distinct_from(record.package_id))
+                .filter(
+                    owner_invitations::organization_id.is_not_distinct_from(record.organization_id),
+                )
                 .for_update()
                 .first(conn)
                 .optional()?;
@@ -96,15 +150,9 @@ impl OwnerInvitation {
         })?;
 
         let secure_token = SecureToken::generate();
+        record.token = secure_token.inner.sha256;
         let mut res: OwnerInvitation = diesel::insert_into(owner_invitations::table)
-            .values(&NewRecord {
-                invited_user_id,
-                invited_by_user_id,
-                package_id,
-                token: secure_token.inner.sha256,
-                is_transferring: is_transferring.unwrap_or(false),
-                created_at,
-            })
+            .values(&record)
             // The ON CONFLICT DO NOTHING clause results in not creating the invite if another one
             // already exists. This does not cause problems with expired invitation as those are
             // deleted before doing this INSERT.
@@ -128,7 +176,8 @@ impl OwnerInvitation {
         conn: &DieselPgConnection,
     ) -> Result<Self> {
         Ok(owner_invitations::table
-            .find((invited_user_id, package_id))
+            .filter(owner_invitations::invited_user_id.eq(invited_user_id))
+            .filter(owner_invitations::package_id.eq(package_id))
             .first::<Self>(conn)?)
     }
 
@@ -147,13 +196,15 @@ impl OwnerInvitation {
         Ok(owner_invitations::table
             .filter(owner_invitations::invited_user_id.eq(invited_user_id))
             .filter(owner_invitations::created_at.gt(now - expiration_days.days()))
-            .inner_join(packages::table.on(owner_invitations::package_id.eq(packages::id)))
+            .inner_join(
+                packages::table.on(owner_invitations::package_id.eq(packages::id.nullable())),
+            )
             .inner_join(accounts::table.on(owner_invitations::invited_by_user_id.eq(accounts::id)))
             .select((
                 owner_invitations::invited_user_id,
                 owner_invitations::invited_by_user_id,
                 accounts::email,
-                owner_invitations::package_id,
+                packages::id,
                 packages::name,
                 owner_invitations::is_transferring,
             ))
@@ -182,27 +233,64 @@ impl OwnerInvitation {
         Ok(no_deleted_rows)
     }
 
-    pub fn accept(&self, conn: &DieselPgConnection) -> Result<()> {
-        if self.is_transferring {
-            diesel::update(package_collaborators::table)
-                .set(package_collaborators::role.eq(Role::Collaborator as i32))
-                .filter(package_collaborators::account_id.eq(self.invited_by_user_id))
-                .execute(conn)?;
-            diesel::update(package_collaborators::table)
-                .set(package_collaborators::role.eq(Role::Owner as i32))
-                .filter(package_collaborators::account_id.eq(self.invited_user_id))
-                .execute(conn)?;
-            self.delete(conn)?
-        } else {
-            PackageCollaborator::new_collaborator(
-                self.package_id,
-                self.invited_user_id,
-                self.invited_by_user_id,
+    pub fn delete_by_organization_id(
+        invited_user_id: i32,
+        organization_id: i32,
+        conn: &DieselPgConnection,
+    ) -> Result<usize> {
+        Ok(diesel::delete(
+            owner_invitations::table
+                .filter(owner_invitations::invited_user_id.eq(invited_user_id))
+                .filter(owner_invitations::organization_id.eq(organization_id)),
+        )
+        .execute(conn)?)
+    }
+
+    /// Accepts the invitation on behalf of the invited user. Invitations to a package are
+    /// recorded in its audit log with `actor`.
+    pub fn accept(&self, actor: &EventActor, conn: &DieselPgConnection) -> Result<()> {
+        conn.transaction(|| {
+            if let Some(organization_id) = self.organization_id {
+                Organization::add_member(
+                    organization_id,
+                    self.invited_user_id,
+                    self.organization_role
+                        .map_or(OrganizationRole::Member, OrganizationRole::from_i32),
+                    conn,
+                )?;
+                return self.delete(conn);
+            }
+            let package_id = self.package_id.ok_or_else(|| {
+                Error::Generic(format!("Invitation without a target. id: {}", self.id))
+            })?;
+            let kind = if self.is_transferring {
+                PackageCollaborator::transfer_ownership(
+                    package_id,
+                    self.invited_by_user_id,
+                    self.invited_user_id,
+                    conn,
+                )?;
+                PackageEventKind::OwnershipTransferred
+            } else {
+                PackageCollaborator::new_collaborator(
+                    package_id,
+                    self.invited_user_id,
+                    self.invited_by_user_id,
+                    conn,
+                )?;
+                PackageEventKind::InvitationAccepted
+            };
+            self.delete(conn)?;
+            PackageEvent::create(
+                package_id,
+                kind,
+                actor,
+                Some(self.invited_user_id),
+                "",
                 conn,
             )?;
-            self.delete(conn)?
-        }
-        Ok(())
+            Ok(())
+        })
     }
 
     pub fn is_expired(&self) -> bool {
@@ -218,7 +306,7 @@ impl OwnerInvitation {
         self.created_at + days
     }
 
-    fn get_expiration_days() -> i64 {
+    pub(crate) fn get_expiration_days() -> i64 {
         let expiration_days = env::var("OWNERSHIP_INVITATIONS_EXPIRATION_DAYS")
             .expect("OWNERSHIP_INVITATIONS_EXPIRATION_DAYS not set!");
         expiration_days
@@ -1,9 +1,12 @@
+use crate::organizations::models::organization::{Organization, OrganizationRole};
 use crate::package_collaborators::models::owner_invitation::OwnerInvitation;
 use crate::package_collaborators::package_collaborator::{PackageCollaborator, Role};
+use crate::packages::models::package_event::EventActor;
 use crate::packages::Package;
 use crate::test::util::setup_user;
 use crate::test::{DatabaseTestContext, DB_POOL};
 use crate::utils::token::TOKEN_LENGTH;
+use jelly::chrono::{Duration, Utc};
 use jelly::prelude::*;
 use std::env;
 
@@ -64,14 +67,14 @@ async fn find_by_id_works() {
     let owner_invitation1 = setup_invitation(None);
     let owner_invitation2 = OwnerInvitation::find_by_id(
         owner_invitation1.invited_user_id,
-        owner_invitation1.package_id,
+        owner_invitation1.package_id.unwrap(),
         &conn,
     )
     .unwrap();
     assert_eq!(owner_invitation1, owner_invitation2);
     let not_found = OwnerInvitation::find_by_id(
         owner_invitation1.invited_by_user_id,
-        owner_invitation1.package_id,
+        owner_invitation1.package_id.unwrap(),
         &conn,
     );
     assert!(not_found.is_err());
@@ -89,13 +92,13 @@ async fn accept_collaborator_works() {
 
     let owner_invitation = setup_invitation(None);
     let package_collaborator = PackageCollaborator::get(
-        owner_invitation.package_id,
+        owner_invitation.package_id.unwrap(),
         owner_invitation.invited_user_id,
         &DB_POOL.get().unwrap(),
     );
     assert!(package_collaborator.is_err());
 
-    owner_invitation.accept(&conn).unwrap();
+    owner_invitation.accept(&EventActor::default(), &conn).unwrap();
     let not_found = OwnerInvitation::find_by_token(&owner_invitation.token, &conn);
     assert!(not_found.is_err());
     if let Err(Error::Database(diesel::NotFound)) = not_found {
@@ -104,7 +107,7 @@ async fn accept_collaborator_works() {
     }
 
     let package_collaborator = PackageCollaborator::get(
-        owner_invitation.package_id,
+        owner_invitation.package_id.unwrap(),
         owner_invitation.invited_user_id,
         &DB_POOL.get().unwrap(),
     )
@@ -120,20 +123,20 @@ async fn accept_owner_works() {
 
     let owner_invitation = setup_invitation(Some(true));
     let _ = PackageCollaborator::new_collaborator(
-        owner_invitation.package_id,
+        owner_invitation.package_id.unwrap(),
         owner_invitation.invited_user_id,
         owner_invitation.invited_user_id,
         &DB_POOL.get().unwrap(),
     );
     let invited_collaborator = PackageCollaborator::get(
-        owner_invitation.package_id,
+        owner_invitation.package_id.unwrap(),
         owner_invitation.invited_user_id,
         &DB_POOL.get().unwrap(),
     )
     .unwrap();
     assert_eq!(invited_collaborator.role, Role::Collaborator as i32);
 
-    owner_invitation.accept(&conn).unwrap();
+    owner_invitation.accept(&EventActor::default(), &conn).unwrap();
     let not_found = OwnerInvitation::find_by_token(&owner_invitation.token, &conn);
     assert!(not_found.is_err());
     if let Err(Error::Database(diesel::NotFound)) = not_found {
@@ -142,7 +145,7 @@ async fn accept_owner_works() {
     }
 
     let package_collaborator = PackageCollaborator::get(
-        owner_invitation.package_id,
+        owner_invitation.package_id.unwrap(),
         owner_invitation.invited_user_id,
         &DB_POOL.get().unwrap(),
     )
@@ -150,6 +153,32 @@ async fn accept_owner_works() {
     assert_eq!(package_collaborator.role, Role::Owner as i32);
 }
 
+#[actix_rt::test]
+async fn accept_owner_keeps_other_owners() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+    let conn = DB_POOL.get().unwrap();
+
+    let owner_invitation = setup_invitation(Some(true));
+    let pid = owner_invitation.package_id.unwrap();
+    let co_owner_uid = setup_user(Some("email2@mail.com".to_string()), None);
+    for uid in [owner_invitation.invited_user_id, co_owner_uid] {
+        PackageCollaborator::new_collaborator(pid, uid, owner_invitation.invited_by_user_id, &conn)
+            .unwrap();
+    }
+    PackageCollaborator::promote_to_owner(pid, co_owner_uid, &conn).unwrap();
+
+    owner_invitation.accept(&EventActor::default(), &conn).unwrap();
+    let mut owner_ids = PackageCollaborator::get_owner_ids(pid, &conn).unwrap();
+    owner_ids.sort_unstable();
+    let mut expected_ids = vec![owner_invitation.invited_user_id, co_owner_uid];
+    expected_ids.sort_unstable();
+    assert_eq!(owner_ids, expected_ids);
+    let previous_owner =
+        PackageCollaborator::get(pid, owner_invitation.invited_by_user_id, &conn).unwrap();
+    assert_eq!(previous_owner.role, Role::Collaborator as i32);
+}
+
 #[actix_rt::test]
 async fn delete_works() {
     crate::test::init();
@@ -225,7 +254,7 @@ async fn create_new_invitation_if_existing_one_is_expired() {
     let owner_invitation = OwnerInvitation::create(
         owner_invitation.invited_user_id,
         owner_invitation.invited_by_user_id,
-        owner_invitation.package_id,
+        owner_invitation.package_id.unwrap(),
         None,
         None,
         &conn,
@@ -247,7 +276,7 @@ async fn not_create_new_invitation_if_it_already_exists() {
     OwnerInvitation::create(
         owner_invitation.invited_user_id,
         owner_invitation.invited_by_user_id,
-        owner_invitation.package_id,
+        owner_invitation.package_id.unwrap(),
         None,
         None,
         &conn,
@@ -271,7 +300,10 @@ async fn find_by_invited_account_works() {
         invited_account[0].invited_user_id,
         owner_invitation.invited_user_id
     );
-    assert_eq!(invited_account[0].package_id, owner_invitation.package_id);
+    assert_eq!(
+        Some(invited_account[0].package_id),
+        owner_invitation.package_id
+    );
     assert_eq!(
         invited_account[0].invited_by_user_id,
         owner_invitation.invited_by_user_id
@@ -307,14 +339,14 @@ async fn delete_by_id_works() {
 
     let res = OwnerInvitation::delete_by_id(
         owner_invitation.invited_user_id,
-        owner_invitation.package_id,
+        owner_invitation.package_id.unwrap(),
         &conn,
     )
     .unwrap();
     assert_eq!(res, 1);
     let not_found = OwnerInvitation::find_by_id(
         owner_invitation.invited_user_id,
-        owner_invitation.package_id,
+        owner_invitation.package_id.unwrap(),
         &conn,
     );
     assert!(not_found.is_err());
@@ -323,3 +355,87 @@ async fn delete_by_id_works() {
         panic!()
     }
 }
+
+#[actix_rt::test]
+async fn accept_organization_invitation_adds_member() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let admin_uid = setup_user(None, None);
+    let invited_uid = setup_user(Some("invited@host.com".to_string()), None);
+    let conn = DB_POOL.get().unwrap();
+    let organization = Organization::create("Move Labs", "", admin_uid, &conn).unwrap();
+
+    env::set_var("OWNERSHIP_INVITATIONS_EXPIRATION_DAYS", "1");
+    let invitation = OwnerInvitation::create_for_organization(
+        invited_uid,
+        admin_uid,
+        organization.id,
+        OrganizationRole::Admin,
+        None,
+        &conn,
+    )
+    .unwrap();
+    assert!(!invitation.is_expired());
+    assert_eq!(invitation.package_id, None);
+    // A pending invitation isn't replaced
+    assert!(OwnerInvitation::create_for_organization(
+        invited_uid,
+        admin_uid,
+        organization.id,
+        OrganizationRole::Member,
+        None,
+        &conn
+    )
+    .is_err());
+    // Only package invitations are listed in the settings of the invited user
+    assert!(OwnerInvitation::find_by_invited_account(invited_uid, &conn)
+        .unwrap()
+        .is_empty());
+
+    let found = OwnerInvitation::find_by_token(&invitation.token, &conn).unwrap();
+    found.accept(&EventActor::default(), &conn).unwrap();
+    let member = Organization::get_member(organization.id, invited_uid, &conn).unwrap();
+    assert_eq!(member.role, OrganizationRole::Admin as i32);
+    assert!(OwnerInvitation::find_by_token(&invitation.token, &conn).is_err());
+}
+
+#[actix_rt::test]
+async fn expired_organization_invitation_is_replaced() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let admin_uid = setup_user(None, None);
+    let invited_uid = setup_user(Some("invited@host.com".to_string()), None);
+    let conn = DB_POOL.get().unwrap();
+    let organization = Organization::create("Move Labs", "", admin_uid, &conn).unwrap();
+
+    env::set_var("OWNERSHIP_INVITATIONS_EXPIRATION_DAYS", "1");
+    let created_at = Utc::now().naive_utc() - Duration::days(2);
+    let expired = OwnerInvitation::create_for_organization(
+        invited_uid,
+        admin_uid,
+        organization.id,
+        OrganizationRole::Member,
+        Some(created_at),
+        &conn,
+    )
+    .unwrap();
+    assert!(expired.is_expired());
+
+    let invitation = OwnerInvitation::create_for_organization(
+        invited_uid,
+        admin_uid,
+        organization.id,
+        OrganizationRole::Member,
+        None,
+        &conn,
+    )
+    .unwrap();
+    assert!(!invitation.is_expired());
+    assert!(OwnerInvitation::find_by_token(&expired.token, &conn).is_err());
+    assert_eq!(
+        OwnerInvitation::delete_by_organization_id(invited_uid, organization.id, &conn).unwrap(),
+        1
+    );
+}
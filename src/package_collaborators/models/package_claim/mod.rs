@@ -0,0 +1,83 @@
+use diesel::prelude::*;
+use diesel::{Identifiable, Queryable};
+use jelly::chrono::{DateTime, Utc};
+use jelly::serde::{Deserialize, Serialize};
+use jelly::DieselPgConnection;
+use jelly::Result;
+
+use crate::package_collaborators::package_collaborator::PackageCollaborator;
+use crate::schema::{package_claims, packages};
+use crate::utils::token::{generate_secure_alphanumeric_string, TOKEN_LENGTH};
+
+#[cfg(test)]
+mod tests;
+
+/// File at the root of a repository that claim tokens are committed to, one per line.
+pub const CLAIM_FILE_PATH: &str = ".movey-claim";
+
+/// A user's attempt to become the owner of a crawled package.
+#[derive(Debug, Serialize, Deserialize, Queryable, Identifiable)]
+#[table_name = "package_claims"]
+pub struct PackageClaim {
+    pub id: i32,
+    pub package_id: i32,
+    pub account_id: i32,
+    pub token: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl PackageClaim {
+    /// The claim of the account on the package, created with a new token on first use.
+    pub fn get_or_create(
+        package_id_: i32,
+        account_id_: i32,
+        conn: &DieselPgConnection,
+    ) -> Result<Self> {
+        diesel::insert_into(package_claims::table)
+            .values((
+                package_claims::package_id.eq(package_id_),
+                package_claims::account_id.eq(account_id_),
+                package_claims::token.eq(generate_secure_alphanumeric_string(TOKEN_LENGTH)),
+            ))
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+
+        Self::get(package_id_, account_id_, conn)
+    }
+
+    pub fn get(package_id_: i32, account_id_: i32, conn: &DieselPgConnection) -> Result<Self> {
+        Ok(package_claims::table
+            .filter(
+                package_claims::package_id
+                    .eq(package_id_)
+                    .and(package_claims::account_id.eq(account_id_)),
+            )
+            .first::<Self>(conn)?)
+    }
+
+    /// Whether the content of the claim file lists the token of this claim.
+    pub fn is_listed_in(&self, claim_file: &str) -> bool {
+        claim_file.lines().any(|line| line.trim() == self.token)
+    }
+
+    /// Makes the account the owner of a package that has none yet. Returns `false`
+    /// when the package was claimed already.
+    pub fn claim(package_id_: i32, account_id_: i32, conn: &DieselPgConnection) -> Result<bool> {
+        conn.transaction(|| {
+            // Locks the package so that two claims can't both succeed
+            packages::table
+                .find(package_id_)
+                .select(packages::id)
+                .for_update()
+                .first::<i32>(conn)?;
+            if !PackageCollaborator::get_by_package_id(package_id_, conn)?.is_empty() {
+                return Ok(false);
+            }
+            PackageCollaborator::new_owner(package_id_, account_id_, account_id_, conn)?;
+            diesel::delete(package_claims::table.filter(package_claims::package_id.eq(package_id_)))
+                .execute(conn)?;
+
+            Ok(true)
+        })
+    }
+}
@@ -0,0 +1,93 @@
+use super::*;
+use crate::github_service::GithubRepoData;
+use crate::package_collaborators::package_collaborator::Role;
+use crate::packages::Package;
+use crate::test::util::setup_user;
+use crate::test::{DatabaseTestContext, DB_POOL};
+
+fn create_crawled_package() -> i32 {
+    let github_data = GithubRepoData {
+        name: "MoveStdlib".to_string(),
+        version: "0.1.0".to_string(),
+        readme_content: "".to_string(),
+        license: "".to_string(),
+        description: "".to_string(),
+        size: 0,
+        stars_count: 0,
+        forks_count: 0,
+        url: "".to_string(),
+        rev: "".to_string(),
+        dependencies: vec![],
+        addresses: vec![],
+    };
+    Package::create_from_crawled_data(
+        "https://github.com/move-language/move",
+        "",
+        "",
+        0,
+        0,
+        None,
+        github_data,
+        &DB_POOL,
+    )
+    .unwrap()
+    .id
+}
+
+#[actix_rt::test]
+async fn get_or_create_keeps_the_token() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let uid = setup_user(None, None);
+    let pid = create_crawled_package();
+    let conn = DB_POOL.get().unwrap();
+
+    let claim = PackageClaim::get_or_create(pid, uid, &conn).unwrap();
+    assert_eq!(claim.token.len(), TOKEN_LENGTH);
+    assert_eq!(
+        PackageClaim::get_or_create(pid, uid, &conn).unwrap().token,
+        claim.token
+    );
+
+    let other_uid = setup_user(Some("other@host.com".to_string()), None);
+    let other_claim = PackageClaim::get_or_create(pid, other_uid, &conn).unwrap();
+    assert_ne!(other_claim.token, claim.token);
+}
+
+#[actix_rt::test]
+async fn is_listed_in_works() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let uid = setup_user(None, None);
+    let pid = create_crawled_package();
+    let claim = PackageClaim::get_or_create(pid, uid, &DB_POOL.get().unwrap()).unwrap();
+
+    assert!(claim.is_listed_in(&claim.token));
+    assert!(claim.is_listed_in(&format!("other token\n  {}  \n", claim.token)));
+    assert!(!claim.is_listed_in(""));
+    assert!(!claim.is_listed_in(&claim.token[1..]));
+}
+
+#[actix_rt::test]
+async fn claim_makes_the_first_claimer_owner() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let uid = setup_user(None, None);
+    let other_uid = setup_user(Some("other@host.com".to_string()), None);
+    let pid = create_crawled_package();
+    let conn = DB_POOL.get().unwrap();
+    PackageClaim::get_or_create(pid, uid, &conn).unwrap();
+    PackageClaim::get_or_create(pid, other_uid, &conn).unwrap();
+
+    assert!(PackageClaim::claim(pid, uid, &conn).unwrap());
+    let collaborator = PackageCollaborator::get(pid, uid, &conn).unwrap();
+    assert_eq!(collaborator.role, Role::Owner as i32);
+    // Tokens of other claims are no use anymore
+    assert!(PackageClaim::get(pid, other_uid, &conn).is_err());
+
+    assert!(!PackageClaim::claim(pid, other_uid, &conn).unwrap());
+    assert!(PackageCollaborator::get(pid, other_uid, &conn).is_err());
+}
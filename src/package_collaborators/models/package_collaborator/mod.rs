@@ -1,11 +1,13 @@
 use diesel::prelude::*;
-use diesel::{Associations, Insertable, Queryable};
+use diesel::{AsChangeset, Associations, Insertable, Queryable};
 use jelly::chrono::{DateTime, Utc};
 use jelly::serde::{Deserialize, Serialize};
 use jelly::DieselPgConnection;
 use jelly::Result;
+use std::fmt;
 
 use crate::accounts::Account;
+use crate::organizations::models::organization::Organization;
 use crate::packages::Package;
 use crate::schema;
 use crate::schema::package_collaborators;
@@ -24,14 +26,56 @@ pub struct PackageCollaborator {
     pub role: i32,
     pub created_by: i32,
     pub created_at: DateTime<Utc>,
+    pub can_publish: bool,
+    pub can_manage_collaborators: bool,
+    pub can_invite: bool,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
 pub enum Role {
     Owner = 0,
     Collaborator = 1,
 }
 
+/// Actions on a package that owners can always take and collaborators only when allowed to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum Permission {
+    /// Upload new versions
+    Publish,
+    /// Remove collaborators. Only owners change permissions.
+    ManageCollaborators,
+    /// Invite new collaborators
+    Invite,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, AsChangeset)]
+#[table_name = "package_collaborators"]
+pub struct CollaboratorPermissions {
+    pub can_publish: bool,
+    pub can_manage_collaborators: bool,
+    pub can_invite: bool,
+}
+
+/// Lists the granted permissions, as shown in the audit log of the package.
+impl fmt::Display for CollaboratorPermissions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let granted: Vec<&str> = [
+            (self.can_publish, "publish"),
+            (self.can_invite, "invite"),
+            (self.can_manage_collaborators, "manage collaborators"),
+        ]
+        .iter()
+        .filter(|(is_granted, _)| *is_granted)
+        .map(|(_, name)| *name)
+        .collect();
+        if granted.is_empty() {
+            write!(f, "none")
+        } else {
+            write!(f, "{}", granted.join(", "))
+        }
+    }
+}
+
 #[derive(Insertable)]
 #[table_name = "package_collaborators"]
 pub struct NewCollaborator {
@@ -84,15 +128,189 @@ impl PackageCollaborator {
             .first::<Self>(conn)?)
     }
 
+    /// Role of the account on the package, either as one of its collaborators or as a
+    /// member of the organization that owns it.
+    pub fn get_role(package: &Package, account_id: i32, conn: &DieselPgConnection) -> Result<Role> {
+        if let Some(collaborator) = package_collaborators::table
+            .find((package.id, account_id))
+            .first::<Self>(conn)
+            .optional()?
+        {
+            return Ok(if collaborator.role == Role::Owner as i32 {
+                Role::Owner
+            } else {
+                Role::Collaborator
+            });
+        }
+        if let Some(organization_id) = package.organization_id {
+            if let Some(role) = Organization::get_package_role(organization_id, account_id, conn)? {
+                return Ok(role);
+            }
+        }
+
+        Err(diesel::NotFound.into())
+    }
+
+    pub fn allows(&self, permission: Permission) -> bool {
+        self.role == Role::Owner as i32
+            || match permission {
+                Permission::Publish => self.can_publish,
+                Permission::ManageCollaborators => self.can_manage_collaborators,
+                Permission::Invite => self.can_invite,
+            }
+    }
+
+    pub fn permissions(&self) -> CollaboratorPermissions {
+        CollaboratorPermissions {
+            can_publish: self.allows(Permission::Publish),
+            can_manage_collaborators: self.allows(Permission::ManageCollaborators),
+            can_invite: self.allows(Permission::Invite),
+        }
+    }
+
+    /// Whether the account may take the action on the package, either as one of its
+    /// collaborators or as a member of the organization that owns it. Members of the
+    /// organization's collaborator teams may publish. Packages can require two-factor
+    /// authentication for all of these.
+    pub fn has_permission(
+        package: &Package,
+        account_id: i32,
+        permission: Permission,
+        conn: &DieselPgConnection,
+    ) -> Result<bool> {
+        if !package.meets_two_factor_requirement(account_id, conn)? {
+            return Ok(false);
+        }
+        if let Some(collaborator) = package_collaborators::table
+            .find((package.id, account_id))
+            .first::<Self>(conn)
+            .optional()?
+        {
+            if collaborator.allows(permission) {
+                return Ok(true);
+            }
+        }
+        if let Some(organization_id) = package.organization_id {
+            return Ok(
+                match Organization::get_package_role(organization_id, account_id, conn)? {
+                    Some(Role::Owner) => true,
+                    Some(Role::Collaborator) => permission == Permission::Publish,
+                    None => false,
+                },
+            );
+        }
+
+        Ok(false)
+    }
+
+    /// Changes the permissions of a collaborator. Owners keep all of them.
+    pub fn update_permissions(
+        package_id_: i32,
+        account_id_: i32,
+        permissions: &CollaboratorPermissions,
+        conn: &DieselPgConnection,
+    ) -> Result<usize> {
+        Ok(diesel::update(
+            package_collaborators::table.filter(
+                package_collaborators::package_id
+                    .eq(package_id_)
+                    .and(package_collaborators::account_id.eq(account_id_))
+                    .and(package_collaborators::role.eq(Role::Collaborator as i32)),
+            ),
+        )
+        .set(permissions)
+        .execute(conn)?)
+    }
+
     pub fn get_by_package_id(package_id: i32, conn: &DieselPgConnection) -> Result<Vec<i32>> {
         Ok(package_collaborators::table
             .filter(package_collaborators::package_id.eq(package_id))
             .select(package_collaborators::account_id)
-            // First element is the owner of package
+            // Owners come first. A package can have several of them, use `get_owner_ids`
+            // to tell them apart from collaborators.
             .order(package_collaborators::role.asc())
             .load::<i32>(conn)?)
     }
 
+    /// Owners of the package, the earliest one first.
+    pub fn get_owner_ids(package_id: i32, conn: &DieselPgConnection) -> Result<Vec<i32>> {
+        Ok(package_collaborators::table
+            .filter(
+                package_collaborators::package_id
+                    .eq(package_id)
+                    .and(package_collaborators::role.eq(Role::Owner as i32)),
+            )
+            .select(package_collaborators::account_id)
+            .order(package_collaborators::created_at.asc())
+            .load::<i32>(conn)?)
+    }
+
+    /// Makes a collaborator one more owner of the package.
+    pub fn promote_to_owner(
+        package_id_: i32,
+        account_id_: i32,
+        conn: &DieselPgConnection,
+    ) -> Result<usize> {
+        Ok(diesel::update(
+            package_collaborators::table.filter(
+                package_collaborators::package_id
+                    .eq(package_id_)
+                    .and(package_collaborators::account_id.eq(account_id_))
+                    .and(package_collaborators::role.eq(Role::Collaborator as i32)),
+            ),
+        )
+        .set(package_collaborators::role.eq(Role::Owner as i32))
+        .execute(conn)?)
+    }
+
+    /// Hands the ownership of `from_owner_id` over to the collaborator `to_account_id`,
+    /// who stays a collaborator of the package. Other owners are left untouched.
+    pub fn transfer_ownership(
+        package_id_: i32,
+        from_owner_id: i32,
+        to_account_id: i32,
+        conn: &DieselPgConnection,
+    ) -> Result<()> {
+        conn.transaction(|| {
+            diesel::update(package_collaborators::table.find((package_id_, from_owner_id)))
+                .set(package_collaborators::role.eq(Role::Collaborator as i32))
+                .execute(conn)?;
+            diesel::update(package_collaborators::table.find((package_id_, to_account_id)))
+                .set(package_collaborators::role.eq(Role::Owner as i32))
+                .execute(conn)?;
+            Ok(())
+        })
+    }
+
+    /// Removes an owner from the package. Nothing is deleted if they are its last owner, so
+    /// that a claimed package always keeps one.
+    pub fn delete_owner_by_id(
+        account_id_: i32,
+        package_id_: i32,
+        conn: &DieselPgConnection,
+    ) -> Result<usize> {
+        conn.transaction(|| {
+            // Lock the owners so that two of them can't leave at the same time
+            let owner_ids = package_collaborators::table
+                .filter(
+                    package_collaborators::package_id
+                        .eq(package_id_)
+                        .and(package_collaborators::role.eq(Role::Owner as i32)),
+                )
+                .select(package_collaborators::account_id)
+                .for_update()
+                .load::<i32>(conn)?;
+            if owner_ids.len() < 2 || !owner_ids.contains(&account_id_) {
+                return Ok(0);
+            }
+
+            Ok(
+                diesel::delete(package_collaborators::table.find((package_id_, account_id_)))
+                    .execute(conn)?,
+            )
+        })
+    }
+
     pub fn get_in_bulk_order_by_role(
         package_id: i32,
         account_ids: Vec<i32>,
@@ -1,6 +1,10 @@
-use crate::package_collaborators::package_collaborator::PackageCollaborator;
+use crate::organizations::models::organization::{Organization, OrganizationRole};
+use crate::organizations::models::team::Team;
+use crate::package_collaborators::package_collaborator::{
+    CollaboratorPermissions, PackageCollaborator, Permission,
+};
 use crate::packages::Package;
-use crate::test::util::setup_user;
+use crate::test::util::{setup_two_factor, setup_user};
 use crate::test::{DatabaseTestContext, DB_POOL};
 use jelly::prelude::*;
 
@@ -93,3 +97,182 @@ async fn delete_collaborator_by_id_works() {
     let res = PackageCollaborator::delete_collaborator_by_id(owner_id, pid, &conn).unwrap();
     assert_eq!(res, 0);
 }
+
+#[actix_rt::test]
+async fn new_collaborator_has_no_permissions_by_default() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let (pid, uid) = setup_collaborator();
+    let conn = DB_POOL.get().unwrap();
+    let package = Package::get(pid, &DB_POOL).unwrap();
+    let collaborator = PackageCollaborator::get(pid, uid, &conn).unwrap();
+    assert!(!collaborator.allows(Permission::Publish));
+    assert!(!collaborator.allows(Permission::Invite));
+    assert!(!collaborator.allows(Permission::ManageCollaborators));
+    assert!(
+        !PackageCollaborator::has_permission(&package, uid, Permission::Publish, &conn).unwrap()
+    );
+
+    let owner_id = collaborator.created_by;
+    assert!(
+        PackageCollaborator::has_permission(&package, owner_id, Permission::Publish, &conn)
+            .unwrap()
+    );
+}
+
+#[actix_rt::test]
+async fn packages_can_require_two_factor_authentication() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let (pid, uid) = setup_collaborator();
+    let conn = DB_POOL.get().unwrap();
+    let permissions = CollaboratorPermissions {
+        can_publish: true,
+        can_manage_collaborators: true,
+        can_invite: true,
+    };
+    PackageCollaborator::update_permissions(pid, uid, &permissions, &conn).unwrap();
+    let owner_id = PackageCollaborator::get(pid, uid, &conn)
+        .unwrap()
+        .created_by;
+    Package::set_require_two_factor(pid, true, &conn).unwrap();
+
+    let package = Package::get(pid, &DB_POOL).unwrap();
+    for account_id in [uid, owner_id] {
+        assert!(!package.meets_two_factor_requirement(account_id, &conn).unwrap());
+        assert!(
+            !PackageCollaborator::has_permission(&package, account_id, Permission::Publish, &conn)
+                .unwrap()
+        );
+    }
+
+    setup_two_factor(uid);
+    assert!(package.meets_two_factor_requirement(uid, &conn).unwrap());
+    assert!(
+        PackageCollaborator::has_permission(&package, uid, Permission::Publish, &conn).unwrap()
+    );
+    assert!(PackageCollaborator::has_permission(
+        &package,
+        uid,
+        Permission::ManageCollaborators,
+        &conn
+    )
+    .unwrap());
+    assert!(
+        !PackageCollaborator::has_permission(&package, owner_id, Permission::Invite, &conn)
+            .unwrap()
+    );
+
+    Package::set_require_two_factor(pid, false, &conn).unwrap();
+    let package = Package::get(pid, &DB_POOL).unwrap();
+    assert!(
+        PackageCollaborator::has_permission(&package, owner_id, Permission::Invite, &conn)
+            .unwrap()
+    );
+}
+
+#[actix_rt::test]
+async fn update_permissions_works() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let (pid, uid) = setup_collaborator();
+    let conn = DB_POOL.get().unwrap();
+    let permissions = CollaboratorPermissions {
+        can_publish: true,
+        can_manage_collaborators: false,
+        can_invite: false,
+    };
+    let res = PackageCollaborator::update_permissions(pid, uid, &permissions, &conn).unwrap();
+    assert_eq!(res, 1);
+
+    let collaborator = PackageCollaborator::get(pid, uid, &conn).unwrap();
+    assert_eq!(collaborator.permissions(), permissions);
+    let package = Package::get(pid, &DB_POOL).unwrap();
+    assert!(
+        PackageCollaborator::has_permission(&package, uid, Permission::Publish, &conn).unwrap()
+    );
+    assert!(
+        !PackageCollaborator::has_permission(&package, uid, Permission::Invite, &conn).unwrap()
+    );
+
+    // Owners always keep all of their permissions
+    let owner_id = collaborator.created_by;
+    let res = PackageCollaborator::update_permissions(pid, owner_id, &permissions, &conn).unwrap();
+    assert_eq!(res, 0);
+}
+
+#[actix_rt::test]
+async fn organization_collaborator_teams_can_publish() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let (pid, _) = setup_collaborator();
+    let admin_id = setup_user(Some("admin@host.com".to_string()), None);
+    let member_id = setup_user(Some("member@host.com".to_string()), None);
+    let conn = DB_POOL.get().unwrap();
+    let organization = Organization::create("Move Labs", "", admin_id, &conn).unwrap();
+    Organization::add_member(organization.id, member_id, OrganizationRole::Member, &conn).unwrap();
+    let team = Team::create(organization.id, "Core", true, &conn).unwrap();
+    Team::add_member(team.id, member_id, &conn).unwrap();
+    Organization::set_package_organization(pid, Some(organization.id), &conn).unwrap();
+
+    let package = Package::get(pid, &DB_POOL).unwrap();
+    assert!(
+        PackageCollaborator::has_permission(&package, member_id, Permission::Publish, &conn)
+            .unwrap()
+    );
+    assert!(
+        !PackageCollaborator::has_permission(&package, member_id, Permission::Invite, &conn)
+            .unwrap()
+    );
+    assert!(PackageCollaborator::has_permission(
+        &package,
+        admin_id,
+        Permission::ManageCollaborators,
+        &conn
+    )
+    .unwrap());
+}
+
+#[actix_rt::test]
+async fn packages_can_have_several_owners() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let (pid, uid) = setup_collaborator();
+    let conn = DB_POOL.get().unwrap();
+    let owner_id = PackageCollaborator::get(pid, uid, &conn)
+        .unwrap()
+        .created_by;
+    assert_eq!(
+        PackageCollaborator::get_owner_ids(pid, &conn).unwrap(),
+        vec![owner_id]
+    );
+
+    // The last owner can't leave
+    let res = PackageCollaborator::delete_owner_by_id(owner_id, pid, &conn).unwrap();
+    assert_eq!(res, 0);
+
+    let res = PackageCollaborator::promote_to_owner(pid, uid, &conn).unwrap();
+    assert_eq!(res, 1);
+    assert_eq!(
+        PackageCollaborator::get_owner_ids(pid, &conn).unwrap(),
+        vec![owner_id, uid]
+    );
+    let package = Package::get(pid, &DB_POOL).unwrap();
+    assert!(
+        PackageCollaborator::has_permission(&package, uid, Permission::Publish, &conn).unwrap()
+    );
+
+    let res = PackageCollaborator::delete_owner_by_id(owner_id, pid, &conn).unwrap();
+    assert_eq!(res, 1);
+    assert_eq!(
+        PackageCollaborator::get_owner_ids(pid, &conn).unwrap(),
+        vec![uid]
+    );
+    let res = PackageCollaborator::delete_owner_by_id(uid, pid, &conn).unwrap();
+    assert_eq!(res, 0);
+}
@@ -1,5 +1,7 @@
 use jelly::actix_web::web::{get, resource, scope, ServiceConfig};
 
+use crate::utils::new_auth;
+
 pub mod models;
 pub mod views;
 
@@ -13,10 +15,27 @@ pub fn configure(config: &mut ServiceConfig) {
                 resource("/{package_slug}/versions")
                     .route(get().to(views::controller::show_package_versions)),
             )
+            .service(
+                resource("/{package_slug}/dependencies")
+                    .route(get().to(views::controller::show_package_dependencies)),
+            )
+            .service(
+                resource("/{package_slug}/dependents")
+                    .route(get().to(views::controller::show_package_dependents)),
+            )
             .service(
                 resource("/{package_slug}/collaborators")
                     .route(get().to(views::controller::show_package_settings)),
             )
+            .service(
+                resource("/{package_slug}/activity")
+                    .route(get().to(views::controller::show_package_activity)),
+            )
+            .service(
+                resource("/{package_slug}/claim")
+                    .wrap(new_auth())
+                    .route(get().to(views::controller::show_package_claim)),
+            )
             .service(resource("/search").route(get().to(views::controller::show_search_results)))
             .service(resource("/owned").route(get().to(views::controller::show_owned_packages)))
             .service(resource("/{package_slug}").route(get().to(views::controller::show_package)))
@@ -2,7 +2,7 @@ extern crate slug;
 
 use crate::sql::lower;
 
-use diesel::dsl::{count, now, sum};
+use diesel::dsl::{count, exists, now};
 use diesel::prelude::*;
 use diesel::sql_types::{Integer, Text, Timestamptz};
 use diesel::{AsChangeset, Identifiable, Insertable, Queryable};
@@ -11,27 +11,39 @@ use diesel_full_text_search::{plainto_tsquery, TsVectorExtensions};
 
 use diesel::result::Error::NotFound;
 use diesel::result::{DatabaseErrorKind, Error as DBError};
-use jelly::chrono::{DateTime, NaiveDateTime, Utc};
+use jelly::chrono::{DateTime, Utc};
 use jelly::error::Error;
 use jelly::serde::{Deserialize, Serialize};
 use jelly::{DieselPgConnection, DieselPgPool};
 
+use crate::accounts::models::account_two_factor::AccountTwoFactor;
 use crate::github_service::GithubRepoData;
-use crate::package_collaborators::package_collaborator::{PackageCollaborator, Role};
+use crate::package_collaborators::package_collaborator::{PackageCollaborator, Permission, Role};
 use jelly::Result;
+use named_address::{parse_address_query, NamedAddress};
+use package_dependency::PackageDependency;
+use package_download::PackageDownload;
 use mockall_double::double;
 use rayon::prelude::*;
 
+pub mod named_address;
+pub mod package_dependency;
+pub mod package_download;
+pub mod package_event;
+pub mod package_webhook;
+
 #[cfg(test)]
 mod tests;
 
 #[double]
 use crate::github_service::GithubService;
 use crate::schema::package_collaborators;
+use crate::schema::package_version_addresses;
 use crate::schema::package_versions;
 use crate::schema::package_versions::dsl::*;
 use crate::schema::packages;
 use crate::schema::packages::dsl::*;
+use crate::source_hosts;
 use crate::utils::paginate::LoadPaginated;
 use crate::utils::token::generate_secure_alphanumeric_string;
 
@@ -52,6 +64,9 @@ pub struct Package {
     pub slug: String,
     pub stars_count: i32,
     pub forks_count: i32,
+    pub latest_version_id: Option<i32>,
+    pub organization_id: Option<i32>,
+    pub require_two_factor: bool,
 }
 
 type PackageColumns = (
@@ -65,6 +80,9 @@ type PackageColumns = (
     packages::slug,
     packages::stars_count,
     packages::forks_count,
+    packages::latest_version_id,
+    packages::organization_id,
+    packages::require_two_factor,
 );
 
 pub const PACKAGE_COLUMNS: PackageColumns = (
@@ -78,6 +96,9 @@ pub const PACKAGE_COLUMNS: PackageColumns = (
     packages::slug,
     packages::stars_count,
     packages::forks_count,
+    packages::latest_version_id,
+    packages::organization_id,
+    packages::require_two_factor,
 );
 
 #[derive(Debug, Serialize, Deserialize, QueryableByName, Queryable)]
@@ -91,9 +112,9 @@ pub struct PackageSearchResult {
     #[sql_type = "Integer"]
     pub total_downloads_count: i32,
     #[sql_type = "Timestamptz"]
-    pub created_at: NaiveDateTime,
+    pub created_at: DateTime<Utc>,
     #[sql_type = "Timestamptz"]
-    pub updated_at: NaiveDateTime,
+    pub updated_at: DateTime<Utc>,
     #[sql_type = "Text"]
     pub slug: String,
     #[sql_type = "Integer"]
@@ -195,6 +216,7 @@ pub struct PackageVersion {
     pub rev: Option<String>,
     pub total_files: Option<i32>,
     pub total_size: Option<i32>,
+    pub yanked: bool,
 }
 
 #[derive(Insertable)]
@@ -264,17 +286,11 @@ impl Package {
     ) -> Result<Package> {
         let conn = pool.get()?;
         conn.transaction(|| -> Result<Package> {
-            let (record, package_owner_id) =
+            let (record, package_owner_ids) =
                 match Package::get_by_name_and_repo_url(&github_data.name, repo_url, &conn) {
                     Ok(package) => {
-                        let collaborators =
-                            PackageCollaborator::get_by_package_id(package.id, &conn)?;
-                        let owner_id = if collaborators.len() > 0 {
-                            Some(collaborators[0])
-                        } else {
-                            None
-                        };
-                        (package, owner_id)
+                        let owner_ids = PackageCollaborator::get_owner_ids(package.id, &conn)?;
+                        (package, owner_ids)
                     }
                     Err(_) => {
                         let mut new_package = NewPackage {
@@ -317,6 +333,7 @@ impl Package {
                             }
                         }
                         let inserted_record = insert_result.unwrap();
+                        PackageDependency::resolve_pending(&inserted_record, &conn)?;
                         if account_id_.is_some() {
                             PackageCollaborator::new_owner(
                                 inserted_record.id,
@@ -325,17 +342,31 @@ impl Package {
                                 &conn,
                             )?;
                         }
-                        (inserted_record, account_id_)
+                        (inserted_record, account_id_.into_iter().collect())
                     }
                 };
 
-            // Only creates new version if same user with package owner
-            if package_owner_id == account_id_ {
+            // Only creates new version if the user is one of the package owners, or a user
+            // allowed to publish it
+            let can_publish = match account_id_ {
+                Some(account_id) => {
+                    (package_owner_ids.contains(&account_id)
+                        || PackageCollaborator::has_permission(
+                            &record,
+                            account_id,
+                            Permission::Publish,
+                            &conn,
+                        )?)
+                        && record.meets_two_factor_requirement(account_id, &conn)?
+                }
+                None => package_owner_ids.is_empty(),
+            };
+            if can_publish {
                 let package_version_not_exist = record.get_version(&github_data.version, &conn);
                 if package_version_not_exist.is_err() {
                     let e = package_version_not_exist.unwrap_err();
                     if let Error::Database(NotFound) = e {
-                        PackageVersion::create(
+                        let package_version = PackageVersion::create(
                             record.id,
                             github_data.version,
                             github_data.readme_content,
@@ -346,6 +377,16 @@ impl Package {
                             None,
                             &conn,
                         )?;
+                        PackageDependency::create_for_version(
+                            package_version.id,
+                            &github_data.dependencies,
+                            &conn,
+                        )?;
+                        NamedAddress::create_for_version(
+                            package_version.id,
+                            &github_data.addresses,
+                            &conn,
+                        )?;
                     } else {
                         return Err(e);
                     }
@@ -425,22 +466,25 @@ impl Package {
     pub fn get_badge_info(
         package_name: &str,
         pool: &DieselPgPool,
-    ) -> Result<Vec<(String, i32, String, i32)>> {
+    ) -> Result<Vec<(String, i32, String, i32, bool)>> {
         let connection = pool.get()?;
 
-        let result: Vec<(String, i32, String, i32)> = packages::table
+        let result: Vec<(String, i32, String, i32, bool)> = packages::table
             .inner_join(package_versions::table)
             .filter(lower(packages::name).eq(package_name.to_lowercase()))
             .filter(diesel::dsl::sql(
-                "TRUE GROUP BY packages.name, packages.total_downloads_count, package_versions.version, package_versions.downloads_count",
+                "TRUE GROUP BY packages.name, packages.total_downloads_count, package_versions.version, package_versions.downloads_count, package_versions.id, packages.latest_version_id",
             ))
             .select((
                 packages::name,
                 packages::total_downloads_count,
                 package_versions::version,
                 package_versions::downloads_count,
+                diesel::dsl::sql::<diesel::sql_types::Bool>(
+                    "COALESCE(package_versions.id = packages.latest_version_id, FALSE)",
+                ),
             ))
-            .load::<(String, i32, String, i32)>(&connection)?;
+            .load::<(String, i32, String, i32, bool)>(&connection)?;
 
         Ok(result)
     }
@@ -451,9 +495,8 @@ impl Package {
         let result = packages
             .inner_join(package_collaborators::table)
             .filter(package_collaborators::account_id.eq(owner_id))
-            .inner_join(package_versions::table)
-            .select((packages::id, packages::name, packages::description, packages::total_downloads_count, packages::created_at, packages::updated_at, packages::slug, packages::stars_count, packages::forks_count, diesel::dsl::sql::<diesel::sql_types::Text>("max(version) as version")))
-            .filter(diesel::dsl::sql("TRUE GROUP BY packages.id, name, description, total_downloads_count, packages.created_at, packages.updated_at, packages.slug, packages.stars_count, packages.forks_count")) // workaround since diesel 1.x doesn't support GROUP_BY dsl yet
+            .inner_join(package_versions::table.on(packages::latest_version_id.eq(package_versions::id.nullable())))
+            .select((packages::id, packages::name, packages::description, packages::total_downloads_count, packages::created_at, packages::updated_at, packages::slug, packages::stars_count, packages::forks_count, package_versions::version))
             .load::<PackageSearchResult>(&connection)?;
 
         Ok(result)
@@ -481,15 +524,56 @@ impl Package {
         let result: (Vec<PackageSearchResult>, i64, i64) = packages::table
             .inner_join(package_collaborators::table)
             .filter(package_collaborators::account_id.eq(owner_id).and(package_collaborators::role.eq(Role::Owner as i32)))
-            .inner_join(package_versions::table)
-            .select((packages::id, packages::name, packages::description, packages::total_downloads_count, packages::created_at, packages::updated_at, packages::slug, packages::stars_count, packages::forks_count, diesel::dsl::sql::<diesel::sql_types::Text>("max(version) as version")))
-            .filter(diesel::dsl::sql("TRUE GROUP BY packages.id, name, description, total_downloads_count, packages.created_at, packages.updated_at, packages.slug, packages.stars_count, packages.forks_count")) // workaround since diesel 1.x doesn't support GROUP_BY dsl yet
+            .inner_join(package_versions::table.on(packages::latest_version_id.eq(package_versions::id.nullable())))
+            .select((packages::id, packages::name, packages::description, packages::total_downloads_count, packages::created_at, packages::updated_at, packages::slug, packages::stars_count, packages::forks_count, package_versions::version))
             .order(diesel::dsl::sql::<diesel::sql_types::Text>(&order_query))
             .load_with_pagination(&connection, Some(page), Some(per_page))?;
 
         Ok(result)
     }
 
+    pub fn get_by_organization_paginated(
+        organization_id_: i32,
+        sort_field: &PackageSortField,
+        sort_order: &PackageSortOrder,
+        page: Option<i64>,
+        per_page: Option<i64>,
+        conn: &DieselPgConnection,
+    ) -> Result<(Vec<PackageSearchResult>, i64, i64)> {
+        let field = sort_field.to_column_name();
+        let order = sort_order.to_order_direction();
+        let order_query = format!("packages.{} {}", field, order);
+
+        let page = page.unwrap_or(1);
+        let per_page = per_page.unwrap_or(PACKAGES_PER_PAGE);
+        if page < 1 || per_page < 1 {
+            return Err(Error::Generic(String::from("Invalid page number.")));
+        }
+
+        let result: (Vec<PackageSearchResult>, i64, i64) = packages::table
+            .filter(packages::organization_id.eq(organization_id_))
+            .inner_join(
+                package_versions::table
+                    .on(packages::latest_version_id.eq(package_versions::id.nullable())),
+            )
+            .select((
+                packages::id,
+                packages::name,
+                packages::description,
+                packages::total_downloads_count,
+                packages::created_at,
+                packages::updated_at,
+                packages::slug,
+                packages::stars_count,
+                packages::forks_count,
+                package_versions::version,
+            ))
+            .order(diesel::dsl::sql::<diesel::sql_types::Text>(&order_query))
+            .load_with_pagination(conn, Some(page), Some(per_page))?;
+
+        Ok(result)
+    }
+
     pub fn get_downloads(owner_id: i32, pool: &DieselPgPool) -> Result<i64> {
         let connection = pool.get()?;
         let result = packages
@@ -499,7 +583,7 @@ impl Package {
                     .eq(owner_id)
                     .and(package_collaborators::role.eq(Role::Owner as i32)),
             )
-            .select(sum(total_downloads_count))
+            .select(diesel::dsl::sum(total_downloads_count))
             .first::<Option<i64>>(&connection)?;
 
         match result {
@@ -508,6 +592,26 @@ impl Package {
         }
     }
 
+    /// Whether the account may publish or manage collaborators as far as two-factor
+    /// authentication goes: either the package doesn't require it or the account has it.
+    pub fn meets_two_factor_requirement(
+        &self,
+        account_id_: i32,
+        conn: &DieselPgConnection,
+    ) -> Result<bool> {
+        Ok(!self.require_two_factor || AccountTwoFactor::is_enabled(account_id_, conn)?)
+    }
+
+    pub fn set_require_two_factor(
+        package_id_: i32,
+        require_two_factor_: bool,
+        conn: &DieselPgConnection,
+    ) -> Result<usize> {
+        Ok(diesel::update(packages.find(package_id_))
+            .set(require_two_factor.eq(require_two_factor_))
+            .execute(conn)?)
+    }
+
     pub fn get_version(
         &self,
         version_name: &String,
@@ -520,49 +624,119 @@ impl Package {
         Ok(result)
     }
 
-    pub fn change_owner(
-        package_id_: i32,
-        new_owner_id: i32,
+    pub fn get_latest_version(&self, conn: &DieselPgConnection) -> Result<PackageVersion> {
+        let result = package_versions
+            .filter(package_versions::id.nullable().eq(self.latest_version_id))
+            .first::<PackageVersion>(conn)?;
+
+        Ok(result)
+    }
+
+    /// Recomputes `latest_version_id` from every version of the package, see
+    /// `PackageVersion::latest_version_key` for how versions are ranked. The package row
+    /// is locked while the versions are read, so that concurrent publishes and yanks can't
+    /// overwrite each other with a stale latest version.
+    pub fn update_latest_version(package_id_: i32, conn: &DieselPgConnection) -> Result<()> {
+        conn.transaction(|| -> Result<()> {
+            packages
+                .filter(packages::id.eq(package_id_))
+                .select(packages::id)
+                .for_update()
+                .first::<i32>(conn)?;
+            let versions = package_versions
+                .filter(package_id.eq(package_id_))
+                .load::<PackageVersion>(conn)?;
+            let latest_version = versions
+                .iter()
+                .max_by_key(|package_version| package_version.latest_version_key())
+                .map(|package_version| package_version.id);
+
+            diesel::update(packages.filter(packages::id.eq(package_id_)))
+                .set(latest_version_id.eq(latest_version))
+                .execute(conn)?;
+            Ok(())
+        })
+    }
+
+    /// Sets `latest_version_id` of the packages that have versions but no latest version,
+    /// e.g. packages created before the column was added. Returns how many were updated.
+    pub fn backfill_latest_versions(conn: &DieselPgConnection) -> Result<usize> {
+        let package_ids = packages
+            .filter(latest_version_id.is_null())
+            .filter(exists(
+                package_versions.filter(package_versions::package_id.eq(packages::id)),
+            ))
+            .select(packages::id)
+            .load::<i32>(conn)?;
+        for package_id_ in package_ids.iter() {
+            Self::update_latest_version(*package_id_, conn)?;
+        }
+        Ok(package_ids.len())
+    }
+
+    /// Ids of the packages at `subdir` of the repository, whatever the branch in their url.
+    fn ids_for_repository(url: &str, subdir: &str, conn: &DieselPgConnection) -> Result<Vec<i32>> {
+        let https_url = source_hosts::normalize_repo_url(url);
+        let subdir = subdir.trim_matches('/');
+        if subdir.is_empty() {
+            return Ok(packages
+                .filter(repository_url.eq(&https_url))
+                .select(packages::id)
+                .load::<i32>(conn)?);
+        }
+
+        let host = source_hosts::for_url(&https_url);
+        let candidates = packages
+            .filter(repository_url.like(format!("{}/%", https_url)))
+            .select((packages::id, repository_url))
+            .load::<(i32, String)>(conn)?;
+        Ok(candidates
+            .into_iter()
+            .filter(|(_, package_url)| {
+                let (repo_url, package_subdir) = host.split_tree_url(package_url);
+                repo_url == https_url && package_subdir.trim_matches('/') == subdir
+            })
+            .map(|(package_id_, _)| package_id_)
+            .collect())
+    }
+
+    /// The version at `rev` of the package at `subdir` of the repository, if Movey knows it
+    /// already. Unlike `increase_download_count`, this never calls the source host.
+    pub fn find_version_id(
+        url: &str,
+        subdir: &str,
+        rev_: &str,
         conn: &DieselPgConnection,
-    ) -> Result<()> {
-        diesel::update(package_collaborators::table)
-            .filter(
-                package_collaborators::package_id
-                    .eq(package_id_)
-                    .and(package_collaborators::role.eq(Role::Owner as i32)),
-            )
-            .set(package_collaborators::account_id.eq(new_owner_id))
-            .execute(conn)?;
-        Ok(())
+    ) -> Result<Option<i32>> {
+        let package_ids = Self::ids_for_repository(url, subdir, conn)?;
+        Ok(package_versions
+            .filter(package_id.eq_any(package_ids).and(rev.eq(rev_)))
+            .select(package_versions::id)
+            .first::<i32>(conn)
+            .optional()?)
     }
 
+    /// Records a download of the package at `rev` of the repository by the client with the
+    /// given fingerprint, creating a shadow package or version from the repository if needed.
+    /// Returns whether the download will be counted, see `PackageDownload::record`.
     pub fn increase_download_count(
-        url: &String,
+        url: &str,
         rev_: &String,
         subdir: &String,
+        fingerprint: &str,
         service: &GithubService,
         pool: &DieselPgPool,
-    ) -> Result<usize> {
+    ) -> Result<bool> {
         let connection = pool.get()?;
 
-        let mut https_url = url.to_owned();
-        if url.starts_with("git@github.com") {
-            https_url = url
-                .replace(':', "/")
-                .replace("git@", "https://")
-                .replace(".git", "");
-        }
-        if https_url.ends_with(".git") {
-            https_url = https_url[0..https_url.len() - 4].to_string();
-        }
+        let mut https_url = source_hosts::normalize_repo_url(url);
 
-        let package_id_ = packages
-            .filter(repository_url.eq(&https_url))
-            .select(packages::id)
-            .first(&connection);
+        let package_id_ = Self::ids_for_repository(&https_url, subdir, &connection)?
+            .first()
+            .copied();
 
         let package_id_ = match package_id_ {
-            Ok(package_id_) => {
+            Some(package_id_) => {
                 let package_version_id = package_versions
                     .filter(package_id.eq(package_id_).and(rev.eq(rev_)))
                     .select(package_versions::id)
@@ -583,7 +757,7 @@ impl Package {
                             )?
                         };
 
-                        PackageVersion::create(
+                        let package_version = PackageVersion::create(
                             package_id_,
                             github_data.version,
                             github_data.readme_content,
@@ -594,6 +768,16 @@ impl Package {
                             None,
                             &connection,
                         )?;
+                        PackageDependency::create_for_version(
+                            package_version.id,
+                            &github_data.dependencies,
+                            &connection,
+                        )?;
+                        NamedAddress::create_for_version(
+                            package_version.id,
+                            &github_data.addresses,
+                            &connection,
+                        )?;
                     }
                     Err(e) => {
                         return Err(Error::Database(e));
@@ -602,7 +786,7 @@ impl Package {
 
                 package_id_
             }
-            Err(NotFound) => {
+            None => {
                 // Package is not found, creating shadow package and package version
                 let github_data = if subdir.is_empty() {
                     service.fetch_repo_data(&https_url, None, None)?
@@ -615,7 +799,11 @@ impl Package {
                     )?
                 };
                 if !subdir.is_empty() {
-                    https_url = format!("{}/blob/{}/{}", https_url, github_data.rev, subdir);
+                    https_url = source_hosts::for_url(&https_url).tree_url(
+                        &https_url,
+                        &github_data.rev,
+                        subdir,
+                    );
                 }
                 Package::create_from_crawled_data(
                     &https_url,
@@ -629,22 +817,13 @@ impl Package {
                 )?
                 .id
             }
-            Err(e) => {
-                return Err(Error::Database(e));
-            }
         };
 
-        let mut changed_rows = diesel::update(package_versions)
+        let package_version_id = package_versions
             .filter(package_id.eq(package_id_).and(rev.eq(rev_)))
-            .set(downloads_count.eq(downloads_count + 1))
-            .execute(&connection)?;
-
-        changed_rows += diesel::update(packages)
-            .filter(packages::id.eq(package_id_))
-            .set(total_downloads_count.eq(total_downloads_count + 1))
-            .execute(&connection)?;
-
-        Ok(changed_rows)
+            .select(package_versions::id)
+            .first::<i32>(&connection)?;
+        PackageDownload::record(package_version_id, fingerprint, &connection)
     }
 
     pub fn auto_complete_search(
@@ -659,10 +838,9 @@ impl Package {
         let order_query = format!("packages.{} {}", field, order);
 
         let result: Vec<(String, String, String, String, i32, i32)> = packages::table
-            .inner_join(package_versions::table)
+            .inner_join(package_versions::table.on(packages::latest_version_id.eq(package_versions::id.nullable())))
             .filter(name.ilike(format!("%{}%", search_query)))
-            .filter(diesel::dsl::sql("TRUE GROUP BY packages.id, name, description, total_downloads_count, packages.created_at, packages.updated_at, slug"))
-            .select((packages::name, packages::description, diesel::dsl::sql::<diesel::sql_types::Text>("max(version) as version"), packages::slug, packages::stars_count, packages::forks_count))
+            .select((packages::name, packages::description, package_versions::version, packages::slug, packages::stars_count, packages::forks_count))
             .order(diesel::dsl::sql::<diesel::sql_types::Text>(&order_query))
             .load::<(String, String, String, String, i32, i32)>(&connection)?;
 
@@ -681,6 +859,8 @@ impl Package {
         let field = sort_field.to_column_name();
         let order = sort_order.to_order_direction();
         let order_query = format!("packages.{} {}", field, order);
+        // `None` when the query is not a hex address, which never matches any address below
+        let address_query = parse_address_query(search_query);
         let search_query: &str = &search_query.split(' ').collect::<Vec<&str>>().join(" & ");
 
         let page = page.unwrap_or(1);
@@ -690,11 +870,16 @@ impl Package {
         }
 
         let result: (Vec<PackageSearchResult>, i64, i64) = packages::table
-            .inner_join(package_versions::table)
-            .select((packages::id, packages::name, packages::description, packages::total_downloads_count, packages::created_at, packages::updated_at, packages::slug, packages::stars_count, packages::forks_count, diesel::dsl::sql::<diesel::sql_types::Text>("max(version) as version")))
+            .inner_join(package_versions::table.on(packages::latest_version_id.eq(package_versions::id.nullable())))
+            .select((packages::id, packages::name, packages::description, packages::total_downloads_count, packages::created_at, packages::updated_at, packages::slug, packages::stars_count, packages::forks_count, package_versions::version))
             .filter(name.ilike(format!("%{}%", search_query))
-                .or(tsv.matches(plainto_tsquery(search_query))))
-            .filter(diesel::dsl::sql("TRUE GROUP BY packages.id, name, description, total_downloads_count, packages.created_at, packages.updated_at, packages.slug, packages.stars_count, packages.forks_count")) // workaround since diesel 1.x doesn't support GROUP_BY dsl yet
+                .or(tsv.matches(plainto_tsquery(search_query)))
+                .or(package_versions::id.eq_any(
+                    package_version_addresses::table
+                        .filter(package_version_addresses::address.eq(address_query))
+                        .filter(package_version_addresses::is_dev.eq(false))
+                        .select(package_version_addresses::package_version_id),
+                )))
             .order(diesel::dsl::sql::<diesel::sql_types::Text>(&order_query))
             .load_with_pagination(&connection, Some(page), Some(per_page))?;
 
@@ -720,9 +905,8 @@ impl Package {
         }
 
         let result: (Vec<PackageSearchResult>, i64, i64) = packages::table
-            .inner_join(package_versions::table)
-            .select((packages::id, packages::name, packages::description, packages::total_downloads_count, packages::created_at, packages::updated_at, packages::slug, packages::stars_count, packages::forks_count, diesel::dsl::sql::<diesel::sql_types::Text>("max(version) as version")))
-            .filter(diesel::dsl::sql("TRUE GROUP BY packages.id, name, description, total_downloads_count, packages.created_at, packages.updated_at, packages.slug, packages.stars_count, packages.forks_count")) // workaround since diesel 1.x doesn't support GROUP_BY dsl yet
+            .inner_join(package_versions::table.on(packages::latest_version_id.eq(package_versions::id.nullable())))
+            .select((packages::id, packages::name, packages::description, packages::total_downloads_count, packages::created_at, packages::updated_at, packages::slug, packages::stars_count, packages::forks_count, package_versions::version))
             .order(diesel::dsl::sql::<diesel::sql_types::Text>(&order_query))
             .load_with_pagination(&connection, Some(page), Some(per_page))?;
 
@@ -808,10 +992,37 @@ impl PackageVersion {
             .filter(packages::id.eq(version_package_id))
             .set(packages::updated_at.eq(now))
             .execute(conn)?;
+        Package::update_latest_version(version_package_id, conn)?;
+
+        Ok(record)
+    }
+
+    /// Marks a version as yanked (or not). A yanked version can still be fetched
+    /// by its exact `rev`, but is never picked as the latest version of a package.
+    pub fn set_yanked(&self, is_yanked: bool, conn: &DieselPgConnection) -> Result<PackageVersion> {
+        let record = diesel::update(package_versions.filter(package_versions::id.eq(self.id)))
+            .set(yanked.eq(is_yanked))
+            .get_result::<PackageVersion>(conn)?;
+        Package::update_latest_version(self.package_id, conn)?;
 
         Ok(record)
     }
 
+    fn semver(&self) -> Option<semver::Version> {
+        semver::Version::parse(&self.version).ok()
+    }
+
+    /// The latest version of a package is the one with the highest key: versions that
+    /// are not yanked come first, then stable releases over pre-releases, then semver
+    /// precedence. Versions that are not valid semver rank below every valid one.
+    fn latest_version_key(&self) -> (bool, bool, Option<semver::Version>, i32) {
+        let parsed_version = self.semver();
+        let is_stable = parsed_version
+            .as_ref()
+            .is_some_and(|parsed_version| parsed_version.pre.is_empty());
+        (!self.yanked, is_stable, parsed_version, self.id)
+    }
+
     pub fn from_package_id(
         uid: i32,
         sort_type: &PackageVersionSort,
@@ -821,9 +1032,13 @@ impl PackageVersion {
         let versions = package_versions.filter(package_id.eq(uid));
 
         let records = match sort_type {
-            PackageVersionSort::Latest => versions
-                .order_by(package_versions::dsl::id.desc())
-                .load::<PackageVersion>(&connection)?,
+            PackageVersionSort::Latest => {
+                let mut records = versions.load::<PackageVersion>(&connection)?;
+                records.sort_by_cached_key(|package_version| {
+                    std::cmp::Reverse((package_version.semver(), package_version.id))
+                });
+                records
+            }
             PackageVersionSort::Oldest => versions
                 .order_by(package_versions::dsl::id.asc())
                 .load::<PackageVersion>(&connection)?,
@@ -0,0 +1,92 @@
+use diesel::prelude::*;
+use diesel::{Identifiable, Insertable, Queryable};
+use jelly::chrono::{DateTime, Utc};
+use jelly::serde::{Deserialize, Serialize};
+use jelly::DieselPgConnection;
+use jelly::Result;
+
+use crate::github_service::MoveAddress;
+use crate::schema::package_version_addresses;
+
+#[cfg(test)]
+mod tests;
+
+#[derive(Debug, Serialize, Deserialize, Queryable, Identifiable)]
+#[table_name = "package_version_addresses"]
+pub struct NamedAddress {
+    pub id: i32,
+    pub package_version_id: i32,
+    pub name: String,
+    pub address: Option<String>,
+    pub is_dev: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Insertable)]
+#[table_name = "package_version_addresses"]
+pub struct NewNamedAddress {
+    pub package_version_id: i32,
+    pub name: String,
+    pub address: Option<String>,
+    pub is_dev: bool,
+}
+
+impl NamedAddress {
+    pub fn create_for_version(
+        package_version_id_: i32,
+        addresses: &[MoveAddress],
+        conn: &DieselPgConnection,
+    ) -> Result<usize> {
+        if addresses.is_empty() {
+            return Ok(0);
+        }
+        let new_addresses: Vec<NewNamedAddress> = addresses
+            .iter()
+            .map(|address| NewNamedAddress {
+                package_version_id: package_version_id_,
+                name: address.name.clone(),
+                address: address.address.as_deref().map(normalize_address),
+                is_dev: address.is_dev,
+            })
+            .collect();
+
+        Ok(diesel::insert_into(package_version_addresses::table)
+            .values(new_addresses)
+            .execute(conn)?)
+    }
+
+    pub fn get_by_package_version(
+        package_version_id_: i32,
+        conn: &DieselPgConnection,
+    ) -> Result<Vec<NamedAddress>> {
+        Ok(package_version_addresses::table
+            .filter(package_version_addresses::package_version_id.eq(package_version_id_))
+            .order((
+                package_version_addresses::is_dev.asc(),
+                package_version_addresses::name.asc(),
+            ))
+            .load::<NamedAddress>(conn)?)
+    }
+}
+
+/// Hex addresses are stored without leading zeros so that `0x1` and `0x0000...0001` match.
+pub fn normalize_address(address: &str) -> String {
+    let address = address.trim().to_lowercase();
+    match address.strip_prefix("0x") {
+        Some(hex) if !hex.is_empty() && hex.chars().all(|c| c.is_ascii_hexdigit()) => {
+            let hex = hex.trim_start_matches('0');
+            format!("0x{}", if hex.is_empty() { "0" } else { hex })
+        }
+        _ => address,
+    }
+}
+
+/// Returns the normalized address of a search query like `0x1` or `0x1::coin`.
+pub fn parse_address_query(search_query: &str) -> Option<String> {
+    let address = search_query.trim().split("::").next()?;
+    let hex = address.strip_prefix("0x")?;
+    if hex.is_empty() || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    Some(normalize_address(address))
+}
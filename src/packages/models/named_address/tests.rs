@@ -0,0 +1,143 @@
+use super::*;
+use crate::github_service::GithubRepoData;
+use crate::packages::models::{PackageSortField, PackageSortOrder};
+use crate::packages::Package;
+use crate::test::{DatabaseTestContext, DB_POOL};
+
+fn create_package(package_name: &str, repo_url: &str, addresses: Vec<MoveAddress>) -> Package {
+    let github_data = GithubRepoData {
+        name: package_name.to_string(),
+        version: "0.1.0".to_string(),
+        readme_content: "".to_string(),
+        license: "".to_string(),
+        description: "".to_string(),
+        size: 0,
+        stars_count: 0,
+        forks_count: 0,
+        url: "".to_string(),
+        rev: "".to_string(),
+        dependencies: vec![],
+        addresses,
+    };
+    let package =
+        Package::create_from_crawled_data(repo_url, "", "", 0, 0, None, github_data, &DB_POOL)
+            .unwrap();
+    Package::get(package.id, &DB_POOL).unwrap()
+}
+
+fn search(search_query: &str) -> Vec<String> {
+    let (search_result, _, _) = Package::search(
+        search_query,
+        &PackageSortField::Name,
+        &PackageSortOrder::Asc,
+        None,
+        None,
+        &DB_POOL,
+    )
+    .unwrap();
+    search_result
+        .into_iter()
+        .map(|package| package.name)
+        .collect()
+}
+
+#[actix_rt::test]
+async fn create_for_version_works() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let package = create_package(
+        "MoveStdlib",
+        "https://github.com/move-language/move",
+        vec![
+            MoveAddress {
+                name: "std".to_string(),
+                address: Some("0x0000000000000000000000000000000000000001".to_string()),
+                is_dev: false,
+            },
+            MoveAddress {
+                name: "test".to_string(),
+                address: Some("0x42".to_string()),
+                is_dev: true,
+            },
+            MoveAddress {
+                name: "admin".to_string(),
+                address: None,
+                is_dev: false,
+            },
+        ],
+    );
+    let conn = DB_POOL.get().unwrap();
+    let package_version = package.get_latest_version(&conn).unwrap();
+    let named_addresses = NamedAddress::get_by_package_version(package_version.id, &conn).unwrap();
+
+    assert_eq!(named_addresses.len(), 3);
+    assert_eq!(named_addresses[0].name, "admin");
+    assert_eq!(named_addresses[0].address, None);
+    assert_eq!(named_addresses[1].name, "std");
+    assert_eq!(named_addresses[1].address, Some("0x1".to_string()));
+    assert_eq!(named_addresses[2].name, "test");
+    assert!(named_addresses[2].is_dev);
+}
+
+#[actix_rt::test]
+async fn search_finds_packages_by_address() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    create_package(
+        "MoveStdlib",
+        "https://github.com/move-language/move",
+        vec![MoveAddress {
+            name: "std".to_string(),
+            address: Some("0x1".to_string()),
+            is_dev: false,
+        }],
+    );
+    create_package(
+        "TestPackage",
+        "https://github.com/EastAgile/ea-movey",
+        vec![
+            MoveAddress {
+                name: "test".to_string(),
+                address: Some("0xCAFE".to_string()),
+                is_dev: false,
+            },
+            MoveAddress {
+                name: "std".to_string(),
+                address: Some("0x1".to_string()),
+                is_dev: true,
+            },
+        ],
+    );
+
+    assert_eq!(search("0x1"), vec!["MoveStdlib"]);
+    assert_eq!(search("0x00001::coin"), vec!["MoveStdlib"]);
+    assert_eq!(search("0xcafe"), vec!["TestPackage"]);
+    assert!(search("0x2").is_empty());
+}
+
+#[test]
+fn normalize_address_works() {
+    assert_eq!(normalize_address("0x1"), "0x1");
+    assert_eq!(
+        normalize_address("0x0000000000000000000000000000000000000001"),
+        "0x1"
+    );
+    assert_eq!(normalize_address(" 0xCAFE "), "0xcafe");
+    assert_eq!(normalize_address("0x0"), "0x0");
+    assert_eq!(normalize_address("not_an_address"), "not_an_address");
+}
+
+#[test]
+fn parse_address_query_works() {
+    assert_eq!(parse_address_query("0x1"), Some("0x1".to_string()));
+    assert_eq!(parse_address_query("0x1::coin"), Some("0x1".to_string()));
+    assert_eq!(
+        parse_address_query("0x1::coin::Coin"),
+        Some("0x1".to_string())
+    );
+    assert_eq!(parse_address_query("coin"), None);
+    assert_eq!(parse_address_query("0x"), None);
+    assert_eq!(parse_address_query("0xzz"), None);
+}
@@ -0,0 +1,250 @@
+use diesel::prelude::*;
+use diesel::{Identifiable, Insertable, Queryable};
+use jelly::chrono::{DateTime, Utc};
+use jelly::serde::{Deserialize, Serialize};
+use jelly::DieselPgConnection;
+use jelly::Result;
+
+use crate::github_service::MoveDependency;
+use crate::packages::models::PACKAGE_COLUMNS;
+use crate::packages::Package;
+use crate::schema::package_dependencies;
+use crate::schema::package_versions;
+use crate::schema::packages;
+use crate::source_hosts;
+use crate::sql::lower;
+
+#[cfg(test)]
+mod tests;
+
+#[derive(Debug, Serialize, Deserialize, Queryable, Identifiable)]
+#[table_name = "package_dependencies"]
+pub struct PackageDependency {
+    pub id: i32,
+    pub package_version_id: i32,
+    pub name: String,
+    pub kind: i32,
+    pub local: Option<String>,
+    pub git: Option<String>,
+    pub rev: Option<String>,
+    pub subdir: Option<String>,
+    pub movey: Option<String>,
+    pub dependency_package_id: Option<i32>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub enum DependencyKind {
+    Normal = 0,
+    Dev = 1,
+}
+
+#[derive(Insertable)]
+#[table_name = "package_dependencies"]
+pub struct NewPackageDependency {
+    pub package_version_id: i32,
+    pub name: String,
+    pub kind: i32,
+    pub local: Option<String>,
+    pub git: Option<String>,
+    pub rev: Option<String>,
+    pub subdir: Option<String>,
+    pub movey: Option<String>,
+    pub dependency_package_id: Option<i32>,
+}
+
+// A dependency of a package version, along with the Movey package it resolves to (if any)
+#[derive(Debug, Serialize, Queryable)]
+pub struct ResolvedDependency {
+    pub name: String,
+    pub kind: i32,
+    pub local: Option<String>,
+    pub git: Option<String>,
+    pub rev: Option<String>,
+    pub subdir: Option<String>,
+    pub movey: Option<String>,
+    pub package_name: Option<String>,
+    pub package_slug: Option<String>,
+}
+
+#[derive(Debug, Serialize, Queryable)]
+pub struct Dependent {
+    pub name: String,
+    pub slug: String,
+    pub description: String,
+    pub version: String,
+}
+
+impl PackageDependency {
+    pub fn create_for_version(
+        package_version_id_: i32,
+        dependencies: &[MoveDependency],
+        conn: &DieselPgConnection,
+    ) -> Result<usize> {
+        if dependencies.is_empty() {
+            return Ok(0);
+        }
+        let new_dependencies = dependencies
+            .iter()
+            .map(|dependency| {
+                Ok(NewPackageDependency {
+                    package_version_id: package_version_id_,
+                    name: dependency.name.clone(),
+                    kind: if dependency.is_dev {
+                        DependencyKind::Dev as i32
+                    } else {
+                        DependencyKind::Normal as i32
+                    },
+                    local: dependency.local.clone(),
+                    git: dependency.git.clone(),
+                    rev: dependency.rev.clone(),
+                    subdir: dependency.subdir.clone(),
+                    movey: dependency.movey.clone(),
+                    dependency_package_id: Self::resolve(dependency, conn)?,
+                })
+            })
+            .collect::<Result<Vec<NewPackageDependency>>>()?;
+
+        Ok(diesel::insert_into(package_dependencies::table)
+            .values(new_dependencies)
+            .execute(conn)?)
+    }
+
+    // Finds the Movey package a dependency points to. Local dependencies are never resolved.
+    fn resolve(dependency: &MoveDependency, conn: &DieselPgConnection) -> Result<Option<i32>> {
+        if dependency.git.is_none() && dependency.movey.is_none() {
+            return Ok(None);
+        }
+        let movey = dependency.movey.clone().unwrap_or_default();
+        let candidates = packages::table
+            .filter(
+                lower(packages::name)
+                    .eq(dependency.name.to_lowercase())
+                    .or(lower(packages::name).eq(movey.to_lowercase()))
+                    .or(packages::slug.eq(&movey)),
+            )
+            .select(PACKAGE_COLUMNS)
+            .order(packages::id.asc())
+            .load::<Package>(conn)?;
+
+        Ok(candidates
+            .iter()
+            .find(|package| {
+                Self::matches(
+                    dependency.git.as_deref(),
+                    dependency.movey.as_deref(),
+                    package,
+                )
+            })
+            .map(|package| package.id))
+    }
+
+    /// Links dependencies published before `package` existed on Movey to it.
+    pub fn resolve_pending(package: &Package, conn: &DieselPgConnection) -> Result<usize> {
+        let pending = package_dependencies::table
+            .filter(package_dependencies::dependency_package_id.is_null())
+            .filter(
+                lower(package_dependencies::name)
+                    .eq(package.name.to_lowercase())
+                    .or(package_dependencies::movey.eq(&package.slug)),
+            )
+            .load::<PackageDependency>(conn)?;
+        let resolved_ids: Vec<i32> = pending
+            .iter()
+            .filter(|dependency| {
+                Self::matches(
+                    dependency.git.as_deref(),
+                    dependency.movey.as_deref(),
+                    package,
+                )
+            })
+            .map(|dependency| dependency.id)
+            .collect();
+
+        Ok(diesel::update(
+            package_dependencies::table.filter(package_dependencies::id.eq_any(resolved_ids)),
+        )
+        .set(package_dependencies::dependency_package_id.eq(package.id))
+        .execute(conn)?)
+    }
+
+    fn matches(git: Option<&str>, movey: Option<&str>, package: &Package) -> bool {
+        if let Some(movey) = movey {
+            return package.slug == movey || package.name.eq_ignore_ascii_case(movey);
+        }
+        if let Some(git) = git {
+            let git_url = normalize_git_url(git);
+            let repository_url = package.repository_url.to_lowercase();
+            // Packages in a subdirectory are saved as the url of the subdir, e.g.
+            // <repo url>/tree/<rev>/<subdir> on Github
+            return repository_url == git_url
+                || repository_url.starts_with(&format!("{}/", git_url));
+        }
+        false
+    }
+
+    pub fn get_by_package_version(
+        package_version_id_: i32,
+        conn: &DieselPgConnection,
+    ) -> Result<Vec<ResolvedDependency>> {
+        Ok(package_dependencies::table
+            .left_join(packages::table)
+            .filter(package_dependencies::package_version_id.eq(package_version_id_))
+            .select((
+                package_dependencies::name,
+                package_dependencies::kind,
+                package_dependencies::local,
+                package_dependencies::git,
+                package_dependencies::rev,
+                package_dependencies::subdir,
+                package_dependencies::movey,
+                packages::name.nullable(),
+                packages::slug.nullable(),
+            ))
+            .order((
+                package_dependencies::kind.asc(),
+                package_dependencies::name.asc(),
+            ))
+            .load::<ResolvedDependency>(conn)?)
+    }
+
+    pub fn get_by_package_versions(
+        package_version_ids: Vec<i32>,
+        conn: &DieselPgConnection,
+    ) -> Result<Vec<PackageDependency>> {
+        Ok(package_dependencies::table
+            .filter(package_dependencies::package_version_id.eq_any(package_version_ids))
+            .order((
+                package_dependencies::kind.asc(),
+                package_dependencies::name.asc(),
+            ))
+            .load::<PackageDependency>(conn)?)
+    }
+
+    /// Packages whose latest version depends on the given package.
+    pub fn get_dependents(package_id: i32, conn: &DieselPgConnection) -> Result<Vec<Dependent>> {
+        Ok(packages::table
+            .inner_join(
+                package_versions::table
+                    .on(packages::latest_version_id.eq(package_versions::id.nullable())),
+            )
+            .inner_join(
+                package_dependencies::table
+                    .on(package_dependencies::package_version_id.eq(package_versions::id)),
+            )
+            .filter(package_dependencies::dependency_package_id.eq(package_id))
+            .select((
+                packages::name,
+                packages::slug,
+                packages::description,
+                package_versions::version,
+            ))
+            .distinct()
+            .order(packages::name.asc())
+            .load::<Dependent>(conn)?)
+    }
+}
+
+fn normalize_git_url(url: &str) -> String {
+    source_hosts::normalize_repo_url(url).to_lowercase()
+}
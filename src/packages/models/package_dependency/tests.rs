@@ -0,0 +1,212 @@
+use super::*;
+use crate::github_service::GithubRepoData;
+use crate::packages::PackageVersion;
+use crate::test::{DatabaseTestContext, DB_POOL};
+
+fn repo_data(name: &str, version: &str, dependencies: Vec<MoveDependency>) -> GithubRepoData {
+    GithubRepoData {
+        name: name.to_string(),
+        version: version.to_string(),
+        readme_content: "".to_string(),
+        license: "".to_string(),
+        description: "".to_string(),
+        size: 0,
+        stars_count: 0,
+        forks_count: 0,
+        url: "".to_string(),
+        rev: "".to_string(),
+        dependencies,
+        addresses: vec![],
+    }
+}
+
+fn move_stdlib_dependency() -> MoveDependency {
+    MoveDependency {
+        name: "MoveStdlib".to_string(),
+        git: Some("https://github.com/move-language/move.git".to_string()),
+        rev: Some("main".to_string()),
+        subdir: Some("language/move-stdlib".to_string()),
+        ..Default::default()
+    }
+}
+
+fn create_package(repo_url: &str, github_data: GithubRepoData) -> Package {
+    let package =
+        Package::create_from_crawled_data(repo_url, "", "", 0, 0, None, github_data, &DB_POOL)
+            .unwrap();
+    Package::get(package.id, &DB_POOL).unwrap()
+}
+
+#[actix_rt::test]
+async fn create_for_version_resolves_git_dependencies() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let move_stdlib = create_package(
+        "https://github.com/move-language/move/blob/main/language/move-stdlib",
+        repo_data("MoveStdlib", "0.1.0", vec![]),
+    );
+    let package = create_package(
+        "https://github.com/EastAgile/ea-movey",
+        repo_data(
+            "Movey",
+            "0.1.0",
+            vec![
+                move_stdlib_dependency(),
+                MoveDependency {
+                    name: "TestHelpers".to_string(),
+                    is_dev: true,
+                    local: Some("../test-helpers".to_string()),
+                    ..Default::default()
+                },
+            ],
+        ),
+    );
+
+    let conn = DB_POOL.get().unwrap();
+    let package_version = package.get_latest_version(&conn).unwrap();
+    let dependencies =
+        PackageDependency::get_by_package_version(package_version.id, &conn).unwrap();
+    assert_eq!(dependencies.len(), 2);
+    assert_eq!(dependencies[0].name, "MoveStdlib");
+    assert_eq!(dependencies[0].kind, DependencyKind::Normal as i32);
+    assert_eq!(
+        dependencies[0].package_slug.as_ref().unwrap(),
+        &move_stdlib.slug
+    );
+    assert_eq!(dependencies[1].name, "TestHelpers");
+    assert_eq!(dependencies[1].kind, DependencyKind::Dev as i32);
+    assert_eq!(dependencies[1].local.as_ref().unwrap(), "../test-helpers");
+    assert!(dependencies[1].package_slug.is_none());
+
+    let dependents = PackageDependency::get_dependents(move_stdlib.id, &conn).unwrap();
+    assert_eq!(dependents.len(), 1);
+    assert_eq!(dependents[0].slug, package.slug);
+    assert_eq!(dependents[0].version, "0.1.0");
+}
+
+#[actix_rt::test]
+async fn create_for_version_resolves_movey_dependencies() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let registry_package = create_package(
+        "https://github.com/EastAgile/registry-package",
+        repo_data("RegistryPackage", "0.1.0", vec![]),
+    );
+    let package = create_package(
+        "https://github.com/EastAgile/ea-movey",
+        repo_data(
+            "Movey",
+            "0.1.0",
+            vec![MoveDependency {
+                name: "Registry".to_string(),
+                movey: Some(registry_package.slug.clone()),
+                ..Default::default()
+            }],
+        ),
+    );
+
+    let conn = DB_POOL.get().unwrap();
+    let package_version = package.get_latest_version(&conn).unwrap();
+    let dependencies =
+        PackageDependency::get_by_package_version(package_version.id, &conn).unwrap();
+    assert_eq!(dependencies.len(), 1);
+    assert_eq!(
+        dependencies[0].package_name.as_ref().unwrap(),
+        "RegistryPackage"
+    );
+}
+
+#[actix_rt::test]
+async fn resolve_pending_links_dependencies_published_earlier() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let package = create_package(
+        "https://github.com/EastAgile/ea-movey",
+        repo_data("Movey", "0.1.0", vec![move_stdlib_dependency()]),
+    );
+    let conn = DB_POOL.get().unwrap();
+    let package_version = package.get_latest_version(&conn).unwrap();
+    let dependencies =
+        PackageDependency::get_by_package_version(package_version.id, &conn).unwrap();
+    assert!(dependencies[0].package_slug.is_none());
+
+    // A package with the same name from another repository is not a match
+    let other_stdlib = create_package(
+        "https://github.com/someone/move-stdlib",
+        repo_data("MoveStdlib", "0.1.0", vec![]),
+    );
+    assert!(PackageDependency::get_dependents(other_stdlib.id, &conn)
+        .unwrap()
+        .is_empty());
+
+    let move_stdlib = create_package(
+        "https://github.com/move-language/move/blob/main/language/move-stdlib",
+        repo_data("MoveStdlib", "0.2.0", vec![]),
+    );
+    let dependencies =
+        PackageDependency::get_by_package_version(package_version.id, &conn).unwrap();
+    assert_eq!(
+        dependencies[0].package_slug.as_ref().unwrap(),
+        &move_stdlib.slug
+    );
+    let dependents = PackageDependency::get_dependents(move_stdlib.id, &conn).unwrap();
+    assert_eq!(dependents.len(), 1);
+    assert_eq!(dependents[0].name, "Movey");
+}
+
+#[actix_rt::test]
+async fn get_dependents_only_counts_latest_versions() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let move_stdlib = create_package(
+        "https://github.com/move-language/move/blob/main/language/move-stdlib",
+        repo_data("MoveStdlib", "0.1.0", vec![]),
+    );
+    let package = create_package(
+        "https://github.com/EastAgile/ea-movey",
+        repo_data("Movey", "0.1.0", vec![move_stdlib_dependency()]),
+    );
+    let conn = DB_POOL.get().unwrap();
+    assert_eq!(
+        PackageDependency::get_dependents(move_stdlib.id, &conn)
+            .unwrap()
+            .len(),
+        1
+    );
+
+    PackageVersion::create(
+        package.id,
+        "0.2.0".to_string(),
+        "".to_string(),
+        "".to_string(),
+        "".to_string(),
+        0,
+        0,
+        None,
+        &conn,
+    )
+    .unwrap();
+    assert!(PackageDependency::get_dependents(move_stdlib.id, &conn)
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn normalize_git_url_works() {
+    assert_eq!(
+        normalize_git_url("https://github.com/move-language/move.git"),
+        "https://github.com/move-language/move"
+    );
+    assert_eq!(
+        normalize_git_url("git@github.com:Move-Language/move.git"),
+        "https://github.com/move-language/move"
+    );
+    assert_eq!(
+        normalize_git_url("https://github.com/move-language/move/"),
+        "https://github.com/move-language/move"
+    );
+}
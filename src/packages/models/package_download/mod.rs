@@ -0,0 +1,139 @@
+use diesel::prelude::*;
+use diesel::sql_types::{Integer, Timestamptz, Varchar};
+use jelly::chrono::{Duration, Utc};
+use jelly::DieselPgConnection;
+use jelly::Result;
+use sha2::{Digest, Sha256};
+
+use crate::schema::{package_downloads, shadow_package_requests};
+
+#[cfg(test)]
+mod tests;
+
+/// A client downloading the same version again within this window isn't counted again.
+pub const DEDUP_WINDOW_HOURS: i64 = 24;
+
+/// A shadow package is requested again if its job didn't finish within this window, e.g.
+/// because the job was lost.
+pub const SHADOW_PACKAGE_REQUEST_MINUTES: i64 = 60;
+
+// Records the download unless the client already downloaded the version within the window.
+const RECORD_QUERY: &str = "INSERT INTO package_downloads (package_version_id, fingerprint)
+SELECT $1, $2
+WHERE NOT EXISTS (
+    SELECT 1 FROM package_downloads
+    WHERE package_version_id = $1 AND fingerprint = $2 AND created_at > $3
+)";
+
+// Adds the downloads not counted yet to the counters of their versions and packages, in a
+// single statement so that concurrent flushes never count a download twice.
+const FLUSH_QUERY: &str = "WITH new_downloads AS (
+    UPDATE package_downloads SET counted = TRUE
+    WHERE NOT counted
+    RETURNING package_version_id
+), version_downloads AS (
+    UPDATE package_versions SET downloads_count = downloads_count + counts.downloads
+    FROM (
+        SELECT package_version_id, COUNT(*)::INTEGER AS downloads
+        FROM new_downloads GROUP BY package_version_id
+    ) AS counts
+    WHERE package_versions.id = counts.package_version_id
+    RETURNING package_versions.package_id, counts.downloads
+)
+UPDATE packages SET total_downloads_count = total_downloads_count + counts.downloads
+FROM (
+    SELECT package_id, SUM(downloads)::INTEGER AS downloads
+    FROM version_downloads GROUP BY package_id
+) AS counts
+WHERE packages.id = counts.package_id";
+
+/// A download of a package version by a client. Downloads are recorded as requests come in,
+/// and added to the download counters of versions and packages in batches by `flush`.
+pub struct PackageDownload;
+
+impl PackageDownload {
+    /// Records a download of the version by the client with the given fingerprint. Returns
+    /// whether it will be counted, i.e. the client didn't download the version within the window.
+    pub fn record(
+        package_version_id: i32,
+        fingerprint: &str,
+        conn: &DieselPgConnection,
+    ) -> Result<bool> {
+        let since = Utc::now() - Duration::hours(DEDUP_WINDOW_HOURS);
+        let inserted = diesel::sql_query(RECORD_QUERY)
+            .bind::<Integer, _>(package_version_id)
+            .bind::<Varchar, _>(fingerprint)
+            .bind::<Timestamptz, _>(since)
+            .execute(conn)?;
+        Ok(inserted > 0)
+    }
+
+    /// Adds the downloads recorded since the last flush to the download counters, then drops
+    /// the ones the window no longer needs. Returns the number of packages updated.
+    pub fn flush(conn: &DieselPgConnection) -> Result<usize> {
+        conn.transaction(|| {
+            let updated = diesel::sql_query(FLUSH_QUERY).execute(conn)?;
+            diesel::delete(
+                package_downloads::table
+                    .filter(package_downloads::counted.eq(true))
+                    .filter(
+                        package_downloads::created_at
+                            .lt(Utc::now() - Duration::hours(DEDUP_WINDOW_HOURS)),
+                    ),
+            )
+            .execute(conn)?;
+            Ok(updated)
+        })
+    }
+
+    /// Records that the shadow package at `rev` of the repository is being created. Returns
+    /// whether it wasn't already, i.e. whether a job should be queued for it.
+    pub fn request_shadow_package(
+        url: &str,
+        rev: &str,
+        subdir: &str,
+        conn: &DieselPgConnection,
+    ) -> Result<bool> {
+        diesel::delete(
+            shadow_package_requests::table.filter(
+                shadow_package_requests::created_at
+                    .lt(Utc::now() - Duration::minutes(SHADOW_PACKAGE_REQUEST_MINUTES)),
+            ),
+        )
+        .execute(conn)?;
+        let inserted_rows = diesel::insert_into(shadow_package_requests::table)
+            .values((
+                shadow_package_requests::url.eq(url),
+                shadow_package_requests::rev.eq(rev),
+                shadow_package_requests::subdir.eq(subdir),
+            ))
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+        Ok(inserted_rows == 1)
+    }
+
+    /// Forgets the request for a shadow package, once its job is done or couldn't be queued.
+    pub fn forget_shadow_package(
+        url: &str,
+        rev: &str,
+        subdir: &str,
+        conn: &DieselPgConnection,
+    ) -> Result<usize> {
+        Ok(diesel::delete(
+            shadow_package_requests::table
+                .filter(shadow_package_requests::url.eq(url))
+                .filter(shadow_package_requests::rev.eq(rev))
+                .filter(shadow_package_requests::subdir.eq(subdir)),
+        )
+        .execute(conn)?)
+    }
+}
+
+/// Identifies a client by the IP address of `ClientIp`, which clients can't pick, unlike
+/// their user agent. Only a hash is kept, so the downloads don't record who made them.
+pub fn client_fingerprint(ip_address: Option<&str>) -> String {
+    format!(
+        "{:x}",
+        Sha256::digest(ip_address.unwrap_or_default().as_bytes())
+    )
+}
@@ -0,0 +1,165 @@
+use crate::packages::models::package_download::*;
+use crate::packages::{Package, PackageVersion};
+use crate::schema::{package_downloads, package_versions, shadow_package_requests};
+use crate::test::{DatabaseTestContext, DB_POOL};
+use jelly::chrono::{Duration, Utc};
+
+fn create_package_with_versions() -> (i32, i32, i32) {
+    let package_id = Package::create_test_package(
+        &"Test package".to_string(),
+        &"https://github.com/eadungn/taohe".to_string(),
+        &"".to_string(),
+        &"1.0.0".to_string(),
+        &"".to_string(),
+        &"".to_string(),
+        &"30d4792b29330cf701af04b493a38a82102ed4fd".to_string(),
+        20,
+        100,
+        0,
+        0,
+        None,
+        &DB_POOL,
+    )
+    .unwrap();
+    let conn = DB_POOL.get().unwrap();
+    let first = Package::find_version_id(
+        "https://github.com/eadungn/taohe",
+        "",
+        "30d4792b29330cf701af04b493a38a82102ed4fd",
+        &conn,
+    )
+    .unwrap()
+    .unwrap();
+    let second = PackageVersion::create(
+        package_id,
+        "1.1.0".to_string(),
+        "".to_string(),
+        "".to_string(),
+        "fe66d6c60a3765c322edbcfa9b63650593971a28".to_string(),
+        20,
+        100,
+        None,
+        &conn,
+    )
+    .unwrap()
+    .id;
+    (package_id, first, second)
+}
+
+fn downloads_count(package_version_id: i32) -> i32 {
+    package_versions::table
+        .find(package_version_id)
+        .select(package_versions::downloads_count)
+        .first::<i32>(&DB_POOL.get().unwrap())
+        .unwrap()
+}
+
+#[actix_rt::test]
+async fn record_counts_a_client_once_per_version_within_the_window() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+    let conn = DB_POOL.get().unwrap();
+    let (_, first, second) = create_package_with_versions();
+    let client = client_fingerprint(Some("10.0.0.1"));
+
+    assert!(PackageDownload::record(first, &client, &conn).unwrap());
+    assert!(!PackageDownload::record(first, &client, &conn).unwrap());
+    assert!(PackageDownload::record(second, &client, &conn).unwrap());
+    let other_client = client_fingerprint(Some("10.0.0.2"));
+    assert!(PackageDownload::record(first, &other_client, &conn).unwrap());
+
+    diesel::update(package_downloads::table)
+        .set(package_downloads::created_at.eq(Utc::now() - Duration::hours(DEDUP_WINDOW_HOURS + 1)))
+        .execute(&conn)
+        .unwrap();
+    assert!(PackageDownload::record(first, &client, &conn).unwrap());
+}
+
+#[actix_rt::test]
+async fn flush_adds_recorded_downloads_to_the_counters_once() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+    let conn = DB_POOL.get().unwrap();
+    let (package_id, first, second) = create_package_with_versions();
+
+    PackageDownload::record(first, "a", &conn).unwrap();
+    PackageDownload::record(first, "b", &conn).unwrap();
+    PackageDownload::record(second, "a", &conn).unwrap();
+    assert_eq!(downloads_count(first), 0);
+
+    assert_eq!(PackageDownload::flush(&conn).unwrap(), 1);
+    assert_eq!(downloads_count(first), 2);
+    assert_eq!(downloads_count(second), 1);
+    assert_eq!(
+        Package::get(package_id, &DB_POOL)
+            .unwrap()
+            .total_downloads_count,
+        3
+    );
+
+    assert_eq!(PackageDownload::flush(&conn).unwrap(), 0);
+    PackageDownload::record(second, "b", &conn).unwrap();
+    PackageDownload::flush(&conn).unwrap();
+    assert_eq!(downloads_count(first), 2);
+    assert_eq!(downloads_count(second), 2);
+    assert_eq!(
+        Package::get(package_id, &DB_POOL)
+            .unwrap()
+            .total_downloads_count,
+        4
+    );
+}
+
+#[actix_rt::test]
+async fn flush_prunes_counted_downloads_outside_the_window() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+    let conn = DB_POOL.get().unwrap();
+    let (_, first, _) = create_package_with_versions();
+
+    PackageDownload::record(first, "a", &conn).unwrap();
+    PackageDownload::record(first, "b", &conn).unwrap();
+    PackageDownload::flush(&conn).unwrap();
+    diesel::update(package_downloads::table.filter(package_downloads::fingerprint.eq("a")))
+        .set(package_downloads::created_at.eq(Utc::now() - Duration::hours(DEDUP_WINDOW_HOURS + 1)))
+        .execute(&conn)
+        .unwrap();
+    PackageDownload::record(first, "c", &conn).unwrap();
+    PackageDownload::flush(&conn).unwrap();
+
+    let fingerprints = package_downloads::table
+        .select(package_downloads::fingerprint)
+        .order(package_downloads::fingerprint)
+        .load::<String>(&conn)
+        .unwrap();
+    assert_eq!(fingerprints, vec!["b", "c"]);
+    assert_eq!(downloads_count(first), 3);
+}
+
+#[actix_rt::test]
+async fn request_shadow_package_works_once_until_forgotten_or_expired() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+    let conn = DB_POOL.get().unwrap();
+    let url = "https://github.com/eadungn/taohe";
+
+    assert!(PackageDownload::request_shadow_package(url, "rev", "", &conn).unwrap());
+    assert!(!PackageDownload::request_shadow_package(url, "rev", "", &conn).unwrap());
+    assert!(PackageDownload::request_shadow_package(url, "rev", "sub", &conn).unwrap());
+    assert!(PackageDownload::request_shadow_package(url, "other_rev", "", &conn).unwrap());
+
+    assert_eq!(
+        PackageDownload::forget_shadow_package(url, "rev", "", &conn).unwrap(),
+        1
+    );
+    assert!(PackageDownload::request_shadow_package(url, "rev", "", &conn).unwrap());
+
+    diesel::update(shadow_package_requests::table)
+        .set(
+            shadow_package_requests::created_at
+                .eq(Utc::now() - Duration::minutes(SHADOW_PACKAGE_REQUEST_MINUTES + 1)),
+        )
+        .execute(&conn)
+        .unwrap();
+    assert!(PackageDownload::request_shadow_package(url, "rev", "", &conn).unwrap());
+}
@@ -0,0 +1,152 @@
+use diesel::prelude::*;
+use diesel::{Identifiable, Queryable};
+use jelly::chrono::{DateTime, Utc};
+use jelly::error::Error;
+use jelly::serde::{Deserialize, Serialize};
+use jelly::DieselPgConnection;
+use jelly::Result;
+
+use crate::schema::package_events;
+use crate::utils::paginate::LoadPaginated;
+
+#[cfg(test)]
+mod tests;
+
+pub const EVENTS_PER_PAGE: i64 = 20;
+
+/// What happened to a package. Kinds are stored as integers, so new ones go at the end.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PackageEventKind {
+    VersionPublished = 0,
+    CollaboratorInvited = 1,
+    InvitationAccepted = 2,
+    InvitationDeclined = 3,
+    InvitationCancelled = 4,
+    CollaboratorRemoved = 5,
+    PermissionsChanged = 6,
+    OwnershipTransferRequested = 7,
+    OwnershipTransferred = 8,
+    OwnerAdded = 9,
+    OwnerRemoved = 10,
+    TwoFactorRequirementChanged = 11,
+}
+
+impl PackageEventKind {
+    const ALL: [PackageEventKind; 12] = [
+        PackageEventKind::VersionPublished,
+        PackageEventKind::CollaboratorInvited,
+        PackageEventKind::InvitationAccepted,
+        PackageEventKind::InvitationDeclined,
+        PackageEventKind::InvitationCancelled,
+        PackageEventKind::CollaboratorRemoved,
+        PackageEventKind::PermissionsChanged,
+        PackageEventKind::OwnershipTransferRequested,
+        PackageEventKind::OwnershipTransferred,
+        PackageEventKind::OwnerAdded,
+        PackageEventKind::OwnerRemoved,
+        PackageEventKind::TwoFactorRequirementChanged,
+    ];
+
+    pub fn from_i32(kind: i32) -> Option<Self> {
+        Self::ALL.iter().copied().find(|k| *k as i32 == kind)
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            PackageEventKind::VersionPublished => "published version",
+            PackageEventKind::CollaboratorInvited => "invited",
+            PackageEventKind::InvitationAccepted => "accepted the invitation",
+            PackageEventKind::InvitationDeclined => "declined the invitation",
+            PackageEventKind::InvitationCancelled => "cancelled the invitation of",
+            PackageEventKind::CollaboratorRemoved => "removed collaborator",
+            PackageEventKind::PermissionsChanged => "changed the permissions of",
+            PackageEventKind::OwnershipTransferRequested => "offered the ownership to",
+            PackageEventKind::OwnershipTransferred => "accepted the ownership",
+            PackageEventKind::OwnerAdded => "added owner",
+            PackageEventKind::OwnerRemoved => "removed owner",
+            PackageEventKind::TwoFactorRequirementChanged => {
+                "changed the two-factor authentication requirement"
+            }
+        }
+    }
+}
+
+/// Who took an action on a package, and from where.
+#[derive(Clone, Debug, Default)]
+pub struct EventActor {
+    pub account_id: Option<i32>,
+    /// Set when the action was taken with an API token rather than the session.
+    pub api_token_id: Option<i32>,
+    pub ip_address: Option<String>,
+    /// Set instead of the account when Movey acted on its own.
+    pub system_actor: Option<&'static str>,
+}
+
+impl EventActor {
+    pub fn system(name: &'static str) -> Self {
+        EventActor {
+            system_actor: Some(name),
+            ..Default::default()
+        }
+    }
+}
+
+/// An entry of the audit log of a package. Entries are never updated or deleted.
+#[derive(Debug, Serialize, Deserialize, Queryable, Identifiable)]
+#[table_name = "package_events"]
+pub struct PackageEvent {
+    pub id: i32,
+    pub package_id: i32,
+    pub kind: i32,
+    pub actor_id: Option<i32>,
+    pub api_token_id: Option<i32>,
+    pub target_account_id: Option<i32>,
+    pub details: String,
+    pub ip_address: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub system_actor: Option<String>,
+}
+
+impl PackageEvent {
+    pub fn create(
+        package_id_: i32,
+        kind_: PackageEventKind,
+        actor: &EventActor,
+        target_account_id_: Option<i32>,
+        details_: &str,
+        conn: &DieselPgConnection,
+    ) -> Result<Self> {
+        Ok(diesel::insert_into(package_events::table)
+            .values((
+                package_events::package_id.eq(package_id_),
+                package_events::kind.eq(kind_ as i32),
+                package_events::actor_id.eq(actor.account_id),
+                package_events::api_token_id.eq(actor.api_token_id),
+                package_events::target_account_id.eq(target_account_id_),
+                package_events::details.eq(details_),
+                package_events::ip_address.eq(&actor.ip_address),
+                package_events::system_actor.eq(actor.system_actor),
+            ))
+            .get_result::<Self>(conn)?)
+    }
+
+    /// Events of the package, the most recent first.
+    pub fn get_by_package_id_paginated(
+        package_id_: i32,
+        page: Option<i64>,
+        conn: &DieselPgConnection,
+    ) -> Result<(Vec<Self>, i64, i64)> {
+        if page.is_some_and(|page| page < 1) {
+            return Err(Error::Generic(String::from("Invalid page number.")));
+        }
+        Ok(package_events::table
+            .filter(package_events::package_id.eq(package_id_))
+            .order((package_events::created_at.desc(), package_events::id.desc()))
+            .load_with_pagination(conn, page, Some(EVENTS_PER_PAGE))?)
+    }
+
+    pub fn kind(&self) -> Option<PackageEventKind> {
+        PackageEventKind::from_i32(self.kind)
+    }
+}
@@ -0,0 +1,83 @@
+use crate::packages::models::package_event::{EventActor, PackageEvent, PackageEventKind};
+use crate::test::util::{setup_package, setup_user};
+use crate::test::{DatabaseTestContext, DB_POOL};
+use diesel::prelude::*;
+
+use crate::schema::package_events;
+
+#[actix_rt::test]
+async fn create_and_list_events_works() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let uid = setup_user(None, None);
+    let pid = setup_package("package1", "", uid);
+    let conn = DB_POOL.get().unwrap();
+    let actor = EventActor {
+        account_id: Some(uid),
+        api_token_id: Some(1),
+        ip_address: Some("127.0.0.1".to_string()),
+        system_actor: None,
+    };
+    PackageEvent::create(
+        pid,
+        PackageEventKind::VersionPublished,
+        &actor,
+        None,
+        "1.0.0",
+        &conn,
+    )
+    .unwrap();
+    PackageEvent::create(
+        pid,
+        PackageEventKind::CollaboratorInvited,
+        &actor,
+        None,
+        "external@host.com",
+        &conn,
+    )
+    .unwrap();
+
+    let (events, total_count, total_pages) =
+        PackageEvent::get_by_package_id_paginated(pid, None, &conn).unwrap();
+    assert_eq!(total_count, 2);
+    assert_eq!(total_pages, 1);
+    assert_eq!(
+        events[0].kind(),
+        Some(PackageEventKind::CollaboratorInvited)
+    );
+    assert_eq!(events[0].details, "external@host.com");
+    assert_eq!(events[1].kind(), Some(PackageEventKind::VersionPublished));
+    assert_eq!(events[1].actor_id, Some(uid));
+    assert_eq!(events[1].api_token_id, Some(1));
+    assert_eq!(events[1].ip_address, Some("127.0.0.1".to_string()));
+
+    assert!(PackageEvent::get_by_package_id_paginated(pid, Some(0), &conn).is_err());
+}
+
+#[actix_rt::test]
+async fn events_are_append_only() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let uid = setup_user(None, None);
+    let pid = setup_package("package1", "", uid);
+    let conn = DB_POOL.get().unwrap();
+    let event = PackageEvent::create(
+        pid,
+        PackageEventKind::OwnerAdded,
+        &EventActor::default(),
+        Some(uid),
+        "",
+        &conn,
+    )
+    .unwrap();
+
+    assert!(diesel::update(package_events::table.find(event.id))
+        .set(package_events::details.eq("tampered"))
+        .execute(&conn)
+        .is_err());
+    assert!(diesel::delete(package_events::table.find(event.id))
+        .execute(&conn)
+        .is_err());
+}
@@ -0,0 +1,117 @@
+use diesel::prelude::*;
+use diesel::{Identifiable, Queryable};
+use hmac::{Hmac, Mac, NewMac};
+use jelly::chrono::{DateTime, Duration, Utc};
+use jelly::serde::{Deserialize, Serialize};
+use jelly::DieselPgConnection;
+use jelly::Result;
+use sha2::Sha256;
+
+use crate::schema::{package_webhooks, webhook_deliveries};
+use crate::utils::token::{generate_secure_alphanumeric_string, TOKEN_LENGTH};
+
+#[cfg(test)]
+mod tests;
+
+// GitHub can redeliver a webhook for three days, deliveries are kept a bit longer.
+const KEEP_DELIVERY_DAYS: i64 = 7;
+
+/// A GitHub webhook that publishes a new version of the package when one of
+/// its owners pushes a tag.
+#[derive(Debug, Serialize, Deserialize, Queryable, Identifiable)]
+#[table_name = "package_webhooks"]
+pub struct PackageWebhook {
+    pub id: i32,
+    pub package_id: i32,
+    pub secret: String,
+    pub created_by: i32,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl PackageWebhook {
+    /// Creates the webhook of a package, or replaces the secret of the existing one.
+    pub fn create_or_regenerate(
+        package_id_: i32,
+        created_by_: i32,
+        conn: &DieselPgConnection,
+    ) -> Result<Self> {
+        let secret_ = generate_secure_alphanumeric_string(TOKEN_LENGTH);
+        Ok(diesel::insert_into(package_webhooks::table)
+            .values((
+                package_webhooks::package_id.eq(package_id_),
+                package_webhooks::secret.eq(&secret_),
+                package_webhooks::created_by.eq(created_by_),
+            ))
+            .on_conflict(package_webhooks::package_id)
+            .do_update()
+            .set((
+                package_webhooks::secret.eq(&secret_),
+                package_webhooks::created_by.eq(created_by_),
+                package_webhooks::updated_at.eq(Utc::now()),
+            ))
+            .get_result::<Self>(conn)?)
+    }
+
+    pub fn get_by_package_id(package_id_: i32, conn: &DieselPgConnection) -> Result<Self> {
+        Ok(package_webhooks::table
+            .filter(package_webhooks::package_id.eq(package_id_))
+            .first::<Self>(conn)?)
+    }
+
+    pub fn delete_by_package_id(package_id_: i32, conn: &DieselPgConnection) -> Result<usize> {
+        Ok(diesel::delete(
+            package_webhooks::table.filter(package_webhooks::package_id.eq(package_id_)),
+        )
+        .execute(conn)?)
+    }
+
+    /// Remembers the `X-GitHub-Delivery` id of a webhook delivery. Returns `false` if the
+    /// delivery was already received, e.g. when GitHub redelivers it.
+    pub fn record_delivery(delivery_id: &str, conn: &DieselPgConnection) -> Result<bool> {
+        diesel::delete(webhook_deliveries::table.filter(
+            webhook_deliveries::created_at.lt(Utc::now() - Duration::days(KEEP_DELIVERY_DAYS)),
+        ))
+        .execute(conn)?;
+        let inserted_rows = diesel::insert_into(webhook_deliveries::table)
+            .values(webhook_deliveries::delivery_id.eq(delivery_id))
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+        Ok(inserted_rows == 1)
+    }
+
+    /// Forgets a delivery that couldn't be handled, so that it can be redelivered.
+    pub fn forget_delivery(delivery_id: &str, conn: &DieselPgConnection) -> Result<usize> {
+        Ok(diesel::delete(
+            webhook_deliveries::table.filter(webhook_deliveries::delivery_id.eq(delivery_id)),
+        )
+        .execute(conn)?)
+    }
+
+    /// Whether `signature` is the `X-Hub-Signature-256` header GitHub sends for `payload`,
+    /// e.g. `sha256=757107ea0eb2509fc211221cce984b8a37570b6d7586c22c46f4379c8b043e17`.
+    pub fn verify_signature(&self, payload: &[u8], signature: &str) -> bool {
+        let signature = match signature
+            .strip_prefix("sha256=")
+            .and_then(|hex_digest| hex::decode(hex_digest).ok())
+        {
+            Some(signature) => signature,
+            None => return false,
+        };
+        let mut mac = match Hmac::<Sha256>::new_varkey(self.secret.as_bytes()) {
+            Ok(mac) => mac,
+            Err(_) => return false,
+        };
+        mac.update(payload);
+        // Compares in constant time
+        mac.verify(&signature).is_ok()
+    }
+
+    /// Signature of `payload` in the format of the `X-Hub-Signature-256` header.
+    pub fn sign(&self, payload: &[u8]) -> String {
+        let mut mac = Hmac::<Sha256>::new_varkey(self.secret.as_bytes())
+            .expect("HMAC can take keys of any size");
+        mac.update(payload);
+        format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+    }
+}
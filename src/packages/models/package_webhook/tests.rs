@@ -0,0 +1,79 @@
+use super::*;
+use crate::test::util::{setup_package, setup_user};
+use crate::test::{DatabaseTestContext, DB_POOL};
+
+#[actix_rt::test]
+async fn create_or_regenerate_works() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let uid = setup_user(None, None);
+    let pid = setup_package("package1", "https://github.com/move-language/move", uid);
+    let conn = DB_POOL.get().unwrap();
+
+    let webhook = PackageWebhook::create_or_regenerate(pid, uid, &conn).unwrap();
+    assert_eq!(webhook.package_id, pid);
+    assert_eq!(webhook.secret.len(), TOKEN_LENGTH);
+
+    let regenerated = PackageWebhook::create_or_regenerate(pid, uid, &conn).unwrap();
+    assert_eq!(regenerated.id, webhook.id);
+    assert_ne!(regenerated.secret, webhook.secret);
+    assert_eq!(
+        PackageWebhook::get_by_package_id(pid, &conn)
+            .unwrap()
+            .secret,
+        regenerated.secret
+    );
+
+    assert_eq!(PackageWebhook::delete_by_package_id(pid, &conn).unwrap(), 1);
+    assert!(PackageWebhook::get_by_package_id(pid, &conn).is_err());
+}
+
+#[actix_rt::test]
+async fn verify_signature_works() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let uid = setup_user(None, None);
+    let pid = setup_package("package1", "https://github.com/move-language/move", uid);
+    let mut webhook =
+        PackageWebhook::create_or_regenerate(pid, uid, &DB_POOL.get().unwrap()).unwrap();
+    // Example from https://docs.github.com/en/webhooks/using-webhooks/validating-webhook-deliveries
+    webhook.secret = "It's a Secret to Everybody".to_string();
+    let signature = "sha256=757107ea0eb2509fc211221cce984b8a37570b6d7586c22c46f4379c8b043e17";
+
+    assert_eq!(webhook.sign(b"Hello, World!"), signature);
+    assert!(webhook.verify_signature(b"Hello, World!", signature));
+    assert!(!webhook.verify_signature(b"Hello, World", signature));
+    assert!(!webhook.verify_signature(b"Hello, World!", &signature[7..]));
+    assert!(!webhook.verify_signature(b"Hello, World!", "sha256=not hex"));
+}
+
+#[actix_rt::test]
+async fn record_delivery_only_accepts_new_deliveries() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+    let conn = DB_POOL.get().unwrap();
+
+    assert!(PackageWebhook::record_delivery("delivery-1", &conn).unwrap());
+    assert!(!PackageWebhook::record_delivery("delivery-1", &conn).unwrap());
+    assert!(PackageWebhook::record_delivery("delivery-2", &conn).unwrap());
+
+    PackageWebhook::forget_delivery("delivery-1", &conn).unwrap();
+    assert!(PackageWebhook::record_delivery("delivery-1", &conn).unwrap());
+
+    // Old deliveries are pruned
+    let expired_at = Utc::now() - Duration::days(KEEP_DELIVERY_DAYS + 1);
+    diesel::update(webhook_deliveries::table)
+        .set(webhook_deliveries::created_at.eq(expired_at))
+        .execute(&conn)
+        .unwrap();
+    assert!(PackageWebhook::record_delivery("delivery-1", &conn).unwrap());
+    assert_eq!(
+        webhook_deliveries::table
+            .count()
+            .get_result::<i64>(&conn)
+            .unwrap(),
+        1
+    );
+}
@@ -415,6 +415,8 @@ async fn create_package_works() {
                 forks_count: 0,
                 url: "".to_string(),
                 rev: "".to_string(),
+                dependencies: vec![],
+                addresses: vec![],
             })
         });
 
@@ -454,6 +456,8 @@ async fn create_package_works() {
                 forks_count: 0,
                 url: "".to_string(),
                 rev: "".to_string(),
+                dependencies: vec![],
+                addresses: vec![],
             })
         });
     let uid2 = Package::create(
@@ -505,6 +509,8 @@ async fn create_package_works() {
                 forks_count: 0,
                 url: "".to_string(),
                 rev: "".to_string(),
+                dependencies: vec![],
+                addresses: vec![],
             })
         });
 
@@ -548,6 +554,8 @@ async fn get_versions_by_latest() {
                 forks_count: 0,
                 url: "".to_string(),
                 rev: "".to_string(),
+                dependencies: vec![],
+                addresses: vec![],
             })
         });
 
@@ -606,6 +614,8 @@ async fn get_versions_by_oldest() {
                 forks_count: 0,
                 url: "".to_string(),
                 rev: "".to_string(),
+                dependencies: vec![],
+                addresses: vec![],
             })
         });
 
@@ -662,6 +672,8 @@ async fn get_versions_by_most_downloads() {
                 forks_count: 0,
                 url: "".to_string(),
                 rev: "".to_string(),
+                dependencies: vec![],
+                addresses: vec![],
             })
         });
 
@@ -780,16 +792,34 @@ async fn increase_download_count_works() {
                 forks_count: 0,
                 url: "".to_string(),
                 rev: "".to_string(),
+                dependencies: vec![],
+                addresses: vec![],
             })
         });
 
-    Package::increase_download_count(url, rev_, &String::new(), &mock_github_service, &DB_POOL)
-        .unwrap();
+    Package::increase_download_count(
+        url,
+        rev_,
+        &String::new(),
+        "client-1",
+        &mock_github_service,
+        &DB_POOL,
+    )
+    .unwrap();
+    PackageDownload::flush(&DB_POOL.get().unwrap()).unwrap();
     no_downloads = Package::get_downloads(uid, &DB_POOL);
     assert_eq!(1, no_downloads.unwrap());
 
-    Package::increase_download_count(url, rev_, &String::new(), &mock_github_service, &DB_POOL)
-        .unwrap();
+    Package::increase_download_count(
+        url,
+        rev_,
+        &String::new(),
+        "client-2",
+        &mock_github_service,
+        &DB_POOL,
+    )
+    .unwrap();
+    PackageDownload::flush(&DB_POOL.get().unwrap()).unwrap();
     no_downloads = Package::get_downloads(uid, &DB_POOL);
     assert_eq!(2, no_downloads.unwrap());
     let package_versions_after =
@@ -802,10 +832,12 @@ async fn increase_download_count_works() {
         &"git@github.com:eadungn/taohe.git".to_string(),
         rev_,
         &String::new(),
+        "client-3",
         &mock_github_service,
         &DB_POOL,
     );
 
+    PackageDownload::flush(&DB_POOL.get().unwrap()).unwrap();
     no_downloads = Package::get_downloads(uid, &DB_POOL);
     assert_eq!(3, no_downloads.unwrap());
     let package_versions_after =
@@ -815,6 +847,54 @@ async fn increase_download_count_works() {
     assert_eq!(package_version_after.downloads_count, 3);
 }
 
+#[actix_rt::test]
+async fn find_version_id_matches_packages_in_subdirs() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let rev_ = "30d4792b29330cf701af04b493a38a82102ed4fd".to_string();
+    Package::create_test_package(
+        &"MoveStdlib".to_string(),
+        &"https://github.com/move-language/move/tree/main/language/move-stdlib".to_string(),
+        &"".to_string(),
+        &"1.0.0".to_string(),
+        &"".to_string(),
+        &"".to_string(),
+        &rev_,
+        20,
+        100,
+        0,
+        0,
+        None,
+        &DB_POOL,
+    )
+    .unwrap();
+    let conn = DB_POOL.get().unwrap();
+    let repo_url = "git@github.com:move-language/move.git";
+
+    assert!(
+        Package::find_version_id(repo_url, "/language/move-stdlib/", &rev_, &conn)
+            .unwrap()
+            .is_some()
+    );
+    assert!(Package::find_version_id(repo_url, "", &rev_, &conn)
+        .unwrap()
+        .is_none());
+    assert!(
+        Package::find_version_id(repo_url, "language/move-nursery", &rev_, &conn)
+            .unwrap()
+            .is_none()
+    );
+    assert!(Package::find_version_id(
+        repo_url,
+        "language/move-stdlib",
+        "fe66d6c60a3765c322edbcfa9b63650593971a28",
+        &conn
+    )
+    .unwrap()
+    .is_none());
+}
+
 #[actix_rt::test]
 async fn increase_download_count_for_nonexistent_package() {
     crate::test::init();
@@ -838,6 +918,8 @@ async fn increase_download_count_for_nonexistent_package() {
                 forks_count: 0,
                 url: "".to_string(),
                 rev: "".to_string(),
+                dependencies: vec![],
+                addresses: vec![],
             })
         });
 
@@ -859,10 +941,24 @@ async fn increase_download_count_for_nonexistent_package() {
     assert_eq!(package_before, 0);
     assert_eq!(package_version_before, 0);
 
-    Package::increase_download_count(url, rev_, &String::new(), &mock_github_service, &DB_POOL)
-        .unwrap();
-    Package::increase_download_count(url, rev_, &String::new(), &mock_github_service, &DB_POOL)
-        .unwrap();
+    Package::increase_download_count(
+        url,
+        rev_,
+        &String::new(),
+        "client-1",
+        &mock_github_service,
+        &DB_POOL,
+    )
+    .unwrap();
+    Package::increase_download_count(
+        url,
+        rev_,
+        &String::new(),
+        "client-2",
+        &mock_github_service,
+        &DB_POOL,
+    )
+    .unwrap();
 
     let package_after = packages
         .select(diesel::dsl::count(packages::id))
@@ -936,6 +1032,8 @@ async fn increase_download_count_for_multiple_versions() {
                 forks_count: 0,
                 url: "".to_string(),
                 rev: "".to_string(),
+                dependencies: vec![],
+                addresses: vec![],
             })
         });
 
@@ -945,10 +1043,25 @@ async fn increase_download_count_for_multiple_versions() {
     for package_version_before in package_versions_before {
         assert_eq!(package_version_before.downloads_count, 0);
     }
-    Package::increase_download_count(&url, &rev1, &String::new(), &mock_github_service, &DB_POOL)
-        .unwrap();
-    Package::increase_download_count(&url, &rev2, &String::new(), &mock_github_service, &DB_POOL)
-        .unwrap();
+    Package::increase_download_count(
+        &url,
+        &rev1,
+        &String::new(),
+        "client-1",
+        &mock_github_service,
+        &DB_POOL,
+    )
+    .unwrap();
+    Package::increase_download_count(
+        &url,
+        &rev2,
+        &String::new(),
+        "client-2",
+        &mock_github_service,
+        &DB_POOL,
+    )
+    .unwrap();
+    PackageDownload::flush(&DB_POOL.get().unwrap()).unwrap();
     let package_versions_after =
         PackageVersion::from_package_id(package_id_, &PackageVersionSort::Latest, &DB_POOL)
             .unwrap();
@@ -964,10 +1077,12 @@ async fn increase_download_count_for_multiple_versions() {
         &"git@github.com:eadungn/taohe.git".to_string(),
         &rev2,
         &String::new(),
+        "client-3",
         &mock_github_service,
         &DB_POOL,
     )
     .unwrap();
+    PackageDownload::flush(&DB_POOL.get().unwrap()).unwrap();
     let package_versions_after =
         PackageVersion::from_package_id(package_id_, &PackageVersionSort::Latest, &DB_POOL)
             .unwrap();
@@ -995,17 +1110,19 @@ async fn get_badge_info() {
         &DB_POOL,
     )
     .unwrap();
-    let mut expected: Vec<(String, i32, String, i32)> = vec![(
+    let mut expected: Vec<(String, i32, String, i32, bool)> = vec![(
         "The first package".to_string(),
         1500,
         "0.0.1".to_string(),
         500,
+        false,
     )];
     expected.push((
         "The first package".to_string(),
         1500,
         "0.0.2".to_string(),
         1000,
+        true,
     ));
     let result = Package::get_badge_info(search_query, &DB_POOL).unwrap();
     assert_eq!(result.len(), 2);
@@ -1098,3 +1215,219 @@ async fn get_package() {
     assert_eq!(res.slug, "the-first-package");
     assert_eq!(res.name, "The first package");
 }
+
+#[actix_rt::test]
+async fn set_yanked_works() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let package_id_ = Package::create_test_package_with_multiple_versions(
+        &"The first package".to_string(),
+        &"".to_string(),
+        &"description 1".to_string(),
+        1500,
+        10,
+        2,
+        &DB_POOL,
+    )
+    .unwrap();
+    let conn = DB_POOL.get().unwrap();
+    let package = Package::get(package_id_, &DB_POOL).unwrap();
+    let package_version = package.get_version(&"0.0.2".to_string(), &conn).unwrap();
+    assert!(!package_version.yanked);
+
+    let package_version = package_version.set_yanked(true, &conn).unwrap();
+    assert!(package_version.yanked);
+    assert!(package.get_version(&"0.0.2".to_string(), &conn).unwrap().yanked);
+
+    let package_version = package_version.set_yanked(false, &conn).unwrap();
+    assert!(!package_version.yanked);
+}
+
+#[actix_rt::test]
+async fn search_skips_yanked_versions_for_latest_version() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let package_id_ = Package::create_test_package_with_multiple_versions(
+        &"The first package".to_string(),
+        &"".to_string(),
+        &"description 1".to_string(),
+        1500,
+        10,
+        2,
+        &DB_POOL,
+    )
+    .unwrap();
+    let conn = DB_POOL.get().unwrap();
+    let package = Package::get(package_id_, &DB_POOL).unwrap();
+
+    let (search_result, _, _) = Package::search(
+        "package",
+        &PackageSortField::Name,
+        &PackageSortOrder::Desc,
+        None,
+        None,
+        &DB_POOL,
+    )
+    .unwrap();
+    assert_eq!(search_result[0].version, "0.0.2");
+
+    package
+        .get_version(&"0.0.2".to_string(), &conn)
+        .unwrap()
+        .set_yanked(true, &conn)
+        .unwrap();
+    let (search_result, _, _) = Package::search(
+        "package",
+        &PackageSortField::Name,
+        &PackageSortOrder::Desc,
+        None,
+        None,
+        &DB_POOL,
+    )
+    .unwrap();
+    assert_eq!(search_result[0].version, "0.0.1");
+
+    // Falls back to the highest version when every version is yanked
+    package
+        .get_version(&"0.0.1".to_string(), &conn)
+        .unwrap()
+        .set_yanked(true, &conn)
+        .unwrap();
+    let (search_result, _, _) = Package::all_packages(
+        &PackageSortField::Name,
+        &PackageSortOrder::Desc,
+        None,
+        None,
+        &DB_POOL,
+    )
+    .unwrap();
+    assert_eq!(search_result[0].version, "0.0.2");
+}
+
+#[actix_rt::test]
+async fn latest_version_uses_semver_ordering() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let package_id_ = Package::create_test_package(
+        &"The first package".to_string(),
+        &"".to_string(),
+        &"description 1".to_string(),
+        &"0.9.0".to_string(),
+        &"".to_string(),
+        &"".to_string(),
+        &"".to_string(),
+        0,
+        0,
+        0,
+        0,
+        None,
+        &DB_POOL,
+    )
+    .unwrap();
+    let conn = DB_POOL.get().unwrap();
+    for version_name in ["0.10.0", "1.0.0-alpha.1", "0.2.0"] {
+        PackageVersion::create(
+            package_id_,
+            version_name.to_string(),
+            "".to_string(),
+            "".to_string(),
+            "".to_string(),
+            0,
+            0,
+            None,
+            &conn,
+        )
+        .unwrap();
+    }
+
+    // Pre-releases rank below stable releases
+    let package = Package::get(package_id_, &DB_POOL).unwrap();
+    assert_eq!(package.get_latest_version(&conn).unwrap().version, "0.10.0");
+
+    let (search_result, _, _) = Package::search(
+        "package",
+        &PackageSortField::Name,
+        &PackageSortOrder::Desc,
+        None,
+        None,
+        &DB_POOL,
+    )
+    .unwrap();
+    assert_eq!(search_result.len(), 1);
+    assert_eq!(search_result[0].version, "0.10.0");
+
+    let versions =
+        PackageVersion::from_package_id(package_id_, &PackageVersionSort::Latest, &DB_POOL)
+            .unwrap();
+    let versions: Vec<&str> = versions
+        .iter()
+        .map(|package_version| package_version.version.as_str())
+        .collect();
+    assert_eq!(versions, vec!["1.0.0-alpha.1", "0.10.0", "0.9.0", "0.2.0"]);
+
+    // Pre-releases are only picked when there is no stable release left
+    for version_name in ["0.10.0", "0.9.0", "0.2.0"] {
+        package
+            .get_version(&version_name.to_string(), &conn)
+            .unwrap()
+            .set_yanked(true, &conn)
+            .unwrap();
+    }
+    let package = Package::get(package_id_, &DB_POOL).unwrap();
+    assert_eq!(
+        package.get_latest_version(&conn).unwrap().version,
+        "1.0.0-alpha.1"
+    );
+}
+
+#[actix_rt::test]
+async fn backfill_latest_versions_uses_semver_ordering() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let package_id_ = Package::create_test_package(
+        &"The first package".to_string(),
+        &"".to_string(),
+        &"description 1".to_string(),
+        &"1.0.0-alpha.10".to_string(),
+        &"".to_string(),
+        &"".to_string(),
+        &"".to_string(),
+        0,
+        0,
+        0,
+        0,
+        None,
+        &DB_POOL,
+    )
+    .unwrap();
+    let conn = DB_POOL.get().unwrap();
+    PackageVersion::create(
+        package_id_,
+        "1.0.0-alpha.9".to_string(),
+        "".to_string(),
+        "".to_string(),
+        "".to_string(),
+        0,
+        0,
+        None,
+        &conn,
+    )
+    .unwrap();
+    diesel::update(packages.filter(packages::id.eq(package_id_)))
+        .set(latest_version_id.eq(None::<i32>))
+        .execute(&conn)
+        .unwrap();
+
+    assert_eq!(Package::backfill_latest_versions(&conn).unwrap(), 1);
+    // Numeric pre-release identifiers are compared as numbers
+    let package = Package::get(package_id_, &DB_POOL).unwrap();
+    assert_eq!(
+        package.get_latest_version(&conn).unwrap().version,
+        "1.0.0-alpha.10"
+    );
+    assert_eq!(Package::backfill_latest_versions(&conn).unwrap(), 0);
+}
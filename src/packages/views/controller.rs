@@ -4,17 +4,24 @@ use jelly::forms::TextField;
 use jelly::prelude::*;
 use jelly::request::DatabasePool;
 use jelly::Result;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 use crate::accounts::Account;
 use crate::package_collaborators::models::external_invitation::ExternalInvitation;
 use crate::package_collaborators::models::owner_invitation::OwnerInvitation;
-use crate::package_collaborators::package_collaborator::PackageCollaborator;
+use crate::package_collaborators::models::package_claim::{PackageClaim, CLAIM_FILE_PATH};
+use crate::package_collaborators::package_collaborator::{
+    CollaboratorPermissions, PackageCollaborator, Permission, Role,
+};
+use crate::packages::models::named_address::NamedAddress;
+use crate::packages::models::package_dependency::PackageDependency;
+use crate::packages::models::package_event::PackageEvent;
 use crate::packages::models::{PackageSortField, PackageSortOrder, PACKAGES_PER_PAGE};
 use crate::packages::{Package, PackageVersion, PackageVersionSort};
+use crate::source_hosts;
 use crate::utils::presenter;
 
-use super::serializer::{SerializableInvitation, Status};
+use super::serializer::{SerializableEvent, SerializableInvitation, Status};
 
 #[derive(serde::Serialize, serde::Deserialize)]
 struct PackageShowParams {
@@ -38,14 +45,13 @@ pub async fn show_package(
     let package_version: PackageVersion;
 
     if version.is_empty() {
-        let versions =
-            PackageVersion::from_package_id(package.id, &PackageVersionSort::Latest, db)?;
-        package_version = versions[0].clone();
+        package_version = package.get_latest_version(&conn)?;
     } else {
         package_version = package.get_version(version, &conn)?
     }
 
-    let (account_name, account_slug_url) = presenter::make_account_name(&package, db)?;
+    let named_addresses = NamedAddress::get_by_package_version(package_version.id, &conn)?;
+    let owners = presenter::make_account_names(&package, db)?;
     let (instruction_repo_url, instruction_subdir) =
         presenter::make_package_install_instruction(&package.repository_url);
 
@@ -57,10 +63,10 @@ pub async fn show_package(
         ctx.insert("is_crawled", &collaborators.is_empty());
         ctx.insert("is_anonymous", &request.user()?.is_anonymous);
 
-        ctx.insert("account_name", &account_name);
-        ctx.insert("account_slug_url", &account_slug_url);
+        ctx.insert("owners", &owners);
         ctx.insert("instruction_subdir", &instruction_subdir);
         ctx.insert("instruction_repo_url", &instruction_repo_url);
+        ctx.insert("named_addresses", &named_addresses);
         ctx
     })
 }
@@ -77,8 +83,7 @@ pub async fn show_package_versions(
     let db = request.db_pool()?;
     let conn = db.get()?;
     let package = Package::get_by_slug(&package_slug, &conn)?;
-    let package_latest_version =
-        &PackageVersion::from_package_id(package.id, &PackageVersionSort::Latest, db)?[0];
+    let package_latest_version = package.get_latest_version(&conn)?;
     let collaborators = PackageCollaborator::get_by_package_id(package.id, &conn)?;
 
     let params = Query::<VersionParams>::from_query(request.query_string()).map_err(|e| {
@@ -109,6 +114,60 @@ pub async fn show_package_versions(
     })
 }
 
+pub async fn show_package_dependencies(
+    request: HttpRequest,
+    Path(package_slug): Path<String>,
+) -> Result<HttpResponse> {
+    let db = request.db_pool()?;
+    let conn = db.get()?;
+    let package = Package::get_by_slug(&package_slug, &conn)?;
+    let collaborators = PackageCollaborator::get_by_package_id(package.id, &conn)?;
+
+    let params = Query::<PackageShowParams>::from_query(request.query_string())
+        .map_err(|e| Error::Generic(format!("Error getting query params: {:?}", e)))?;
+    let package_version = match &params.version {
+        Some(version) if !version.is_empty() => package.get_version(version, &conn)?,
+        _ => package.get_latest_version(&conn)?,
+    };
+    let dependencies = PackageDependency::get_by_package_version(package_version.id, &conn)?;
+
+    request.render(200, "packages/dependencies.html", {
+        let mut ctx = Context::new();
+        ctx.insert("package", &package);
+        ctx.insert("package_version", &package_version);
+        ctx.insert("package_tab", "dependencies");
+        ctx.insert("is_crawled", &collaborators.is_empty());
+        ctx.insert("is_anonymous", &request.user()?.is_anonymous);
+
+        ctx.insert("dependencies", &dependencies);
+        ctx
+    })
+}
+
+pub async fn show_package_dependents(
+    request: HttpRequest,
+    Path(package_slug): Path<String>,
+) -> Result<HttpResponse> {
+    let db = request.db_pool()?;
+    let conn = db.get()?;
+    let package = Package::get_by_slug(&package_slug, &conn)?;
+    let package_latest_version = package.get_latest_version(&conn)?;
+    let collaborators = PackageCollaborator::get_by_package_id(package.id, &conn)?;
+    let dependents = PackageDependency::get_dependents(package.id, &conn)?;
+
+    request.render(200, "packages/dependents.html", {
+        let mut ctx = Context::new();
+        ctx.insert("package", &package);
+        ctx.insert("package_version", &package_latest_version);
+        ctx.insert("package_tab", "dependents");
+        ctx.insert("is_crawled", &collaborators.is_empty());
+        ctx.insert("is_anonymous", &request.user()?.is_anonymous);
+
+        ctx.insert("dependents", &dependents);
+        ctx
+    })
+}
+
 pub async fn show_package_settings(
     request: HttpRequest,
     Path(package_slug): Path<String>,
@@ -116,17 +175,19 @@ pub async fn show_package_settings(
     let db_pool = request.db_pool()?;
     let db_connection = db_pool.get()?;
     let package = Package::get_by_slug(&package_slug, &db_connection)?;
-    let package_latest_version =
-        &PackageVersion::from_package_id(package.id, &PackageVersionSort::Latest, &db_pool)?[0];
+    let package_latest_version = package.get_latest_version(&db_connection)?;
 
     // get movey account that is already a collaborator
     let accepted_ids: Vec<i32> =
         PackageCollaborator::get_by_package_id(package.id, &db_connection)?;
-    let owner_id = if accepted_ids.len() > 0 {
-        accepted_ids[0]
-    } else {
-        -1
-    };
+    let owner_ids: HashSet<i32> = PackageCollaborator::get_owner_ids(package.id, &db_connection)?
+        .into_iter()
+        .collect();
+    let permissions: HashMap<i32, CollaboratorPermissions> =
+        PackageCollaborator::get_in_bulk_order_by_role(package.id, accepted_ids.clone(), &db_connection)?
+            .iter()
+            .map(|collaborator| (collaborator.account_id, collaborator.permissions()))
+            .collect();
     // need hashset to find PendingOwner
     let accepted_ids: HashSet<i32> = accepted_ids.into_iter().collect();
     let mut all_invitations: Vec<SerializableInvitation>;
@@ -134,6 +195,14 @@ pub async fn show_package_settings(
     let mut is_current_user_owner = false;
     let mut is_user_collaborator = false;
     let user = request.user()?;
+    let has_permission = |permission| -> Result<bool> {
+        if user.is_anonymous {
+            return Ok(false);
+        }
+        PackageCollaborator::has_permission(&package, user.id, permission, &db_connection)
+    };
+    let can_invite = has_permission(Permission::Invite)?;
+    let can_manage_collaborators = has_permission(Permission::ManageCollaborators)?;
 
     let current_user_email = if user.is_anonymous {
         None
@@ -144,7 +213,7 @@ pub async fn show_package_settings(
     };
 
     if accepted_ids.contains(&user.id) {
-        if owner_id == user.id {
+        if owner_ids.contains(&user.id) {
             is_current_user_owner = true;
         } else {
             is_user_collaborator = true;
@@ -172,25 +241,29 @@ pub async fn show_package_settings(
                 } else {
                     account.email.clone()
                 };
-                if account.id == owner_id {
+                if owner_ids.contains(&account.id) {
                     SerializableInvitation {
                         status: Status::Owner,
                         email: email_or_gh_login,
+                        permissions: None,
                     }
                 } else if pending_owners_ids.contains(&account.id) {
                     SerializableInvitation {
                         status: Status::PendingOwner,
                         email: email_or_gh_login,
+                        permissions: None,
                     }
                 } else if accepted_ids.contains(&account.id) {
                     SerializableInvitation {
                         status: Status::Collaborator,
                         email: email_or_gh_login,
+                        permissions: permissions.get(&account.id).copied(),
                     }
                 } else {
                     SerializableInvitation {
                         status: Status::PendingCollaborator,
                         email: email_or_gh_login,
+                        permissions: None,
                     }
                 }
             })
@@ -202,6 +275,7 @@ pub async fn show_package_settings(
                 .map(|email| SerializableInvitation {
                     status: Status::External,
                     email: email.clone(),
+                    permissions: None,
                 })
                 .collect();
         all_invitations.append(&mut external_email);
@@ -210,15 +284,17 @@ pub async fn show_package_settings(
             Account::get_accounts(&accepted_ids.into_iter().collect(), &db_connection)?
                 .iter()
                 .map(|account| {
-                    if account.id == owner_id {
+                    if owner_ids.contains(&account.id) {
                         SerializableInvitation {
                             status: Status::Owner,
                             email: account.email.clone(),
+                            permissions: None,
                         }
                     } else {
                         SerializableInvitation {
                             status: Status::Collaborator,
                             email: account.email.clone(),
+                            permissions: permissions.get(&account.id).copied(),
                         }
                     }
                 })
@@ -237,7 +313,116 @@ pub async fn show_package_settings(
         ctx.insert("owner_list", &all_invitations);
         ctx.insert("current_email", &current_user_email);
         ctx.insert("is_current_user_owner", &is_current_user_owner);
+        ctx.insert("owners_count", &owner_ids.len());
         ctx.insert("is_current_user_collaborator", &is_user_collaborator);
+        ctx.insert("can_invite", &can_invite);
+        ctx.insert("can_manage_collaborators", &can_manage_collaborators);
+        ctx.insert("can_view_activity", &(is_current_user_owner || user.is_admin));
+        ctx
+    })
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct PackageActivityParams {
+    pub page: Option<i64>,
+}
+
+/// Audit log of a package, only visible to its owners and to site admins.
+pub async fn show_package_activity(
+    request: HttpRequest,
+    Path(package_slug): Path<String>,
+    params: Query<PackageActivityParams>,
+) -> Result<HttpResponse> {
+    let db = request.db_pool()?;
+    let conn = db.get()?;
+    let package = Package::get_by_slug(&package_slug, &conn)?;
+    let user = request.user()?;
+    let is_owner = !user.is_anonymous
+        && PackageCollaborator::get_role(&package, user.id, &conn)
+            .is_ok_and(|role| role == Role::Owner);
+    // Others get the same page as for a package without activity log
+    if !is_owner && !user.is_admin {
+        return request.render(404, "404.html", Context::new());
+    }
+    let package_latest_version = package.get_latest_version(&conn)?;
+
+    let current_page = params.page.unwrap_or(1);
+    let (events, total_count, total_pages) =
+        PackageEvent::get_by_package_id_paginated(package.id, params.page, &conn)?;
+    let account_ids: Vec<i32> = events
+        .iter()
+        .flat_map(|event| [event.actor_id, event.target_account_id])
+        .flatten()
+        .collect::<HashSet<i32>>()
+        .into_iter()
+        .collect();
+    let names: HashMap<i32, String> = Account::get_accounts(&account_ids, &conn)?
+        .into_iter()
+        .map(|account| {
+            let name = if account.is_generated_email() {
+                account.github_login.unwrap_or(account.email)
+            } else {
+                account.email
+            };
+            (account.id, name)
+        })
+        .collect();
+    let events: Vec<SerializableEvent> = events
+        .iter()
+        .map(|event| SerializableEvent::new(event, &names))
+        .collect();
+
+    request.render(200, "packages/activity.html", {
+        let mut ctx = Context::new();
+        ctx.insert("package", &package);
+        ctx.insert("package_version", &package_latest_version);
+        ctx.insert("package_tab", "activity");
+        ctx.insert("is_crawled", &false);
+        ctx.insert("can_view_activity", &true);
+
+        ctx.insert("events", &events);
+        ctx.insert("current_page", &current_page);
+        ctx.insert("total_count", &total_count);
+        ctx.insert("total_pages", &total_pages);
+        ctx
+    })
+}
+
+/// Lets a signed-in user claim the ownership of a crawled package.
+pub async fn show_package_claim(
+    request: HttpRequest,
+    Path(package_slug): Path<String>,
+) -> Result<HttpResponse> {
+    let db = request.db_pool()?;
+    let conn = db.get()?;
+    let package = Package::get_by_slug(&package_slug, &conn)?;
+    if !PackageCollaborator::get_by_package_id(package.id, &conn)?.is_empty() {
+        return request.redirect(&format!("/packages/{}", package.slug));
+    }
+    let package_latest_version = package.get_latest_version(&conn)?;
+    let account = Account::get(request.user()?.id, db)?;
+    // Tokens are only issued by the POST of the claim API, reading this page creates none
+    let claim_token = match PackageClaim::get(package.id, account.id, &conn) {
+        Ok(claim) => Some(claim.token),
+        Err(Error::Database(diesel::NotFound)) => None,
+        Err(e) => return Err(e),
+    };
+    let (repo_url, _) =
+        source_hosts::for_url(&package.repository_url).split_tree_url(&package.repository_url);
+
+    request.render(200, "packages/claim.html", {
+        let mut ctx = Context::new();
+        ctx.insert("package", &package);
+        ctx.insert("package_version", &package_latest_version);
+        ctx.insert("package_tab", "claim");
+        ctx.insert("is_crawled", &true);
+        ctx.insert("is_anonymous", &false);
+
+        ctx.insert("repo_url", &repo_url);
+        ctx.insert("is_github", &source_hosts::is_github_url(&repo_url));
+        ctx.insert("github_login", &account.github_login);
+        ctx.insert("claim_token", &claim_token);
+        ctx.insert("claim_file_path", CLAIM_FILE_PATH);
         ctx
     })
 }
@@ -1,11 +1,18 @@
 use convert_case::{Boundary, Case, Casing};
+use jelly::chrono::{DateTime, Utc};
 use serde::Serialize;
+use std::collections::HashMap;
 use std::hash::{Hash, Hasher};
 
+use crate::package_collaborators::package_collaborator::CollaboratorPermissions;
+use crate::packages::models::package_event::PackageEvent;
+
 #[derive(Serialize, Eq)]
 pub struct SerializableInvitation {
     pub status: Status,
     pub email: String,
+    /// Only set for collaborators that accepted the invitation
+    pub permissions: Option<CollaboratorPermissions>,
 }
 
 impl PartialEq for SerializableInvitation {
@@ -29,6 +36,35 @@ pub enum Status {
     External,
 }
 
+/// An audit log entry with the accounts resolved to their display names.
+#[derive(Serialize)]
+pub struct SerializableEvent {
+    pub description: &'static str,
+    pub actor: Option<String>,
+    pub target: Option<String>,
+    pub details: String,
+    pub via_token: bool,
+    pub via_system: bool,
+    pub ip_address: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl SerializableEvent {
+    pub fn new(event: &PackageEvent, names: &HashMap<i32, String>) -> Self {
+        let name = |id: Option<i32>| id.and_then(|id| names.get(&id).cloned());
+        SerializableEvent {
+            description: event.kind().map_or("unknown event", |kind| kind.description()),
+            actor: name(event.actor_id).or_else(|| event.system_actor.clone()),
+            target: name(event.target_account_id),
+            details: event.details.clone(),
+            via_token: event.api_token_id.is_some(),
+            via_system: event.system_actor.is_some(),
+            ip_address: event.ip_address.clone(),
+            created_at: event.created_at,
+        }
+    }
+}
+
 pub fn slugify_package_name(name: &str) -> String {
     slug::slugify(
         name.from_case(Case::Pascal)
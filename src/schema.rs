@@ -14,6 +14,53 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    account_events (id) {
+        id -> Int4,
+        account_id -> Int4,
+        kind -> Int4,
+        user_agent -> Varchar,
+        ip_address -> Nullable<Varchar>,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    account_sessions (id) {
+        id -> Int4,
+        account_id -> Int4,
+        remember_me_token -> Nullable<Varchar>,
+        password_fingerprint -> Varchar,
+        user_agent -> Varchar,
+        ip_address -> Nullable<Varchar>,
+        created_at -> Timestamptz,
+        last_seen_at -> Timestamptz,
+        expires_at -> Timestamptz,
+        previous_remember_me_token -> Nullable<Varchar>,
+        rotated_at -> Nullable<Timestamptz>,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    account_two_factors (account_id) {
+        account_id -> Int4,
+        secret -> Varchar,
+        recovery_codes -> Array<Text>,
+        last_used_step -> Nullable<Int8>,
+        enabled_at -> Nullable<Timestamptz>,
+        created_at -> Timestamptz,
+    }
+}
+
 diesel::table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector};
@@ -33,6 +80,7 @@ diesel::table! {
         github_id -> Nullable<Int8>,
         avatar -> Nullable<Text>,
         slug -> Nullable<Text>,
+        locked_until -> Nullable<Timestamptz>,
     }
 }
 
@@ -47,6 +95,48 @@ diesel::table! {
         name -> Varchar,
         created_at -> Timestamptz,
         last_used_at -> Nullable<Timestamptz>,
+        endpoint_scopes -> Nullable<Array<Text>>,
+        package_scopes -> Nullable<Array<Text>>,
+        expired_at -> Nullable<Timestamptz>,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    auth_attempts (id) {
+        id -> Int4,
+        kind -> Int4,
+        email -> Nullable<Varchar>,
+        ip_address -> Nullable<Varchar>,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    crawl_cursors (name) {
+        name -> Varchar,
+        page -> Int4,
+        next_run_at -> Nullable<Timestamptz>,
+        locked_until -> Nullable<Timestamptz>,
+        updated_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    crawled_repos (id) {
+        id -> Int4,
+        repository_url -> Text,
+        path -> Text,
+        last_crawled_at -> Timestamptz,
+        created_at -> Timestamptz,
     }
 }
 
@@ -82,16 +172,73 @@ diesel::table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector};
 
-    owner_invitations (invited_user_id, package_id) {
+    github_responses (url) {
+        url -> Text,
+        etag -> Nullable<Text>,
+        body -> Text,
+        is_immutable -> Bool,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    organization_members (organization_id, account_id) {
+        organization_id -> Int4,
+        account_id -> Int4,
+        role -> Int4,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    organizations (id) {
+        id -> Int4,
+        name -> Varchar,
+        slug -> Varchar,
+        description -> Text,
+        created_by -> Int4,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    owner_invitations (id) {
+        id -> Int4,
         invited_user_id -> Int4,
         invited_by_user_id -> Int4,
-        package_id -> Int4,
+        package_id -> Nullable<Int4>,
+        organization_id -> Nullable<Int4>,
+        organization_role -> Nullable<Int4>,
         token -> Text,
         is_transferring -> Bool,
         created_at -> Timestamp,
     }
 }
 
+diesel::table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    package_claims (id) {
+        id -> Int4,
+        package_id -> Int4,
+        account_id -> Int4,
+        token -> Varchar,
+        created_at -> Timestamptz,
+    }
+}
+
 diesel::table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector};
@@ -102,6 +249,73 @@ diesel::table! {
         role -> Int4,
         created_by -> Int4,
         created_at -> Timestamptz,
+        can_publish -> Bool,
+        can_manage_collaborators -> Bool,
+        can_invite -> Bool,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    package_dependencies (id) {
+        id -> Int4,
+        package_version_id -> Int4,
+        name -> Text,
+        kind -> Int4,
+        local -> Nullable<Text>,
+        git -> Nullable<Text>,
+        rev -> Nullable<Text>,
+        subdir -> Nullable<Text>,
+        movey -> Nullable<Text>,
+        dependency_package_id -> Nullable<Int4>,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    package_downloads (id) {
+        id -> Int4,
+        package_version_id -> Int4,
+        fingerprint -> Varchar,
+        counted -> Bool,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    package_events (id) {
+        id -> Int4,
+        package_id -> Int4,
+        kind -> Int4,
+        actor_id -> Nullable<Int4>,
+        api_token_id -> Nullable<Int4>,
+        target_account_id -> Nullable<Int4>,
+        details -> Text,
+        ip_address -> Nullable<Varchar>,
+        created_at -> Timestamptz,
+        system_actor -> Nullable<Varchar>,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    package_version_addresses (id) {
+        id -> Int4,
+        package_version_id -> Int4,
+        name -> Text,
+        address -> Nullable<Text>,
+        is_dev -> Bool,
+        created_at -> Timestamptz,
     }
 }
 
@@ -121,6 +335,21 @@ diesel::table! {
         rev -> Nullable<Text>,
         total_files -> Nullable<Int4>,
         total_size -> Nullable<Int4>,
+        yanked -> Bool,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    package_webhooks (id) {
+        id -> Int4,
+        package_id -> Int4,
+        secret -> Varchar,
+        created_by -> Int4,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
     }
 }
 
@@ -140,6 +369,46 @@ diesel::table! {
         slug -> Text,
         stars_count -> Int4,
         forks_count -> Int4,
+        latest_version_id -> Nullable<Int4>,
+        organization_id -> Nullable<Int4>,
+        require_two_factor -> Bool,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    shadow_package_requests (url, rev, subdir) {
+        url -> Varchar,
+        rev -> Varchar,
+        subdir -> Varchar,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    team_members (team_id, account_id) {
+        team_id -> Int4,
+        account_id -> Int4,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    teams (id) {
+        id -> Int4,
+        organization_id -> Int4,
+        name -> Varchar,
+        slug -> Varchar,
+        is_collaborator -> Bool,
+        created_at -> Timestamptz,
     }
 }
 
@@ -154,22 +423,101 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    webauthn_challenges (challenge) {
+        challenge -> Varchar,
+        ceremony -> Varchar,
+        expires_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    webauthn_credentials (id) {
+        id -> Int4,
+        account_id -> Int4,
+        credential_id -> Varchar,
+        public_key -> Bytea,
+        sign_count -> Int8,
+        name -> Varchar,
+        created_at -> Timestamptz,
+        last_used_at -> Nullable<Timestamptz>,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    webhook_deliveries (delivery_id) {
+        delivery_id -> Varchar,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::joinable!(account_events -> accounts (account_id));
+diesel::joinable!(account_sessions -> accounts (account_id));
+diesel::joinable!(account_two_factors -> accounts (account_id));
 diesel::joinable!(api_tokens -> accounts (account_id));
 diesel::joinable!(external_invitations -> accounts (invited_by_user_id));
 diesel::joinable!(external_invitations -> packages (package_id));
+diesel::joinable!(organization_members -> accounts (account_id));
+diesel::joinable!(organization_members -> organizations (organization_id));
+diesel::joinable!(organizations -> accounts (created_by));
+diesel::joinable!(owner_invitations -> organizations (organization_id));
 diesel::joinable!(owner_invitations -> packages (package_id));
+diesel::joinable!(package_claims -> accounts (account_id));
+diesel::joinable!(package_claims -> packages (package_id));
 diesel::joinable!(package_collaborators -> packages (package_id));
+diesel::joinable!(package_dependencies -> package_versions (package_version_id));
+diesel::joinable!(package_dependencies -> packages (dependency_package_id));
+diesel::joinable!(package_downloads -> package_versions (package_version_id));
+diesel::joinable!(package_events -> packages (package_id));
+diesel::joinable!(package_version_addresses -> package_versions (package_version_id));
 diesel::joinable!(package_versions -> packages (package_id));
+diesel::joinable!(package_webhooks -> accounts (created_by));
+diesel::joinable!(package_webhooks -> packages (package_id));
+diesel::joinable!(packages -> organizations (organization_id));
+diesel::joinable!(team_members -> accounts (account_id));
+diesel::joinable!(team_members -> teams (team_id));
+diesel::joinable!(teams -> organizations (organization_id));
+diesel::joinable!(webauthn_credentials -> accounts (account_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
     _sqlx_migrations,
+    account_events,
+    account_sessions,
+    account_two_factors,
     accounts,
     api_tokens,
+    auth_attempts,
+    crawl_cursors,
+    crawled_repos,
     domains,
     external_invitations,
+    github_responses,
+    organization_members,
+    organizations,
     owner_invitations,
+    package_claims,
     package_collaborators,
+    package_dependencies,
+    package_downloads,
+    package_events,
+    package_version_addresses,
     package_versions,
+    package_webhooks,
     packages,
+    shadow_package_requests,
+    team_members,
+    teams,
     users,
+    webauthn_challenges,
+    webauthn_credentials,
+    webhook_deliveries,
 );
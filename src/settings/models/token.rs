@@ -4,10 +4,11 @@ use crate::schema::accounts::dsl::*;
 use crate::schema::api_tokens;
 use crate::schema::api_tokens::dsl::*;
 use crate::utils::token::SecureToken;
+use diesel::dsl::now;
 use diesel::prelude::*;
 use diesel::{Associations, ExpressionMethods, Identifiable, Queryable, RunQueryDsl};
-use jelly::chrono::NaiveDateTime;
-use jelly::serde::Serialize;
+use jelly::chrono::{NaiveDateTime, Utc};
+use jelly::serde::{Deserialize, Serialize};
 use jelly::DieselPgPool;
 use jelly::Result;
 
@@ -20,6 +21,38 @@ pub struct ApiToken {
     pub name: String,
     pub created_at: NaiveDateTime,
     pub last_used_at: Option<NaiveDateTime>,
+    pub endpoint_scopes: Option<Vec<String>>,
+    pub package_scopes: Option<Vec<String>>,
+    pub expired_at: Option<NaiveDateTime>,
+}
+
+/// Actions a token can be restricted to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum EndpointScope {
+    PublishNew,
+    PublishUpdate,
+    Yank,
+    ManageCollaborators,
+}
+
+impl EndpointScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EndpointScope::PublishNew => "publish-new",
+            EndpointScope::PublishUpdate => "publish-update",
+            EndpointScope::Yank => "yank",
+            EndpointScope::ManageCollaborators => "manage-collaborators",
+        }
+    }
+}
+
+/// Restrictions of a new token. `None` leaves the token unrestricted.
+#[derive(Default)]
+pub struct ApiTokenScopes {
+    pub endpoint_scopes: Option<Vec<EndpointScope>>,
+    pub package_scopes: Option<Vec<String>>,
+    pub expired_at: Option<NaiveDateTime>,
 }
 
 impl ApiToken {
@@ -28,14 +61,31 @@ impl ApiToken {
         account: &Account,
         api_key_name: &str,
         pool: &DieselPgPool,
+    ) -> Result<CreatedApiToken> {
+        Self::insert_with_scopes(account, api_key_name, &ApiTokenScopes::default(), pool)
+    }
+
+    /// Generates a new named API token for a user, restricted to the given scopes
+    pub fn insert_with_scopes(
+        account: &Account,
+        api_key_name: &str,
+        scopes: &ApiTokenScopes,
+        pool: &DieselPgPool,
     ) -> Result<CreatedApiToken> {
         let connection = pool.get()?;
         let secure_token = SecureToken::generate();
+        let endpoint_scope_names: Option<Vec<&str>> = scopes
+            .endpoint_scopes
+            .as_ref()
+            .map(|scope_list| scope_list.iter().map(|scope| scope.as_str()).collect());
         let model: ApiToken = diesel::insert_into(api_tokens::table)
             .values((
                 api_tokens::account_id.eq(account.id),
                 api_tokens::name.eq(api_key_name),
                 api_tokens::token.eq(&secure_token.inner.sha256),
+                api_tokens::endpoint_scopes.eq(endpoint_scope_names),
+                api_tokens::package_scopes.eq(&scopes.package_scopes),
+                api_tokens::expired_at.eq(scopes.expired_at),
             ))
             .get_result(&connection)?;
 
@@ -45,14 +95,19 @@ impl ApiToken {
         })
     }
 
-    /// Check and return an account with given plaintext api token
+    /// Check and return an account with given plaintext api token,
+    /// recording the time the token was used
     pub fn associated_account(plaintext_token: &str, pool: &DieselPgPool) -> Result<Account> {
         let connection = pool.get()?;
         let formatted_sha256 = SecureToken::hash(plaintext_token);
 
-        let matched_token = api_tokens
-            .filter(api_tokens::token.eq(formatted_sha256))
-            .first::<ApiToken>(&connection)?;
+        let matched_token = diesel::update(
+            api_tokens
+                .filter(api_tokens::token.eq(formatted_sha256))
+                .filter(expired_at.is_null().or(expired_at.gt(now))),
+        )
+        .set(last_used_at.eq(now))
+        .get_result::<ApiToken>(&connection)?;
 
         let account = accounts
             .filter(accounts::id.eq(matched_token.account_id))
@@ -61,6 +116,41 @@ impl ApiToken {
         Ok(account)
     }
 
+    /// Returns the token matching a plaintext api token, even if it has expired
+    pub fn get_by_plaintext(plaintext_token: &str, pool: &DieselPgPool) -> Result<Self> {
+        let connection = pool.get()?;
+        let result = api_tokens
+            .filter(api_tokens::token.eq(SecureToken::hash(plaintext_token)))
+            .first::<Self>(&connection)?;
+        Ok(result)
+    }
+
+    pub fn is_expired(&self) -> bool {
+        match self.expired_at {
+            Some(expiry) => expiry <= Utc::now().naive_utc(),
+            None => false,
+        }
+    }
+
+    pub fn has_endpoint_scope(&self, scope: EndpointScope) -> bool {
+        match &self.endpoint_scopes {
+            Some(scope_names) => scope_names.iter().any(|name_| name_ == scope.as_str()),
+            None => true,
+        }
+    }
+
+    /// Package scopes are either package slugs or globs of package names like `move-*`.
+    pub fn allows_package(&self, package_name: &str, package_slug: &str) -> bool {
+        match &self.package_scopes {
+            Some(patterns) => patterns.iter().any(|pattern| {
+                let pattern = pattern.to_lowercase();
+                glob_match(&pattern, &package_name.to_lowercase())
+                    || glob_match(&pattern, &package_slug.to_lowercase())
+            }),
+            None => true,
+        }
+    }
+
     pub fn get(api_token: &str, pool: &DieselPgPool) -> Result<i32> {
         let connection = pool.get()?;
         let sha256 = SecureToken::hash(api_token);
@@ -109,6 +199,33 @@ impl ApiToken {
     }
 }
 
+// Matches `text` against a pattern where `*` stands for any sequence of characters. On a
+// mismatch only the latest `*` is retried, one character further, so that patterns with many
+// stars take at most pattern length × text length steps instead of exponentially many.
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let text = text.chars().collect::<Vec<_>>();
+    let (mut p, mut t) = (0, 0);
+    // The latest `*` and the position in the text it was tried at
+    let mut star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, t));
+            p += 1;
+        } else if p < pattern.len() && pattern[p] == text[t] {
+            p += 1;
+            t += 1;
+        } else if let Some((star_p, star_t)) = star {
+            p = star_p + 1;
+            t = star_t + 1;
+            star = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
 pub struct CreatedApiToken {
     pub model: ApiToken,
     pub plaintext: String,
@@ -117,11 +234,12 @@ pub struct CreatedApiToken {
 #[cfg(test)]
 mod tests {
     use crate::accounts::Account;
-    use crate::settings::models::token::ApiToken;
+    use crate::settings::models::token::{glob_match, ApiToken, ApiTokenScopes, EndpointScope};
     use crate::test::util::setup_user;
     use crate::test::{DatabaseTestContext, DB_POOL};
     use diesel::result::DatabaseErrorKind;
     use diesel::result::Error::DatabaseError;
+    use jelly::chrono::{Duration, Utc};
     use jelly::error::Error;
 
     #[actix_rt::test]
@@ -168,6 +286,23 @@ mod tests {
         }
     }
 
+    #[actix_rt::test]
+    async fn api_token_associated_account_records_last_used_at() {
+        crate::test::init();
+        let _ctx = DatabaseTestContext::new();
+
+        let uid = setup_user(None, None);
+        let account = Account::get(uid, &DB_POOL).unwrap();
+        let result = ApiToken::insert(&account, "name1", &DB_POOL).unwrap();
+        assert!(result.model.last_used_at.is_none());
+
+        ApiToken::associated_account(&result.plaintext, &DB_POOL).unwrap();
+        let token = ApiToken::get_by_id(result.model.id, &DB_POOL).unwrap();
+        assert!(token.last_used_at.is_some());
+
+        assert!(ApiToken::associated_account("invalid-token", &DB_POOL).is_err());
+    }
+
     #[actix_rt::test]
     async fn api_token_get_by_account_works() {
         crate::test::init();
@@ -245,4 +380,86 @@ mod tests {
             0
         );
     }
+
+    #[actix_rt::test]
+    async fn insert_with_scopes_works() {
+        crate::test::init();
+        let _ctx = DatabaseTestContext::new();
+
+        let uid = setup_user(None, None);
+        let account = Account::get(uid, &DB_POOL).unwrap();
+        let scopes = ApiTokenScopes {
+            endpoint_scopes: Some(vec![EndpointScope::PublishUpdate, EndpointScope::Yank]),
+            package_scopes: Some(vec!["move-*".to_string()]),
+            expired_at: Some(Utc::now().naive_utc() + Duration::days(30)),
+        };
+        let result = ApiToken::insert_with_scopes(&account, "name1", &scopes, &DB_POOL).unwrap();
+        let token = ApiToken::get_by_id(result.model.id, &DB_POOL).unwrap();
+
+        assert_eq!(
+            token.endpoint_scopes,
+            Some(vec!["publish-update".to_string(), "yank".to_string()])
+        );
+        assert_eq!(token.package_scopes, Some(vec!["move-*".to_string()]));
+        assert!(!token.is_expired());
+        assert!(token.has_endpoint_scope(EndpointScope::Yank));
+        assert!(!token.has_endpoint_scope(EndpointScope::PublishNew));
+        assert!(token.allows_package("MoveStdlib", "move-stdlib"));
+        assert!(!token.allows_package("Stdlib", "stdlib"));
+    }
+
+    #[actix_rt::test]
+    async fn unscoped_token_allows_everything() {
+        crate::test::init();
+        let _ctx = DatabaseTestContext::new();
+
+        let uid = setup_user(None, None);
+        let account = Account::get(uid, &DB_POOL).unwrap();
+        let token = ApiToken::insert(&account, "name1", &DB_POOL).unwrap().model;
+
+        assert!(!token.is_expired());
+        assert!(token.has_endpoint_scope(EndpointScope::PublishNew));
+        assert!(token.has_endpoint_scope(EndpointScope::ManageCollaborators));
+        assert!(token.allows_package("anything", "anything"));
+    }
+
+    #[actix_rt::test]
+    async fn associated_account_rejects_expired_token() {
+        crate::test::init();
+        let _ctx = DatabaseTestContext::new();
+
+        let uid = setup_user(None, None);
+        let account = Account::get(uid, &DB_POOL).unwrap();
+        let scopes = ApiTokenScopes {
+            expired_at: Some(Utc::now().naive_utc() - Duration::minutes(1)),
+            ..Default::default()
+        };
+        let result = ApiToken::insert_with_scopes(&account, "name1", &scopes, &DB_POOL).unwrap();
+
+        assert!(result.model.is_expired());
+        assert!(ApiToken::associated_account(&result.plaintext, &DB_POOL).is_err());
+        let token = ApiToken::get_by_plaintext(&result.plaintext, &DB_POOL).unwrap();
+        assert_eq!(token.id, result.model.id);
+        assert!(token.last_used_at.is_none());
+    }
+
+    #[test]
+    fn glob_match_works() {
+        assert!(glob_match("move-stdlib", "move-stdlib"));
+        assert!(glob_match("move-*", "move-stdlib"));
+        assert!(glob_match("*-stdlib", "move-stdlib"));
+        assert!(glob_match("m*e-*b", "move-stdlib"));
+        assert!(glob_match("*", "anything"));
+        assert!(!glob_match("move-*", "stdlib"));
+        assert!(!glob_match("move", "move-stdlib"));
+        assert!(glob_match("**a*", "banana"));
+        assert!(glob_match("m*v*", "m\u{e9}ve"));
+        assert!(!glob_match("*a*b", "aaab-c"));
+    }
+
+    #[test]
+    fn glob_match_does_not_backtrack_exponentially() {
+        let pattern = format!("{}b", "a*".repeat(32));
+        assert!(!glob_match(&pattern, &"a".repeat(64)));
+    }
 }
@@ -1,9 +1,14 @@
 use crate::accounts::forms::ChangePasswordForm;
+use crate::accounts::models::account_event::{AccountEvent, AccountEventKind};
+use crate::accounts::models::account_session::{AccountSession, SessionDevice};
+use crate::accounts::models::account_two_factor::AccountTwoFactor;
+use crate::accounts::models::webauthn_credential::WebauthnCredential;
 use crate::accounts::Account;
 use crate::constants;
 use crate::package_collaborators::models::owner_invitation::OwnerInvitation;
 use crate::packages::Package;
 use crate::settings::models::token::ApiToken;
+use crate::utils::request_utils;
 
 use jelly::actix_session::UserSession;
 use jelly::actix_web::http::header;
@@ -17,17 +22,39 @@ pub async fn profile(request: HttpRequest) -> Result<HttpResponse> {
     let user = request.user()?;
     let db = request.db_pool()?;
     let account = Account::get(user.id, db)?;
+    let mut context = Context::new();
+    insert_security(&request, &account, &mut context)?;
     request.render(200, "settings/profile.html", {
-        let mut context = Context::new();
         context.insert("account", &account);
         context.insert("profile_tab", "profile");
         context
     })
 }
 
+/// Adds the signed-in browsers, the two-factor authentication status, the passkeys and the
+/// security log of the account to the context of the profile page.
+fn insert_security(request: &HttpRequest, account: &Account, context: &mut Context) -> Result<()> {
+    let conn = request.db_pool()?.get()?;
+    let sessions = AccountSession::get_by_account(account, &conn)?;
+    let current_session_id = request_utils::current_session_id(&request.get_session())?;
+    context.insert("sessions", &sessions);
+    context.insert("current_session_id", &current_session_id);
+    context.insert(
+        "two_factor_enabled",
+        &AccountTwoFactor::is_enabled(account.id, &conn)?,
+    );
+    context.insert(
+        "passkeys",
+        &WebauthnCredential::get_by_account(account.id, &conn)?,
+    );
+    context.insert("security_log", &AccountEvent::security_log(account, &conn)?);
+    Ok(())
+}
+
 pub async fn change_password(
     request: HttpRequest,
     form: Form<ChangePasswordForm>,
+    device: SessionDevice,
 ) -> Result<HttpResponse> {
     let mut form = form.into_inner();
     let user = request.user()?;
@@ -37,8 +64,9 @@ pub async fn change_password(
     form.email = Some(account.email.clone());
 
     if !form.is_valid() {
+        let mut context = Context::new();
+        insert_security(&request, &account, &mut context)?;
         return request.render(200, "settings/profile.html", {
-            let mut context = Context::new();
             context.insert("form", &form);
             context.insert("is_ok", &false);
             context.insert("account", &account);
@@ -68,6 +96,12 @@ pub async fn change_password(
         }
     };
     if is_ok {
+        AccountEvent::record(
+            user.id,
+            AccountEventKind::PasswordChanged,
+            &device,
+            &db.get()?,
+        )?;
         request.get_session().clear();
         return Ok(HttpResponse::Found()
             .header(header::SET_COOKIE, constants::REMEMBER_ME_TOKEN_INVALIDATE)
@@ -78,8 +112,9 @@ pub async fn change_password(
             .header(header::LOCATION, "/accounts/login/")
             .finish());
     }
+    let mut context = Context::new();
+    insert_security(&request, &account, &mut context)?;
     request.render(200, "settings/profile.html", {
-        let mut context = Context::new();
         context.insert("error", message);
         context.insert("account", &account);
         context.insert("connect-status", &account.email);
@@ -143,10 +178,16 @@ pub async fn show_tokens(request: HttpRequest) -> Result<HttpResponse> {
     let db = request.db_pool()?;
     if let Ok(user) = request.user() {
         let tokens = ApiToken::get_by_account(user.id, db)?;
+        let expired_token_ids: Vec<i32> = tokens
+            .iter()
+            .filter(|token| token.is_expired())
+            .map(|token| token.id)
+            .collect();
 
         request.render(200, "settings/tokens.html", {
             let mut ctx = Context::new();
             ctx.insert("tokens", &tokens);
+            ctx.insert("expired_token_ids", &expired_token_ids);
             ctx.insert("profile_tab", "tokens");
             ctx
         })
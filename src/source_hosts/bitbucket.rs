@@ -0,0 +1,206 @@
+use jelly::error::Error;
+use jelly::error::Error::Generic;
+use serde::Deserialize;
+use std::env;
+
+use super::{
+    call_api, count_files_in, fetch_file, repo_path, Authorization, SourceHost, TreeEntry,
+    MAX_TREE_PAGES,
+};
+use crate::github_service::GithubRepoInfo;
+
+// Depth of the directory listings, Bitbucket lists subdirectories breadth first up to it
+const TREE_MAX_DEPTH: usize = 100;
+// Entries listed per page of a directory listing, the most Bitbucket allows
+const TREE_PAGE_SIZE: usize = 100;
+
+/// bitbucket.org, whose API is served from api.bitbucket.org.
+pub struct BitbucketHost {
+    base_url: String,
+    api_base_url: String,
+}
+
+#[derive(Default, Deserialize)]
+struct BitbucketRepo {
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    size: i64,
+    mainbranch: Option<BitbucketBranch>,
+}
+
+#[derive(Deserialize)]
+struct BitbucketBranch {
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct BitbucketCommit {
+    hash: String,
+}
+
+// A page of a paginated listing, with the url of the next page if any
+#[derive(Deserialize)]
+struct BitbucketPage<T> {
+    #[serde(default = "Vec::new")]
+    values: Vec<T>,
+    next: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct BitbucketTreeEntry {
+    path: String,
+    // `commit_file` for files, `commit_directory` for directories
+    #[serde(rename = "type")]
+    kind: String,
+}
+
+impl BitbucketHost {
+    pub fn new(base_url: &str, api_base_url: &str) -> Self {
+        BitbucketHost {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_base_url: api_base_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    // e.g. https://bitbucket.org/move/stdlib -> https://api.bitbucket.org/2.0/repositories/move/stdlib
+    fn api_url(&self, repo_url: &str) -> String {
+        format!(
+            "{}/2.0/repositories/{}",
+            self.api_base_url,
+            repo_path(&self.base_url, repo_url)
+        )
+    }
+
+    fn authorization(&self) -> Authorization {
+        env::var("BITBUCKET_ACCESS_TOKEN")
+            .ok()
+            .filter(|access_token| !access_token.is_empty())
+            .map(|access_token| ("Authorization", format!("Bearer {}", access_token)))
+    }
+
+    fn get_repo(&self, repo_url: &str) -> Result<BitbucketRepo, Error> {
+        let url = self.api_url(repo_url);
+        call_api(&self.api_base_url, &url, self.authorization())?
+            .error_for_status()
+            .and_then(|response| response.json::<BitbucketRepo>())
+            .map_err(|error| {
+                Generic(format!(
+                    "Error getting Bitbucket repo. url: {:?}, error: {}",
+                    url, error
+                ))
+            })
+    }
+}
+
+impl SourceHost for BitbucketHost {
+    // Bitbucket has no stars, and licenses aren't detected
+    fn repo_info(&self, repo_url: &str) -> Result<GithubRepoInfo, Error> {
+        let repo = self.get_repo(repo_url)?;
+        Ok(GithubRepoInfo {
+            description: Some(repo.description).filter(|d| !d.is_empty()),
+            // Github reports sizes in KB
+            size: (repo.size / 1024) as i32,
+            stargazers_count: 0,
+            forks_count: 0,
+            default_branch: repo.mainbranch.map(|b| b.name).unwrap_or_default(),
+            license: None,
+        })
+    }
+
+    // Commits of every branch are listed newest first, so only the main branch is listed
+    fn latest_commit_sha(&self, repo_url: &str) -> Result<String, Error> {
+        let branch = match self.get_repo(repo_url)?.mainbranch {
+            Some(branch) => branch.name,
+            None => {
+                return Err(Generic(format!(
+                    "Error getting repo commit. url: {:?}, error: No main branch",
+                    repo_url
+                )))
+            }
+        };
+        let url = format!("{}/commits/{}?pagelen=1", self.api_url(repo_url), branch);
+        match call_api(&self.api_base_url, &url, self.authorization())?
+            .error_for_status()
+            .and_then(|response| response.json::<BitbucketPage<BitbucketCommit>>())
+        {
+            Ok(page) if !page.values.is_empty() => Ok(page.values[0].hash.clone()),
+            Ok(_) => Err(Generic(format!(
+                "Error getting repo commit. url: {:?}, error: Empty response",
+                url
+            ))),
+            Err(error) => Err(Generic(format!(
+                "Error getting repo commit. url: {:?}, error: {}",
+                url, error
+            ))),
+        }
+    }
+
+    // Raw files are read through the API, so that the access token only goes to it, e.g.
+    // https://api.bitbucket.org/2.0/repositories/move/stdlib/src/main/README.md
+    fn raw_file_url(&self, repo_url: &str, rev: &str, path: &str) -> String {
+        format!("{}/src/{}/{}", self.api_url(repo_url), rev, path)
+    }
+
+    fn fetch_file(&self, url: &str, _is_immutable: bool) -> Result<Option<String>, Error> {
+        fetch_file(&self.api_base_url, url, self.authorization())
+    }
+
+    // The subdir is listed a page at a time, following the url of the next page, e.g.
+    // https://api.bitbucket.org/2.0/repositories/move/stdlib/src/main/sources/?max_depth=100&pagelen=100
+    fn count_files(&self, repo_url: &str, rev: &str, subdir: &str) -> Result<i32, Error> {
+        let subdir = subdir.trim_matches('/');
+        let mut url = format!(
+            "{}/src/{}/{}?max_depth={}&pagelen={}",
+            self.api_url(repo_url),
+            rev,
+            if subdir.is_empty() {
+                "".to_string()
+            } else {
+                format!("{}/", subdir)
+            },
+            TREE_MAX_DEPTH,
+            TREE_PAGE_SIZE
+        );
+        let mut entries = vec![];
+        for _ in 0..MAX_TREE_PAGES {
+            let page = call_api(&self.api_base_url, &url, self.authorization())?
+                .error_for_status()
+                .and_then(|response| response.json::<BitbucketPage<BitbucketTreeEntry>>())
+                .map_err(|error| {
+                    Generic(format!(
+                        "Error listing repo files. url: {:?}, error: {}",
+                        url, error
+                    ))
+                })?;
+            entries.extend(page.values.into_iter().map(|entry| TreeEntry {
+                path: entry.path,
+                kind: if entry.kind == "commit_file" {
+                    "blob".to_string()
+                } else {
+                    "tree".to_string()
+                },
+            }));
+            match page.next {
+                Some(next) => url = next,
+                None => break,
+            }
+        }
+        Ok(count_files_in(&entries, subdir))
+    }
+
+    // e.g. https://bitbucket.org/move/stdlib/src/main/sources
+    fn tree_url(&self, repo_url: &str, rev: &str, subdir: &str) -> String {
+        format!("{}/src/{}/{}", repo_url, rev, subdir)
+    }
+
+    fn split_tree_url(&self, url: &str) -> (String, String) {
+        match url.split_once("/src/") {
+            Some((repo_url, rev_and_subdir)) => {
+                let subdir = rev_and_subdir.split_once('/').map_or("", |(_, s)| s);
+                (repo_url.to_string(), subdir.to_string())
+            }
+            None => (url.to_string(), "".to_string()),
+        }
+    }
+}
@@ -0,0 +1,161 @@
+use jelly::error::Error;
+use jelly::error::Error::Generic;
+use serde::Deserialize;
+
+use super::{
+    access_token, call_api, count_files_in, fetch_file, is_commit_sha, repo_path, Authorization,
+    SourceHost, TreeEntry, MAX_TREE_PAGES,
+};
+use crate::github_service::GithubRepoInfo;
+
+/// Gitea, Forgejo and other hosts with a Gitea compatible API.
+pub struct GiteaHost {
+    base_url: String,
+}
+
+#[derive(Default, Deserialize)]
+struct GiteaRepo {
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    size: i32,
+    #[serde(default)]
+    stars_count: i32,
+    #[serde(default)]
+    forks_count: i32,
+    #[serde(default)]
+    default_branch: String,
+}
+
+#[derive(Deserialize)]
+struct GiteaCommit {
+    sha: String,
+}
+
+#[derive(Deserialize)]
+struct GiteaTree {
+    #[serde(default)]
+    tree: Vec<TreeEntry>,
+    #[serde(default)]
+    truncated: bool,
+}
+
+impl GiteaHost {
+    pub fn new(base_url: &str) -> Self {
+        GiteaHost {
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    // e.g. https://gitea.com/move/stdlib -> https://gitea.com/api/v1/repos/move/stdlib
+    fn api_url(&self, repo_url: &str) -> String {
+        format!(
+            "{}/api/v1/repos/{}",
+            self.base_url,
+            repo_path(&self.base_url, repo_url)
+        )
+    }
+
+    fn authorization(&self) -> Authorization {
+        access_token("GITEA_ACCESS_TOKEN", "GITEA_BASE_URL", None, &self.base_url)
+            .map(|access_token| ("Authorization", format!("token {}", access_token)))
+    }
+}
+
+impl SourceHost for GiteaHost {
+    fn repo_info(&self, repo_url: &str) -> Result<GithubRepoInfo, Error> {
+        let url = self.api_url(repo_url);
+        // Plain git hosts have no API, their packages are saved without repo info
+        let repo = match call_api(&self.base_url, &url, self.authorization())?.json::<GiteaRepo>() {
+            Ok(repo) => repo,
+            Err(error) => {
+                warn!("Error getting repo info. url: {:?}, error: {}", url, error);
+                Default::default()
+            }
+        };
+        Ok(GithubRepoInfo {
+            description: Some(repo.description).filter(|d| !d.is_empty()),
+            size: repo.size,
+            stargazers_count: repo.stars_count,
+            forks_count: repo.forks_count,
+            default_branch: repo.default_branch,
+            license: None,
+        })
+    }
+
+    fn latest_commit_sha(&self, repo_url: &str) -> Result<String, Error> {
+        let url = format!("{}/commits?limit=1", self.api_url(repo_url));
+        match call_api(&self.base_url, &url, self.authorization())?.json::<Vec<GiteaCommit>>() {
+            Ok(commits) if !commits.is_empty() => Ok(commits[0].sha.clone()),
+            Ok(_) => Err(Generic(format!(
+                "Error getting repo commit. url: {:?}, error: Empty response",
+                url
+            ))),
+            Err(error) => Err(Generic(format!(
+                "Error getting repo commit. url: {:?}, error: {}",
+                url, error
+            ))),
+        }
+    }
+
+    // e.g. https://gitea.com/move/stdlib/raw/main/README.md
+    fn raw_file_url(&self, repo_url: &str, rev: &str, path: &str) -> String {
+        format!("{}/raw/{}/{}", repo_url, rev, path)
+    }
+
+    fn fetch_file(&self, url: &str, _is_immutable: bool) -> Result<Option<String>, Error> {
+        fetch_file(&self.base_url, url, self.authorization())
+    }
+
+    // The tree is listed a page at a time, e.g.
+    // https://gitea.com/api/v1/repos/move/stdlib/git/trees/main?recursive=true&page=1
+    fn count_files(&self, repo_url: &str, rev: &str, subdir: &str) -> Result<i32, Error> {
+        let mut entries = vec![];
+        for page in 1..=MAX_TREE_PAGES {
+            let url = format!(
+                "{}/git/trees/{}?recursive=true&page={}",
+                self.api_url(repo_url),
+                rev,
+                page
+            );
+            let tree = call_api(&self.base_url, &url, self.authorization())?
+                .error_for_status()
+                .and_then(|response| response.json::<GiteaTree>())
+                .map_err(|error| {
+                    Generic(format!(
+                        "Error listing repo files. url: {:?}, error: {}",
+                        url, error
+                    ))
+                })?;
+            entries.extend(tree.tree);
+            if !tree.truncated {
+                break;
+            }
+        }
+        Ok(count_files_in(&entries, subdir))
+    }
+
+    // e.g. https://gitea.com/move/stdlib/src/branch/main/sources
+    fn tree_url(&self, repo_url: &str, rev: &str, subdir: &str) -> String {
+        let kind = if is_commit_sha(rev) {
+            "commit"
+        } else {
+            "branch"
+        };
+        format!("{}/src/{}/{}/{}", repo_url, kind, rev, subdir)
+    }
+
+    fn split_tree_url(&self, url: &str) -> (String, String) {
+        match url.split_once("/src/") {
+            Some((repo_url, ref_and_subdir)) => {
+                let ref_and_subdir = ["branch/", "commit/", "tag/"]
+                    .iter()
+                    .find_map(|kind| ref_and_subdir.strip_prefix(kind))
+                    .unwrap_or(ref_and_subdir);
+                let subdir = ref_and_subdir.split_once('/').map_or("", |(_, s)| s);
+                (repo_url.to_string(), subdir.to_string())
+            }
+            None => (url.to_string(), "".to_string()),
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use jelly::error::Error;
+use jelly::error::Error::Generic;
+use reqwest::StatusCode;
+use serde::Deserialize;
+
+use super::{count_files_in, is_commit_sha, SourceHost, TreeEntry};
+use crate::github_service::{
+    call_github_api, get_repo_description_and_size, get_repo_latest_commit_sha, GithubRepoInfo,
+};
+
+/// github.com, called through the shared Github client.
+pub struct GithubHost;
+
+#[derive(Deserialize)]
+struct GithubTree {
+    tree: Vec<TreeEntry>,
+    #[serde(default)]
+    truncated: bool,
+}
+
+impl SourceHost for GithubHost {
+    fn repo_info(&self, repo_url: &str) -> Result<GithubRepoInfo, Error> {
+        get_repo_description_and_size(repo_url)
+    }
+
+    fn latest_commit_sha(&self, repo_url: &str) -> Result<String, Error> {
+        get_repo_latest_commit_sha(repo_url)
+    }
+
+    // e.g. https://raw.githubusercontent.com/move-language/move/main/README.md
+    fn raw_file_url(&self, repo_url: &str, rev: &str, path: &str) -> String {
+        format!(
+            "{}/{}/{}",
+            repo_url.replace("https://github.com", "https://raw.githubusercontent.com"),
+            rev,
+            path
+        )
+    }
+
+    fn fetch_file(&self, url: &str, is_immutable: bool) -> Result<Option<String>, Error> {
+        let response = call_github_api(url, is_immutable)?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        Ok(Some(response.text()))
+    }
+
+    // e.g. https://api.github.com/repos/move-language/move/git/trees/main?recursive=1
+    fn count_files(&self, repo_url: &str, rev: &str, subdir: &str) -> Result<i32, Error> {
+        let url = format!(
+            "{}/git/trees/{}?recursive=1",
+            repo_url.replace("https://github.com/", "https://api.github.com/repos/"),
+            rev
+        );
+        let response = call_github_api(&url, is_commit_sha(rev))?;
+        if response.status() != StatusCode::OK {
+            return Err(Generic(format!(
+                "Error listing repo files. url: {:?}, status: {}",
+                url,
+                response.status()
+            )));
+        }
+        let tree = response.json::<GithubTree>().map_err(|error| {
+            Generic(format!(
+                "Error listing repo files. url: {:?}, error: {}",
+                url, error
+            ))
+        })?;
+        // Github lists up to 100,000 entries
+        if tree.truncated {
+            warn!("Repo file list is truncated. url: {:?}", url);
+        }
+        Ok(count_files_in(&tree.tree, subdir))
+    }
+
+    // e.g. https://github.com/move-language/move/tree/main/language/move-stdlib
+    fn tree_url(&self, repo_url: &str, rev: &str, subdir: &str) -> String {
+        format!("{}/tree/{}/{}", repo_url, rev, subdir)
+    }
+
+    // Both /tree/ and /blob/ urls have the rev in the 7th token:
+    // https://github.com/move-language/move/tree/main/language/evm/hardhat-examples/contracts/ABIStruct
+    //      -> https://github.com/move-language/move, language/evm/hardhat-examples/contracts/ABIStruct
+    fn split_tree_url(&self, url: &str) -> (String, String) {
+        let tokens = url.split('/').collect::<Vec<&str>>();
+        if tokens.len() > 7 {
+            (tokens[..5].join("/"), tokens[7..].join("/"))
+        } else {
+            (url.to_string(), "".to_string())
+        }
+    }
+}
@@ -0,0 +1,183 @@
+use jelly::error::Error;
+use jelly::error::Error::Generic;
+use serde::Deserialize;
+use url::form_urlencoded;
+
+use super::{
+    access_token, call_api, count_files_in, fetch_file, repo_path, Authorization, SourceHost,
+    TreeEntry, DEFAULT_GITLAB_BASE_URL, MAX_TREE_PAGES,
+};
+use crate::github_service::{GithubLicenseInfo, GithubRepoInfo};
+
+/// gitlab.com or a self-hosted Gitlab, e.g. gitlab.example.com.
+pub struct GitlabHost {
+    base_url: String,
+}
+
+#[derive(Default, Deserialize)]
+struct GitlabProject {
+    description: Option<String>,
+    #[serde(default)]
+    star_count: i32,
+    #[serde(default)]
+    forks_count: i32,
+    default_branch: Option<String>,
+    license: Option<GitlabLicense>,
+    statistics: Option<GitlabStatistics>,
+}
+
+#[derive(Deserialize)]
+struct GitlabLicense {
+    key: String,
+    name: String,
+    html_url: Option<String>,
+}
+
+// Only returned to project members
+#[derive(Deserialize)]
+struct GitlabStatistics {
+    repository_size: i64,
+}
+
+#[derive(Deserialize)]
+struct GitlabCommit {
+    id: String,
+}
+
+// Entries listed per page of a repository tree
+const TREE_PAGE_SIZE: usize = 100;
+
+impl GitlabHost {
+    pub fn new(base_url: &str) -> Self {
+        GitlabHost {
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    // e.g. https://gitlab.com/api/v4/projects/move%2Fstdlib
+    fn project_url(&self, repo_url: &str) -> String {
+        format!(
+            "{}/api/v4/projects/{}",
+            self.base_url,
+            repo_path(&self.base_url, repo_url).replace('/', "%2F")
+        )
+    }
+
+    fn authorization(&self) -> Authorization {
+        access_token(
+            "GITLAB_ACCESS_TOKEN",
+            "GITLAB_BASE_URL",
+            Some(DEFAULT_GITLAB_BASE_URL),
+            &self.base_url,
+        )
+        .map(|access_token| ("PRIVATE-TOKEN", access_token))
+    }
+}
+
+impl SourceHost for GitlabHost {
+    fn repo_info(&self, repo_url: &str) -> Result<GithubRepoInfo, Error> {
+        let url = format!(
+            "{}?license=true&statistics=true",
+            self.project_url(repo_url)
+        );
+        let project =
+            match call_api(&self.base_url, &url, self.authorization())?.json::<GitlabProject>() {
+                Ok(project) => project,
+                Err(error) => {
+                    error!(
+                        "Error getting Gitlab project. url: {:?}, error: {}",
+                        url, error
+                    );
+                    Default::default()
+                }
+            };
+        Ok(GithubRepoInfo {
+            description: project.description.filter(|d| !d.is_empty()),
+            // Github reports sizes in KB
+            size: project
+                .statistics
+                .map_or(0, |s| (s.repository_size / 1024) as i32),
+            stargazers_count: project.star_count,
+            forks_count: project.forks_count,
+            default_branch: project.default_branch.unwrap_or_default(),
+            license: project.license.map(|license| GithubLicenseInfo {
+                key: license.key,
+                name: license.name,
+                url: license.html_url.unwrap_or_default(),
+            }),
+        })
+    }
+
+    fn latest_commit_sha(&self, repo_url: &str) -> Result<String, Error> {
+        let url = format!(
+            "{}/repository/commits?per_page=1",
+            self.project_url(repo_url)
+        );
+        match call_api(&self.base_url, &url, self.authorization())?.json::<Vec<GitlabCommit>>() {
+            Ok(commits) if !commits.is_empty() => Ok(commits[0].id.clone()),
+            Ok(_) => Err(Generic(format!(
+                "Error getting repo commit. url: {:?}, error: Empty response",
+                url
+            ))),
+            Err(error) => Err(Generic(format!(
+                "Error getting repo commit. url: {:?}, error: {}",
+                url, error
+            ))),
+        }
+    }
+
+    // e.g. https://gitlab.com/move/stdlib/-/raw/main/README.md
+    fn raw_file_url(&self, repo_url: &str, rev: &str, path: &str) -> String {
+        format!("{}/-/raw/{}/{}", repo_url, rev, path)
+    }
+
+    fn fetch_file(&self, url: &str, _is_immutable: bool) -> Result<Option<String>, Error> {
+        fetch_file(&self.base_url, url, self.authorization())
+    }
+
+    // The tree of the subdir is listed a page at a time, e.g.
+    // https://gitlab.com/api/v4/projects/move%2Fstdlib/repository/tree?ref=main&path=sources&recursive=true&per_page=100&page=1
+    fn count_files(&self, repo_url: &str, rev: &str, subdir: &str) -> Result<i32, Error> {
+        let mut entries = vec![];
+        for page in 1..=MAX_TREE_PAGES {
+            let query = form_urlencoded::Serializer::new(String::new())
+                .append_pair("ref", rev)
+                .append_pair("path", subdir.trim_matches('/'))
+                .append_pair("recursive", "true")
+                .append_pair("per_page", &TREE_PAGE_SIZE.to_string())
+                .append_pair("page", &page.to_string())
+                .finish();
+            let url = format!("{}/repository/tree?{}", self.project_url(repo_url), query);
+            let page_entries = call_api(&self.base_url, &url, self.authorization())?
+                .error_for_status()
+                .and_then(|response| response.json::<Vec<TreeEntry>>())
+                .map_err(|error| {
+                    Generic(format!(
+                        "Error listing repo files. url: {:?}, error: {}",
+                        url, error
+                    ))
+                })?;
+            let is_last_page = page_entries.len() < TREE_PAGE_SIZE;
+            entries.extend(page_entries);
+            if is_last_page {
+                break;
+            }
+        }
+        Ok(count_files_in(&entries, subdir))
+    }
+
+    // e.g. https://gitlab.com/move/stdlib/-/tree/main/sources
+    fn tree_url(&self, repo_url: &str, rev: &str, subdir: &str) -> String {
+        format!("{}/-/tree/{}/{}", repo_url, rev, subdir)
+    }
+
+    fn split_tree_url(&self, url: &str) -> (String, String) {
+        match url.split_once("/-/tree/") {
+            Some((repo_url, rev_and_subdir)) => {
+                let subdir = rev_and_subdir.split_once('/').map_or("", |(_, s)| s);
+                (repo_url.to_string(), subdir.to_string())
+            }
+            None => (url.to_string(), "".to_string()),
+        }
+    }
+}
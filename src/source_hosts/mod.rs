@@ -0,0 +1,302 @@
+use jelly::error::Error;
+use jelly::error::Error::Generic;
+use reqwest::blocking::Response;
+use reqwest::redirect::Policy;
+use reqwest::StatusCode;
+use serde::Deserialize;
+use std::env;
+use std::net::Ipv4Addr;
+use url::{Host, Url};
+
+use crate::github_service::{GithubRepoInfo, APP_USER_AGENT};
+
+mod bitbucket;
+mod gitea;
+mod github;
+mod gitlab;
+mod unsupported;
+pub use bitbucket::BitbucketHost;
+pub use gitea::GiteaHost;
+pub use github::GithubHost;
+pub use gitlab::GitlabHost;
+pub use unsupported::UnsupportedHost;
+
+#[cfg(test)]
+mod tests;
+
+const GITHUB_ORIGIN: &str = "https://github.com";
+const BITBUCKET_ORIGIN: &str = "https://bitbucket.org";
+const BITBUCKET_API_BASE_URL: &str = "https://api.bitbucket.org";
+const DEFAULT_GITLAB_BASE_URL: &str = "https://gitlab.com";
+const MAX_REDIRECTS: usize = 5;
+// Paged tree listings stop there, so huge repositories can't keep a job busy
+const MAX_TREE_PAGES: usize = 50;
+
+/// A service hosting git repositories that packages can be fetched from.
+pub trait SourceHost {
+    /// Description, size, stars and default branch of the repository.
+    fn repo_info(&self, repo_url: &str) -> Result<GithubRepoInfo, Error>;
+
+    /// Sha of the latest commit on the default branch.
+    fn latest_commit_sha(&self, repo_url: &str) -> Result<String, Error>;
+
+    /// Url of the raw content of the file at `path` in `rev`.
+    fn raw_file_url(&self, repo_url: &str, rev: &str, path: &str) -> String;
+
+    /// Content of a raw file, or `None` if there is no such file.
+    fn fetch_file(&self, url: &str, is_immutable: bool) -> Result<Option<String>, Error>;
+
+    /// Number of files in `subdir` of `rev`, or in the whole repository for an empty subdir.
+    fn count_files(&self, repo_url: &str, rev: &str, subdir: &str) -> Result<i32, Error>;
+
+    /// Url of the web page of `subdir` in `rev`.
+    fn tree_url(&self, repo_url: &str, rev: &str, subdir: &str) -> String;
+
+    /// Splits the url of a subdir's web page into the repository url and the subdir.
+    /// Urls of a repository are returned as is with an empty subdir.
+    fn split_tree_url(&self, url: &str) -> (String, String);
+
+    /// Whether packages can be fetched from the host.
+    fn is_supported(&self) -> bool {
+        true
+    }
+}
+
+/// The host of a repository url, e.g. `https://gitlab.com/move/stdlib`. Packages can only be
+/// fetched from Github, Bitbucket and the Gitlab and Gitea instances listed in
+/// `GITLAB_BASE_URL` and `GITEA_BASE_URL`, so that requests and access tokens never go to any
+/// other host. Urls of other hosts can still be split and joined, but fetching from them fails.
+pub fn for_url(repo_url: &str) -> Box<dyn SourceHost> {
+    let origin = origin(repo_url).filter(|origin| origin.starts_with("https://"));
+    if origin.as_deref() == Some(GITHUB_ORIGIN) {
+        return Box::new(GithubHost);
+    }
+    if origin.as_deref() == Some(BITBUCKET_ORIGIN) {
+        return Box::new(BitbucketHost::new(BITBUCKET_ORIGIN, BITBUCKET_API_BASE_URL));
+    }
+    if let Some(origin) = origin.filter(|_| has_public_host(repo_url)) {
+        if configured_origins("GITLAB_BASE_URL", Some(DEFAULT_GITLAB_BASE_URL)).contains(&origin) {
+            return Box::new(GitlabHost::new(&origin));
+        }
+        if configured_origins("GITEA_BASE_URL", None).contains(&origin) {
+            return Box::new(GiteaHost::new(&origin));
+        }
+    }
+    Box::new(UnsupportedHost(GiteaHost::new(&base_url(repo_url))))
+}
+
+/// Whether packages can be fetched from the repository, see `for_url`.
+pub fn is_supported_url(repo_url: &str) -> bool {
+    for_url(repo_url).is_supported()
+}
+
+/// Whether the repository is hosted on github.com.
+pub fn is_github_url(repo_url: &str) -> bool {
+    domain(repo_url) == "github.com"
+}
+
+/// Turns a git url into the https url of the repository, e.g.
+/// `git@gitlab.com:move/stdlib.git` -> `https://gitlab.com/move/stdlib`
+pub fn normalize_repo_url(url: &str) -> String {
+    let mut url = url.trim().trim_end_matches('/').to_string();
+    if let Some(ssh_url) = url.strip_prefix("git@") {
+        url = format!("https://{}", ssh_url.replacen(':', "/", 1));
+    }
+    url.trim_end_matches(".git").to_string()
+}
+
+/// Whether `url` looks like `https://<host>/<owner>/<repository>` on a supported host.
+pub fn is_repo_url(url: &str) -> bool {
+    match url.strip_prefix("https://") {
+        Some(path) => {
+            path.split('/').filter(|token| !token.is_empty()).count() >= 3 && is_supported_url(url)
+        }
+        None => false,
+    }
+}
+
+/// Name and url of the user or group that owns the repository.
+pub fn owner(repo_url: &str) -> Option<(String, String)> {
+    let tokens = repo_url.split('/').collect::<Vec<&str>>();
+    if tokens.len() < 4 || tokens[3].is_empty() {
+        return None;
+    }
+    Some((tokens[3].to_string(), tokens[..4].join("/")))
+}
+
+pub fn is_commit_sha(rev: &str) -> bool {
+    rev.len() == 40 && rev.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+// e.g. https://gitlab.com/move/stdlib -> gitlab.com
+fn domain(url: &str) -> &str {
+    let url = url.split_once("://").map_or(url, |(_, rest)| rest);
+    url.split('/').next().unwrap_or("")
+}
+
+// e.g. https://gitlab.com/move/stdlib -> https://gitlab.com, without the default port
+fn origin(url: &str) -> Option<String> {
+    let url = Url::parse(url).ok()?;
+    url.host()?;
+    Some(url.origin().ascii_serialization())
+}
+
+// Origins of the instances listed in a comma separated variable, e.g.
+// `https://gitlab.com,https://gitlab.example.com`
+fn configured_origins(name: &str, default: Option<&str>) -> Vec<String> {
+    let base_urls = env::var(name)
+        .ok()
+        .filter(|base_urls| !base_urls.trim().is_empty());
+    base_urls
+        .as_deref()
+        .or(default)
+        .unwrap_or_default()
+        .split(',')
+        .filter_map(|base_url| origin(base_url.trim()))
+        .collect()
+}
+
+// The access token in `token_name` only goes to the first instance listed in `name`, so
+// that the token of one instance is never sent to another.
+fn access_token(
+    token_name: &str,
+    name: &str,
+    default: Option<&str>,
+    base_url: &str,
+) -> Option<String> {
+    if configured_origins(name, default).first() != origin(base_url).as_ref() {
+        return None;
+    }
+    env::var(token_name)
+        .ok()
+        .filter(|access_token| !access_token.is_empty())
+}
+
+// Hosts on private networks are never fetched from, whatever the configuration.
+// `Option::is_none_or` needs Rust 1.82, hence `map_or`.
+#[allow(clippy::unnecessary_map_or)]
+fn has_public_host(url: &str) -> bool {
+    let url = match Url::parse(url) {
+        Ok(url) => url,
+        Err(_) => return false,
+    };
+    match url.host() {
+        Some(Host::Domain(domain)) => {
+            let domain = domain.trim_end_matches('.').to_lowercase();
+            domain != "localhost" && !domain.ends_with(".localhost")
+        }
+        Some(Host::Ipv4(ip)) => is_public_ipv4(ip),
+        Some(Host::Ipv6(ip)) => {
+            let segments = ip.segments();
+            !(ip.is_loopback()
+                || ip.is_unspecified()
+                // unique local fc00::/7 and link local fe80::/10
+                || (segments[0] & 0xfe00) == 0xfc00
+                || (segments[0] & 0xffc0) == 0xfe80)
+                && ip.to_ipv4_mapped().map_or(true, is_public_ipv4)
+        }
+        None => false,
+    }
+}
+
+fn is_public_ipv4(ip: Ipv4Addr) -> bool {
+    !(ip.is_private()
+        || ip.is_loopback()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        || ip.is_documentation())
+}
+
+// e.g. https://gitlab.com/move/stdlib -> https://gitlab.com
+fn base_url(url: &str) -> String {
+    match url.split_once("://") {
+        Some((scheme, rest)) => format!("{}://{}", scheme, rest.split('/').next().unwrap_or("")),
+        None => url.to_string(),
+    }
+}
+
+// Path of the repository on its host, e.g. https://gitlab.com/move/stdlib -> move/stdlib
+fn repo_path<'a>(base_url: &str, repo_url: &'a str) -> &'a str {
+    repo_url
+        .get(base_url.len()..)
+        .unwrap_or("")
+        .trim_matches('/')
+}
+
+// An entry of a recursive tree listing, with its path from the root of the repository
+#[derive(Deserialize)]
+struct TreeEntry {
+    path: String,
+    // `blob` for files, `tree` for directories
+    #[serde(rename = "type")]
+    kind: String,
+}
+
+fn count_files_in(entries: &[TreeEntry], subdir: &str) -> i32 {
+    let subdir = subdir.trim_matches('/');
+    entries
+        .iter()
+        .filter(|entry| entry.kind == "blob")
+        .filter(|entry| {
+            subdir.is_empty()
+                || entry
+                    .path
+                    .strip_prefix(subdir)
+                    .is_some_and(|rest| rest.starts_with('/'))
+        })
+        .count() as i32
+}
+
+// Header that authenticates requests to a host, e.g. `PRIVATE-TOKEN` for Gitlab
+type Authorization = Option<(&'static str, String)>;
+
+// Requests to hosts other than Github. Only urls of the host itself are requested, and
+// redirects to other hosts aren't followed, so its access token never leaves it.
+fn call_api(base_url: &str, url: &str, authorization: Authorization) -> Result<Response, Error> {
+    if origin(url).is_none() || origin(url) != origin(base_url) {
+        return Err(Generic(format!(
+            "Refusing to request {} from host {}",
+            url, base_url
+        )));
+    }
+    let client = reqwest::blocking::Client::builder()
+        .user_agent(APP_USER_AGENT)
+        .redirect(Policy::custom(|attempt| {
+            let is_same_origin = attempt
+                .previous()
+                .first()
+                .is_some_and(|first| first.origin() == attempt.url().origin());
+            if is_same_origin && attempt.previous().len() < MAX_REDIRECTS {
+                attempt.follow()
+            } else {
+                attempt.stop()
+            }
+        }))
+        .build()?;
+    let mut request = client.get(url);
+    if let Some((header, value)) = authorization {
+        request = request.header(header, value);
+    }
+    Ok(request.send()?)
+}
+
+fn fetch_file(
+    base_url: &str,
+    url: &str,
+    authorization: Authorization,
+) -> Result<Option<String>, Error> {
+    let response = call_api(base_url, url, authorization)?;
+    if response.status() == StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    // Error pages and redirects to other hosts aren't the content of the file
+    if !response.status().is_success() {
+        return Err(Generic(format!(
+            "Error fetching file. url: {:?}, status: {}",
+            url,
+            response.status()
+        )));
+    }
+    Ok(Some(response.text()?))
+}
@@ -0,0 +1,534 @@
+use httpmock::prelude::*;
+use httpmock::MockServer;
+use serde_json::json;
+use std::env;
+
+use super::*;
+
+#[test]
+fn for_url_picks_host_from_domain() {
+    let host = for_url("https://github.com/move-language/move");
+    assert_eq!(
+        host.raw_file_url("https://github.com/move-language/move", "main", "Move.toml"),
+        "https://raw.githubusercontent.com/move-language/move/main/Move.toml"
+    );
+
+    let host = for_url("https://gitlab.com/move/stdlib");
+    assert_eq!(
+        host.raw_file_url("https://gitlab.com/move/stdlib", "main", "Move.toml"),
+        "https://gitlab.com/move/stdlib/-/raw/main/Move.toml"
+    );
+
+    let host = for_url("https://bitbucket.org/move/stdlib");
+    assert_eq!(
+        host.raw_file_url("https://bitbucket.org/move/stdlib", "main", "Move.toml"),
+        "https://api.bitbucket.org/2.0/repositories/move/stdlib/src/main/Move.toml"
+    );
+
+    let host = for_url("https://git.example.com/move/stdlib");
+    assert_eq!(
+        host.raw_file_url("https://git.example.com/move/stdlib", "main", "Move.toml"),
+        "https://git.example.com/move/stdlib/raw/main/Move.toml"
+    );
+}
+
+#[test]
+fn for_url_only_fetches_from_configured_hosts() {
+    assert!(is_supported_url("https://github.com/move-language/move"));
+    assert!(is_supported_url("https://gitlab.com/move/stdlib"));
+    assert!(is_supported_url("https://bitbucket.org/move/stdlib"));
+    assert!(!is_supported_url("http://github.com/move-language/move"));
+    assert!(!is_supported_url(
+        "https://github.com.example.com/move/stdlib"
+    ));
+    assert!(!is_supported_url("https://gitlab.example.com/move/stdlib"));
+    assert!(!is_supported_url(
+        "https://gitlab.com@example.com/move/stdlib"
+    ));
+    assert!(!is_supported_url("https://git.example.com/move/stdlib"));
+
+    let host = for_url("https://git.example.com/move/stdlib");
+    assert!(host
+        .repo_info("https://git.example.com/move/stdlib")
+        .is_err());
+    assert!(host
+        .fetch_file(
+            "https://git.example.com/move/stdlib/raw/main/Move.toml",
+            false
+        )
+        .is_err());
+
+    env::set_var("GITEA_BASE_URL", "https://git.example.com/");
+    assert!(is_supported_url("https://git.example.com/move/stdlib"));
+    assert!(!is_supported_url(
+        "https://git.example.com:8443/move/stdlib"
+    ));
+    // Several instances can be listed
+    env::set_var(
+        "GITEA_BASE_URL",
+        "https://gitea.com, https://git.example.com:8443",
+    );
+    assert!(is_supported_url("https://gitea.com/move/stdlib"));
+    assert!(is_supported_url("https://git.example.com:8443/move/stdlib"));
+    assert!(!is_supported_url("https://git.example.com/move/stdlib"));
+    // Private addresses are never fetched from
+    env::set_var("GITEA_BASE_URL", "https://10.0.0.1");
+    assert!(!is_supported_url("https://10.0.0.1/move/stdlib"));
+    env::set_var("GITEA_BASE_URL", "https://localhost");
+    assert!(!is_supported_url("https://localhost/move/stdlib"));
+    env::remove_var("GITEA_BASE_URL");
+
+    assert!(has_public_host("https://93.184.216.34/move/stdlib"));
+    assert!(!has_public_host("https://127.0.0.1/move/stdlib"));
+    assert!(!has_public_host("https://169.254.169.254/latest"));
+    assert!(!has_public_host("https://[::1]/move/stdlib"));
+    assert!(!has_public_host("https://[::ffff:192.168.0.1]/move/stdlib"));
+    assert!(!has_public_host("https://[fd00::1]/move/stdlib"));
+}
+
+#[test]
+fn hosts_only_request_their_own_urls() {
+    crate::test::init();
+
+    let server = MockServer::start();
+    let other_server = MockServer::start();
+    let other_mock = other_server.mock(|when, then| {
+        when.any_request();
+        then.status(200).body("secret");
+    });
+    let redirect_mock = server.mock(|when, then| {
+        when.method(GET).path("/move/stdlib/raw/main/Move.toml");
+        then.status(302)
+            .header("Location", other_server.url("/Move.toml"));
+    });
+
+    let host = GiteaHost::new(&server.base_url());
+    assert!(host
+        .fetch_file(&other_server.url("/move/stdlib/raw/main/Move.toml"), false)
+        .is_err());
+    let file_url = host.raw_file_url(
+        &format!("{}/move/stdlib", server.base_url()),
+        "main",
+        "Move.toml",
+    );
+    assert!(host.fetch_file(&file_url, false).is_err());
+    redirect_mock.assert();
+    other_mock.assert_hits(0);
+}
+
+#[test]
+fn normalize_repo_url_works() {
+    assert_eq!(
+        normalize_repo_url("git@github.com:move-language/move.git"),
+        "https://github.com/move-language/move"
+    );
+    assert_eq!(
+        normalize_repo_url("git@gitlab.com:move/stdlib.git"),
+        "https://gitlab.com/move/stdlib"
+    );
+    assert_eq!(
+        normalize_repo_url(" https://git.example.com/move/stdlib/ "),
+        "https://git.example.com/move/stdlib"
+    );
+}
+
+#[test]
+fn is_repo_url_works() {
+    assert!(is_repo_url("https://gitlab.com/move/stdlib"));
+    assert!(!is_repo_url("https://gitlab.com/move"));
+    assert!(!is_repo_url("http://gitlab.com/move/stdlib"));
+}
+
+#[test]
+fn owner_works() {
+    assert_eq!(
+        owner("https://gitlab.com/move/stdlib/-/tree/main/sources"),
+        Some(("move".to_string(), "https://gitlab.com/move".to_string()))
+    );
+    assert_eq!(owner("https://gitlab.com"), None);
+}
+
+#[test]
+fn is_commit_sha_works() {
+    assert!(is_commit_sha("30b0b8dd1b6b5b0a0a1ad4c4a1b0b2c8ee4ad421"));
+    assert!(!is_commit_sha("main"));
+    assert!(!is_commit_sha("30b0b8d"));
+}
+
+#[test]
+fn tree_urls_can_be_split_again() {
+    let hosts: Vec<(Box<dyn SourceHost>, &str)> = vec![
+        (
+            Box::new(GithubHost),
+            "https://github.com/move-language/move",
+        ),
+        (
+            Box::new(GitlabHost::new("https://gitlab.com")),
+            "https://gitlab.com/group/subgroup/stdlib",
+        ),
+        (
+            Box::new(GiteaHost::new("https://gitea.com")),
+            "https://gitea.com/move/stdlib",
+        ),
+        (
+            Box::new(BitbucketHost::new(
+                "https://bitbucket.org",
+                "https://api.bitbucket.org",
+            )),
+            "https://bitbucket.org/move/stdlib",
+        ),
+    ];
+    for (host, repo_url) in hosts {
+        for rev in ["main", "30b0b8dd1b6b5b0a0a1ad4c4a1b0b2c8ee4ad421"] {
+            let tree_url = host.tree_url(repo_url, rev, "language/move-stdlib");
+            assert_eq!(
+                host.split_tree_url(&tree_url),
+                (repo_url.to_string(), "language/move-stdlib".to_string()),
+                "tree url: {}",
+                tree_url
+            );
+        }
+        assert_eq!(
+            host.split_tree_url(repo_url),
+            (repo_url.to_string(), "".to_string())
+        );
+    }
+}
+
+#[test]
+fn gitlab_host_fetches_repo_info() {
+    crate::test::init();
+
+    let server = MockServer::start();
+    let project_mock = server.mock(|when, then| {
+        when.method(GET)
+            .path("/api/v4/projects/move%2Fstdlib")
+            .query_param("license", "true");
+        then.status(200).json_body(json!({
+            "description": "test description",
+            "star_count": 2,
+            "forks_count": 3,
+            "default_branch": "main",
+            "license": { "key": "mit", "name": "MIT License", "html_url": null },
+        }));
+    });
+    let commits_mock = server.mock(|when, then| {
+        when.method(GET)
+            .path("/api/v4/projects/move%2Fstdlib/repository/commits");
+        then.status(200).json_body(json!([{ "id": "test sha" }]));
+    });
+    let file_mock = server.mock(|when, then| {
+        when.method(GET).path("/move/stdlib/-/raw/main/Move.toml");
+        then.status(200).body("[package]");
+    });
+
+    let host = GitlabHost::new(&server.base_url());
+    let repo_url = format!("{}/move/stdlib", server.base_url());
+    let info = host.repo_info(&repo_url).unwrap();
+    project_mock.assert();
+    assert_eq!(info.description, Some("test description".to_string()));
+    assert_eq!(info.stargazers_count, 2);
+    assert_eq!(info.forks_count, 3);
+    assert_eq!(info.default_branch, "main");
+    assert_eq!(info.license.unwrap().name, "MIT License");
+
+    assert_eq!(host.latest_commit_sha(&repo_url).unwrap(), "test sha");
+    commits_mock.assert();
+
+    let file_url = host.raw_file_url(&repo_url, "main", "Move.toml");
+    assert_eq!(
+        host.fetch_file(&file_url, false).unwrap(),
+        Some("[package]".to_string())
+    );
+    file_mock.assert();
+}
+
+#[test]
+fn gitea_host_fetches_repo_info() {
+    crate::test::init();
+
+    let server = MockServer::start();
+    let repo_mock = server.mock(|when, then| {
+        when.method(GET).path("/api/v1/repos/move/stdlib");
+        then.status(200).json_body(json!({
+            "description": "",
+            "size": 1,
+            "stars_count": 2,
+            "forks_count": 3,
+            "default_branch": "main",
+        }));
+    });
+    let commits_mock = server.mock(|when, then| {
+        when.method(GET).path("/api/v1/repos/move/stdlib/commits");
+        then.status(200).json_body(json!([{ "sha": "test sha" }]));
+    });
+    let file_mock = server.mock(|when, then| {
+        when.method(GET).path("/move/stdlib/raw/main/README.md");
+        then.status(404);
+    });
+
+    let host = GiteaHost::new(&server.base_url());
+    let repo_url = format!("{}/move/stdlib", server.base_url());
+    let info = host.repo_info(&repo_url).unwrap();
+    repo_mock.assert();
+    // Empty descriptions are generated from the readme
+    assert_eq!(info.description, None);
+    assert_eq!(info.size, 1);
+    assert_eq!(info.stargazers_count, 2);
+    assert_eq!(info.default_branch, "main");
+
+    assert_eq!(host.latest_commit_sha(&repo_url).unwrap(), "test sha");
+    commits_mock.assert();
+
+    let file_url = host.raw_file_url(&repo_url, "main", "README.md");
+    assert_eq!(host.fetch_file(&file_url, false).unwrap(), None);
+    file_mock.assert();
+}
+
+#[test]
+fn hosts_return_error_for_unexpected_file_status() {
+    crate::test::init();
+
+    let server = MockServer::start();
+    let file_mock = server.mock(|when, then| {
+        when.method(GET).path("/move/stdlib/raw/main/Move.toml");
+        then.status(500).body("Internal Server Error");
+    });
+
+    let host = GiteaHost::new(&server.base_url());
+    let file_url = host.raw_file_url(
+        &format!("{}/move/stdlib", server.base_url()),
+        "main",
+        "Move.toml",
+    );
+    assert!(host.fetch_file(&file_url, false).is_err());
+    file_mock.assert();
+}
+
+#[test]
+fn access_tokens_only_go_to_the_first_instance() {
+    env::set_var(
+        "GITLAB_BASE_URL",
+        "https://gitlab.example.org,https://gitlab.com",
+    );
+    env::set_var("GITLAB_ACCESS_TOKEN", "test token");
+    assert_eq!(
+        access_token(
+            "GITLAB_ACCESS_TOKEN",
+            "GITLAB_BASE_URL",
+            Some(DEFAULT_GITLAB_BASE_URL),
+            "https://gitlab.example.org"
+        ),
+        Some("test token".to_string())
+    );
+    assert_eq!(
+        access_token(
+            "GITLAB_ACCESS_TOKEN",
+            "GITLAB_BASE_URL",
+            Some(DEFAULT_GITLAB_BASE_URL),
+            "https://gitlab.com"
+        ),
+        None
+    );
+    env::remove_var("GITLAB_BASE_URL");
+    env::remove_var("GITLAB_ACCESS_TOKEN");
+}
+
+#[test]
+fn bitbucket_host_fetches_repo_info() {
+    crate::test::init();
+
+    let server = MockServer::start();
+    let repo_mock = server.mock(|when, then| {
+        when.method(GET).path("/2.0/repositories/move/stdlib");
+        then.status(200).json_body(json!({
+            "description": "test description",
+            "size": 2048,
+            "mainbranch": { "name": "main" },
+        }));
+    });
+    let commits_mock = server.mock(|when, then| {
+        when.method(GET)
+            .path("/2.0/repositories/move/stdlib/commits/main")
+            .query_param("pagelen", "1");
+        then.status(200)
+            .json_body(json!({ "values": [{ "hash": "test sha" }] }));
+    });
+    let file_mock = server.mock(|when, then| {
+        when.method(GET)
+            .path("/2.0/repositories/move/stdlib/src/main/Move.toml");
+        then.status(200).body("[package]");
+    });
+
+    let host = BitbucketHost::new("https://bitbucket.org", &server.base_url());
+    let repo_url = "https://bitbucket.org/move/stdlib";
+    let info = host.repo_info(repo_url).unwrap();
+    assert_eq!(info.description, Some("test description".to_string()));
+    assert_eq!(info.size, 2);
+    assert_eq!(info.default_branch, "main");
+
+    assert_eq!(host.latest_commit_sha(repo_url).unwrap(), "test sha");
+    repo_mock.assert_hits(2);
+    commits_mock.assert();
+
+    let file_url = host.raw_file_url(repo_url, "main", "Move.toml");
+    assert_eq!(
+        host.fetch_file(&file_url, false).unwrap(),
+        Some("[package]".to_string())
+    );
+    file_mock.assert();
+}
+
+#[test]
+fn gitea_host_returns_default_repo_info_without_api() {
+    crate::test::init();
+
+    let server = MockServer::start();
+    let repo_mock = server.mock(|when, then| {
+        when.method(GET).path("/api/v1/repos/move/stdlib");
+        then.status(404).body("Not found");
+    });
+
+    let host = GiteaHost::new(&server.base_url());
+    let info = host
+        .repo_info(&format!("{}/move/stdlib", server.base_url()))
+        .unwrap();
+    repo_mock.assert();
+    assert_eq!(info.default_branch, "");
+    assert_eq!(info.stargazers_count, 0);
+}
+
+#[test]
+fn github_host_counts_files_in_subdir() {
+    crate::test::init();
+
+    let server = MockServer::start();
+    let tree_mock = server.mock(|when, then| {
+        when.method(GET)
+            .path("/move/stdlib/git/trees/main")
+            .query_param("recursive", "1");
+        then.status(200).json_body(json!({
+            "tree": [
+                { "path": "README.md", "type": "blob" },
+                { "path": "sources", "type": "tree" },
+                { "path": "sources/vector.move", "type": "blob" },
+                { "path": "sources-old/vector.move", "type": "blob" },
+            ],
+            "truncated": false,
+        }));
+    });
+
+    let repo_url = format!("{}/move/stdlib", server.base_url());
+    assert_eq!(
+        GithubHost
+            .count_files(&repo_url, "main", "sources")
+            .unwrap(),
+        1
+    );
+    assert_eq!(GithubHost.count_files(&repo_url, "main", "").unwrap(), 3);
+    tree_mock.assert_hits(2);
+}
+
+#[test]
+fn gitlab_host_counts_files_of_every_page() {
+    crate::test::init();
+
+    let server = MockServer::start();
+    let first_page = (0..100)
+        .map(|i| json!({ "path": format!("sources/{}.move", i), "type": "blob" }))
+        .collect::<Vec<_>>();
+    let first_page_mock = server.mock(|when, then| {
+        when.method(GET)
+            .path("/api/v4/projects/move%2Fstdlib/repository/tree")
+            .query_param("ref", "main")
+            .query_param("path", "sources")
+            .query_param("page", "1");
+        then.status(200).json_body(json!(first_page));
+    });
+    let second_page_mock = server.mock(|when, then| {
+        when.method(GET)
+            .path("/api/v4/projects/move%2Fstdlib/repository/tree")
+            .query_param("page", "2");
+        then.status(200).json_body(json!([
+            { "path": "sources/nested", "type": "tree" },
+            { "path": "sources/nested/vector.move", "type": "blob" },
+        ]));
+    });
+
+    let host = GitlabHost::new(&server.base_url());
+    let repo_url = format!("{}/move/stdlib", server.base_url());
+    assert_eq!(host.count_files(&repo_url, "main", "sources").unwrap(), 101);
+    first_page_mock.assert();
+    second_page_mock.assert();
+}
+
+#[test]
+fn gitea_host_counts_files_of_every_page() {
+    crate::test::init();
+
+    let server = MockServer::start();
+    let first_page_mock = server.mock(|when, then| {
+        when.method(GET)
+            .path("/api/v1/repos/move/stdlib/git/trees/main")
+            .query_param("recursive", "true")
+            .query_param("page", "1");
+        then.status(200).json_body(json!({
+            "tree": [{ "path": "Move.toml", "type": "blob" }],
+            "truncated": true,
+        }));
+    });
+    let second_page_mock = server.mock(|when, then| {
+        when.method(GET)
+            .path("/api/v1/repos/move/stdlib/git/trees/main")
+            .query_param("page", "2");
+        then.status(200).json_body(json!({
+            "tree": [{ "path": "sources/vector.move", "type": "blob" }],
+            "truncated": false,
+        }));
+    });
+
+    let host = GiteaHost::new(&server.base_url());
+    let repo_url = format!("{}/move/stdlib", server.base_url());
+    assert_eq!(host.count_files(&repo_url, "main", "").unwrap(), 2);
+    first_page_mock.assert();
+    second_page_mock.assert();
+
+    let unsupported = UnsupportedHost(GiteaHost::new(&server.base_url()));
+    assert!(unsupported.count_files(&repo_url, "main", "").is_err());
+}
+
+#[test]
+fn bitbucket_host_counts_files_of_every_page() {
+    crate::test::init();
+
+    let server = MockServer::start();
+    let first_page_mock = server.mock(|when, then| {
+        when.method(GET)
+            .path("/2.0/repositories/move/stdlib/src/main/sources/")
+            .query_param("max_depth", "100");
+        then.status(200).json_body(json!({
+            "values": [
+                { "path": "sources/vector.move", "type": "commit_file" },
+                { "path": "sources/nested", "type": "commit_directory" },
+            ],
+            "next": server.url("/2.0/repositories/move/stdlib/src/main/sources/?page=2"),
+        }));
+    });
+    let second_page_mock = server.mock(|when, then| {
+        when.method(GET)
+            .path("/2.0/repositories/move/stdlib/src/main/sources/")
+            .query_param("page", "2");
+        then.status(200).json_body(json!({
+            "values": [{ "path": "sources/nested/option.move", "type": "commit_file" }],
+        }));
+    });
+
+    let host = BitbucketHost::new("https://bitbucket.org", &server.base_url());
+    assert_eq!(
+        host.count_files("https://bitbucket.org/move/stdlib", "main", "sources")
+            .unwrap(),
+        2
+    );
+    first_page_mock.assert();
+    second_page_mock.assert();
+}
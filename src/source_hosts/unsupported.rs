@@ -0,0 +1,47 @@
+use jelly::error::Error;
+use jelly::error::Error::Generic;
+
+use super::{GiteaHost, SourceHost};
+use crate::github_service::GithubRepoInfo;
+
+/// A host that isn't configured. Its urls are read like Gitea ones, but nothing is fetched
+/// from it.
+pub struct UnsupportedHost(pub GiteaHost);
+
+fn unsupported(url: &str) -> Error {
+    Generic(format!("Repositories of {} can't be fetched from.", url))
+}
+
+impl SourceHost for UnsupportedHost {
+    fn repo_info(&self, repo_url: &str) -> Result<GithubRepoInfo, Error> {
+        Err(unsupported(repo_url))
+    }
+
+    fn latest_commit_sha(&self, repo_url: &str) -> Result<String, Error> {
+        Err(unsupported(repo_url))
+    }
+
+    fn raw_file_url(&self, repo_url: &str, rev: &str, path: &str) -> String {
+        self.0.raw_file_url(repo_url, rev, path)
+    }
+
+    fn fetch_file(&self, url: &str, _is_immutable: bool) -> Result<Option<String>, Error> {
+        Err(unsupported(url))
+    }
+
+    fn count_files(&self, repo_url: &str, _rev: &str, _subdir: &str) -> Result<i32, Error> {
+        Err(unsupported(repo_url))
+    }
+
+    fn tree_url(&self, repo_url: &str, rev: &str, subdir: &str) -> String {
+        self.0.tree_url(repo_url, rev, subdir)
+    }
+
+    fn split_tree_url(&self, url: &str) -> (String, String) {
+        self.0.split_tree_url(url)
+    }
+
+    fn is_supported(&self) -> bool {
+        false
+    }
+}
@@ -1,3 +1,4 @@
-use diesel::sql_types::Text;
+use diesel::sql_types::{Array, Text};
 
 sql_function!(fn lower(x: Text) -> Text);
+sql_function!(fn array_remove(a: Array<Text>, e: Text) -> Array<Text>);
@@ -5,6 +5,7 @@ use crate::github_service::{GithubRepoCommit, GithubRepoData, GithubRepoInfo};
 use futures::future::Ready;
 use jelly::accounts::User;
 use jelly::actix_session::Session;
+use jelly::actix_web::http::HeaderMap;
 use jelly::actix_web::FromRequest;
 use jelly::jobs::Job;
 use jelly::DieselPgPool;
@@ -27,6 +28,7 @@ mock! {
         pub fn render(&self, code: usize, template: &str, context: Context) -> Result<HttpResponse, Error>;
         pub fn redirect(&self, location: &str) -> Result<HttpResponse, Error>;
         pub fn queue<J: Job + 'static>(&self, job: J) -> Result<(), Error>;
+        pub fn headers(&self) -> &HeaderMap;
     }
 
     impl DatabasePool for HttpRequest {
@@ -63,8 +65,13 @@ impl GithubService {
                     forks_count: 0,
                     url: "".to_string(),
                     rev: "".to_string(),
+                    dependencies: vec![],
+                    addresses: vec![],
                 })
             });
         mock_gh_service
+            .expect_fetch_default_branch_file()
+            .returning(|_, _| Ok(Some("test-claim-token\n".to_string())));
+        mock_gh_service
     }
 }
@@ -1,8 +1,11 @@
 use crate::accounts::forms::NewAccountForm;
+use crate::accounts::models::account_two_factor::AccountTwoFactor;
 use crate::accounts::Account;
 use crate::packages::Package;
 use crate::settings::models::token::ApiToken;
 use crate::test::DB_POOL;
+use crate::utils::totp;
+use jelly::chrono::Utc;
 use jelly::forms::{EmailField, PasswordField};
 
 fn new_account_form(custom_email: Option<&str>) -> NewAccountForm {
@@ -36,6 +39,37 @@ pub fn setup_user(email: Option<String>, password: Option<String>) -> i32 {
     uid
 }
 
+/// Creates version 1.0.0 of a package owned by the account, returning the package id.
+pub fn setup_package(name: &str, repository_url: &str, owner_id: i32) -> i32 {
+    Package::create_test_package(
+        &name.to_string(),
+        &repository_url.to_string(),
+        &"".to_string(),
+        &"1.0.0".to_string(),
+        &"".to_string(),
+        &"".to_string(),
+        &"".to_string(),
+        -1,
+        -1,
+        0,
+        0,
+        Some(owner_id),
+        &DB_POOL,
+    )
+    .unwrap()
+}
+
+/// Enables two-factor authentication for the account, returning its recovery codes.
+pub fn setup_two_factor(account_id: i32) -> Vec<String> {
+    let conn = DB_POOL.get().unwrap();
+    let two_factor = AccountTwoFactor::start_enrollment(account_id, &conn).unwrap();
+    let step = totp::step_at(Utc::now().timestamp() as u64);
+    let code = totp::code_at(&two_factor.secret, step).unwrap();
+    AccountTwoFactor::confirm_enrollment(account_id, &code, &conn)
+        .unwrap()
+        .unwrap()
+}
+
 pub fn create_test_token() -> String {
     let form = new_account_form(None);
     let uid = Account::register(&form, &DB_POOL).unwrap();
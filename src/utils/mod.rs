@@ -1,14 +1,30 @@
-use jelly::guards::Auth;
+use jelly::chrono::Duration;
+use jelly::guards::{Auth, RateLimit, RateLimitStore};
+use std::env;
 
 pub mod paginate;
 pub mod presenter;
+#[cfg(test)]
+mod rate_limit_tests;
 pub mod request_utils;
 #[cfg(test)]
 pub mod tests;
 pub mod token;
+pub mod totp;
 
 pub fn new_auth() -> Auth {
     Auth {
         redirect_to: "/accounts/login/",
+        is_authenticated: request_utils::is_authenticated,
+    }
+}
+
+/// A rate limit per IP address. Requests are counted in Postgres when `RATE_LIMIT_STORE` is
+/// "postgres", so that every server shares the counters, and in memory otherwise.
+pub fn new_rate_limit(name: &'static str, requests: u32, window: Duration) -> RateLimit {
+    let rate_limit = RateLimit::new(name, requests, window);
+    match env::var("RATE_LIMIT_STORE") {
+        Ok(store) if store == "postgres" => rate_limit.with_store(RateLimitStore::Postgres),
+        _ => rate_limit,
     }
 }
@@ -5,6 +5,7 @@ use jelly::Result;
 use crate::accounts::Account;
 use crate::package_collaborators::package_collaborator::PackageCollaborator;
 use crate::packages::Package;
+use crate::source_hosts;
 
 pub fn censor_email(email: &str) -> Result<String> {
     let mut censored_email = String::new();
@@ -18,37 +19,40 @@ pub fn censor_email(email: &str) -> Result<String> {
     Ok(censored_email)
 }
 
-pub fn make_account_name(package: &Package, db: &DieselPgPool) -> Result<(String, String)> {
+/// Names and profile urls of the package owners, the earliest one first.
+pub fn make_account_names(package: &Package, db: &DieselPgPool) -> Result<Vec<(String, String)>> {
     let connection = db.get()?;
-    let collaborators = PackageCollaborator::get_by_package_id(package.id, &connection)?;
-    let package_owner_id = if collaborators.len() > 0 {
-        Some(collaborators[0])
-    } else {
-        None
-    };
+    let owner_ids = PackageCollaborator::get_owner_ids(package.id, &connection)?;
+    if owner_ids.is_empty() {
+        // Default account name is derived from https://<host>/<owner>
+        return Ok(vec![source_hosts::owner(&package.repository_url)
+            .ok_or_else(|| {
+                Error::Generic(format!(
+                    "Invalid repository url: {}",
+                    package.repository_url
+                ))
+            })?]);
+    }
 
-    Ok(if let Some(uid) = package_owner_id {
-        let account = Account::get(uid, db)?;
-        let name = if account.name.is_empty() {
-            account.github_login.as_ref().unwrap_or(&account.email)
-        } else {
-            &account.name
-        };
-        let slug_url = format!(
-            "/users/{}",
-            account.slug.as_ref().ok_or_else(|| Error::Generic(format!(
-                "This account has no slug. uid: {}.",
-                account.id
-            )))?
-        );
-        (name.clone(), slug_url)
-    } else {
-        // Default account name is derived from https://github.com/<github login>
-        let repo_url = package.repository_url.clone();
-        let derived_name = repo_url.split('/').collect::<Vec<&str>>()[3];
-        let account_url = repo_url.split('/').collect::<Vec<&str>>()[..4].join("/");
-        (derived_name.to_string(), account_url)
-    })
+    owner_ids
+        .into_iter()
+        .map(|uid| {
+            let account = Account::get(uid, db)?;
+            let name = if account.name.is_empty() {
+                account.github_login.as_ref().unwrap_or(&account.email)
+            } else {
+                &account.name
+            };
+            let slug_url = format!(
+                "/users/{}",
+                account.slug.as_ref().ok_or_else(|| Error::Generic(format!(
+                    "This account has no slug. uid: {}.",
+                    account.id
+                )))?
+            );
+            Ok((name.clone(), slug_url))
+        })
+        .collect()
 }
 
 pub fn make_package_install_instruction(repo_url: &str) -> (String, String) {
@@ -56,17 +60,8 @@ pub fn make_package_install_instruction(repo_url: &str) -> (String, String) {
     // example: https://github.com/move-language/move/tree/main/language/evm/hardhat-examples/contracts/ABIStruct
     //          -> repo_url: https://github.com/move-language/move
     //             subdir: language/evm/hardhat-examples/contracts/ABIStruct
-    let mut instruction_subdir = String::from("");
-    let mut instruction_repo_url: String;
-    let repo_url_tokens = repo_url.split('/').collect::<Vec<&str>>();
-    if repo_url_tokens.len() > 7 {
-        instruction_repo_url = repo_url_tokens[..5].join("/");
-        instruction_subdir = repo_url_tokens[7..].join("/");
-    } else {
-        // Should be the root directory, not a subdir,
-        // like https://github.com/move-language/move
-        instruction_repo_url = repo_url.to_string();
-    }
+    let (mut instruction_repo_url, instruction_subdir) =
+        source_hosts::for_url(repo_url).split_tree_url(repo_url);
     instruction_repo_url.push_str(".git");
     (instruction_repo_url, instruction_subdir)
 }
@@ -118,4 +113,28 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn make_package_install_instruction_works_for_all_hosts() {
+        assert_eq!(
+            make_package_install_instruction(
+                "https://github.com/move-language/move/tree/main/language/move-stdlib"
+            ),
+            (
+                "https://github.com/move-language/move.git".to_string(),
+                "language/move-stdlib".to_string()
+            )
+        );
+        assert_eq!(
+            make_package_install_instruction("https://gitlab.com/move/stdlib/-/tree/main/sources"),
+            (
+                "https://gitlab.com/move/stdlib.git".to_string(),
+                "sources".to_string()
+            )
+        );
+        assert_eq!(
+            make_package_install_instruction("https://gitea.com/move/stdlib"),
+            ("https://gitea.com/move/stdlib.git".to_string(), "".to_string())
+        );
+    }
 }
@@ -0,0 +1,241 @@
+use std::net::IpAddr;
+use std::sync::Arc;
+
+use diesel::prelude::*;
+use jelly::actix_web::http::header::RETRY_AFTER;
+use jelly::actix_web::http::StatusCode;
+use jelly::actix_web::web::{get, resource};
+use jelly::actix_web::{test, App, HttpRequest, HttpResponse};
+use jelly::chrono::{Duration, TimeZone, Utc};
+use jelly::guards::rate_limit::MemoryStore;
+use jelly::guards::{RateLimit, RateLimitKey, RateLimitStore};
+use jelly::request::client::{parse_trusted_proxies, resolve_client_ip};
+
+use crate::accounts::Account;
+use crate::api::auth::api_token_id;
+use crate::settings::models::token::ApiToken;
+use crate::test::util::setup_user;
+use crate::test::{DatabaseTestContext, DB_POOL};
+
+fn memory_store() -> RateLimitStore {
+    RateLimitStore::Memory(Arc::new(MemoryStore::default()))
+}
+
+fn request(ip: &str, token: Option<&str>) -> test::TestRequest {
+    let request = test::TestRequest::get()
+        .uri("/")
+        .peer_addr(format!("{}:4000", ip).parse().unwrap());
+    match token {
+        Some(token) => request.header("Authorization", format!("Bearer {}", token)),
+        None => request,
+    }
+}
+
+// Only the tokens "first" and "second" authenticate
+fn test_token_id(_: &HttpRequest, token: &str) -> Option<i32> {
+    match token {
+        "first" => Some(1),
+        "second" => Some(2),
+        _ => None,
+    }
+}
+
+fn ip(address: &str) -> IpAddr {
+    address.parse().unwrap()
+}
+
+fn hops(addresses: &[&str]) -> Vec<String> {
+    addresses
+        .iter()
+        .map(|address| address.to_string())
+        .collect()
+}
+
+#[test]
+fn resolve_client_ip_only_believes_trusted_proxies() {
+    let trusted = parse_trusted_proxies("10.0.0.0/8, 192.168.1.1, invalid");
+    assert_eq!(trusted.len(), 2);
+
+    // Forwarded addresses from anyone else are ignored
+    let forwarded = hops(&["1.1.1.1"]);
+    assert_eq!(
+        resolve_client_ip(ip("8.8.8.8"), &forwarded, &trusted),
+        ip("8.8.8.8")
+    );
+    assert_eq!(
+        resolve_client_ip(ip("10.0.0.1"), &forwarded, &[]),
+        ip("10.0.0.1")
+    );
+
+    assert_eq!(
+        resolve_client_ip(ip("10.0.0.1"), &forwarded, &trusted),
+        ip("1.1.1.1")
+    );
+    assert_eq!(
+        resolve_client_ip(ip("10.0.0.1"), &hops(&[]), &trusted),
+        ip("10.0.0.1")
+    );
+    // Hops are only followed through trusted proxies, so an address made up by the
+    // client isn't picked
+    let forwarded = hops(&["6.6.6.6", "2.2.2.2", "192.168.1.1:8080"]);
+    assert_eq!(
+        resolve_client_ip(ip("10.0.0.1"), &forwarded, &trusted),
+        ip("2.2.2.2")
+    );
+    let forwarded = hops(&["2.2.2.2", "unknown"]);
+    assert_eq!(
+        resolve_client_ip(ip("10.0.0.1"), &forwarded, &trusted),
+        ip("10.0.0.1")
+    );
+}
+
+#[test]
+fn memory_store_starts_a_new_window_once_the_last_one_ends() {
+    let store = MemoryStore::default();
+    let now = Utc.timestamp_opt(1_600_000_000, 0).unwrap();
+    let window = Duration::minutes(1);
+
+    assert_eq!(store.hit("a", window, now).count, 1);
+    let hit = store.hit("a", window, now + Duration::seconds(30));
+    assert_eq!(hit.count, 2);
+    assert_eq!(hit.reset_at, now + window);
+    assert_eq!(store.hit("b", window, now).count, 1);
+
+    let hit = store.hit("a", window, now + window);
+    assert_eq!(hit.count, 1);
+    assert_eq!(hit.reset_at, now + window + window);
+}
+
+#[actix_rt::test]
+async fn rate_limit_turns_away_requests_over_the_limit() {
+    let rate_limit = RateLimit::new("test", 2, Duration::minutes(1)).with_store(memory_store());
+    let mut app = test::init_service(
+        App::new().service(
+            resource("/")
+                .wrap(rate_limit)
+                .route(get().to(HttpResponse::Ok)),
+        ),
+    )
+    .await;
+
+    for _ in 0..2 {
+        let response = test::call_service(&mut app, request("10.0.0.1", None).to_request()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+    let response = test::call_service(&mut app, request("10.0.0.1", None).to_request()).await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    let retry_after = response.headers().get(RETRY_AFTER).unwrap();
+    let retry_after = retry_after.to_str().unwrap().parse::<i64>().unwrap();
+    assert!(retry_after > 0 && retry_after <= 60);
+
+    // Other clients have their own counters
+    let response = test::call_service(&mut app, request("10.0.0.2", None).to_request()).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // Forwarded addresses from an untrusted peer don't make a new client
+    let spoofed = request("10.0.0.1", None)
+        .header("X-Forwarded-For", "10.0.0.3")
+        .to_request();
+    let response = test::call_service(&mut app, spoofed).await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+}
+
+#[actix_rt::test]
+async fn rate_limit_keyed_by_api_token_counts_each_token() {
+    let rate_limit = RateLimit::new("test", 1, Duration::minutes(1))
+        .keyed_by(RateLimitKey::ApiToken(test_token_id))
+        .with_store(memory_store());
+    let mut app = test::init_service(
+        App::new().service(
+            resource("/")
+                .wrap(rate_limit)
+                .route(get().to(HttpResponse::Ok)),
+        ),
+    )
+    .await;
+
+    let first = request("10.0.0.1", Some("first")).to_request();
+    assert_eq!(
+        test::call_service(&mut app, first).await.status(),
+        StatusCode::OK
+    );
+    let second = request("10.0.0.1", Some("second")).to_request();
+    assert_eq!(
+        test::call_service(&mut app, second).await.status(),
+        StatusCode::OK
+    );
+    let first = request("10.0.0.2", Some("first")).to_request();
+    assert_eq!(
+        test::call_service(&mut app, first).await.status(),
+        StatusCode::TOO_MANY_REQUESTS
+    );
+    // Requests without a valid token are counted by IP address
+    let anonymous = request("10.0.0.1", None).to_request();
+    assert_eq!(
+        test::call_service(&mut app, anonymous).await.status(),
+        StatusCode::OK
+    );
+    let made_up = request("10.0.0.1", Some("made-up")).to_request();
+    assert_eq!(
+        test::call_service(&mut app, made_up).await.status(),
+        StatusCode::TOO_MANY_REQUESTS
+    );
+}
+
+#[actix_rt::test]
+async fn rate_limit_check_counts_the_given_client() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let rate_limit = RateLimit::new("test", 1, Duration::minutes(1)).with_store(memory_store());
+    assert_eq!(rate_limit.check("ip:10.0.0.1", &DB_POOL).unwrap(), None);
+    let retry_after = rate_limit.check("ip:10.0.0.1", &DB_POOL).unwrap().unwrap();
+    assert!(retry_after > 0 && retry_after <= 60);
+    assert_eq!(rate_limit.check("ip:10.0.0.2", &DB_POOL).unwrap(), None);
+}
+
+#[actix_rt::test]
+async fn rate_limit_counts_requests_in_postgres() {
+    crate::test::init();
+    let _ctx = DatabaseTestContext::new();
+
+    let rate_limit = RateLimit::new("test", 1, Duration::minutes(1))
+        .keyed_by(RateLimitKey::ApiToken(api_token_id))
+        .with_store(RateLimitStore::Postgres);
+    let mut app = test::init_service(
+        App::new().app_data(DB_POOL.clone()).service(
+            resource("/")
+                .wrap(rate_limit)
+                .route(get().to(HttpResponse::Ok)),
+        ),
+    )
+    .await;
+
+    let account = Account::get(setup_user(None, None), &DB_POOL).unwrap();
+    let token = ApiToken::insert(&account, "test_key", &DB_POOL).unwrap();
+
+    let response = test::call_service(
+        &mut app,
+        request("10.0.0.1", Some(&token.plaintext)).to_request(),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = test::call_service(
+        &mut app,
+        request("10.0.0.2", Some(&token.plaintext)).to_request(),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+
+    let keys = diesel::sql_query("SELECT key FROM rate_limits")
+        .load::<RateLimitRow>(&DB_POOL.get().unwrap())
+        .unwrap();
+    assert_eq!(keys.len(), 1);
+    assert_eq!(keys[0].key, format!("test:token:{}", token.model.id));
+}
+
+#[derive(QueryableByName)]
+struct RateLimitRow {
+    #[sql_type = "diesel::sql_types::Varchar"]
+    key: String,
+}
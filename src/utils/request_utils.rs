@@ -1,18 +1,71 @@
-use diesel::result::Error as DBError;
+use futures::future::{ready, Ready};
 use jelly::accounts::User;
-use jelly::actix_session::UserSession;
+use jelly::actix_session::{Session, UserSession};
+use jelly::actix_web::cookie::Cookie;
+use jelly::actix_web::dev::Payload;
 use jelly::actix_web::http::header;
-use jelly::actix_web::{HttpMessage, HttpRequest};
-use jelly::anyhow::anyhow;
-use jelly::error::Error;
+use jelly::actix_web::{Error as ActixError, FromRequest, HttpMessage, HttpRequest};
+use jelly::chrono::Utc;
 use jelly::prelude::*;
+use jelly::request::client::client_ip;
+use jelly::request::ResponseCookies;
+use jelly::{DieselPgConnection, DieselPgPool};
 use jelly::Result;
+use serde::{Deserialize, Serialize};
+use time::Duration;
 
+use crate::accounts::models::account_event::{AccountEvent, AccountEventKind};
+use crate::accounts::models::account_session::{
+    max_remember_me_days, AccountSession, Renewal, SessionDevice,
+};
+use crate::accounts::models::account_two_factor::AccountTwoFactor;
 use crate::accounts::Account;
 use crate::constants;
 
+pub const REMEMBER_ME_COOKIE: &str = "remember_me_token";
+// Key of the id of the `AccountSession` in the cookie session
+const SESSION_ID_KEY: &str = "sid";
+// Key of the sign-in waiting for a two-factor code in the cookie session
+const PENDING_TWO_FACTOR_KEY: &str = "2fa";
+const PENDING_TWO_FACTOR_SECONDS: i64 = 10 * 60;
+
+/// The IP address of the client: the peer address of the connection, or the address
+/// forwarded by one of the `TRUSTED_PROXIES` in front of the app.
+pub struct ClientIp(pub Option<String>);
+
+impl FromRequest for ClientIp {
+    type Error = ActixError;
+    type Future = Ready<std::result::Result<Self, ActixError>>;
+    type Config = ();
+
+    fn from_request(request: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(Ok(ClientIp(client_ip(request))))
+    }
+}
+
+impl FromRequest for SessionDevice {
+    type Error = ActixError;
+    type Future = Ready<std::result::Result<Self, ActixError>>;
+    type Config = ();
+
+    fn from_request(request: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let user_agent = request
+            .headers()
+            .get(header::USER_AGENT)
+            .and_then(|user_agent| user_agent.to_str().ok())
+            .unwrap_or_default()
+            .to_string();
+        ready(Ok(SessionDevice {
+            user_agent,
+            ip_address: client_ip(request),
+        }))
+    }
+}
+
+/// Whether the request comes from a signed-in user whose session is still live, signing the
+/// user back in from their remember-me token if needed.
 pub fn is_authenticated(request: &HttpRequest) -> Result<bool> {
-    Ok(request.is_authenticated()? && renew_token(request)?)
+    renew_token(request)
 }
 
 pub fn clear_cookie(request: &HttpRequest) -> HttpResponse {
@@ -22,45 +75,623 @@ pub fn clear_cookie(request: &HttpRequest) -> HttpResponse {
         .body("")
 }
 
+fn remember_me_cookie(token: String) -> Cookie<'static> {
+    Cookie::build(REMEMBER_ME_COOKIE, token)
+        .path("/")
+        .max_age(Duration::days(max_remember_me_days()))
+        .http_only(true)
+        .finish()
+}
+
+/// Signs the account in and records a new session for it. Returns the remember-me cookie to
+/// set when `remember_me` is on.
+pub fn sign_in(
+    session: &Session,
+    account: &Account,
+    device: &SessionDevice,
+    remember_me: bool,
+    conn: &DieselPgConnection,
+) -> Result<Option<Cookie<'static>>> {
+    let created = AccountSession::create(account, device, remember_me, conn)?;
+    AccountEvent::record(account.id, AccountEventKind::SignedIn, device, conn)?;
+    set_session(session, account, created.model.id)?;
+    Ok(created.remember_me_token.map(remember_me_cookie))
+}
+
+/// The result of `start_sign_in`.
+pub enum SignIn {
+    /// Signed in, with the remember-me cookie to set, if any.
+    Complete(Option<Cookie<'static>>),
+    /// The account has two-factor authentication enabled, and needs to enter a code on
+    /// `/accounts/login/two-factor` first.
+    TwoFactorRequired,
+}
+
+impl SignIn {
+    /// Redirects to `location`, or to the second step of the sign-in.
+    pub fn redirect(self, location: &str) -> HttpResponse {
+        match self {
+            SignIn::Complete(Some(cookie)) => HttpResponse::Found()
+                .cookie(cookie)
+                .header(header::LOCATION, location)
+                .finish(),
+            SignIn::Complete(None) => HttpResponse::Found()
+                .header(header::LOCATION, location)
+                .finish(),
+            SignIn::TwoFactorRequired => HttpResponse::Found()
+                .header(header::LOCATION, "/accounts/login/two-factor")
+                .finish(),
+        }
+    }
+
+    /// Like `redirect`, for sign-ins made from scripts, which follow `{"redirect": location}`.
+    pub fn json_redirect(self, location: &str) -> HttpResponse {
+        let (cookie, location) = match self {
+            SignIn::Complete(cookie) => (cookie, location),
+            SignIn::TwoFactorRequired => (None, "/accounts/login/two-factor"),
+        };
+        let mut response = HttpResponse::Ok();
+        if let Some(cookie) = cookie {
+            response.cookie(cookie);
+        }
+        response.json(serde_json::json!({ "redirect": location }))
+    }
+}
+
+/// A sign-in that still needs a two-factor code.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PendingTwoFactor {
+    pub account_id: i32,
+    pub remember_me: bool,
+    expires_at: i64,
+}
+
+/// Signs the account in like `sign_in`, unless it has two-factor authentication enabled. The
+/// account is then kept in the cookie session until `finish_two_factor_sign_in`.
+pub fn start_sign_in(
+    session: &Session,
+    account: &Account,
+    device: &SessionDevice,
+    remember_me: bool,
+    conn: &DieselPgConnection,
+) -> Result<SignIn> {
+    if !AccountTwoFactor::is_enabled(account.id, conn)? {
+        return Ok(SignIn::Complete(sign_in(
+            session,
+            account,
+            device,
+            remember_me,
+            conn,
+        )?));
+    }
+
+    session.remove("sku");
+    session.remove(SESSION_ID_KEY);
+    session.set(
+        PENDING_TWO_FACTOR_KEY,
+        PendingTwoFactor {
+            account_id: account.id,
+            remember_me,
+            expires_at: Utc::now().timestamp() + PENDING_TWO_FACTOR_SECONDS,
+        },
+    )?;
+    Ok(SignIn::TwoFactorRequired)
+}
+
+/// The sign-in waiting for a two-factor code, unless it expired.
+pub fn pending_two_factor(session: &Session) -> Result<Option<PendingTwoFactor>> {
+    Ok(session
+        .get::<PendingTwoFactor>(PENDING_TWO_FACTOR_KEY)?
+        .filter(|pending| pending.expires_at > Utc::now().timestamp()))
+}
+
+/// Signs in the pending account if the code is right. Wrong codes count toward locking the
+/// account, so that codes can't be guessed.
+pub fn finish_two_factor_sign_in(
+    session: &Session,
+    code: &str,
+    device: &SessionDevice,
+    pool: &DieselPgPool,
+) -> Result<Option<SignIn>> {
+    complete_two_factor_sign_in(session, device, pool, |account_id, conn| {
+        AccountTwoFactor::verify(account_id, code, conn)
+    })
+}
+
+/// Signs in the pending account if `verify` accepts the second factor it was given, like a
+/// code or a passkey. Failures are recorded for the account, whichever session they come
+/// from, and the one that locks it cancels the sign-in and returns `Error::AccountLocked`.
+pub fn complete_two_factor_sign_in<F>(
+    session: &Session,
+    device: &SessionDevice,
+    pool: &DieselPgPool,
+    verify: F,
+) -> Result<Option<SignIn>>
+where
+    F: FnOnce(i32, &DieselPgConnection) -> Result<bool>,
+{
+    let conn = pool.get()?;
+    let pending = match pending_two_factor(session)? {
+        Some(pending) => pending,
+        None => return Ok(None),
+    };
+    // Locked since the password was checked, e.g. by wrong codes from another session
+    if Account::get(pending.account_id, pool)?.is_locked() {
+        session.remove(PENDING_TWO_FACTOR_KEY);
+        return Ok(None);
+    }
+    if !verify(pending.account_id, &conn)? {
+        let failure = Account::fail_second_factor(pending.account_id, device, &conn);
+        if let Err(Error::AccountLocked) = failure {
+            session.remove(PENDING_TWO_FACTOR_KEY);
+        }
+        return failure.map(|_| None);
+    }
+
+    Account::clear_second_factor_failures(pending.account_id, &conn)?;
+    Account::update_last_login(pending.account_id, pool)?;
+    let account = Account::get(pending.account_id, pool)?;
+    Ok(Some(SignIn::Complete(sign_in(
+        session,
+        &account,
+        device,
+        pending.remember_me,
+        &conn,
+    )?)))
+}
+
+/// Checks a code of the signed-in account before its two-factor settings change. Wrong codes
+/// count toward locking the account like at sign-in, so that a stolen session can't guess
+/// them, and the one that locks it signs the session out and returns `Error::AccountLocked`.
+pub fn verify_two_factor_code(
+    request: &HttpRequest,
+    code: &str,
+    device: &SessionDevice,
+) -> Result<bool> {
+    let user = request.user()?;
+    let pool = request.db_pool()?;
+    let conn = pool.get()?;
+    // Locked by wrong codes from another session
+    if Account::get(user.id, pool)?.is_locked() {
+        return Ok(false);
+    }
+    if AccountTwoFactor::verify(user.id, code, &conn)? {
+        Account::clear_second_factor_failures(user.id, &conn)?;
+        return Ok(true);
+    }
+    match Account::fail_second_factor(user.id, device, &conn) {
+        Err(Error::AccountLocked) => {
+            sign_out(request)?;
+            Err(Error::AccountLocked)
+        }
+        failure => failure.map(|_| false),
+    }
+}
+
+fn set_session(session: &Session, account: &Account, session_id: i32) -> Result<()> {
+    session.set(
+        "sku",
+        User {
+            id: account.id,
+            name: account.name.clone(),
+            is_admin: account.is_admin,
+            is_anonymous: false,
+        },
+    )?;
+    session.set(SESSION_ID_KEY, session_id)?;
+    session.remove(PENDING_TWO_FACTOR_KEY);
+    Ok(())
+}
+
+/// Id of the `AccountSession` of the signed-in user.
+pub fn current_session_id(session: &Session) -> Result<Option<i32>> {
+    Ok(session.get::<i32>(SESSION_ID_KEY)?)
+}
+
+/// Deletes the session of the signed-in user, if any, so that its remember-me token stops
+/// working too.
+pub fn sign_out(request: &HttpRequest) -> Result<()> {
+    let session = request.get_session();
+    if let (Some(user), Some(session_id)) =
+        (session.get::<User>("sku")?, current_session_id(&session)?)
+    {
+        let conn = request.db_pool()?.get()?;
+        AccountSession::revoke(session_id, user.id, &conn)?;
+    }
+    session.clear();
+    Ok(())
+}
+
+/// Checks the cookie session against its `AccountSession`, or signs the user in again with
+/// their remember-me token. Remember-me tokens are single use: a new one is set on the response.
 pub fn renew_token(request: &HttpRequest) -> Result<bool> {
-    if request.get_session().get::<User>("sku")?.is_none() {
-        if let Some(cookie) = request.cookie("remember_me_token") {
-            let cookie = cookie.value();
-            let index = cookie
-                .find('=')
-                .ok_or_else(|| anyhow!("Error parsing cookie: Should be key=value."))?;
-            let uid = &cookie[index + 1..]
-                .parse::<i32>()
-                .map_err(|e| anyhow!("Error parsing user id from cookie: {:?}", e))?;
-
-            let account = match Account::get(*uid, request.db_pool()?) {
-                Ok(account) => account,
-                Err(Error::Database(DBError::NotFound)) => {
-                    error!(
-                        "Account from remember me token doesn't exist or have been removed. uid={}",
-                        uid
-                    );
-                    return Ok(false);
-                }
-                Err(e) => {
-                    error!(
-                        "Error getting account from remember me token: {:?}. uid={}",
-                        e, uid
-                    );
-                    return Err(Error::Anyhow(anyhow!(
-                        "Error getting account from remember me token"
-                    )));
-                }
-            };
-
-            let user = User {
-                id: account.id,
-                name: account.name,
-                is_admin: account.is_admin,
-                is_anonymous: false,
-            };
-            request.set_user(user)?;
+    let session = request.get_session();
+    let user = session.get::<User>("sku")?;
+    let cookie = request.cookie(REMEMBER_ME_COOKIE);
+    if user.is_none() && cookie.is_none() {
+        return Ok(false);
+    }
+    let conn = request.db_pool()?.get()?;
+
+    if let Some(user) = user {
+        if let Some(session_id) = current_session_id(&session)? {
+            if AccountSession::touch(session_id, user.id, &conn)? {
+                return Ok(true);
+            }
+        }
+        // Revoked, expired or signed in before sessions were recorded
+        session.clear();
+    }
+
+    let cookie = match cookie {
+        Some(cookie) => cookie,
+        None => return Ok(false),
+    };
+    match AccountSession::renew(cookie.value(), &conn)? {
+        Renewal::Renewed(account, renewed) => {
+            set_session(&session, &account, renewed.model.id)?;
+            if let Some(token) = renewed.remember_me_token {
+                request.add_response_cookie(remember_me_cookie(token));
+            }
+            Ok(true)
+        }
+        // Clearing the cookie would also clear the new token, set by the concurrent request
+        // that renewed it
+        Renewal::Rotated => Ok(false),
+        Renewal::Rejected => {
+            warn!("Rejected an unknown or expired remember me token.");
+            request.add_response_cookie(
+                Cookie::build(REMEMBER_ME_COOKIE, "")
+                    .path("/")
+                    .max_age(Duration::zero())
+                    .finish(),
+            );
+            Ok(false)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::accounts::models::auth_attempt::{
+        AuthAttempt, AuthAttemptKind, LOCKOUT_SECOND_FACTOR_FAILURES,
+    };
+    use crate::accounts::models::webauthn_credential::WebauthnCredential;
+    use crate::accounts::webauthn::software_authenticator::SoftwareAuthenticator;
+    use crate::accounts::webauthn::{verify_registration, RelyingParty};
+    use crate::test::util::{setup_two_factor, setup_user};
+    use crate::test::{DatabaseTestContext, DB_POOL};
+    use jelly::actix_web::cookie::{CookieJar, Key};
+    use jelly::actix_web::test::TestRequest;
+
+    fn request_with_remember_me(token: &str) -> HttpRequest {
+        TestRequest::default()
+            .app_data(DB_POOL.clone())
+            .cookie(Cookie::new(REMEMBER_ME_COOKIE, token.to_string()))
+            .to_http_request()
+    }
+
+    #[actix_rt::test]
+    async fn renew_token_rejects_forged_remember_me_cookies() {
+        crate::test::init();
+        let _ctx = DatabaseTestContext::new();
+
+        let uid = setup_user(None, None);
+        // the user id signed with the secret key, as the old cookies were
+        let key = std::env::var("SECRET_KEY").expect("SECRET_KEY not set!");
+        let mut jar = CookieJar::new();
+        jar.signed(&Key::derive_from(key.as_bytes()))
+            .add(Cookie::new(REMEMBER_ME_COOKIE, uid.to_string()));
+        let signed_uid = jar.get(REMEMBER_ME_COOKIE).unwrap().value().to_string();
+
+        for forged in [
+            signed_uid,
+            format!("{}={}", "a".repeat(43), uid),
+            "forged-token".to_string(),
+        ] {
+            let request = request_with_remember_me(&forged);
+            assert!(!renew_token(&request).unwrap());
+            assert!(request.user().unwrap().is_anonymous);
         }
     }
-    Ok(true)
+
+    #[actix_rt::test]
+    async fn renew_token_signs_in_with_a_remember_me_token_once() {
+        crate::test::init();
+        let _ctx = DatabaseTestContext::new();
+
+        let uid = setup_user(None, None);
+        let account = Account::get(uid, &DB_POOL).unwrap();
+        let conn = DB_POOL.get().unwrap();
+        let created =
+            AccountSession::create(&account, &SessionDevice::default(), true, &conn).unwrap();
+        let token = created.remember_me_token.unwrap();
+
+        let request = request_with_remember_me(&token);
+        assert!(is_authenticated(&request).unwrap());
+        assert_eq!(request.user().unwrap().id, uid);
+        assert_eq!(
+            current_session_id(&request.get_session()).unwrap(),
+            Some(created.model.id)
+        );
+
+        // the token was rotated
+        assert!(!is_authenticated(&request_with_remember_me(&token)).unwrap());
+    }
+
+    #[actix_rt::test]
+    async fn start_sign_in_waits_for_the_two_factor_code() {
+        crate::test::init();
+        let _ctx = DatabaseTestContext::new();
+
+        let uid = setup_user(None, None);
+        let recovery_codes = setup_two_factor(uid);
+        let account = Account::get(uid, &DB_POOL).unwrap();
+        let conn = DB_POOL.get().unwrap();
+        let request = TestRequest::default()
+            .app_data(DB_POOL.clone())
+            .to_http_request();
+        let session = request.get_session();
+        let device = SessionDevice::default();
+
+        let sign_in = start_sign_in(&session, &account, &device, true, &conn).unwrap();
+        assert!(matches!(sign_in, SignIn::TwoFactorRequired));
+        assert!(!is_authenticated(&request).unwrap());
+        assert_eq!(pending_two_factor(&session).unwrap().unwrap().account_id, uid);
+
+        assert!(
+            finish_two_factor_sign_in(&session, "aaaaa-aaaaa", &device, &DB_POOL)
+                .unwrap()
+                .is_none()
+        );
+        assert!(!is_authenticated(&request).unwrap());
+
+        let sign_in = finish_two_factor_sign_in(&session, &recovery_codes[0], &device, &DB_POOL)
+            .unwrap()
+            .unwrap();
+        // the remember-me choice of the first step is kept
+        assert!(matches!(sign_in, SignIn::Complete(Some(_))));
+        assert!(is_authenticated(&request).unwrap());
+        assert_eq!(request.user().unwrap().id, uid);
+        assert!(pending_two_factor(&session).unwrap().is_none());
+    }
+
+    #[actix_rt::test]
+    async fn complete_two_factor_sign_in_accepts_a_passkey() {
+        crate::test::init();
+        let _ctx = DatabaseTestContext::new();
+
+        let uid = setup_user(None, None);
+        setup_two_factor(uid);
+        let account = Account::get(uid, &DB_POOL).unwrap();
+        let conn = DB_POOL.get().unwrap();
+        let rp = RelyingParty::new("http://127.0.0.1:17001").unwrap();
+        let challenge = base64_url::encode(&[7u8; 32]);
+        let mut authenticator = SoftwareAuthenticator::es256();
+        let registration = authenticator.register(&rp, &challenge);
+        let verified = verify_registration(&rp, &challenge, &registration).unwrap();
+        WebauthnCredential::create(uid, "Security key", &verified, &conn).unwrap();
+
+        let request = TestRequest::default()
+            .app_data(DB_POOL.clone())
+            .to_http_request();
+        let session = request.get_session();
+        let device = SessionDevice::default();
+        start_sign_in(&session, &account, &device, false, &conn).unwrap();
+
+        // passkeys of other accounts don't count
+        let other_uid = setup_user(Some("other@host.com".to_string()), None);
+        let mut other_authenticator = SoftwareAuthenticator::es256();
+        let registration = other_authenticator.register(&rp, &challenge);
+        let verified = verify_registration(&rp, &challenge, &registration).unwrap();
+        WebauthnCredential::create(other_uid, "Security key", &verified, &conn).unwrap();
+        let assertion = other_authenticator.assert(&rp, &challenge);
+        let sign_in = complete_two_factor_sign_in(&session, &device, &DB_POOL, |id, conn| {
+            Ok(WebauthnCredential::authenticate(
+                &rp,
+                &challenge,
+                &assertion,
+                Some(id),
+                false,
+                conn,
+            )?
+            .is_some())
+        })
+        .unwrap();
+        assert!(sign_in.is_none());
+
+        let assertion = authenticator.assert(&rp, &challenge);
+        let sign_in = complete_two_factor_sign_in(&session, &device, &DB_POOL, |id, conn| {
+            Ok(WebauthnCredential::authenticate(
+                &rp,
+                &challenge,
+                &assertion,
+                Some(id),
+                false,
+                conn,
+            )?
+            .is_some())
+        })
+        .unwrap();
+        assert!(matches!(sign_in, Some(SignIn::Complete(None))));
+        assert_eq!(request.user().unwrap().id, uid);
+    }
+
+    #[actix_rt::test]
+    async fn too_many_wrong_second_factors_lock_the_account() {
+        crate::test::init();
+        let _ctx = DatabaseTestContext::new();
+
+        let uid = setup_user(None, None);
+        let recovery_codes = setup_two_factor(uid);
+        let account = Account::get(uid, &DB_POOL).unwrap();
+        let conn = DB_POOL.get().unwrap();
+        let device = SessionDevice::default();
+        let new_session = || {
+            let request = TestRequest::default()
+                .app_data(DB_POOL.clone())
+                .to_http_request();
+            let session = request.get_session();
+            start_sign_in(&session, &account, &device, false, &conn).unwrap();
+            (request, session)
+        };
+
+        // Failures are counted for the account, so a new session doesn't start over, and
+        // wrong passkeys count like wrong codes
+        for _ in 0..LOCKOUT_SECOND_FACTOR_FAILURES - 2 {
+            let (_, session) = new_session();
+            assert!(
+                finish_two_factor_sign_in(&session, "aaaaa-aaaaa", &device, &DB_POOL)
+                    .unwrap()
+                    .is_none()
+            );
+        }
+        let (_, session) = new_session();
+        let sign_in = complete_two_factor_sign_in(&session, &device, &DB_POOL, |_, _| Ok(false));
+        assert!(sign_in.unwrap().is_none());
+
+        let (request, session) = new_session();
+        let sign_in = finish_two_factor_sign_in(&session, "aaaaa-aaaaa", &device, &DB_POOL);
+        assert!(matches!(sign_in, Err(Error::AccountLocked)));
+        assert!(pending_two_factor(&session).unwrap().is_none());
+        assert!(Account::get(uid, &DB_POOL).unwrap().is_locked());
+
+        // Pending sign-ins of a locked account can't be completed, even with the right code
+        let (_, other_session) = new_session();
+        assert!(
+            finish_two_factor_sign_in(&other_session, &recovery_codes[0], &device, &DB_POOL)
+                .unwrap()
+                .is_none()
+        );
+        assert!(pending_two_factor(&other_session).unwrap().is_none());
+        assert!(!is_authenticated(&request).unwrap());
+    }
+
+    #[actix_rt::test]
+    async fn wrong_codes_of_signed_in_sessions_lock_the_account() {
+        crate::test::init();
+        let _ctx = DatabaseTestContext::new();
+
+        let uid = setup_user(None, None);
+        let recovery_codes = setup_two_factor(uid);
+        let account = Account::get(uid, &DB_POOL).unwrap();
+        let conn = DB_POOL.get().unwrap();
+        let request = TestRequest::default()
+            .app_data(DB_POOL.clone())
+            .to_http_request();
+        let device = SessionDevice::default();
+        sign_in(&request.get_session(), &account, &device, false, &conn).unwrap();
+
+        for _ in 0..LOCKOUT_SECOND_FACTOR_FAILURES - 1 {
+            assert!(!verify_two_factor_code(&request, "aaaaa-aaaaa", &device).unwrap());
+        }
+        assert!(matches!(
+            verify_two_factor_code(&request, "aaaaa-aaaaa", &device),
+            Err(Error::AccountLocked)
+        ));
+        assert!(Account::get(uid, &DB_POOL).unwrap().is_locked());
+        // The session that locked the account is signed out
+        assert!(!is_authenticated(&request).unwrap());
+
+        // Other sessions of a locked account can't use the right code either
+        let other_request = TestRequest::default()
+            .app_data(DB_POOL.clone())
+            .to_http_request();
+        sign_in(
+            &other_request.get_session(),
+            &account,
+            &device,
+            false,
+            &conn,
+        )
+        .unwrap();
+        assert!(!verify_two_factor_code(&other_request, &recovery_codes[0], &device).unwrap());
+    }
+
+    #[actix_rt::test]
+    async fn signing_in_forgets_wrong_second_factors() {
+        crate::test::init();
+        let _ctx = DatabaseTestContext::new();
+
+        let uid = setup_user(None, None);
+        let recovery_codes = setup_two_factor(uid);
+        let account = Account::get(uid, &DB_POOL).unwrap();
+        let conn = DB_POOL.get().unwrap();
+        let request = TestRequest::default()
+            .app_data(DB_POOL.clone())
+            .to_http_request();
+        let session = request.get_session();
+        let device = SessionDevice::default();
+        start_sign_in(&session, &account, &device, false, &conn).unwrap();
+
+        for _ in 0..LOCKOUT_SECOND_FACTOR_FAILURES - 1 {
+            finish_two_factor_sign_in(&session, "aaaaa-aaaaa", &device, &DB_POOL).unwrap();
+        }
+        finish_two_factor_sign_in(&session, &recovery_codes[0], &device, &DB_POOL)
+            .unwrap()
+            .unwrap();
+        let since = Utc::now() - jelly::chrono::Duration::hours(1);
+        let failures = AuthAttempt::count_for_email(
+            AuthAttemptKind::FailedSecondFactor,
+            &account.email,
+            since,
+            &conn,
+        )
+        .unwrap();
+        assert_eq!(failures, 0);
+    }
+
+    #[actix_rt::test]
+    async fn start_sign_in_signs_in_accounts_without_two_factor() {
+        crate::test::init();
+        let _ctx = DatabaseTestContext::new();
+
+        let uid = setup_user(None, None);
+        let account = Account::get(uid, &DB_POOL).unwrap();
+        let conn = DB_POOL.get().unwrap();
+        let request = TestRequest::default()
+            .app_data(DB_POOL.clone())
+            .to_http_request();
+
+        let sign_in = start_sign_in(
+            &request.get_session(),
+            &account,
+            &SessionDevice::default(),
+            false,
+            &conn,
+        )
+        .unwrap();
+        assert!(matches!(sign_in, SignIn::Complete(None)));
+        assert!(is_authenticated(&request).unwrap());
+        let log = AccountEvent::security_log(&account, &conn).unwrap();
+        assert_eq!(log.len(), 1);
+        assert_eq!(log[0].description, "Signed in");
+    }
+
+    #[actix_rt::test]
+    async fn renew_token_rejects_revoked_sessions() {
+        crate::test::init();
+        let _ctx = DatabaseTestContext::new();
+
+        let uid = setup_user(None, None);
+        let account = Account::get(uid, &DB_POOL).unwrap();
+        let conn = DB_POOL.get().unwrap();
+        let request = TestRequest::default()
+            .app_data(DB_POOL.clone())
+            .to_http_request();
+        sign_in(
+            &request.get_session(),
+            &account,
+            &SessionDevice::default(),
+            false,
+            &conn,
+        )
+        .unwrap();
+        assert!(is_authenticated(&request).unwrap());
+
+        AccountSession::revoke_all(uid, &conn).unwrap();
+        assert!(!is_authenticated(&request).unwrap());
+        assert!(request.user().unwrap().is_anonymous);
+    }
 }
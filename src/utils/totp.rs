@@ -0,0 +1,138 @@
+//! Time-based one-time passwords (RFC 6238) as understood by authenticator apps:
+//! HMAC-SHA1, 30 second steps and 6 digits.
+use base32::Alphabet;
+use hmac::{Hmac, Mac, NewMac};
+use rand::{rngs::OsRng, RngCore};
+use reqwest::Url;
+use sha1::Sha1;
+
+pub const STEP_SECONDS: u64 = 30;
+pub const DIGITS: u32 = 6;
+// 160 bits, the length of the SHA-1 output recommended by RFC 4226
+const SECRET_LENGTH: usize = 20;
+const ALPHABET: Alphabet = Alphabet::RFC4648 { padding: false };
+
+/// A new random secret, base32 encoded so that it can be typed into an authenticator app.
+pub fn generate_secret() -> String {
+    let mut secret = [0u8; SECRET_LENGTH];
+    OsRng.fill_bytes(&mut secret);
+    base32::encode(ALPHABET, &secret)
+}
+
+pub fn step_at(unix_time: u64) -> u64 {
+    unix_time / STEP_SECONDS
+}
+
+/// The code of the time step, or `None` if the secret isn't valid base32.
+pub fn code_at(secret: &str, step: u64) -> Option<String> {
+    let key = base32::decode(ALPHABET, secret)?;
+    let mut mac = Hmac::<Sha1>::new_varkey(&key).ok()?;
+    mac.update(&step.to_be_bytes());
+    let hash = mac.finalize().into_bytes();
+
+    // Dynamic truncation, RFC 4226 section 5.3
+    let offset = (hash[hash.len() - 1] & 0xf) as usize;
+    let binary = u32::from_be_bytes([
+        hash[offset] & 0x7f,
+        hash[offset + 1],
+        hash[offset + 2],
+        hash[offset + 3],
+    ]);
+    Some(format!(
+        "{:0width$}",
+        binary % 10u32.pow(DIGITS),
+        width = DIGITS as usize
+    ))
+}
+
+/// Checks the code against the current time step and its neighbours, to allow for clock
+/// drift and slow typing. Returns the step the code belongs to.
+pub fn verify(secret: &str, code: &str, unix_time: u64) -> Option<u64> {
+    let code: String = code.chars().filter(|c| !c.is_whitespace()).collect();
+    if code.len() != DIGITS as usize {
+        return None;
+    }
+    let step = step_at(unix_time);
+    [step.saturating_sub(1), step, step + 1]
+        .iter()
+        .copied()
+        .find(|&step| code_at(secret, step).is_some_and(|expected| expected == code))
+}
+
+/// The `otpauth://` URI authenticator apps read from QR codes.
+pub fn otpauth_uri(issuer: &str, account_name: &str, secret: &str) -> String {
+    let mut uri = Url::parse("otpauth://totp/").unwrap();
+    uri.set_path(&format!("{}:{}", issuer, account_name));
+    uri.query_pairs_mut()
+        .append_pair("secret", secret)
+        .append_pair("issuer", issuer)
+        .append_pair("digits", &DIGITS.to_string())
+        .append_pair("period", &STEP_SECONDS.to_string());
+    uri.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::totp::{code_at, generate_secret, otpauth_uri, step_at, verify};
+    use base32::Alphabet;
+
+    // The SHA-1 secret of the RFC 6238 test vectors
+    fn rfc_secret() -> String {
+        base32::encode(
+            Alphabet::RFC4648 { padding: false },
+            b"12345678901234567890",
+        )
+    }
+
+    #[actix_rt::test]
+    async fn code_at_matches_rfc_6238_test_vectors() {
+        let secret = rfc_secret();
+        // The RFC lists 8 digit codes, these are their last 6 digits
+        for (time, code) in &[
+            (59, "287082"),
+            (1111111109, "081804"),
+            (1111111111, "050471"),
+            (1234567890, "005924"),
+            (2000000000, "279037"),
+            (20000000000, "353130"),
+        ] {
+            assert_eq!(code_at(&secret, step_at(*time)).unwrap(), *code);
+        }
+    }
+
+    #[actix_rt::test]
+    async fn verify_allows_one_step_of_drift() {
+        let secret = rfc_secret();
+        let step = step_at(1111111111);
+
+        assert_eq!(verify(&secret, "050471", 1111111111), Some(step));
+        assert_eq!(verify(&secret, "050 471", 1111111111), Some(step));
+        assert_eq!(verify(&secret, "050471", 1111111111 + 30), Some(step));
+        assert_eq!(verify(&secret, "050471", 1111111111 - 30), Some(step));
+        assert_eq!(verify(&secret, "050471", 1111111111 + 60), None);
+        assert_eq!(verify(&secret, "050472", 1111111111), None);
+        assert_eq!(verify(&secret, "50471", 1111111111), None);
+    }
+
+    #[actix_rt::test]
+    async fn invalid_secrets_are_rejected() {
+        assert!(code_at("not base32!", 1).is_none());
+        assert!(verify("not base32!", "000000", 59).is_none());
+    }
+
+    #[actix_rt::test]
+    async fn generate_secret_works() {
+        let secret = generate_secret();
+        assert_eq!(secret.len(), 32);
+        assert_ne!(secret, generate_secret());
+        assert!(code_at(&secret, 1).is_some());
+    }
+
+    #[actix_rt::test]
+    async fn otpauth_uri_works() {
+        assert_eq!(
+            otpauth_uri("Movey", "email@host.com", "JBSWY3DPEHPK3PXP"),
+            "otpauth://totp/Movey:email@host.com?secret=JBSWY3DPEHPK3PXP&issuer=Movey&digits=6&period=30"
+        );
+    }
+}
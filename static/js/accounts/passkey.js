@@ -0,0 +1,93 @@
+// WebAuthn ceremonies for passkeys. The server sends and expects binary values base64url
+// encoded, the browser API works with ArrayBuffers.
+class Passkey {
+  static isSupported() {
+    return !!window.PublicKeyCredential
+  }
+
+  static toBuffer(value) {
+    const base64 = value.replace(/-/g, '+').replace(/_/g, '/')
+    const padded = base64 + '='.repeat((4 - base64.length % 4) % 4)
+    return Uint8Array.from(atob(padded), (c) => c.charCodeAt(0)).buffer
+  }
+
+  static toBase64url(buffer) {
+    const binary = String.fromCharCode(...new Uint8Array(buffer))
+    return btoa(binary).replace(/\+/g, '-').replace(/\//g, '_').replace(/=+$/, '')
+  }
+
+  static credentialDescriptors(descriptors) {
+    return descriptors.map((descriptor) => ({ ...descriptor, id: Passkey.toBuffer(descriptor.id) }))
+  }
+
+  static async create(options) {
+    const credential = await navigator.credentials.create({
+      publicKey: {
+        ...options,
+        challenge: Passkey.toBuffer(options.challenge),
+        user: { ...options.user, id: Passkey.toBuffer(options.user.id) },
+        excludeCredentials: Passkey.credentialDescriptors(options.excludeCredentials)
+      }
+    })
+    return {
+      id: credential.id,
+      response: {
+        clientDataJSON: Passkey.toBase64url(credential.response.clientDataJSON),
+        attestationObject: Passkey.toBase64url(credential.response.attestationObject)
+      }
+    }
+  }
+
+  static async get(options) {
+    const credential = await navigator.credentials.get({
+      publicKey: {
+        ...options,
+        challenge: Passkey.toBuffer(options.challenge),
+        allowCredentials: Passkey.credentialDescriptors(options.allowCredentials)
+      }
+    })
+    const response = credential.response
+    return {
+      id: credential.id,
+      response: {
+        clientDataJSON: Passkey.toBase64url(response.clientDataJSON),
+        authenticatorData: Passkey.toBase64url(response.authenticatorData),
+        signature: Passkey.toBase64url(response.signature),
+        userHandle: response.userHandle && Passkey.toBase64url(response.userHandle)
+      }
+    }
+  }
+
+  // Shows the button in browsers with passkeys. Clicking it asks the server for the options,
+  // signs the challenge and posts the credential, with the object `data()` returns if any.
+  static bindSignIn(button, optionsUrl, url, data) {
+    if (!Passkey.isSupported()) {
+      return
+    }
+    $(button).prop('hidden', false).on('click', () => {
+      $('.passkey-error').text('')
+      $.post(optionsUrl)
+        .then((options) => Passkey.get(options))
+        .then((credential) => $.ajax({
+          type: 'POST',
+          url: url,
+          contentType: 'application/json',
+          data: JSON.stringify(data ? { ...data(), credential: credential } : credential)
+        }))
+        .then((response) => {
+          window.location.href = response.redirect
+        })
+        .catch((error) => $('.passkey-error').text(Passkey.errorMessage(error)))
+    })
+  }
+
+  static errorMessage(error) {
+    if (error && error.responseText) {
+      return error.responseText
+    }
+    if (error && error.name === 'NotAllowedError') {
+      return 'The passkey request was cancelled.'
+    }
+    return 'Unexpected error. Try again!'
+  }
+}
@@ -0,0 +1,70 @@
+class Claim {
+  constructor() {
+    this.github_btn = $(".claim_btn.github");
+    this.token_btn = $(".claim_btn.token");
+    this.new_token_btn = $(".claim_btn.new_token");
+
+    // pathname should be something like /packages/<package-slug>/claim
+    this.packageSlug = window.location.pathname.split('/')[2];
+
+    this.init();
+  }
+
+  init() {
+    this.github_btn.click(() => {
+      this.claim("github");
+    });
+
+    this.token_btn.click(() => {
+      this.claim("verify");
+    });
+
+    this.new_token_btn.click(() => {
+      this.getToken();
+    });
+  }
+
+  getToken() {
+    $.ajax({
+      type: "POST",
+      dataType: "json",
+      url: "/api/v1/packages/" + this.packageSlug + "/claim/token",
+      contentType: "application/json",
+      processData: false,
+      headers: {},
+      success: (data) => {
+        $(".claim_token").text(data.token).prop("hidden", false);
+        this.new_token_btn.prop("hidden", true);
+        this.token_btn.prop("hidden", false);
+      },
+      error: (data) => {
+        this.messageReturn(data.responseJSON.msg, false);
+      },
+    });
+  }
+
+  claim(method) {
+    const claimUrl = "/api/v1/packages/" + this.packageSlug + "/claim/" + method;
+    $.ajax({
+      type: "POST",
+      dataType: "json",
+      url: claimUrl,
+      contentType: "application/json",
+      processData: false,
+      headers: {},
+      success: (data) => {
+        this.messageReturn(data.msg, true);
+        window.location.href = "/packages/" + this.packageSlug + "/collaborators";
+      },
+      error: (data) => {
+        this.messageReturn(data.responseJSON.msg, false);
+      },
+    });
+  }
+
+  messageReturn(msg, isSuccess) {
+    $("#return-message").text(msg);
+    $("#return-message").removeClass();
+    $("#return-message").addClass(isSuccess ? "message success" : "message error");
+  }
+}
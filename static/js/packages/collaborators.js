@@ -3,6 +3,8 @@ class Collaborator {
     this.add_collaborator_btn = $(".add_collaborators_btn");
     this.transfer_owner_btn = $(".ownership_btn.transfer");
     this.remove_btn = $(".ownership_btn.remove");
+    this.promote_btn = $(".ownership_btn.promote");
+    this.remove_owner_btn = $(".ownership_btn.remove_owner");
     this.invite_btn = $(".collaborators_btn.add");
 
     this.collaborators_modal = $("#new_collaborator_modal");
@@ -52,6 +54,19 @@ class Collaborator {
       this.transfer_modal.foundation("open");
     });
     this.remove_btn.click(this.removeBtnListener);
+    this.remove_owner_btn.click((e) => {
+      $("#removed_email").text($(e.target).data("email"));
+      this.removeAction = "owners";
+      this.remove_modal.foundation("open");
+    });
+    this.promote_btn.click((e) => {
+      this.addOwner(
+        e.target.parentElement.parentElement.querySelector(".email_address")
+          .innerText
+      );
+    });
+    $(".permission_checkbox").change(this.permissionListener);
+    $("#require_two_factor").change(this.updateTwoFactorRequirement);
 
     this.transfer_modal.find(".submit").on("click", () => {
       this.transferOwnership();
@@ -139,9 +154,34 @@ class Collaborator {
     });
   };
 
+  addOwner = (user) => {
+    let ownersUrl =
+        "/api/v1/collaborators/packages/" + this.packageSlug + "/owners";
+
+    $.ajax({
+      type: "POST",
+      dataType: "json",
+      url: ownersUrl,
+      contentType: "application/json",
+      processData: false,
+      headers: {},
+      data: JSON.stringify({ user: user.trim() }),
+      success: (data) => {
+        $('#success_modal_message').text(data.msg);
+        this.success_modal.foundation("open");
+        window.location.reload()
+      },
+      error: (data) => {
+        $('#success_modal_message').text(data.responseJSON.msg);
+        this.success_modal.foundation("open");
+      },
+    });
+  };
+
   removeCollaborator = () => {
+    // owners are removed through their own endpoint, which keeps the last one
     let collaboratorUrl =
-        "/api/v1/collaborators/packages/" + this.packageSlug + "/remove";
+        "/api/v1/collaborators/packages/" + this.packageSlug + "/" + this.removeAction;
 
     $.ajax({
       type: "DELETE",
@@ -164,6 +204,62 @@ class Collaborator {
     });
   };
 
+  updatePermissions = (row) => {
+    let permissionsUrl =
+        "/api/v1/collaborators/packages/" + this.packageSlug + "/permissions";
+    let payload = { user: row.find(".email_address").text().trim() };
+    row.find(".permission_checkbox").each((_, checkbox) => {
+      payload[$(checkbox).data("permission")] = checkbox.checked;
+    });
+
+    $.ajax({
+      type: "POST",
+      dataType: "json",
+      url: permissionsUrl,
+      contentType: "application/json",
+      processData: false,
+      headers: {},
+      data: JSON.stringify(payload),
+      success: (data) => {
+        $('#success_modal_message').text(data.msg);
+        this.success_modal.foundation("open");
+      },
+      error: (data) => {
+        $('#success_modal_message').text(data.responseJSON.msg);
+        this.success_modal.foundation("open");
+        window.location.reload()
+      },
+    });
+  };
+
+  updateTwoFactorRequirement = (e) => {
+    let twoFactorUrl =
+        "/api/v1/collaborators/packages/" + this.packageSlug + "/two-factor";
+
+    $.ajax({
+      type: "POST",
+      dataType: "json",
+      url: twoFactorUrl,
+      contentType: "application/json",
+      processData: false,
+      headers: {},
+      data: JSON.stringify({ require_two_factor: e.target.checked }),
+      success: (data) => {
+        $('#success_modal_message').text(data.msg);
+        this.success_modal.foundation("open");
+      },
+      error: (data) => {
+        e.target.checked = !e.target.checked;
+        $('#success_modal_message').text(data.responseJSON.msg);
+        this.success_modal.foundation("open");
+      },
+    });
+  };
+
+  permissionListener = (e) => {
+    this.updatePermissions($(e.target).closest(".collaborator_row"));
+  };
+
   updateRow = (name, role) => {
     $(".collaborators_table").append(`
       <div class="collaborator_row">
@@ -191,6 +287,7 @@ class Collaborator {
       e.target.parentElement.parentElement.querySelector(".email_address")
         .innerText
     );
+    this.removeAction = "remove";
     this.remove_modal.foundation("open");
   };
 
@@ -30,5 +30,119 @@ class Profile {
     $(this.saveButton).on('click', () => {
       this.form.submit()
     })
+
+    $('.session-item time.timeago').timeago()
+    $('.revoke-session-btn').on('click', (e) => {
+      const sessionItem = $(e.currentTarget).closest('.session-item')
+      this.revokeSessions('/api/v1/settings/sessions/' + sessionItem.data('id'), () => {
+        if (sessionItem.find('.session-current').length) {
+          window.location.href = '/accounts/login'
+        } else {
+          sessionItem.remove()
+        }
+      })
+    })
+    $('.sign-out-everywhere-btn').on('click', () => {
+      this.revokeSessions('/api/v1/settings/sessions', () => {
+        window.location.href = '/accounts/login'
+      })
+    })
+
+    $('.two-factor-setup-btn').on('click', (e) => {
+      this.twoFactorRequest('POST', '/api/v1/settings/two-factor', null, (data) => {
+        $('.two-factor-secret').text(data.secret)
+        $('.two-factor-qr-code').html(data.qr_code_svg)
+        $('.two-factor-enrollment').prop('hidden', false)
+        $(e.currentTarget).remove()
+      })
+    })
+    $('.two-factor-confirm-btn').on('click', () => {
+      this.twoFactorRequest('PUT', '/api/v1/settings/two-factor', this.twoFactorCode(), (data) => {
+        $('.two-factor-enrollment').remove()
+        $('.two-factor-header').append($('<span class="two-factor-enabled">').text('Enabled'))
+        this.showRecoveryCodes(data.recovery_codes)
+      })
+    })
+    $('.two-factor-recovery-btn').on('click', () => {
+      this.twoFactorRequest('POST', '/api/v1/settings/two-factor/recovery-codes', this.twoFactorCode(), (data) => {
+        $('.two-factor-code').val('')
+        this.showRecoveryCodes(data.recovery_codes)
+      })
+    })
+    $('.two-factor-disable-btn').on('click', () => {
+      this.twoFactorRequest('DELETE', '/api/v1/settings/two-factor', this.twoFactorCode(), () => {
+        window.location.reload()
+      })
+    })
+
+    this.initPasskeys()
+  }
+
+  initPasskeys() {
+    if (!Passkey.isSupported()) {
+      $('.passkeys-form').remove()
+      $('.passkeys-error').text('This browser does not support passkeys.')
+    }
+    $('.passkey-item time.timeago').timeago()
+    $('.passkey-add-btn').on('click', () => {
+      $('.passkeys-error').text('')
+      const name = $('.passkey-name').val().trim()
+      $.post('/api/v1/settings/passkeys/options')
+        .then((options) => Passkey.create(options))
+        .then((credential) => $.ajax({
+          type: 'POST',
+          url: '/api/v1/settings/passkeys',
+          contentType: 'application/json',
+          data: JSON.stringify({ name: name, credential: credential })
+        }))
+        .then(() => window.location.reload())
+        .catch((error) => $('.passkeys-error').text(Passkey.errorMessage(error)))
+    })
+    $('.remove-passkey-btn').on('click', (e) => {
+      const passkeyItem = $(e.currentTarget).closest('.passkey-item')
+      $.ajax({
+        type: 'DELETE',
+        url: '/api/v1/settings/passkeys/' + passkeyItem.data('id'),
+        success: () => passkeyItem.remove(),
+        error: (xhr) => {
+          $('.passkeys-error').text(xhr.responseText || 'Unexpected error. Try again!')
+        }
+      })
+    })
+  }
+
+  twoFactorCode() {
+    return { code: $('.two-factor-code').val().trim() }
+  }
+
+  twoFactorRequest(type, url, data, onSuccess) {
+    $('.two-factor-error').text('')
+    $.ajax({
+      type: type,
+      url: url,
+      contentType: 'application/json',
+      data: data && JSON.stringify(data),
+      success: onSuccess,
+      error: (xhr) => {
+        $('.two-factor-error').text(xhr.responseText || 'Unexpected error. Try again!')
+      }
+    })
+  }
+
+  showRecoveryCodes(recoveryCodes) {
+    const list = $('.two-factor-recovery-codes ul').empty()
+    recoveryCodes.forEach((code) => list.append($('<li>').text(code)))
+    $('.two-factor-recovery-codes').prop('hidden', false)
+  }
+
+  revokeSessions(url, onSuccess) {
+    $.ajax({
+      type: 'DELETE',
+      url: url,
+      success: onSuccess,
+      error: (xhr) => {
+        $('.sessions-error').text(xhr.responseText || 'Unexpected error. Try again!')
+      }
+    })
   }
 }
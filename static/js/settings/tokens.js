@@ -2,7 +2,7 @@ class Tokens {
   constructor() {
     this.createTokenButton = $(".create-new-token-btn, .create-new-token-mobile-btn")
     this.modal = $('#new_token_modal')
-    this.newTokenInput = this.modal.find('input')
+    this.newTokenInput = this.modal.find('.new-token-input')
     this.newTokenItemTemplate = $('.token-item-template .token-item')
     this.tokensList = $('.tokens-list')
     this.revokeModal = $('#revoke_token_modal')
@@ -21,14 +21,14 @@ class Tokens {
     this.modal.find('.new-token-submit-btn').on('click', () => {
       this.modal.foundation('close')
       this.submitNewToken()
-      this.newTokenInput.val('')
+      this.resetNewTokenForm()
     })
 
     this.newTokenInput.on('keypress', (e) => {
       if (e.key == "Enter") {
         this.modal.foundation('close')
         this.submitNewToken()
-        this.newTokenInput.val('')
+        this.resetNewTokenForm()
       }
     })
 
@@ -52,9 +52,33 @@ class Tokens {
     })
   }
 
+  resetNewTokenForm() {
+    this.newTokenInput.val('')
+    this.modal.find('.new-token-endpoint-scopes input').prop('checked', true)
+    this.modal.find('.new-token-package-scopes').val('')
+    this.modal.find('.new-token-expiration').val('')
+  }
+
+  newTokenScopes() {
+    const endpointScopes = this.modal.find('.new-token-endpoint-scopes input:checked')
+      .map((_, input) => input.value).get()
+    const packageScopes = this.modal.find('.new-token-package-scopes').val()
+      .split(',').map((packageScope) => packageScope.trim()).filter((packageScope) => packageScope)
+    const expirationDays = this.modal.find('.new-token-expiration').val()
+    return {
+      "endpoint_scopes": endpointScopes,
+      "package_scopes": packageScopes.length ? packageScopes : null,
+      // Sent as a UTC date time without timezone, e.g. 2022-10-08T02:17:36
+      "expired_at": expirationDays
+        ? new Date(Date.now() + expirationDays * 24 * 60 * 60 * 1000).toISOString().slice(0, 19)
+        : null,
+    }
+  }
+
   submitNewToken() {
     const tokenName = this.newTokenInput.val();
     if (!tokenName) return
+    const scopes = this.newTokenScopes()
     $.ajax({
       type: 'PUT',
       dataType: "json",
@@ -62,13 +86,22 @@ class Tokens {
       contentType: "application/json",
       processData: false,
       headers: {},
-      data: JSON.stringify({"name": tokenName}),
+      data: JSON.stringify({"name": tokenName, ...scopes}),
       success: (data, status, xhr) => {
         $('.no-tokens').remove()
         const newTokenItem = this.newTokenItemTemplate.clone()
         newTokenItem.data('id', data.id)
         newTokenItem.find('.token-name').text(data.name)
         newTokenItem.find('.token-plaintext').text(data.token)
+        if (data.endpoint_scopes) {
+          newTokenItem.find('.token-endpoint-scopes').text(data.endpoint_scopes.join(', '))
+        }
+        if (data.package_scopes) {
+          newTokenItem.find('.token-package-scopes').text(data.package_scopes.join(', '))
+        }
+        if (data.expired_at) {
+          newTokenItem.find('.token-expired-at').text(new Date(data.expired_at + 'Z').toUTCString())
+        }
 
         this.tokensList.append(newTokenItem)
         return data
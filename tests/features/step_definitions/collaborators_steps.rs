@@ -6,7 +6,9 @@ use cucumber::{given, then, when};
 use jelly::forms::{EmailField, PasswordField};
 use mainlib::accounts::forms::NewAccountForm;
 use mainlib::accounts::Account;
-use mainlib::package_collaborators::package_collaborator::PackageCollaborator;
+use mainlib::package_collaborators::package_collaborator::{
+    CollaboratorPermissions, PackageCollaborator,
+};
 use mainlib::packages::{Package, PackageVersion};
 use mainlib::test::DB_POOL;
 use regex::Regex;
@@ -985,6 +987,17 @@ async fn collaborator_of_package(world: &mut TestWorld) {
     )
     .unwrap();
     PackageCollaborator::new_collaborator(pid, 1, 1, &DB_POOL.get().unwrap()).unwrap();
+    PackageCollaborator::update_permissions(
+        pid,
+        1,
+        &CollaboratorPermissions {
+            can_publish: false,
+            can_manage_collaborators: true,
+            can_invite: true,
+        },
+        &DB_POOL.get().unwrap(),
+    )
+    .unwrap();
     world.first_account.owned_package_name = Some("test package".to_string());
 }
 